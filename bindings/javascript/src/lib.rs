@@ -627,6 +627,10 @@ impl turso_core::DatabaseStorage for DatabaseFile {
     fn size(&self) -> turso_core::Result<u64> {
         self.file.size()
     }
+
+    fn truncate(&self, len: u64) -> turso_core::Result<()> {
+        self.file.truncate(len)
+    }
 }
 
 #[inline]
@@ -374,6 +374,10 @@ impl turso_core::DatabaseStorage for DatabaseFile {
     fn size(&self) -> Result<u64> {
         self.file.size()
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        self.file.truncate(len)
+    }
 }
 
 #[cfg(all(feature = "web", not(feature = "nodejs")))]
@@ -1,6 +1,9 @@
 use crate::{
     commands::{
-        args::{EchoMode, TimerMode},
+        args::{
+            BinaryMode, ConnectionTarget, EchoMode, HeadersMode, LimitArgs, ParameterCommand,
+            SelftestArgs, Sha3sumArgs, StatsMode, TimerMode, TypesMode,
+        },
         import::ImportFile,
         Command, CommandParser,
     },
@@ -8,6 +11,7 @@ use crate::{
     helper::LimboHelper,
     input::{get_io, get_writer, DbLocation, OutputMode, Settings},
     opcodes_dictionary::OPCODE_DESCRIPTIONS,
+    sha3::Sha3,
     HISTORY_FILE,
 };
 use anyhow::anyhow;
@@ -15,18 +19,24 @@ use clap::Parser;
 use comfy_table::{Attribute, Cell, CellAlignment, ContentArrangement, Row, Table};
 use rustyline::{error::ReadlineError, history::DefaultHistory, Editor};
 use std::{
+    collections::BTreeMap,
     fmt,
+    fs::File,
     io::{self, BufRead as _, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
-use turso_core::{Connection, Database, LimboError, OpenFlags, Statement, StepResult, Value};
+use turso_core::{
+    Connection, Database, LimboError, Limit, OpenFlags, PagerStats, Statement, StepResult, Value,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Parser, Debug)]
 #[command(name = "Turso")]
@@ -63,20 +73,45 @@ pub struct Opts {
     pub experimental_indexes: bool,
     #[clap(short = 't', long, help = "specify output file for log traces")]
     pub tracing_output: Option<String>,
+    #[clap(long, help = "Print errors to stderr as JSON objects")]
+    pub json_errors: bool,
+    #[clap(long, help = "Disable the .system and .shell commands")]
+    pub no_shell: bool,
 }
 
 const PROMPT: &str = "turso> ";
+/// Number of VM instructions between checks for a pending Ctrl-C
+const INTERRUPT_CHECK_STEPS: u64 = 1000;
 
 pub struct Limbo {
     pub prompt: String,
     io: Arc<dyn turso_core::IO>,
     writer: Box<dyn Write>,
+    /// The active connection, one of `connections`
     conn: Arc<turso_core::Connection>,
+    /// Connections to the database opened with `.connection new`, the first one included
+    connections: Vec<Arc<turso_core::Connection>>,
+    /// Index of the active connection in `connections`
+    active_conn: usize,
     pub interrupt_count: Arc<AtomicUsize>,
+    /// Whether `.progress` printed dots during the running statement
+    progress_shown: Arc<AtomicBool>,
     input_buff: String,
     opts: Settings,
     pub rl: Option<Editor<LimboHelper, DefaultHistory>>,
     config: Option<Config>,
+    trace_file: Option<File>,
+    /// Whether statements are traced with their parameters replaced by their bound values
+    trace_expanded: bool,
+    /// Number of `.read` scripts currently being run
+    read_depth: usize,
+    /// Values set with `.parameter`, by the name the parameter has in statements, e.g. `:name`
+    /// or `?1`
+    parameters: BTreeMap<String, Value>,
+    /// Separator between the fields of a row in list and ascii modes, set by `.separator`
+    column_separator: String,
+    /// Separator written after every row in list and ascii modes, set by `.separator`
+    row_separator: String,
 }
 
 struct QueryStatistics {
@@ -152,17 +187,32 @@ impl Limbo {
         let sql = opts.sql.clone();
         let quiet = opts.quiet;
         let config = Config::for_output_mode(opts.output_mode);
+        let (column_separator, row_separator) = opts
+            .output_mode
+            .default_separators()
+            .or(OutputMode::List.default_separators())
+            .unwrap();
         let mut app = Self {
             prompt: PROMPT.to_string(),
             io,
             writer: get_writer(&opts.output),
+            connections: vec![conn.clone()],
+            active_conn: 0,
             conn,
             interrupt_count,
+            progress_shown: Arc::new(AtomicBool::new(false)),
             input_buff: String::new(),
             opts: Settings::from(opts),
             rl: None,
             config: Some(config),
+            trace_file: None,
+            trace_expanded: false,
+            read_depth: 0,
+            parameters: BTreeMap::new(),
+            column_separator: column_separator.to_string(),
+            row_separator: row_separator.to_string(),
         };
+        app.install_interrupt_handler();
         app.first_run(sql, quiet)?;
         Ok(app)
     }
@@ -327,6 +377,113 @@ impl Limbo {
         Ok(())
     }
 
+    /// Writes a script that rebuilds the database from the rows salvaged by reading its pages
+    /// directly, followed by the number of rows recovered from each table. Rows that belong to
+    /// no table are inserted into a `lost_and_found` table.
+    fn recover_database(&mut self) -> anyhow::Result<()> {
+        let recovered = self.conn.recover()?;
+        let mut schema = Vec::new();
+        query_internal!(
+            self,
+            r#"
+    SELECT type, name, sql
+    FROM sqlite_schema
+    WHERE sql NOT NULL
+        AND name NOT LIKE 'sqlite_%'
+    ORDER BY rowid"#,
+            |row: &turso_core::Row| -> Result<(), LimboError> {
+                let kind: &str = row.get::<&str>(0)?;
+                let name: &str = row.get::<&str>(1)?;
+                let sql: &str = row.get::<&str>(2)?;
+                schema.push((kind.to_string(), name.to_string(), sql.to_string()));
+                Ok(())
+            }
+        )?;
+
+        self.writeln("BEGIN;")?;
+        let mut report = Vec::new();
+        // Tables are filled before the indexes are created, like in a dump
+        for (_, name, sql) in schema.iter().filter(|(kind, _, _)| kind == "table") {
+            self.writeln(format!("{};", sql))?;
+            let Some(table) = recovered.iter().find(|table| {
+                table
+                    .name
+                    .as_ref()
+                    .is_some_and(|table| table.eq_ignore_ascii_case(name))
+            }) else {
+                continue;
+            };
+            for row in &table.rows {
+                let len = row.values.len().min(table.columns.len());
+                let mut columns = table.columns[..len]
+                    .iter()
+                    .map(|column| quote_identifier(column))
+                    .collect::<Vec<_>>();
+                let mut values = row.values[..len]
+                    .iter()
+                    .map(sql_literal)
+                    .collect::<Vec<_>>();
+                if table.rowid_alias.map_or(true, |pos| pos >= len) {
+                    columns.insert(0, "rowid".to_string());
+                    values.insert(0, row.rowid.to_string());
+                }
+                self.writeln(format!(
+                    "INSERT INTO {}({}) VALUES({});",
+                    quote_identifier(name),
+                    columns.join(","),
+                    values.join(",")
+                ))?;
+            }
+            report.push((quote_identifier(name), table.rows.len()));
+        }
+        for (_, _, sql) in schema.iter().filter(|(kind, _, _)| kind != "table") {
+            self.writeln(format!("{};", sql))?;
+        }
+
+        if let Some(lost) = recovered.iter().find(|table| table.name.is_none()) {
+            let mut name = "lost_and_found".to_string();
+            let mut suffix = 0;
+            while schema
+                .iter()
+                .any(|(_, existing, _)| existing.eq_ignore_ascii_case(&name))
+            {
+                name = format!("lost_and_found_{}", suffix);
+                suffix += 1;
+            }
+            let width = lost
+                .rows
+                .iter()
+                .map(|row| row.values.len())
+                .max()
+                .unwrap_or(0);
+            let columns = (0..width).map(|i| format!(", c{}", i)).collect::<String>();
+            self.writeln(format!(
+                "CREATE TABLE {}(pgno INTEGER, nfield INTEGER, id INTEGER{});",
+                name, columns
+            ))?;
+            for row in &lost.rows {
+                let mut values = vec![
+                    row.page.to_string(),
+                    row.values.len().to_string(),
+                    row.rowid.to_string(),
+                ];
+                values.extend(row.values.iter().map(sql_literal));
+                values.resize(width + 3, "NULL".to_string());
+                self.writeln(format!(
+                    "INSERT INTO {} VALUES({});",
+                    name,
+                    values.join(",")
+                ))?;
+            }
+            report.push((quote_identifier(&name), lost.rows.len()));
+        }
+        self.writeln("COMMIT;")?;
+        for (name, rows) in report {
+            self.writeln(format!("/* {} rows recovered from {} */", rows, name))?;
+        }
+        Ok(())
+    }
+
     fn display_in_memory(&mut self) -> io::Result<()> {
         if self.opts.db_file == ":memory:" {
             self.writeln("Connected to a transient in-memory database.")?;
@@ -337,16 +494,95 @@ impl Limbo {
 
     fn show_info(&mut self) -> io::Result<()> {
         let opts = format!("{}", self.opts);
-        self.writeln(opts)
+        self.writeln(opts)?;
+        self.writeln(format!(
+            "Column separator: {:?}\nRow separator: {:?}",
+            self.column_separator, self.row_separator
+        ))
     }
 
     pub fn reset_input(&mut self) {
-        self.prompt = PROMPT.to_string();
+        self.prompt = self.main_prompt();
         self.input_buff.clear();
     }
 
+    /// The prompt for a new statement, which shows the active connection once there are several
+    fn main_prompt(&self) -> String {
+        if self.connections.len() > 1 {
+            format!("turso[{}]> ", self.active_conn)
+        } else {
+            PROMPT.to_string()
+        }
+    }
+
     pub fn close_conn(&mut self) -> Result<(), LimboError> {
-        self.conn.close()
+        for conn in &self.connections {
+            conn.close()?;
+        }
+        Ok(())
+    }
+
+    /// Lists the connections to the database when `target` is `None`, otherwise switches to the
+    /// connection at that index or to a new one. Every connection keeps its own transaction, so
+    /// statements can be interleaved between them to observe locking.
+    fn handle_connection(&mut self, target: Option<ConnectionTarget>) -> anyhow::Result<()> {
+        match target {
+            None => {
+                for (i, conn) in self.connections.clone().iter().enumerate() {
+                    let state = if conn.get_auto_commit() {
+                        "autocommit"
+                    } else {
+                        "in transaction"
+                    };
+                    let marker = if i == self.active_conn { "ACTIVE" } else { "" };
+                    self.writeln(format!(
+                        "{:>6} {}: {} ({})",
+                        marker, i, self.opts.db_file, state
+                    ))?;
+                }
+                return Ok(());
+            }
+            Some(ConnectionTarget::New) => {
+                let conn = self.conn.database().connect()?;
+                self.connections.push(conn);
+                self.active_conn = self.connections.len() - 1;
+            }
+            Some(ConnectionTarget::Index(i)) => {
+                if i >= self.connections.len() {
+                    return Err(anyhow!(
+                        "no connection {}, there are {} connections",
+                        i,
+                        self.connections.len()
+                    ));
+                }
+                self.active_conn = i;
+            }
+        }
+        self.conn = self.connections[self.active_conn].clone();
+        self.install_interrupt_handler();
+        self.prompt = self.main_prompt();
+        Ok(())
+    }
+
+    /// Whether the input comes from a script, either piped through stdin or run with
+    /// `.read`, rather than being typed at the prompt
+    fn reading_script(&self) -> bool {
+        self.rl.is_none() || self.read_depth > 0
+    }
+
+    fn read_script(&mut self, path: &str) -> anyhow::Result<()> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot open \"{}\": {}", path, e))?;
+        self.read_depth += 1;
+        for line in contents.lines() {
+            if let Err(e) = self.handle_input_line(line.trim()) {
+                self.report_input_error(line.trim(), &e);
+            }
+        }
+        // Like at the end of stdin, a statement missing its semicolon still runs
+        self.handle_remaining_input();
+        self.read_depth -= 1;
+        Ok(())
     }
 
     fn toggle_echo(&mut self, arg: EchoMode) {
@@ -357,9 +593,14 @@ impl Limbo {
     }
 
     fn open_db(&mut self, path: &str, vfs_name: Option<&str>) -> anyhow::Result<()> {
-        self.conn.close()?;
+        self.close_conn()?;
+        let db_path = match path {
+            ":memory:" => path.to_string(),
+            _ => self.resolve_path(path).to_string_lossy().to_string(),
+        };
+        let db_path = db_path.as_str();
         let (io, db) = if let Some(vfs_name) = vfs_name {
-            self.conn.open_new(path, vfs_name)?
+            self.conn.open_new(db_path, vfs_name)?
         } else {
             let io = {
                 match path {
@@ -369,25 +610,55 @@ impl Limbo {
             };
             (
                 io.clone(),
-                Database::open_file(io.clone(), path, false, false)?,
+                Database::open_file(io.clone(), db_path, false, false)?,
             )
         };
         self.io = io;
         self.conn = db.connect()?;
+        self.connections = vec![self.conn.clone()];
+        self.active_conn = 0;
+        self.prompt = self.main_prompt();
+        self.install_interrupt_handler();
         self.opts.db_file = path.to_string();
         Ok(())
     }
 
+    /// Lets Ctrl-C interrupt a running statement even when it does not produce rows. With
+    /// `.progress`, the same handler prints a dot to stderr every time it runs.
+    fn install_interrupt_handler(&self) {
+        let interrupt_count = Arc::clone(&self.interrupt_count);
+        let progress_shown = Arc::clone(&self.progress_shown);
+        let progress = self.opts.progress;
+        let steps = if progress > 0 {
+            progress
+        } else {
+            INTERRUPT_CHECK_STEPS
+        };
+        self.conn.set_progress_handler(
+            steps,
+            Some(Box::new(move || {
+                if progress > 0 {
+                    eprint!(".");
+                    let _ = io::stderr().flush();
+                    progress_shown.store(true, Ordering::SeqCst);
+                }
+                interrupt_count.load(Ordering::SeqCst) > 0
+            })),
+        );
+    }
+
     fn set_output_file(&mut self, path: &str) -> Result<(), String> {
         if path.is_empty() || path.trim().eq_ignore_ascii_case("stdout") {
             self.set_output_stdout();
             return Ok(());
         }
-        match std::fs::File::create(path) {
+        match std::fs::File::create(self.resolve_path(path)) {
             Ok(file) => {
                 self.writer = Box::new(file);
                 self.opts.is_stdout = false;
-                self.opts.output_mode = OutputMode::List;
+                if self.opts.output_mode == OutputMode::Pretty {
+                    self.opts.output_mode = OutputMode::List;
+                }
                 self.opts.output_filename = path.to_string();
                 Ok(())
             }
@@ -395,6 +666,65 @@ impl Limbo {
         }
     }
 
+    /// Resolves a path given to a dot-command against the directory set with `.cd`. The process
+    /// working directory is left alone, so the open database keeps the paths it was opened with.
+    fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.opts.cwd.join(path)
+    }
+
+    fn change_directory(&mut self, directory: &str) -> Result<(), String> {
+        match self.resolve_path(directory).canonicalize() {
+            Ok(path) if path.is_dir() => {
+                self.opts.cwd = path;
+                Ok(())
+            }
+            _ => Err(format!("Cannot change to directory \"{}\"", directory)),
+        }
+    }
+
+    /// Runs `command` in the system shell from the current directory of the shell, and writes
+    /// what it prints followed by its exit status when it fails, like `.system` in SQLite
+    fn run_system_command(&mut self, command: &[String]) -> Result<(), String> {
+        if self.opts.no_shell {
+            return Err("the .system and .shell commands are disabled".to_string());
+        }
+        // Like sqlite3, the arguments holding whitespace are quoted to be passed as a whole
+        let command = command
+            .iter()
+            .map(|arg| {
+                if arg.is_empty() || arg.contains(char::is_whitespace) {
+                    format!("\"{}\"", arg)
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut shell = if cfg!(windows) {
+            let mut shell = std::process::Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = std::process::Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        let output = shell
+            .arg(&command)
+            .current_dir(&self.opts.cwd)
+            .output()
+            .map_err(|e| format!("cannot run \"{}\": {}", command, e))?;
+        let _ = self.writer.write_all(&output.stdout);
+        let _ = io::stderr().write_all(&output.stderr);
+        if !output.status.success() {
+            let _ = match output.status.code() {
+                Some(code) => self.writeln(format!("System command returns {}", code)),
+                None => self.writeln("System command was terminated by a signal"),
+            };
+        }
+        Ok(())
+    }
+
     fn set_output_stdout(&mut self) {
         let _ = self.writer.flush();
         self.writer = Box::new(io::stdout());
@@ -406,10 +736,73 @@ impl Limbo {
             Err("pretty output can only be written to a tty".to_string())
         } else {
             self.opts.output_mode = mode;
+            if let Some((column_separator, row_separator)) = mode.default_separators() {
+                self.column_separator = column_separator.to_string();
+                self.row_separator = row_separator.to_string();
+            }
             Ok(())
         }
     }
 
+    fn set_trace_file(&mut self, path: &str) -> Result<(), String> {
+        if path.eq_ignore_ascii_case("off") {
+            self.trace_file = None;
+            return Ok(());
+        }
+        match std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+        {
+            Ok(file) => {
+                self.trace_file = Some(file);
+                Ok(())
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Lists all limits, shows a single limit or sets it when a value is given
+    fn display_limits(&mut self, args: LimitArgs) -> Result<(), String> {
+        let limits = match args.name {
+            Some(name) => {
+                let limit = Limit::from_name(&name).ok_or_else(|| {
+                    format!(
+                        "unknown limit: \"{}\"\nenter \".limits\" with no arguments for a list.",
+                        name
+                    )
+                })?;
+                if let Some(value) = args.value {
+                    self.conn.set_limit(limit, value);
+                }
+                vec![limit]
+            }
+            None => Limit::ALL.to_vec(),
+        };
+        for limit in limits {
+            let value = self.conn.limit(limit);
+            let _ = self.write_fmt(format_args!("{:>20} {}", limit.name(), value));
+        }
+        Ok(())
+    }
+
+    /// Appends a statement to the trace file, if tracing is enabled
+    fn trace_statement(&mut self, sql: &str) {
+        let Some(file) = self.trace_file.as_mut() else {
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let _ = writeln!(
+            file,
+            "-- {}.{:03}\n{}",
+            timestamp.as_secs(),
+            timestamp.subsec_millis(),
+            sql.trim()
+        );
+    }
+
     fn write_fmt(&mut self, fmt: std::fmt::Arguments) -> io::Result<()> {
         let _ = self.writer.write_fmt(fmt);
         self.writer.write_all(b"\n")
@@ -426,12 +819,18 @@ impl Limbo {
     }
 
     fn run_query(&mut self, input: &str) {
+        // Expanded traces are written for each statement once it is prepared, as they need
+        // the values bound to it
+        if !self.trace_expanded {
+            self.trace_statement(input);
+        }
         let echo = self.opts.echo;
         if echo {
-            let _ = self.writeln(input);
+            let _ = self.writeln(input.trim_end());
         }
 
         let start = Instant::now();
+        let pager_stats = self.conn.pager_stats();
         let mut stats = QueryStatistics {
             io_time_elapsed_samples: vec![],
             execute_time_elapsed_samples: vec![],
@@ -447,17 +846,26 @@ impl Limbo {
         {
             match self.conn.query(input) {
                 Ok(Some(stmt)) => {
+                    if self.trace_expanded {
+                        self.trace_statement(&stmt.expanded_sql());
+                    }
                     let _ = self.writeln(stmt.explain().as_bytes());
                 }
-                Err(e) => {
-                    let _ = self.writeln(e.to_string());
-                }
+                Err(e) => self.write_error(input, e, true),
                 _ => {}
             }
         } else {
             let conn = self.conn.clone();
             let runner = conn.query_runner(input.as_bytes());
-            for output in runner {
+            for mut output in runner {
+                if let Ok(Some(stmt)) = &mut output {
+                    if let Err(e) = self.bind_parameters(stmt) {
+                        output = Err(e);
+                    }
+                }
+                if let (true, Ok(Some(stmt))) = (self.trace_expanded, &output) {
+                    self.trace_statement(&stmt.expanded_sql());
+                }
                 if self
                     .print_query_result(input, output, Some(&mut stats))
                     .is_err()
@@ -466,10 +874,96 @@ impl Limbo {
                 }
             }
         }
+        // The dots end their line before anything else is printed
+        if self.progress_shown.swap(false, Ordering::SeqCst) {
+            eprintln!();
+        }
         self.print_query_performance_stats(start, stats);
+        if self.opts.stats {
+            let current = self.conn.pager_stats();
+            let _ = self.display_pager_stats(PagerStats {
+                cache_hits: current.cache_hits - pager_stats.cache_hits,
+                cache_misses: current.cache_misses - pager_stats.cache_misses,
+                pages_written: current.pages_written - pager_stats.pages_written,
+                ..current
+            });
+        }
         self.reset_input();
     }
 
+    /// Binds the values set with `.parameter` to the parameters of a statement. Like in
+    /// sqlite3's shell, an anonymous `?` is looked up as `?N`, N being its index.
+    fn bind_parameters(&self, stmt: &mut Statement) -> Result<(), LimboError> {
+        let mut indexes = stmt
+            .parameters()
+            .list
+            .iter()
+            .map(|parameter| parameter.index())
+            .collect::<Vec<_>>();
+        indexes.sort();
+        indexes.dedup();
+        for index in indexes {
+            let name = match stmt.parameters().name(index) {
+                Some(name) if name != "?" => name,
+                _ => format!("?{}", index),
+            };
+            let Some(value) = self.parameters.get(&name) else {
+                return Err(LimboError::InvalidArgument(format!(
+                    "no value for parameter {}, set it with .parameter set {} VALUE",
+                    name, name
+                )));
+            };
+            stmt.bind_at(index, value.clone());
+        }
+        Ok(())
+    }
+
+    /// Sets a parameter to the value of an SQL expression, or to the expression as text when it
+    /// can't be evaluated, like sqlite3's `.parameter set`
+    fn set_parameter(&mut self, name: String, value: String) {
+        let value = match self.evaluate(&value) {
+            Ok(value) => value,
+            Err(_) => Value::build_text(value),
+        };
+        self.parameters.insert(name, value);
+    }
+
+    fn list_parameters(&mut self) -> io::Result<()> {
+        let width = self.parameters.keys().map(String::len).max().unwrap_or(0);
+        let lines = self
+            .parameters
+            .iter()
+            .map(|(name, value)| format!("{:<width$} {}", name, value.exec_quote()))
+            .collect::<Vec<_>>();
+        for line in lines {
+            self.writeln(line)?;
+        }
+        Ok(())
+    }
+
+    fn display_pager_stats(&mut self, stats: PagerStats) -> io::Result<()> {
+        self.write_fmt(format_args!(
+            "Memory Used:                         {} (bytes)",
+            stats.cache_memory
+        ))?;
+        self.write_fmt(format_args!(
+            "Pages in cache:                      {}",
+            stats.cached_pages
+        ))?;
+        self.write_fmt(format_args!(
+            "Page cache hits:                     {}",
+            stats.cache_hits
+        ))?;
+        self.write_fmt(format_args!(
+            "Page cache misses:                   {}",
+            stats.cache_misses
+        ))?;
+        self.write_fmt(format_args!(
+            "Pages written:                       {}",
+            stats.pages_written
+        ))
+    }
+
     fn print_query_performance_stats(&mut self, start: Instant, stats: QueryStatistics) {
         let elapsed_as_str = |duration: Duration| {
             if duration.as_secs() >= 1 {
@@ -524,6 +1018,10 @@ impl Limbo {
                 return Ok(());
             }
             if let Some(command) = line.strip_prefix('.') {
+                // Like sqlite3, dot-commands are only echoed when they come from a script
+                if self.opts.echo && self.reading_script() {
+                    let _ = self.writeln(line);
+                }
                 self.handle_dot_command(command);
                 let _ = self.reset_line(line);
                 return Ok(());
@@ -569,7 +1067,17 @@ impl Limbo {
     }
 
     pub fn handle_dot_command(&mut self, line: &str) {
-        let args: Vec<&str> = line.split_whitespace().collect();
+        // The arguments of a shell command may be quoted to hold whitespace, as in sqlite3
+        let args: Vec<String> = match line.split_whitespace().next() {
+            Some("system" | "shell") => match shlex::split(line) {
+                Some(args) => args,
+                None => {
+                    let _ = self.writeln("Error: unterminated quote");
+                    return;
+                }
+            },
+            _ => line.split_whitespace().map(str::to_string).collect(),
+        };
         if args.is_empty() {
             return;
         }
@@ -584,6 +1092,10 @@ impl Limbo {
                     std::process::exit(args.code);
                 }
                 Command::Quit => {
+                    if self.opts.stats {
+                        let _ = self.writeln("Session statistics:");
+                        let _ = self.display_pager_stats(self.conn.pager_stats());
+                    }
                     let _ = self.writeln("Exiting Turso SQL Shell.");
                     let _ = self.close_conn();
                     self.save_history();
@@ -623,6 +1135,9 @@ impl Limbo {
                 Command::OutputMode(args) => {
                     if let Err(e) = self.set_mode(args.mode) {
                         let _ = self.write_fmt(format_args!("Error: {}", e));
+                    } else {
+                        self.opts.csv_quote_all = args.quote_all;
+                        self.opts.csv_crlf = args.crlf;
                     }
                 }
                 Command::SetOutput(args) => {
@@ -637,16 +1152,57 @@ impl Limbo {
                 Command::Echo(args) => {
                     self.toggle_echo(args.mode);
                 }
-                Command::Cwd(args) => {
-                    let _ = std::env::set_current_dir(args.directory);
+                Command::Read(args) => {
+                    if let Err(e) = self.read_script(&args.path) {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                }
+                Command::Cwd(args) => match args.directory {
+                    Some(directory) => {
+                        if let Err(e) = self.change_directory(&directory) {
+                            let _ = self.writeln(e);
+                        }
+                    }
+                    None => {
+                        let _ = self.writeln(self.opts.cwd.display().to_string());
+                    }
+                },
+                Command::System(args) => {
+                    if let Err(e) = self.run_system_command(&args.command) {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                }
+                Command::DbInfo => {
+                    if let Err(e) = self.display_dbinfo() {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                }
+                Command::HexDump(args) => {
+                    if let Err(e) = self.display_hexdump(args.page) {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
                 }
                 Command::ShowInfo => {
                     let _ = self.show_info();
                 }
-                Command::Import(args) => {
+                Command::Import(mut args) => {
+                    args.file = self.resolve_path(&args.file);
+                    // In ascii mode, what the mode writes is read back
+                    let separators = if args.ascii {
+                        OutputMode::Ascii.default_separators()
+                    } else if self.opts.output_mode == OutputMode::Ascii {
+                        Some((self.column_separator.as_str(), self.row_separator.as_str()))
+                    } else {
+                        None
+                    };
                     let mut import_file =
                         ImportFile::new(self.conn.clone(), self.io.clone(), &mut self.writer);
-                    import_file.import(args)
+                    match separators {
+                        Some((column_separator, row_separator)) => {
+                            import_file.import_ascii(args, column_separator, row_separator)
+                        }
+                        None => import_file.import(args),
+                    }
                 }
                 Command::LoadExtension(args) => {
                     #[cfg(not(target_family = "wasm"))]
@@ -659,6 +1215,11 @@ impl Limbo {
                         let _ = self.write_fmt(format_args!("/****** ERROR: {} ******/", e));
                     }
                 }
+                Command::Recover => {
+                    if let Err(e) = self.recover_database() {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                }
                 Command::ListVfs => {
                     let _ = self.writeln("Available VFS modules:");
                     self.conn.list_vfs().iter().for_each(|v| {
@@ -676,10 +1237,155 @@ impl Limbo {
                         TimerMode::Off => false,
                     };
                 }
+                Command::Progress(args) => {
+                    self.opts.progress = args.steps;
+                    self.install_interrupt_handler();
+                }
+                Command::Trace(args) => {
+                    if let Err(e) = self.set_trace_file(&args.file) {
+                        let _ =
+                            self.writeln(format!("Error: cannot open \"{}\": {}", args.file, e));
+                    }
+                    self.trace_expanded = args.expanded;
+                }
+                Command::Limit(args) => {
+                    if let Err(e) = self.display_limits(args) {
+                        let _ = self.writeln(e);
+                    }
+                }
+                Command::Width(args) => {
+                    self.opts.column_widths = args.widths;
+                }
+                Command::Separator(args) => {
+                    self.column_separator = args.column;
+                    if let Some(row) = args.row {
+                        self.row_separator = row;
+                    }
+                }
+                Command::Headers(args) => {
+                    self.opts.headers = match args.mode {
+                        HeadersMode::On => true,
+                        HeadersMode::Off => false,
+                    };
+                }
+                Command::Types(args) => {
+                    self.opts.types = match args.mode {
+                        TypesMode::On => true,
+                        TypesMode::Off => false,
+                    };
+                }
+                Command::Binary(args) => {
+                    self.opts.binary = match args.mode {
+                        BinaryMode::On => true,
+                        BinaryMode::Off => false,
+                    };
+                }
+                Command::Selftest(args) => {
+                    if let Err(e) = self.run_selftest(args) {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                }
+                Command::Sha3sum(args) => match self.sha3sum(args) {
+                    Ok(digest) => {
+                        let _ = self.writeln(digest);
+                    }
+                    Err(e) => {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                },
+                Command::Parameter(args) => match args.command {
+                    ParameterCommand::Set { name, value } => self.set_parameter(name, value),
+                    ParameterCommand::Unset { name } => {
+                        self.parameters.remove(&name);
+                    }
+                    ParameterCommand::List => {
+                        let _ = self.list_parameters();
+                    }
+                    ParameterCommand::Clear => self.parameters.clear(),
+                },
+                Command::Connection(args) => {
+                    if let Err(e) = self.handle_connection(args.target) {
+                        let _ = self.writeln(format!("Error: {}", e));
+                    }
+                }
+                Command::Stats(args) => match args.mode {
+                    Some(StatsMode::On) => self.opts.stats = true,
+                    Some(StatsMode::Off) => self.opts.stats = false,
+                    None => {
+                        let _ = self.display_pager_stats(self.conn.pager_stats());
+                    }
+                },
             },
         }
     }
 
+    /// Renders a non-NULL `value`. With `.binary on`, BLOBs are written as hex literals and the
+    /// non-printable characters of text are escaped, so that no raw bytes reach the terminal
+    fn format_value(&self, value: &Value) -> String {
+        if !self.opts.binary {
+            return format!("{}", value);
+        }
+        match value {
+            Value::Blob(_) => value.exec_quote().to_string(),
+            Value::Text(text) => escape_non_printable(text.as_str()),
+            _ => format!("{}", value),
+        }
+    }
+
+    /// Appends the storage class of `value` to its rendered `content` when `.types` is on
+    fn annotate_type(&self, content: String, value: &Value) -> String {
+        if !self.opts.types {
+            return content;
+        }
+        format!("{} ({})", content, value.exec_typeof())
+    }
+
+    /// Truncates `content` to the width set by `.width` for column `idx`, cutting between
+    /// grapheme clusters and ending with an ellipsis
+    fn fit_column_width(&self, idx: usize, content: &str) -> String {
+        match self.opts.column_widths.get(idx) {
+            Some(&width) if width > 0 => truncate_to_width(content, width),
+            _ => content.to_string(),
+        }
+    }
+
+    /// Writes a row of list, csv, html or quote output, opening the html table if it is not open yet
+    fn write_list_row(
+        &mut self,
+        cells: &[String],
+        header: bool,
+        html_table_open: &mut bool,
+    ) -> io::Result<()> {
+        match self.opts.output_mode {
+            OutputMode::Html => {
+                if !*html_table_open {
+                    self.writeln("<table>")?;
+                    *html_table_open = true;
+                }
+                let tag = if header { "th" } else { "td" };
+                let row = cells
+                    .iter()
+                    .map(|cell| format!("<{tag}>{}</{tag}>", html_escape(cell)))
+                    .collect::<String>();
+                self.writeln(format!("<tr>{}</tr>", row))
+            }
+            OutputMode::Csv => {
+                self.writer.write_all(cells.join(",").as_bytes())?;
+                if self.opts.csv_crlf {
+                    self.writer.write_all(b"\r\n")
+                } else {
+                    self.writeln("")
+                }
+            }
+            OutputMode::Quote => self.writeln(cells.join(",")),
+            _ => {
+                self.writer
+                    .write_all(cells.join(&self.column_separator).as_bytes())?;
+                self.writer.write_all(self.row_separator.as_bytes())
+            }
+        }
+    }
+
     fn print_query_result(
         &mut self,
         sql: &str,
@@ -688,65 +1394,108 @@ impl Limbo {
     ) -> anyhow::Result<()> {
         match output {
             Ok(Some(ref mut rows)) => match self.opts.output_mode {
-                OutputMode::List => loop {
-                    if self.interrupt_count.load(Ordering::SeqCst) > 0 {
-                        println!("Query interrupted.");
-                        return Ok(());
+                OutputMode::List
+                | OutputMode::Csv
+                | OutputMode::Html
+                | OutputMode::Quote
+                | OutputMode::Ascii => {
+                    let csv = self.opts.output_mode == OutputMode::Csv;
+                    let quote = self.opts.output_mode == OutputMode::Quote;
+                    // The html table is opened by its first line, so that statements without
+                    // results print nothing
+                    let mut html_table_open = false;
+                    if self.opts.headers && rows.num_columns() > 0 {
+                        let names = (0..rows.num_columns())
+                            .map(|i| {
+                                let name = rows.get_column_name(i);
+                                if csv {
+                                    csv_quote(name, self.opts.csv_quote_all)
+                                } else if quote {
+                                    Value::build_text(name).exec_quote().to_string()
+                                } else {
+                                    name.to_string()
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        self.write_list_row(&names, true, &mut html_table_open)?;
                     }
+                    loop {
+                        if self.interrupt_count.load(Ordering::SeqCst) > 0 {
+                            self.writeln("Query interrupted.")?;
+                            return Ok(());
+                        }
 
-                    let start = Instant::now();
+                        let start = Instant::now();
 
-                    match rows.step() {
-                        Ok(StepResult::Row) => {
-                            if let Some(ref mut stats) = statistics {
-                                stats.execute_time_elapsed_samples.push(start.elapsed());
-                            }
-                            let row = rows.row().unwrap();
-                            for (i, value) in row.get_values().enumerate() {
-                                if i > 0 {
-                                    let _ = self.writer.write(b"|");
+                        match rows.step() {
+                            Ok(StepResult::Row) => {
+                                if let Some(ref mut stats) = statistics {
+                                    stats.execute_time_elapsed_samples.push(start.elapsed());
                                 }
-                                if matches!(value, Value::Null) {
-                                    let _ = self.writer.write(self.opts.null_value.as_bytes())?;
-                                } else {
-                                    let _ = self.writer.write(format!("{}", value).as_bytes())?;
+                                let row = rows.row().unwrap();
+                                let mut cells = Vec::with_capacity(row.len());
+                                for value in row.get_values() {
+                                    // Quote mode writes SQL literals, so NULL is always NULL
+                                    let content = if quote && self.opts.binary {
+                                        escape_non_printable(&value.exec_quote().to_string())
+                                    } else if quote {
+                                        value.exec_quote().to_string()
+                                    } else if matches!(value, Value::Null) {
+                                        self.opts.null_value.clone()
+                                    } else {
+                                        self.format_value(value)
+                                    };
+                                    let content = self.annotate_type(content, value);
+                                    // NULLs are written as the null value as is, like in sqlite
+                                    let content = if csv && !matches!(value, Value::Null) {
+                                        csv_quote(&content, self.opts.csv_quote_all)
+                                    } else {
+                                        content
+                                    };
+                                    cells.push(content);
                                 }
+                                self.write_list_row(&cells, false, &mut html_table_open)?;
                             }
-                            let _ = self.writeln("");
-                        }
-                        Ok(StepResult::IO) => {
-                            let start = Instant::now();
-                            self.io.run_once()?;
-                            if let Some(ref mut stats) = statistics {
-                                stats.io_time_elapsed_samples.push(start.elapsed());
+                            Ok(StepResult::IO) => {
+                                let start = Instant::now();
+                                self.io.run_once()?;
+                                if let Some(ref mut stats) = statistics {
+                                    stats.io_time_elapsed_samples.push(start.elapsed());
+                                }
                             }
-                        }
-                        Ok(StepResult::Interrupt) => break,
-                        Ok(StepResult::Done) => {
-                            if let Some(ref mut stats) = statistics {
-                                stats.execute_time_elapsed_samples.push(start.elapsed());
+                            Ok(StepResult::Interrupt) => {
+                                self.writeln("Query interrupted.")?;
+                                break;
                             }
-                            break;
-                        }
-                        Ok(StepResult::Busy) => {
-                            if let Some(ref mut stats) = statistics {
-                                stats.execute_time_elapsed_samples.push(start.elapsed());
+                            Ok(StepResult::Done) => {
+                                if let Some(ref mut stats) = statistics {
+                                    stats.execute_time_elapsed_samples.push(start.elapsed());
+                                }
+                                break;
                             }
-                            let _ = self.writeln("database is busy");
-                            break;
-                        }
-                        Err(err) => {
-                            if let Some(ref mut stats) = statistics {
-                                stats.execute_time_elapsed_samples.push(start.elapsed());
+                            Ok(StepResult::Busy) => {
+                                if let Some(ref mut stats) = statistics {
+                                    stats.execute_time_elapsed_samples.push(start.elapsed());
+                                }
+                                self.write_error(sql, LimboError::Busy, true);
+                                break;
+                            }
+                            Err(err) => {
+                                if let Some(ref mut stats) = statistics {
+                                    stats.execute_time_elapsed_samples.push(start.elapsed());
+                                }
+                                self.write_error(sql, err, true);
+                                break;
                             }
-                            let _ = self.writeln(err.to_string());
-                            break;
                         }
                     }
-                },
+                    if html_table_open {
+                        self.writeln("</table>")?;
+                    }
+                }
                 OutputMode::Pretty => {
                     if self.interrupt_count.load(Ordering::SeqCst) > 0 {
-                        println!("Query interrupted.");
+                        self.writeln("Query interrupted.")?;
                         return Ok(());
                     }
                     let config = self.config.as_ref().unwrap();
@@ -758,7 +1507,7 @@ impl Limbo {
                     if rows.num_columns() > 0 {
                         let header = (0..rows.num_columns())
                             .map(|i| {
-                                let name = rows.get_column_name(i);
+                                let name = self.fit_column_width(i, rows.get_column_name(i));
                                 Cell::new(name)
                                     .add_attribute(Attribute::Bold)
                                     .fg(config.table.header_color.as_comfy_table_color())
@@ -766,6 +1515,7 @@ impl Limbo {
                             .collect::<Vec<_>>();
                         table.set_header(header);
                     }
+                    let mut interrupted = false;
                     loop {
                         let start = Instant::now();
                         match rows.step() {
@@ -787,13 +1537,12 @@ impl Limbo {
                                         Value::Float(_) => {
                                             (format!("{}", value), CellAlignment::Right)
                                         }
-                                        Value::Text(_) => {
-                                            (format!("{}", value), CellAlignment::Left)
-                                        }
-                                        Value::Blob(_) => {
-                                            (format!("{}", value), CellAlignment::Left)
+                                        Value::Text(_) | Value::Blob(_) => {
+                                            (self.format_value(value), CellAlignment::Left)
                                         }
                                     };
+                                    let content = self.annotate_type(content, value);
+                                    let content = self.fit_column_width(idx, &content);
                                     row.add_cell(
                                         Cell::new(content)
                                             .set_alignment(alignment)
@@ -815,6 +1564,7 @@ impl Limbo {
                                 if let Some(ref mut stats) = statistics {
                                     stats.execute_time_elapsed_samples.push(start.elapsed());
                                 }
+                                interrupted = true;
                                 break;
                             }
                             Ok(StepResult::Done) => {
@@ -827,16 +1577,14 @@ impl Limbo {
                                 if let Some(ref mut stats) = statistics {
                                     stats.execute_time_elapsed_samples.push(start.elapsed());
                                 }
-                                let _ = self.writeln("database is busy");
+                                self.write_error(sql, LimboError::Busy, true);
                                 break;
                             }
                             Err(err) => {
                                 if let Some(ref mut stats) = statistics {
                                     stats.execute_time_elapsed_samples.push(start.elapsed());
                                 }
-                                let report =
-                                    miette::Error::from(err).with_source_code(sql.to_owned());
-                                let _ = self.write_fmt(format_args!("{:?}", report));
+                                self.write_error(sql, err, false);
                                 break;
                             }
                         }
@@ -845,18 +1593,49 @@ impl Limbo {
                     if !table.is_empty() {
                         let _ = self.write_fmt(format_args!("{}", table));
                     }
+                    // The rows read before the interruption are still shown
+                    if interrupted {
+                        self.writeln("Query interrupted.")?;
+                    }
                 }
             },
             Ok(None) => {}
             Err(err) => {
-                let report = miette::Error::from(err).with_source_code(sql.to_owned());
-                let _ = self.write_fmt(format_args!("{:?}", report));
+                self.write_error(sql, err, false);
                 anyhow::bail!("We have to throw here, even if we printed error");
             }
         }
         Ok(())
     }
 
+    /// Reports an error raised while running `sql`. With `--json-errors` it is printed to
+    /// stderr as a JSON object, otherwise it goes to the output either as a plain message
+    /// or as a report pointing into the SQL.
+    fn write_error(&mut self, sql: &str, err: LimboError, plain: bool) {
+        if self.opts.json_errors {
+            print_json_error(err.code(), err.constraint_kind(), &err, sql);
+        } else if matches!(err, LimboError::Busy) {
+            let _ = self.writeln("database is busy");
+        } else if plain {
+            let _ = self.writeln(err.to_string());
+        } else {
+            let report = miette::Error::from(err).with_source_code(sql.to_owned());
+            let _ = self.write_fmt(format_args!("{:?}", report));
+        }
+    }
+
+    /// Reports an error returned while handling an input line
+    pub fn report_input_error(&self, line: &str, err: &anyhow::Error) {
+        if self.opts.json_errors {
+            let limbo_err = err.downcast_ref::<LimboError>();
+            let code = limbo_err.map_or("error", LimboError::code);
+            let constraint = limbo_err.and_then(LimboError::constraint_kind);
+            print_json_error(code, constraint, err, line);
+        } else {
+            eprintln!("{}", err);
+        }
+    }
+
     pub fn init_tracing(&mut self) -> Result<WorkerGuard, std::io::Error> {
         let ((non_blocking, guard), should_emit_ansi) =
             if let Some(file) = &self.opts.tracing_output {
@@ -996,27 +1775,381 @@ impl Limbo {
         Ok(())
     }
 
+    /// Prints a page in the `hexdump -C` layout: offset, 16 bytes in hex and their ASCII
+    /// rendering. Runs of identical lines are collapsed into a single `*`.
+    fn display_hexdump(&mut self, page: usize) -> anyhow::Result<()> {
+        let bytes = self.conn.page_bytes(page)?;
+        let mut previous: Option<&[u8]> = None;
+        let mut collapsed = false;
+        for (i, line) in bytes.chunks(16).enumerate() {
+            if previous == Some(line) {
+                if !collapsed {
+                    self.writeln("*")?;
+                    collapsed = true;
+                }
+                continue;
+            }
+            previous = Some(line);
+            collapsed = false;
+            let mut hex = String::with_capacity(49);
+            for (j, byte) in line.iter().enumerate() {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x} ", byte));
+            }
+            let ascii = line
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            self.writeln(format!("{:08x}  {:<49} |{}|", i * 16, hex, ascii))?;
+        }
+        self.writeln(format!("{:08x}", bytes.len()))?;
+        Ok(())
+    }
+
+    fn display_dbinfo(&mut self) -> anyhow::Result<()> {
+        let header = match self.conn.database_header() {
+            Ok(header) => header,
+            Err(_) => return Err(anyhow!("unable to read database header")),
+        };
+        let page_size = match header.page_size {
+            1 => 65536,
+            size => size as u32,
+        };
+        let encoding = match header.text_encoding {
+            1 => " (utf8)",
+            2 => " (utf16le)",
+            3 => " (utf16be)",
+            _ => "",
+        };
+        let count_schema = |app: &mut Self, kind: &str| {
+            app.query_scalar(&format!(
+                "SELECT count(*) FROM sqlite_schema WHERE type='{}'",
+                kind
+            ))
+        };
+        let tables = count_schema(self, "table")?;
+        let indexes = count_schema(self, "index")?;
+        let triggers = count_schema(self, "trigger")?;
+        let views = count_schema(self, "view")?;
+        let schema_size =
+            self.query_scalar("SELECT coalesce(sum(length(sql)), 0) FROM sqlite_schema")?;
+
+        let fields: [(&str, String); 21] = [
+            ("database page size:", page_size.to_string()),
+            ("write format:", header.write_version.to_string()),
+            ("read format:", header.read_version.to_string()),
+            ("reserved bytes:", header.reserved_space.to_string()),
+            ("file change counter:", header.change_counter.to_string()),
+            ("database page count:", header.database_size.to_string()),
+            ("freelist page count:", header.freelist_pages.to_string()),
+            ("schema cookie:", header.schema_cookie.to_string()),
+            ("schema format:", header.schema_format.to_string()),
+            (
+                "default cache size:",
+                header.default_page_cache_size.to_string(),
+            ),
+            (
+                "autovacuum top root:",
+                header.vacuum_mode_largest_root_page.to_string(),
+            ),
+            (
+                "incremental vacuum:",
+                header.incremental_vacuum_enabled.to_string(),
+            ),
+            (
+                "text encoding:",
+                format!("{}{}", header.text_encoding, encoding),
+            ),
+            ("user version:", header.user_version.to_string()),
+            ("application id:", header.application_id.to_string()),
+            ("software version:", header.version_number.to_string()),
+            ("number of tables:", tables.to_string()),
+            ("number of indexes:", indexes.to_string()),
+            ("number of triggers:", triggers.to_string()),
+            ("number of views:", views.to_string()),
+            ("schema size:", schema_size.to_string()),
+        ];
+        for (name, value) in fields {
+            self.write_fmt(format_args!("{:<20} {}", name, value))?;
+        }
+        Ok(())
+    }
+
+    /// Runs a query that returns a single integer and returns that value.
+    fn query_scalar(&mut self, sql: &str) -> anyhow::Result<i64> {
+        let mut value = 0;
+        if let Some(mut rows) = self.conn.query(sql)? {
+            loop {
+                match rows.step()? {
+                    StepResult::Row => {
+                        let row = rows.row().unwrap();
+                        if let Ok(Value::Integer(v)) = row.get::<&Value>(0) {
+                            value = *v;
+                        }
+                    }
+                    StepResult::IO => {
+                        self.io.run_once()?;
+                    }
+                    StepResult::Interrupt | StepResult::Done => break,
+                    StepResult::Busy => return Err(anyhow!("database is busy")),
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Evaluates an SQL expression
+    fn evaluate(&mut self, expr: &str) -> anyhow::Result<Value> {
+        let mut value = Value::Null;
+        if let Some(mut rows) = self.conn.query(format!("SELECT {}", expr))? {
+            loop {
+                match rows.step()? {
+                    StepResult::Row => {
+                        let row = rows.row().unwrap();
+                        if let Ok(v) = row.get::<&Value>(0) {
+                            value = v.clone();
+                        }
+                    }
+                    StepResult::IO => {
+                        self.io.run_once()?;
+                    }
+                    StepResult::Interrupt | StepResult::Done => break,
+                    StepResult::Busy => return Err(anyhow!("database is busy")),
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Runs a query and returns every value of every row rendered as text, in order.
+    fn query_values(&mut self, sql: &str) -> anyhow::Result<Vec<String>> {
+        let mut values = Vec::new();
+        if let Some(mut rows) = self.conn.query(sql)? {
+            loop {
+                match rows.step()? {
+                    StepResult::Row => {
+                        let row = rows.row().unwrap();
+                        values.extend(row.get_values().map(|value| value.to_string()));
+                    }
+                    StepResult::IO => {
+                        self.io.run_once()?;
+                    }
+                    StepResult::Interrupt | StepResult::Done => break,
+                    StepResult::Busy => return Err(anyhow!("database is busy")),
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Runs the tests of the `selftest(tno, op, cmd, ans)` table like sqlite3 does: a `run` test
+    /// passes when the values returned by `cmd`, separated by spaces, equal `ans`, and a `memo`
+    /// prints `cmd`. Without that table, only `PRAGMA integrity_check` is run.
+    fn run_selftest(&mut self, args: SelftestArgs) -> anyhow::Result<()> {
+        if args.init {
+            self.init_selftest()?;
+        }
+        let has_table = self.query_scalar(
+            "SELECT count(*) FROM sqlite_schema WHERE type='table' AND name='selftest'",
+        )? > 0;
+        let tests = if has_table {
+            self.query_values("SELECT tno, op, cmd, ans FROM selftest ORDER BY tno")?
+                .chunks(4)
+                .map(|test| {
+                    (
+                        test[0].clone(),
+                        test[1].clone(),
+                        test[2].clone(),
+                        test[3].clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        } else {
+            vec![(
+                "1".to_string(),
+                "run".to_string(),
+                "PRAGMA integrity_check".to_string(),
+                "ok".to_string(),
+            )]
+        };
+
+        let (mut errors, mut count) = (0, 0);
+        for (tno, op, cmd, ans) in tests {
+            if args.verbose {
+                self.writeln(format!("{}: {} {}", tno, op, cmd))?;
+            }
+            match op.as_str() {
+                "memo" => self.writeln(&cmd)?,
+                "run" => {
+                    count += 1;
+                    let got = match self.query_values(&cmd) {
+                        Ok(values) => values.join(" "),
+                        Err(e) => format!("Error: {}", e),
+                    };
+                    if got != ans.trim() {
+                        errors += 1;
+                        self.writeln(format!("{}: Expected: [{}]", tno, ans))?;
+                        self.writeln(format!("{}:      Got: [{}]", tno, got))?;
+                    }
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "unknown operation \"{}\" on selftest line {}",
+                        op,
+                        tno
+                    ))
+                }
+            }
+        }
+        self.writeln(format!("{} errors out of {} tests", errors, count))?;
+        Ok(())
+    }
+
+    /// Creates the selftest table with an integrity check and the row count of every table,
+    /// as they are now.
+    fn init_selftest(&mut self) -> anyhow::Result<()> {
+        let tables = self.query_values(
+            "SELECT name FROM sqlite_schema WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name <> 'selftest' ORDER BY 1",
+        )?;
+        let mut tests = vec![("PRAGMA integrity_check".to_string(), "ok".to_string())];
+        for table in tables {
+            let cmd = format!("SELECT count(*) FROM \"{}\"", table.replace('"', "\"\""));
+            let ans = self.query_values(&cmd)?.join(" ");
+            tests.push((cmd, ans));
+        }
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS selftest(tno INTEGER PRIMARY KEY, op TEXT, cmd TEXT, ans TEXT)",
+        )?;
+        self.conn.execute("DELETE FROM selftest")?;
+        for (i, (cmd, ans)) in tests.into_iter().enumerate() {
+            self.conn.execute(format!(
+                "INSERT INTO selftest VALUES ({}, 'run', '{}', '{}')",
+                i + 1,
+                cmd.replace('\'', "''"),
+                ans.replace('\'', "''")
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Hashes the logical content of the database: the schema entries ordered by name, then the
+    /// rows of every table ordered by primary key and then by every column, so that neither
+    /// VACUUM nor reinserting the rows changes the digest. Like sqlite3's `sha3_query()`, the
+    /// text of each query is hashed before its values, which are hashed with their storage
+    /// class: `N`, `I` or `F` followed by 8 big-endian bytes, and `T` or `B` followed by the
+    /// length, a colon and the bytes.
+    fn sha3sum(&mut self, args: Sha3sumArgs) -> anyhow::Result<String> {
+        let like = args
+            .pattern
+            .as_ref()
+            .map(|pattern| format!(" AND tbl_name LIKE '{}'", pattern.replace('\'', "''")))
+            .unwrap_or_default();
+        let mut queries = vec![format!(
+            "SELECT type, name, tbl_name, sql FROM sqlite_schema WHERE name NOT LIKE 'sqlite_%'{} ORDER BY name",
+            like
+        )];
+        let tables = self.query_values(&format!(
+            "SELECT name FROM sqlite_schema WHERE type='table' AND name NOT LIKE 'sqlite_%'{} ORDER BY name",
+            like
+        ))?;
+        for table in tables {
+            let info =
+                self.query_values(&format!("PRAGMA table_info({})", quote_identifier(&table)))?;
+            // Rows of `cid, name, type, notnull, dflt_value, pk`
+            let columns = info
+                .chunks(6)
+                .map(|column| (quote_identifier(&column[1]), column[5] == "1"))
+                .collect::<Vec<_>>();
+            let order_by = columns
+                .iter()
+                .filter(|(_, pk)| *pk)
+                .chain(columns.iter().filter(|(_, pk)| !*pk))
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let names = columns
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            queries.push(format!(
+                "SELECT {} FROM {} ORDER BY {}",
+                names,
+                quote_identifier(&table),
+                order_by
+            ));
+        }
+
+        let mut hasher = Sha3::new(args.bits());
+        for query in queries {
+            hasher.update(format!("S{}:", query.len()).as_bytes());
+            hasher.update(query.as_bytes());
+            query_internal!(
+                self,
+                query.as_str(),
+                |row: &turso_core::Row| -> Result<(), LimboError> {
+                    for value in row.get_values() {
+                        match value {
+                            Value::Null => hasher.update(b"N"),
+                            Value::Integer(i) => {
+                                hasher.update(b"I");
+                                hasher.update(&i.to_be_bytes());
+                            }
+                            Value::Float(f) => {
+                                hasher.update(b"F");
+                                hasher.update(&f.to_bits().to_be_bytes());
+                            }
+                            Value::Text(text) => {
+                                let text = text.as_str().as_bytes();
+                                hasher.update(format!("T{}:", text.len()).as_bytes());
+                                hasher.update(text);
+                            }
+                            Value::Blob(blob) => {
+                                hasher.update(format!("B{}:", blob.len()).as_bytes());
+                                hasher.update(blob);
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+            )?;
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    }
+
     fn display_tables(&mut self, pattern: Option<&str>) -> anyhow::Result<()> {
+        // Like sqlite3, the pattern is matched with LIKE, e.g. `.tables user%`
         let sql = match pattern {
             Some(pattern) => format!(
-                "SELECT name FROM sqlite_schema WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name LIKE '{}' ORDER BY 1",
-                pattern
+                "SELECT name FROM sqlite_schema WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' AND name LIKE '{}' ORDER BY 1",
+                pattern.replace('\'', "''")
             ),
             None => String::from(
-                "SELECT name FROM sqlite_schema WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY 1"
+                "SELECT name FROM sqlite_schema WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY 1"
             ),
         };
 
         match self.conn.query(&sql) {
             Ok(Some(ref mut rows)) => {
-                let mut tables = String::new();
+                let mut tables = Vec::new();
                 loop {
                     match rows.step()? {
                         StepResult::Row => {
                             let row = rows.row().unwrap();
                             if let Ok(Value::Text(table)) = row.get::<&Value>(0) {
-                                tables.push_str(table.as_str());
-                                tables.push(' ');
+                                tables.push(table.as_str().to_string());
                             }
                         }
                         StepResult::IO => {
@@ -1032,7 +2165,7 @@ impl Limbo {
                 }
 
                 if !tables.is_empty() {
-                    let _ = self.writeln(tables.trim_end());
+                    let _ = self.write_fmt(format_args!("{}", tables_in_columns(&tables)));
                 } else if let Some(pattern) = pattern {
                     let _ = self.write_fmt(format_args!(
                         "Error: Tables with pattern '{}' not found.",
@@ -1100,3 +2233,130 @@ impl Drop for Limbo {
         self.save_history()
     }
 }
+
+/// Prints an error to stderr as a single-line JSON object, for tools driving the shell.
+/// Constraint violations also carry the kind of the constraint that failed.
+fn print_json_error(
+    code: &str,
+    constraint: Option<&str>,
+    message: &dyn std::fmt::Display,
+    sql: &str,
+) {
+    let mut error = serde_json::json!({
+        "code": code,
+        "message": message.to_string(),
+        "sql": sql.trim(),
+    });
+    if let Some(constraint) = constraint {
+        error["constraint"] = constraint.into();
+    }
+    eprintln!("{}", error);
+}
+
+/// Truncates `text` so that it takes at most `width` columns on a terminal, measuring wide
+/// characters such as CJK and emoji as two columns. Truncated text ends with `…`.
+fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 1; // the ellipsis
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if used + grapheme_width > width {
+            break;
+        }
+        used += grapheme_width;
+        truncated.push_str(grapheme);
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Renders a value as a SQL literal that reads back as the same value
+fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) if f.is_nan() => "NULL".to_string(),
+        Value::Float(f) if f.is_infinite() => {
+            let sign = if f.is_sign_negative() { "-" } else { "" };
+            format!("{}1e999", sign)
+        }
+        Value::Float(f) => format!("{:?}", f),
+        Value::Text(text) => format!("'{}'", text.as_str().replace('\'', "''")),
+        Value::Blob(blob) => format!(
+            "X'{}'",
+            blob.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        ),
+    }
+}
+
+/// Escapes the control characters of `text` as `\n`, `\t`, `\r` or `\xNN`, and backslashes
+/// as `\\` so that the escapes can be told apart from the text
+fn escape_non_printable(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Lays out the names of `.tables` like sqlite3: in as many columns as fit in 80 characters,
+/// filled top to bottom, each name padded to the width of the longest one
+fn tables_in_columns(names: &[String]) -> String {
+    let width = names.iter().map(|name| name.width()).max().unwrap_or(0);
+    let columns = (80 / (width + 2)).max(1);
+    let rows = names.len().div_ceil(columns);
+    let mut out = String::new();
+    for row in 0..rows {
+        for (i, name) in names.iter().enumerate().skip(row).step_by(rows) {
+            if i >= rows {
+                out.push_str("  ");
+            }
+            out.push_str(name);
+            out.push_str(&" ".repeat(width - name.width()));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Escapes the characters of `text` that have a meaning in HTML
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats a field for csv output following RFC 4180: fields holding the separator, a quote or
+/// a line break are enclosed in quotes, with the quotes inside them doubled.
+fn csv_quote(field: &str, quote_all: bool) -> String {
+    if quote_all || field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Args, ValueEnum};
+use clap::{Args, Subcommand, ValueEnum};
 use clap_complete::{ArgValueCompleter, CompletionCandidate, PathCompleter};
 
 use crate::{input::OutputMode, opcodes_dictionary::OPCODE_DESCRIPTIONS};
@@ -34,6 +34,13 @@ pub struct SchemaArgs {
     pub table_name: Option<String>,
 }
 
+#[derive(Debug, Clone, Args)]
+pub struct ReadArgs {
+    /// File with the SQL statements and dot-commands to run
+    #[arg(add = ArgValueCompleter::new(PathCompleter::file()))]
+    pub path: String,
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct SetOutputArgs {
     /// File path to send output to
@@ -45,6 +52,12 @@ pub struct SetOutputArgs {
 pub struct OutputModeArgs {
     #[arg(value_enum)]
     pub mode: OutputMode,
+    /// Quote every field in csv mode
+    #[arg(long, default_value = "false")]
+    pub quote_all: bool,
+    /// End rows with \r\n instead of \n in csv mode
+    #[arg(long, default_value = "false")]
+    pub crlf: bool,
 }
 
 fn opcodes_completer(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
@@ -79,9 +92,16 @@ pub struct OpcodesArgs {
 
 #[derive(Debug, Clone, Args)]
 pub struct CwdArgs {
-    /// Target directory
+    /// Target directory, the current directory is printed when omitted
     #[arg(add = ArgValueCompleter::new(PathCompleter::dir()))]
-    pub directory: String,
+    pub directory: Option<String>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SystemArgs {
+    /// Command to run in the system shell, with its arguments
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
 }
 
 #[derive(Debug, Clone, Args)]
@@ -103,6 +123,7 @@ pub enum EchoMode {
 
 #[derive(Debug, Clone, Args)]
 pub struct TablesArgs {
+    /// Only list the tables whose name matches this LIKE pattern, e.g. user%
     pub pattern: Option<String>,
 }
 
@@ -124,3 +145,241 @@ pub struct TimerArgs {
     #[arg(value_enum)]
     pub mode: TimerMode,
 }
+
+#[derive(Debug, Clone, Args)]
+pub struct ProgressArgs {
+    /// Number of VM instructions between dots, 0 to stop printing them
+    pub steps: u64,
+}
+
+#[derive(Debug, ValueEnum, Clone)]
+pub enum StatsMode {
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct StatsArgs {
+    /// Print statistics after every statement ('on') or stop doing so ('off')
+    #[arg(value_enum)]
+    pub mode: Option<StatsMode>,
+}
+
+#[derive(Debug, ValueEnum, Clone)]
+pub enum TypesMode {
+    On,
+    Off,
+}
+
+#[derive(Debug, ValueEnum, Clone)]
+pub enum HeadersMode {
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct HeadersArgs {
+    /// Print the names of the columns before the rows ('on') or stop doing so ('off')
+    #[arg(value_enum)]
+    pub mode: HeadersMode,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct HexDumpArgs {
+    /// Page number, starting at 1
+    pub page: usize,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SeparatorArgs {
+    /// Separator between the fields of a row, e.g. "\t"
+    #[arg(value_parser = parse_separator)]
+    pub column: String,
+    /// Separator written after every row, e.g. "\n"
+    #[arg(value_parser = parse_separator)]
+    pub row: Option<String>,
+}
+
+/// Parses a separator given to `.separator`, which may be quoted and contain the backslash
+/// escapes \t, \n, \r, \\, \" and \', octal escapes such as \037, and hex escapes such as \x1F
+fn parse_separator(arg: &str) -> Result<String, String> {
+    let arg = ['"', '\'']
+        .iter()
+        .find_map(|&q| arg.strip_prefix(q).and_then(|a| a.strip_suffix(q)))
+        .unwrap_or(arg);
+    let mut separator = String::new();
+    let mut chars = arg.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            separator.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some(c @ ('\\' | '"' | '\'')) => c,
+            Some('x') => {
+                let mut digits = String::new();
+                while digits.len() < 2 && chars.peek().is_some_and(char::is_ascii_hexdigit) {
+                    digits.push(chars.next().unwrap());
+                }
+                u8::from_str_radix(&digits, 16)
+                    .map_err(|_| format!("invalid escape in separator: {}", arg))?
+                    as char
+            }
+            Some(c @ '0'..='7') => {
+                let mut digits = c.to_string();
+                while digits.len() < 3 && chars.peek().is_some_and(|c| ('0'..='7').contains(c)) {
+                    digits.push(chars.next().unwrap());
+                }
+                u8::from_str_radix(&digits, 8)
+                    .map_err(|_| format!("invalid escape in separator: {}", arg))?
+                    as char
+            }
+            _ => return Err(format!("invalid escape in separator: {}", arg)),
+        };
+        separator.push(escaped);
+    }
+    if separator.is_empty() {
+        return Err("separator cannot be empty".to_string());
+    }
+    Ok(separator)
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct WidthArgs {
+    /// Maximum display width of each column, in order; 0 leaves a column unlimited
+    pub widths: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct SelftestArgs {
+    /// Create the selftest table with checks of the current contents of the database
+    #[arg(long, default_value = "false")]
+    pub init: bool,
+    /// Print every test before running it
+    #[arg(short, long, default_value = "false")]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct Sha3sumArgs {
+    /// Compute a 224-bit digest
+    #[arg(long = "sha3-224", group = "size")]
+    pub sha3_224: bool,
+    /// Compute a 256-bit digest (default)
+    #[arg(long = "sha3-256", group = "size")]
+    pub sha3_256: bool,
+    /// Compute a 384-bit digest
+    #[arg(long = "sha3-384", group = "size")]
+    pub sha3_384: bool,
+    /// Compute a 512-bit digest
+    #[arg(long = "sha3-512", group = "size")]
+    pub sha3_512: bool,
+    /// Only hash the tables whose name matches this LIKE pattern
+    pub pattern: Option<String>,
+}
+
+impl Sha3sumArgs {
+    pub fn bits(&self) -> usize {
+        if self.sha3_224 {
+            224
+        } else if self.sha3_384 {
+            384
+        } else if self.sha3_512 {
+            512
+        } else {
+            256
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TypesArgs {
+    /// Annotate every output value with its storage class ('on') or stop doing so ('off')
+    #[arg(value_enum)]
+    pub mode: TypesMode,
+}
+
+#[derive(Debug, ValueEnum, Clone)]
+pub enum BinaryMode {
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct BinaryArgs {
+    /// Print BLOBs as hex literals and escape the non-printable characters of text ('on') or
+    /// print them as they are ('off')
+    #[arg(value_enum)]
+    pub mode: BinaryMode,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct TraceArgs {
+    /// File to append executed statements to, or 'off' to stop tracing
+    #[arg(add = ArgValueCompleter::new(PathCompleter::file()))]
+    pub file: String,
+    /// Log each statement with its parameters replaced by the literals of their bound values,
+    /// instead of as written
+    #[arg(long)]
+    pub expanded: bool,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ParameterArgs {
+    #[command(subcommand)]
+    pub command: ParameterCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ParameterCommand {
+    /// Set NAME to the value of the SQL expression VALUE, or to VALUE as text if it isn't one
+    Set {
+        /// Name of the parameter as written in statements, e.g. :name, $name, @name or ?1
+        name: String,
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+    /// Remove the value of NAME
+    Unset { name: String },
+    /// List the parameters and their values
+    List,
+    /// Remove the values of all parameters
+    Clear,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ConnectionArgs {
+    /// Index of the connection to switch to, or 'new' to open another connection
+    pub target: Option<ConnectionTarget>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConnectionTarget {
+    New,
+    Index(usize),
+}
+
+impl std::str::FromStr for ConnectionTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("new") {
+            return Ok(ConnectionTarget::New);
+        }
+        s.parse()
+            .map(ConnectionTarget::Index)
+            .map_err(|_| format!("expected a connection index or 'new', got '{}'", s))
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LimitArgs {
+    /// Name of the limit (e.g. LENGTH, SQL_LENGTH, COLUMN)
+    pub name: Option<String>,
+    /// New value for the limit
+    #[arg(allow_negative_numbers = true)]
+    pub value: Option<i32>,
+}
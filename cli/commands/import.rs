@@ -1,21 +1,40 @@
 use clap::Args;
 use clap_complete::{ArgValueCompleter, PathCompleter};
-use std::{fs::File, io::Write, path::PathBuf, sync::Arc};
-use turso_core::Connection;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    num::NonZero,
+    path::PathBuf,
+    sync::Arc,
+};
+use turso_core::{Connection, Statement, StepResult, Value};
 
 #[derive(Debug, Clone, Args)]
 pub struct ImportArgs {
     /// Use , and \n as column and row separators
     #[arg(long, default_value = "true")]
     csv: bool,
+    /// Use \x1F and \x1E as column and row separators, as written by ascii mode
+    #[arg(long, default_value = "false")]
+    pub ascii: bool,
+    /// Read one JSON object per line, mapping its keys to the columns of the table
+    #[arg(long, default_value = "false")]
+    ndjson: bool,
+    /// Skip JSON keys that do not match a column of the table instead of failing
+    #[arg(long, default_value = "false")]
+    ignore_extra: bool,
     /// "Verbose" - increase auxiliary output
     #[arg(short, default_value = "false")]
     verbose: bool,
     /// Skip the first N rows of input
     #[arg(long, default_value = "0")]
     skip: u64,
+    /// Read the column names from the first row, and create the table from them and the types
+    /// of a sample of the rows if it does not exist
+    #[arg(long, default_value = "false")]
+    schema_infer: bool,
     #[arg(add = ArgValueCompleter::new(PathCompleter::file()))]
-    file: PathBuf,
+    pub file: PathBuf,
     table: String,
 }
 
@@ -35,7 +54,363 @@ impl<'a> ImportFile<'a> {
     }
 
     pub fn import(&mut self, args: ImportArgs) {
-        self.import_csv(args);
+        if args.ndjson {
+            self.import_ndjson(args);
+        } else if args.schema_infer {
+            self.import_csv_schema_infer(args);
+        } else {
+            self.import_csv(args);
+        }
+    }
+
+    /// Loads a file with one JSON object per line in a single transaction, so that a bad line
+    /// leaves the table untouched. Missing keys are inserted as NULL, and the values are
+    /// converted following the affinity of their column.
+    pub fn import_ndjson(&mut self, args: ImportArgs) {
+        let file = match File::open(&args.file) {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = self.writer.write_all(format!("{:?}\n", e).as_bytes());
+                return;
+            }
+        };
+        let columns = match self.table_columns(&args.table) {
+            Ok(columns) if !columns.is_empty() => columns,
+            Ok(_) => {
+                let _ = self
+                    .writer
+                    .write_all(format!("Error: no such table: {}\n", args.table).as_bytes());
+                return;
+            }
+            Err(e) => {
+                let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+                return;
+            }
+        };
+
+        if let Err(e) = self.conn.execute("BEGIN") {
+            let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+            return;
+        }
+        match self.load_ndjson(file, &args, &columns) {
+            Ok(rows) => {
+                if let Err(e) = self.conn.execute("COMMIT") {
+                    let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+                    return;
+                }
+                if args.verbose {
+                    let _ = self
+                        .writer
+                        .write_all(format!("Added {} rows\n", rows).as_bytes());
+                }
+            }
+            Err(e) => {
+                let _ = self.conn.execute("ROLLBACK");
+                let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+            }
+        }
+    }
+
+    /// Loads a file written by ascii mode in a single transaction. Fields are separated by
+    /// `column_separator` and rows end with `row_separator`; nothing is quoted, so the fields
+    /// are taken as they are and converted following the affinity of their column.
+    pub fn import_ascii(&mut self, args: ImportArgs, column_separator: &str, row_separator: &str) {
+        let contents = match std::fs::read_to_string(&args.file) {
+            Ok(contents) => contents,
+            Err(e) => {
+                let _ = self.writer.write_all(format!("{:?}\n", e).as_bytes());
+                return;
+            }
+        };
+        let columns = match self.table_columns(&args.table) {
+            Ok(columns) if !columns.is_empty() => columns,
+            Ok(_) => {
+                let _ = self
+                    .writer
+                    .write_all(format!("Error: no such table: {}\n", args.table).as_bytes());
+                return;
+            }
+            Err(e) => {
+                let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+                return;
+            }
+        };
+
+        if let Err(e) = self.conn.execute("BEGIN") {
+            let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+            return;
+        }
+        match self.load_ascii(&contents, &args, &columns, column_separator, row_separator) {
+            Ok(rows) => {
+                if let Err(e) = self.conn.execute("COMMIT") {
+                    let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+                    return;
+                }
+                if args.verbose {
+                    let _ = self
+                        .writer
+                        .write_all(format!("Added {} rows\n", rows).as_bytes());
+                }
+            }
+            Err(e) => {
+                let _ = self.conn.execute("ROLLBACK");
+                let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+            }
+        }
+    }
+
+    fn load_ascii(
+        &mut self,
+        contents: &str,
+        args: &ImportArgs,
+        columns: &[(String, Affinity)],
+        column_separator: &str,
+        row_separator: &str,
+    ) -> Result<u64, String> {
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = self
+            .conn
+            .prepare(format!(
+                "INSERT INTO {} VALUES ({})",
+                args.table, placeholders
+            ))
+            .map_err(|e| e.to_string())?;
+
+        // The last row ends with the row separator too
+        let contents = contents.strip_suffix(row_separator).unwrap_or(contents);
+        if contents.is_empty() {
+            return Ok(0);
+        }
+        let mut rows = 0u64;
+        let records = contents.split(row_separator).enumerate();
+        for (row_number, record) in records.skip(args.skip as usize) {
+            let row_number = row_number + 1;
+            let fields = record.split(column_separator).collect::<Vec<_>>();
+            if fields.len() != columns.len() {
+                return Err(format!(
+                    "row {}: expected {} values, got {}",
+                    row_number,
+                    columns.len(),
+                    fields.len()
+                ));
+            }
+            for (i, ((_, affinity), field)) in columns.iter().zip(fields).enumerate() {
+                let value = affinity.apply(Value::build_text(field.to_string()));
+                insert.bind_at(NonZero::new(i + 1).unwrap(), value);
+            }
+            self.run_to_completion(&mut insert)
+                .map_err(|e| format!("row {}: {}", row_number, e))?;
+            insert.reset();
+            rows += 1;
+        }
+        Ok(rows)
+    }
+
+    fn load_ndjson(
+        &mut self,
+        file: File,
+        args: &ImportArgs,
+        columns: &[(String, Affinity)],
+    ) -> Result<u64, String> {
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = self
+            .conn
+            .prepare(format!(
+                "INSERT INTO {} VALUES ({})",
+                args.table, placeholders
+            ))
+            .map_err(|e| e.to_string())?;
+
+        let mut rows = 0u64;
+        let lines = BufReader::new(file).lines().enumerate();
+        for (line_number, line) in lines.skip(args.skip as usize) {
+            let line_number = line_number + 1;
+            let line = line.map_err(|e| format!("line {}: {}", line_number, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let object = match serde_json::from_str::<serde_json::Value>(&line) {
+                Ok(serde_json::Value::Object(object)) => object,
+                Ok(_) => return Err(format!("line {}: expected a JSON object", line_number)),
+                Err(e) => return Err(format!("line {}: malformed JSON: {}", line_number, e)),
+            };
+            if !args.ignore_extra {
+                if let Some(key) = object
+                    .keys()
+                    .find(|key| !columns.iter().any(|(c, _)| c.eq_ignore_ascii_case(key)))
+                {
+                    return Err(format!(
+                        "line {}: table {} has no column named {}",
+                        line_number, args.table, key
+                    ));
+                }
+            }
+            for (i, (column, affinity)) in columns.iter().enumerate() {
+                let value = object
+                    .iter()
+                    .find(|(key, _)| column.eq_ignore_ascii_case(key))
+                    .map_or(Value::Null, |(_, value)| {
+                        affinity.apply(json_to_value(value))
+                    });
+                insert.bind_at(NonZero::new(i + 1).unwrap(), value);
+            }
+            self.run_to_completion(&mut insert)
+                .map_err(|e| format!("line {}: {}", line_number, e))?;
+            insert.reset();
+            rows += 1;
+        }
+        Ok(rows)
+    }
+
+    /// Returns the name and the affinity of each column of `table`
+    fn table_columns(&mut self, table: &str) -> Result<Vec<(String, Affinity)>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(format!("PRAGMA table_info({})", table))
+            .map_err(|e| e.to_string())?;
+        let mut columns = Vec::new();
+        loop {
+            match stmt.step().map_err(|e| e.to_string())? {
+                StepResult::Row => {
+                    let row = stmt.row().unwrap();
+                    let name = row.get_value(1).to_string();
+                    let affinity = Affinity::from_type(&row.get_value(2).to_string());
+                    columns.push((name, affinity));
+                }
+                StepResult::IO => self.io.run_once().map_err(|e| e.to_string())?,
+                StepResult::Done => return Ok(columns),
+                StepResult::Interrupt => return Err("interrupted".to_string()),
+                StepResult::Busy => return Err("database is busy".to_string()),
+            }
+        }
+    }
+
+    fn run_to_completion(&mut self, stmt: &mut Statement) -> Result<(), String> {
+        loop {
+            match stmt.step().map_err(|e| e.to_string())? {
+                StepResult::Row => {}
+                StepResult::IO => self.io.run_once().map_err(|e| e.to_string())?,
+                StepResult::Done => return Ok(()),
+                StepResult::Interrupt => return Err("interrupted".to_string()),
+                StepResult::Busy => return Err("database is busy".to_string()),
+            }
+        }
+    }
+
+    /// Loads a CSV file whose first row holds the column names in a single transaction. When the
+    /// table does not exist, it is created first with a type guessed for each column from the
+    /// rows of `SCHEMA_INFER_SAMPLE_ROWS`, and its schema is reported.
+    pub fn import_csv_schema_infer(&mut self, args: ImportArgs) {
+        let file = match File::open(&args.file) {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = self.writer.write_all(format!("{:?}\n", e).as_bytes());
+                return;
+            }
+        };
+        let columns = match self.table_columns(&args.table) {
+            Ok(columns) => columns,
+            Err(e) => {
+                let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+                return;
+            }
+        };
+
+        if let Err(e) = self.conn.execute("BEGIN") {
+            let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+            return;
+        }
+        match self.load_csv_schema_infer(file, &args, columns) {
+            Ok(rows) => {
+                if let Err(e) = self.conn.execute("COMMIT") {
+                    let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+                    return;
+                }
+                if args.verbose {
+                    let _ = self
+                        .writer
+                        .write_all(format!("Added {} rows\n", rows).as_bytes());
+                }
+            }
+            Err(e) => {
+                let _ = self.conn.execute("ROLLBACK");
+                let _ = self.writer.write_all(format!("Error: {}\n", e).as_bytes());
+            }
+        }
+    }
+
+    fn load_csv_schema_infer(
+        &mut self,
+        file: File,
+        args: &ImportArgs,
+        mut columns: Vec<(String, Affinity)>,
+    ) -> Result<u64, String> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(file);
+        let mut records = rdr.records().skip(args.skip as usize);
+        let header = match records.next() {
+            Some(header) => header.map_err(|e| e.to_string())?,
+            None => return Err(format!("{} is empty", args.file.display())),
+        };
+        let sample = records
+            .by_ref()
+            .take(SCHEMA_INFER_SAMPLE_ROWS)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        if columns.is_empty() {
+            let definitions = header
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    let name = if name.is_empty() {
+                        format!("c{}", i + 1)
+                    } else {
+                        name.to_string()
+                    };
+                    let column_type = infer_column_type(sample.iter().filter_map(|r| r.get(i)));
+                    columns.push((name.clone(), Affinity::from_type(column_type)));
+                    format!("\"{}\" {}", name.replace('"', "\"\""), column_type)
+                })
+                .collect::<Vec<_>>();
+            let create = format!("CREATE TABLE {} ({})", args.table, definitions.join(", "));
+            self.conn.execute(&create).map_err(|e| e.to_string())?;
+            let _ = self.writer.write_all(format!("{};\n", create).as_bytes());
+        }
+
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = self
+            .conn
+            .prepare(format!(
+                "INSERT INTO {} VALUES ({})",
+                args.table, placeholders
+            ))
+            .map_err(|e| e.to_string())?;
+
+        let mut rows = 0u64;
+        for record in sample.into_iter().map(Ok).chain(records) {
+            let record = record.map_err(|e| e.to_string())?;
+            let line_number = record.position().map_or(0, |p| p.line());
+            if record.len() != columns.len() {
+                return Err(format!(
+                    "line {}: expected {} values, got {}",
+                    line_number,
+                    columns.len(),
+                    record.len()
+                ));
+            }
+            for (i, ((_, affinity), field)) in columns.iter().zip(record.iter()).enumerate() {
+                let value = affinity.apply(Value::build_text(field.to_string()));
+                insert.bind_at(NonZero::new(i + 1).unwrap(), value);
+            }
+            self.run_to_completion(&mut insert)
+                .map_err(|e| format!("line {}: {}", line_number, e))?;
+            insert.reset();
+            rows += 1;
+        }
+        Ok(rows)
     }
 
     pub fn import_csv(&mut self, args: ImportArgs) {
@@ -114,3 +489,103 @@ impl<'a> ImportFile<'a> {
         }
     }
 }
+
+/// Number of rows after the header that `--schema-infer` guesses the column types from
+const SCHEMA_INFER_SAMPLE_ROWS: usize = 100;
+
+/// Guesses the type of a column from some of its values: INTEGER if they are all integers, REAL
+/// if they are all numbers, and TEXT otherwise. Empty values are ignored, and a column without
+/// any other value is TEXT.
+fn infer_column_type<'a>(values: impl Iterator<Item = &'a str>) -> &'static str {
+    let mut column_type = "TEXT";
+    for value in values.map(str::trim).filter(|v| !v.is_empty()) {
+        if value.parse::<i64>().is_ok() {
+            if column_type == "TEXT" {
+                column_type = "INTEGER";
+            }
+        } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+            column_type = "REAL";
+        } else {
+            return "TEXT";
+        }
+    }
+    column_type
+}
+
+/// Converts a JSON value to the value inserted for it. Nested objects and arrays are kept as
+/// JSON text, and booleans become 1 or 0 like in SQLite.
+fn json_to_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::build_text(s.clone()),
+        value => Value::build_text(value.to_string()),
+    }
+}
+
+/// Type affinity of a column, which decides how imported values are converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Determines the affinity of a declared column type following the rules of SQLite
+    fn from_type(declared: &str) -> Self {
+        let declared = declared.to_uppercase();
+        if declared.contains("INT") {
+            Self::Integer
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|t| declared.contains(t))
+        {
+            Self::Text
+        } else if declared.is_empty() || declared.contains("BLOB") {
+            Self::Blob
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|t| declared.contains(t))
+        {
+            Self::Real
+        } else {
+            Self::Numeric
+        }
+    }
+
+    fn apply(self, value: Value) -> Value {
+        match (self, value) {
+            (Self::Text, Value::Integer(i)) => Value::build_text(i.to_string()),
+            (Self::Text, Value::Float(f)) => Value::build_text(f.to_string()),
+            (Self::Integer | Self::Numeric, Value::Text(t)) => {
+                let text = t.as_str().trim();
+                if let Ok(i) = text.parse::<i64>() {
+                    Value::Integer(i)
+                } else if let Ok(f) = text.parse::<f64>() {
+                    Self::Numeric.apply(Value::Float(f))
+                } else {
+                    Value::Text(t)
+                }
+            }
+            // A real without a fractional part is stored as an integer
+            (Self::Integer | Self::Numeric, Value::Float(f))
+                if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 =>
+            {
+                Value::Integer(f as i64)
+            }
+            (Self::Real, Value::Integer(i)) => Value::Float(i as f64),
+            (Self::Real, Value::Text(t)) => match t.as_str().trim().parse::<f64>() {
+                Ok(f) => Value::Float(f),
+                Err(_) => Value::Text(t),
+            },
+            (_, value) => value,
+        }
+    }
+}
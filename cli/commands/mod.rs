@@ -2,8 +2,10 @@ pub mod args;
 pub mod import;
 
 use args::{
-    CwdArgs, EchoArgs, ExitArgs, IndexesArgs, LoadExtensionArgs, NullValueArgs, OpcodesArgs,
-    OpenArgs, OutputModeArgs, SchemaArgs, SetOutputArgs, TablesArgs, TimerArgs,
+    BinaryArgs, ConnectionArgs, CwdArgs, EchoArgs, ExitArgs, HeadersArgs, HexDumpArgs, IndexesArgs,
+    LimitArgs, LoadExtensionArgs, NullValueArgs, OpcodesArgs, OpenArgs, OutputModeArgs,
+    ParameterArgs, ProgressArgs, ReadArgs, SchemaArgs, SelftestArgs, SeparatorArgs, SetOutputArgs,
+    Sha3sumArgs, StatsArgs, SystemArgs, TablesArgs, TimerArgs, TraceArgs, TypesArgs, WidthArgs,
 };
 use clap::Parser;
 use import::ImportArgs;
@@ -50,6 +52,15 @@ pub enum Command {
     /// Change the current working directory
     #[command(name = "cd", display_name = ".cd")]
     Cwd(CwdArgs),
+    /// Show status information about the database
+    #[command(name = "dbinfo", display_name = ".dbinfo")]
+    DbInfo,
+    /// Run a command in the system shell and show its output
+    #[command(name = "system", display_name = ".system", alias = "shell")]
+    System(SystemArgs),
+    /// Display the raw bytes of a database page
+    #[command(name = "hexdump", display_name = ".hexdump")]
+    HexDump(HexDumpArgs),
     /// Display information about settings
     #[command(name = "show", display_name = ".show")]
     ShowInfo,
@@ -59,6 +70,10 @@ pub enum Command {
     /// Toggle 'echo' mode to repeat commands before execution
     #[command(display_name = ".echo")]
     Echo(EchoArgs),
+    /// Read input from FILE. Its statements are bound to the values set with '.parameter', which
+    /// it may set itself
+    #[command(name = "read", display_name = ".read")]
+    Read(ReadArgs),
     /// Display tables
     Tables(TablesArgs),
     /// Import data from FILE into TABLE
@@ -69,6 +84,9 @@ pub enum Command {
     LoadExtension(LoadExtensionArgs),
     /// Dump the current database as a list of SQL statements
     Dump,
+    /// Recover as much data as possible from a corrupted database as a list of SQL statements
+    #[command(name = "recover", display_name = ".recover")]
+    Recover,
     /// List vfs modules available
     #[command(name = "vfslist", display_name = ".vfslist")]
     ListVfs,
@@ -77,6 +95,46 @@ pub enum Command {
     ListIndexes(IndexesArgs),
     #[command(name = "timer", display_name = ".timer")]
     Timer(TimerArgs),
+    /// Print a dot every N VM instructions while a statement runs
+    #[command(name = "progress", display_name = ".progress")]
+    Progress(ProgressArgs),
+    /// Log every executed SQL statement to FILE ('off' to stop)
+    #[command(name = "trace", display_name = ".trace")]
+    Trace(TraceArgs),
+    /// Display or change the value of a run-time limit
+    #[command(name = "limit", display_name = ".limit", alias = "limits")]
+    Limit(LimitArgs),
+    /// Show page cache and I/O statistics, or toggle printing them after each statement
+    #[command(name = "stats", display_name = ".stats")]
+    Stats(StatsArgs),
+    /// Show the names of the columns in list, csv, html and quote modes
+    #[command(name = "headers", display_name = ".headers", alias = "header")]
+    Headers(HeadersArgs),
+    /// Show the storage class of every value in query results
+    #[command(name = "types", display_name = ".types")]
+    Types(TypesArgs),
+    /// Print BLOBs as hex and escape control characters in text, to keep the terminal safe
+    #[command(name = "binary", display_name = ".binary")]
+    Binary(BinaryArgs),
+    /// Set the maximum width of columns in pretty mode, truncating longer values
+    #[command(name = "width", display_name = ".width")]
+    Width(WidthArgs),
+    /// Change the separators between fields and after rows in list and ascii modes, which
+    /// '.import' also reads in ascii mode
+    #[command(name = "separator", display_name = ".separator")]
+    Separator(SeparatorArgs),
+    /// Run the tests of the selftest table, or an integrity check when there is none
+    #[command(name = "selftest", display_name = ".selftest")]
+    Selftest(SelftestArgs),
+    /// Print a SHA3 digest of the schema and rows, which does not depend on their layout on disk
+    #[command(name = "sha3sum", display_name = ".sha3sum", alias = "cksum")]
+    Sha3sum(Sha3sumArgs),
+    /// Manage the values bound to the parameters of statements, such as :name or ?1
+    #[command(name = "parameter", display_name = ".parameter", alias = "param")]
+    Parameter(ParameterArgs),
+    /// List the connections to the database, switch to one, or open another with 'new'
+    #[command(name = "connection", display_name = ".connection")]
+    Connection(ConnectionArgs),
 }
 
 const _HELP_TEMPLATE: &str = "{before-help}{name}
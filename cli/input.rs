@@ -3,6 +3,7 @@ use clap::ValueEnum;
 use std::{
     fmt::{Display, Formatter},
     io::{self, Write},
+    path::PathBuf,
     sync::Arc,
 };
 
@@ -61,7 +62,27 @@ impl Default for Io {
 #[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum OutputMode {
     List,
+    #[value(alias = "box", alias = "table")]
     Pretty,
+    Csv,
+    Html,
+    /// Values as SQL literals separated by commas
+    Quote,
+    /// Values as they are, with the ASCII unit separator (0x1F) between fields and the record
+    /// separator (0x1E) after each row
+    Ascii,
+}
+
+impl OutputMode {
+    /// Separators between fields and after rows that switching to this mode sets, for the
+    /// modes that write them
+    pub fn default_separators(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Self::List => Some(("|", "\n")),
+            Self::Ascii => Some(("\x1F", "\x1E")),
+            Self::Pretty | Self::Csv | Self::Html | Self::Quote => None,
+        }
+    }
 }
 
 impl std::fmt::Display for OutputMode {
@@ -83,6 +104,28 @@ pub struct Settings {
     pub io: Io,
     pub tracing_output: Option<String>,
     pub timer: bool,
+    /// Number of VM instructions between the dots printed while a statement runs, set by
+    /// `.progress`; 0 means no dots
+    pub progress: u64,
+    pub stats: bool,
+    pub types: bool,
+    /// Whether BLOBs are printed as hex literals and the non-printable characters of text are
+    /// escaped, set by `.binary`
+    pub binary: bool,
+    /// Whether list, csv, html and quote output start with the names of the columns, set by `.headers`
+    pub headers: bool,
+    /// Directory that relative paths given to dot-commands are resolved against, set by `.cd`
+    pub cwd: PathBuf,
+    /// Whether errors are printed to stderr as JSON objects
+    pub json_errors: bool,
+    /// Whether `.system` and `.shell` are disabled, set by `--no-shell`
+    pub no_shell: bool,
+    /// Whether csv mode quotes every field, not only the ones that need quoting
+    pub csv_quote_all: bool,
+    /// Whether csv mode ends rows with `\r\n` instead of `\n`
+    pub csv_crlf: bool,
+    /// Maximum display width of each column in pretty mode, set by `.width`; 0 means no limit
+    pub column_widths: Vec<usize>,
 }
 
 impl From<Opts> for Settings {
@@ -107,6 +150,17 @@ impl From<Opts> for Settings {
             },
             tracing_output: opts.tracing_output,
             timer: false,
+            progress: 0,
+            stats: false,
+            types: false,
+            binary: false,
+            headers: false,
+            cwd: std::env::current_dir().unwrap_or_default(),
+            json_errors: opts.json_errors,
+            no_shell: opts.no_shell,
+            csv_quote_all: false,
+            csv_crlf: false,
+            column_widths: Vec::new(),
         }
     }
 }
@@ -115,7 +169,7 @@ impl std::fmt::Display for Settings {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Settings:\nOutput mode: {}\nDB: {}\nOutput: {}\nNull value: {}\nCWD: {}\nEcho: {}",
+            "Settings:\nOutput mode: {}\nDB: {}\nOutput: {}\nNull value: {}\nCWD: {}\nEcho: {}\nHeaders: {}",
             self.output_mode,
             self.db_file,
             match self.is_stdout {
@@ -123,10 +177,14 @@ impl std::fmt::Display for Settings {
                 false => &self.output_filename,
             },
             self.null_value,
-            std::env::current_dir().unwrap().display(),
+            self.cwd.display(),
             match self.echo {
                 true => "on",
                 false => "off",
+            },
+            match self.headers {
+                true => "on",
+                false => "off",
             }
         )
     }
@@ -189,8 +247,9 @@ pub const AFTER_HELP_MSG: &str = r#"Usage Examples:
 3. To view the schema of a table named 'employees':
    .schema employees
 
-4. To list all tables:
+4. To list all tables, or the ones whose name starts with 'user':
    .tables
+   .tables user%
 
 5. To list all available SQL opcodes:
    .opcodes
@@ -221,6 +280,11 @@ pub const AFTER_HELP_MSG: &str = r#"Usage Examples:
 14. To show names of indexes:
    .indexes ?TABLE?
 
+15. To run a script whose statements use the parameter :id, which the script may
+    also set itself with a '.parameter set' line:
+   .parameter set :id 42
+   .read script.sql
+
 Note:
 - All SQL commands must end with a semicolon (;).
 - Special commands start with a dot (.) and are not required to end with a semicolon."#;
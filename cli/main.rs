@@ -5,6 +5,7 @@ mod config;
 mod helper;
 mod input;
 mod opcodes_dictionary;
+mod sha3;
 
 use config::CONFIG_DIR;
 use rustyline::{error::ReadlineError, Config, Editor};
@@ -50,9 +51,7 @@ fn main() -> anyhow::Result<()> {
         match readline {
             Ok(line) => match app.handle_input_line(line.trim()) {
                 Ok(_) => {}
-                Err(e) => {
-                    eprintln!("{}", e);
-                }
+                Err(e) => app.report_input_error(line.trim(), &e),
             },
             Err(ReadlineError::Interrupted) => {
                 // At prompt, increment interrupt count
@@ -0,0 +1,174 @@
+//! SHA3 hashing (FIPS 202) for `.sha3sum`, which like sqlite3's shell carries its own
+//! implementation instead of pulling a dependency for it.
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation of each lane visited by the pi step, in the order of [PI_LANES]
+const RHO_OFFSETS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const PI_LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        // theta
+        let columns: [u64; 5] = std::array::from_fn(|x| {
+            state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20]
+        });
+        for (i, lane) in state.iter_mut().enumerate() {
+            let x = i % 5;
+            *lane ^= columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+        }
+        // rho and pi
+        let mut last = state[1];
+        for (&lane, &offset) in PI_LANES.iter().zip(RHO_OFFSETS.iter()) {
+            let next = state[lane];
+            state[lane] = last.rotate_left(offset);
+            last = next;
+        }
+        // chi
+        for row in state.chunks_exact_mut(5) {
+            let lanes: [u64; 5] = [row[0], row[1], row[2], row[3], row[4]];
+            for (x, lane) in row.iter_mut().enumerate() {
+                *lane = lanes[x] ^ (!lanes[(x + 1) % 5] & lanes[(x + 2) % 5]);
+            }
+        }
+        // iota
+        state[0] ^= round_constant;
+    }
+}
+
+/// Incremental SHA3 hasher with a digest of 224, 256, 384 or 512 bits
+pub struct Sha3 {
+    state: [u64; 25],
+    /// Bytes absorbed per permutation
+    rate: usize,
+    /// Position of the next byte to absorb within the rate
+    position: usize,
+    digest_len: usize,
+}
+
+impl Sha3 {
+    pub fn new(bits: usize) -> Self {
+        assert!(
+            matches!(bits, 224 | 256 | 384 | 512),
+            "unsupported SHA3 size: {}",
+            bits
+        );
+        let digest_len = bits / 8;
+        Self {
+            state: [0; 25],
+            rate: 200 - 2 * digest_len,
+            position: 0,
+            digest_len,
+        }
+    }
+
+    fn absorb_byte(&mut self, byte: u8) {
+        self.state[self.position / 8] ^= (byte as u64) << (8 * (self.position % 8));
+        self.position += 1;
+        if self.position == self.rate {
+            keccak_f(&mut self.state);
+            self.position = 0;
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.absorb_byte(byte);
+        }
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        self.state[self.position / 8] ^= 0x06 << (8 * (self.position % 8));
+        self.state[(self.rate - 1) / 8] ^= 0x80 << (8 * ((self.rate - 1) % 8));
+        keccak_f(&mut self.state);
+        self.state
+            .iter()
+            .flat_map(|lane| lane.to_le_bytes())
+            .take(self.digest_len)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sha3;
+
+    fn hex_digest(bits: usize, data: &[u8]) -> String {
+        let mut hasher = Sha3::new(bits);
+        hasher.update(data);
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn test_sha3_known_digests() {
+        assert_eq!(
+            hex_digest(256, b""),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_eq!(
+            hex_digest(256, b"abc"),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            hex_digest(224, b"abc"),
+            "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"
+        );
+        assert_eq!(
+            hex_digest(512, b"abc"),
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+             10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+        );
+    }
+
+    #[test]
+    fn test_sha3_input_longer_than_rate() {
+        // A million 'a's crosses the rate many times, byte by byte and in one update
+        let data = vec![b'a'; 1_000_000];
+        let mut hasher = Sha3::new(256);
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        let expected = "5c8875ae474a3634ba4fd55ec85bffd661f32aca75c6d699d0cdcb6c115891c1";
+        let digest: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(digest, expected);
+        assert_eq!(hex_digest(256, &data), expected);
+    }
+}
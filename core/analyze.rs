@@ -0,0 +1,273 @@
+//! `ANALYZE` counts the rows of each table and the distinct keys of each of its indexes, and
+//! stores them in `sqlite_stat1` in the same format as SQLite, so that the planner can estimate
+//! how many rows a seek on an index returns.
+//!
+//! Each row of `sqlite_stat1` holds a table name, an index name and a list of integers: the
+//! number of rows of the table followed, for an index on N columns, by the average number of
+//! rows that share the values of the first 1, 2, ... N columns of the index. A table without
+//! indexes gets a single row whose index name is NULL, and an empty table gets no row at all.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::schema::{BTreeTable, Index, Schema, Table, TableStats};
+use crate::util::normalize_ident;
+use crate::vdbe::StepResult;
+use crate::{Connection, LimboError, OpenFlags, Result, TransactionState, Value};
+
+const STAT1_TABLE_NAME: &str = "sqlite_stat1";
+
+/// Analyzes `table`, or every table of the database if `None`, and makes the statistics
+/// available to the planner once the transaction commits. When `index` is provided, only the
+/// statistics of that index of `table` are refreshed.
+pub(crate) fn analyze(
+    conn: &Arc<Connection>,
+    table: Option<&str>,
+    index: Option<&str>,
+) -> Result<()> {
+    if conn.readonly.get() || conn._db.open_flags.contains(OpenFlags::ReadOnly) {
+        return Err(LimboError::ReadOnly);
+    }
+    let schema = conn.schema.borrow().clone();
+    let mut tables = schema
+        .tables
+        .values()
+        .filter_map(|t| match t.as_ref() {
+            Table::BTree(t) if t.has_rowid && !t.name.starts_with("sqlite_") => Some(t.clone()),
+            _ => None,
+        })
+        .filter(|t| {
+            table.map_or(true, |name| {
+                normalize_ident(&t.name) == normalize_ident(name)
+            })
+        })
+        .collect::<Vec<_>>();
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Statements run on the connection share a single transaction, which is committed here
+    // unless ANALYZE runs in a transaction started by the user
+    let auto_commit = conn.auto_commit.get();
+    if auto_commit {
+        conn.execute("BEGIN")?;
+    }
+    if let Err(err) = write_stats(conn, &schema, &tables, index) {
+        if auto_commit {
+            let _ = conn.execute("ROLLBACK");
+        }
+        conn.schema.borrow_mut().table_stats = schema.table_stats;
+        return Err(err);
+    }
+    // Committing a transaction that changes the schema publishes the statistics to the other
+    // connections, and rolling it back restores the previous ones
+    if let TransactionState::Write { .. } = conn.transaction_state.get() {
+        conn.transaction_state.set(TransactionState::Write {
+            change_schema: true,
+        });
+    }
+    if auto_commit {
+        conn.execute("COMMIT")?;
+    }
+    Ok(())
+}
+
+fn write_stats(
+    conn: &Arc<Connection>,
+    schema: &Schema,
+    tables: &[Rc<BTreeTable>],
+    index: Option<&str>,
+) -> Result<()> {
+    if schema.get_table(STAT1_TABLE_NAME).is_none() {
+        conn.execute(format!("CREATE TABLE {}(tbl,idx,stat)", STAT1_TABLE_NAME))?;
+    }
+    for table in tables {
+        let indexes = match index {
+            Some(index) => schema
+                .get_indices(&table.name)
+                .iter()
+                .filter(|i| normalize_ident(&i.name) == normalize_ident(index))
+                .cloned()
+                .collect::<Vec<_>>(),
+            None => schema.get_indices(&table.name).to_vec(),
+        };
+        match index {
+            Some(_) => {
+                for index in &indexes {
+                    conn.execute(format!(
+                        "DELETE FROM {} WHERE tbl = {} AND idx = {}",
+                        STAT1_TABLE_NAME,
+                        quote_string(&table.name),
+                        quote_string(&index.name)
+                    ))?;
+                }
+            }
+            None => conn.execute(format!(
+                "DELETE FROM {} WHERE tbl = {}",
+                STAT1_TABLE_NAME,
+                quote_string(&table.name)
+            ))?,
+        }
+        let stats = table_stats(conn, table, &indexes)?;
+        if stats.row_count > 0 && index.is_none() && indexes.is_empty() {
+            conn.execute(format!(
+                "INSERT INTO {} VALUES ({}, NULL, '{}')",
+                STAT1_TABLE_NAME,
+                quote_string(&table.name),
+                stats.row_count
+            ))?;
+        }
+        if stats.row_count > 0 {
+            for index in &indexes {
+                let rows_per_key = &stats.index_rows_per_key[&index.name];
+                let stat = std::iter::once(stats.row_count)
+                    .chain(rows_per_key.iter().copied())
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                conn.execute(format!(
+                    "INSERT INTO {} VALUES ({}, {}, '{}')",
+                    STAT1_TABLE_NAME,
+                    quote_string(&table.name),
+                    quote_string(&index.name),
+                    stat
+                ))?;
+            }
+        }
+        let name = normalize_ident(&table.name);
+        let mut schema = conn.schema.borrow_mut();
+        if index.is_some() {
+            // The statistics of the other indexes of the table are kept as they are
+            let table_stats = schema.table_stats.entry(name).or_default();
+            table_stats.row_count = stats.row_count;
+            for index in &indexes {
+                match stats.index_rows_per_key.get(&index.name) {
+                    Some(rows_per_key) if stats.row_count > 0 => {
+                        table_stats
+                            .index_rows_per_key
+                            .insert(index.name.clone(), rows_per_key.clone());
+                    }
+                    _ => {
+                        table_stats.index_rows_per_key.remove(&index.name);
+                    }
+                }
+            }
+        } else if stats.row_count > 0 {
+            schema.table_stats.insert(name, stats);
+        } else {
+            schema.table_stats.remove(&name);
+        }
+    }
+    Ok(())
+}
+
+/// Counts the rows of `table` and the distinct keys of the prefixes of each of `indexes`.
+fn table_stats(
+    conn: &Arc<Connection>,
+    table: &BTreeTable,
+    indexes: &[Arc<Index>],
+) -> Result<TableStats> {
+    let table_name = quote_ident(&table.name);
+    let row_count = count(conn, &format!("SELECT count(*) FROM {}", table_name))?;
+    let mut index_rows_per_key = HashMap::new();
+    for index in indexes {
+        let mut rows_per_key = Vec::with_capacity(index.columns.len());
+        let mut key = Vec::with_capacity(index.columns.len());
+        for column in &index.columns {
+            // Keys are grouped with the collation of the index, like the index compares them
+            key.push(match &column.collation {
+                Some(collation) => format!("{} COLLATE {}", quote_ident(&column.name), collation),
+                None => quote_ident(&column.name),
+            });
+            let distinct = count(
+                conn,
+                &format!(
+                    "SELECT count(*) FROM (SELECT 1 FROM {} GROUP BY {})",
+                    table_name,
+                    key.join(", ")
+                ),
+            )?;
+            rows_per_key.push(row_count.div_ceil(distinct.max(1)));
+        }
+        index_rows_per_key.insert(index.name.clone(), rows_per_key);
+    }
+    Ok(TableStats {
+        row_count,
+        index_rows_per_key,
+    })
+}
+
+/// Reads the statistics stored in `sqlite_stat1`, ignoring the rows that are malformed or refer
+/// to tables that do not exist.
+pub(crate) fn load_stats(conn: &Arc<Connection>) -> Result<HashMap<String, TableStats>> {
+    let mut stats: HashMap<String, TableStats> = HashMap::new();
+    if conn.schema.borrow().get_table(STAT1_TABLE_NAME).is_none() {
+        return Ok(stats);
+    }
+    let rows = query(
+        conn,
+        &format!("SELECT tbl, idx, stat FROM {}", STAT1_TABLE_NAME),
+    )?;
+    let schema = conn.schema.borrow();
+    for row in rows {
+        let [Value::Text(table), index, Value::Text(stat)] = row.as_slice() else {
+            continue;
+        };
+        if schema.get_btree_table(table.as_str()).is_none() {
+            continue;
+        }
+        // Options such as `unordered` may follow the numbers
+        let numbers = stat
+            .as_str()
+            .split_whitespace()
+            .map_while(|n| n.parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        let Some((&row_count, rows_per_key)) = numbers.split_first() else {
+            continue;
+        };
+        let table_stats = stats.entry(normalize_ident(table.as_str())).or_default();
+        table_stats.row_count = row_count;
+        if let Value::Text(index) = index {
+            table_stats
+                .index_rows_per_key
+                .insert(index.as_str().to_string(), rows_per_key.to_vec());
+        }
+    }
+    Ok(stats)
+}
+
+fn count(conn: &Arc<Connection>, sql: &str) -> Result<u64> {
+    match query(conn, sql)?.first().and_then(|row| row.first()) {
+        Some(Value::Integer(n)) => Ok(*n as u64),
+        _ => Err(LimboError::InternalError(format!(
+            "unexpected result for {}",
+            sql
+        ))),
+    }
+}
+
+fn query(conn: &Arc<Connection>, sql: &str) -> Result<Vec<Vec<Value>>> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = Vec::new();
+    loop {
+        match stmt.step()? {
+            StepResult::Row => {
+                let row = stmt.row().unwrap();
+                rows.push(row.get_values().cloned().collect());
+            }
+            StepResult::IO => conn.pager.io.run_once()?,
+            StepResult::Done => return Ok(rows),
+            StepResult::Interrupt => {
+                return Err(LimboError::InternalError("ANALYZE interrupted".to_string()))
+            }
+            StepResult::Busy => return Err(LimboError::Busy),
+        }
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn quote_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
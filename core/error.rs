@@ -47,8 +47,21 @@ pub enum LimboError {
     InvalidFormatter(String),
     #[error("Runtime error: {0}")]
     Constraint(String),
+    /// A NOT NULL constraint failed on the column named `table.column`
+    #[error("Runtime error: NOT NULL constraint failed: {0} (19)")]
+    NotNullConstraint(String),
+    /// A CHECK constraint failed, named by its name or by its expression when it is unnamed
+    #[error("Runtime error: CHECK constraint failed: {0} (19)")]
+    CheckConstraint(String),
+    /// A UNIQUE or PRIMARY KEY constraint failed on the columns named `table.column, ...`
+    #[error("Runtime error: UNIQUE constraint failed: {0} (19)")]
+    UniqueConstraint(String),
+    #[error("Runtime error: FOREIGN KEY constraint failed (19)")]
+    ForeignKeyConstraint,
     #[error("Extension error: {0}")]
     ExtensionError(String),
+    #[error("Runtime error: {0}")]
+    TooBig(String),
     #[error("Runtime error: integer overflow")]
     IntegerOverflow,
     #[error("Schema is locked for write")]
@@ -57,6 +70,73 @@ pub enum LimboError {
     ReadOnly,
     #[error("Database is busy")]
     Busy,
+    #[error("Database or disk is full")]
+    Full,
+}
+
+impl LimboError {
+    /// Stable, machine-readable category of the error, for tools that need to tell
+    /// error kinds apart without parsing messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ParseError(_)
+            | Self::LexerError(_)
+            | Self::ParseIntError(_)
+            | Self::ParseFloatError(_)
+            | Self::InvalidDate(_)
+            | Self::InvalidTime(_)
+            | Self::InvalidModifier(_) => "parse",
+            Self::Constraint(_)
+            | Self::NotNullConstraint(_)
+            | Self::CheckConstraint(_)
+            | Self::UniqueConstraint(_)
+            | Self::ForeignKeyConstraint => "constraint",
+            Self::Busy | Self::LockingError(_) | Self::SchemaLocked => "busy",
+            Self::IOError(_) => "io",
+            #[cfg(all(target_os = "linux", feature = "io_uring"))]
+            Self::UringIOError(_) => "io",
+            #[cfg(target_family = "unix")]
+            Self::RustixIOError(_) => "io",
+            Self::Corrupt(_) | Self::NotADB => "corrupt",
+            Self::ReadOnly => "readonly",
+            Self::Full => "full",
+            Self::InternalError(_)
+            | Self::CacheFull
+            | Self::ConversionError(_)
+            | Self::EnvVarError(_)
+            | Self::TxError(_)
+            | Self::InvalidArgument(_)
+            | Self::InvalidFormatter(_)
+            | Self::ExtensionError(_)
+            | Self::TooBig(_)
+            | Self::IntegerOverflow => "error",
+        }
+    }
+
+    /// Kind of the constraint that failed, for errors raised by a constraint violation.
+    pub fn constraint_kind(&self) -> Option<&'static str> {
+        match self {
+            Self::NotNullConstraint(_) => Some("notnull"),
+            Self::CheckConstraint(_) => Some("check"),
+            Self::UniqueConstraint(_) => Some("unique"),
+            Self::ForeignKeyConstraint => Some("foreignkey"),
+            _ => None,
+        }
+    }
+
+    /// Builds the error a statement halts with when a constraint fails, from the extended
+    /// result code of the violation and the description of the constraint.
+    pub fn from_constraint_code(err_code: usize, description: &str) -> Self {
+        match err_code {
+            SQLITE_CONSTRAINT_PRIMARYKEY | SQLITE_CONSTRAINT_UNIQUE => {
+                Self::UniqueConstraint(description.to_string())
+            }
+            SQLITE_CONSTRAINT_NOTNULL => Self::NotNullConstraint(description.to_string()),
+            SQLITE_CONSTRAINT_CHECK => Self::CheckConstraint(description.to_string()),
+            SQLITE_CONSTRAINT_FOREIGNKEY => Self::ForeignKeyConstraint,
+            _ => Self::Constraint(format!("undocumented halt error code {}", description)),
+        }
+    }
 }
 
 #[macro_export]
@@ -89,3 +169,6 @@ impl From<turso_ext::ResultCode> for LimboError {
 pub const SQLITE_CONSTRAINT: usize = 19;
 pub const SQLITE_CONSTRAINT_PRIMARYKEY: usize = SQLITE_CONSTRAINT | (6 << 8);
 pub const SQLITE_CONSTRAINT_NOTNULL: usize = SQLITE_CONSTRAINT | (5 << 8);
+pub const SQLITE_CONSTRAINT_FOREIGNKEY: usize = SQLITE_CONSTRAINT | (3 << 8);
+pub const SQLITE_CONSTRAINT_CHECK: usize = SQLITE_CONSTRAINT | (1 << 8);
+pub const SQLITE_CONSTRAINT_UNIQUE: usize = SQLITE_CONSTRAINT | (8 << 8);
@@ -315,9 +315,12 @@ pub enum ScalarFunc {
     LoadExtension,
     StrfTime,
     Printf,
+    Format,
     Likely,
     TimeDiff,
     Likelihood,
+    #[cfg(feature = "regexp")]
+    Regexp,
 }
 
 impl ScalarFunc {
@@ -372,9 +375,12 @@ impl ScalarFunc {
             ScalarFunc::LoadExtension => true,
             ScalarFunc::StrfTime => false,
             ScalarFunc::Printf => false,
+            ScalarFunc::Format => false,
             ScalarFunc::Likely => true,
             ScalarFunc::TimeDiff => false,
             ScalarFunc::Likelihood => true,
+            #[cfg(feature = "regexp")]
+            ScalarFunc::Regexp => true,
         }
     }
 }
@@ -431,9 +437,12 @@ impl Display for ScalarFunc {
             Self::LoadExtension => "load_extension".to_string(),
             Self::StrfTime => "strftime".to_string(),
             Self::Printf => "printf".to_string(),
+            Self::Format => "format".to_string(),
             Self::Likely => "likely".to_string(),
             Self::TimeDiff => "timediff".to_string(),
             Self::Likelihood => "likelihood".to_string(),
+            #[cfg(feature = "regexp")]
+            Self::Regexp => "regexp".to_string(),
         };
         write!(f, "{}", str)
     }
@@ -724,6 +733,8 @@ impl Func {
             "replace" => Ok(Self::Scalar(ScalarFunc::Replace)),
             "likely" => Ok(Self::Scalar(ScalarFunc::Likely)),
             "likelihood" => Ok(Self::Scalar(ScalarFunc::Likelihood)),
+            #[cfg(feature = "regexp")]
+            "regexp" => Ok(Self::Scalar(ScalarFunc::Regexp)),
             #[cfg(feature = "json")]
             "json" => Ok(Self::Json(JsonFunc::Json)),
             #[cfg(feature = "json")]
@@ -809,6 +820,7 @@ impl Func {
             "load_extension" => Ok(Self::Scalar(ScalarFunc::LoadExtension)),
             "strftime" => Ok(Self::Scalar(ScalarFunc::StrfTime)),
             "printf" => Ok(Self::Scalar(ScalarFunc::Printf)),
+            "format" => Ok(Self::Scalar(ScalarFunc::Format)),
             "vector" => Ok(Self::Vector(VectorFunc::Vector)),
             "vector32" => Ok(Self::Vector(VectorFunc::Vector32)),
             "vector64" => Ok(Self::Vector(VectorFunc::Vector64)),
@@ -1,8 +1,89 @@
 use crate::types::Value;
+use crate::util::{cast_text_to_integer, cast_text_to_real};
 use crate::vdbe::Register;
 use crate::LimboError;
 
-// TODO: Support %!.3s %i, %x, %X, %o, %e, %E, %c. flags: - + 0 ! ,
+/// Flags, width and precision of a conversion specification, e.g. `%-08.3f`
+#[derive(Default)]
+struct Spec {
+    /// `-`: pad on the right instead of the left
+    left_justify: bool,
+    /// `+`: always print the sign of numbers
+    plus_sign: bool,
+    /// ` `: print a space in place of the sign of positive numbers
+    space_sign: bool,
+    /// `0`: pad numbers with zeros instead of spaces
+    zero_pad: bool,
+    /// `#`: alternate form, e.g. `0x` in front of hexadecimal numbers
+    alternate: bool,
+    /// `,`: separate thousands in integers
+    thousands: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// Hands out the arguments that follow the format string
+struct Args<'a> {
+    values: &'a [Register],
+    index: usize,
+}
+
+impl<'a> Args<'a> {
+    fn next(&mut self) -> crate::Result<&'a Value> {
+        let value = self
+            .values
+            .get(self.index)
+            .ok_or_else(|| LimboError::InvalidArgument("not enough arguments".into()))?;
+        self.index += 1;
+        Ok(value.get_owned_value())
+    }
+
+    fn next_integer(&mut self) -> crate::Result<i64> {
+        Ok(match self.next()? {
+            Value::Integer(i) => *i,
+            Value::Float(f) => *f as i64,
+            Value::Text(t) => match cast_text_to_integer(t.as_str()) {
+                Value::Integer(i) => i,
+                _ => 0,
+            },
+            Value::Blob(b) => match cast_text_to_integer(&String::from_utf8_lossy(b)) {
+                Value::Integer(i) => i,
+                _ => 0,
+            },
+            Value::Null => 0,
+        })
+    }
+
+    fn next_float(&mut self) -> crate::Result<f64> {
+        Ok(match self.next()? {
+            Value::Integer(i) => *i as f64,
+            Value::Float(f) => *f,
+            Value::Text(t) => match cast_text_to_real(t.as_str()) {
+                Value::Float(f) => f,
+                _ => 0.0,
+            },
+            Value::Blob(b) => match cast_text_to_real(&String::from_utf8_lossy(b)) {
+                Value::Float(f) => f,
+                _ => 0.0,
+            },
+            Value::Null => 0.0,
+        })
+    }
+
+    /// Returns the next argument as text, or `None` if it is NULL
+    fn next_text(&mut self) -> crate::Result<Option<String>> {
+        Ok(match self.next()? {
+            Value::Null => None,
+            Value::Text(t) => Some(t.as_str().to_string()),
+            Value::Blob(b) => Some(String::from_utf8_lossy(b).into_owned()),
+            value => Some(value.to_string()),
+        })
+    }
+}
+
+/// Implements `printf(FORMAT, ...)` and its alias `format(FORMAT, ...)`, following the
+/// conversions of `sqlite3_mprintf()`: `%d %i %u %f %e %E %g %G %x %X %o %c %s %z %q %Q %w %%`,
+/// with the `- + space 0 # ,` flags, and a width and precision that may be given as `*`.
 #[inline(always)]
 pub fn exec_printf(values: &[Register]) -> crate::Result<Value> {
     if values.is_empty() {
@@ -14,7 +95,7 @@ pub fn exec_printf(values: &[Register]) -> crate::Result<Value> {
     };
 
     let mut result = String::new();
-    let mut args_index = 1;
+    let mut args = Args { values, index: 1 };
     let mut chars = format_str.chars().peekable();
 
     while let Some(c) = chars.next() {
@@ -23,51 +104,132 @@ pub fn exec_printf(values: &[Register]) -> crate::Result<Value> {
             continue;
         }
 
-        match chars.next() {
-            Some('%') => {
-                result.push('%');
-                continue;
-            }
-            Some('d') => {
-                if args_index >= values.len() {
-                    return Err(LimboError::InvalidArgument("not enough arguments".into()));
-                }
-                let value = &values[args_index].get_owned_value();
-                match value {
-                    Value::Integer(_) => result.push_str(&format!("{}", value)),
-                    Value::Float(_) => result.push_str(&format!("{}", value)),
-                    _ => result.push('0'),
-                }
-                args_index += 1;
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left_justify = true,
+                '+' => spec.plus_sign = true,
+                ' ' => spec.space_sign = true,
+                '0' => spec.zero_pad = true,
+                '#' => spec.alternate = true,
+                ',' => spec.thousands = true,
+                // Alternate form of SQLite that only changes how many digits floats show
+                '!' => {}
+                _ => break,
             }
-            Some('s') => {
-                if args_index >= values.len() {
-                    return Err(LimboError::InvalidArgument("not enough arguments".into()));
-                }
-                match &values[args_index].get_owned_value() {
-                    Value::Text(t) => result.push_str(t.as_str()),
-                    Value::Null => result.push_str("(null)"),
-                    v => result.push_str(&format!("{}", v)),
-                }
-                args_index += 1;
-            }
-            Some('f') => {
-                if args_index >= values.len() {
-                    return Err(LimboError::InvalidArgument("not enough arguments".into()));
-                }
-                let value = &values[args_index].get_owned_value();
-                match value {
-                    Value::Float(f) => result.push_str(&format!("{:.6}", f)),
-                    Value::Integer(i) => result.push_str(&format!("{:.6}", *i as f64)),
-                    _ => result.push_str("0.0"),
-                }
-                args_index += 1;
+            chars.next();
+        }
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let width = args.next_integer()?;
+            if width < 0 {
+                spec.left_justify = true;
             }
+            spec.width = width.unsigned_abs() as usize;
+        } else {
+            spec.width = parse_number(&mut chars);
+        }
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = if chars.peek() == Some(&'*') {
+                chars.next();
+                let precision = args.next_integer()?;
+                (precision >= 0).then_some(precision as usize)
+            } else {
+                Some(parse_number(&mut chars))
+            };
+        }
+        // Length modifiers make no difference, as all integers are 64 bits wide
+        while chars.peek() == Some(&'l') {
+            chars.next();
+        }
+
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
             None => {
                 return Err(LimboError::InvalidArgument(
                     "incomplete format specifier".into(),
                 ))
             }
+        };
+        match conversion {
+            '%' => result.push('%'),
+            'd' | 'i' => {
+                let value = args.next_integer()?;
+                let digits = integer_digits(value.unsigned_abs(), 10, false, &spec);
+                push_number(&mut result, value < 0, "", &digits, &spec);
+            }
+            'u' => {
+                let value = args.next_integer()? as u64;
+                let digits = integer_digits(value, 10, false, &spec);
+                push_number(&mut result, false, "", &digits, &spec);
+            }
+            'x' | 'X' | 'o' => {
+                let value = args.next_integer()? as u64;
+                let (radix, prefix) = match conversion {
+                    'x' => (16, "0x"),
+                    'X' => (16, "0X"),
+                    _ => (8, "0"),
+                };
+                let digits = integer_digits(value, radix, conversion == 'X', &spec);
+                let prefix = if spec.alternate && value != 0 {
+                    prefix
+                } else {
+                    ""
+                };
+                push_number(&mut result, false, prefix, &digits, &spec);
+            }
+            'f' | 'e' | 'E' | 'g' | 'G' => {
+                let value = args.next_float()?;
+                if !value.is_finite() {
+                    let text = if value.is_nan() { "NaN" } else { "Inf" };
+                    let spec = Spec {
+                        zero_pad: false,
+                        ..spec
+                    };
+                    push_number(&mut result, value < 0.0, "", text, &spec);
+                    continue;
+                }
+                let precision = spec.precision.unwrap_or(6);
+                let digits = match conversion {
+                    'f' => format!("{:.*}", precision, value.abs()),
+                    'e' | 'E' => exponential(value.abs(), precision, conversion == 'E'),
+                    _ => general(value.abs(), precision, conversion == 'G', spec.alternate),
+                };
+                let negative = value.is_sign_negative() && value != 0.0;
+                push_number(&mut result, negative, "", &digits, &spec);
+            }
+            'c' => {
+                let text = args.next_text()?.unwrap_or_default();
+                let repeat = spec.precision.unwrap_or(1);
+                let text = text
+                    .chars()
+                    .next()
+                    .map(|c| c.to_string().repeat(repeat))
+                    .unwrap_or_default();
+                push_padded(&mut result, &text, &spec);
+            }
+            's' | 'z' => {
+                let text = args.next_text()?.unwrap_or_default();
+                push_padded(&mut result, truncate(&text, spec.precision), &spec);
+            }
+            'q' | 'Q' | 'w' => {
+                let quote = if conversion == 'w' { '"' } else { '\'' };
+                let text = match args.next_text()? {
+                    Some(text) => {
+                        let escaped = truncate(&text, spec.precision)
+                            .replace(quote, &format!("{quote}{quote}"));
+                        if conversion == 'Q' {
+                            format!("'{}'", escaped)
+                        } else {
+                            escaped
+                        }
+                    }
+                    None if conversion == 'Q' => "NULL".to_string(),
+                    None => "(NULL)".to_string(),
+                };
+                push_padded(&mut result, &text, &spec);
+            }
             _ => {
                 return Err(LimboError::InvalidFormatter(
                     "this formatter is not supported".into(),
@@ -78,6 +240,127 @@ pub fn exec_printf(values: &[Register]) -> crate::Result<Value> {
     Ok(Value::build_text(result))
 }
 
+fn parse_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> usize {
+    let mut number = 0usize;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        number = number.saturating_mul(10).saturating_add(digit as usize);
+        chars.next();
+    }
+    number
+}
+
+/// Renders the digits of an integer, with at least as many digits as the precision asks for
+fn integer_digits(value: u64, radix: u32, uppercase: bool, spec: &Spec) -> String {
+    let mut digits = match radix {
+        16 if uppercase => format!("{:X}", value),
+        16 => format!("{:x}", value),
+        8 => format!("{:o}", value),
+        _ => value.to_string(),
+    };
+    if let Some(precision) = spec.precision {
+        if digits.len() < precision {
+            digits.insert_str(0, &"0".repeat(precision - digits.len()));
+        }
+    }
+    if spec.thousands && radix == 10 {
+        let mut separated = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                separated.push(',');
+            }
+            separated.push(digit);
+        }
+        digits = separated;
+    }
+    digits
+}
+
+/// Formats a non-negative float like C's `%e`, e.g. `1.500000e+02`
+fn exponential(value: f64, precision: usize, uppercase: bool) -> String {
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent = exponent.parse::<i32>().unwrap();
+    format!(
+        "{}{}{}{:02}",
+        mantissa,
+        if uppercase { 'E' } else { 'e' },
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// Formats a non-negative float like C's `%g`, which picks the shortest of `%f` and `%e` and
+/// drops the trailing zeros, unless `alternate` is set.
+fn general(value: f64, precision: usize, uppercase: bool, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let exponent = if value == 0.0 {
+        0
+    } else {
+        // The exponent after rounding to the precision, e.g. 9.9999995 rounds up to 1.00000e+01
+        let formatted = format!("{:.*e}", precision - 1, value);
+        formatted.split_once('e').unwrap().1.parse::<i32>().unwrap()
+    };
+    let formatted = if exponent < -4 || exponent >= precision as i32 {
+        exponential(value, precision - 1, uppercase)
+    } else {
+        format!("{:.*}", (precision as i32 - 1 - exponent) as usize, value)
+    };
+    if alternate {
+        return formatted;
+    }
+    let (mantissa, exponent) = match formatted.find(['e', 'E']) {
+        Some(pos) => formatted.split_at(pos),
+        None => (formatted.as_str(), ""),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{}{}", mantissa, exponent)
+}
+
+/// Keeps at most `precision` characters of `text`
+fn truncate(text: &str, precision: Option<usize>) -> &str {
+    match precision.and_then(|precision| text.char_indices().nth(precision)) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Pushes a number made of its sign, a prefix such as `0x` and its digits, padded to the width
+fn push_number(result: &mut String, negative: bool, prefix: &str, digits: &str, spec: &Spec) {
+    let sign = if negative {
+        "-"
+    } else if spec.plus_sign {
+        "+"
+    } else if spec.space_sign {
+        " "
+    } else {
+        ""
+    };
+    let len = sign.len() + prefix.len() + digits.chars().count();
+    if spec.zero_pad && !spec.left_justify && len < spec.width {
+        result.push_str(sign);
+        result.push_str(prefix);
+        result.push_str(&"0".repeat(spec.width - len));
+        result.push_str(digits);
+    } else {
+        push_padded(result, &format!("{}{}{}", sign, prefix, digits), spec);
+    }
+}
+
+fn push_padded(result: &mut String, text: &str, spec: &Spec) {
+    let padding = spec.width.saturating_sub(text.chars().count());
+    if spec.left_justify {
+        result.push_str(text);
+        result.push_str(&" ".repeat(padding));
+    } else {
+        result.push_str(&" ".repeat(padding));
+        result.push_str(text);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,7 +406,7 @@ mod tests {
             // String with null value
             (
                 vec![text("Hello, %s!"), Register::Value(Value::Null)],
-                text("Hello, (null)!"),
+                text("Hello, !"),
             ),
             // String with number conversion
             (vec![text("Value: %s"), integer(42)], text("Value: 42")),
@@ -184,7 +467,7 @@ mod tests {
             // Non-numeric value defaults to 0.0
             (
                 vec![text("Number: %f"), text("not a number")],
-                text("Number: 0.0"),
+                text("Number: 0.000000"),
             ),
         ];
 
@@ -228,6 +511,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_printf_width_and_precision() {
+        let test_cases = vec![
+            (vec![text("[%5d]"), integer(42)], text("[   42]")),
+            (vec![text("[%-5d]"), integer(42)], text("[42   ]")),
+            (vec![text("[%05d]"), integer(-42)], text("[-0042]")),
+            (vec![text("[%+d]"), integer(42)], text("[+42]")),
+            (vec![text("[%.3d]"), integer(7)], text("[007]")),
+            (vec![text("[%,d]"), integer(1234567)], text("[1,234,567]")),
+            (vec![text("[%*d]"), integer(4), integer(7)], text("[   7]")),
+            (vec![text("[%8.3f]"), float(3.14159)], text("[   3.142]")),
+            (vec![text("[%.2e]"), float(1234.5)], text("[1.23e+03]")),
+            (vec![text("[%g]"), float(0.0001)], text("[0.0001]")),
+            (vec![text("[%g]"), float(1e20)], text("[1e+20]")),
+            (vec![text("[%10.3s]"), text("abcdef")], text("[       abc]")),
+            (vec![text("[%-6s]"), text("ab")], text("[ab    ]")),
+            (vec![text("[%x]"), integer(255)], text("[ff]")),
+            (vec![text("[%#X]"), integer(255)], text("[0XFF]")),
+            (vec![text("[%o]"), integer(8)], text("[10]")),
+            (vec![text("[%x]"), integer(-1)], text("[ffffffffffffffff]")),
+            (vec![text("[%c]"), text("xyz")], text("[x]")),
+            (vec![text("[%.3c]"), text("xyz")], text("[xxx]")),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(exec_printf(&input).unwrap(), *expected.get_owned_value());
+        }
+    }
+
+    #[test]
+    fn test_printf_sql_quoting() {
+        let test_cases = vec![
+            (vec![text("'%q'"), text("it's")], text("'it''s'")),
+            (vec![text("%Q"), text("it's")], text("'it''s'")),
+            (vec![text("%Q"), Register::Value(Value::Null)], text("NULL")),
+            (
+                vec![text("%q"), Register::Value(Value::Null)],
+                text("(NULL)"),
+            ),
+            (vec![text("\"%w\""), text("a\"b")], text("\"a\"\"b\"")),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(exec_printf(&input).unwrap(), *expected.get_owned_value());
+        }
+    }
+
     #[test]
     fn test_printf_error_cases() {
         let error_cases = vec![
@@ -340,6 +340,11 @@ impl File for UringFile {
     fn size(&self) -> Result<u64> {
         Ok(self.file.metadata()?.len())
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        self.file.set_len(len)?;
+        Ok(())
+    }
 }
 
 impl Drop for UringFile {
@@ -176,6 +176,18 @@ impl File for MemoryFile {
     fn size(&self) -> Result<u64> {
         Ok(self.size.get() as u64)
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        let len = len as usize;
+        let pages = unsafe { &mut *self.pages.get() };
+        pages.retain(|&page_no, _| page_no * PAGE_SIZE < len);
+        // Bytes past the new end must read back as zeros if the file grows again
+        if let Some(page) = pages.get_mut(&(len / PAGE_SIZE)) {
+            page[len % PAGE_SIZE..].fill(0);
+        }
+        self.size.set(len);
+        Ok(())
+    }
 }
 
 impl Drop for MemoryFile {
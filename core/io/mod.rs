@@ -23,6 +23,11 @@ pub trait File: Send + Sync {
     ) -> Result<Arc<Completion>>;
     fn sync(&self, c: Completion) -> Result<Arc<Completion>>;
     fn size(&self) -> Result<u64>;
+    /// Sets the length of the file to `len` bytes. Backends that can't resize files keep
+    /// their current length.
+    fn truncate(&self, _len: u64) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -419,6 +419,12 @@ impl File for UnixFile<'_> {
         let file = self.file.borrow();
         Ok(file.metadata()?.len())
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        let file = self.file.borrow();
+        file.set_len(len)?;
+        Ok(())
+    }
 }
 
 impl Drop for UnixFile<'_> {
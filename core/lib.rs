@@ -1,5 +1,6 @@
 #![allow(clippy::arc_with_non_send_sync)]
 
+mod analyze;
 mod assert;
 mod error;
 mod ext;
@@ -10,15 +11,18 @@ mod info;
 mod io;
 #[cfg(feature = "json")]
 mod json;
+mod limits;
 pub mod mvcc;
 mod parameters;
 mod pragma;
 mod pseudo;
+mod recover;
 pub mod result;
 mod schema;
 #[cfg(feature = "series")]
 mod series;
 mod storage;
+mod temp;
 #[allow(dead_code)]
 #[cfg(feature = "time")]
 mod time;
@@ -27,6 +31,7 @@ pub mod types;
 mod util;
 #[cfg(feature = "uuid")]
 mod uuid;
+mod vacuum;
 mod vdbe;
 mod vector;
 mod vtab;
@@ -57,7 +62,9 @@ pub use io::{
     Buffer, Completion, CompletionType, File, MemoryIO, OpenFlags, PlatformIO, SyscallIO,
     WriteCompletion, IO,
 };
+pub use limits::{Limit, Limits};
 use parking_lot::RwLock;
+pub use recover::{RecoveredRow, RecoveredTable};
 use schema::Schema;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
@@ -68,15 +75,15 @@ use std::{
     fmt::Display,
     io::Write,
     num::NonZero,
-    ops::Deref,
     rc::Rc,
     sync::Arc,
 };
 #[cfg(feature = "fs")]
 use storage::database::DatabaseFile;
 use storage::page_cache::DumbLruPageCache;
-pub use storage::pager::PagerCacheflushStatus;
-use storage::pager::{DB_STATE_INITIALIZED, DB_STATE_UNITIALIZED};
+pub use storage::pager::{PagerCacheflushStatus, PagerStats};
+use storage::pager::{PagerSnapshot, DB_STATE_INITIALIZED, DB_STATE_UNITIALIZED};
+pub use storage::sqlite3_ondisk::DatabaseHeader;
 pub use storage::{
     buffer_pool::BufferPool,
     database::DatabaseStorage,
@@ -84,6 +91,7 @@ pub use storage::{
     pager::{Page, Pager},
     wal::{CheckpointMode, CheckpointResult, CheckpointStatus, Wal, WalFile, WalFileShared},
 };
+use temp::TempDatabase;
 use tracing::{instrument, Level};
 use translate::select::prepare_select_plan;
 use turso_sqlite3_parser::{ast, ast::Cmd, lexer::sql::Parser};
@@ -102,6 +110,18 @@ enum TransactionState {
     None,
 }
 
+/// A savepoint opened with `SAVEPOINT`, holding the state to go back to on `ROLLBACK TO`.
+struct Savepoint {
+    name: String,
+    /// Whether the savepoint was opened outside of a transaction and so started one,
+    /// which releasing the savepoint commits.
+    starts_transaction: bool,
+    pages: PagerSnapshot,
+    schema: Schema,
+    /// Contents of the temp database, if it was already changed in the transaction
+    temp: Option<(PagerSnapshot, Schema)>,
+}
+
 pub(crate) type MvStore = mvcc::MvStore<mvcc::LocalClock>;
 
 pub(crate) type MvCursor = mvcc::cursor::ScanCursor<mvcc::LocalClock>;
@@ -231,15 +251,19 @@ impl Database {
                 // a warning to the user to load the module
                 eprintln!("Warning: {}", e);
             }
+            // Statistics are read with the parsed schema, which knows about sqlite_stat1
+            conn.schema.replace(schema.clone());
+            schema.table_stats = analyze::load_stats(&conn)?;
         }
         Ok(db)
     }
 
     pub fn connect(self: &Arc<Database>) -> Result<Arc<Connection>> {
-        let buffer_pool = Arc::new(BufferPool::new(None));
-
         // Open existing WAL file if present
         if let Some(shared_wal) = self.maybe_shared_wal.read().clone() {
+            // The WAL records the page size of the database, which may not have pages yet
+            let page_size = unsafe { &*shared_wal.get() }.page_size();
+            let buffer_pool = Arc::new(BufferPool::new(Some(page_size as usize)));
             // No pages in DB file or WAL -> empty database
             let is_empty = self.is_empty.clone();
             let wal = Rc::new(RefCell::new(WalFile::new(
@@ -257,12 +281,9 @@ impl Database {
                 self.init_lock.clone(),
             )?);
 
-            let page_size = header_accessor::get_page_size(&pager)
-                .unwrap_or(storage::sqlite3_ondisk::DEFAULT_PAGE_SIZE)
-                as u32;
+            pager.adopt_header_page_size();
             let default_cache_size = header_accessor::get_default_page_cache_size(&pager)
                 .unwrap_or(storage::sqlite3_ondisk::DEFAULT_CACHE_SIZE);
-            pager.buffer_pool.set_page_size(page_size as usize);
             let conn = Arc::new(Connection {
                 _db: self.clone(),
                 pager: pager.clone(),
@@ -278,6 +299,12 @@ impl Database {
                 cache_size: Cell::new(default_cache_size),
                 readonly: Cell::new(false),
                 wal_checkpoint_disabled: Cell::new(false),
+                foreign_keys: Cell::new(false),
+                limits: Cell::new(Limits::default()),
+                progress_interval: Cell::new(0),
+                progress_handler: RefCell::new(None),
+                savepoints: RefCell::new(Vec::new()),
+                temp: RefCell::new(None),
             });
             if let Err(e) = conn.register_builtins() {
                 return Err(LimboError::ExtensionError(e));
@@ -288,6 +315,7 @@ impl Database {
         // No existing WAL; create one.
         // TODO: currently Pager needs to be instantiated with some implementation of trait Wal, so here's a workaround.
         let dummy_wal = Rc::new(RefCell::new(DummyWAL {}));
+        let buffer_pool = Arc::new(BufferPool::new(None));
         let is_empty = self.is_empty.clone();
         let mut pager = Pager::new(
             self.db_file.clone(),
@@ -298,8 +326,8 @@ impl Database {
             is_empty,
             Arc::new(Mutex::new(())),
         )?;
-        let page_size = header_accessor::get_page_size(&pager)
-            .unwrap_or(storage::sqlite3_ondisk::DEFAULT_PAGE_SIZE) as u32;
+        pager.adopt_header_page_size();
+        let page_size = pager.get_page_size();
         let default_cache_size = header_accessor::get_default_page_cache_size(&pager)
             .unwrap_or(storage::sqlite3_ondisk::DEFAULT_CACHE_SIZE);
 
@@ -330,6 +358,12 @@ impl Database {
             cache_size: Cell::new(default_cache_size),
             readonly: Cell::new(false),
             wal_checkpoint_disabled: Cell::new(false),
+            foreign_keys: Cell::new(false),
+            limits: Cell::new(Limits::default()),
+            progress_interval: Cell::new(0),
+            progress_handler: RefCell::new(None),
+            savepoints: RefCell::new(Vec::new()),
+            temp: RefCell::new(None),
         });
 
         if let Err(e) = conn.register_builtins() {
@@ -450,8 +484,21 @@ pub struct Connection {
     cache_size: Cell<i32>,
     readonly: Cell<bool>,
     wal_checkpoint_disabled: Cell<bool>,
+    /// Whether foreign key constraints are enforced (`PRAGMA foreign_keys`)
+    foreign_keys: Cell<bool>,
+    limits: Cell<Limits>,
+    /// Number of VM instructions between calls to the progress handler, 0 when disabled
+    progress_interval: Cell<u64>,
+    progress_handler: RefCell<Option<ProgressHandler>>,
+    /// Savepoints opened with `SAVEPOINT`, innermost last
+    savepoints: RefCell<Vec<Savepoint>>,
+    /// Database of the temp tables, opened when the first one is created
+    temp: RefCell<Option<TempDatabase>>,
 }
 
+/// Callback invoked periodically while a statement runs, returning `true` interrupts it
+pub type ProgressHandler = Box<dyn Fn() -> bool>;
+
 impl Connection {
     #[instrument(skip_all, level = Level::TRACE)]
     pub fn prepare(self: &Arc<Connection>, sql: impl AsRef<str>) -> Result<Statement> {
@@ -463,6 +510,7 @@ impl Connection {
 
         let sql = sql.as_ref();
         tracing::trace!("Preparing: {}", sql);
+        self.check_sql_length(sql)?;
         let mut parser = Parser::new(sql.as_bytes());
         let cmd = parser.next()?;
         let syms = self.syms.borrow();
@@ -474,15 +522,17 @@ impl Connection {
         self.maybe_update_schema();
         match cmd {
             Cmd::Stmt(stmt) => {
-                let program = Rc::new(translate::translate(
-                    self.schema.borrow().deref(),
-                    stmt,
-                    self.pager.clone(),
-                    self.clone(),
-                    &syms,
-                    QueryMode::Normal,
-                    input,
-                )?);
+                let program = Rc::new(self.with_schema(|schema| {
+                    translate::translate(
+                        schema,
+                        stmt,
+                        self.pager.clone(),
+                        self.clone(),
+                        &syms,
+                        QueryMode::Normal,
+                        input,
+                    )
+                })?);
                 Ok(Statement::new(
                     program,
                     self._db.mv_store.clone(),
@@ -516,18 +566,21 @@ impl Connection {
         cmd: Cmd,
         input: &str,
     ) -> Result<Option<Statement>> {
+        self.check_sql_length(input)?;
         let syms = self.syms.borrow();
         match cmd {
             Cmd::Stmt(ref stmt) | Cmd::Explain(ref stmt) => {
-                let program = translate::translate(
-                    self.schema.borrow().deref(),
-                    stmt.clone(),
-                    self.pager.clone(),
-                    self.clone(),
-                    &syms,
-                    cmd.into(),
-                    input,
-                )?;
+                let program = self.with_schema(|schema| {
+                    translate::translate(
+                        schema,
+                        stmt.clone(),
+                        self.pager.clone(),
+                        self.clone(),
+                        &syms,
+                        cmd.into(),
+                        input,
+                    )
+                })?;
                 let stmt = Statement::new(
                     program.into(),
                     self._db.mv_store.clone(),
@@ -539,15 +592,18 @@ impl Connection {
                 let mut table_ref_counter = TableRefIdCounter::new();
                 match stmt {
                     ast::Stmt::Select(select) => {
-                        let mut plan = prepare_select_plan(
-                            self.schema.borrow().deref(),
-                            *select,
-                            &syms,
-                            &[],
-                            &mut table_ref_counter,
-                            translate::plan::QueryDestination::ResultRows,
-                        )?;
-                        optimize_plan(&mut plan, self.schema.borrow().deref())?;
+                        let plan = self.with_schema(|schema| {
+                            let mut plan = prepare_select_plan(
+                                schema,
+                                *select,
+                                &syms,
+                                &[],
+                                &mut table_ref_counter,
+                                translate::plan::QueryDestination::ResultRows,
+                            )?;
+                            optimize_plan(&mut plan, schema)?;
+                            Ok::<_, LimboError>(plan)
+                        })?;
                         let _ = std::io::stdout().write_all(plan.to_string().as_bytes());
                     }
                     _ => todo!(),
@@ -566,6 +622,7 @@ impl Connection {
     #[instrument(skip_all, level = Level::TRACE)]
     pub fn execute(self: &Arc<Connection>, sql: impl AsRef<str>) -> Result<()> {
         let sql = sql.as_ref();
+        self.check_sql_length(sql)?;
         let mut parser = Parser::new(sql.as_bytes());
         while let Some(cmd) = parser.next()? {
             let syms = self.syms.borrow();
@@ -576,28 +633,32 @@ impl Connection {
             self.maybe_update_schema();
             match cmd {
                 Cmd::Explain(stmt) => {
-                    let program = translate::translate(
-                        self.schema.borrow().deref(),
-                        stmt,
-                        self.pager.clone(),
-                        self.clone(),
-                        &syms,
-                        QueryMode::Explain,
-                        input,
-                    )?;
+                    let program = self.with_schema(|schema| {
+                        translate::translate(
+                            schema,
+                            stmt,
+                            self.pager.clone(),
+                            self.clone(),
+                            &syms,
+                            QueryMode::Explain,
+                            input,
+                        )
+                    })?;
                     let _ = std::io::stdout().write_all(program.explain().as_bytes());
                 }
                 Cmd::ExplainQueryPlan(_stmt) => todo!(),
                 Cmd::Stmt(stmt) => {
-                    let program = translate::translate(
-                        self.schema.borrow().deref(),
-                        stmt,
-                        self.pager.clone(),
-                        self.clone(),
-                        &syms,
-                        QueryMode::Normal,
-                        input,
-                    )?;
+                    let program = self.with_schema(|schema| {
+                        translate::translate(
+                            schema,
+                            stmt,
+                            self.pager.clone(),
+                            self.clone(),
+                            &syms,
+                            QueryMode::Normal,
+                            input,
+                        )
+                    })?;
 
                     let mut state =
                         vdbe::ProgramState::new(program.max_registers, program.cursor_ref.len());
@@ -684,13 +745,44 @@ impl Connection {
         Ok(())
     }
 
-    pub fn checkpoint(&self) -> Result<CheckpointResult> {
+    /// Reads the database file header. Fails if the database is still empty.
+    pub fn database_header(&self) -> Result<DatabaseHeader> {
+        header_accessor::get_database_header(&self.pager)
+    }
+
+    /// Reads the raw bytes of the 1-based page `page_idx` through the pager, so pages that only
+    /// exist in the WAL are returned in their latest version.
+    pub fn page_bytes(&self, page_idx: usize) -> Result<Vec<u8>> {
+        let page_count = header_accessor::get_database_size(&self.pager)? as usize;
+        if page_idx == 0 || page_idx > page_count {
+            return Err(LimboError::InvalidArgument(format!(
+                "page {page_idx} is out of range, the database has {page_count} pages"
+            )));
+        }
+        self.pager.read_page_bytes(page_idx)
+    }
+
+    /// Salvages as many rows as possible by decoding the table b-tree pages directly, for
+    /// databases too corrupted to be read with queries. The rows of leaf pages that no table
+    /// reaches are returned last, in a table without a name.
+    pub fn recover(&self) -> Result<Vec<RecoveredTable>> {
+        recover::recover(self)
+    }
+
+    /// Page cache and I/O statistics of this connection's pager.
+    pub fn pager_stats(&self) -> PagerStats {
+        self.pager.stats()
+    }
+
+    pub fn checkpoint(&self, mode: CheckpointMode) -> Result<CheckpointResult> {
         self.pager
-            .wal_checkpoint(self.wal_checkpoint_disabled.get())
+            .wal_checkpoint(self.wal_checkpoint_disabled.get(), mode)
     }
 
     /// Close a connection and checkpoint.
     pub fn close(&self) -> Result<()> {
+        // The temp tables only live as long as the connection
+        self.temp.replace(None);
         self.pager
             .checkpoint_shutdown(self.wal_checkpoint_disabled.get())
     }
@@ -720,8 +812,89 @@ impl Connection {
     pub fn get_cache_size(&self) -> i32 {
         self.cache_size.get()
     }
-    pub fn set_cache_size(&self, size: i32) {
-        self.cache_size.set(size);
+
+    /// Sets the size of the page cache, either as a number of pages or, when negative, as the
+    /// amount of KiB the cache may use, and resizes the page cache of the pager accordingly.
+    pub fn set_cache_size(&self, size: i64) -> Result<()> {
+        // SQLite uses this value as threshold for maximum cache size
+        const MAX_SAFE_CACHE_SIZE: i64 = 2147450880;
+
+        let mut size = size;
+        let mut pages = if size < 0 {
+            let page_size = self.pager.get_page_size() as i64;
+            if page_size == 0 {
+                return Err(LimboError::InternalError(
+                    "Page size cannot be zero".to_string(),
+                ));
+            }
+            size.saturating_abs().saturating_mul(1024) / page_size
+        } else {
+            size
+        };
+        if pages > MAX_SAFE_CACHE_SIZE {
+            pages = 0;
+            size = 0;
+        }
+        let pages = if (pages as usize) < storage::sqlite3_ondisk::MIN_PAGE_CACHE_SIZE {
+            if size >= 0 {
+                size = storage::sqlite3_ondisk::MIN_PAGE_CACHE_SIZE as i64;
+            }
+            storage::sqlite3_ondisk::MIN_PAGE_CACHE_SIZE
+        } else {
+            pages as usize
+        };
+        self.cache_size.set(size as i32);
+
+        // Pages that can't be evicted right away are evicted as new pages come in
+        self.pager.change_page_cache_size(pages).map_err(|e| {
+            LimboError::InternalError(format!("Failed to update page cache size: {}", e))
+        })?;
+        Ok(())
+    }
+
+    pub fn foreign_keys_enabled(&self) -> bool {
+        self.foreign_keys.get()
+    }
+    pub fn set_foreign_keys_enabled(&self, enabled: bool) {
+        self.foreign_keys.set(enabled);
+    }
+
+    pub fn limit(&self, limit: Limit) -> i32 {
+        self.limits.get().get(limit)
+    }
+    /// Sets a run-time limit and returns its previous value, see [Limits::set]
+    pub fn set_limit(&self, limit: Limit, value: i32) -> i32 {
+        let mut limits = self.limits.get();
+        let prior = limits.set(limit, value);
+        self.limits.set(limits);
+        prior
+    }
+
+    /// Registers a handler that is invoked every `interval` VM instructions, like
+    /// `sqlite3_progress_handler()`. Passing `None` or a zero interval removes it.
+    pub fn set_progress_handler(&self, interval: u64, handler: Option<ProgressHandler>) {
+        let interval = if handler.is_some() { interval } else { 0 };
+        self.progress_interval.set(interval);
+        self.progress_handler.replace(handler);
+    }
+
+    pub(crate) fn progress_interval(&self) -> u64 {
+        self.progress_interval.get()
+    }
+
+    /// Returns true if the progress handler asks for the running statement to be interrupted
+    pub(crate) fn invoke_progress_handler(&self) -> bool {
+        self.progress_handler
+            .borrow()
+            .as_ref()
+            .is_some_and(|handler| handler())
+    }
+
+    fn check_sql_length(&self, sql: &str) -> Result<()> {
+        if sql.len() > self.limit(Limit::SqlLength) as usize {
+            return Err(LimboError::TooBig("statement too long".to_string()));
+        }
+        Ok(())
     }
 
     #[cfg(feature = "fs")]
@@ -729,6 +902,11 @@ impl Connection {
         Database::open_with_vfs(&self._db, path, vfs)
     }
 
+    /// The database the connection was opened on, e.g. to open more connections to it
+    pub fn database(&self) -> Arc<Database> {
+        self._db.clone()
+    }
+
     pub fn list_vfs(&self) -> Vec<String> {
         let mut all_vfs = vec![String::from("memory")];
         #[cfg(feature = "fs")]
@@ -746,6 +924,42 @@ impl Connection {
         all_vfs
     }
 
+    /// Runs `f` with the schema that names are resolved against, in which the temp tables of the
+    /// connection shadow the tables of the main database with the same name
+    pub(crate) fn with_schema<T>(&self, f: impl FnOnce(&Schema) -> T) -> T {
+        let temp = self.temp.borrow();
+        let Some(temp) = temp.as_ref() else {
+            return f(&self.schema.borrow());
+        };
+        let shadowed = temp.shadow(&mut self.schema.borrow_mut());
+        let result = f(&self.schema.borrow());
+        temp.unshadow(&mut self.schema.borrow_mut(), shadowed);
+        result
+    }
+
+    /// The pager of the temp database, which is opened if it does not exist yet, with a write
+    /// transaction open on it until the transaction of the connection ends
+    pub(crate) fn temp_pager(&self) -> Result<Rc<Pager>> {
+        if let Some(temp) = self.temp.borrow().as_ref() {
+            temp.begin_tx()?;
+            return Ok(temp.pager.clone());
+        }
+        let temp = TempDatabase::open(self)?;
+        temp.begin_tx()?;
+        let pager = temp.pager.clone();
+        self.temp.replace(Some(temp));
+        Ok(pager)
+    }
+
+    /// Commits or rolls back the changes to the temp database, when the transaction of the
+    /// connection ends
+    pub(crate) fn end_temp_tx(&self, rollback: bool) -> Result<()> {
+        match self.temp.borrow_mut().as_mut() {
+            Some(temp) => temp.end_tx(self, rollback),
+            None => Ok(()),
+        }
+    }
+
     pub fn get_auto_commit(&self) -> bool {
         self.auto_commit.get()
     }
@@ -910,6 +1124,32 @@ impl Statement {
         self.state.bind_at(index, value);
     }
 
+    /// Index of the parameter written as `name` in the SQL, either a named parameter with its
+    /// prefix (`:foo`, `@foo`, `$foo`) or a numbered one (`?2`)
+    pub fn parameter_index(&self, name: &str) -> Option<NonZero<usize>> {
+        let parameters = &self.program.parameters;
+        if let Some(index) = parameters.index(name) {
+            return Some(index);
+        }
+        let index = name.strip_prefix('?')?.parse::<NonZero<usize>>().ok()?;
+        parameters
+            .list
+            .iter()
+            .any(|p| p.index() == index)
+            .then_some(index)
+    }
+
+    /// Binds `value` to the parameter written as `name` in the SQL, see [Self::parameter_index]
+    pub fn bind_named(&mut self, name: &str, value: Value) -> Result<()> {
+        let Some(index) = self.parameter_index(name) else {
+            return Err(LimboError::InvalidArgument(format!(
+                "no such parameter: {name}"
+            )));
+        };
+        self.bind_at(index, value);
+        Ok(())
+    }
+
     pub fn reset(&mut self) {
         self.state.reset();
     }
@@ -921,6 +1161,19 @@ impl Statement {
     pub fn explain(&self) -> String {
         self.program.explain()
     }
+
+    /// The text of the statement
+    pub fn sql(&self) -> &str {
+        &self.program.sql
+    }
+
+    /// The text of the statement with its parameters replaced by the literals of their bound
+    /// values, or NULL when unbound, like `sqlite3_expanded_sql()`
+    pub fn expanded_sql(&self) -> String {
+        parameters::expand_sql(&self.program.sql, &self.program.parameters, |index| {
+            self.state.get_parameter(index)
+        })
+    }
 }
 
 pub type Row = vdbe::Row;
@@ -0,0 +1,84 @@
+//! Run-time limits of a connection, mirroring `sqlite3_limit()`.
+
+/// A run-time limit that can be lowered per connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Maximum size of a string, blob or table row in bytes
+    Length,
+    /// Maximum length of an SQL statement in bytes
+    SqlLength,
+    /// Maximum number of columns in a table definition
+    Column,
+    /// Maximum number of terms in a compound SELECT
+    CompoundSelect,
+    /// Maximum number of attached databases
+    Attached,
+}
+
+impl Limit {
+    pub const ALL: [Limit; 5] = [
+        Limit::Length,
+        Limit::SqlLength,
+        Limit::Column,
+        Limit::CompoundSelect,
+        Limit::Attached,
+    ];
+
+    /// Name of the limit as used by the sqlite3 shell
+    pub fn name(&self) -> &'static str {
+        match self {
+            Limit::Length => "length",
+            Limit::SqlLength => "sql_length",
+            Limit::Column => "column",
+            Limit::CompoundSelect => "compound_select",
+            Limit::Attached => "attached",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|limit| limit.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Compile-time upper bound of the limit, limits can only be lowered below this value
+    pub fn hard_max(&self) -> i32 {
+        match self {
+            Limit::Length => 1_000_000_000,
+            Limit::SqlLength => 1_000_000_000,
+            Limit::Column => 2000,
+            Limit::CompoundSelect => 500,
+            Limit::Attached => 10,
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|limit| limit == self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits([i32; Limit::ALL.len()]);
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self(Limit::ALL.map(|limit| limit.hard_max()))
+    }
+}
+
+impl Limits {
+    pub fn get(&self, limit: Limit) -> i32 {
+        self.0[limit.index()]
+    }
+
+    /// Sets a new value for `limit` and returns the previous one. Like `sqlite3_limit()`,
+    /// a negative value leaves the limit unchanged and values above the hard maximum are
+    /// truncated.
+    pub fn set(&mut self, limit: Limit, value: i32) -> i32 {
+        let prior = self.get(limit);
+        if value >= 0 {
+            self.0[limit.index()] = value.min(limit.hard_max());
+        }
+        prior
+    }
+}
@@ -1,5 +1,10 @@
 use std::num::NonZero;
 
+use turso_sqlite3_parser::lexer::sql::{TokenType, Tokenizer};
+use turso_sqlite3_parser::lexer::Scanner;
+
+use crate::Value;
+
 pub const PARAM_PREFIX: &str = "__param_";
 
 #[derive(Clone, Debug)]
@@ -122,3 +127,40 @@ impl Parameters {
         }
     }
 }
+
+/// Replaces each parameter of `sql` with the SQL literal of its value, as returned by `value`
+/// for its index. Anonymous parameters are numbered in the order they appear, like when the
+/// statement is translated, and parameters that can't be resolved are kept as they are.
+pub fn expand_sql(
+    sql: &str,
+    parameters: &Parameters,
+    value: impl Fn(NonZero<usize>) -> Value,
+) -> String {
+    let mut expanded = String::with_capacity(sql.len());
+    let mut scanner = Scanner::new(Tokenizer::new());
+    let mut copied_up_to = 0;
+    let mut anonymous_count = 0;
+    while let Ok((start, Some((token, token_type)), end)) = scanner.scan(sql.as_bytes()) {
+        if token_type != TokenType::TK_VARIABLE {
+            continue;
+        }
+        // The token of `?NNN` is the number alone, and the one of `?` is empty
+        let token = std::str::from_utf8(token).unwrap_or_default();
+        let index = if token.is_empty() {
+            anonymous_count += 1;
+            NonZero::new(anonymous_count)
+        } else if token.starts_with(['$', ':', '@', '#']) {
+            parameters.index(token)
+        } else {
+            token.parse().ok()
+        };
+        let Some(index) = index else {
+            continue;
+        };
+        expanded.push_str(&sql[copied_up_to..start]);
+        expanded.push_str(&value(index).exec_quote().to_string());
+        copied_up_to = end;
+    }
+    expanded.push_str(&sql[copied_up_to..]);
+    expanded
+}
@@ -0,0 +1,262 @@
+//! Salvages the rows of a possibly corrupted database by reading its table b-tree pages directly
+//! instead of going through cursors, like the `.recover` command of the SQLite shell.
+//!
+//! Each table b-tree is walked from its root page, skipping the pages and cells that cannot be
+//! decoded instead of failing. The table leaf pages that no b-tree reaches, e.g. because an
+//! interior page pointing to them is broken, are scanned too and their rows are returned as lost.
+
+use std::collections::HashSet;
+
+use crate::schema::Table;
+use crate::storage::sqlite3_ondisk::{payload_overflows, read_u32, read_value, read_varint};
+use crate::types::{RefValue, SerialType};
+use crate::{Connection, Result, Value};
+
+const TABLE_INTERIOR: u8 = 0x05;
+const TABLE_LEAF: u8 = 0x0d;
+
+/// Rows salvaged from the b-tree of a table
+#[derive(Debug, Clone)]
+pub struct RecoveredTable {
+    /// Name of the table, or `None` for the rows of leaf pages that no table b-tree reaches
+    pub name: Option<String>,
+    /// Names of the columns of the table
+    pub columns: Vec<String>,
+    /// Position of the `INTEGER PRIMARY KEY` column, which holds the rowid of each row
+    pub rowid_alias: Option<usize>,
+    pub rows: Vec<RecoveredRow>,
+}
+
+/// A row salvaged from a table leaf page
+#[derive(Debug, Clone)]
+pub struct RecoveredRow {
+    /// Page the row was found on
+    pub page: usize,
+    pub rowid: i64,
+    /// Values of the record, which holds fewer values than the table has columns when columns
+    /// were added after the row was written
+    pub values: Vec<Value>,
+}
+
+pub(crate) fn recover(conn: &Connection) -> Result<Vec<RecoveredTable>> {
+    let header = conn.database_header()?;
+    let page_size = header.get_page_size() as usize;
+    let mut reader = PageReader {
+        conn,
+        page_count: header.database_size as usize,
+        // With a corrupted reserved space larger than the page, no cell can be decoded
+        usable_size: page_size.saturating_sub(header.reserved_space as usize),
+        visited: HashSet::new(),
+    };
+
+    // The pages of the schema table are not data, but they must not be reported as lost
+    reader.walk(1);
+    let mut tables = conn
+        .schema
+        .borrow()
+        .tables
+        .values()
+        .filter_map(|table| match table.as_ref() {
+            Table::BTree(table) if table.has_rowid && table.root_page > 1 => Some(table.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    tables.sort_by_key(|table| table.root_page);
+
+    let mut recovered = Vec::with_capacity(tables.len() + 1);
+    for table in tables {
+        let rowid_alias = table.get_rowid_alias_column().map(|(pos, _)| pos);
+        let mut rows = reader.walk(table.root_page);
+        if let Some(pos) = rowid_alias {
+            for row in &mut rows {
+                if let Some(value @ Value::Null) = row.values.get_mut(pos) {
+                    *value = Value::Integer(row.rowid);
+                }
+            }
+        }
+        recovered.push(RecoveredTable {
+            name: Some(table.name.clone()),
+            columns: table
+                .columns
+                .iter()
+                .map(|column| column.name.clone().unwrap_or_default())
+                .collect(),
+            rowid_alias,
+            rows,
+        });
+    }
+
+    let mut lost = Vec::new();
+    for page in 1..=reader.page_count {
+        if reader.visited.contains(&page) {
+            continue;
+        }
+        // Other pages are left unclaimed, as they may be overflow pages of a lost row
+        if let Some((TABLE_LEAF, bytes)) = reader.read(page) {
+            reader.visited.insert(page);
+            lost.extend(reader.leaf_rows(page, &bytes));
+        }
+    }
+    if !lost.is_empty() {
+        recovered.push(RecoveredTable {
+            name: None,
+            columns: vec![],
+            rowid_alias: None,
+            rows: lost,
+        });
+    }
+    Ok(recovered)
+}
+
+struct PageReader<'a> {
+    conn: &'a Connection,
+    page_count: usize,
+    usable_size: usize,
+    /// Pages already claimed by a b-tree, which also keeps a broken b-tree from looping
+    visited: HashSet<usize>,
+}
+
+impl PageReader<'_> {
+    /// Returns the type of the b-tree page and its bytes, or `None` if it cannot be read
+    fn read(&self, page: usize) -> Option<(u8, Vec<u8>)> {
+        let bytes = self.conn.page_bytes(page).ok()?;
+        let page_type = *bytes.get(header_offset(page))?;
+        Some((page_type, bytes))
+    }
+
+    /// Collects the rows of the table b-tree rooted at `root`, in rowid order
+    fn walk(&mut self, root: usize) -> Vec<RecoveredRow> {
+        let mut rows = Vec::new();
+        let mut stack = vec![root];
+        while let Some(page) = stack.pop() {
+            if page == 0 || page > self.page_count || !self.visited.insert(page) {
+                continue;
+            }
+            match self.read(page) {
+                Some((TABLE_LEAF, bytes)) => rows.extend(self.leaf_rows(page, &bytes)),
+                Some((TABLE_INTERIOR, bytes)) => {
+                    let offset = header_offset(page);
+                    let mut children = cell_offsets(&bytes, offset, 12)
+                        .filter_map(|cell| {
+                            bytes.get(cell..cell + 4).map(|_| read_u32(&bytes, cell))
+                        })
+                        .collect::<Vec<_>>();
+                    if let Some(right) = bytes.get(offset + 8..offset + 12) {
+                        children.push(u32::from_be_bytes(right.try_into().unwrap()));
+                    }
+                    stack.extend(children.into_iter().rev().map(|child| child as usize));
+                }
+                _ => {}
+            }
+        }
+        rows
+    }
+
+    /// Decodes the cells of a table leaf page, skipping the ones that are corrupted
+    fn leaf_rows(&mut self, page: usize, bytes: &[u8]) -> Vec<RecoveredRow> {
+        cell_offsets(bytes, header_offset(page), 8)
+            .filter_map(|cell| {
+                let (rowid, values) = self.leaf_cell(bytes, cell)?;
+                Some(RecoveredRow {
+                    page,
+                    rowid,
+                    values,
+                })
+            })
+            .collect()
+    }
+
+    /// Decodes a cell of a table leaf page, or returns `None` if it cannot be decoded, which
+    /// is also the case of every cell when the usable size of the pages is too small
+    fn leaf_cell(&mut self, bytes: &[u8], cell: usize) -> Option<(i64, Vec<Value>)> {
+        let (payload_size, n) = varint(bytes.get(cell..)?)?;
+        let (rowid, m) = varint(bytes.get(cell + n..)?)?;
+        let start = cell + n + m;
+        let payload_size = payload_size as usize;
+        let max_local = self.usable_size.checked_sub(35)?;
+        let min_local = (self.usable_size.checked_sub(12)? * 32 / 255).checked_sub(23)?;
+        let (overflows, local) =
+            payload_overflows(payload_size, max_local, min_local, self.usable_size);
+        if !overflows {
+            let payload = bytes.get(start..start + payload_size)?;
+            return Some((rowid as i64, read_record(payload)?));
+        }
+
+        // The cell keeps the start of the payload followed by the number of its first overflow
+        // page, and each overflow page starts with the number of the next one
+        let mut payload = bytes.get(start..start + local - 4)?.to_vec();
+        let mut next = read_u32(bytes.get(start..start + local)?, local - 4) as usize;
+        while payload.len() < payload_size {
+            if next == 0 || next > self.page_count || !self.visited.insert(next) {
+                return None;
+            }
+            let overflow = self.conn.page_bytes(next).ok()?;
+            let len = (payload_size - payload.len()).min(self.usable_size - 4);
+            payload.extend_from_slice(overflow.get(4..4 + len)?);
+            next = read_u32(&overflow, 0) as usize;
+        }
+        Some((rowid as i64, read_record(&payload)?))
+    }
+}
+
+/// Offset of the b-tree page header, which follows the database header on the first page
+fn header_offset(page: usize) -> usize {
+    if page == 1 {
+        100
+    } else {
+        0
+    }
+}
+
+/// Offsets of the cells of a b-tree page whose header is `header_size` bytes long
+fn cell_offsets(
+    bytes: &[u8],
+    offset: usize,
+    header_size: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    let cell_count = bytes
+        .get(offset + 3..offset + 5)
+        .map_or(0, |count| u16::from_be_bytes([count[0], count[1]]) as usize);
+    (0..cell_count).filter_map(move |i| {
+        let pointer = offset + header_size + 2 * i;
+        let cell = bytes.get(pointer..pointer + 2)?;
+        Some(u16::from_be_bytes([cell[0], cell[1]]) as usize)
+    })
+}
+
+/// [read_varint] that also fails instead of panicking when the buffer ends in the middle of
+/// the varint
+fn varint(buf: &[u8]) -> Option<(u64, usize)> {
+    if buf.len() < 9 && buf.iter().all(|b| b & 0x80 != 0) {
+        return None;
+    }
+    read_varint(buf).ok()
+}
+
+/// Decodes a record, or returns `None` if it is malformed. Text that is not valid UTF-8 is
+/// recovered with its invalid bytes replaced.
+fn read_record(payload: &[u8]) -> Option<Vec<Value>> {
+    let (header_size, mut pos) = varint(payload)?;
+    let header_size = header_size as usize;
+    if header_size < pos || header_size > payload.len() {
+        return None;
+    }
+    let mut serial_types = Vec::new();
+    while pos < header_size {
+        let (serial_type, n) = varint(&payload[pos..header_size])?;
+        serial_types.push(SerialType::try_from(serial_type).ok()?);
+        pos += n;
+    }
+    let mut values = Vec::with_capacity(serial_types.len());
+    for serial_type in serial_types {
+        let (value, n) = read_value(&payload[pos..], serial_type).ok()?;
+        values.push(match value {
+            RefValue::Text(text) => {
+                Value::build_text(String::from_utf8_lossy(text.value.to_slice()))
+            }
+            value => value.to_owned(),
+        });
+        pos += n;
+    }
+    Some(values)
+}
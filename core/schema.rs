@@ -1,4 +1,6 @@
+use crate::temp::{TEMP_SCHEMA_TABLE_NAME, TEMP_SCHEMA_TABLE_NAME_ALT};
 use crate::translate::collate::CollationSeq;
+use crate::translate::expr::{walk_expr, WalkControl};
 use crate::translate::plan::SelectPlan;
 use crate::{util::normalize_ident, Result};
 use crate::{LimboError, VirtualTable};
@@ -25,6 +27,10 @@ pub struct Schema {
     pub has_indexes: std::collections::HashSet<String>,
     pub indexes_enabled: bool,
     pub schema_version: u32,
+    /// Statistics gathered by `ANALYZE`, by table name
+    pub table_stats: HashMap<String, TableStats>,
+    /// table_name to list of triggers on the table, in the order they were created
+    pub triggers: HashMap<String, Vec<Arc<Trigger>>>,
 }
 
 impl Schema {
@@ -43,6 +49,8 @@ impl Schema {
             has_indexes,
             indexes_enabled,
             schema_version: 0,
+            table_stats: HashMap::new(),
+            triggers: HashMap::new(),
         }
     }
 
@@ -67,6 +75,8 @@ impl Schema {
         let name = normalize_ident(name);
         let name = if name.eq_ignore_ascii_case(SCHEMA_TABLE_NAME_ALT) {
             SCHEMA_TABLE_NAME
+        } else if name.eq_ignore_ascii_case(TEMP_SCHEMA_TABLE_NAME_ALT) {
+            TEMP_SCHEMA_TABLE_NAME
         } else {
             &name
         };
@@ -76,6 +86,7 @@ impl Schema {
     pub fn remove_table(&mut self, table_name: &str) {
         let name = normalize_ident(table_name);
         self.tables.remove(&name);
+        self.table_stats.remove(&name);
     }
 
     pub fn get_btree_table(&self, name: &str) -> Option<Rc<BTreeTable>> {
@@ -121,6 +132,9 @@ impl Schema {
             .get_mut(&name)
             .expect("Must have the index")
             .retain_mut(|other_idx| other_idx.name != idx.name);
+        if let Some(stats) = self.table_stats.get_mut(&name) {
+            stats.index_rows_per_key.remove(&idx.name);
+        }
     }
 
     pub fn table_has_indexes(&self, table_name: &str) -> bool {
@@ -134,6 +148,53 @@ impl Schema {
     pub fn indexes_enabled(&self) -> bool {
         self.indexes_enabled
     }
+
+    pub fn get_table_stats(&self, table_name: &str) -> Option<&TableStats> {
+        self.table_stats.get(&normalize_ident(table_name))
+    }
+
+    pub fn add_trigger(&mut self, trigger: Arc<Trigger>) {
+        let table_name = normalize_ident(&trigger.table_name);
+        self.triggers.entry(table_name).or_default().push(trigger)
+    }
+
+    pub fn get_triggers(&self, table_name: &str) -> &[Arc<Trigger>] {
+        let name = normalize_ident(table_name);
+        self.triggers
+            .get(&name)
+            .map_or_else(|| &[] as &[Arc<Trigger>], |v| v.as_slice())
+    }
+
+    pub fn get_trigger(&self, trigger_name: &str) -> Option<&Arc<Trigger>> {
+        let name = normalize_ident(trigger_name);
+        self.triggers
+            .values()
+            .flatten()
+            .find(|trigger| trigger.name == name)
+    }
+
+    pub fn remove_trigger(&mut self, trigger_name: &str) {
+        let name = normalize_ident(trigger_name);
+        for triggers in self.triggers.values_mut() {
+            triggers.retain(|trigger| trigger.name != name);
+        }
+        self.triggers.retain(|_, triggers| !triggers.is_empty());
+    }
+
+    pub fn remove_triggers_for_table(&mut self, table_name: &str) {
+        let name = normalize_ident(table_name);
+        self.triggers.remove(&name);
+    }
+}
+
+/// Statistics of a table stored in `sqlite_stat1` by `ANALYZE`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableStats {
+    /// Number of rows of the table
+    pub row_count: u64,
+    /// For each index of the table, the average number of rows that share the same values in
+    /// the first 1, 2, ... N columns of the index
+    pub index_rows_per_key: HashMap<String, Vec<u64>>,
 }
 
 #[derive(Clone, Debug)]
@@ -213,6 +274,43 @@ pub struct BTreeTable {
     pub has_rowid: bool,
     pub is_strict: bool,
     pub unique_sets: Option<Vec<Vec<(String, SortOrder)>>>,
+    pub foreign_keys: Vec<ForeignKey>,
+    /// Whether the INTEGER PRIMARY KEY is declared AUTOINCREMENT, in which case the largest
+    /// rowid ever used is kept in `sqlite_sequence` so that rowids are never reused
+    pub has_autoincrement: bool,
+    pub check_constraints: Vec<CheckConstraint>,
+}
+
+/// A `CHECK` constraint, declared on a column or on the table. A row written to the table must
+/// not make its expression false; NULL counts as passing.
+#[derive(Clone, Debug)]
+pub struct CheckConstraint {
+    /// Name given with `CONSTRAINT name`, if any
+    pub name: Option<String>,
+    pub expr: Expr,
+}
+
+impl CheckConstraint {
+    /// How the constraint is referred to in the error raised when it fails: its name, or its
+    /// expression when it is unnamed.
+    pub fn description(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.expr.to_string(),
+        }
+    }
+}
+
+/// A `FOREIGN KEY` constraint declared on a child table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForeignKey {
+    /// Columns of the child table that hold the reference
+    pub child_columns: Vec<String>,
+    /// Name of the referenced (parent) table
+    pub parent_table: String,
+    /// Referenced columns of the parent table.
+    /// Empty when the primary key of the parent is referenced implicitly.
+    pub parent_columns: Vec<String>,
 }
 
 impl BTreeTable {
@@ -243,6 +341,73 @@ impl BTreeTable {
             .find(|(_, column)| column.name.as_ref() == Some(&name))
     }
 
+    /// Whether any column of the table is a VIRTUAL generated column
+    pub fn has_virtual_columns(&self) -> bool {
+        self.columns.iter().any(|column| column.is_virtual())
+    }
+
+    /// Position in the record of the column at index `column`, or None for a VIRTUAL generated
+    /// column, which is not stored. E.g. for CREATE TABLE t(a, b AS (a + 1), c)
+    /// the column `c` is stored at position 1.
+    pub fn record_position(&self, column: usize) -> Option<usize> {
+        if self.columns[column].is_virtual() {
+            return None;
+        }
+        Some(
+            self.columns[..column]
+                .iter()
+                .filter(|column| !column.is_virtual())
+                .count(),
+        )
+    }
+
+    /// Indices of the generated columns, ordered so that each one comes after the generated
+    /// columns its expression references. Fails if the expressions reference each other in a loop.
+    pub fn generated_columns_order(&self) -> Result<Vec<usize>> {
+        fn visit(
+            table: &BTreeTable,
+            column: usize,
+            visiting: &mut [bool],
+            order: &mut Vec<usize>,
+        ) -> Result<()> {
+            let Some(generated) = &table.columns[column].generated else {
+                return Ok(());
+            };
+            if visiting[column] {
+                return Ok(());
+            }
+            visiting[column] = true;
+            let mut dependencies = vec![];
+            walk_expr(&generated.expr, &mut |expr: &Expr| -> Result<WalkControl> {
+                if let Expr::Id(id) = expr {
+                    if let Some((idx, _)) = table.get_column(&id.0) {
+                        dependencies.push(idx);
+                    }
+                }
+                Ok(WalkControl::Continue)
+            })?;
+            for dependency in dependencies {
+                // A dependency that is being visited but not ordered yet closes a loop
+                if visiting[dependency] && !order.contains(&dependency) {
+                    return Err(LimboError::ParseError(format!(
+                        "generated column loop on \"{}\"",
+                        table.columns[column].name.as_deref().unwrap_or_default()
+                    )));
+                }
+                visit(table, dependency, visiting, order)?;
+            }
+            order.push(column);
+            Ok(())
+        }
+
+        let mut visiting = vec![false; self.columns.len()];
+        let mut order = vec![];
+        for column in 0..self.columns.len() {
+            visit(self, column, &mut visiting, &mut order)?;
+        }
+        Ok(order)
+    }
+
     pub fn from_sql(sql: &str, root_page: usize) -> Result<BTreeTable> {
         let mut parser = Parser::new(sql.as_bytes());
         let cmd = parser.next()?;
@@ -278,6 +443,28 @@ impl BTreeTable {
                 sql.push_str(" DEFAULT ");
                 sql.push_str(&default.to_string());
             }
+
+            if let Some(generated) = &column.generated {
+                sql.push_str(&format!(
+                    " AS ({}) {}",
+                    generated.expr,
+                    if generated.stored {
+                        "STORED"
+                    } else {
+                        "VIRTUAL"
+                    }
+                ));
+            }
+        }
+        for fk in &self.foreign_keys {
+            sql.push_str(&format!(
+                ", FOREIGN KEY ({}) REFERENCES {}",
+                fk.child_columns.join(", "),
+                fk.parent_table
+            ));
+            if !fk.parent_columns.is_empty() {
+                sql.push_str(&format!("({})", fk.parent_columns.join(", ")));
+            }
         }
         sql.push(')');
         sql
@@ -343,7 +530,7 @@ impl Ord for UniqueColumnProps {
     }
 }
 
-fn create_table(
+pub(crate) fn create_table(
     tbl_name: QualifiedName,
     body: CreateTableBody,
     root_page: usize,
@@ -356,6 +543,9 @@ fn create_table(
     let is_strict: bool;
     // BtreeSet here to preserve order of inserted keys
     let mut unique_sets: Vec<BTreeSet<UniqueColumnProps>> = vec![];
+    let mut foreign_keys = vec![];
+    let mut has_autoincrement = false;
+    let mut check_constraints = vec![];
     match body {
         CreateTableBody::ColumnsAndConstraints {
             columns,
@@ -366,9 +556,12 @@ fn create_table(
             if let Some(constraints) = constraints {
                 for c in constraints {
                     if let turso_sqlite3_parser::ast::TableConstraint::PrimaryKey {
-                        columns, ..
+                        columns,
+                        auto_increment,
+                        ..
                     } = c.constraint
                     {
+                        has_autoincrement |= auto_increment;
                         for column in columns {
                             let col_name = match column.expr {
                                 Expr::Id(id) => normalize_ident(&id.0),
@@ -406,6 +599,24 @@ fn create_table(
                             })
                             .collect();
                         unique_sets.push(unique_set);
+                    } else if let turso_sqlite3_parser::ast::TableConstraint::ForeignKey {
+                        columns,
+                        clause,
+                        ..
+                    } = c.constraint
+                    {
+                        let child_columns = columns
+                            .iter()
+                            .map(|column| normalize_ident(&column.col_name.0))
+                            .collect();
+                        foreign_keys.push(foreign_key_from_clause(child_columns, &clause));
+                    } else if let turso_sqlite3_parser::ast::TableConstraint::Check(expr) =
+                        c.constraint
+                    {
+                        check_constraints.push(CheckConstraint {
+                            name: c.name.map(|name| normalize_ident(&name.0)),
+                            expr,
+                        });
                     }
                 }
             }
@@ -464,13 +675,16 @@ fn create_table(
                 let mut order = SortOrder::Asc;
                 let mut unique = false;
                 let mut collation = None;
+                let mut generated = None;
                 for c_def in &col_def.constraints {
                     match &c_def.constraint {
                         turso_sqlite3_parser::ast::ColumnConstraint::PrimaryKey {
                             order: o,
+                            auto_increment,
                             ..
                         } => {
                             primary_key = true;
+                            has_autoincrement |= *auto_increment;
                             if let Some(o) = o {
                                 order = *o;
                             }
@@ -491,6 +705,23 @@ fn create_table(
                         turso_sqlite3_parser::ast::ColumnConstraint::Collate { collation_name } => {
                             collation = Some(CollationSeq::new(collation_name.0.as_str())?);
                         }
+                        turso_sqlite3_parser::ast::ColumnConstraint::Generated { expr, typ } => {
+                            generated = Some(Generated::new(expr, typ.as_ref())?);
+                        }
+                        turso_sqlite3_parser::ast::ColumnConstraint::ForeignKey {
+                            clause, ..
+                        } => {
+                            foreign_keys.push(foreign_key_from_clause(
+                                vec![normalize_ident(&name)],
+                                clause,
+                            ));
+                        }
+                        turso_sqlite3_parser::ast::ColumnConstraint::Check(expr) => {
+                            check_constraints.push(CheckConstraint {
+                                name: c_def.name.as_ref().map(|name| normalize_ident(&name.0)),
+                                expr: expr.clone(),
+                            });
+                        }
                         // Collate
                         _ => {}
                     }
@@ -505,6 +736,19 @@ fn create_table(
                     primary_key = true;
                 }
 
+                if generated.is_some() {
+                    if primary_key {
+                        return Err(LimboError::ParseError(
+                            "generated columns cannot be part of the PRIMARY KEY".to_string(),
+                        ));
+                    }
+                    if default.is_some() {
+                        return Err(LimboError::ParseError(
+                            "cannot use DEFAULT on a generated column".to_string(),
+                        ));
+                    }
+                }
+
                 cols.push(Column {
                     name: Some(normalize_ident(&name)),
                     ty,
@@ -515,10 +759,37 @@ fn create_table(
                     default,
                     unique,
                     collation,
+                    generated,
                 });
             }
             if options.contains(TableOptions::WITHOUT_ROWID) {
                 has_rowid = false;
+                if has_autoincrement {
+                    return Err(LimboError::ParseError(
+                        "AUTOINCREMENT not allowed on WITHOUT ROWID tables".to_string(),
+                    ));
+                }
+            }
+            if cols.iter().all(|col| col.generated.is_some()) {
+                return Err(LimboError::ParseError(
+                    "must have at least one non-generated column".to_string(),
+                ));
+            }
+            // UNIQUE constraints are backed by an index, which can't be built on a column that is
+            // not stored in the record
+            let unique_virtual_column = cols.iter().find(|col| {
+                col.is_virtual()
+                    && (col.unique
+                        || unique_sets.iter().any(|set| {
+                            set.iter()
+                                .any(|props| col.name.as_ref() == Some(&props.column_name))
+                        }))
+            });
+            if let Some(col) = unique_virtual_column {
+                return Err(LimboError::ParseError(format!(
+                    "UNIQUE constraints on VIRTUAL generated column '{}' are not supported",
+                    col.name.as_deref().unwrap_or_default()
+                )));
             }
         }
         CreateTableBody::AsSelect(_) => todo!(),
@@ -530,6 +801,11 @@ fn create_table(
             col.is_rowid_alias = false;
         }
     }
+    if has_autoincrement && !cols.iter().any(|col| col.is_rowid_alias) {
+        return Err(LimboError::ParseError(
+            "AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY".to_string(),
+        ));
+    }
     Ok(BTreeTable {
         root_page,
         name: table_name,
@@ -553,9 +829,32 @@ fn create_table(
                     .collect(),
             )
         },
+        foreign_keys,
+        has_autoincrement,
+        check_constraints,
     })
 }
 
+fn foreign_key_from_clause(
+    child_columns: Vec<String>,
+    clause: &turso_sqlite3_parser::ast::ForeignKeyClause,
+) -> ForeignKey {
+    ForeignKey {
+        child_columns,
+        parent_table: normalize_ident(&clause.tbl_name.0),
+        parent_columns: clause
+            .columns
+            .as_ref()
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|column| normalize_ident(&column.col_name.0))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
 pub fn _build_pseudo_table(columns: &[ResultColumn]) -> PseudoCursorType {
     let table = PseudoCursorType::new();
     for column in columns {
@@ -586,12 +885,48 @@ pub struct Column {
     pub default: Option<Expr>,
     pub unique: bool,
     pub collation: Option<CollationSeq>,
+    /// `GENERATED ALWAYS AS (expr)` clause of the column, if any
+    pub generated: Option<Generated>,
 }
 
 impl Column {
     pub fn affinity(&self) -> Affinity {
         affinity(&self.ty_str)
     }
+
+    /// Whether the column is a VIRTUAL generated column, which is not stored in the record
+    pub fn is_virtual(&self) -> bool {
+        self.generated.as_ref().is_some_and(|g| !g.stored)
+    }
+}
+
+/// Expression of a generated column. STORED columns are computed when the row is written and
+/// kept in the record like any other column, VIRTUAL ones are left out of the record and
+/// computed every time they are read.
+#[derive(Debug, Clone)]
+pub struct Generated {
+    pub expr: Expr,
+    pub stored: bool,
+}
+
+impl Generated {
+    pub fn new(expr: &Expr, typ: Option<&ast::Id>) -> Result<Self> {
+        let stored = match typ {
+            None => false,
+            Some(typ) if typ.0.eq_ignore_ascii_case("VIRTUAL") => false,
+            Some(typ) if typ.0.eq_ignore_ascii_case("STORED") => true,
+            Some(typ) => {
+                return Err(LimboError::ParseError(format!(
+                    "unknown generated column type: {}",
+                    typ.0
+                )))
+            }
+        };
+        Ok(Self {
+            expr: expr.clone(),
+            stored,
+        })
+    }
 }
 
 // TODO: This might replace some of util::columns_from_create_table_body
@@ -604,6 +939,7 @@ impl From<ColumnDefinition> for Column {
         let mut primary_key = false;
         let mut unique = false;
         let mut collation = None;
+        let mut generated = None;
 
         for ast::NamedColumnConstraint { constraint, .. } in value.constraints {
             match constraint {
@@ -619,6 +955,9 @@ impl From<ColumnDefinition> for Column {
                             .expect("collation should have been set correctly in create table"),
                     );
                 }
+                ast::ColumnConstraint::Generated { expr, typ } => {
+                    generated = Generated::new(&expr, typ.as_ref()).ok();
+                }
                 _ => {}
             };
         }
@@ -664,6 +1003,7 @@ impl From<ColumnDefinition> for Column {
             is_rowid_alias: primary_key && matches!(ty, Type::Integer),
             unique,
             collation,
+            generated,
         }
     }
 }
@@ -880,6 +1220,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             },
             Column {
                 name: Some("name".to_string()),
@@ -891,6 +1232,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             },
             Column {
                 name: Some("tbl_name".to_string()),
@@ -902,6 +1244,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             },
             Column {
                 name: Some("rootpage".to_string()),
@@ -913,6 +1256,7 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             },
             Column {
                 name: Some("sql".to_string()),
@@ -924,9 +1268,13 @@ pub fn sqlite_schema_table() -> BTreeTable {
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             },
         ],
         unique_sets: None,
+        foreign_keys: vec![],
+        has_autoincrement: false,
+        check_constraints: vec![],
     }
 }
 
@@ -1200,6 +1548,50 @@ impl Index {
     }
 }
 
+/// A row-level trigger, whose commands run for each row of its table changed by the event
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    pub name: String,
+    pub table_name: String,
+    pub time: ast::TriggerTime,
+    pub event: ast::TriggerEvent,
+    /// Condition on the `NEW` and `OLD` rows for the commands to run
+    pub when_clause: Option<Expr>,
+    pub commands: Vec<ast::TriggerCmd>,
+}
+
+impl Trigger {
+    pub fn from_sql(sql: &str) -> Result<Trigger> {
+        let mut parser = Parser::new(sql.as_bytes());
+        let cmd = parser.next()?;
+        match cmd {
+            Some(Cmd::Stmt(Stmt::CreateTrigger(trigger))) => {
+                let ast::CreateTrigger {
+                    trigger_name,
+                    time,
+                    event,
+                    tbl_name,
+                    when_clause,
+                    commands,
+                    ..
+                } = *trigger;
+                Ok(Trigger {
+                    name: normalize_ident(&trigger_name.name.0),
+                    table_name: normalize_ident(&tbl_name.name.0),
+                    time: time.unwrap_or(ast::TriggerTime::Before),
+                    event,
+                    when_clause,
+                    commands,
+                })
+            }
+            _ => Err(LimboError::InternalError(format!(
+                "Expected create trigger statement: {}",
+                sql
+            ))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1221,6 +1613,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_has_autoincrement() -> Result<()> {
+        let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY AUTOINCREMENT, b TEXT);"#;
+        let table = BTreeTable::from_sql(sql, 0)?;
+        assert!(table.has_autoincrement, "has_autoincrement should be set");
+        let sql = r#"CREATE TABLE t1 (a INTEGER, b TEXT, PRIMARY KEY (a AUTOINCREMENT));"#;
+        let table = BTreeTable::from_sql(sql, 0)?;
+        assert!(table.has_autoincrement, "has_autoincrement should be set");
+        let sql = r#"CREATE TABLE t1 (a INTEGER PRIMARY KEY, b TEXT);"#;
+        let table = BTreeTable::from_sql(sql, 0)?;
+        assert!(
+            !table.has_autoincrement,
+            "has_autoincrement should not be set"
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_autoincrement_not_rowid_alias() {
+        let sql = r#"CREATE TABLE t1 (a INT PRIMARY KEY AUTOINCREMENT, b TEXT);"#;
+        assert!(matches!(
+            BTreeTable::from_sql(sql, 0),
+            Err(LimboError::ParseError(msg)) if msg.contains("INTEGER PRIMARY KEY")
+        ));
+    }
+
     #[test]
     pub fn test_column_is_rowid_alias_single_text() -> Result<()> {
         let sql = r#"CREATE TABLE t1 (a TEXT PRIMARY KEY, b TEXT);"#;
@@ -1573,8 +1991,12 @@ mod tests {
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             }],
             unique_sets: None,
+            foreign_keys: vec![],
+            has_autoincrement: false,
+            check_constraints: vec![],
         };
 
         let _result = Index::automatic_from_primary_key_and_unique(
@@ -19,7 +19,13 @@ impl BufferPool {
     }
 
     pub fn set_page_size(&self, page_size: usize) {
-        self.page_size.store(page_size, Ordering::Relaxed);
+        if self.page_size.swap(page_size, Ordering::Relaxed) != page_size {
+            self.free_buffers.lock().clear();
+        }
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size.load(Ordering::Relaxed)
     }
 
     pub fn get(&self) -> BufferData {
@@ -28,7 +34,10 @@ impl BufferPool {
     }
 
     pub fn put(&self, buffer: BufferData) {
-        self.free_buffers.lock().push(buffer);
+        // Buffers handed out before a page size change are too small or too large to reuse
+        if buffer.len() == self.page_size() {
+            self.free_buffers.lock().push(buffer);
+        }
     }
 }
 
@@ -18,6 +18,7 @@ pub trait DatabaseStorage: Send + Sync {
     ) -> Result<()>;
     fn sync(&self, c: Completion) -> Result<()>;
     fn size(&self) -> Result<u64>;
+    fn truncate(&self, len: u64) -> Result<()>;
 }
 
 #[cfg(feature = "fs")]
@@ -68,6 +69,10 @@ impl DatabaseStorage for DatabaseFile {
     fn size(&self) -> Result<u64> {
         self.file.size()
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        self.file.truncate(len)
+    }
 }
 
 #[cfg(feature = "fs")]
@@ -123,6 +128,10 @@ impl DatabaseStorage for FileMemoryStorage {
     fn size(&self) -> Result<u64> {
         self.file.size()
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        self.file.truncate(len)
+    }
 }
 
 impl FileMemoryStorage {
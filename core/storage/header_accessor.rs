@@ -2,7 +2,7 @@ use crate::{
     storage::{
         self,
         pager::{PageRef, Pager},
-        sqlite3_ondisk::DATABASE_HEADER_PAGE_ID,
+        sqlite3_ondisk::{DatabaseHeader, DATABASE_HEADER_PAGE_ID},
     },
     LimboError, Result,
 };
@@ -47,6 +47,46 @@ fn get_header_page(pager: &Pager) -> Result<PageRef> {
     Ok(page)
 }
 
+/// Reads the whole 100-byte database header from page 1.
+pub fn get_database_header(pager: &Pager) -> Result<DatabaseHeader> {
+    let page = get_header_page(pager)?;
+    let page_inner = page.get();
+    let page_content = page_inner.contents.as_ref().unwrap();
+    let buf = page_content.buffer.borrow();
+    let buf = buf.as_slice();
+    let be_u32 = |offset: usize| u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap());
+    let be_i32 = |offset: usize| i32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap());
+    Ok(DatabaseHeader {
+        magic: buf[0..16].try_into().unwrap(),
+        page_size: u16::from_be_bytes(
+            buf[HEADER_OFFSET_PAGE_SIZE..HEADER_OFFSET_PAGE_SIZE + 2]
+                .try_into()
+                .unwrap(),
+        ),
+        write_version: buf[HEADER_OFFSET_WRITE_VERSION],
+        read_version: buf[HEADER_OFFSET_READ_VERSION],
+        reserved_space: buf[HEADER_OFFSET_RESERVED_SPACE],
+        max_embed_frac: buf[HEADER_OFFSET_MAX_EMBED_FRAC],
+        min_embed_frac: buf[HEADER_OFFSET_MIN_EMBED_FRAC],
+        min_leaf_frac: buf[HEADER_OFFSET_MIN_LEAF_FRAC],
+        change_counter: be_u32(HEADER_OFFSET_CHANGE_COUNTER),
+        database_size: be_u32(HEADER_OFFSET_DATABASE_SIZE),
+        freelist_trunk_page: be_u32(HEADER_OFFSET_FREELIST_TRUNK_PAGE),
+        freelist_pages: be_u32(HEADER_OFFSET_FREELIST_PAGES),
+        schema_cookie: be_u32(HEADER_OFFSET_SCHEMA_COOKIE),
+        schema_format: be_u32(HEADER_OFFSET_SCHEMA_FORMAT),
+        default_page_cache_size: be_i32(HEADER_OFFSET_DEFAULT_PAGE_CACHE_SIZE),
+        vacuum_mode_largest_root_page: be_u32(HEADER_OFFSET_VACUUM_MODE_LARGEST_ROOT_PAGE),
+        text_encoding: be_u32(HEADER_OFFSET_TEXT_ENCODING),
+        user_version: be_i32(HEADER_OFFSET_USER_VERSION),
+        incremental_vacuum_enabled: be_u32(HEADER_OFFSET_INCREMENTAL_VACUUM_ENABLED),
+        application_id: be_u32(HEADER_OFFSET_APPLICATION_ID),
+        reserved_for_expansion: buf[72..92].try_into().unwrap(),
+        version_valid_for: be_u32(HEADER_OFFSET_VERSION_VALID_FOR),
+        version_number: be_u32(HEADER_OFFSET_VERSION_NUMBER),
+    })
+}
+
 // Helper to get a writable reference to the header page and mark it dirty.
 fn get_header_page_for_write(pager: &Pager) -> Result<PageRef> {
     if pager.is_empty.load(Ordering::SeqCst) < 2 {
@@ -166,6 +166,10 @@ impl DumbLruPageCache {
         Some(page)
     }
 
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     // To match SQLite behavior, just set capacity and try to shrink as much as possible.
     // In case of failure, the caller should request further evictions (e.g. after I/O).
     pub fn resize(&mut self, capacity: usize) -> CacheResizeResult {
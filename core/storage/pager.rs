@@ -9,8 +9,8 @@ use crate::types::CursorResult;
 use crate::{Buffer, Connection, LimboError, Result};
 use crate::{Completion, WalFile};
 use parking_lot::RwLock;
-use std::cell::{OnceCell, RefCell, UnsafeCell};
-use std::collections::HashSet;
+use std::cell::{Cell, OnceCell, RefCell, UnsafeCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
@@ -18,7 +18,9 @@ use tracing::{trace, Level};
 
 use super::btree::{btree_init_page, BTreePage};
 use super::page_cache::{CacheError, CacheResizeResult, DumbLruPageCache, PageCacheKey};
-use super::sqlite3_ondisk::{begin_write_btree_page, DATABASE_HEADER_SIZE};
+use super::sqlite3_ondisk::{
+    begin_write_btree_page, DATABASE_HEADER_PAGE_ID, DATABASE_HEADER_SIZE,
+};
 use super::wal::{CheckpointMode, CheckpointStatus};
 
 #[cfg(not(feature = "omit_autovacuum"))]
@@ -225,8 +227,26 @@ pub struct Pager {
     /// Cache page_size and reserved_space at Pager init and reuse for subsequent
     /// `usable_space` calls. TODO: Invalidate reserved_space when we add the functionality
     /// to change it.
-    page_size: OnceCell<u16>,
+    page_size: OnceCell<u32>,
     reserved_space: OnceCell<u8>,
+    /// Page cache and I/O counters accumulated since the pager was created.
+    stats: Cell<PagerStats>,
+}
+
+/// Page cache and I/O statistics for a single pager.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PagerStats {
+    /// Number of page lookups served from the page cache.
+    pub cache_hits: u64,
+    /// Number of page lookups that had to go to the WAL or the database file, each of which
+    /// reads the page from there.
+    pub cache_misses: u64,
+    /// Number of pages written to the WAL.
+    pub pages_written: u64,
+    /// Number of pages currently held in the page cache.
+    pub cached_pages: usize,
+    /// Bytes of page data currently held in the page cache.
+    pub cache_memory: usize,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -293,6 +313,7 @@ impl Pager {
             allocate_page1_state,
             page_size: OnceCell::new(),
             reserved_space: OnceCell::new(),
+            stats: Cell::new(PagerStats::default()),
         })
     }
 
@@ -308,13 +329,53 @@ impl Pager {
         *self.auto_vacuum_mode.borrow_mut() = mode;
     }
 
+    /// The size of the database pages. Until page 1 is allocated this is the size the
+    /// database will be created with.
+    pub fn get_page_size(&self) -> u32 {
+        self.header_page_size()
+            .unwrap_or(self.buffer_pool.page_size() as u32)
+    }
+
+    /// The page size recorded in the database header
+    fn header_page_size(&self) -> Result<u32> {
+        match header_accessor::get_page_size(self)? {
+            // 65536 doesn't fit in the two header bytes and is stored as 1
+            1 => Ok(sqlite3_ondisk::MAX_PAGE_SIZE),
+            page_size => Ok(page_size as u32),
+        }
+    }
+
+    /// Sets the size of the pages of a database that has no pages yet. Like SQLite, invalid
+    /// sizes and changes made after page 1 was allocated are ignored.
+    pub fn set_page_size(&self, page_size: u32) -> Result<()> {
+        if self.is_empty.load(Ordering::SeqCst) != DB_STATE_UNITIALIZED
+            || !(sqlite3_ondisk::MIN_PAGE_SIZE..=sqlite3_ondisk::MAX_PAGE_SIZE).contains(&page_size)
+            || !page_size.is_power_of_two()
+        {
+            return Ok(());
+        }
+        self.wal.borrow_mut().set_page_size(page_size)?;
+        self.buffer_pool.set_page_size(page_size as usize);
+        Ok(())
+    }
+
+    /// Makes the pager read and write pages of the size recorded in the database header,
+    /// evicting the pages that were read with the previous size to get to the header.
+    pub fn adopt_header_page_size(&self) {
+        let page_size = self.get_page_size() as usize;
+        if page_size != self.buffer_pool.page_size() {
+            self.buffer_pool.set_page_size(page_size);
+            self.clear_page_cache();
+        }
+    }
+
     /// Retrieves the pointer map entry for a given database page.
     /// `target_page_num` (1-indexed) is the page whose entry is sought.
     /// Returns `Ok(None)` if the page is not supposed to have a ptrmap entry (e.g. header, or a ptrmap page itself).
     #[cfg(not(feature = "omit_autovacuum"))]
     pub fn ptrmap_get(&self, target_page_num: u32) -> Result<CursorResult<Option<PtrmapEntry>>> {
         tracing::trace!("ptrmap_get(page_idx = {})", target_page_num);
-        let configured_page_size = header_accessor::get_page_size(self)? as usize;
+        let configured_page_size = self.header_page_size()? as usize;
 
         if target_page_num < FIRST_PTRMAP_PAGE_NO
             || is_ptrmap_page(target_page_num, configured_page_size)
@@ -400,7 +461,7 @@ impl Pager {
             parent_page_no
         );
 
-        let page_size = header_accessor::get_page_size(self)? as usize;
+        let page_size = self.header_page_size()? as usize;
 
         if db_page_no_to_update < FIRST_PTRMAP_PAGE_NO
             || is_ptrmap_page(db_page_no_to_update, page_size)
@@ -501,10 +562,7 @@ impl Pager {
                     root_page_num += 1;
                     assert!(root_page_num >= FIRST_PTRMAP_PAGE_NO); //  can never be less than 2 because we have already incremented
 
-                    while is_ptrmap_page(
-                        root_page_num,
-                        header_accessor::get_page_size(self)? as usize,
-                    ) {
+                    while is_ptrmap_page(root_page_num, self.header_page_size()? as usize) {
                         root_page_num += 1;
                     }
                     assert!(root_page_num >= 3); //  the very first root page is page 3
@@ -579,7 +637,7 @@ impl Pager {
     pub fn usable_space(&self) -> usize {
         let page_size = *self
             .page_size
-            .get_or_init(|| header_accessor::get_page_size(self).unwrap_or_default());
+            .get_or_init(|| self.header_page_size().unwrap_or_default());
 
         let reserved_space = *self
             .reserved_space
@@ -671,6 +729,18 @@ impl Pager {
         Ok(())
     }
 
+    /// Reads a page and returns a copy of its raw bytes, waiting for the read to complete.
+    pub fn read_page_bytes(&self, page_idx: usize) -> Result<Vec<u8>> {
+        let page = self.read_page(page_idx)?;
+        while !page.is_loaded() || page.is_locked() {
+            self.io.run_once()?;
+        }
+        let page_inner = page.get();
+        let contents = page_inner.contents.as_ref().unwrap();
+        let buf = contents.buffer.borrow();
+        Ok(buf.as_slice().to_vec())
+    }
+
     /// Reads a page from the database.
     #[tracing::instrument(skip_all, level = Level::DEBUG)]
     pub fn read_page(&self, page_idx: usize) -> Result<PageRef, LimboError> {
@@ -679,8 +749,10 @@ impl Pager {
         let page_key = PageCacheKey::new(page_idx);
         if let Some(page) = page_cache.get(&page_key) {
             tracing::trace!("read_page(page_idx = {}) = cached", page_idx);
+            self.update_stats(|stats| stats.cache_hits += 1);
             return Ok(page.clone());
         }
+        self.update_stats(|stats| stats.cache_misses += 1);
         let page = Arc::new(Page::new(page_idx));
         page.set_locked();
 
@@ -739,6 +811,25 @@ impl Pager {
         page_cache.get(&page_key)
     }
 
+    /// Returns the page cache and I/O statistics gathered so far.
+    pub fn stats(&self) -> PagerStats {
+        let mut stats = self.stats.get();
+        stats.cached_pages = self.page_cache.read().len();
+        stats.cache_memory = stats.cached_pages * self.buffer_pool.page_size();
+        stats
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut PagerStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+
+    /// Returns the maximum number of pages the page cache holds.
+    pub fn page_cache_capacity(&self) -> usize {
+        self.page_cache.read().capacity()
+    }
+
     /// Changes the size of the page cache.
     pub fn change_page_cache_size(&self, capacity: usize) -> Result<CacheResizeResult> {
         let mut page_cache = self.page_cache.write();
@@ -751,6 +842,67 @@ impl Pager {
         dirty_pages.insert(page_id);
     }
 
+    /// Copies every page modified by the current write transaction, so that a savepoint
+    /// can later undo the changes made after it.
+    pub fn snapshot_dirty_pages(&self) -> PagerSnapshot {
+        let mut page_cache = self.page_cache.write();
+        let pages = self
+            .dirty_pages
+            .borrow()
+            .iter()
+            .filter_map(|&page_id| {
+                let page = page_cache.peek(&PageCacheKey::new(page_id), false)?;
+                let contents = page.get().contents.as_ref()?;
+                let bytes = contents.buffer.borrow().as_slice().to_vec();
+                Some((page_id, bytes))
+            })
+            .collect();
+        PagerSnapshot { pages }
+    }
+
+    /// Undoes the changes made since `snapshot` was taken. Pages that were already dirty get
+    /// their saved contents back, while pages dirtied afterwards are dropped from the cache so
+    /// that they are read again from the WAL or the database file.
+    pub fn restore_snapshot(&self, snapshot: &PagerSnapshot) -> Result<()> {
+        let saved = snapshot
+            .pages
+            .iter()
+            .map(|(page_id, bytes)| (*page_id, bytes))
+            .collect::<HashMap<_, _>>();
+        let mut page_cache = self.page_cache.write();
+        let mut dirty_pages = self.dirty_pages.borrow_mut();
+        for page_id in dirty_pages.clone() {
+            let key = PageCacheKey::new(page_id);
+            let page = page_cache.peek(&key, false);
+            match saved.get(&page_id) {
+                Some(bytes) => {
+                    if let Some(page) = page {
+                        let contents = page.get_contents();
+                        contents
+                            .buffer
+                            .borrow_mut()
+                            .as_mut_slice()
+                            .copy_from_slice(bytes);
+                        contents.overflow_cells.clear();
+                    }
+                }
+                None => {
+                    if let Some(page) = page {
+                        page.clear_dirty();
+                    }
+                    page_cache.delete(key).map_err(|e| {
+                        LimboError::InternalError(format!(
+                            "failed to evict page {} while rolling back to a savepoint: {:?}",
+                            page_id, e
+                        ))
+                    })?;
+                    dirty_pages.remove(&page_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn wal_frame_count(&self) -> Result<u64> {
         Ok(self.wal.borrow().get_max_frame_in_wal())
     }
@@ -781,6 +933,7 @@ impl Pager {
                             self.flush_info.borrow().in_flight_writes.clone(),
                         )?;
                         page.clear_dirty();
+                        self.update_stats(|stats| stats.pages_written += 1);
                     }
                     // This is okay assuming we use shared cache by default.
                     {
@@ -928,24 +1081,25 @@ impl Pager {
                 attempts += 1;
             }
         }
-        self.wal_checkpoint(wal_checkpoint_disabled)?;
+        self.wal_checkpoint(wal_checkpoint_disabled, CheckpointMode::Passive)?;
         Ok(())
     }
 
-    pub fn wal_checkpoint(&self, wal_checkpoint_disabled: bool) -> Result<CheckpointResult> {
+    pub fn wal_checkpoint(
+        &self,
+        wal_checkpoint_disabled: bool,
+        mode: CheckpointMode,
+    ) -> Result<CheckpointResult> {
         if wal_checkpoint_disabled {
-            return Ok(CheckpointResult {
-                num_wal_frames: 0,
-                num_checkpointed_frames: 0,
-            });
+            return Ok(CheckpointResult::new());
         }
         let checkpoint_result: CheckpointResult;
         loop {
-            match self.wal.borrow_mut().checkpoint(
-                self,
-                Rc::new(RefCell::new(0)),
-                CheckpointMode::Passive,
-            ) {
+            match self
+                .wal
+                .borrow_mut()
+                .checkpoint(self, Rc::new(RefCell::new(0)), mode)
+            {
                 Ok(CheckpointStatus::IO) => {
                     let _ = self.io.run_once();
                 }
@@ -1043,6 +1197,7 @@ impl Pager {
                 tracing::trace!("allocate_page1(Start)");
                 self.is_empty.store(DB_STATE_INITIALIZING, Ordering::SeqCst);
                 let mut default_header = DatabaseHeader::default();
+                default_header.update_page_size(self.buffer_pool.page_size() as u32);
                 default_header.database_size += 1;
                 let page = allocate_page(1, &self.buffer_pool, 0);
 
@@ -1124,7 +1279,7 @@ impl Pager {
             //  - autovacuum is enabled
             //  - the last page is a pointer map page
             if matches!(*self.auto_vacuum_mode.borrow(), AutoVacuumMode::Full)
-                && is_ptrmap_page(new_db_size, header_accessor::get_page_size(self)? as usize)
+                && is_ptrmap_page(new_db_size, self.header_page_size()? as usize)
             {
                 let page = allocate_page(new_db_size as usize, &self.buffer_pool, 0);
                 page.set_dirty();
@@ -1167,6 +1322,33 @@ impl Pager {
         }
     }
 
+    /// Replaces the contents of a page as part of the current write transaction, without
+    /// reading what the page held before. The page must not be in the page cache.
+    pub fn overwrite_page(&self, page_id: usize, bytes: &[u8]) -> Result<()> {
+        let offset = if page_id == DATABASE_HEADER_PAGE_ID {
+            DATABASE_HEADER_SIZE
+        } else {
+            0
+        };
+        let page = allocate_page(page_id, &self.buffer_pool, offset);
+        page.get_contents()
+            .buffer
+            .borrow_mut()
+            .as_mut_slice()
+            .copy_from_slice(bytes);
+        page.set_dirty();
+        self.add_dirty(page_id);
+
+        let mut cache = self.page_cache.write();
+        match cache.insert(PageCacheKey::new(page_id), page) {
+            Err(CacheError::Full) => Err(LimboError::CacheFull),
+            Err(_) => Err(LimboError::InternalError(
+                "Unknown error inserting page to cache".into(),
+            )),
+            Ok(_) => Ok(()),
+        }
+    }
+
     pub fn update_dirty_loaded_page_in_cache(
         &self,
         id: usize,
@@ -1190,7 +1372,7 @@ impl Pager {
     }
 
     pub fn usable_size(&self) -> usize {
-        let page_size = header_accessor::get_page_size(self).unwrap_or_default() as u32;
+        let page_size = self.header_page_size().unwrap_or_default();
         let reserved_space = header_accessor::get_reserved_space(self).unwrap_or_default() as u32;
         (page_size - reserved_space) as usize
     }
@@ -1225,6 +1407,12 @@ pub fn allocate_page(page_id: usize, buffer_pool: &Arc<BufferPool>, offset: usiz
     page
 }
 
+/// Contents of the dirty pages of a write transaction at the time a savepoint was opened.
+#[derive(Debug, Clone, Default)]
+pub struct PagerSnapshot {
+    pages: Vec<(usize, Vec<u8>)>,
+}
+
 #[derive(Debug)]
 pub struct CreateBTreeFlags(pub u8);
 impl CreateBTreeFlags {
@@ -81,7 +81,7 @@ pub const MIN_PAGE_CACHE_SIZE: usize = 10;
 pub const MIN_PAGE_SIZE: u32 = 512;
 
 /// The maximum page size in bytes.
-const MAX_PAGE_SIZE: u32 = 65536;
+pub const MAX_PAGE_SIZE: u32 = 65536;
 
 /// The default page size in bytes.
 pub const DEFAULT_PAGE_SIZE: u16 = 4096;
@@ -1487,7 +1487,7 @@ pub fn begin_write_wal_frame(
     io: &Arc<dyn File>,
     offset: usize,
     page: &PageRef,
-    page_size: u16,
+    page_size: u32,
     db_size: u32,
     write_counter: Rc<RefCell<usize>>,
     wal_header: &WalHeader,
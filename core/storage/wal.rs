@@ -44,6 +44,8 @@ pub struct CheckpointResult {
     pub num_wal_frames: u64,
     /// number of frames moved successfully from WAL to db file after checkpoint
     pub num_checkpointed_frames: u64,
+    /// true if a mode other than `Passive` couldn't do all of its work because of other readers
+    pub busy: bool,
 }
 
 impl Default for CheckpointResult {
@@ -57,6 +59,7 @@ impl CheckpointResult {
         Self {
             num_wal_frames: 0,
             num_checkpointed_frames: 0,
+            busy: false,
         }
     }
 }
@@ -250,6 +253,9 @@ pub trait Wal {
     fn get_max_frame(&self) -> u64;
     fn get_min_frame(&self) -> u64;
     fn rollback(&mut self) -> Result<()>;
+
+    /// Change the page size recorded in the header of a WAL without frames.
+    fn set_page_size(&mut self, page_size: u32) -> Result<()>;
 }
 
 /// A dummy WAL implementation that does nothing.
@@ -346,6 +352,10 @@ impl Wal for DummyWAL {
     fn rollback(&mut self) -> Result<()> {
         Ok(())
     }
+
+    fn set_page_size(&mut self, _page_size: u32) -> Result<()> {
+        Ok(())
+    }
 }
 
 // Syncing requires a state machine because we need to schedule a sync and then wait until it is
@@ -363,6 +373,8 @@ pub enum CheckpointState {
     WaitReadFrame,
     WritePage,
     WaitWritePage,
+    SyncDbFile,
+    WaitSyncDbFile,
     Done,
 }
 
@@ -392,6 +404,8 @@ struct OngoingCheckpoint {
     min_frame: u64,
     max_frame: u64,
     current_page: u64,
+    /// Set while the db file is synced, before a mode other than `Passive` restarts the log
+    db_syncing: Rc<RefCell<bool>>,
 }
 
 impl fmt::Debug for OngoingCheckpoint {
@@ -671,11 +685,15 @@ impl Wal for WalFile {
             let header = shared.wal_header.clone();
             let header = header.lock();
             let checksums = self.last_checksum;
+            // The first frame of a restarted log needs a header with its new salts.
+            if frame_id == 1 {
+                sqlite3_ondisk::begin_write_wal_header(&shared.file, &header)?;
+            }
             begin_write_wal_frame(
                 &shared.file,
                 offset,
                 &page,
-                header.page_size as u16,
+                header.page_size,
                 db_size,
                 write_counter,
                 &header,
@@ -712,10 +730,6 @@ impl Wal for WalFile {
         write_counter: Rc<RefCell<usize>>,
         mode: CheckpointMode,
     ) -> Result<CheckpointStatus> {
-        assert!(
-            matches!(mode, CheckpointMode::Passive),
-            "only passive mode supported for now"
-        );
         'checkpoint_loop: loop {
             let state = self.ongoing_checkpoint.state;
             tracing::debug!(?state);
@@ -762,7 +776,7 @@ impl Wal for WalFile {
                     let frame_cache = frame_cache.lock();
                     assert!(self.ongoing_checkpoint.current_page as usize <= pages_in_frames.len());
                     if self.ongoing_checkpoint.current_page as usize == pages_in_frames.len() {
-                        self.ongoing_checkpoint.state = CheckpointState::Done;
+                        self.ongoing_checkpoint.state = CheckpointState::SyncDbFile;
                         continue 'checkpoint_loop;
                     }
                     let page = pages_in_frames[self.ongoing_checkpoint.current_page as usize];
@@ -823,8 +837,31 @@ impl Wal for WalFile {
                         self.ongoing_checkpoint.current_page += 1;
                         self.ongoing_checkpoint.state = CheckpointState::ReadFrame;
                     } else {
+                        self.ongoing_checkpoint.state = CheckpointState::SyncDbFile;
+                    }
+                }
+                CheckpointState::SyncDbFile => {
+                    if *write_counter.borrow() > 0 {
+                        return Ok(CheckpointStatus::IO);
+                    }
+                    // The modes other than `Passive` may restart the log, discarding the frames
+                    // that were just backfilled, so the db file must hold them durably first.
+                    // `Passive` leaves the syncing of the db file to the caller.
+                    if matches!(mode, CheckpointMode::Passive) {
                         self.ongoing_checkpoint.state = CheckpointState::Done;
+                    } else {
+                        sqlite3_ondisk::begin_sync(
+                            pager.db_file.clone(),
+                            self.ongoing_checkpoint.db_syncing.clone(),
+                        )?;
+                        self.ongoing_checkpoint.state = CheckpointState::WaitSyncDbFile;
+                    }
+                }
+                CheckpointState::WaitSyncDbFile => {
+                    if *self.ongoing_checkpoint.db_syncing.borrow() {
+                        return Ok(CheckpointStatus::IO);
                     }
+                    self.ongoing_checkpoint.state = CheckpointState::Done;
                 }
                 CheckpointState::Done => {
                     if *write_counter.borrow() > 0 {
@@ -834,9 +871,10 @@ impl Wal for WalFile {
 
                     // Record two num pages fields to return as checkpoint result to caller.
                     // Ref: pnLog, pnCkpt on https://www.sqlite.org/c3ref/wal_checkpoint_v2.html
-                    let checkpoint_result = CheckpointResult {
+                    let mut checkpoint_result = CheckpointResult {
                         num_wal_frames: shared.max_frame.load(Ordering::SeqCst),
                         num_checkpointed_frames: self.ongoing_checkpoint.max_frame,
+                        busy: false,
                     };
                     let everything_backfilled = shared.max_frame.load(Ordering::SeqCst)
                         == self.ongoing_checkpoint.max_frame;
@@ -844,23 +882,28 @@ impl Wal for WalFile {
                         // TODO: Even in Passive mode, if everything was backfilled we should
                         // truncate and fsync the *db file*
 
-                        // To properly reset the *wal file* we will need restart and/or truncate mode.
-                        // Currently, it will grow the WAL file indefinetly, but don't resetting is better than breaking.
+                        // Passive leaves the *wal file* alone and lets it grow, the other modes
+                        // restart it so that the next writer appends from the first frame again.
                         // Check: https://github.com/sqlite/sqlite/blob/2bd9f69d40dd240c4122c6d02f1ff447e7b5c098/src/wal.c#L2193
                         if !matches!(mode, CheckpointMode::Passive) {
-                            // Here we know that we backfilled everything, therefore we can safely
-                            // reset the wal.
-                            shared.frame_cache.lock().clear();
-                            shared.pages_in_frames.lock().clear();
-                            shared.max_frame.store(0, Ordering::SeqCst);
-                            shared.nbackfills.store(0, Ordering::SeqCst);
-                            // TODO: if all frames were backfilled into the db file, calls fsync
-                            // TODO(pere): truncate wal file here.
+                            // Here we know that we backfilled everything and that the db file was
+                            // synced, therefore we can safely reset the wal.
+                            if self.restart_log(mode)? {
+                                if matches!(mode, CheckpointMode::Truncate) {
+                                    checkpoint_result.num_wal_frames = 0;
+                                    checkpoint_result.num_checkpointed_frames = 0;
+                                }
+                            } else {
+                                // Full only has to backfill everything, the others also have to
+                                // wait until no reader uses the log anymore.
+                                checkpoint_result.busy = !matches!(mode, CheckpointMode::Full);
+                            }
                         }
                     } else {
-                        shared
+                        self.get_shared()
                             .nbackfills
                             .store(self.ongoing_checkpoint.max_frame, Ordering::SeqCst);
+                        checkpoint_result.busy = !matches!(mode, CheckpointMode::Passive);
                     }
                     self.ongoing_checkpoint.state = CheckpointState::Start;
                     return Ok(CheckpointStatus::Done(checkpoint_result));
@@ -947,6 +990,29 @@ impl Wal for WalFile {
         shared.last_checksum = self.last_checksum;
         Ok(())
     }
+
+    fn set_page_size(&mut self, page_size: u32) -> Result<()> {
+        let header = {
+            let shared = self.get_shared();
+            assert_eq!(
+                shared.max_frame.load(Ordering::SeqCst),
+                0,
+                "page size of a WAL with frames cannot change"
+            );
+            let mut header = shared.wal_header.lock();
+            header.page_size = page_size;
+            let checksums = wal_header_checksum(&header);
+            header.checksum_1 = checksums.0;
+            header.checksum_2 = checksums.1;
+            sqlite3_ondisk::begin_write_wal_header(&shared.file, &header)?;
+            let header = *header;
+            shared.last_checksum = checksums;
+            header
+        };
+        self.last_checksum = (header.checksum_1, header.checksum_2);
+        self.header = header;
+        Ok(())
+    }
 }
 
 impl WalFile {
@@ -980,6 +1046,7 @@ impl WalFile {
                 min_frame: 0,
                 max_frame: 0,
                 current_page: 0,
+                db_syncing: Rc::new(RefCell::new(false)),
             },
             checkpoint_threshold: 1000,
             buffer_pool,
@@ -1004,6 +1071,66 @@ impl WalFile {
         offset as usize
     }
 
+    /// Restarts a log whose frames are all in the database file, so that the next writer appends
+    /// from the first frame again. Returns false, leaving the log as is, if another reader could
+    /// still be reading frames from it.
+    fn restart_log(&mut self, mode: CheckpointMode) -> Result<bool> {
+        let own_read_lock_index = self.max_frame_read_lock_index;
+        let shared = self.get_shared();
+        let mut acquired = Vec::with_capacity(shared.read_locks.len());
+        let mut busy = false;
+        for (index, lock) in shared.read_locks.iter_mut().enumerate() {
+            if lock.write() {
+                acquired.push(index);
+            } else if index != own_read_lock_index || lock.nreads.load(Ordering::SeqCst) != 1 {
+                // Unless we are the only holder of our own read lock, someone else is reading.
+                busy = true;
+                break;
+            }
+        }
+        if !busy {
+            for (index, lock) in shared.read_locks.iter_mut().enumerate() {
+                let mark = if index == 0 { 0 } else { READMARK_NOT_USED };
+                lock.value.store(mark, Ordering::SeqCst);
+            }
+            shared.frame_cache.lock().clear();
+            shared.pages_in_frames.lock().clear();
+            shared.max_frame.store(0, Ordering::SeqCst);
+            shared.nbackfills.store(0, Ordering::SeqCst);
+            // New salts invalidate the frames left over from the previous log
+            let header = {
+                let mut header = shared.wal_header.lock();
+                header.checkpoint_seq = header.checkpoint_seq.wrapping_add(1);
+                header.salt_1 = header.salt_1.wrapping_add(1);
+                header.salt_2 = self.io.generate_random_number() as u32;
+                let checksums = wal_header_checksum(&header);
+                header.checksum_1 = checksums.0;
+                header.checksum_2 = checksums.1;
+                *header
+            };
+            shared.last_checksum = (header.checksum_1, header.checksum_2);
+            if matches!(mode, CheckpointMode::Truncate) {
+                // The header is written again along with the first frame of the next writer
+                shared.file.truncate(0)?;
+            } else {
+                sqlite3_ondisk::begin_write_wal_header(&shared.file, &header)?;
+            }
+            self.header = header;
+        }
+        let shared = self.get_shared();
+        for index in acquired {
+            shared.read_locks[index].unlock();
+        }
+        if busy {
+            return Ok(false);
+        }
+        self.last_checksum = (self.header.checksum_1, self.header.checksum_2);
+        self.max_frame = 0;
+        self.min_frame = 0;
+        tracing::debug!("restart_log(mode={:?})", mode);
+        Ok(true)
+    }
+
     #[allow(clippy::mut_from_ref)]
     fn get_shared(&self) -> &mut WalFileShared {
         unsafe { self.shared.get().as_mut().unwrap() }
@@ -1056,17 +1183,7 @@ impl WalFileShared {
             checksum_1: 0,
             checksum_2: 0,
         };
-        let native = cfg!(target_endian = "big"); // if target_endian is
-                                                  // already big then we don't care but if isn't, header hasn't yet been
-                                                  // encoded to big endian, therefore we want to swap bytes to compute this
-                                                  // checksum.
-        let checksums = (0, 0);
-        let checksums = checksum_wal(
-            &wal_header.as_bytes()[..WAL_HEADER_SIZE - 2 * 4], // first 24 bytes
-            &wal_header,
-            checksums,
-            native, // this is false because we haven't encoded the wal header yet
-        );
+        let checksums = wal_header_checksum(&wal_header);
         wal_header.checksum_1 = checksums.0;
         wal_header.checksum_2 = checksums.1;
         sqlite3_ondisk::begin_write_wal_header(&file, &wal_header)?;
@@ -1103,3 +1220,18 @@ impl WalFileShared {
         self.wal_header.lock().page_size
     }
 }
+
+/// Checksum of the first 24 bytes of the WAL header, which starts the cumulative checksum of the
+/// frames that follow it.
+fn wal_header_checksum(header: &WalHeader) -> (u32, u32) {
+    let native = cfg!(target_endian = "big"); // if target_endian is
+                                              // already big then we don't care but if isn't, header hasn't yet been
+                                              // encoded to big endian, therefore we want to swap bytes to compute this
+                                              // checksum.
+    checksum_wal(
+        &header.as_bytes()[..WAL_HEADER_SIZE - 2 * 4], // first 24 bytes
+        header,
+        (0, 0),
+        native, // this is false because we haven't encoded the wal header yet
+    )
+}
@@ -0,0 +1,207 @@
+//! Tables created with `CREATE TEMP TABLE` live in a database private to the connection, which
+//! is kept in memory and dropped when the connection is closed. Its transactions begin when a
+//! statement first uses it and end along with the ones of the main database, so that temp tables
+//! are committed and rolled back with the rest of the transaction.
+//!
+//! Like in SQLite, the temp database has its own schema table, `sqlite_temp_schema`, and the
+//! names of its tables shadow the ones of the main database. The root pages of its b-trees are
+//! tagged with [TEMP_ROOT_PAGE_FLAG], so that the instructions that open a cursor on a b-tree
+//! find it in the pager of the temp database without having to know which database it is in.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+
+use parking_lot::RwLock;
+
+use crate::result::LimboResult;
+use crate::schema::{sqlite_schema_table, BTreeTable, Index, Schema, Table, TableStats, Trigger};
+use crate::storage::database::FileMemoryStorage;
+use crate::storage::page_cache::DumbLruPageCache;
+use crate::storage::pager::{Pager, PagerCacheflushStatus};
+use crate::storage::wal::{DummyWAL, WalFile, WalFileShared};
+use crate::storage::{self, header_accessor};
+use crate::types::CursorResult;
+use crate::{BufferPool, Connection, LimboError, OpenFlags, Result, IO};
+
+/// Bit set in the root pages of the b-trees of the temp database. SQLite limits databases to
+/// 2^31 - 2 pages, so it is never set in the root page of a b-tree of the main database.
+pub(crate) const TEMP_ROOT_PAGE_FLAG: usize = 1 << 31;
+
+pub(crate) const TEMP_SCHEMA_TABLE_NAME: &str = "sqlite_temp_schema";
+pub(crate) const TEMP_SCHEMA_TABLE_NAME_ALT: &str = "sqlite_temp_master";
+
+pub(crate) fn is_temp_root_page(root_page: usize) -> bool {
+    root_page & TEMP_ROOT_PAGE_FLAG != 0
+}
+
+pub(crate) struct TempDatabase {
+    pub(crate) pager: Rc<Pager>,
+    /// Tables and indexes of the temp database, `sqlite_temp_schema` included
+    pub(crate) schema: Schema,
+    /// The schema as of the last commit, which a rollback goes back to
+    committed_schema: Schema,
+    /// Whether a write transaction is open on the pager
+    in_tx: Cell<bool>,
+}
+
+impl TempDatabase {
+    pub(crate) fn open(conn: &Connection) -> Result<Self> {
+        let io: Arc<dyn IO> = conn.pager.io.get_memory_io();
+        let file = io.open_file("", OpenFlags::Create, true)?;
+        let db_file = Arc::new(FileMemoryStorage::new(file));
+        let buffer_pool = Arc::new(BufferPool::new(None));
+        let mut pager = Pager::new(
+            db_file,
+            Rc::new(RefCell::new(DummyWAL)),
+            io.clone(),
+            Arc::new(RwLock::new(DumbLruPageCache::default())),
+            buffer_pool.clone(),
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(Mutex::new(())),
+        )?;
+        let page_size = header_accessor::get_page_size(&pager)
+            .unwrap_or(storage::sqlite3_ondisk::DEFAULT_PAGE_SIZE) as usize;
+        buffer_pool.set_page_size(page_size);
+        // Like for the main database, committed pages are appended to a log and checkpointed
+        // into the backing file, so the page cache can evict them
+        let wal_file = io.open_file("", OpenFlags::Create, true)?;
+        let shared_wal = WalFileShared::new_shared(page_size as u32, &io, wal_file)?;
+        pager.set_wal(Rc::new(RefCell::new(WalFile::new(
+            io,
+            shared_wal,
+            buffer_pool,
+        ))));
+
+        let mut schema = Schema::new(conn.schema.borrow().indexes_enabled);
+        schema.tables.clear();
+        schema.add_btree_table(Rc::new(temp_schema_table()));
+        Ok(Self {
+            pager: Rc::new(pager),
+            committed_schema: schema.clone(),
+            schema,
+            in_tx: Cell::new(false),
+        })
+    }
+
+    /// Begins a write transaction on the temp database, unless the ongoing transaction of the
+    /// connection already did
+    pub(crate) fn begin_tx(&self) -> Result<()> {
+        if self.in_tx.get() {
+            return Ok(());
+        }
+        loop {
+            match self.pager.begin_read_tx()? {
+                CursorResult::IO => self.pager.io.run_once()?,
+                CursorResult::Ok(LimboResult::Busy) => return Err(LimboError::Busy),
+                CursorResult::Ok(LimboResult::Ok) => break,
+            }
+        }
+        loop {
+            match self.pager.begin_write_tx()? {
+                CursorResult::IO => self.pager.io.run_once()?,
+                CursorResult::Ok(LimboResult::Busy) => return Err(LimboError::Busy),
+                CursorResult::Ok(LimboResult::Ok) => break,
+            }
+        }
+        self.in_tx.set(true);
+        Ok(())
+    }
+
+    /// Commits or rolls back the transaction of the temp database, if one is open
+    pub(crate) fn end_tx(&mut self, conn: &Connection, rollback: bool) -> Result<()> {
+        if !self.in_tx.get() {
+            return Ok(());
+        }
+        if rollback {
+            self.pager.rollback(false, conn)?;
+            self.pager.end_tx(true, false, conn, false)?;
+            self.schema = self.committed_schema.clone();
+        } else {
+            loop {
+                match self.pager.end_tx(false, false, conn, false)? {
+                    PagerCacheflushStatus::IO => self.pager.io.run_once()?,
+                    PagerCacheflushStatus::Done(_) => break,
+                }
+            }
+            self.committed_schema = self.schema.clone();
+        }
+        self.in_tx.set(false);
+        Ok(())
+    }
+
+    pub(crate) fn in_tx(&self) -> bool {
+        self.in_tx.get()
+    }
+
+    /// Puts the tables of the temp database over the ones of the main database with the same
+    /// name in `main`, so that names are resolved against both. The indexes, statistics and
+    /// triggers of a shadowed table do not apply to the temp table. The returned entries put
+    /// `main` back as it was with [Self::unshadow].
+    pub(crate) fn shadow(&self, main: &mut Schema) -> Vec<ShadowedEntry> {
+        self.schema
+            .tables
+            .iter()
+            .map(|(name, table)| ShadowedEntry {
+                name: name.clone(),
+                table: main.tables.insert(name.clone(), table.clone()),
+                indexes: match self.schema.indexes.get(name) {
+                    Some(indexes) => main.indexes.insert(name.clone(), indexes.clone()),
+                    None => main.indexes.remove(name),
+                },
+                has_indexes: if self.schema.has_indexes.contains(name) {
+                    !main.has_indexes.insert(name.clone())
+                } else {
+                    main.has_indexes.remove(name)
+                },
+                table_stats: main.table_stats.remove(name),
+                triggers: main.triggers.remove(name),
+            })
+            .collect()
+    }
+
+    pub(crate) fn unshadow(&self, main: &mut Schema, shadowed: Vec<ShadowedEntry>) {
+        fn restore<T>(map: &mut HashMap<String, T>, name: &str, value: Option<T>) {
+            match value {
+                Some(value) => map.insert(name.to_string(), value),
+                None => map.remove(name),
+            };
+        }
+        for entry in shadowed {
+            restore(&mut main.tables, &entry.name, entry.table);
+            restore(&mut main.indexes, &entry.name, entry.indexes);
+            restore(&mut main.table_stats, &entry.name, entry.table_stats);
+            restore(&mut main.triggers, &entry.name, entry.triggers);
+            if entry.has_indexes {
+                main.has_indexes.insert(entry.name);
+            } else {
+                main.has_indexes.remove(&entry.name);
+            }
+        }
+    }
+}
+
+/// The entries of the main schema for the name of a temp table, while the temp table shadows them
+pub(crate) struct ShadowedEntry {
+    name: String,
+    table: Option<Arc<Table>>,
+    indexes: Option<Vec<Arc<Index>>>,
+    has_indexes: bool,
+    table_stats: Option<TableStats>,
+    triggers: Option<Vec<Arc<Trigger>>>,
+}
+
+/// The schema table of the temp database, which has the same columns as `sqlite_schema`
+pub(crate) fn temp_schema_table() -> BTreeTable {
+    let mut table = sqlite_schema_table();
+    table.name = TEMP_SCHEMA_TABLE_NAME.to_string();
+    table.root_page = TEMP_ROOT_PAGE_FLAG | 1;
+    table
+}
+
+/// Whether `table` is a table of the temp database
+pub(crate) fn is_temp_table(table: &Table) -> bool {
+    matches!(table, Table::BTree(table) if is_temp_root_page(table.root_page))
+}
@@ -4,12 +4,14 @@ use crate::{
     function::AggFunc,
     vdbe::{
         builder::ProgramBuilder,
-        insn::{IdxInsertFlags, Insn},
+        insn::{CmpInsFlags, IdxInsertFlags, Insn},
+        BranchOffset,
     },
     LimboError, Result,
 };
 
 use super::{
+    collate::get_collseq_from_expr,
     emitter::{Resolver, TranslateCtx},
     expr::translate_expr,
     plan::{Aggregate, Distinctness, SelectPlan, TableReferences},
@@ -93,6 +95,86 @@ pub fn handle_distinct(program: &mut ProgramBuilder, agg: &Aggregate, agg_arg_re
     });
 }
 
+/// Emits the bytecode deciding whether the bare columns of a group are read from the current row,
+/// when they follow the row of a `min()` or `max()` aggregate (see
+/// [SelectPlan::bare_columns_agg_index]). `reg_arg` holds the argument of the aggregate for the
+/// current row, and `reg_best` the best value seen so far in the group. Execution falls through
+/// when the row is the first of its group, holds a new minimum or maximum, or no non-NULL value was
+/// seen yet, and jumps to `label_skip` otherwise. Ties keep the first row, like SQLite.
+pub fn emit_bare_columns_row_check(
+    program: &mut ProgramBuilder,
+    referenced_tables: &TableReferences,
+    agg: &Aggregate,
+    reg_arg: usize,
+    reg_best: usize,
+    reg_group_started: usize,
+    label_skip: BranchOffset,
+) -> Result<()> {
+    let label_load = program.allocate_label();
+    program.emit_insn(Insn::IfNot {
+        reg: reg_group_started,
+        target_pc: label_load,
+        jump_if_null: true,
+    });
+    program.emit_insn(Insn::IsNull {
+        reg: reg_best,
+        target_pc: label_load,
+    });
+    program.emit_insn(Insn::IsNull {
+        reg: reg_arg,
+        target_pc: label_skip,
+    });
+    let collation = get_collseq_from_expr(&agg.args[0], referenced_tables)?;
+    let flags = CmpInsFlags::default();
+    match agg.func {
+        AggFunc::Min => program.emit_insn(Insn::Ge {
+            lhs: reg_arg,
+            rhs: reg_best,
+            target_pc: label_skip,
+            flags,
+            collation,
+        }),
+        AggFunc::Max => program.emit_insn(Insn::Le {
+            lhs: reg_arg,
+            rhs: reg_best,
+            target_pc: label_skip,
+            flags,
+            collation,
+        }),
+        _ => unreachable!("bare columns can only follow min() or max()"),
+    }
+    program.preassign_label_to_next_insn(label_load);
+    program.emit_insn(Insn::Copy {
+        src_reg: reg_arg,
+        dst_reg: reg_best,
+        amount: 0,
+    });
+    Ok(())
+}
+
+/// Emits the bytecode setting the `ORDER BY` keys of the row an aggregate is stepped with,
+/// which are in the registers starting at `key_start_reg`.
+pub fn emit_agg_order_key(
+    program: &mut ProgramBuilder,
+    referenced_tables: &TableReferences,
+    agg: &Aggregate,
+    target_register: usize,
+    key_start_reg: usize,
+) -> Result<()> {
+    let collations = agg
+        .order_by
+        .iter()
+        .map(|(expr, _)| Ok(get_collseq_from_expr(expr, referenced_tables)?.unwrap_or_default()))
+        .collect::<Result<Vec<_>>>()?;
+    program.emit_insn(Insn::AggOrderKey {
+        acc_reg: target_register,
+        key_start_reg,
+        order: agg.order_by.iter().map(|(_, order)| *order).collect(),
+        collations,
+    });
+    Ok(())
+}
+
 /// Emits the bytecode for processing an aggregate step.
 /// E.g. in `SELECT SUM(price) FROM t`, 'price' is evaluated for every row, and the result is added to the accumulator.
 ///
@@ -105,6 +187,25 @@ pub fn translate_aggregation_step(
     target_register: usize,
     resolver: &Resolver,
 ) -> Result<usize> {
+    if !agg.order_by.is_empty() {
+        let key_start_reg = program.alloc_registers(agg.order_by.len());
+        for (i, (expr, _)) in agg.order_by.iter().enumerate() {
+            translate_expr(
+                program,
+                Some(referenced_tables),
+                expr,
+                key_start_reg + i,
+                resolver,
+            )?;
+        }
+        emit_agg_order_key(
+            program,
+            referenced_tables,
+            agg,
+            target_register,
+            key_start_reg,
+        )?;
+    }
     let dest = match agg.func {
         AggFunc::Avg => {
             if agg.args.len() != 1 {
@@ -4,16 +4,20 @@ use turso_sqlite3_parser::{ast, lexer::sql::Parser};
 use crate::{
     function::{AlterTableFunc, Func},
     schema::{Column, Schema},
+    temp::is_temp_root_page,
     util::normalize_ident,
     vdbe::{
         builder::ProgramBuilder,
-        insn::{Cookie, Insn, RegisterOrLiteral},
+        insn::{CmpInsFlags, Cookie, InsertFlags, Insn, RegisterOrLiteral},
     },
     LimboError, Result, SymbolTable,
 };
 
 use super::{
-    emitter::TransactionMode, schema::SQLITE_TABLEID, update::translate_update_with_after,
+    collate::CollationSeq,
+    emitter::TransactionMode,
+    schema::{SQLITE_SEQUENCE_TABLEID, SQLITE_TABLEID},
+    update::translate_update_with_after,
 };
 
 pub fn translate_alter_table(
@@ -41,6 +45,23 @@ pub fn translate_alter_table(
         )));
     };
 
+    if is_temp_root_page(original_btree.root_page) {
+        crate::bail_parse_error!("ALTER TABLE on temp tables is not supported.");
+    }
+
+    // The expressions of generated columns reference the other columns by name, and VIRTUAL
+    // columns shift the position of the columns after them in the record
+    if matches!(
+        alter_table,
+        ast::AlterTableBody::DropColumn(_) | ast::AlterTableBody::RenameColumn { .. }
+    ) && original_btree
+        .columns
+        .iter()
+        .any(|column| column.generated.is_some())
+    {
+        crate::bail_parse_error!("ALTER TABLE on tables with generated columns is not supported.");
+    }
+
     let mut btree = (*original_btree).clone();
 
     Ok(match alter_table {
@@ -79,14 +100,44 @@ pub fn translate_alter_table(
                 )));
             }
 
+            if let Some(index) = schema.get_indices(&table_name).iter().find(|index| {
+                index
+                    .columns
+                    .iter()
+                    .any(|column| column.pos_in_table == dropped_index)
+            }) {
+                return Err(LimboError::ParseError(format!(
+                    "error in index {} after drop column: no such column: {column_name}",
+                    index.name
+                )));
+            }
+
+            if btree.foreign_keys.iter().any(|fk| {
+                fk.child_columns
+                    .iter()
+                    .any(|name| normalize_ident(name) == normalize_ident(&column_name))
+            }) {
+                return Err(LimboError::ParseError(format!(
+                    "error in table {table_name} after drop column: unknown column \"{column_name}\" in foreign key definition"
+                )));
+            }
+
             btree.columns.remove(dropped_index);
 
             let sql = btree.to_sql();
+            let mut escaped = String::with_capacity(sql.len());
+
+            for ch in sql.chars() {
+                match ch {
+                    '\'' => escaped.push_str("''"),
+                    ch => escaped.push(ch),
+                }
+            }
 
             let stmt = format!(
                 r#"
                     UPDATE {SQLITE_TABLEID}
-                    SET sql = '{sql}'
+                    SET sql = '{escaped}'
                     WHERE name = '{table_name}' COLLATE NOCASE AND type = 'table'
                 "#,
             );
@@ -158,6 +209,13 @@ pub fn translate_alter_table(
         ast::AlterTableBody::AddColumn(col_def) => {
             let column = Column::from(col_def);
 
+            // The rows already in the table would need the value of a STORED column
+            if column.generated.as_ref().is_some_and(|g| g.stored) {
+                return Err(LimboError::ParseError(
+                    "cannot add a STORED column".to_string(),
+                ));
+            }
+
             if let Some(default) = &column.default {
                 if !matches!(
                     default,
@@ -377,6 +435,50 @@ pub fn translate_alter_table(
                 });
             });
 
+            // The largest rowid used by an AUTOINCREMENT table follows it under its new name
+            if original_btree.has_autoincrement {
+                if let Some(sequence_table) = schema.get_btree_table(SQLITE_SEQUENCE_TABLEID) {
+                    let cursor_id = program.alloc_cursor_id(
+                        crate::vdbe::builder::CursorType::BTreeTable(sequence_table.clone()),
+                    );
+                    program.emit_insn(Insn::OpenWrite {
+                        cursor_id,
+                        root_page: RegisterOrLiteral::Literal(sequence_table.root_page),
+                        name: sequence_table.name.clone(),
+                    });
+                    let old_name_reg = program.emit_string8_new_reg(original_btree.name.clone());
+                    program.cursor_loop(cursor_id, |program, rowid| {
+                        let entry_reg = program.alloc_registers(2);
+                        program.emit_column(cursor_id, 0, entry_reg);
+                        let next_label = program.allocate_label();
+                        program.emit_insn(Insn::Ne {
+                            lhs: entry_reg,
+                            rhs: old_name_reg,
+                            target_pc: next_label,
+                            flags: CmpInsFlags::default(),
+                            collation: Some(CollationSeq::NoCase),
+                        });
+                        program.emit_string8(normalize_ident(&new_name), entry_reg);
+                        program.emit_column(cursor_id, 1, entry_reg + 1);
+                        let record = program.alloc_register();
+                        program.emit_insn(Insn::MakeRecord {
+                            start_reg: entry_reg,
+                            count: 2,
+                            dest_reg: record,
+                            index_name: None,
+                        });
+                        program.emit_insn(Insn::Insert {
+                            cursor: cursor_id,
+                            key_reg: rowid,
+                            record_reg: record,
+                            flag: InsertFlags::new().skip_last_rowid(),
+                            table_name: SQLITE_SEQUENCE_TABLEID.to_string(),
+                        });
+                        program.preassign_label_to_next_insn(next_label);
+                    });
+                }
+            }
+
             program.emit_insn(Insn::SetCookie {
                 db: 0,
                 cookie: Cookie::SchemaVersion,
@@ -0,0 +1,60 @@
+use turso_sqlite3_parser::ast::QualifiedName;
+
+use crate::{
+    bail_parse_error,
+    schema::Schema,
+    translate::{emitter::TransactionMode, ProgramBuilder, ProgramBuilderOpts},
+    util::normalize_ident,
+    vdbe::insn::Insn,
+    Result,
+};
+
+pub fn translate_analyze(
+    target: Option<QualifiedName>,
+    schema: &Schema,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    let (table_name, index_name) = match target {
+        None => (None, None),
+        // `ANALYZE main` analyzes every table of the database
+        Some(QualifiedName {
+            db_name: None,
+            name,
+            ..
+        }) if normalize_ident(&name.0) == "main" => (None, None),
+        Some(QualifiedName { db_name, name, .. }) => {
+            if let Some(db_name) = db_name {
+                if normalize_ident(&db_name.0) != "main" {
+                    bail_parse_error!("unknown database {}", db_name.0);
+                }
+            }
+            let normalized = normalize_ident(&name.0);
+            if let Some(table) = schema.get_btree_table(&normalized) {
+                (Some(table.name.clone()), None)
+            } else if let Some(index) = schema
+                .indexes
+                .values()
+                .flatten()
+                .find(|index| normalize_ident(&index.name) == normalized)
+            {
+                (Some(index.table_name.clone()), Some(index.name.clone()))
+            } else {
+                bail_parse_error!("no such table: {}", name.0);
+            }
+        }
+    };
+    program.extend(&ProgramBuilderOpts {
+        num_cursors: 0,
+        approx_num_insns: 0,
+        approx_num_labels: 0,
+    });
+    // ANALYZE writes sqlite_stat1 in its own write transaction, unless it runs in a transaction
+    // started by the user
+    program.emit_insn(Insn::Analyze {
+        db: 0,
+        table_name,
+        index_name,
+    });
+    program.epilogue(TransactionMode::None);
+    Ok(program)
+}
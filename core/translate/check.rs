@@ -0,0 +1,63 @@
+//! `CHECK` constraints, declared on a column or on the table with `CHECK (expr)`.
+//!
+//! The expression of each constraint is evaluated against every row written to the table, and
+//! the statement fails if it is false. A NULL result does not fail the constraint.
+
+use turso_sqlite3_parser::ast::TableInternalId;
+
+use crate::error::SQLITE_CONSTRAINT_CHECK;
+use crate::schema::BTreeTable;
+use crate::translate::emitter::Resolver;
+use crate::translate::expr::translate_expr;
+use crate::translate::generated::{bind_row_expr, row_column_exprs, row_resolver};
+use crate::vdbe::builder::ProgramBuilder;
+use crate::vdbe::insn::Insn;
+use crate::vdbe::BranchOffset;
+use crate::Result;
+
+/// Evaluates the CHECK constraints of `table` against a row being written to it. The values of
+/// the columns are in the registers starting at `columns_start_reg`, in table order, and the
+/// rowid is in `rowid_reg`.
+///
+/// A failing constraint halts the statement, unless `skip_row_label` is given, in which case the
+/// row is skipped by jumping to it (`ON CONFLICT IGNORE`).
+pub fn emit_check_constraints(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    columns_start_reg: usize,
+    rowid_reg: usize,
+    resolver: &Resolver,
+    skip_row_label: Option<BranchOffset>,
+) -> Result<()> {
+    if table.check_constraints.is_empty() {
+        return Ok(());
+    }
+    let table_ref_id = TableInternalId::default();
+    let columns = row_column_exprs(table, table_ref_id);
+    let row_resolver = row_resolver(resolver, table, &columns, columns_start_reg, rowid_reg);
+    for check in table.check_constraints.iter() {
+        let expr = bind_row_expr(table, &check.expr, table_ref_id)?;
+        let result_reg = program.alloc_register();
+        translate_expr(program, None, &expr, result_reg, &row_resolver)?;
+        if let Some(skip_row_label) = skip_row_label {
+            program.emit_insn(Insn::IfNot {
+                reg: result_reg,
+                target_pc: skip_row_label,
+                jump_if_null: false,
+            });
+            continue;
+        }
+        let check_ok_label = program.allocate_label();
+        program.emit_insn(Insn::If {
+            reg: result_reg,
+            target_pc: check_ok_label,
+            jump_if_null: true,
+        });
+        program.emit_insn(Insn::Halt {
+            err_code: SQLITE_CONSTRAINT_CHECK,
+            description: check.description(),
+        });
+        program.preassign_label_to_next_insn(check_ok_label);
+    }
+    Ok(())
+}
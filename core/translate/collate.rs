@@ -1,6 +1,9 @@
 use std::{cmp::Ordering, str::FromStr as _};
 
 use tracing::Level;
+use turso_sqlite3_parser::ast;
+
+use super::plan::TableReferences;
 
 // TODO: in the future allow user to define collation sequences
 // Will have to meddle with ffi for this
@@ -51,3 +54,27 @@ impl CollationSeq {
         lhs.trim_end().cmp(rhs.trim_end())
     }
 }
+
+/// Determines the collating sequence of an expression.
+/// An explicit `COLLATE` operator takes precedence, then the declared collation of a column reference.
+/// Any other expression uses the BINARY collating sequence.
+pub fn get_collseq_from_expr(
+    expr: &ast::Expr,
+    referenced_tables: &TableReferences,
+) -> crate::Result<Option<CollationSeq>> {
+    match expr {
+        ast::Expr::Collate(_, collation_name) => CollationSeq::new(collation_name).map(Some),
+        ast::Expr::Column { table, column, .. } => {
+            let Some(table) = referenced_tables.find_table_by_internal_id(*table) else {
+                crate::bail_parse_error!("table not found for column reference");
+            };
+
+            let Some(table_column) = table.get_column_at(*column) else {
+                crate::bail_parse_error!("column index out of bounds");
+            };
+
+            Ok(table_column.collation)
+        }
+        _ => Ok(Some(CollationSeq::default())),
+    }
+}
@@ -1,5 +1,7 @@
-use crate::schema::{Index, IndexColumn, Schema};
+use crate::schema::{Index, IndexColumn, PseudoCursorType, Schema};
+use crate::translate::collate::{get_collseq_from_expr, CollationSeq};
 use crate::translate::emitter::{emit_query, LimitCtx, TransactionMode, TranslateCtx};
+use crate::translate::order_by::sorter_insert;
 use crate::translate::plan::{Plan, QueryDestination, SelectPlan};
 use crate::vdbe::builder::{CursorType, ProgramBuilder};
 use crate::vdbe::insn::Insn;
@@ -7,7 +9,7 @@ use crate::vdbe::BranchOffset;
 use crate::SymbolTable;
 use std::sync::Arc;
 use tracing::instrument;
-use turso_sqlite3_parser::ast::{CompoundOperator, SortOrder};
+use turso_sqlite3_parser::ast::{self, CompoundOperator, SortOrder};
 
 use tracing::Level;
 
@@ -22,6 +24,7 @@ pub fn emit_program_for_compound_select(
         left: _left,
         right_most,
         limit,
+        order_by,
         ..
     } = &plan
     else {
@@ -39,6 +42,14 @@ pub fn emit_program_for_compound_select(
         }
     }
 
+    if order_by.is_some() {
+        emit_sorted_compound_select(program, plan, schema, syms)?;
+        program.epilogue(TransactionMode::Read);
+        program.result_columns = right_plan.result_columns;
+        program.table_references.extend(right_plan.table_references);
+        return Ok(());
+    }
+
     // Each subselect shares the same limit_ctx, because the LIMIT applies to the entire compound select,
     // not just a single subselect.
     let limit_ctx = limit.map(|limit| {
@@ -262,6 +273,207 @@ fn emit_compound_select(
     Ok(())
 }
 
+/// Emits a compound SELECT with an ORDER BY. The compound SELECT runs as a coroutine whose rows
+/// are all fed into a sorter, and the LIMIT is applied while reading the sorted rows back.
+fn emit_sorted_compound_select(
+    program: &mut ProgramBuilder,
+    plan: Plan,
+    schema: &Schema,
+    syms: &SymbolTable,
+) -> crate::Result<()> {
+    let Plan::CompoundSelect {
+        mut left,
+        mut right_most,
+        limit,
+        offset,
+        order_by: Some(order_by),
+    } = plan
+    else {
+        unreachable!()
+    };
+    let num_columns = right_most.result_columns.len();
+
+    // Without an explicit COLLATE, a term sorts with the collation of the column in the
+    // leftmost SELECT
+    let leftmost = &left.first().expect("compound select has a left part").0;
+    let mut key_columns = Vec::with_capacity(order_by.len());
+    let mut collations = Vec::with_capacity(order_by.len());
+    for (expr, _) in order_by.iter() {
+        let (column, collation) = compound_order_by_key(expr);
+        key_columns.push(column);
+        collations.push(match collation {
+            Some(collation) => Some(CollationSeq::new(collation)?),
+            None => get_collseq_from_expr(
+                &leftmost.result_columns[column].expr,
+                &leftmost.table_references,
+            )?,
+        });
+    }
+
+    // The rows are either returned or yielded to the query that contains this compound SELECT
+    let outer_yield_reg = match right_most.query_destination {
+        QueryDestination::CoroutineYield { yield_reg, .. } => Some(yield_reg),
+        _ => None,
+    };
+
+    // The result columns must directly follow the yield register, as the UNION and INTERSECT
+    // rows are read back into the registers after it
+    let yield_reg = program.alloc_register();
+    let reg_result_cols_start = program.alloc_registers(num_columns);
+    let coroutine_start_label = program.allocate_label();
+    let coroutine_end_label = program.allocate_label();
+    let query_destination = QueryDestination::CoroutineYield {
+        yield_reg,
+        coroutine_implementation_start: coroutine_start_label,
+    };
+    right_most.query_destination = query_destination.clone();
+    for (plan, _) in left.iter_mut() {
+        plan.query_destination = query_destination.clone();
+    }
+    program.emit_insn(Insn::InitCoroutine {
+        yield_reg,
+        jump_on_definition: coroutine_end_label,
+        start_offset: coroutine_start_label,
+    });
+    program.preassign_label_to_next_insn(coroutine_start_label);
+    let compound_select = Plan::CompoundSelect {
+        left,
+        right_most,
+        limit: None,
+        offset,
+        order_by: None,
+    };
+    emit_compound_select(
+        program,
+        compound_select,
+        schema,
+        syms,
+        None,
+        Some(yield_reg),
+        Some(reg_result_cols_start),
+    )?;
+    program.emit_insn(Insn::EndCoroutine { yield_reg });
+    program.preassign_label_to_next_insn(coroutine_end_label);
+
+    let sort_cursor = program.alloc_cursor_id(CursorType::Sorter);
+    program.emit_insn(Insn::SorterOpen {
+        cursor_id: sort_cursor,
+        columns: order_by.len(),
+        order: order_by.iter().map(|(_, direction)| *direction).collect(),
+        collations,
+    });
+
+    // Insert every row of the compound SELECT into the sorter, the sort keys first and then
+    // all the result columns
+    let label_insert_loop = program.allocate_label();
+    let label_insert_loop_end = program.allocate_label();
+    let sorter_column_count = order_by.len() + num_columns;
+    let reg_sorter_record = program.alloc_registers(sorter_column_count);
+    let reg_sorter_data = program.alloc_register();
+    program.preassign_label_to_next_insn(label_insert_loop);
+    program.emit_insn(Insn::Yield {
+        yield_reg,
+        end_offset: label_insert_loop_end,
+    });
+    for (i, column) in key_columns.iter().enumerate() {
+        program.emit_insn(Insn::Copy {
+            src_reg: reg_result_cols_start + column,
+            dst_reg: reg_sorter_record + i,
+            amount: 0,
+        });
+    }
+    program.emit_insn(Insn::Copy {
+        src_reg: reg_result_cols_start,
+        dst_reg: reg_sorter_record + order_by.len(),
+        amount: num_columns - 1,
+    });
+    sorter_insert(
+        program,
+        reg_sorter_record,
+        sorter_column_count,
+        sort_cursor,
+        reg_sorter_data,
+    );
+    program.emit_insn(Insn::Goto {
+        target_pc: label_insert_loop,
+    });
+    program.preassign_label_to_next_insn(label_insert_loop_end);
+
+    let reg_limit = limit.map(|limit| {
+        let reg = program.alloc_register();
+        program.emit_insn(Insn::Integer {
+            value: limit as i64,
+            dest: reg,
+        });
+        reg
+    });
+    let pseudo_cursor = program.alloc_cursor_id(CursorType::Pseudo(PseudoCursorType {
+        column_count: sorter_column_count,
+    }));
+    program.emit_insn(Insn::OpenPseudo {
+        cursor_id: pseudo_cursor,
+        content_reg: reg_sorter_data,
+        num_fields: sorter_column_count,
+    });
+    let label_sort_loop = program.allocate_label();
+    let label_sort_loop_end = program.allocate_label();
+    program.emit_insn(Insn::SorterSort {
+        cursor_id: sort_cursor,
+        pc_if_empty: label_sort_loop_end,
+    });
+    program.preassign_label_to_next_insn(label_sort_loop);
+    program.emit_insn(Insn::SorterData {
+        cursor_id: sort_cursor,
+        dest_reg: reg_sorter_data,
+        pseudo_cursor,
+    });
+    let reg_output_start = match outer_yield_reg {
+        Some(outer_yield_reg) => outer_yield_reg + 1,
+        None => program.alloc_registers(num_columns),
+    };
+    for i in 0..num_columns {
+        program.emit_column(pseudo_cursor, order_by.len() + i, reg_output_start + i);
+    }
+    match outer_yield_reg {
+        Some(outer_yield_reg) => program.emit_insn(Insn::Yield {
+            yield_reg: outer_yield_reg,
+            end_offset: BranchOffset::Offset(0),
+        }),
+        None => program.emit_insn(Insn::ResultRow {
+            start_reg: reg_output_start,
+            count: num_columns,
+        }),
+    }
+    if let Some(reg_limit) = reg_limit {
+        program.emit_insn(Insn::DecrJumpZero {
+            reg: reg_limit,
+            target_pc: label_sort_loop_end,
+        });
+    }
+    program.emit_insn(Insn::SorterNext {
+        cursor_id: sort_cursor,
+        pc_if_next: label_sort_loop,
+    });
+    program.preassign_label_to_next_insn(label_sort_loop_end);
+
+    Ok(())
+}
+
+/// Splits a term of the ORDER BY of a compound SELECT, as resolved by the planner, into the
+/// index of the result column it sorts by and its explicit collation, if any.
+fn compound_order_by_key(expr: &ast::Expr) -> (usize, Option<&str>) {
+    match expr {
+        ast::Expr::Collate(expr, collation) => (compound_order_by_key(expr).0, Some(collation)),
+        ast::Expr::Literal(ast::Literal::Numeric(num)) => (
+            num.parse::<usize>()
+                .expect("compound ORDER BY terms are column numbers")
+                - 1,
+            None,
+        ),
+        _ => unreachable!("compound ORDER BY terms are column numbers"),
+    }
+}
+
 // Creates an ephemeral index that will be used to deduplicate the results of any sub-selects
 fn create_dedupe_index(
     program: &mut ProgramBuilder,
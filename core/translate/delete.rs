@@ -1,18 +1,22 @@
 use crate::schema::Table;
 use crate::translate::emitter::emit_program;
-use crate::translate::optimizer::optimize_plan;
-use crate::translate::plan::{DeletePlan, Operation, Plan};
-use crate::translate::planner::{parse_limit, parse_where};
-use crate::vdbe::builder::{ProgramBuilder, ProgramBuilderOpts, TableRefIdCounter};
-use crate::{schema::Schema, Result, SymbolTable};
-use turso_sqlite3_parser::ast::{Expr, Limit, QualifiedName};
+use crate::translate::optimizer::{optimize_plan, optimize_select_plan};
+use crate::translate::plan::{DeletePlan, Operation, Plan, QueryDestination, SelectPlan};
+use crate::translate::planner::{bind_column_references, parse_limit, parse_where};
+use crate::vdbe::builder::{CursorType, ProgramBuilder, ProgramBuilderOpts, TableRefIdCounter};
+use crate::{bail_parse_error, schema::Schema, Result, SymbolTable};
+use turso_sqlite3_parser::ast::{Expr, Limit, QualifiedName, SortOrder, SortedColumn};
 
-use super::plan::{ColumnUsedMask, IterationDirection, JoinedTable, TableReferences};
+use super::plan::{
+    ColumnUsedMask, Distinctness, IterationDirection, JoinedTable, ResultSetColumn, TableReferences,
+};
+use super::update::ephemeral_rowid_table;
 
 pub fn translate_delete(
     schema: &Schema,
     tbl_name: &QualifiedName,
     where_clause: Option<Box<Expr>>,
+    order_by: Option<Vec<SortedColumn>>,
     limit: Option<Box<Limit>>,
     syms: &SymbolTable,
     mut program: ProgramBuilder,
@@ -28,8 +32,9 @@ pub fn translate_delete(
         schema,
         tbl_name,
         where_clause,
+        order_by,
         limit,
-        &mut program.table_reference_counter,
+        &mut program,
     )?;
     optimize_plan(&mut delete_plan, schema)?;
     let Plan::Delete(ref delete) = delete_plan else {
@@ -49,8 +54,9 @@ pub fn prepare_delete_plan(
     schema: &Schema,
     tbl_name: &QualifiedName,
     where_clause: Option<Box<Expr>>,
+    order_by: Option<Vec<SortedColumn>>,
     limit: Option<Box<Limit>>,
-    table_ref_counter: &mut TableRefIdCounter,
+    program: &mut ProgramBuilder,
 ) -> Result<Plan> {
     let table = match schema.get_table(tbl_name.name.0.as_str()) {
         Some(table) => table,
@@ -65,31 +71,103 @@ pub fn prepare_delete_plan(
     };
     let name = tbl_name.name.0.as_str().to_string();
     let indexes = schema.get_indices(table.get_name()).to_vec();
-    let joined_tables = vec![JoinedTable {
-        table,
-        identifier: name,
-        internal_id: table_ref_counter.next(),
-        op: Operation::Scan {
-            iter_dir: IterationDirection::Forwards,
-            index: None,
-        },
-        join_info: None,
-        col_used_mask: ColumnUsedMask::default(),
-    }];
-    let mut table_references = TableReferences::new(joined_tables, vec![]);
+
+    // Parse the LIMIT/OFFSET clause
+    let (resolved_limit, resolved_offset) = limit.map_or(Ok((None, None)), |l| parse_limit(&l))?;
+    if order_by.is_some() && resolved_limit.is_none() {
+        bail_parse_error!("ORDER BY without LIMIT on DELETE");
+    }
+
+    // Picking the rows to delete by an ORDER BY or an OFFSET needs the whole candidate set
+    // first, so their rowids are collected into an ephemeral table by a SELECT and the
+    // DELETE loop then iterates over that table.
+    let needs_ephemeral_plan =
+        order_by.is_some() || resolved_offset.is_some_and(|offset| offset > 0);
+    if needs_ephemeral_plan && table.virtual_table().is_some() {
+        bail_parse_error!("DELETE with ORDER BY or OFFSET is not supported on virtual tables");
+    }
+
+    let new_table_references = |table_ref_counter: &mut TableRefIdCounter| {
+        TableReferences::new(
+            vec![JoinedTable {
+                table: table.clone(),
+                identifier: name.clone(),
+                internal_id: table_ref_counter.next(),
+                op: Operation::Scan {
+                    iter_dir: IterationDirection::Forwards,
+                    index: None,
+                },
+                join_info: None,
+                col_used_mask: ColumnUsedMask::default(),
+                index_hint: None,
+            }],
+            vec![],
+        )
+    };
+    let mut table_references = new_table_references(&mut program.table_reference_counter);
 
     let mut where_predicates = vec![];
+    let ephemeral_plan = if needs_ephemeral_plan {
+        let mut select_table_references =
+            new_table_references(&mut program.table_reference_counter);
+        let internal_id = select_table_references.joined_tables()[0].internal_id;
 
-    // Parse the WHERE clause
-    parse_where(
-        where_clause.map(|e| *e),
-        &mut table_references,
-        None,
-        &mut where_predicates,
-    )?;
+        let mut select_where = vec![];
+        parse_where(
+            where_clause.map(|e| *e),
+            &mut select_table_references,
+            None,
+            &mut select_where,
+        )?;
 
-    // Parse the LIMIT/OFFSET clause
-    let (resolved_limit, resolved_offset) = limit.map_or(Ok((None, None)), |l| parse_limit(&l))?;
+        let order_by = order_by
+            .map(|order_by| {
+                order_by
+                    .into_iter()
+                    .map(|mut o| {
+                        bind_column_references(&mut o.expr, &mut select_table_references, None)?;
+                        Ok((o.expr, o.order.unwrap_or(SortOrder::Asc)))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?;
+
+        let table = ephemeral_rowid_table();
+        let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+        let mut ephemeral_plan = SelectPlan {
+            table_references: select_table_references,
+            result_columns: vec![ResultSetColumn {
+                expr: Expr::RowId {
+                    database: None,
+                    table: internal_id,
+                },
+                alias: None,
+                contains_aggregates: false,
+            }],
+            where_clause: select_where,
+            group_by: None,
+            order_by,
+            aggregates: vec![],
+            limit: resolved_limit,
+            query_destination: QueryDestination::EphemeralTable { cursor_id, table },
+            join_order: vec![],
+            offset: resolved_offset,
+            contains_constant_false_condition: false,
+            distinctness: Distinctness::NonDistinct,
+            values: vec![],
+        };
+        optimize_select_plan(&mut ephemeral_plan, schema)?;
+        Some(ephemeral_plan)
+    } else {
+        // Parse the WHERE clause
+        parse_where(
+            where_clause.map(|e| *e),
+            &mut table_references,
+            None,
+            &mut where_predicates,
+        )?;
+        None
+    };
 
     let plan = DeletePlan {
         table_references,
@@ -100,6 +178,7 @@ pub fn prepare_delete_plan(
         offset: resolved_offset,
         contains_constant_false_condition: false,
         indexes,
+        ephemeral_plan,
     };
 
     Ok(Plan::Delete(plan))
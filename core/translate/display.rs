@@ -88,14 +88,21 @@ impl Display for SelectPlan {
             };
 
             match &reference.op {
-                Operation::Scan { .. } => {
+                Operation::Scan { index, .. } => {
                     let table_name = if reference.table.get_name() == reference.identifier {
                         reference.identifier.clone()
                     } else {
                         format!("{} AS {}", reference.table.get_name(), reference.identifier)
                     };
 
-                    writeln!(f, "{}SCAN {}", indent, table_name)?;
+                    match index {
+                        Some(index) => writeln!(
+                            f,
+                            "{}SCAN {} USING INDEX {}",
+                            indent, table_name, index.name
+                        )?,
+                        None => writeln!(f, "{}SCAN {}", indent, table_name)?,
+                    }
                 }
                 Operation::Search(search) => match search {
                     Search::RowidEq { .. } | Search::Seek { index: None, .. } => {
@@ -466,23 +473,32 @@ impl ToSqlString for DeletePlan {
             .joined_tables()
             .first()
             .expect("Delete Plan should have only one table reference");
-        let context = &[&self.table_references];
-        let context = &PlanContext(context);
+        // With an ephemeral plan, the WHERE and ORDER BY clauses live in the plan that
+        // gathers the rowids to delete
+        let mut context = vec![&self.table_references];
+        let (where_clause, order_by) = match &self.ephemeral_plan {
+            Some(plan) => {
+                context.push(&plan.table_references);
+                (&plan.where_clause, &plan.order_by)
+            }
+            None => (&self.where_clause, &self.order_by),
+        };
+        let context = &PlanContext(&context);
         let mut ret = Vec::new();
 
         ret.push(format!("DELETE FROM {}", table.table.get_name()));
 
-        if !self.where_clause.is_empty() {
+        if !where_clause.is_empty() {
             ret.push("WHERE".to_string());
             ret.push(
-                self.where_clause
+                where_clause
                     .iter()
                     .map(|where_clause| where_clause.expr.to_sql_string(context))
                     .collect::<Vec<_>>()
                     .join(" AND "),
             );
         }
-        if let Some(order_by) = &self.order_by {
+        if let Some(order_by) = order_by {
             ret.push(format!(
                 "ORDER BY {}",
                 order_by
@@ -509,13 +525,30 @@ impl ToSqlString for UpdatePlan {
             .joined_tables()
             .first()
             .expect("UPDATE Plan should have only one table reference");
-        let context = [&self.table_references];
+        // With an ephemeral plan, the WHERE clause and the FROM tables live in the plan that
+        // gathers the rows to update
+        let mut context = vec![&self.table_references];
+        let (where_clause, from_tables) = match &self.ephemeral_plan {
+            Some(plan) => {
+                context.push(&plan.table_references);
+                (
+                    &plan.where_clause,
+                    &plan.table_references.joined_tables()[1..],
+                )
+            }
+            None => (&self.where_clause, &[][..]),
+        };
         let context = &PlanContext(&context);
         let mut ret = Vec::new();
 
-        // TODO: we don't work with conflict clauses yet
-
-        ret.push(format!("UPDATE {} SET", table.table.get_name()));
+        match self.or_conflict {
+            Some(or_conflict) => ret.push(format!(
+                "UPDATE OR {} {} SET",
+                or_conflict,
+                table.table.get_name()
+            )),
+            None => ret.push(format!("UPDATE {} SET", table.table.get_name())),
+        }
 
         // TODO: does not support column_name_list yet
         ret.push(
@@ -539,10 +572,21 @@ impl ToSqlString for UpdatePlan {
                 .join(", "),
         );
 
-        if !self.where_clause.is_empty() {
+        if !from_tables.is_empty() {
+            ret.push(format!(
+                "FROM {}",
+                from_tables
+                    .iter()
+                    .map(|table| table.to_sql_string(context))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if !where_clause.is_empty() {
             ret.push("WHERE".to_string());
             ret.push(
-                self.where_clause
+                where_clause
                     .iter()
                     .map(|where_clause| where_clause.expr.to_sql_string(context))
                     .collect::<Vec<_>>()
@@ -7,7 +7,7 @@ use tracing::{instrument, Level};
 use turso_sqlite3_parser::ast::{self, Expr};
 
 use super::aggregation::emit_ungrouped_aggregation;
-use super::expr::translate_expr;
+use super::expr::{translate_expr, translate_expr_no_constant_opt, NoConstantOptReason};
 use super::group_by::{
     group_by_agg_phase, group_by_emit_row_phase, init_group_by, GroupByMetadata, GroupByRowSource,
 };
@@ -23,8 +23,15 @@ use super::subquery::emit_subqueries;
 use crate::error::SQLITE_CONSTRAINT_PRIMARYKEY;
 use crate::function::Func;
 use crate::schema::Schema;
+use crate::translate::check::emit_check_constraints;
 use crate::translate::compound_select::emit_program_for_compound_select;
+use crate::translate::generated::{emit_generated_columns, emit_record_registers};
+use crate::translate::insert::{
+    emit_foreign_key_check, emit_foreign_key_child_checks, emit_foreign_key_parent_checks,
+    emit_replace_delete,
+};
 use crate::translate::plan::{DeletePlan, Plan, QueryDestination, Search};
+use crate::translate::trigger::{emit_triggers, has_triggers, TriggerOperation, TriggerRow};
 use crate::translate::values::emit_values;
 use crate::util::exprs_are_equivalent;
 use crate::vdbe::builder::{CursorKey, CursorType, ProgramBuilder};
@@ -50,13 +57,12 @@ impl<'a> Resolver<'a> {
         }
     }
 
+    /// Functions registered by extensions take precedence over the built-in ones of the same
+    /// name, as application-defined functions do in SQLite.
     pub fn resolve_function(&self, func_name: &str, arg_count: usize) -> Option<Func> {
-        match Func::resolve_function(func_name, arg_count).ok() {
-            Some(func) => Some(func),
-            None => self
-                .symbol_table
-                .resolve_function(func_name, arg_count)
-                .map(|arg| Func::External(arg.clone())),
+        match self.symbol_table.resolve_function(func_name, arg_count) {
+            Some(func) => Some(Func::External(func.clone())),
+            None => Func::resolve_function(func_name, arg_count).ok(),
         }
     }
 
@@ -320,7 +326,7 @@ pub fn emit_query<'a>(
     }
 
     let distinct_ctx = if let Distinctness::Distinct { .. } = &plan.distinctness {
-        Some(init_distinct(program, plan))
+        Some(init_distinct(program, plan)?)
     } else {
         None
     };
@@ -398,7 +404,7 @@ pub fn emit_query<'a>(
 #[instrument(skip_all, level = Level::TRACE)]
 fn emit_program_for_delete(
     program: &mut ProgramBuilder,
-    plan: DeletePlan,
+    mut plan: DeletePlan,
     schema: &Schema,
     syms: &SymbolTable,
 ) -> Result<()> {
@@ -418,7 +424,17 @@ fn emit_program_for_delete(
         return Ok(());
     }
 
-    init_limit(program, &mut t_ctx, plan.limit, None);
+    let ephemeral_plan = plan.ephemeral_plan.take();
+    let temp_cursor_id = ephemeral_plan.as_ref().map(|plan| {
+        let QueryDestination::EphemeralTable { cursor_id, .. } = &plan.query_destination else {
+            unreachable!()
+        };
+        *cursor_id
+    });
+    // The ephemeral plan already applies the LIMIT/OFFSET when gathering the rowids
+    if ephemeral_plan.is_none() {
+        init_limit(program, &mut t_ctx, plan.limit, None);
+    }
 
     // No rows will be read from source table loops if there is a constant false condition eg. WHERE 0
     let after_main_loop_label = program.allocate_label();
@@ -429,6 +445,16 @@ fn emit_program_for_delete(
         });
     }
 
+    if let Some(ephemeral_plan) = ephemeral_plan {
+        program.emit_insn(Insn::OpenEphemeral {
+            cursor_id: temp_cursor_id.unwrap(),
+            is_table: true,
+        });
+        program.incr_nesting();
+        emit_program_for_select(program, ephemeral_plan, schema, syms)?;
+        program.decr_nesting();
+    }
+
     // Initialize cursors and other resources needed for query execution
     init_loop(
         program,
@@ -447,10 +473,10 @@ fn emit_program_for_delete(
         &plan.table_references,
         &[JoinOrderMember::default()],
         &plan.where_clause,
-        None,
+        temp_cursor_id,
     )?;
 
-    emit_delete_insns(program, &mut t_ctx, &plan.table_references)?;
+    emit_delete_insns(program, &mut t_ctx, &plan.table_references, temp_cursor_id)?;
 
     // Clean up and close the main execution loop
     close_loop(
@@ -458,7 +484,7 @@ fn emit_program_for_delete(
         &mut t_ctx,
         &plan.table_references,
         &[JoinOrderMember::default()],
-        None,
+        temp_cursor_id,
     )?;
     program.preassign_label_to_next_insn(after_main_loop_label);
 
@@ -473,6 +499,7 @@ fn emit_delete_insns(
     program: &mut ProgramBuilder,
     t_ctx: &mut TranslateCtx,
     table_references: &TableReferences,
+    temp_cursor_id: Option<CursorID>,
 ) -> Result<()> {
     let table_reference = table_references.joined_tables().first().unwrap();
     let cursor_id = match &table_reference.op {
@@ -497,9 +524,17 @@ fn emit_delete_insns(
     // Emit the instructions to delete the row
     let key_reg = program.alloc_register();
     program.emit_insn(Insn::RowId {
-        cursor_id: main_table_cursor_id,
+        cursor_id: temp_cursor_id.unwrap_or(main_table_cursor_id),
         dest: key_reg,
     });
+    if temp_cursor_id.is_some() {
+        // Position the table cursor on the row whose rowid was gathered by the ephemeral plan
+        program.emit_insn(Insn::NotExists {
+            cursor: main_table_cursor_id,
+            rowid_reg: key_reg,
+            target_pc: t_ctx.labels_main_loop.first().unwrap().next,
+        });
+    }
 
     if table_reference.virtual_table().is_some() {
         let conflict_action = 0u16;
@@ -538,6 +573,48 @@ fn emit_delete_insns(
                 .collect::<Vec<_>>()
         });
 
+        // The deleted row is read while the cursor still points to it, for the triggers that
+        // fire after it is gone
+        let old_row = match table_reference.btree() {
+            Some(table)
+                if has_triggers(t_ctx.resolver.schema, &table, &TriggerOperation::Delete) =>
+            {
+                let columns_start = program.alloc_registers(table.columns.len());
+                for idx in 0..table.columns.len() {
+                    program.emit_column(main_table_cursor_id, idx, columns_start + idx);
+                }
+                emit_generated_columns(program, &table, columns_start, key_reg, &t_ctx.resolver)?;
+                Some((
+                    table,
+                    TriggerRow {
+                        columns_start,
+                        rowid_reg: key_reg,
+                    },
+                ))
+            }
+            _ => None,
+        };
+
+        if program.foreign_keys {
+            if let Some(table) = table_reference.btree() {
+                emit_foreign_key_parent_checks(
+                    program,
+                    t_ctx.resolver.schema,
+                    &table,
+                    main_table_cursor_id,
+                    None,
+                    None,
+                )?;
+                emit_foreign_key_child_checks(
+                    program,
+                    t_ctx.resolver.schema,
+                    &table,
+                    main_table_cursor_id,
+                    None,
+                )?;
+            }
+        }
+
         if let Some(index_refs) = index_refs_opt {
             for (index, index_cursor_id) in index_refs {
                 let num_regs = index.columns.len() + 1;
@@ -569,6 +646,18 @@ fn emit_delete_insns(
         program.emit_insn(Insn::Delete {
             cursor_id: main_table_cursor_id,
         });
+
+        if let Some((table, old_row)) = old_row {
+            emit_triggers(
+                program,
+                t_ctx.resolver.schema,
+                t_ctx.resolver.symbol_table,
+                &table,
+                TriggerOperation::Delete,
+                None,
+                Some(old_row),
+            )?;
+        }
     }
     if let Some(limit_ctx) = t_ctx.limit_ctx {
         program.emit_insn(Insn::DecrJumpZero {
@@ -766,17 +855,25 @@ fn emit_update_insns(
         None
     };
 
-    let check_rowid_not_exists_label = if has_user_provided_rowid {
-        Some(program.allocate_label())
-    } else {
-        None
-    };
+    // Under OR IGNORE, a row that would violate a constraint is left as it is
+    let ignore_conflicts = plan.or_conflict == Some(ast::ResolveType::Ignore);
+    // Under OR REPLACE, the rows conflicting with the updated one are deleted
+    let replace_conflicts = plan.or_conflict == Some(ast::ResolveType::Replace);
 
-    if has_user_provided_rowid {
+    // When the rowids come from the ephemeral table, the table cursor must be moved to
+    // the row before its columns can be read. A row already deleted by REPLACE is skipped.
+    let check_rowid_not_exists_label =
+        if has_user_provided_rowid || plan.set_values_in_ephemeral_table || replace_conflicts {
+            Some(program.allocate_label())
+        } else {
+            None
+        };
+
+    if let Some(label) = check_rowid_not_exists_label {
         program.emit_insn(Insn::NotExists {
             cursor: cursor_id,
             rowid_reg: beg,
-            target_pc: check_rowid_not_exists_label.unwrap(),
+            target_pc: label,
         });
     } else {
         // if no rowid, we're done
@@ -802,23 +899,57 @@ fn emit_update_insns(
         });
     }
 
+    // The row as it was before the update, for the triggers that fire after it is written
+    let updated_columns = plan
+        .set_clauses
+        .iter()
+        .map(|(idx, _)| *idx)
+        .collect::<Vec<_>>();
+    let old_row = match table_ref.btree() {
+        Some(table)
+            if has_triggers(
+                t_ctx.resolver.schema,
+                &table,
+                &TriggerOperation::Update(&updated_columns),
+            ) =>
+        {
+            let columns_start = program.alloc_registers(table.columns.len());
+            for idx in 0..table.columns.len() {
+                program.emit_column(cursor_id, idx, columns_start + idx);
+            }
+            emit_generated_columns(program, &table, columns_start, beg, &t_ctx.resolver)?;
+            Some(TriggerRow {
+                columns_start,
+                rowid_reg: beg,
+            })
+        }
+        _ => None,
+    };
+
     // we scan a column at a time, loading either the column's values, or the new value
     // from the Set expression, into registers so we can emit a MakeRecord and update the row.
     let start = if is_virtual { beg + 2 } else { beg + 1 };
     for (idx, table_column) in table_ref.columns().iter().enumerate() {
         let target_reg = start + idx;
-        if let Some((_, expr)) = plan.set_clauses.iter().find(|(i, _)| *i == idx) {
+        if let Some((set_idx, (_, expr))) = plan
+            .set_clauses
+            .iter()
+            .enumerate()
+            .find(|(_, (i, _))| *i == idx)
+        {
             if has_user_provided_rowid
                 && (table_column.primary_key || table_column.is_rowid_alias)
                 && !is_virtual
             {
                 let rowid_set_clause_reg = rowid_set_clause_reg.unwrap();
-                translate_expr(
+                emit_set_clause_value(
                     program,
-                    Some(&plan.table_references),
+                    plan,
+                    t_ctx,
+                    temp_cursor_id,
+                    set_idx,
                     expr,
                     rowid_set_clause_reg,
-                    &t_ctx.resolver,
                 )?;
 
                 program.emit_insn(Insn::MustBeInt {
@@ -827,14 +958,41 @@ fn emit_update_insns(
 
                 program.emit_null(target_reg, None);
             } else {
-                translate_expr(
+                emit_set_clause_value(
                     program,
-                    Some(&plan.table_references),
+                    plan,
+                    t_ctx,
+                    temp_cursor_id,
+                    set_idx,
                     expr,
                     target_reg,
-                    &t_ctx.resolver,
                 )?;
-                if table_column.notnull {
+                if table_column.notnull && ignore_conflicts {
+                    program.emit_insn(Insn::IsNull {
+                        reg: target_reg,
+                        target_pc: loop_labels.next,
+                    });
+                } else if let Some(default) = table_column
+                    .default
+                    .as_ref()
+                    .filter(|_| table_column.notnull && replace_conflicts)
+                {
+                    // REPLACE stores the default of the column instead of a NULL
+                    let not_null_label = program.allocate_label();
+                    program.emit_insn(Insn::NotNull {
+                        reg: target_reg,
+                        target_pc: not_null_label,
+                    });
+                    translate_expr_no_constant_opt(
+                        program,
+                        None,
+                        default,
+                        target_reg,
+                        &t_ctx.resolver,
+                        NoConstantOptReason::RegisterReuse,
+                    )?;
+                    program.preassign_label_to_next_insn(not_null_label);
+                } else if table_column.notnull {
                     use crate::error::SQLITE_CONSTRAINT_NOTNULL;
                     program.emit_insn(Insn::HaltIfNull {
                         target_reg,
@@ -883,6 +1041,67 @@ fn emit_update_insns(
         }
     }
 
+    if let Some(btree_table) = table_ref.btree() {
+        emit_generated_columns(
+            program,
+            &btree_table,
+            start,
+            rowid_set_clause_reg.unwrap_or(beg),
+            &t_ctx.resolver,
+        )?;
+        emit_check_constraints(
+            program,
+            &btree_table,
+            start,
+            rowid_set_clause_reg.unwrap_or(beg),
+            &t_ctx.resolver,
+            ignore_conflicts.then_some(loop_labels.next),
+        )?;
+
+        if program.foreign_keys {
+            // The rows referencing the old key of the row, while the cursor still points to it
+            emit_foreign_key_parent_checks(
+                program,
+                t_ctx.resolver.schema,
+                &btree_table,
+                cursor_id,
+                Some(&updated_columns),
+                Some((start, rowid_set_clause_reg.unwrap_or(beg))),
+            )?;
+            emit_foreign_key_child_checks(
+                program,
+                t_ctx.resolver.schema,
+                &btree_table,
+                cursor_id,
+                Some(&updated_columns),
+            )?;
+            for fk in btree_table.foreign_keys.iter().filter(|fk| {
+                fk.child_columns.iter().any(|name| {
+                    btree_table
+                        .get_column(name)
+                        .is_some_and(|(pos, _)| updated_columns.contains(&pos))
+                })
+            }) {
+                emit_foreign_key_check(
+                    program,
+                    t_ctx.resolver.schema,
+                    &btree_table,
+                    fk,
+                    start,
+                    rowid_set_clause_reg.unwrap_or(beg),
+                )?;
+            }
+        }
+    }
+
+    // REPLACE deletes the conflicting rows from every index, all of which are opened
+    let replace_indexes = plan
+        .indexes_to_update
+        .iter()
+        .zip(&index_cursors)
+        .map(|(index, (idx_cursor_id, _))| (index.clone(), *idx_cursor_id))
+        .collect::<Vec<_>>();
+
     for (index, (idx_cursor_id, record_reg)) in plan.indexes_to_update.iter().zip(&index_cursors) {
         let num_cols = index.columns.len();
         // allocate scratch registers for the index columns plus rowid
@@ -958,18 +1177,32 @@ fn emit_update_insns(
             dest: idx_rowid_reg,
         });
 
+        // The entry of the row being updated still holds its old rowid
         program.emit_insn(Insn::Eq {
-            lhs: rowid_reg,
+            lhs: beg,
             rhs: idx_rowid_reg,
             target_pc: constraint_check,
             flags: CmpInsFlags::default(), // TODO: not sure what type of comparison flag is needed
             collation: program.curr_collation(),
         });
 
-        program.emit_insn(Insn::Halt {
-            err_code: SQLITE_CONSTRAINT_PRIMARYKEY, // TODO: distinct between primary key and unique index for error code
-            description: column_names,
-        });
+        if ignore_conflicts {
+            program.emit_insn(Insn::Goto {
+                target_pc: loop_labels.next,
+            });
+        } else if replace_conflicts {
+            program.emit_insn(Insn::NotExists {
+                cursor: cursor_id,
+                rowid_reg: idx_rowid_reg,
+                target_pc: constraint_check,
+            });
+            emit_replace_delete(program, cursor_id, &replace_indexes);
+        } else {
+            program.emit_insn(Insn::Halt {
+                err_code: SQLITE_CONSTRAINT_PRIMARYKEY, // TODO: distinct between primary key and unique index for error code
+                description: column_names,
+            });
+        }
 
         program.preassign_label_to_next_insn(constraint_check);
     }
@@ -1002,33 +1235,46 @@ fn emit_update_insns(
                 target_pc: record_label,
             });
 
-            program.emit_insn(Insn::Halt {
-                err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
-                description: format!(
-                    "{}.{}",
-                    table_ref.table.get_name(),
-                    &table_ref
-                        .columns()
-                        .get(idx)
-                        .unwrap()
-                        .name
-                        .as_ref()
-                        .map_or("", |v| v)
-                ),
-            });
+            if ignore_conflicts {
+                program.emit_insn(Insn::Goto {
+                    target_pc: loop_labels.next,
+                });
+            } else if replace_conflicts {
+                // The table cursor points to the conflicting row
+                emit_replace_delete(program, cursor_id, &replace_indexes);
+            } else {
+                program.emit_insn(Insn::Halt {
+                    err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
+                    description: format!(
+                        "{}.{}",
+                        table_ref.table.get_name(),
+                        &table_ref
+                            .columns()
+                            .get(idx)
+                            .unwrap()
+                            .name
+                            .as_ref()
+                            .map_or("", |v| v)
+                    ),
+                });
+            }
 
             program.preassign_label_to_next_insn(record_label);
         }
 
         let record_reg = program.alloc_register();
+        let (record_start_reg, record_num_cols) =
+            emit_record_registers(program, &btree_table, start);
         program.emit_insn(Insn::MakeRecord {
-            start_reg: start,
-            count: table_ref.columns().len(),
+            start_reg: record_start_reg,
+            count: record_num_cols,
             dest_reg: record_reg,
             index_name: None,
         });
 
-        if has_user_provided_rowid {
+        // Checking the new rowid or deleting the conflicting rows moved the table cursor, so it
+        // is positioned again on the row being updated
+        if has_user_provided_rowid || replace_conflicts {
             program.emit_insn(Insn::NotExists {
                 cursor: cursor_id,
                 rowid_reg: beg,
@@ -1090,6 +1336,21 @@ fn emit_update_insns(
             flag: InsertFlags::new().update(true),
             table_name: table_ref.identifier.clone(),
         });
+
+        if old_row.is_some() {
+            emit_triggers(
+                program,
+                t_ctx.resolver.schema,
+                t_ctx.resolver.symbol_table,
+                &btree_table,
+                TriggerOperation::Update(&updated_columns),
+                Some(TriggerRow {
+                    columns_start: start,
+                    rowid_reg: rowid_set_clause_reg.unwrap_or(beg),
+                }),
+                old_row,
+            )?;
+        }
     } else if table_ref.virtual_table().is_some() {
         let arg_count = table_ref.columns().len() + 2;
         program.emit_insn(Insn::VUpdate {
@@ -1115,6 +1376,31 @@ fn emit_update_insns(
     Ok(())
 }
 
+/// Loads the new value of the `set_idx`-th SET clause of an UPDATE into `target_reg`.
+fn emit_set_clause_value(
+    program: &mut ProgramBuilder,
+    plan: &UpdatePlan,
+    t_ctx: &TranslateCtx,
+    temp_cursor_id: Option<CursorID>,
+    set_idx: usize,
+    expr: &Expr,
+    target_reg: usize,
+) -> crate::Result<()> {
+    if plan.set_values_in_ephemeral_table {
+        let temp_cursor_id = temp_cursor_id.expect("UPDATE ... FROM must have an ephemeral table");
+        program.emit_column(temp_cursor_id, set_idx, target_reg);
+    } else {
+        translate_expr(
+            program,
+            Some(&plan.table_references),
+            expr,
+            target_reg,
+            &t_ctx.resolver,
+        )?;
+    }
+    Ok(())
+}
+
 /// Initialize the limit/offset counters and registers.
 /// In case of compound SELECTs, the limit counter is initialized only once,
 /// hence [LimitCtx::initialize_counter] being false in those cases.
@@ -2,6 +2,7 @@ use tracing::{instrument, Level};
 use turso_sqlite3_parser::ast::{self, Expr, UnaryOperator};
 
 use super::emitter::Resolver;
+use super::generated::{bind_generated_expr, emit_generated_column_affinity};
 use super::optimizer::Optimizable;
 use super::plan::TableReferences;
 #[cfg(feature = "json")]
@@ -203,6 +204,7 @@ pub fn translate_condition_expr(
         | ast::Expr::FunctionCall { .. }
         | ast::Expr::Column { .. }
         | ast::Expr::RowId { .. }
+        | ast::Expr::Register(_)
         | ast::Expr::Case { .. } => {
             let reg = program.alloc_register();
             translate_expr(program, Some(referenced_tables), expr, reg, resolver)?;
@@ -677,7 +679,7 @@ pub fn translate_expr(
             distinctness: _,
             args,
             filter_over: _,
-            order_by: _,
+            order_by,
         } => {
             let args_count = if let Some(args) = args { args.len() } else { 0 };
             let func_name = normalize_ident(name.0.as_str());
@@ -692,6 +694,13 @@ pub fn translate_expr(
                 arg_count: args_count,
             };
 
+            if order_by.is_some() && !matches!(func_ctx.func, Func::Agg(_)) {
+                crate::bail_parse_error!(
+                    "ORDER BY may not be used with non-aggregate {}()",
+                    name.0
+                );
+            }
+
             match &func_ctx.func {
                 Func::Agg(_) => {
                     crate::bail_parse_error!("aggregation function in non-aggregation context")
@@ -1541,6 +1550,35 @@ pub fn translate_expr(
 
                             Ok(target_register)
                         }
+                        #[cfg(feature = "regexp")]
+                        ScalarFunc::Regexp => {
+                            let args = match args {
+                                Some(args) if args.len() == 2 => args,
+                                _ => crate::bail_parse_error!(
+                                    "wrong number of arguments to function {}()",
+                                    srf.to_string()
+                                ),
+                            };
+                            let start_reg = program.alloc_registers(2);
+                            for (i, arg) in args.iter().enumerate() {
+                                translate_expr(
+                                    program,
+                                    referenced_tables,
+                                    arg,
+                                    start_reg + i,
+                                    resolver,
+                                )?;
+                            }
+                            // The compiled regex is only cached for a literal pattern
+                            let constant_mask = matches!(args[0], ast::Expr::Literal(_)) as i32;
+                            program.emit_insn(Insn::Function {
+                                constant_mask,
+                                start_reg,
+                                dest: target_register,
+                                func: func_ctx,
+                            });
+                            Ok(target_register)
+                        }
                         ScalarFunc::SqliteVersion => {
                             if args.is_some() {
                                 crate::bail_parse_error!("sqlite_version function with arguments");
@@ -1653,7 +1691,7 @@ pub fn translate_expr(
                             });
                             Ok(target_register)
                         }
-                        ScalarFunc::Printf => translate_function(
+                        ScalarFunc::Printf | ScalarFunc::Format => translate_function(
                             program,
                             args.as_deref().unwrap_or(&[]),
                             referenced_tables,
@@ -1849,6 +1887,17 @@ pub fn translate_expr(
             let Some(table_column) = table.get_column_at(*column) else {
                 crate::bail_parse_error!("column index out of bounds");
             };
+            // VIRTUAL generated columns are not stored, their expression is evaluated instead
+            if let (Table::BTree(btree), Some(generated)) = (
+                &table,
+                table_column.generated.as_ref().filter(|g| !g.stored),
+            ) {
+                let expr = bind_generated_expr(btree, generated, *table_ref_id)?;
+                translate_expr(program, referenced_tables, &expr, target_register, resolver)?;
+                emit_generated_column_affinity(program, table_column, target_register);
+                program.set_collation(Some((table_column.collation.unwrap_or_default(), false)));
+                return Ok(target_register);
+            }
             // Counter intuitive but a column always needs to have a collation
             program.set_collation(Some((table_column.collation.unwrap_or_default(), false)));
 
@@ -1968,6 +2017,14 @@ pub fn translate_expr(
             }
             Ok(target_register)
         }
+        ast::Expr::Register(src_reg) => {
+            program.emit_insn(Insn::Copy {
+                src_reg: *src_reg,
+                dst_reg: target_register,
+                amount: 0,
+            });
+            Ok(target_register)
+        }
         ast::Expr::InList { .. } => todo!(),
         ast::Expr::InSelect { .. } => todo!(),
         ast::Expr::InTable { .. } => todo!(),
@@ -2111,9 +2168,9 @@ pub fn translate_expr(
                     resolver,
                 )?;
             } else {
-                // Parenthesized expressions with multiple arguments are reserved for special cases
-                // like `(a, b) IN ((1, 2), (3, 4))`.
-                todo!("TODO: parenthesized expression with multiple arguments not yet supported");
+                // Row values are only supported as operands of comparisons, which are rewritten
+                // into comparisons of their elements before translation
+                crate::bail_parse_error!("row value misused");
             }
             Ok(target_register)
         }
@@ -2503,38 +2560,41 @@ fn translate_like_base(
         crate::bail_parse_error!("expected Like expression");
     };
     match op {
-        ast::LikeOperator::Like | ast::LikeOperator::Glob => {
+        ast::LikeOperator::Like | ast::LikeOperator::Glob | ast::LikeOperator::Regexp => {
+            if *op == ast::LikeOperator::Regexp && escape.is_some() {
+                crate::bail_parse_error!("ESCAPE expression is not supported with REGEXP");
+            }
             let arg_count = if escape.is_some() { 3 } else { 2 };
             let start_reg = program.alloc_registers(arg_count);
             let mut constant_mask = 0;
             translate_expr(program, referenced_tables, lhs, start_reg + 1, resolver)?;
             let _ = translate_expr(program, referenced_tables, rhs, start_reg, resolver)?;
-            if arg_count == 3 {
-                if let Some(escape) = escape {
-                    translate_expr(program, referenced_tables, escape, start_reg + 2, resolver)?;
-                }
-            }
             if matches!(rhs.as_ref(), ast::Expr::Literal(_)) {
                 program.mark_last_insn_constant();
                 constant_mask = 1;
             }
+            if let Some(escape) = escape {
+                translate_expr(program, referenced_tables, escape, start_reg + 2, resolver)?;
+            }
             let func = match op {
-                ast::LikeOperator::Like => ScalarFunc::Like,
-                ast::LikeOperator::Glob => ScalarFunc::Glob,
+                ast::LikeOperator::Like => Func::Scalar(ScalarFunc::Like),
+                ast::LikeOperator::Glob => Func::Scalar(ScalarFunc::Glob),
+                // `X REGEXP Y` calls `regexp(Y, X)`: the function registered by an extension if
+                // one is loaded, the built-in implementation otherwise.
+                ast::LikeOperator::Regexp => match resolver.resolve_function("regexp", 2) {
+                    Some(func) => func,
+                    None => crate::bail_parse_error!("no such function: regexp"),
+                },
                 _ => unreachable!(),
             };
             program.emit_insn(Insn::Function {
                 constant_mask,
                 start_reg,
                 dest: target_register,
-                func: FuncCtx {
-                    func: Func::Scalar(func),
-                    arg_count,
-                },
+                func: FuncCtx { func, arg_count },
             });
         }
         ast::LikeOperator::Match => todo!(),
-        ast::LikeOperator::Regexp => todo!(),
     }
 
     Ok(target_register)
@@ -2847,6 +2907,7 @@ where
                 ast::Expr::Id(_)
                 | ast::Expr::Column { .. }
                 | ast::Expr::RowId { .. }
+                | ast::Expr::Register(_)
                 | ast::Expr::Literal(_)
                 | ast::Expr::DoublyQualified(..)
                 | ast::Expr::Name(_)
@@ -3043,6 +3104,7 @@ where
         ast::Expr::Id(_)
         | ast::Expr::Column { .. }
         | ast::Expr::RowId { .. }
+        | ast::Expr::Register(_)
         | ast::Expr::Literal(_)
         | ast::Expr::DoublyQualified(..)
         | ast::Expr::Name(_)
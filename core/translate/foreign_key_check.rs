@@ -0,0 +1,146 @@
+use std::rc::Rc;
+
+use crate::{
+    schema::{BTreeTable, Schema},
+    vdbe::{builder::CursorType, builder::ProgramBuilder, insn::Insn},
+};
+
+use super::insert::{emit_parent_key_seek, open_parent_key_cursor, resolve_parent_key_lookup};
+
+/// Emits `PRAGMA foreign_key_check`, which returns a `(table, rowid, parent, fkid)` row for every
+/// foreign key of a row that references no row of its parent table. Only `table` is checked when
+/// it is given, otherwise every table is.
+///
+/// As in SQLite, the foreign keys of a table are numbered from the last declared one, rows whose
+/// parent table does not exist are all reported, and a parent key that is neither the primary
+/// key nor covered by a unique index is an error.
+pub fn translate_foreign_key_check(
+    schema: &Schema,
+    table: Option<&str>,
+    program: &mut ProgramBuilder,
+) -> crate::Result<()> {
+    let tables = match table {
+        Some(name) => match schema.get_btree_table(name) {
+            Some(table) => vec![table],
+            None => crate::bail_parse_error!("no such table: {}", name),
+        },
+        None => {
+            let mut tables = schema
+                .tables
+                .values()
+                .filter_map(|table| table.btree())
+                .filter(|table| !table.foreign_keys.is_empty())
+                .collect::<Vec<_>>();
+            tables.sort_by(|a, b| a.name.cmp(&b.name));
+            tables
+        }
+    };
+
+    let result_reg = program.alloc_registers(4);
+    for table in tables {
+        emit_table_foreign_key_check(schema, &table, result_reg, program)?;
+    }
+    for name in ["table", "rowid", "parent", "fkid"] {
+        program.add_pragma_result_column(name.into());
+    }
+    Ok(())
+}
+
+fn emit_table_foreign_key_check(
+    schema: &Schema,
+    table: &Rc<BTreeTable>,
+    result_reg: usize,
+    program: &mut ProgramBuilder,
+) -> crate::Result<()> {
+    // The parents are resolved and opened before the scan of the table
+    let mut checks = Vec::with_capacity(table.foreign_keys.len());
+    for fk in table.foreign_keys.iter().rev() {
+        let parent = match schema.get_btree_table(&fk.parent_table) {
+            Some(parent) => {
+                let (lookup, parent_columns) =
+                    resolve_parent_key_lookup(schema, table, fk, &parent)?;
+                let cursor_id = open_parent_key_cursor(program, &parent, &lookup);
+                Some((cursor_id, lookup, parent_columns))
+            }
+            None => None,
+        };
+        let child_columns = fk
+            .child_columns
+            .iter()
+            .map(|name| match table.get_column(name) {
+                Some((pos, column)) => Ok((pos, column.is_rowid_alias)),
+                None => {
+                    crate::bail_parse_error!(
+                        "unknown column \"{}\" in foreign key definition",
+                        name
+                    )
+                }
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        checks.push((fk, parent, child_columns));
+    }
+
+    let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+    program.emit_insn(Insn::OpenRead {
+        cursor_id,
+        root_page: table.root_page,
+    });
+    let label_loop_start = program.allocate_label();
+    let label_loop_end = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id,
+        pc_if_empty: label_loop_end,
+    });
+    program.preassign_label_to_next_insn(label_loop_start);
+    for (fkid, (fk, parent, child_columns)) in checks.iter().enumerate() {
+        let label_fk_ok = program.allocate_label();
+        let label_fk_violation = program.allocate_label();
+        let child_registers = program.alloc_registers(child_columns.len());
+        let child_registers =
+            (child_registers..child_registers + child_columns.len()).collect::<Vec<_>>();
+        for (&(pos, is_rowid_alias), &reg) in child_columns.iter().zip(&child_registers) {
+            // The rowid alias column is NULL in the record, its value is the rowid
+            if is_rowid_alias {
+                program.emit_insn(Insn::RowId {
+                    cursor_id,
+                    dest: reg,
+                });
+            } else {
+                program.emit_column(cursor_id, pos, reg);
+            }
+            program.emit_insn(Insn::IsNull {
+                reg,
+                target_pc: label_fk_ok,
+            });
+        }
+        if let Some((parent_cursor_id, lookup, parent_columns)) = parent {
+            emit_parent_key_seek(
+                program,
+                *parent_cursor_id,
+                lookup,
+                parent_columns,
+                &child_registers,
+                label_fk_violation,
+            );
+            program.emit_insn(Insn::Goto {
+                target_pc: label_fk_ok,
+            });
+        }
+        program.preassign_label_to_next_insn(label_fk_violation);
+        program.emit_string8(table.name.clone(), result_reg);
+        program.emit_insn(Insn::RowId {
+            cursor_id,
+            dest: result_reg + 1,
+        });
+        program.emit_string8(fk.parent_table.clone(), result_reg + 2);
+        program.emit_int(fkid as i64, result_reg + 3);
+        program.emit_result_row(result_reg, 4);
+        program.preassign_label_to_next_insn(label_fk_ok);
+    }
+    program.emit_insn(Insn::Next {
+        cursor_id,
+        pc_if_next: label_loop_start,
+    });
+    program.preassign_label_to_next_insn(label_loop_end);
+    Ok(())
+}
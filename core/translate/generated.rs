@@ -0,0 +1,165 @@
+//! Generated columns, declared with `GENERATED ALWAYS AS (expr) [STORED | VIRTUAL]`.
+//!
+//! STORED columns are computed whenever a row is written and kept in the record, so reading
+//! them is no different from reading any other column. VIRTUAL columns are left out of the
+//! record and their expression is evaluated every time they are read.
+
+use std::num::NonZeroUsize;
+
+use turso_sqlite3_parser::ast::{self, TableInternalId};
+
+use crate::schema::{BTreeTable, Column, Generated};
+use crate::translate::emitter::Resolver;
+use crate::translate::expr::{translate_expr, walk_expr_mut};
+use crate::vdbe::builder::ProgramBuilder;
+use crate::vdbe::insn::Insn;
+use crate::Result;
+
+/// Returns the expression of a generated column of `table`, with the column names it references
+/// bound to the columns of the table reference `table_ref_id`.
+pub fn bind_generated_expr(
+    table: &BTreeTable,
+    generated: &Generated,
+    table_ref_id: TableInternalId,
+) -> Result<ast::Expr> {
+    bind_row_expr(table, &generated.expr, table_ref_id)
+}
+
+/// Returns `expr` with the column names it references bound to the columns of `table` in the
+/// table reference `table_ref_id`.
+pub fn bind_row_expr(
+    table: &BTreeTable,
+    expr: &ast::Expr,
+    table_ref_id: TableInternalId,
+) -> Result<ast::Expr> {
+    let mut expr = expr.clone();
+    walk_expr_mut(&mut expr, &mut |expr: &mut ast::Expr| -> Result<()> {
+        if let ast::Expr::Id(id) = expr {
+            if let Some((column, col)) = table.get_column(&id.0) {
+                *expr = ast::Expr::Column {
+                    database: None,
+                    table: table_ref_id,
+                    column,
+                    is_rowid_alias: col.is_rowid_alias,
+                };
+            }
+        }
+        Ok(())
+    })?;
+    Ok(expr)
+}
+
+/// Computes the generated columns of a row being written to `table`. The values of the other
+/// columns are in the registers starting at `columns_start_reg`, in table order, and the rowid
+/// is in `rowid_reg`; each generated column is written to its own register in that range.
+pub fn emit_generated_columns(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    columns_start_reg: usize,
+    rowid_reg: usize,
+    resolver: &Resolver,
+) -> Result<()> {
+    let order = table.generated_columns_order()?;
+    if order.is_empty() {
+        return Ok(());
+    }
+    let table_ref_id = TableInternalId::default();
+    let columns = row_column_exprs(table, table_ref_id);
+    let exprs = order
+        .iter()
+        .map(|&column| {
+            let generated = table.columns[column].generated.as_ref().unwrap();
+            Ok((column, bind_generated_expr(table, generated, table_ref_id)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let row_resolver = row_resolver(resolver, table, &columns, columns_start_reg, rowid_reg);
+    for (column, expr) in exprs.iter() {
+        let target_reg = columns_start_reg + column;
+        translate_expr(program, None, expr, target_reg, &row_resolver)?;
+        emit_generated_column_affinity(program, &table.columns[*column], target_reg);
+    }
+    Ok(())
+}
+
+/// Returns the references to each column of `table` in the table reference `table_ref_id`.
+///
+/// There is no table reference for a row being written, so expressions evaluated against it
+/// (generated columns, CHECK constraints) are bound to a placeholder one, whose column
+/// references are then resolved to the column registers by [row_resolver].
+pub fn row_column_exprs(table: &BTreeTable, table_ref_id: TableInternalId) -> Vec<ast::Expr> {
+    table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(column, col)| ast::Expr::Column {
+            database: None,
+            table: table_ref_id,
+            column,
+            is_rowid_alias: col.is_rowid_alias,
+        })
+        .collect()
+}
+
+/// Returns a resolver that translates the column references in `columns` (see
+/// [row_column_exprs]) to the registers of a row whose columns start at `columns_start_reg` and
+/// whose rowid is in `rowid_reg`.
+pub fn row_resolver<'a>(
+    resolver: &Resolver<'a>,
+    table: &BTreeTable,
+    columns: &'a [ast::Expr],
+    columns_start_reg: usize,
+    rowid_reg: usize,
+) -> Resolver<'a> {
+    let mut row_resolver = Resolver::new(resolver.schema, resolver.symbol_table);
+    row_resolver.enable_expr_to_reg_cache();
+    for (column, expr) in columns.iter().enumerate() {
+        let reg = if table.columns[column].is_rowid_alias {
+            rowid_reg
+        } else {
+            columns_start_reg + column
+        };
+        row_resolver.expr_to_reg_cache.push((expr, reg));
+    }
+    row_resolver
+}
+
+/// Applies the affinity of the declared type of a generated `column` to the value computed in
+/// `reg`, as it would be for a value inserted in the column.
+pub fn emit_generated_column_affinity(program: &mut ProgramBuilder, column: &Column, reg: usize) {
+    program.emit_insn(Insn::Affinity {
+        start_reg: reg,
+        count: NonZeroUsize::new(1).unwrap(),
+        affinities: column.affinity().aff_mask().to_string(),
+    });
+}
+
+/// Returns the registers holding the record of a row of `table` whose columns are in the
+/// registers starting at `columns_start_reg`, as a `(start, count)` pair. The VIRTUAL columns
+/// are not part of the record, so if there are any, the other columns are first copied to a
+/// contiguous range of registers.
+pub fn emit_record_registers(
+    program: &mut ProgramBuilder,
+    table: &BTreeTable,
+    columns_start_reg: usize,
+) -> (usize, usize) {
+    if !table.has_virtual_columns() {
+        return (columns_start_reg, table.columns.len());
+    }
+    let stored = table
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, col)| !col.is_virtual())
+        .map(|(column, _)| column)
+        .collect::<Vec<_>>();
+    let record_start_reg = program.alloc_registers(stored.len());
+    for (i, column) in stored.iter().enumerate() {
+        program.emit_insn(Insn::Copy {
+            src_reg: columns_start_reg + column,
+            dst_reg: record_start_reg + i,
+            amount: 0,
+        });
+    }
+    (record_start_reg, stored.len())
+}
@@ -5,7 +5,7 @@ use crate::translate::plan::ResultSetColumn;
 use crate::{
     function::AggFunc,
     schema::PseudoCursorType,
-    translate::collate::CollationSeq,
+    translate::collate::get_collseq_from_expr,
     util::exprs_are_equivalent,
     vdbe::{
         builder::{CursorType, ProgramBuilder},
@@ -16,7 +16,7 @@ use crate::{
 };
 
 use super::{
-    aggregation::handle_distinct,
+    aggregation::{emit_bare_columns_row_check, handle_distinct},
     emitter::{Resolver, TranslateCtx},
     expr::{translate_condition_expr, translate_expr, ConditionMetadata},
     order_by::order_by_sorter_insert,
@@ -123,7 +123,7 @@ pub fn init_group_by<'a>(
     // END BLOCK
 
     let reg_sorter_key = program.alloc_register();
-    let column_count = plan.agg_args_count() + t_ctx.non_aggregate_expressions.len();
+    let column_count = plan.agg_inputs_count() + t_ctx.non_aggregate_expressions.len();
     let reg_group_by_source_cols_start = program.alloc_registers(column_count);
 
     let row_source = if let Some(sort_order) = group_by.sort_order.as_ref() {
@@ -139,24 +139,7 @@ pub fn init_group_by<'a>(
         let collations = group_by
             .exprs
             .iter()
-            .map(|expr| match expr {
-                ast::Expr::Collate(_, collation_name) => {
-                    CollationSeq::new(collation_name).map(Some)
-                }
-                ast::Expr::Column { table, column, .. } => {
-                    let table_reference = plan
-                        .table_references
-                        .find_joined_table_by_internal_id(*table)
-                        .unwrap();
-
-                    let Some(table_column) = table_reference.table.get_column_at(*column) else {
-                        crate::bail_parse_error!("column index out of bounds");
-                    };
-
-                    Ok(table_column.collation)
-                }
-                _ => Ok(Some(CollationSeq::default())),
-            })
+            .map(|expr| get_collseq_from_expr(expr, &plan.table_references))
             .collect::<Result<Vec<_>>>()?;
 
         program.emit_insn(Insn::SorterOpen {
@@ -428,7 +411,7 @@ impl<'a> GroupByAggArgumentSource<'a> {
         col_start: usize,
         aggregate: &'a Aggregate,
     ) -> Self {
-        let dest_reg_start = program.alloc_registers(aggregate.args.len());
+        let dest_reg_start = program.alloc_registers(aggregate.inputs_count());
         Self::PseudoCursor {
             cursor_id,
             col_start,
@@ -488,6 +471,32 @@ impl<'a> GroupByAggArgumentSource<'a> {
             } => Ok(*start_reg + arg_idx),
         }
     }
+    /// Read the `ORDER BY` keys of the aggregate function into contiguous registers, returning
+    /// the first one. They follow its arguments in the sorter data and in the registers.
+    pub fn translate_order_keys(&self, program: &mut ProgramBuilder) -> usize {
+        let aggregate = self.aggregate();
+        match self {
+            GroupByAggArgumentSource::PseudoCursor {
+                cursor_id,
+                col_start,
+                dest_reg_start,
+                ..
+            } => {
+                let key_start_reg = dest_reg_start + aggregate.args.len();
+                for i in 0..aggregate.order_by.len() {
+                    program.emit_column(
+                        *cursor_id,
+                        col_start + aggregate.args.len() + i,
+                        key_start_reg + i,
+                    );
+                }
+                key_start_reg
+            }
+            GroupByAggArgumentSource::Register { src_reg_start, .. } => {
+                src_reg_start + aggregate.args.len()
+            }
+        }
+    }
 }
 
 /// Emits bytecode for processing a single GROUP BY group.
@@ -583,11 +592,20 @@ pub fn group_by_process_single_group(
         return_reg: registers.reg_subrtn_acc_clear_return_offset,
     });
 
+    // Bare columns, i.e. non-aggregate columns outside of GROUP BY, may follow a min() or max()
+    let bare_columns_agg_index = plan
+        .bare_columns_agg_index()
+        .filter(|_| t_ctx.non_aggregate_expressions.len() > group_by.exprs.len());
+
     // Process each aggregate function for the current row
     program.preassign_label_to_next_insn(labels.label_grouping_agg_step);
     let cursor_index = t_ctx.non_aggregate_expressions.len(); // Skipping all columns in sorter that not an aggregation arguments
+    let mut bare_columns_agg_offset = 0;
     let mut offset = 0;
     for (i, agg) in plan.aggregates.iter().enumerate() {
+        if bare_columns_agg_index == Some(i) {
+            bare_columns_agg_offset = offset;
+        }
         let start_reg = t_ctx
             .reg_agg_start
             .expect("aggregate registers must be initialized");
@@ -620,20 +638,52 @@ pub fn group_by_process_single_group(
                 .expect("distinct aggregate context not populated");
             program.preassign_label_to_next_insn(ctx.label_on_conflict);
         }
-        offset += agg.args.len();
+        offset += agg.inputs_count();
     }
 
-    // We only need to store non-aggregate columns once per group
+    // We only need to store non-aggregate columns once per group, unless they follow a min() or
+    // max() and the row holds a new minimum or maximum
     // Skip if we've already stored them for this group
-    program.add_comment(
-        program.offset(),
-        "don't emit group columns if continuing existing group",
-    );
-    program.emit_insn(Insn::If {
-        target_pc: labels.label_acc_indicator_set_flag_true,
-        reg: registers.reg_data_in_acc_flag,
-        jump_if_null: false,
-    });
+    if let Some(agg_index) = bare_columns_agg_index {
+        let reg_arg = match &row_source {
+            GroupByRowSource::Sorter { pseudo_cursor, .. } => {
+                let reg_arg = program.alloc_register();
+                program.emit_column(
+                    *pseudo_cursor,
+                    cursor_index + bare_columns_agg_offset,
+                    reg_arg,
+                );
+                reg_arg
+            }
+            GroupByRowSource::MainLoop { start_reg_src, .. } => {
+                start_reg_src + t_ctx.non_aggregate_expressions.len() + bare_columns_agg_offset
+            }
+        };
+        let reg_best = program.alloc_register();
+        program.add_comment(
+            program.offset(),
+            "don't emit group columns unless the row holds a new min or max",
+        );
+        emit_bare_columns_row_check(
+            program,
+            &plan.table_references,
+            &plan.aggregates[agg_index],
+            reg_arg,
+            reg_best,
+            registers.reg_data_in_acc_flag,
+            labels.label_acc_indicator_set_flag_true,
+        )?;
+    } else {
+        program.add_comment(
+            program.offset(),
+            "don't emit group columns if continuing existing group",
+        );
+        program.emit_insn(Insn::If {
+            target_pc: labels.label_acc_indicator_set_flag_true,
+            reg: registers.reg_data_in_acc_flag,
+            jump_if_null: false,
+        });
+    }
 
     // Read non-aggregate columns from the current row
     match row_source {
@@ -913,6 +963,17 @@ pub fn translate_aggregation_step_groupby(
     resolver: &Resolver,
 ) -> Result<usize> {
     let num_args = agg_arg_source.num_args();
+    let aggregate = agg_arg_source.aggregate();
+    if !aggregate.order_by.is_empty() {
+        let key_start_reg = agg_arg_source.translate_order_keys(program);
+        emit_agg_order_key(
+            program,
+            referenced_tables,
+            aggregate,
+            target_register,
+            key_start_reg,
+        )?;
+    }
     let dest = match agg_arg_source.agg_func() {
         AggFunc::Avg => {
             if num_args != 1 {
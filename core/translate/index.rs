@@ -1,9 +1,12 @@
+use std::rc::Rc;
 use std::sync::Arc;
 
+use crate::translate::collate::CollationSeq;
 use crate::vdbe::insn::{CmpInsFlags, Cookie};
 use crate::{
     schema::{BTreeTable, Column, Index, IndexColumn, PseudoCursorType, Schema},
     storage::pager::CreateBTreeFlags,
+    temp::is_temp_root_page,
     util::normalize_ident,
     vdbe::{
         builder::{CursorType, ProgramBuilder},
@@ -47,6 +50,9 @@ pub fn translate_create_index(
     let Some(tbl) = tbl.btree() else {
         crate::bail_parse_error!("Error: table '{tbl_name}' is not a b-tree table.");
     };
+    if is_temp_root_page(tbl.root_page) {
+        crate::bail_parse_error!("Error: indexes on temp table '{tbl_name}' are not supported.");
+    }
     let columns = resolve_sorted_columns(&tbl, columns)?;
 
     let idx = Arc::new(Index {
@@ -68,22 +74,9 @@ pub fn translate_create_index(
         has_rowid: tbl.has_rowid,
     });
 
-    // Allocate the necessary cursors:
-    //
-    // 1. sqlite_schema_cursor_id - sqlite_schema table
-    // 2. btree_cursor_id         - new index btree
-    // 3. table_cursor_id         - table we are creating the index on
-    // 4. sorter_cursor_id        - sorter
-    // 5. pseudo_cursor_id        - pseudo table to store the sorted index values
     let sqlite_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
     let sqlite_schema_cursor_id =
         program.alloc_cursor_id(CursorType::BTreeTable(sqlite_table.clone()));
-    let btree_cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(idx.clone()));
-    let table_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(tbl.clone()));
-    let sorter_cursor_id = program.alloc_cursor_id(CursorType::Sorter);
-    let pseudo_cursor_id = program.alloc_cursor_id(CursorType::Pseudo(PseudoCursorType {
-        column_count: tbl.columns.len(),
-    }));
 
     // Create a new B-Tree and store the root page index in a register
     let root_page_reg = program.alloc_register();
@@ -110,12 +103,168 @@ pub fn translate_create_index(
         Some(sql),
     );
 
+    emit_index_build(
+        &mut program,
+        &tbl,
+        &idx,
+        RegisterOrLiteral::Register(root_page_reg),
+    );
+
+    // Keep schema table open to emit ParseSchema.
+    program.emit_insn(Insn::SetCookie {
+        db: 0,
+        cookie: Cookie::SchemaVersion,
+        value: schema.schema_version as i32 + 1,
+        p5: 0,
+    });
+    // Parse the schema table to get the index root page and add new index to Schema
+    let parse_schema_where_clause = format!("name = '{}' AND type = 'index'", idx_name);
+    program.emit_insn(Insn::ParseSchema {
+        db: 0,
+        where_clause: Some(parse_schema_where_clause),
+    });
+    // Close the final sqlite_schema cursor
+    program.emit_insn(Insn::Close {
+        cursor_id: sqlite_schema_cursor_id,
+    });
+
+    // Epilogue:
+    program.epilogue(super::emitter::TransactionMode::Write);
+
+    Ok(program)
+}
+
+pub fn translate_reindex(
+    obj_name: Option<ast::QualifiedName>,
+    schema: &Schema,
+    mut program: ProgramBuilder,
+) -> crate::Result<ProgramBuilder> {
+    // Like SQLite, the name is looked up as a collation first, then as a table and finally
+    // as an index.
+    let mut indexes = match obj_name {
+        None => schema
+            .indexes
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>(),
+        Some(name) => {
+            let name = normalize_ident(&name.name.0);
+            if let Ok(collation) = CollationSeq::new(&name) {
+                schema
+                    .indexes
+                    .values()
+                    .flatten()
+                    .filter(|idx| {
+                        // Without a COLLATE clause in the index the column's own collation
+                        // is used
+                        let table = schema.get_btree_table(&idx.table_name);
+                        idx.columns.iter().any(|col| {
+                            col.collation
+                                .or_else(|| {
+                                    table
+                                        .as_ref()
+                                        .and_then(|tbl| tbl.columns.get(col.pos_in_table))
+                                        .and_then(|column| column.collation)
+                                })
+                                .unwrap_or_default()
+                                == collation
+                        })
+                    })
+                    .cloned()
+                    .collect()
+            } else if schema.get_btree_table(&name).is_some() {
+                schema.get_indices(&name).to_vec()
+            } else if let Some(idx) = schema
+                .indexes
+                .values()
+                .flatten()
+                .find(|idx| idx.name == name)
+            {
+                vec![idx.clone()]
+            } else {
+                crate::bail_parse_error!("unable to identify the object to be reindexed");
+            }
+        }
+    };
+    indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let opts = crate::vdbe::builder::ProgramBuilderOpts {
+        num_cursors: 5 * indexes.len(),
+        approx_num_insns: 40 * indexes.len(),
+        approx_num_labels: 5 * indexes.len(),
+    };
+    program.extend(&opts);
+
+    for idx in &indexes {
+        let Some(tbl) = schema.get_btree_table(&idx.table_name) else {
+            crate::bail_parse_error!("no such table: {}", idx.table_name);
+        };
+
+        // Empty the index by repeatedly deleting its first entry, then build it again
+        // from the table contents.
+        let cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(idx.clone()));
+        program.emit_insn(Insn::OpenWrite {
+            cursor_id,
+            root_page: RegisterOrLiteral::Literal(idx.root_page),
+            name: idx.name.clone(),
+        });
+        let clear_loop_start = program.allocate_label();
+        let clear_loop_end = program.allocate_label();
+        program.preassign_label_to_next_insn(clear_loop_start);
+        program.emit_insn(Insn::Rewind {
+            cursor_id,
+            pc_if_empty: clear_loop_end,
+        });
+        program.emit_insn(Insn::Delete { cursor_id });
+        program.emit_insn(Insn::Goto {
+            target_pc: clear_loop_start,
+        });
+        program.preassign_label_to_next_insn(clear_loop_end);
+        program.emit_insn(Insn::Close { cursor_id });
+
+        emit_index_build(
+            &mut program,
+            &tbl,
+            idx,
+            RegisterOrLiteral::Literal(idx.root_page),
+        );
+    }
+
+    program.epilogue(super::emitter::TransactionMode::Write);
+
+    Ok(program)
+}
+
+/// Fills the index b-tree rooted at `root_page` with an entry for every row of `tbl`.
+/// The entries are sorted first so that they can be appended to the end of the b-tree,
+/// which is therefore expected to be empty.
+fn emit_index_build(
+    program: &mut ProgramBuilder,
+    tbl: &Rc<BTreeTable>,
+    idx: &Arc<Index>,
+    root_page: RegisterOrLiteral<usize>,
+) {
+    // Allocate the necessary cursors:
+    //
+    // 1. btree_cursor_id         - index btree being filled
+    // 2. table_cursor_id         - table the index is built on
+    // 3. sorter_cursor_id        - sorter
+    // 4. pseudo_cursor_id        - pseudo table to store the sorted index values
+    let btree_cursor_id = program.alloc_cursor_id(CursorType::BTreeIndex(idx.clone()));
+    let table_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(tbl.clone()));
+    let sorter_cursor_id = program.alloc_cursor_id(CursorType::Sorter);
+    let pseudo_cursor_id = program.alloc_cursor_id(CursorType::Pseudo(PseudoCursorType {
+        column_count: tbl.columns.len(),
+    }));
+    let num_columns = idx.columns.len();
+
     // determine the order of the columns in the index for the sorter
     let order = idx.columns.iter().map(|c| c.order).collect();
     // open the sorter and the pseudo table
     program.emit_insn(Insn::SorterOpen {
         cursor_id: sorter_cursor_id,
-        columns: columns.len(),
+        columns: num_columns,
         order,
         collations: tbl.column_collations(),
     });
@@ -123,10 +272,10 @@ pub fn translate_create_index(
     program.emit_insn(Insn::OpenPseudo {
         cursor_id: pseudo_cursor_id,
         content_reg,
-        num_fields: columns.len() + 1,
+        num_fields: num_columns + 1,
     });
 
-    // open the table we are creating the index on for reading
+    // open the table we are building the index on for reading
     program.emit_insn(Insn::OpenRead {
         cursor_id: table_cursor_id,
         root_page: tbl.root_page,
@@ -145,11 +294,11 @@ pub fn translate_create_index(
     // emit MakeRecord (index key + rowid) into record_reg.
     //
     // Then insert the record into the sorter
-    let start_reg = program.alloc_registers(columns.len() + 1);
-    for (i, (col, _)) in columns.iter().enumerate() {
-        program.emit_column(table_cursor_id, col.0, start_reg + i);
+    let start_reg = program.alloc_registers(num_columns + 1);
+    for (i, col) in idx.columns.iter().enumerate() {
+        program.emit_column(table_cursor_id, col.pos_in_table, start_reg + i);
     }
-    let rowid_reg = start_reg + columns.len();
+    let rowid_reg = start_reg + num_columns;
     program.emit_insn(Insn::RowId {
         cursor_id: table_cursor_id,
         dest: rowid_reg,
@@ -157,9 +306,9 @@ pub fn translate_create_index(
     let record_reg = program.alloc_register();
     program.emit_insn(Insn::MakeRecord {
         start_reg,
-        count: columns.len() + 1,
+        count: num_columns + 1,
         dest_reg: record_reg,
-        index_name: Some(idx_name.clone()),
+        index_name: Some(idx.name.clone()),
     });
     program.emit_insn(Insn::SorterInsert {
         cursor_id: sorter_cursor_id,
@@ -172,12 +321,12 @@ pub fn translate_create_index(
     });
     program.preassign_label_to_next_insn(loop_end_label);
 
-    // Open the index btree we created for writing to insert the
+    // Open the index btree for writing to insert the
     // newly sorted index records.
     program.emit_insn(Insn::OpenWrite {
         cursor_id: btree_cursor_id,
-        root_page: RegisterOrLiteral::Register(root_page_reg),
-        name: idx_name.clone(),
+        root_page,
+        name: idx.name.clone(),
     });
 
     let sorted_loop_start = program.allocate_label();
@@ -214,32 +363,7 @@ pub fn translate_create_index(
     });
     program.preassign_label_to_next_insn(sorted_loop_end);
 
-    // End of the outer loop
-    //
-    // Keep schema table open to emit ParseSchema, close the other cursors.
     program.close_cursors(&[sorter_cursor_id, table_cursor_id, btree_cursor_id]);
-
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: schema.schema_version as i32 + 1,
-        p5: 0,
-    });
-    // Parse the schema table to get the index root page and add new index to Schema
-    let parse_schema_where_clause = format!("name = '{}' AND type = 'index'", idx_name);
-    program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
-        where_clause: Some(parse_schema_where_clause),
-    });
-    // Close the final sqlite_schema cursor
-    program.emit_insn(Insn::Close {
-        cursor_id: sqlite_schema_cursor_id,
-    });
-
-    // Epilogue:
-    program.epilogue(super::emitter::TransactionMode::Write);
-
-    Ok(program)
 }
 
 fn resolve_sorted_columns<'a>(
@@ -260,6 +384,12 @@ fn resolve_sorted_columns<'a>(
                 table.name
             );
         };
+        // VIRTUAL columns are not in the table's records, so the index build can't read them
+        if col.1.is_virtual() {
+            crate::bail_parse_error!(
+                "Error: indexes on VIRTUAL generated column '{ident}' are not supported."
+            );
+        }
         resolved.push((col, sc.order.unwrap_or(SortOrder::Asc)));
     }
     Ok(resolved)
@@ -1,14 +1,17 @@
 use std::rc::Rc;
+use std::sync::Arc;
 
 use turso_sqlite3_parser::ast::{
     DistinctNames, Expr, InsertBody, OneSelect, QualifiedName, ResolveType, ResultColumn, With,
 };
 
-use crate::error::{SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY};
-use crate::schema::{IndexColumn, Table};
+use crate::error::{
+    SQLITE_CONSTRAINT_FOREIGNKEY, SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY,
+};
+use crate::schema::{BTreeTable, ForeignKey, Index, IndexColumn, Table};
 use crate::util::normalize_ident;
 use crate::vdbe::builder::ProgramBuilderOpts;
-use crate::vdbe::insn::{IdxInsertFlags, InsertFlags, RegisterOrLiteral};
+use crate::vdbe::insn::{CmpInsFlags, IdxInsertFlags, InsertFlags, RegisterOrLiteral};
 use crate::vdbe::BranchOffset;
 use crate::{
     schema::{Column, Schema},
@@ -19,11 +22,17 @@ use crate::{
 };
 use crate::{Result, SymbolTable, VirtualTable};
 
+use super::check::emit_check_constraints;
+use super::collate::CollationSeq;
 use super::emitter::Resolver;
 use super::expr::{translate_expr, translate_expr_no_constant_opt, NoConstantOptReason};
+use super::generated::{emit_generated_columns, emit_record_registers};
 use super::optimizer::rewrite_expr;
 use super::plan::QueryDestination;
+use super::schema::SQLITE_SEQUENCE_TABLEID;
 use super::select::translate_select;
+use super::trigger::{emit_triggers, TriggerOperation, TriggerRow};
+use super::upsert::{ConflictRow, ConflictTarget, Upserts};
 
 struct TempTableCtx {
     cursor_id: usize,
@@ -41,6 +50,7 @@ pub fn translate_insert(
     mut body: InsertBody,
     _returning: Option<Vec<ResultColumn>>,
     syms: &SymbolTable,
+    foreign_keys: bool,
     mut program: ProgramBuilder,
 ) -> Result<ProgramBuilder> {
     let opts = ProgramBuilderOpts {
@@ -52,9 +62,6 @@ pub fn translate_insert(
     if with.is_some() {
         crate::bail_parse_error!("WITH clause is not supported");
     }
-    if on_conflict.is_some() {
-        crate::bail_parse_error!("ON CONFLICT clause is not supported");
-    }
 
     if schema.table_has_indexes(&tbl_name.name.to_string()) && !schema.indexes_enabled() {
         // Let's disable altering a table with indices altogether instead of checking column by
@@ -90,9 +97,19 @@ pub fn translate_insert(
     if !btree_table.has_rowid {
         crate::bail_parse_error!("INSERT into WITHOUT ROWID table is not supported");
     }
+    // Without a conflict clause, a constraint violation aborts the statement
+    let on_conflict = on_conflict.unwrap_or(ResolveType::Abort);
+    if on_conflict == ResolveType::Rollback {
+        program.rollback_on_constraint = true;
+    }
 
     let root_page = btree_table.root_page;
 
+    let upsert = match &mut body {
+        InsertBody::Select(_, upsert) => upsert.take(),
+        InsertBody::DefaultValues => None,
+    };
+    let mut param_idx = 1;
     let mut values: Option<Vec<Expr>> = None;
     let inserting_multiple_rows = match &mut body {
         InsertBody::Select(select, _) => match select.body.select.as_mut() {
@@ -101,7 +118,6 @@ pub fn translate_insert(
                 if values_expr.is_empty() {
                     crate::bail_parse_error!("no values to insert");
                 }
-                let mut param_idx = 1;
                 for expr in values_expr.iter_mut().flat_map(|v| v.iter_mut()) {
                     rewrite_expr(expr, &mut param_idx)?;
                 }
@@ -115,11 +131,11 @@ pub fn translate_insert(
 
     let halt_label = program.allocate_label();
     let loop_start_label = program.allocate_label();
+    let autoincrement = emit_autoincrement_begin(&mut program, schema, &btree_table)?;
 
     let mut yield_reg_opt = None;
     let mut temp_table_ctx = None;
     let (num_values, cursor_id) = match body {
-        // TODO: upsert
         InsertBody::Select(select, _) => {
             // Simple Common case of INSERT INTO <table> VALUES (...)
             if matches!(select.body.select.as_ref(),  OneSelect::Values(values) if values.len() <= 1)
@@ -266,6 +282,13 @@ pub fn translate_insert(
             )
         })
         .collect::<Vec<(&String, usize, usize)>>();
+    let upserts = Upserts::new(
+        &mut program,
+        upsert,
+        &btree_table,
+        schema.get_indices(&table_name.0),
+        &mut param_idx,
+    )?;
 
     let column_mappings = resolve_columns_for_insert(&table, &columns, num_values)?;
     // Check if rowid was provided (through INTEGER PRIMARY KEY as a rowid alias)
@@ -365,43 +388,100 @@ pub fn translate_insert(
     program.emit_insn(Insn::NewRowid {
         cursor: cursor_id,
         rowid_reg,
-        prev_largest_reg: 0,
+        prev_largest_reg: autoincrement
+            .as_ref()
+            .map_or(0, |autoincrement| autoincrement.largest_rowid_reg()),
     });
 
     if let Some(must_be_int_label) = check_rowid_is_integer_label {
         program.resolve_label(must_be_int_label, program.offset());
         // If the user provided a rowid, it must be an integer.
         program.emit_insn(Insn::MustBeInt { reg: rowid_reg });
+        // A rowid provided by the user counts as used by an AUTOINCREMENT table too
+        if let Some(autoincrement) = &autoincrement {
+            program.emit_insn(Insn::MemMax {
+                dest_reg: autoincrement.largest_rowid_reg(),
+                src_reg: rowid_reg,
+            });
+        }
     }
 
-    // Check uniqueness constraint for rowid if it was provided by user.
-    // When the DB allocates it there are no need for separate uniqueness checks.
-    if has_user_provided_rowid {
-        let make_record_label = program.allocate_label();
-        program.emit_insn(Insn::NotExists {
-            cursor: cursor_id,
-            rowid_reg,
-            target_pc: make_record_label,
-        });
-        let rowid_column_name = if let Some(index) = rowid_alias_index {
-            btree_table
-                .columns
-                .get(index)
-                .unwrap()
-                .name
-                .as_ref()
-                .expect("column name is None")
-        } else {
-            "rowid"
-        };
+    // Rows skipped by an IGNORE conflict resolution continue with the next row
+    let row_done_label = program.allocate_label();
+    // Under REPLACE, the rows conflicting with the new one are deleted along with their index
+    // entries
+    let index_cursors = schema
+        .get_indices(&table_name.0)
+        .iter()
+        .zip(idx_cursors.iter())
+        .map(|(index, (_, _, idx_cursor_id))| (index.clone(), *idx_cursor_id))
+        .collect::<Vec<_>>();
 
-        program.emit_insn(Insn::Halt {
-            err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
-            description: format!("{}.{}", table_name.0, rowid_column_name),
-        });
-        program.preassign_label_to_next_insn(make_record_label);
+    emit_generated_columns(
+        &mut program,
+        &btree_table,
+        column_registers_start,
+        rowid_reg,
+        &resolver,
+    )?;
+
+    // NOT NULL constraints are checked first, as the default that replaces a NULL under REPLACE
+    // is part of the keys checked for uniqueness
+    for (i, col) in column_mappings
+        .iter()
+        .enumerate()
+        .filter(|(_, col)| col.column.notnull)
+    {
+        let target_reg = i + column_registers_start;
+        match (on_conflict, &col.column.default) {
+            (ResolveType::Ignore, _) => {
+                program.emit_insn(Insn::IsNull {
+                    reg: target_reg,
+                    target_pc: row_done_label,
+                });
+            }
+            (ResolveType::Replace, Some(default)) => {
+                let not_null_label = program.allocate_label();
+                program.emit_insn(Insn::NotNull {
+                    reg: target_reg,
+                    target_pc: not_null_label,
+                });
+                translate_expr_no_constant_opt(
+                    &mut program,
+                    None,
+                    default,
+                    target_reg,
+                    &resolver,
+                    NoConstantOptReason::RegisterReuse,
+                )?;
+                program.preassign_label_to_next_insn(not_null_label);
+            }
+            _ => {
+                program.emit_insn(Insn::HaltIfNull {
+                    target_reg,
+                    err_code: SQLITE_CONSTRAINT_NOTNULL,
+                    description: format!(
+                        "{}.{}",
+                        table_name,
+                        col.column
+                            .name
+                            .as_ref()
+                            .expect("Column name must be present")
+                    ),
+                });
+            }
+        }
     }
 
+    emit_check_constraints(
+        &mut program,
+        &btree_table,
+        column_registers_start,
+        rowid_reg,
+        &resolver,
+        matches!(on_conflict, ResolveType::Ignore).then_some(row_done_label),
+    )?;
+
     match table.btree() {
         Some(t) if t.is_strict => {
             program.emit_insn(Insn::TypeCheck {
@@ -414,7 +494,53 @@ pub fn translate_insert(
         _ => (),
     }
 
+    // Check uniqueness constraint for rowid if it was provided by user.
+    // When the DB allocates it there are no need for separate uniqueness checks.
+    if has_user_provided_rowid {
+        let make_record_label = program.allocate_label();
+        program.emit_insn(Insn::NotExists {
+            cursor: cursor_id,
+            rowid_reg,
+            target_pc: make_record_label,
+        });
+        let resolved = upserts.emit_conflict(
+            &mut program,
+            &ConflictTarget::Rowid,
+            ConflictRow::Register(rowid_reg),
+            row_done_label,
+        );
+        match on_conflict {
+            _ if resolved => {}
+            ResolveType::Ignore => program.emit_insn(Insn::Goto {
+                target_pc: row_done_label,
+            }),
+            ResolveType::Replace => emit_replace_delete(&mut program, cursor_id, &index_cursors),
+            _ => {
+                let rowid_column_name = if let Some(index) = rowid_alias_index {
+                    btree_table
+                        .columns
+                        .get(index)
+                        .unwrap()
+                        .name
+                        .as_ref()
+                        .expect("column name is None")
+                } else {
+                    "rowid"
+                };
+
+                program.emit_insn(Insn::Halt {
+                    err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
+                    description: format!("{}.{}", table_name.0, rowid_column_name),
+                });
+            }
+        }
+        program.preassign_label_to_next_insn(make_record_label);
+    }
+
+    // Every unique index is checked before any index is written to, so that a row skipped by
+    // IGNORE leaves no entries behind
     let index_col_mappings = resolve_indicies_for_insert(schema, table.as_ref(), &column_mappings)?;
+    let mut index_records = Vec::with_capacity(index_col_mappings.len());
     for index_col_mapping in index_col_mappings {
         // find which cursor we opened earlier for this index
         let idx_cursor_id = idx_cursors
@@ -464,37 +590,81 @@ pub fn translate_insert(
                 record_reg: idx_start_reg,
                 num_regs: num_cols,
             });
-            let column_names = index_col_mapping.columns.iter().enumerate().fold(
-                String::with_capacity(50),
-                |mut accum, (idx, (index, _))| {
-                    if idx > 0 {
-                        accum.push_str(", ");
-                    }
-
-                    accum.push_str(&btree_table.name);
-                    accum.push('.');
-
-                    let name = btree_table
-                        .columns
-                        .get(*index)
-                        .unwrap()
-                        .name
-                        .as_ref()
-                        .expect("column name is None");
-                    accum.push_str(name);
-
-                    accum
-                },
+            let resolved = upserts.emit_conflict(
+                &mut program,
+                &ConflictTarget::Index(index.name.clone()),
+                ConflictRow::IndexCursor(idx_cursor_id),
+                row_done_label,
             );
-
-            program.emit_insn(Insn::Halt {
-                err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
-                description: column_names,
-            });
+            match on_conflict {
+                _ if resolved => {}
+                ResolveType::Ignore => program.emit_insn(Insn::Goto {
+                    target_pc: row_done_label,
+                }),
+                ResolveType::Replace => {
+                    // The index cursor points to the entry of the conflicting row
+                    let conflict_rowid_reg = program.alloc_register();
+                    program.emit_insn(Insn::IdxRowId {
+                        cursor_id: idx_cursor_id,
+                        dest: conflict_rowid_reg,
+                    });
+                    program.emit_insn(Insn::NotExists {
+                        cursor: cursor_id,
+                        rowid_reg: conflict_rowid_reg,
+                        target_pc: label_idx_insert,
+                    });
+                    emit_replace_delete(&mut program, cursor_id, &index_cursors);
+                }
+                _ => {
+                    let column_names = index_col_mapping.columns.iter().enumerate().fold(
+                        String::with_capacity(50),
+                        |mut accum, (idx, (index, _))| {
+                            if idx > 0 {
+                                accum.push_str(", ");
+                            }
+
+                            accum.push_str(&btree_table.name);
+                            accum.push('.');
+
+                            let name = btree_table
+                                .columns
+                                .get(*index)
+                                .unwrap()
+                                .name
+                                .as_ref()
+                                .expect("column name is None");
+                            accum.push_str(name);
+
+                            accum
+                        },
+                    );
+
+                    program.emit_insn(Insn::Halt {
+                        err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
+                        description: column_names,
+                    });
+                }
+            }
 
             program.resolve_label(label_idx_insert, program.offset());
         }
+        index_records.push((idx_cursor_id, record_reg, idx_start_reg, num_cols));
+    }
+
+    if foreign_keys {
+        for fk in btree_table.foreign_keys.iter() {
+            emit_foreign_key_check(
+                &mut program,
+                schema,
+                &btree_table,
+                fk,
+                column_registers_start,
+                rowid_reg,
+            )?;
+        }
+    }
 
+    for (idx_cursor_id, record_reg, idx_start_reg, num_cols) in index_records {
         // now do the actual index insertion using the unpacked registers
         program.emit_insn(Insn::IdxInsert {
             cursor_id: idx_cursor_id,
@@ -506,29 +676,23 @@ pub fn translate_insert(
         });
     }
 
-    for (i, col) in column_mappings
-        .iter()
-        .enumerate()
-        .filter(|(_, col)| col.column.notnull)
-    {
-        let target_reg = i + column_registers_start;
-        program.emit_insn(Insn::HaltIfNull {
-            target_reg,
-            err_code: SQLITE_CONSTRAINT_NOTNULL,
-            description: format!(
-                "{}.{}",
-                table_name,
-                col.column
-                    .name
-                    .as_ref()
-                    .expect("Column name must be present")
-            ),
+    if on_conflict == ResolveType::Replace {
+        // Deleting the conflicting rows moved the table cursor, so it is positioned again at
+        // the place of the new row
+        let insert_label = program.allocate_label();
+        program.emit_insn(Insn::NotExists {
+            cursor: cursor_id,
+            rowid_reg,
+            target_pc: insert_label,
         });
+        program.preassign_label_to_next_insn(insert_label);
     }
     // Create and insert the record
+    let (record_start_reg, record_num_cols) =
+        emit_record_registers(&mut program, &btree_table, column_registers_start);
     program.emit_insn(Insn::MakeRecord {
-        start_reg: column_registers_start,
-        count: num_cols,
+        start_reg: record_start_reg,
+        count: record_num_cols,
         dest_reg: record_register,
         index_name: None,
     });
@@ -540,6 +704,36 @@ pub fn translate_insert(
         flag: InsertFlags::new(),
         table_name: table_name.to_string(),
     });
+    emit_triggers(
+        &mut program,
+        schema,
+        syms,
+        &btree_table,
+        TriggerOperation::Insert,
+        Some(TriggerRow {
+            columns_start: column_registers_start,
+            rowid_reg,
+        }),
+        None,
+    )?;
+    if upserts.has_updates() {
+        program.emit_insn(Insn::Goto {
+            target_pc: row_done_label,
+        });
+        upserts.emit_updates(
+            &mut program,
+            schema,
+            syms,
+            &btree_table,
+            cursor_id,
+            &index_cursors,
+            column_registers_start,
+            rowid_reg,
+            foreign_keys,
+            row_done_label,
+        )?;
+    }
+    program.preassign_label_to_next_insn(row_done_label);
 
     if inserting_multiple_rows {
         if let Some(temp_table_ctx) = temp_table_ctx {
@@ -561,11 +755,581 @@ pub fn translate_insert(
     }
 
     program.resolve_label(halt_label, program.offset());
+    if let Some(autoincrement) = autoincrement {
+        emit_autoincrement_end(&mut program, &autoincrement);
+    }
     program.epilogue(super::emitter::TransactionMode::Write);
 
     Ok(program)
 }
 
+/// The entry of an AUTOINCREMENT table in `sqlite_sequence`, loaded before the rows are inserted
+/// and written back once they all are.
+struct AutoincrementCtx {
+    cursor_id: usize,
+    /// Rowid of the entry in `sqlite_sequence`, NULL if the table has none yet. It is followed
+    /// by the registers of the entry: the name of the table and the largest rowid used so far.
+    rowid_reg: usize,
+}
+
+impl AutoincrementCtx {
+    fn name_reg(&self) -> usize {
+        self.rowid_reg + 1
+    }
+
+    fn largest_rowid_reg(&self) -> usize {
+        self.rowid_reg + 2
+    }
+}
+
+/// Looks up the largest rowid used so far by `table` in `sqlite_sequence`, if the table is an
+/// AUTOINCREMENT one, so that new rowids are always larger than it.
+fn emit_autoincrement_begin(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    table: &BTreeTable,
+) -> Result<Option<AutoincrementCtx>> {
+    if !table.has_autoincrement {
+        return Ok(None);
+    }
+    let Some(sequence_table) = schema.get_btree_table(SQLITE_SEQUENCE_TABLEID) else {
+        crate::bail_parse_error!("no such table: {}", SQLITE_SEQUENCE_TABLEID);
+    };
+    let ctx = AutoincrementCtx {
+        cursor_id: program.alloc_cursor_id(CursorType::BTreeTable(sequence_table.clone())),
+        rowid_reg: program.alloc_registers(3),
+    };
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id: ctx.cursor_id,
+        root_page: RegisterOrLiteral::Literal(sequence_table.root_page),
+        name: sequence_table.name.clone(),
+    });
+    program.emit_null(ctx.rowid_reg, None);
+    program.emit_string8(table.name.clone(), ctx.name_reg());
+    program.emit_insn(Insn::Integer {
+        value: 0,
+        dest: ctx.largest_rowid_reg(),
+    });
+
+    let loop_start_label = program.allocate_label();
+    let loop_end_label = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id: ctx.cursor_id,
+        pc_if_empty: loop_end_label,
+    });
+    program.preassign_label_to_next_insn(loop_start_label);
+    let entry_name_reg = program.alloc_register();
+    program.emit_column(ctx.cursor_id, 0, entry_name_reg);
+    let next_label = program.allocate_label();
+    // Table names are case insensitive
+    program.emit_insn(Insn::Ne {
+        lhs: entry_name_reg,
+        rhs: ctx.name_reg(),
+        target_pc: next_label,
+        flags: CmpInsFlags::default(),
+        collation: Some(CollationSeq::NoCase),
+    });
+    program.emit_insn(Insn::RowId {
+        cursor_id: ctx.cursor_id,
+        dest: ctx.rowid_reg,
+    });
+    program.emit_column(ctx.cursor_id, 1, ctx.largest_rowid_reg());
+    program.emit_insn(Insn::Goto {
+        target_pc: loop_end_label,
+    });
+    program.preassign_label_to_next_insn(next_label);
+    program.emit_insn(Insn::Next {
+        cursor_id: ctx.cursor_id,
+        pc_if_next: loop_start_label,
+    });
+    program.preassign_label_to_next_insn(loop_end_label);
+    Ok(Some(ctx))
+}
+
+/// Writes the largest rowid used by the table back to its entry in `sqlite_sequence`, creating
+/// the entry if the table had none.
+fn emit_autoincrement_end(program: &mut ProgramBuilder, ctx: &AutoincrementCtx) {
+    let has_entry_label = program.allocate_label();
+    program.emit_insn(Insn::NotNull {
+        reg: ctx.rowid_reg,
+        target_pc: has_entry_label,
+    });
+    program.emit_insn(Insn::NewRowid {
+        cursor: ctx.cursor_id,
+        rowid_reg: ctx.rowid_reg,
+        prev_largest_reg: 0,
+    });
+    program.preassign_label_to_next_insn(has_entry_label);
+    let record_reg = program.alloc_register();
+    program.emit_insn(Insn::MakeRecord {
+        start_reg: ctx.name_reg(),
+        count: 2,
+        dest_reg: record_reg,
+        index_name: None,
+    });
+    program.emit_insn(Insn::Insert {
+        cursor: ctx.cursor_id,
+        key_reg: ctx.rowid_reg,
+        record_reg,
+        flag: InsertFlags::new().skip_last_rowid(),
+        table_name: SQLITE_SEQUENCE_TABLEID.to_string(),
+    });
+}
+
+/// Deletes the row the table cursor `cursor_id` points to, along with its entries in the
+/// `indexes` of the table, to make room for a new row that conflicts with it under REPLACE.
+pub(crate) fn emit_replace_delete(
+    program: &mut ProgramBuilder,
+    cursor_id: usize,
+    indexes: &[(Arc<Index>, usize)],
+) {
+    for (index, index_cursor_id) in indexes {
+        let num_regs = index.columns.len() + 1;
+        let start_reg = program.alloc_registers(num_regs);
+        for (i, column) in index.columns.iter().enumerate() {
+            program.emit_column(cursor_id, column.pos_in_table, start_reg + i);
+        }
+        program.emit_insn(Insn::RowId {
+            cursor_id,
+            dest: start_reg + num_regs - 1,
+        });
+        program.emit_insn(Insn::IdxDelete {
+            start_reg,
+            num_regs,
+            cursor_id: *index_cursor_id,
+        });
+    }
+    program.emit_insn(Insn::Delete { cursor_id });
+}
+
+/// How the parent key of a foreign key is looked up in the parent table.
+pub(crate) enum ParentKeyLookup {
+    /// The parent key is the rowid of the parent table
+    Rowid,
+    /// The parent key is covered by a unique index of the parent table
+    Index(Arc<Index>),
+}
+
+/// Resolves how the parent key referenced by `fk` can be looked up.
+/// As in SQLite, the parent key must be the primary key of the parent table or
+/// have a unique index on it, otherwise the foreign key is considered a mismatch.
+pub(crate) fn resolve_parent_key_lookup(
+    schema: &Schema,
+    child: &BTreeTable,
+    fk: &ForeignKey,
+    parent: &BTreeTable,
+) -> Result<(ParentKeyLookup, Vec<String>)> {
+    let parent_columns = if fk.parent_columns.is_empty() {
+        parent
+            .primary_key_columns
+            .iter()
+            .map(|(name, _)| normalize_ident(name))
+            .collect::<Vec<_>>()
+    } else {
+        fk.parent_columns.clone()
+    };
+    if parent_columns.len() == fk.child_columns.len() {
+        if let [column] = parent_columns.as_slice() {
+            if parent
+                .get_column(column)
+                .is_some_and(|(_, column)| column.is_rowid_alias)
+            {
+                return Ok((ParentKeyLookup::Rowid, parent_columns));
+            }
+        }
+        let index = schema.get_indices(&parent.name).iter().find(|index| {
+            index.unique
+                && index.columns.len() == parent_columns.len()
+                && index
+                    .columns
+                    .iter()
+                    .all(|column| parent_columns.contains(&column.name))
+        });
+        if let Some(index) = index {
+            return Ok((ParentKeyLookup::Index(index.clone()), parent_columns));
+        }
+    }
+    crate::bail_parse_error!(
+        "foreign key mismatch - \"{}\" referencing \"{}\"",
+        child.name,
+        parent.name
+    );
+}
+
+/// Emits a check that the row about to be inserted references an existing row of the parent table.
+/// The constraint is satisfied if any of the child key columns is NULL.
+pub(crate) fn emit_foreign_key_check(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    table: &BTreeTable,
+    fk: &ForeignKey,
+    column_registers_start: usize,
+    rowid_reg: usize,
+) -> Result<()> {
+    let Some(parent) = schema.get_btree_table(&fk.parent_table) else {
+        crate::bail_parse_error!("no such table: {}", fk.parent_table);
+    };
+    let (lookup, parent_columns) = resolve_parent_key_lookup(schema, table, fk, &parent)?;
+
+    // The rowid alias column register is NULL in the record, its value lives in the rowid register
+    let child_registers = fk
+        .child_columns
+        .iter()
+        .map(|name| match table.get_column(name) {
+            Some((_, column)) if column.is_rowid_alias => Ok(rowid_reg),
+            Some((pos, _)) => Ok(column_registers_start + pos),
+            None => {
+                crate::bail_parse_error!("unknown column \"{}\" in foreign key definition", name)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let label_fk_ok = program.allocate_label();
+    let label_fk_violation = program.allocate_label();
+    for reg in child_registers.iter() {
+        program.emit_insn(Insn::IsNull {
+            reg: *reg,
+            target_pc: label_fk_ok,
+        });
+    }
+
+    let cursor_id = open_parent_key_cursor(program, &parent, &lookup);
+    emit_parent_key_seek(
+        program,
+        cursor_id,
+        &lookup,
+        &parent_columns,
+        &child_registers,
+        label_fk_violation,
+    );
+    program.emit_insn(Insn::Goto {
+        target_pc: label_fk_ok,
+    });
+    program.preassign_label_to_next_insn(label_fk_violation);
+    program.emit_insn(Insn::Halt {
+        err_code: SQLITE_CONSTRAINT_FOREIGNKEY,
+        description: String::new(),
+    });
+    program.preassign_label_to_next_insn(label_fk_ok);
+
+    Ok(())
+}
+
+/// Emits checks that no row of a child table references the parent key of the row of `parent`
+/// the table cursor `cursor_id` points to, as that key is about to be deleted or changed.
+/// Only the foreign keys whose parent key contains one of `changed_columns` are checked, all of
+/// them when it is `None`. With `new_row`, the registers of the updated row and its rowid, a key
+/// left unchanged is not checked.
+///
+/// The referencing rows are found by scanning the child table, and each of them is counted as a
+/// violation with [Insn::FkCounter]. The statement fails if they still are when it ends, unless
+/// it deleted the referencing rows too, see [emit_foreign_key_child_checks]. A row referencing
+/// its own key is not counted, as it goes away with it.
+pub(crate) fn emit_foreign_key_parent_checks(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    parent: &BTreeTable,
+    cursor_id: usize,
+    changed_columns: Option<&[usize]>,
+    new_row: Option<(usize, usize)>,
+) -> Result<()> {
+    let parent_name = normalize_ident(&parent.name);
+    let mut children = schema
+        .tables
+        .values()
+        .filter_map(|table| table.btree())
+        .filter(|table| {
+            table
+                .foreign_keys
+                .iter()
+                .any(|fk| fk.parent_table == parent_name)
+        })
+        .collect::<Vec<_>>();
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for child in children {
+        for fk in child
+            .foreign_keys
+            .iter()
+            .filter(|fk| fk.parent_table == parent_name)
+        {
+            let (_, parent_columns) = resolve_parent_key_lookup(schema, &child, fk, parent)?;
+            let parent_columns = parent_columns
+                .iter()
+                .map(|name| {
+                    let (pos, column) = parent.get_column(name).expect("parent key column");
+                    (pos, column.is_rowid_alias)
+                })
+                .collect::<Vec<_>>();
+            if let Some(changed_columns) = changed_columns {
+                if !parent_columns
+                    .iter()
+                    .any(|(pos, _)| changed_columns.contains(pos))
+                {
+                    continue;
+                }
+            }
+            let child_columns = fk
+                .child_columns
+                .iter()
+                .map(|name| match child.get_column(name) {
+                    Some((pos, column)) => Ok((pos, column.is_rowid_alias)),
+                    None => {
+                        crate::bail_parse_error!(
+                            "unknown column \"{}\" in foreign key definition",
+                            name
+                        )
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let label_fk_ok = program.allocate_label();
+            // A key with a NULL column is referenced by no row
+            let key_start_reg = program.alloc_registers(parent_columns.len());
+            for (i, &(pos, is_rowid_alias)) in parent_columns.iter().enumerate() {
+                if is_rowid_alias {
+                    program.emit_insn(Insn::RowId {
+                        cursor_id,
+                        dest: key_start_reg + i,
+                    });
+                } else {
+                    program.emit_column(cursor_id, pos, key_start_reg + i);
+                }
+                program.emit_insn(Insn::IsNull {
+                    reg: key_start_reg + i,
+                    target_pc: label_fk_ok,
+                });
+            }
+            if let Some((columns_start, rowid_reg)) = new_row {
+                let label_key_changed = program.allocate_label();
+                for (i, &(pos, is_rowid_alias)) in parent_columns.iter().enumerate() {
+                    program.emit_insn(Insn::Ne {
+                        lhs: key_start_reg + i,
+                        rhs: if is_rowid_alias {
+                            rowid_reg
+                        } else {
+                            columns_start + pos
+                        },
+                        target_pc: label_key_changed,
+                        flags: CmpInsFlags::default().jump_if_null(),
+                        collation: program.curr_collation(),
+                    });
+                }
+                program.emit_insn(Insn::Goto {
+                    target_pc: label_fk_ok,
+                });
+                program.preassign_label_to_next_insn(label_key_changed);
+            }
+
+            let child_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(child.clone()));
+            program.emit_insn(Insn::OpenRead {
+                cursor_id: child_cursor_id,
+                root_page: child.root_page,
+            });
+            let label_loop_start = program.allocate_label();
+            let label_next = program.allocate_label();
+            program.emit_insn(Insn::Rewind {
+                cursor_id: child_cursor_id,
+                pc_if_empty: label_fk_ok,
+            });
+            program.preassign_label_to_next_insn(label_loop_start);
+            let child_reg = program.alloc_register();
+            if child.name == parent.name {
+                let rowid_reg = program.alloc_register();
+                program.emit_insn(Insn::RowId {
+                    cursor_id,
+                    dest: rowid_reg,
+                });
+                program.emit_insn(Insn::RowId {
+                    cursor_id: child_cursor_id,
+                    dest: child_reg,
+                });
+                program.emit_insn(Insn::Eq {
+                    lhs: child_reg,
+                    rhs: rowid_reg,
+                    target_pc: label_next,
+                    flags: CmpInsFlags::default(),
+                    collation: program.curr_collation(),
+                });
+            }
+            for (i, &(pos, is_rowid_alias)) in child_columns.iter().enumerate() {
+                // The rowid alias column is NULL in the record, its value is the rowid
+                if is_rowid_alias {
+                    program.emit_insn(Insn::RowId {
+                        cursor_id: child_cursor_id,
+                        dest: child_reg,
+                    });
+                } else {
+                    program.emit_column(child_cursor_id, pos, child_reg);
+                }
+                program.emit_insn(Insn::Ne {
+                    lhs: child_reg,
+                    rhs: key_start_reg + i,
+                    target_pc: label_next,
+                    flags: CmpInsFlags::default().jump_if_null(),
+                    collation: program.curr_collation(),
+                });
+            }
+            program.emit_insn(Insn::FkCounter { increment_value: 1 });
+            program.preassign_label_to_next_insn(label_next);
+            program.emit_insn(Insn::Next {
+                cursor_id: child_cursor_id,
+                pc_if_next: label_loop_start,
+            });
+            program.preassign_label_to_next_insn(label_fk_ok);
+        }
+    }
+    Ok(())
+}
+
+/// Emits the counterpart of [emit_foreign_key_parent_checks] for the row of `table` the table
+/// cursor `cursor_id` points to, as it is about to be deleted or changed. If the parent key the
+/// row references is missing, the violation counted when that key went away is resolved.
+/// Only the foreign keys whose child key contains one of `changed_columns` are checked, all of
+/// them when it is `None`.
+pub(crate) fn emit_foreign_key_child_checks(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    table: &BTreeTable,
+    cursor_id: usize,
+    changed_columns: Option<&[usize]>,
+) -> Result<()> {
+    for fk in table.foreign_keys.iter() {
+        let child_columns = fk
+            .child_columns
+            .iter()
+            .map(|name| match table.get_column(name) {
+                Some((pos, column)) => Ok((pos, column.is_rowid_alias)),
+                None => {
+                    crate::bail_parse_error!(
+                        "unknown column \"{}\" in foreign key definition",
+                        name
+                    )
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(changed_columns) = changed_columns {
+            if !child_columns
+                .iter()
+                .any(|(pos, _)| changed_columns.contains(pos))
+            {
+                continue;
+            }
+        }
+        // Without a parent table no violation was counted for its key going away
+        let Some(parent) = schema.get_btree_table(&fk.parent_table) else {
+            continue;
+        };
+        let (lookup, parent_columns) = resolve_parent_key_lookup(schema, table, fk, &parent)?;
+
+        let label_fk_ok = program.allocate_label();
+        let label_parent_missing = program.allocate_label();
+        program.emit_insn(Insn::FkIfZero {
+            target_pc: label_fk_ok,
+        });
+        let key_start_reg = program.alloc_registers(child_columns.len());
+        for (i, &(pos, is_rowid_alias)) in child_columns.iter().enumerate() {
+            if is_rowid_alias {
+                program.emit_insn(Insn::RowId {
+                    cursor_id,
+                    dest: key_start_reg + i,
+                });
+            } else {
+                program.emit_column(cursor_id, pos, key_start_reg + i);
+            }
+            program.emit_insn(Insn::IsNull {
+                reg: key_start_reg + i,
+                target_pc: label_fk_ok,
+            });
+        }
+        let child_registers =
+            (key_start_reg..key_start_reg + child_columns.len()).collect::<Vec<_>>();
+        let parent_cursor_id = open_parent_key_cursor(program, &parent, &lookup);
+        emit_parent_key_seek(
+            program,
+            parent_cursor_id,
+            &lookup,
+            &parent_columns,
+            &child_registers,
+            label_parent_missing,
+        );
+        program.emit_insn(Insn::Goto {
+            target_pc: label_fk_ok,
+        });
+        program.preassign_label_to_next_insn(label_parent_missing);
+        program.emit_insn(Insn::FkCounter {
+            increment_value: -1,
+        });
+        program.preassign_label_to_next_insn(label_fk_ok);
+    }
+    Ok(())
+}
+
+/// Opens a cursor on the table or index the parent key of a foreign key is looked up in.
+pub(crate) fn open_parent_key_cursor(
+    program: &mut ProgramBuilder,
+    parent: &Rc<BTreeTable>,
+    lookup: &ParentKeyLookup,
+) -> usize {
+    let (cursor_id, root_page) = match lookup {
+        ParentKeyLookup::Rowid => (
+            program.alloc_cursor_id(CursorType::BTreeTable(parent.clone())),
+            parent.root_page,
+        ),
+        ParentKeyLookup::Index(index) => (
+            program.alloc_cursor_id(CursorType::BTreeIndex(index.clone())),
+            index.root_page,
+        ),
+    };
+    program.emit_insn(Insn::OpenRead {
+        cursor_id,
+        root_page,
+    });
+    cursor_id
+}
+
+/// Emits a lookup of the parent key held in `child_registers`, in the order of `parent_columns`,
+/// that jumps to `label_not_found` if no row of the parent table has it.
+pub(crate) fn emit_parent_key_seek(
+    program: &mut ProgramBuilder,
+    cursor_id: usize,
+    lookup: &ParentKeyLookup,
+    parent_columns: &[String],
+    child_registers: &[usize],
+    label_not_found: BranchOffset,
+) {
+    match lookup {
+        ParentKeyLookup::Rowid => {
+            program.emit_insn(Insn::SeekRowid {
+                cursor_id,
+                src_reg: child_registers[0],
+                target_pc: label_not_found,
+            });
+        }
+        ParentKeyLookup::Index(index) => {
+            // The key must be laid out in the order of the index columns
+            let key_start_reg = program.alloc_registers(index.columns.len());
+            for (i, index_column) in index.columns.iter().enumerate() {
+                let pos = parent_columns
+                    .iter()
+                    .position(|name| name == &index_column.name)
+                    .expect("index column should be part of the parent key");
+                program.emit_insn(Insn::Copy {
+                    src_reg: child_registers[pos],
+                    dst_reg: key_start_reg + i,
+                    amount: 0,
+                });
+            }
+            program.emit_insn(Insn::NoConflict {
+                cursor_id,
+                target_pc: label_not_found,
+                record_reg: key_start_reg,
+                num_regs: index.columns.len(),
+            });
+        }
+    }
+}
+
 #[derive(Debug)]
 /// Represents how a column should be populated during an INSERT.
 /// Contains both the column definition and optionally the index into the VALUES tuple.
@@ -603,22 +1367,31 @@ fn resolve_columns_for_insert<'a>(
     let table_columns = table.columns();
     // Case 1: No columns specified - map values to columns in order
     if columns.is_none() {
-        if num_values != table_columns.len() {
+        // Generated columns are computed, so they don't take a value
+        let num_writable_columns = table_columns
+            .iter()
+            .filter(|col| col.generated.is_none())
+            .count();
+        if num_values != num_writable_columns {
             crate::bail_parse_error!(
                 "table {} has {} columns but {} values were supplied",
                 &table.get_name(),
-                table_columns.len(),
+                num_writable_columns,
                 num_values
             );
         }
 
         // Map each column to either its corresponding value index or None
+        let mut value_indices = 0..num_values;
         return Ok(table_columns
             .iter()
-            .enumerate()
-            .map(|(i, col)| ColumnMapping {
+            .map(|col| ColumnMapping {
                 column: col,
-                value_index: if i < num_values { Some(i) } else { None },
+                value_index: if col.generated.is_none() {
+                    value_indices.next()
+                } else {
+                    None
+                },
                 default_value: col.default.as_ref(),
             })
             .collect());
@@ -650,6 +1423,9 @@ fn resolve_columns_for_insert<'a>(
                 column_name
             );
         };
+        if table_columns[table_index].generated.is_some() {
+            crate::bail_parse_error!("cannot INSERT into generated column \"{}\"", column_name);
+        }
 
         mappings[table_index].value_index = Some(value_index);
     }
@@ -738,11 +1514,14 @@ fn populate_columns_multiple_rows(
             // Decrement as we have now seen a value index instead
             other_values_seen -= 1;
             if let Some(temp_table_ctx) = temp_table_ctx {
-                program.emit_column(
-                    temp_table_ctx.cursor_id,
-                    value_index_seen,
-                    column_registers_start + i,
-                );
+                // The temp table holds the rows of the SELECT, not records of the table, so the
+                // value is read at its position in the row
+                program.emit_insn(Insn::Column {
+                    cursor_id: temp_table_ctx.cursor_id,
+                    column: value_index_seen,
+                    dest: column_registers_start + i,
+                    default: None,
+                });
             } else {
                 program.emit_insn(Insn::Copy {
                     src_reg: yield_reg + value_index_seen,
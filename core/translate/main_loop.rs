@@ -19,7 +19,8 @@ use crate::{
 };
 
 use super::{
-    aggregation::translate_aggregation_step,
+    aggregation::{emit_bare_columns_row_check, translate_aggregation_step},
+    collate::get_collseq_from_expr,
     emitter::{OperationMode, TranslateCtx},
     expr::{
         translate_condition_expr, translate_expr, translate_expr_no_constant_opt,
@@ -66,7 +67,7 @@ impl LoopLabels {
     }
 }
 
-pub fn init_distinct(program: &mut ProgramBuilder, plan: &SelectPlan) -> DistinctCtx {
+pub fn init_distinct(program: &mut ProgramBuilder, plan: &SelectPlan) -> Result<DistinctCtx> {
     let index_name = format!("distinct_{}", program.offset().as_offset_int()); // we don't really care about the name that much, just enough that we don't get name collisions
     let index = Arc::new(Index {
         name: index_name.clone(),
@@ -77,14 +78,16 @@ pub fn init_distinct(program: &mut ProgramBuilder, plan: &SelectPlan) -> Distinc
             .result_columns
             .iter()
             .enumerate()
-            .map(|(i, col)| IndexColumn {
-                name: col.expr.to_string(),
-                order: SortOrder::Asc,
-                pos_in_table: i,
-                collation: None, // FIXME: this should be determined based on the result column expression!
-                default: None, // FIXME: this should be determined based on the result column expression!
+            .map(|(i, col)| {
+                Ok(IndexColumn {
+                    name: col.expr.to_string(),
+                    order: SortOrder::Asc,
+                    pos_in_table: i,
+                    collation: get_collseq_from_expr(&col.expr, &plan.table_references)?,
+                    default: None, // FIXME: this should be determined based on the result column expression!
+                })
             })
-            .collect(),
+            .collect::<Result<Vec<_>>>()?,
         unique: false,
         has_rowid: false,
     });
@@ -100,7 +103,7 @@ pub fn init_distinct(program: &mut ProgramBuilder, plan: &SelectPlan) -> Distinc
         is_table: false,
     });
 
-    ctx
+    Ok(ctx)
 }
 
 /// Initialize resources needed for the source operators (tables, joins, etc)
@@ -137,8 +140,8 @@ pub fn init_loop(
                 name: agg.args[0].to_string(),
                 order: SortOrder::Asc,
                 pos_in_table: 0,
-                collation: None, // FIXME: this should be inferred from the expression
-                default: None,   // FIXME: this should be inferred from the expression
+                collation: get_collseq_from_expr(&agg.args[0], tables)?,
+                default: None, // FIXME: this should be inferred from the expression
             }],
             has_rowid: false,
             unique: false,
@@ -819,12 +822,13 @@ fn emit_loop_source(
             }
 
             // Step 2: Process arguments for all aggregate functions
-            // For each aggregate, translate all its argument expressions
+            // For each aggregate, translate all its argument expressions, followed by its ORDER BY keys
             for agg in aggregates.iter() {
                 // For a query like: SELECT group_col, SUM(val1), AVG(val2) FROM table GROUP BY group_col
                 // we'll process val1 and val2 here, storing them in the sorter so they're available
                 // when computing the aggregates after sorting by group_col
-                for expr in agg.args.iter() {
+                let order_keys = agg.order_by.iter().map(|(expr, _)| expr);
+                for expr in agg.args.iter().chain(order_keys) {
                     let agg_reg = cur_reg;
                     cur_reg += 1;
                     translate_expr(
@@ -904,11 +908,35 @@ fn emit_loop_source(
 
             let label_emit_nonagg_only_once = if let Some(flag) = t_ctx.reg_nonagg_emit_once_flag {
                 let if_label = program.allocate_label();
-                program.emit_insn(Insn::If {
-                    reg: flag,
-                    target_pc: if_label,
-                    jump_if_null: false,
-                });
+                if let Some(agg_index) = plan.bare_columns_agg_index() {
+                    // The non-aggregate columns are read again whenever the row holds a new
+                    // minimum or maximum. The aggregate step evaluates its own copy of the argument.
+                    let agg = &plan.aggregates[agg_index];
+                    let reg_arg = program.alloc_register();
+                    let reg_best = program.alloc_register();
+                    translate_expr(
+                        program,
+                        Some(&plan.table_references),
+                        &agg.args[0],
+                        reg_arg,
+                        &t_ctx.resolver,
+                    )?;
+                    emit_bare_columns_row_check(
+                        program,
+                        &plan.table_references,
+                        agg,
+                        reg_arg,
+                        reg_best,
+                        flag,
+                        if_label,
+                    )?;
+                } else {
+                    program.emit_insn(Insn::If {
+                        reg: flag,
+                        target_pc: if_label,
+                        jump_if_null: false,
+                    });
+                }
                 Some(if_label)
             } else {
                 None
@@ -9,12 +9,16 @@
 
 pub(crate) mod aggregation;
 pub(crate) mod alter;
+pub(crate) mod analyze;
+pub(crate) mod check;
 pub(crate) mod collate;
 mod compound_select;
 pub(crate) mod delete;
 pub(crate) mod display;
 pub(crate) mod emitter;
 pub(crate) mod expr;
+pub(crate) mod foreign_key_check;
+pub(crate) mod generated;
 pub(crate) mod group_by;
 pub(crate) mod index;
 pub(crate) mod insert;
@@ -31,17 +35,22 @@ pub(crate) mod schema;
 pub(crate) mod select;
 pub(crate) mod subquery;
 pub(crate) mod transaction;
+pub(crate) mod trigger;
 pub(crate) mod update;
+pub(crate) mod upsert;
+pub(crate) mod vacuum;
 mod values;
 
 use crate::schema::Schema;
 use crate::storage::pager::Pager;
 use crate::translate::delete::translate_delete;
 use crate::vdbe::builder::{ProgramBuilder, ProgramBuilderOpts, QueryMode};
+use crate::vdbe::insn::SavepointOp;
 use crate::vdbe::Program;
-use crate::{bail_parse_error, Connection, Result, SymbolTable};
+use crate::{bail_parse_error, Connection, Limit, Result, SymbolTable};
 use alter::translate_alter_table;
-use index::{translate_create_index, translate_drop_index};
+use analyze::translate_analyze;
+use index::{translate_create_index, translate_drop_index, translate_reindex};
 use insert::translate_insert;
 use rollback::translate_rollback;
 use schema::{translate_create_table, translate_create_virtual_table, translate_drop_table};
@@ -49,9 +58,11 @@ use select::translate_select;
 use std::rc::Rc;
 use std::sync::Arc;
 use tracing::{instrument, Level};
-use transaction::{translate_tx_begin, translate_tx_commit};
+use transaction::{translate_savepoint, translate_tx_begin, translate_tx_commit};
+use trigger::{translate_create_trigger, translate_drop_trigger};
 use turso_sqlite3_parser::ast::{self, Delete, Insert};
 use update::translate_update;
+use vacuum::translate_vacuum;
 
 #[instrument(skip_all, level = Level::TRACE)]
 #[allow(clippy::too_many_arguments)]
@@ -62,9 +73,9 @@ pub fn translate(
     connection: Arc<Connection>,
     syms: &SymbolTable,
     query_mode: QueryMode,
-    _input: &str, // TODO: going to be used for CREATE VIEW
+    input: &str,
 ) -> Result<Program> {
-    tracing::trace!("querying {}", _input);
+    tracing::trace!("querying {}", input);
     let change_cnt_on = matches!(
         stmt,
         ast::Stmt::CreateIndex { .. }
@@ -83,6 +94,7 @@ pub fn translate(
         },
     );
 
+    program.foreign_keys = connection.foreign_keys_enabled();
     program.prologue();
 
     program = match stmt {
@@ -95,12 +107,12 @@ pub fn translate(
             connection.clone(),
             program,
         )?,
-        stmt => translate_inner(schema, stmt, syms, program)?,
+        stmt => translate_inner(schema, stmt, syms, &connection, program)?,
     };
 
     // TODO: bring epilogue here when I can sort out what instructions correspond to a Write or a Read transaction
 
-    Ok(program.build(connection, change_cnt_on))
+    Ok(program.build(connection, change_cnt_on, input))
 }
 
 // TODO: for now leaving the return value as a Program. But ideally to support nested parsing of arbitraty
@@ -110,11 +122,12 @@ pub fn translate_inner(
     schema: &Schema,
     stmt: ast::Stmt,
     syms: &SymbolTable,
+    connection: &Arc<Connection>,
     program: ProgramBuilder,
 ) -> Result<ProgramBuilder> {
     let program = match stmt {
         ast::Stmt::AlterTable(alter) => translate_alter_table(*alter, syms, schema, program)?,
-        ast::Stmt::Analyze(_) => bail_parse_error!("ANALYZE not supported yet"),
+        ast::Stmt::Analyze(target) => translate_analyze(target, schema, program)?,
         ast::Stmt::Attach { .. } => bail_parse_error!("ATTACH not supported yet"),
         ast::Stmt::Begin(tx_type, tx_name) => translate_tx_begin(tx_type, tx_name, program)?,
         ast::Stmt::Commit(tx_name) => translate_tx_commit(tx_name, program)?,
@@ -138,8 +151,15 @@ pub fn translate_inner(
             if_not_exists,
             tbl_name,
             body,
-        } => translate_create_table(tbl_name, temporary, *body, if_not_exists, schema, program)?,
-        ast::Stmt::CreateTrigger { .. } => bail_parse_error!("CREATE TRIGGER not supported yet"),
+        } => {
+            if let ast::CreateTableBody::ColumnsAndConstraints { columns, .. } = body.as_ref() {
+                if columns.len() > connection.limit(Limit::Column) as usize {
+                    bail_parse_error!("too many columns on {}", tbl_name.name.0);
+                }
+            }
+            translate_create_table(tbl_name, temporary, *body, if_not_exists, schema, program)?
+        }
+        ast::Stmt::CreateTrigger(trigger) => translate_create_trigger(*trigger, schema, program)?,
         ast::Stmt::CreateView { .. } => bail_parse_error!("CREATE VIEW not supported yet"),
         ast::Stmt::CreateVirtualTable(vtab) => {
             translate_create_virtual_table(*vtab, schema, syms, program)?
@@ -148,10 +168,19 @@ pub fn translate_inner(
             let Delete {
                 tbl_name,
                 where_clause,
+                order_by,
                 limit,
                 ..
             } = *delete;
-            translate_delete(schema, &tbl_name, where_clause, limit, syms, program)?
+            translate_delete(
+                schema,
+                &tbl_name,
+                where_clause,
+                order_by,
+                limit,
+                syms,
+                program,
+            )?
         }
         ast::Stmt::Detach(_) => bail_parse_error!("DETACH not supported yet"),
         ast::Stmt::DropIndex {
@@ -162,19 +191,26 @@ pub fn translate_inner(
             if_exists,
             tbl_name,
         } => translate_drop_table(tbl_name, if_exists, schema, program)?,
-        ast::Stmt::DropTrigger { .. } => bail_parse_error!("DROP TRIGGER not supported yet"),
+        ast::Stmt::DropTrigger {
+            if_exists,
+            trigger_name,
+        } => translate_drop_trigger(&trigger_name, if_exists, schema, program)?,
         ast::Stmt::DropView { .. } => bail_parse_error!("DROP VIEW not supported yet"),
         ast::Stmt::Pragma(..) => {
             bail_parse_error!("PRAGMA statement cannot be evaluated in a nested context")
         }
-        ast::Stmt::Reindex { .. } => bail_parse_error!("REINDEX not supported yet"),
-        ast::Stmt::Release(_) => bail_parse_error!("RELEASE not supported yet"),
+        ast::Stmt::Reindex { obj_name } => translate_reindex(obj_name, schema, program)?,
+        ast::Stmt::Release(name) => translate_savepoint(SavepointOp::Release, name, program)?,
         ast::Stmt::Rollback {
             tx_name,
             savepoint_name,
         } => translate_rollback(schema, syms, program, tx_name, savepoint_name)?,
-        ast::Stmt::Savepoint(_) => bail_parse_error!("SAVEPOINT not supported yet"),
+        ast::Stmt::Savepoint(name) => translate_savepoint(SavepointOp::Begin, name, program)?,
         ast::Stmt::Select(select) => {
+            let terms = select.body.compounds.as_ref().map_or(0, |c| c.len()) + 1;
+            if terms > connection.limit(Limit::CompoundSelect) as usize {
+                bail_parse_error!("too many terms in compound SELECT");
+            }
             translate_select(
                 schema,
                 *select,
@@ -185,7 +221,7 @@ pub fn translate_inner(
             .program
         }
         ast::Stmt::Update(mut update) => translate_update(schema, &mut update, syms, program)?,
-        ast::Stmt::Vacuum(_, _) => bail_parse_error!("VACUUM not supported yet"),
+        ast::Stmt::Vacuum(schema_name, into) => translate_vacuum(schema_name, into, program)?,
         ast::Stmt::Insert(insert) => {
             let Insert {
                 with,
@@ -204,6 +240,7 @@ pub fn translate_inner(
                 body,
                 returning,
                 syms,
+                connection.foreign_keys_enabled(),
                 program,
             )?
         }
@@ -4,7 +4,7 @@ use turso_sqlite3_parser::ast::SortOrder;
 
 use crate::{
     schema::Index,
-    translate::plan::{IterationDirection, JoinOrderMember, JoinedTable},
+    translate::plan::{IndexHint, IterationDirection, JoinOrderMember, JoinedTable},
     Result,
 };
 
@@ -36,9 +36,20 @@ impl AccessMethod<'_> {
         self.constraint_refs.is_empty()
     }
 
-    pub fn new_table_scan(input_cardinality: f64, iter_dir: IterationDirection) -> Self {
+    pub fn new_table_scan(
+        input_cardinality: f64,
+        row_count: f64,
+        iter_dir: IterationDirection,
+    ) -> Self {
         Self {
-            cost: estimate_cost_for_scan_or_seek(None, &[], &[], input_cardinality),
+            cost: estimate_cost_for_scan_or_seek(
+                None,
+                &[],
+                &[],
+                input_cardinality,
+                row_count,
+                None,
+            ),
             iter_dir,
             index: None,
             constraint_refs: &[],
@@ -55,9 +66,12 @@ pub fn find_best_access_method_for_join_order<'a>(
     input_cardinality: f64,
 ) -> Result<AccessMethod<'a>> {
     let table_no = join_order.last().unwrap().table_id;
+    let row_count = rhs_constraints.estimated_row_count();
     let mut best_access_method =
-        AccessMethod::new_table_scan(input_cardinality, IterationDirection::Forwards);
+        AccessMethod::new_table_scan(input_cardinality, row_count, IterationDirection::Forwards);
     let rowid_column_idx = rhs_table.columns().iter().position(|c| c.is_rowid_alias);
+    // A table with `INDEXED BY` is accessed through its index even if scanning the table looks cheaper.
+    let must_use_index = matches!(rhs_table.index_hint, Some(IndexHint::IndexedBy(_)));
 
     // Estimate cost for each candidate index (including the rowid index) and replace best_access_method if the cost is lower.
     for candidate in rhs_constraints.candidates.iter() {
@@ -83,6 +97,8 @@ pub fn find_best_access_method_for_join_order<'a>(
             &rhs_constraints.constraints,
             usable_constraint_refs,
             input_cardinality,
+            row_count,
+            candidate.rows_per_key.as_deref(),
         );
 
         // All other things being equal, prefer an access method that satisfies the order target.
@@ -134,7 +150,9 @@ pub fn find_best_access_method_for_join_order<'a>(
         } else {
             (IterationDirection::Forwards, Cost(0.0))
         };
-        if cost < best_access_method.cost + order_satisfiability_bonus {
+        if cost < best_access_method.cost + order_satisfiability_bonus
+            || (must_use_index && best_access_method.index.is_none())
+        {
             best_access_method = AccessMethod {
                 cost,
                 index: candidate.index.clone(),
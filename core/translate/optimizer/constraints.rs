@@ -1,12 +1,13 @@
 use std::{cmp::Ordering, collections::HashMap, sync::Arc};
 
 use crate::{
-    schema::{Column, Index},
+    schema::{Column, Index, TableStats},
     translate::{
         expr::as_binary_components,
-        plan::{JoinOrderMember, TableReferences, WhereTerm},
+        plan::{IndexHint, JoinOrderMember, TableReferences, WhereTerm},
         planner::{table_mask_from_expr, TableMask},
     },
+    util::normalize_ident,
     Result,
 };
 use turso_sqlite3_parser::ast::{self, SortOrder, TableInternalId};
@@ -128,6 +129,9 @@ pub struct ConstraintUseCandidate {
     pub index: Option<Arc<Index>>,
     /// References to the constraints that may be used as an access path for the index.
     pub refs: Vec<ConstraintRef>,
+    /// The average number of rows sharing the values of the first 1, 2, ... N columns of the index,
+    /// if the index was analyzed with `ANALYZE`.
+    pub rows_per_key: Option<Vec<u64>>,
 }
 
 #[derive(Debug)]
@@ -139,6 +143,18 @@ pub struct TableConstraints {
     pub constraints: Vec<Constraint>,
     /// Candidates for indexes that may use the constraints to perform a lookup.
     pub candidates: Vec<ConstraintUseCandidate>,
+    /// The number of rows of the table, if the table was analyzed with `ANALYZE`.
+    pub row_count: Option<u64>,
+}
+
+impl TableConstraints {
+    /// The number of rows of the table, or a hardcoded estimate in lieu of statistics.
+    pub fn estimated_row_count(&self) -> f64 {
+        self.row_count
+            .map_or(ESTIMATED_HARDCODED_ROWS_PER_TABLE as f64, |rows| {
+                rows as f64
+            })
+    }
 }
 
 /// In lieu of statistics, we estimate that an equality filter will reduce the output set to 1% of its size.
@@ -184,26 +200,38 @@ pub fn constraints_from_where_clause(
             .iter()
             .position(|c| c.is_rowid_alias);
 
+        // `INDEXED BY` and `NOT INDEXED` narrow down the indexes the table may be accessed through.
+        let table_indexes = match &table_reference.index_hint {
+            Some(IndexHint::IndexedBy(index)) => vec![index.clone()],
+            Some(IndexHint::NotIndexed) => Vec::new(),
+            None => available_indexes
+                .get(table_reference.table.get_name())
+                .cloned()
+                .unwrap_or_default(),
+        };
+
         let mut cs = TableConstraints {
             table_id: table_reference.internal_id,
             constraints: Vec::new(),
-            candidates: available_indexes
-                .get(table_reference.table.get_name())
-                .map_or(Vec::new(), |indexes| {
-                    indexes
-                        .iter()
-                        .map(|index| ConstraintUseCandidate {
-                            index: Some(index.clone()),
-                            refs: Vec::new(),
-                        })
-                        .collect()
-                }),
+            candidates: table_indexes
+                .iter()
+                .map(|index| ConstraintUseCandidate {
+                    index: Some(index.clone()),
+                    refs: Vec::new(),
+                    rows_per_key: None,
+                })
+                .collect(),
+            row_count: None,
         };
-        // Add a candidate for the rowid index, which is always available when the table has a rowid alias.
-        cs.candidates.push(ConstraintUseCandidate {
-            index: None,
-            refs: Vec::new(),
-        });
+        // Add a candidate for the rowid index, which is always available when the table has a rowid alias,
+        // unless the table must be accessed through the index named by `INDEXED BY`.
+        if !matches!(table_reference.index_hint, Some(IndexHint::IndexedBy(_))) {
+            cs.candidates.push(ConstraintUseCandidate {
+                index: None,
+                refs: Vec::new(),
+                rows_per_key: None,
+            });
+        }
 
         for (i, term) in where_clause.iter().enumerate() {
             let Some((lhs, operator, rhs)) = as_binary_components(&term.expr)? else {
@@ -294,27 +322,22 @@ pub fn constraints_from_where_clause(
         // For each constraint we found, add a reference to it for each index that may be able to use it.
         for (i, constraint) in cs.constraints.iter().enumerate() {
             if rowid_alias_column.map_or(false, |idx| constraint.table_col_pos == idx) {
-                let rowid_candidate = cs
-                    .candidates
-                    .iter_mut()
-                    .find_map(|candidate| {
-                        if candidate.index.is_none() {
-                            Some(candidate)
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap();
-                rowid_candidate.refs.push(ConstraintRef {
-                    constraint_vec_pos: i,
-                    index_col_pos: 0,
-                    sort_order: SortOrder::Asc,
+                let rowid_candidate = cs.candidates.iter_mut().find_map(|candidate| {
+                    if candidate.index.is_none() {
+                        Some(candidate)
+                    } else {
+                        None
+                    }
                 });
+                if let Some(rowid_candidate) = rowid_candidate {
+                    rowid_candidate.refs.push(ConstraintRef {
+                        constraint_vec_pos: i,
+                        index_col_pos: 0,
+                        sort_order: SortOrder::Asc,
+                    });
+                }
             }
-            for index in available_indexes
-                .get(table_reference.table.get_name())
-                .unwrap_or(&Vec::new())
-            {
+            for index in table_indexes.iter() {
                 if let Some(position_in_index) =
                     index.column_table_pos_to_index_pos(constraint.table_col_pos)
                 {
@@ -370,6 +393,56 @@ pub fn constraints_from_where_clause(
     Ok(constraints)
 }
 
+/// Refine the estimates of each [TableConstraints] with the statistics gathered by `ANALYZE`.
+///
+/// The selectivity of an equality on a column becomes the fraction of rows that share a key of
+/// the most selective analyzed index starting with that column.
+pub fn apply_table_stats(
+    table_constraints: &mut [TableConstraints],
+    table_references: &TableReferences,
+    table_stats: &HashMap<String, TableStats>,
+) {
+    for (cs, table_reference) in table_constraints
+        .iter_mut()
+        .zip(table_references.joined_tables())
+    {
+        let Some(stats) = table_stats.get(&normalize_ident(table_reference.table.get_name()))
+        else {
+            continue;
+        };
+        cs.row_count = Some(stats.row_count);
+        for candidate in cs.candidates.iter_mut() {
+            if let Some(index) = &candidate.index {
+                candidate.rows_per_key = stats.index_rows_per_key.get(&index.name).cloned();
+            }
+        }
+        let row_count = stats.row_count.max(1) as f64;
+        let columns = table_reference.columns();
+        for constraint in cs.constraints.iter_mut() {
+            if constraint.operator != ast::Operator::Equals {
+                continue;
+            }
+            let column = &columns[constraint.table_col_pos];
+            let rows = if column.is_rowid_alias {
+                Some(1)
+            } else {
+                cs.candidates
+                    .iter()
+                    .filter(|candidate| {
+                        candidate.index.as_ref().is_some_and(|index| {
+                            index.columns[0].pos_in_table == constraint.table_col_pos
+                        })
+                    })
+                    .filter_map(|candidate| candidate.rows_per_key.as_ref()?.first().copied())
+                    .min()
+            };
+            if let Some(rows) = rows {
+                constraint.selectivity = rows as f64 / row_count;
+            }
+        }
+    }
+}
+
 /// Find which [Constraint]s are usable for a given join order.
 /// Returns a slice of the references to the constraints that are usable.
 /// A constraint is considered usable for a given table if all of the other tables referenced by the constraint
@@ -1,3 +1,5 @@
+use turso_sqlite3_parser::ast;
+
 use super::constraints::{Constraint, ConstraintRef};
 
 /// A simple newtype wrapper over a f64 that represents the cost of an operation.
@@ -40,33 +42,40 @@ pub fn estimate_page_io_cost(rowcount: f64) -> Cost {
 ///
 /// This is a very simple model that estimates the number of pages read
 /// based on the number of rows read, ignoring any CPU costs.
+///
+/// `rows_per_key` holds the statistics of the index gathered by `ANALYZE`, if any, i.e. the average
+/// number of rows sharing the values of the first 1, 2, ... N columns of the index.
 pub fn estimate_cost_for_scan_or_seek(
     index_info: Option<IndexInfo>,
     constraints: &[Constraint],
     usable_constraint_refs: &[ConstraintRef],
     input_cardinality: f64,
+    row_count: f64,
+    rows_per_key: Option<&[u64]>,
 ) -> Cost {
     let Some(index_info) = index_info else {
-        return estimate_page_io_cost(
-            input_cardinality * ESTIMATED_HARDCODED_ROWS_PER_TABLE as f64,
-        );
+        return estimate_page_io_cost(input_cardinality * row_count);
     };
 
-    let selectivity_multiplier: f64 = usable_constraint_refs
+    let selectivity = |cref: &ConstraintRef| constraints[cref.constraint_vec_pos].selectivity;
+    // The equalities on the leading columns of the index match as many rows as a key of those
+    // columns does on average, which the statistics know better than the constraints do.
+    let equalities = usable_constraint_refs
         .iter()
-        .map(|cref| {
-            let constraint = &constraints[cref.constraint_vec_pos];
-            constraint.selectivity
-        })
-        .product();
+        .take_while(|cref| constraints[cref.constraint_vec_pos].operator == ast::Operator::Equals)
+        .count();
+    let (equality_refs, other_refs) = usable_constraint_refs.split_at(equalities);
+    let equality_multiplier = match rows_per_key.and_then(|r| r.get(equalities.checked_sub(1)?)) {
+        Some(rows) => *rows as f64 / row_count.max(1.0),
+        None => equality_refs.iter().map(selectivity).product(),
+    };
+    let selectivity_multiplier: f64 =
+        equality_multiplier * other_refs.iter().map(selectivity).product::<f64>();
 
     // little cheeky bonus for covering indexes
     let covering_multiplier = if index_info.covering { 0.9 } else { 1.0 };
 
     estimate_page_io_cost(
-        selectivity_multiplier
-            * ESTIMATED_HARDCODED_ROWS_PER_TABLE as f64
-            * input_cardinality
-            * covering_multiplier,
+        selectivity_multiplier * row_count * input_cardinality * covering_multiplier,
     )
 }
@@ -14,7 +14,6 @@ use crate::{
 use super::{
     access_method::{find_best_access_method_for_join_order, AccessMethod},
     constraints::TableConstraints,
-    cost::ESTIMATED_HARDCODED_ROWS_PER_TABLE,
     order::OrderTarget,
 };
 
@@ -95,7 +94,7 @@ pub fn join_lhs_and_rhs<'a>(
     // If this table is the rightmost table in the join order, we multiply by the input cardinality,
     // which is the output cardinality of the previous tables.
     let output_cardinality = (input_cardinality as f64
-        * ESTIMATED_HARDCODED_ROWS_PER_TABLE as f64
+        * rhs_constraints.estimated_row_count()
         * output_cardinality_multiplier)
         .ceil() as usize;
 
@@ -496,9 +495,11 @@ mod tests {
 
     use super::*;
     use crate::{
-        schema::{BTreeTable, Column, Index, IndexColumn, Table, Type},
+        schema::{BTreeTable, Column, Index, IndexColumn, Table, TableStats, Type},
         translate::{
-            optimizer::constraints::{constraints_from_where_clause, BinaryExprSide},
+            optimizer::constraints::{
+                apply_table_stats, constraints_from_where_clause, BinaryExprSide,
+            },
             plan::{
                 ColumnUsedMask, IterationDirection, JoinInfo, Operation, TableReferences, WhereTerm,
             },
@@ -1313,6 +1314,7 @@ mod tests {
             identifier: "t1".to_string(),
             join_info: None,
             col_used_mask: ColumnUsedMask::default(),
+            index_hint: None,
         });
 
         // Create where clause that only references second column
@@ -1404,6 +1406,7 @@ mod tests {
             identifier: "t1".to_string(),
             join_info: None,
             col_used_mask: ColumnUsedMask::default(),
+            index_hint: None,
         });
 
         // Create where clause that references first and third columns
@@ -1520,6 +1523,7 @@ mod tests {
             identifier: "t1".to_string(),
             join_info: None,
             col_used_mask: ColumnUsedMask::default(),
+            index_hint: None,
         });
 
         // Create where clause: c1 = 5 AND c2 > 10 AND c3 = 7
@@ -1602,6 +1606,101 @@ mod tests {
         assert!(constraint.table_col_pos == 1); // c2
     }
 
+    #[test]
+    /// Test that the statistics of `ANALYZE` make the planner pick the most selective index
+    /// when the constraints alone cannot tell the indexes apart.
+    fn test_index_chosen_from_table_stats() {
+        let mut table_id_counter = TableRefIdCounter::new();
+        let mut joined_tables = Vec::new();
+        let mut available_indexes = HashMap::new();
+
+        let columns = _create_column_list(&["a", "b"], Type::Integer);
+        let table = _create_btree_table("t1", columns);
+        let create_index = |name: &str, column: &str, pos_in_table: usize| {
+            Arc::new(Index {
+                name: name.to_string(),
+                table_name: "t1".to_string(),
+                columns: vec![IndexColumn {
+                    name: column.to_string(),
+                    order: SortOrder::Asc,
+                    pos_in_table,
+                    collation: None,
+                    default: None,
+                }],
+                root_page: 2 + pos_in_table,
+                ephemeral: false,
+                has_rowid: true,
+                unique: false,
+            })
+        };
+        available_indexes.insert(
+            "t1".to_string(),
+            vec![create_index("idx_a", "a", 0), create_index("idx_b", "b", 1)],
+        );
+
+        joined_tables.push(JoinedTable {
+            table: Table::BTree(table),
+            internal_id: table_id_counter.next(),
+            op: Operation::Scan {
+                iter_dir: IterationDirection::Forwards,
+                index: None,
+            },
+            identifier: "t1".to_string(),
+            join_info: None,
+            col_used_mask: ColumnUsedMask::default(),
+            index_hint: None,
+        });
+
+        // SELECT * FROM t1 WHERE a = 1 AND b = 2
+        let where_clause = vec![
+            _create_binary_expr(
+                _create_column_expr(joined_tables[0].internal_id, 0, false),
+                ast::Operator::Equals,
+                _create_numeric_literal("1"),
+            ),
+            _create_binary_expr(
+                _create_column_expr(joined_tables[0].internal_id, 1, false),
+                ast::Operator::Equals,
+                _create_numeric_literal("2"),
+            ),
+        ];
+        let table_references = TableReferences::new(joined_tables, vec![]);
+
+        let best_index = |table_stats: &HashMap<String, TableStats>| {
+            let access_methods_arena = RefCell::new(Vec::new());
+            let mut table_constraints =
+                constraints_from_where_clause(&where_clause, &table_references, &available_indexes)
+                    .unwrap();
+            apply_table_stats(&mut table_constraints, &table_references, table_stats);
+            let BestJoinOrderResult { best_plan, .. } = compute_best_join_order(
+                table_references.joined_tables(),
+                None,
+                &table_constraints,
+                &access_methods_arena,
+            )
+            .unwrap()
+            .unwrap();
+            let access_method = &access_methods_arena.borrow()[best_plan.data[0].1];
+            access_method.index.as_ref().map(|index| index.name.clone())
+        };
+
+        // Without statistics both equalities look alike, so the first index is used
+        assert_eq!(best_index(&HashMap::new()), Some("idx_a".to_string()));
+
+        // Half of the rows have the same `a`, while each `b` is unique
+        let table_stats = HashMap::from([(
+            "t1".to_string(),
+            TableStats {
+                row_count: 1000,
+                index_rows_per_key: HashMap::from([
+                    ("idx_a".to_string(), vec![500]),
+                    ("idx_b".to_string(), vec![1]),
+                ]),
+            },
+        )]);
+        assert_eq!(best_index(&table_stats), Some("idx_b".to_string()));
+    }
+
     fn _create_column(c: &TestColumn) -> Column {
         Column {
             name: Some(c.name.clone()),
@@ -1613,6 +1712,7 @@ mod tests {
             default: None,
             unique: false,
             collation: None,
+            generated: None,
         }
     }
     fn _create_column_of_type(name: &str, ty: Type) -> Column {
@@ -1648,6 +1748,9 @@ mod tests {
             has_rowid: true,
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            has_autoincrement: false,
+            check_constraints: vec![],
         })
     }
 
@@ -1668,6 +1771,7 @@ mod tests {
             internal_id,
             join_info,
             col_used_mask: ColumnUsedMask::default(),
+            index_hint: None,
         }
     }
 
@@ -1,7 +1,8 @@
 use std::{cell::RefCell, cmp::Ordering, collections::HashMap, sync::Arc};
 
 use constraints::{
-    constraints_from_where_clause, usable_constraints_for_join_order, Constraint, ConstraintRef,
+    apply_table_stats, constraints_from_where_clause, usable_constraints_for_join_order,
+    Constraint, ConstraintRef,
 };
 use cost::Cost;
 use join::{compute_best_join_order, BestJoinOrderResult};
@@ -23,8 +24,9 @@ use crate::{
 use super::{
     emitter::Resolver,
     plan::{
-        DeletePlan, GroupBy, IterationDirection, JoinOrderMember, JoinedTable, Operation, Plan,
-        Search, SeekDef, SeekKey, SelectPlan, TableReferences, UpdatePlan, WhereTerm,
+        DeletePlan, GroupBy, IndexHint, IterationDirection, JoinOrderMember, JoinedTable,
+        Operation, Plan, Search, SeekDef, SeekKey, SelectPlan, TableReferences, UpdatePlan,
+        WhereTerm,
     },
 };
 
@@ -83,9 +85,38 @@ pub fn optimize_select_plan(plan: &mut SelectPlan, schema: &Schema) -> Result<()
         plan.join_order = best_join_order;
     }
 
+    if plan.is_simple_count() {
+        use_smallest_index_for_count(plan, schema);
+    }
+
     Ok(())
 }
 
+/// For `SELECT count(*) FROM t`, count the entries of the narrowest index on `t`
+/// instead of the table btree. Every index holds one entry per row, and since its
+/// entries carry fewer columns than the table rows, it spans fewer pages.
+fn use_smallest_index_for_count(plan: &mut SelectPlan, schema: &Schema) {
+    if !schema.indexes_enabled() {
+        return;
+    }
+    let table = &mut plan.table_references.joined_tables_mut()[0];
+    let smallest = match &table.index_hint {
+        Some(IndexHint::IndexedBy(index)) => Some(index),
+        Some(IndexHint::NotIndexed) => None,
+        None => schema
+            .get_indices(table.table.get_name())
+            .iter()
+            .filter(|index| !index.ephemeral)
+            .min_by_key(|index| index.columns.len()),
+    };
+    if let Some(index) = smallest {
+        table.op = Operation::Scan {
+            iter_dir: IterationDirection::Forwards,
+            index: Some(index.clone()),
+        };
+    }
+}
+
 fn optimize_delete_plan(plan: &mut DeletePlan, _schema: &Schema) -> Result<()> {
     rewrite_exprs_delete(plan)?;
     if let ConstantConditionEliminationResult::ImpossibleCondition =
@@ -158,8 +189,13 @@ fn optimize_table_access(
 ) -> Result<Option<Vec<JoinOrderMember>>> {
     let access_methods_arena = RefCell::new(Vec::new());
     let maybe_order_target = compute_order_target(order_by, group_by.as_mut());
-    let constraints_per_table =
+    let mut constraints_per_table =
         constraints_from_where_clause(where_clause, table_references, available_indexes)?;
+    apply_table_stats(
+        &mut constraints_per_table,
+        table_references,
+        &schema.table_stats,
+    );
     let Some(best_join_order_result) = compute_best_join_order(
         table_references.joined_tables_mut(),
         maybe_order_target.as_ref(),
@@ -247,7 +283,12 @@ fn optimize_table_access(
                     &joined_tables[table_idx].table,
                     Table::FromClauseSubquery(_)
                 );
-                !is_leftmost_table && !uses_index && !source_table_is_from_clause_subquery
+                // Automatic indexes are indexes too, so `INDEXED BY` and `NOT INDEXED` rule them out.
+                let has_index_hint = joined_tables[table_idx].index_hint.is_some();
+                !is_leftmost_table
+                    && !uses_index
+                    && !source_table_is_from_clause_subquery
+                    && !has_index_hint
             } else {
                 false
             };
@@ -261,9 +302,15 @@ fn optimize_table_access(
             }
             // This branch means we have a full table scan for a non-outermost table.
             // Try to construct an ephemeral index since it's going to be better than a scan.
+            // VIRTUAL generated columns are computed from the columns read through the table
+            // cursor, which an ephemeral index would replace, so such tables are scanned instead.
+            let has_virtual_columns = joined_tables[table_idx]
+                .btree()
+                .is_some_and(|btree| btree.has_virtual_columns());
             let table_constraints = constraints_per_table
                 .iter()
-                .find(|c| c.table_id == join_order_member.table_id);
+                .find(|c| c.table_id == join_order_member.table_id)
+                .filter(|_| !has_virtual_columns);
             let Some(table_constraints) = table_constraints else {
                 joined_tables[table_idx].op = Operation::Scan {
                     iter_dir: access_method.iter_dir,
@@ -532,6 +579,7 @@ impl Optimizable for ast::Expr {
                 column.primary_key || column.notnull
             }
             Expr::RowId { .. } => true,
+            Expr::Register(_) => false,
             Expr::InList { lhs, rhs, .. } => {
                 lhs.is_nonnull(tables)
                     && rhs
@@ -612,6 +660,7 @@ impl Optimizable for ast::Expr {
             Expr::Id(_) => panic!("Id should have been rewritten as Column"),
             Expr::Column { .. } => false,
             Expr::RowId { .. } => false,
+            Expr::Register(_) => false,
             Expr::InList { lhs, rhs, .. } => {
                 lhs.is_constant(resolver)
                     && rhs
@@ -1332,6 +1381,11 @@ pub fn rewrite_expr(top_level_expr: &mut ast::Expr, param_idx: &mut usize) -> Re
                 start,
                 end,
             } => {
+                // `y` is duplicated by the rewrite, so the anonymous variables are numbered
+                // first, in the order they appear in `y BETWEEN x AND z`
+                rewrite_expr(lhs, param_idx)?;
+                rewrite_expr(start, param_idx)?;
+                rewrite_expr(end, param_idx)?;
                 // Convert `y NOT BETWEEN x AND z` to `x > y OR y > z`
                 let (lower_op, upper_op) = if *not {
                     (ast::Operator::Greater, ast::Operator::Greater)
@@ -1362,6 +1416,18 @@ pub fn rewrite_expr(top_level_expr: &mut ast::Expr, param_idx: &mut usize) -> Re
                     );
                 }
             }
+            ast::Expr::Binary(lhs, op, rhs) if is_row_value(lhs) || is_row_value(rhs) => {
+                // The elements of the row values are duplicated by the rewrite, so their
+                // anonymous variables must be numbered first
+                rewrite_expr(lhs, param_idx)?;
+                rewrite_expr(rhs, param_idx)?;
+                let (ast::Expr::Parenthesized(lhs), ast::Expr::Parenthesized(rhs)) =
+                    (lhs.take_ownership(), rhs.take_ownership())
+                else {
+                    crate::bail_parse_error!("row value misused");
+                };
+                *expr = rewrite_row_value_comparison(lhs, *op, rhs)?;
+            }
             _ => {}
         }
 
@@ -1369,6 +1435,60 @@ pub fn rewrite_expr(top_level_expr: &mut ast::Expr, param_idx: &mut usize) -> Re
     })
 }
 
+fn is_row_value(expr: &ast::Expr) -> bool {
+    matches!(expr, ast::Expr::Parenthesized(exprs) if exprs.len() > 1)
+}
+
+/// Converts a comparison of row values into comparisons of their elements.
+/// `(a, b) = (x, y)` becomes `a = x AND b = y`, and `(a, b) < (x, y)` is compared
+/// lexicographically as `a < x OR (a = x AND b < y)`, which is NULL exactly when
+/// SQLite's vector comparison is, i.e. when a NULL is met before the order is decided.
+fn rewrite_row_value_comparison(
+    lhs: Vec<ast::Expr>,
+    op: ast::Operator,
+    rhs: Vec<ast::Expr>,
+) -> Result<ast::Expr> {
+    if lhs.len() != rhs.len() {
+        crate::bail_parse_error!("row value misused");
+    }
+    let compare = |l: &ast::Expr, op, r: &ast::Expr| {
+        ast::Expr::Parenthesized(vec![ast::Expr::Binary(
+            Box::new(l.clone()),
+            op,
+            Box::new(r.clone()),
+        )])
+    };
+    let fold = |connective| {
+        lhs.iter()
+            .zip(rhs.iter())
+            .map(|(l, r)| compare(l, op, r))
+            .reduce(|acc, cmp| ast::Expr::Binary(Box::new(acc), connective, Box::new(cmp)))
+            .unwrap()
+    };
+    let strict_op = match op {
+        ast::Operator::Equals | ast::Operator::Is => return Ok(fold(ast::Operator::And)),
+        ast::Operator::NotEquals | ast::Operator::IsNot => return Ok(fold(ast::Operator::Or)),
+        ast::Operator::Less | ast::Operator::LessEquals => ast::Operator::Less,
+        ast::Operator::Greater | ast::Operator::GreaterEquals => ast::Operator::Greater,
+        _ => crate::bail_parse_error!("row value misused"),
+    };
+    let last = lhs.len() - 1;
+    let mut result = compare(&lhs[last], op, &rhs[last]);
+    for (l, r) in lhs[..last].iter().zip(rhs[..last].iter()).rev() {
+        let tie = ast::Expr::Binary(
+            Box::new(compare(l, ast::Operator::Equals, r)),
+            ast::Operator::And,
+            Box::new(result),
+        );
+        result = ast::Expr::Parenthesized(vec![ast::Expr::Binary(
+            Box::new(compare(l, strict_op, r)),
+            ast::Operator::Or,
+            Box::new(ast::Expr::Parenthesized(vec![tie])),
+        )]);
+    }
+    Ok(result)
+}
+
 trait TakeOwnership {
     fn take_ownership(&mut self) -> Self;
 }
@@ -2,7 +2,7 @@ use turso_sqlite3_parser::ast::{self, SortOrder};
 
 use crate::{
     schema::PseudoCursorType,
-    translate::collate::CollationSeq,
+    translate::collate::get_collseq_from_expr,
     util::exprs_are_equivalent,
     vdbe::{
         builder::{CursorType, ProgramBuilder},
@@ -49,19 +49,7 @@ pub fn init_order_by(
      */
     let collations = order_by
         .iter()
-        .map(|(expr, _)| match expr {
-            ast::Expr::Collate(_, collation_name) => CollationSeq::new(collation_name).map(Some),
-            ast::Expr::Column { table, column, .. } => {
-                let table = referenced_tables.find_table_by_internal_id(*table).unwrap();
-
-                let Some(table_column) = table.get_column_at(*column) else {
-                    crate::bail_parse_error!("column index out of bounds");
-                };
-
-                Ok(table_column.collation)
-            }
-            _ => Ok(Some(CollationSeq::default())),
-        })
+        .map(|(expr, _)| get_collseq_from_expr(expr, referenced_tables))
         .collect::<Result<Vec<_>>>()?;
     program.emit_insn(Insn::SorterOpen {
         cursor_id: sort_cursor,
@@ -295,6 +295,8 @@ pub enum Plan {
         right_most: SelectPlan,
         limit: Option<isize>,
         offset: Option<isize>,
+        /// Sorts the combined rows. Each term is the number of a result column, possibly
+        /// wrapped in a COLLATE.
         order_by: Option<Vec<(ast::Expr, SortOrder)>>,
     },
     Delete(DeletePlan),
@@ -451,20 +453,41 @@ impl SelectPlan {
         self.aggregates.iter().map(|agg| agg.args.len()).sum()
     }
 
+    /// Number of values evaluated for every row fed to the aggregates, see
+    /// [Aggregate::inputs_count]
+    pub fn agg_inputs_count(&self) -> usize {
+        self.aggregates.iter().map(|agg| agg.inputs_count()).sum()
+    }
+
+    /// Index of the aggregate that picks the row the bare columns of each group are read from.
+    /// As in SQLite, when a query has exactly one `min()` or `max()` aggregate, its bare columns
+    /// come from the row holding the minimum or maximum value, e.g. `SELECT name, max(age)`
+    /// returns the name of the oldest person. Otherwise they come from the first row of the group.
+    pub fn bare_columns_agg_index(&self) -> Option<usize> {
+        let mut min_max = self
+            .aggregates
+            .iter()
+            .enumerate()
+            .filter(|(_, agg)| matches!(agg.func, AggFunc::Min | AggFunc::Max));
+        match (min_max.next(), min_max.next()) {
+            (Some((i, _)), None) => Some(i),
+            _ => None,
+        }
+    }
+
     /// Reference: https://github.com/sqlite/sqlite/blob/5db695197b74580c777b37ab1b787531f15f7f9f/src/select.c#L8613
     ///
     /// Checks to see if the query is of the format `SELECT count(*) FROM <tbl>`
     pub fn is_simple_count(&self) -> bool {
         if !self.where_clause.is_empty()
             || self.aggregates.len() != 1
-            || matches!(
-                self.query_destination,
-                QueryDestination::CoroutineYield { .. }
-            )
+            || !matches!(self.query_destination, QueryDestination::ResultRows)
             || self.table_references.joined_tables().len() != 1
-            || self.table_references.outer_query_refs().is_empty()
+            || !self.table_references.outer_query_refs().is_empty()
             || self.result_columns.len() != 1
             || self.group_by.is_some()
+            || self.limit.is_some()
+            || self.offset.is_some()
             || self.contains_constant_false_condition
         // TODO: (pedrocarlo) maybe can optimize to use the count optmization with more columns
         {
@@ -516,6 +539,9 @@ pub struct DeletePlan {
     pub contains_constant_false_condition: bool,
     /// Indexes that must be updated by the delete operation.
     pub indexes: Vec<Arc<Index>>,
+    /// With ORDER BY or OFFSET, the rowids of the rows to delete are gathered into an
+    /// ephemeral table first, and that table is the one iterated by the DELETE loop.
+    pub ephemeral_plan: Option<SelectPlan>,
 }
 
 #[derive(Debug, Clone)]
@@ -534,6 +560,10 @@ pub struct UpdatePlan {
     pub indexes_to_update: Vec<Arc<Index>>,
     // If the table's rowid alias is used, gather all the target rowids into an ephemeral table, and then use that table as the single JoinedTable for the actual UPDATE loop.
     pub ephemeral_plan: Option<SelectPlan>,
+    // With a FROM clause, the ephemeral table also holds the new value of each SET clause, in order, and the UPDATE loop reads them from there.
+    pub set_values_in_ephemeral_table: bool,
+    // The resolution of constraint violations given by UPDATE OR <resolution>
+    pub or_conflict: Option<ast::ResolveType>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -614,6 +644,18 @@ pub struct JoinedTable {
     /// Bitmask of columns that are referenced in the query.
     /// Used to decide whether a covering index can be used.
     pub col_used_mask: ColumnUsedMask,
+    /// The `INDEXED BY` or `NOT INDEXED` clause of the table, if any.
+    pub index_hint: Option<IndexHint>,
+}
+
+/// Restricts how the optimizer may access a table, e.g. `FROM t INDEXED BY idx`.
+#[derive(Debug, Clone)]
+pub enum IndexHint {
+    /// `INDEXED BY`: the table must be accessed through this index.
+    IndexedBy(Arc<Index>),
+    /// `NOT INDEXED`: the table must not be accessed through any index,
+    /// although lookups by rowid are still allowed.
+    NotIndexed,
 }
 
 #[derive(Debug, Clone)]
@@ -918,6 +960,7 @@ impl JoinedTable {
                 default: None,
                 unique: false,
                 collation: None, // FIXME: infer collation from subquery
+                generated: None,
             })
             .collect();
 
@@ -937,6 +980,7 @@ impl JoinedTable {
             internal_id,
             join_info,
             col_used_mask: ColumnUsedMask::default(),
+            index_hint: None,
         }
     }
 
@@ -1126,10 +1170,30 @@ pub struct Aggregate {
     pub args: Vec<ast::Expr>,
     pub original_expr: ast::Expr,
     pub distinctness: Distinctness,
+    /// The `ORDER BY` in the argument list, e.g. `group_concat(name ORDER BY age DESC)`, which
+    /// sets the order the rows of a group are fed to the aggregate in
+    pub order_by: Vec<(ast::Expr, SortOrder)>,
 }
 
 impl Aggregate {
     pub fn is_distinct(&self) -> bool {
         self.distinctness.is_distinct()
     }
+
+    /// Converts the `ORDER BY` of an aggregate function call to its sort keys
+    pub fn order_by_from_ast(
+        order_by: Option<&Vec<ast::SortedColumn>>,
+    ) -> Vec<(ast::Expr, SortOrder)> {
+        order_by
+            .into_iter()
+            .flatten()
+            .map(|col| (col.expr.clone(), col.order.unwrap_or(SortOrder::Asc)))
+            .collect()
+    }
+
+    /// Number of values evaluated for every row fed to the aggregate: its arguments followed by
+    /// its `ORDER BY` keys
+    pub fn inputs_count(&self) -> usize {
+        self.args.len() + self.order_by.len()
+    }
 }
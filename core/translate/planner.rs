@@ -3,7 +3,7 @@ use std::cell::Cell;
 use super::{
     expr::walk_expr,
     plan::{
-        Aggregate, ColumnUsedMask, Distinctness, EvalAt, IterationDirection, JoinInfo,
+        Aggregate, ColumnUsedMask, Distinctness, EvalAt, IndexHint, IterationDirection, JoinInfo,
         JoinOrderMember, JoinedTable, Operation, OuterQueryReference, Plan, QueryDestination,
         ResultSetColumn, TableReferences, WhereTerm,
     },
@@ -44,6 +44,7 @@ pub fn resolve_aggregates(
                 name,
                 args,
                 distinctness,
+                order_by,
                 ..
             } => {
                 let args_count = if let Some(args) = &args {
@@ -71,6 +72,7 @@ pub fn resolve_aggregates(
                             args: args.clone().unwrap_or_default(),
                             original_expr: expr.clone(),
                             distinctness,
+                            order_by: Aggregate::order_by_from_ast(order_by.as_ref()),
                         });
                         contains_aggregates = true;
                     }
@@ -92,6 +94,7 @@ pub fn resolve_aggregates(
                         args: vec![],
                         original_expr: expr.clone(),
                         distinctness: Distinctness::NonDistinct,
+                        order_by: vec![],
                     });
                     contains_aggregates = true;
                 }
@@ -241,6 +244,25 @@ pub fn bind_column_references(
     })
 }
 
+/// Looks up the index named by an `INDEXED BY` clause on `table_name`.
+pub fn resolve_index_hint(
+    schema: &Schema,
+    table_name: &str,
+    indexed: Option<ast::Indexed>,
+) -> Result<Option<IndexHint>> {
+    match indexed {
+        None => Ok(None),
+        Some(ast::Indexed::NotIndexed) => Ok(Some(IndexHint::NotIndexed)),
+        Some(ast::Indexed::IndexedBy(name)) => {
+            let index_name = normalize_ident(name.0.as_str());
+            match schema.get_index(table_name, &index_name) {
+                Some(index) => Ok(Some(IndexHint::IndexedBy(index.clone()))),
+                None => crate::bail_parse_error!("no such index: {}", index_name),
+            }
+        }
+    }
+}
+
 fn parse_from_clause_table(
     schema: &Schema,
     table: ast::SelectTable,
@@ -250,7 +272,7 @@ fn parse_from_clause_table(
     table_ref_counter: &mut TableRefIdCounter,
 ) -> Result<()> {
     match table {
-        ast::SelectTable::Table(qualified_name, maybe_alias, _) => {
+        ast::SelectTable::Table(qualified_name, maybe_alias, indexed) => {
             let normalized_qualified_name = normalize_ident(qualified_name.name.0.as_str());
             // Check if the FROM clause table is referring to a CTE in the current scope.
             if let Some(cte_idx) = ctes
@@ -280,6 +302,7 @@ fn parse_from_clause_table(
                         "Table type not supported".to_string(),
                     ));
                 };
+                let index_hint = resolve_index_hint(schema, &normalized_qualified_name, indexed)?;
                 table_references.add_joined_table(JoinedTable {
                     op: Operation::Scan {
                         iter_dir: IterationDirection::Forwards,
@@ -290,6 +313,7 @@ fn parse_from_clause_table(
                     internal_id: table_ref_counter.next(),
                     join_info: None,
                     col_used_mask: ColumnUsedMask::default(),
+                    index_hint,
                 });
                 return Ok(());
             };
@@ -315,6 +339,7 @@ fn parse_from_clause_table(
                         internal_id: table_ref_counter.next(),
                         join_info: None,
                         col_used_mask: ColumnUsedMask::default(),
+                        index_hint: None,
                     });
                     return Ok(());
                 }
@@ -373,6 +398,7 @@ fn parse_from_clause_table(
                 identifier: alias,
                 internal_id: table_ref_counter.next(),
                 col_used_mask: ColumnUsedMask::default(),
+                index_hint: None,
             });
 
             Ok(())
@@ -404,9 +430,6 @@ pub fn parse_from(
             if cte.materialized == Materialized::Yes {
                 crate::bail_parse_error!("Materialized CTEs are not yet supported");
             }
-            if cte.columns.is_some() {
-                crate::bail_parse_error!("CTE columns are not yet supported");
-            }
 
             // Check if normalized name conflicts with catalog tables or other CTEs
             // TODO: sqlite actually allows overriding a catalog table with a CTE.
@@ -452,9 +475,23 @@ pub fn parse_from(
                     coroutine_implementation_start: BranchOffset::Placeholder, // will be set later in bytecode emission
                 },
             )?;
-            let Plan::Select(cte_plan) = cte_plan else {
+            let Plan::Select(mut cte_plan) = cte_plan else {
                 crate::bail_parse_error!("Only SELECT queries are currently supported in CTEs");
             };
+            if let Some(columns) = cte.columns {
+                // Column names given to the CTE override the names of its result columns
+                if columns.len() != cte_plan.result_columns.len() {
+                    crate::bail_parse_error!(
+                        "table {} has {} values for {} columns",
+                        cte.tbl_name.0,
+                        cte_plan.result_columns.len(),
+                        columns.len()
+                    );
+                }
+                for (result_column, column) in cte_plan.result_columns.iter_mut().zip(columns) {
+                    result_column.alias = Some(normalize_ident(&column.col_name.0));
+                }
+            }
             ctes_as_subqueries.push(JoinedTable::new_subquery(
                 cte_name_normalized,
                 cte_plan,
@@ -906,6 +943,7 @@ pub fn parse_limit(limit: &Limit) -> Result<(Option<isize>, Option<isize>)> {
         None => Some(0),
     };
 
+    // A negative LIMIT means there is no limit, which is how an OFFSET is written on its own
     if let Expr::Literal(ast::Literal::Numeric(n)) = &limit.expr {
         Ok((n.parse().ok(), offset_val))
     } else if let Expr::Unary(UnaryOperator::Negative, expr) = &limit.expr {
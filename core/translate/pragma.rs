@@ -8,15 +8,15 @@ use turso_sqlite3_parser::ast::{self, Expr};
 
 use crate::schema::Schema;
 use crate::storage::pager::AutoVacuumMode;
-use crate::storage::sqlite3_ondisk::MIN_PAGE_CACHE_SIZE;
 use crate::storage::wal::CheckpointMode;
 use crate::util::{normalize_ident, parse_signed_number};
 use crate::vdbe::builder::{ProgramBuilder, ProgramBuilderOpts};
 use crate::vdbe::insn::{Cookie, Insn};
-use crate::{bail_parse_error, storage, LimboError, Value};
+use crate::{bail_parse_error, LimboError, Value};
 use std::str::FromStr;
 use strum::IntoEnumIterator;
 
+use super::foreign_key_check::translate_foreign_key_check;
 use super::integrity_check::translate_integrity_check;
 use crate::storage::header_accessor;
 use crate::storage::pager::Pager;
@@ -62,7 +62,7 @@ pub fn translate_pragma(
             query_pragma(pragma, schema, None, pager, connection, &mut program)?;
         }
         Some(ast::PragmaBody::Equals(value) | ast::PragmaBody::Call(value)) => match pragma {
-            PragmaName::TableInfo => {
+            PragmaName::TableInfo | PragmaName::ForeignKeyCheck => {
                 query_pragma(pragma, schema, Some(value), pager, connection, &mut program)?;
             }
             _ => {
@@ -94,10 +94,21 @@ fn update_pragma(
                 Value::Float(size) => size as i64,
                 _ => bail_parse_error!("Invalid value for cache size pragma"),
             };
-            update_cache_size(cache_size, pager, connection)?;
+            connection.set_cache_size(cache_size)?;
+            Ok(())
+        }
+        PragmaName::ForeignKeys => {
+            connection.set_foreign_keys_enabled(parse_pragma_bool(&value));
             Ok(())
         }
         PragmaName::JournalMode => {
+            // Rollback journals are not supported, the database stays in WAL mode. Like in
+            // SQLite, an unknown mode leaves the journal mode unchanged.
+            if let Some(mode) = parse_journal_mode(&value) {
+                if mode != "wal" {
+                    bail_parse_error!("journal mode {} is not supported", mode);
+                }
+            }
             query_pragma(
                 PragmaName::JournalMode,
                 schema,
@@ -147,6 +158,21 @@ fn update_pragma(
             });
             Ok(())
         }
+        PragmaName::ApplicationId => {
+            let application_id = match parse_signed_number(&value)? {
+                Value::Integer(i) => i as i32,
+                Value::Float(f) => f as i32,
+                _ => unreachable!(),
+            };
+
+            program.emit_insn(Insn::SetCookie {
+                db: 0,
+                cookie: Cookie::ApplicationId,
+                value: application_id,
+                p5: 1,
+            });
+            Ok(())
+        }
         PragmaName::SchemaVersion => {
             // TODO: Implement updating schema_version
             todo!("updating schema_version not yet implemented")
@@ -158,7 +184,19 @@ fn update_pragma(
             unreachable!();
         }
         PragmaName::PageSize => {
-            bail_parse_error!("Updating database page size is not supported.");
+            let page_size = match parse_signed_number(&value)? {
+                Value::Integer(size) => size,
+                Value::Float(size) => size as i64,
+                _ => unreachable!(),
+            };
+            // Out of range sizes are ignored along with the rest of the invalid ones
+            pager.set_page_size(u32::try_from(page_size).unwrap_or(0))?;
+            // A cache size given in KiB holds a different number of pages now
+            let cache_size = connection.get_cache_size();
+            if cache_size < 0 {
+                connection.set_cache_size(cache_size as i64)?;
+            }
+            Ok(())
         }
         PragmaName::AutoVacuum => {
             let auto_vacuum_mode = match value {
@@ -217,6 +255,7 @@ fn update_pragma(
             Ok(())
         }
         PragmaName::IntegrityCheck => unreachable!("integrity_check cannot be set"),
+        PragmaName::ForeignKeyCheck => unreachable!("foreign_key_check cannot be set"),
     }
 }
 
@@ -235,6 +274,11 @@ fn query_pragma(
             program.emit_result_row(register, 1);
             program.add_pragma_result_column(pragma.to_string());
         }
+        PragmaName::ForeignKeys => {
+            program.emit_bool(connection.foreign_keys_enabled(), register);
+            program.emit_result_row(register, 1);
+            program.add_pragma_result_column(pragma.to_string());
+        }
         PragmaName::JournalMode => {
             program.emit_string8("wal".into(), register);
             program.emit_result_row(register, 1);
@@ -254,12 +298,6 @@ fn query_pragma(
                 _ => CheckpointMode::Passive,
             };
 
-            if !matches!(mode, CheckpointMode::Passive) {
-                return Err(LimboError::ParseError(
-                    "only Passive mode supported".to_string(),
-                ));
-            }
-
             program.alloc_registers(2);
             program.emit_insn(Insn::Checkpoint {
                 database: 0,
@@ -288,7 +326,13 @@ fn query_pragma(
             let base_reg = register;
             program.alloc_registers(5);
             if let Some(table) = table {
-                for (i, column) in table.columns().iter().enumerate() {
+                // Like in SQLite, generated columns are not listed
+                for (i, column) in table
+                    .columns()
+                    .iter()
+                    .filter(|column| column.generated.is_none())
+                    .enumerate()
+                {
                     // cid
                     program.emit_int(i as i64, base_reg);
                     // name
@@ -330,6 +374,15 @@ fn query_pragma(
             program.add_pragma_result_column(pragma.to_string());
             program.emit_result_row(register, 1);
         }
+        PragmaName::ApplicationId => {
+            program.emit_insn(Insn::ReadCookie {
+                db: 0,
+                dest: register,
+                cookie: Cookie::ApplicationId,
+            });
+            program.add_pragma_result_column(pragma.to_string());
+            program.emit_result_row(register, 1);
+        }
         PragmaName::SchemaVersion => {
             program.emit_insn(Insn::ReadCookie {
                 db: 0,
@@ -340,11 +393,7 @@ fn query_pragma(
             program.emit_result_row(register, 1);
         }
         PragmaName::PageSize => {
-            program.emit_int(
-                header_accessor::get_page_size(&pager)
-                    .unwrap_or(storage::sqlite3_ondisk::DEFAULT_PAGE_SIZE) as i64,
-                register,
-            );
+            program.emit_int(pager.get_page_size() as i64, register);
             program.emit_result_row(register, 1);
             program.add_pragma_result_column(pragma.to_string());
         }
@@ -367,11 +416,59 @@ fn query_pragma(
         PragmaName::IntegrityCheck => {
             translate_integrity_check(schema, program)?;
         }
+        PragmaName::ForeignKeyCheck => {
+            let table = match &value {
+                Some(ast::Expr::Name(name)) => Some(name.0.as_str()),
+                Some(ast::Expr::Id(id)) => Some(id.0.as_str()),
+                Some(ast::Expr::Literal(ast::Literal::String(name))) => {
+                    Some(name.trim_matches('\''))
+                }
+                Some(_) => bail_parse_error!("Invalid table name for foreign_key_check"),
+                None => None,
+            };
+            translate_foreign_key_check(schema, table, program)?;
+        }
     }
 
     Ok(())
 }
 
+fn parse_journal_mode(value: &ast::Expr) -> Option<String> {
+    let value = match value {
+        Expr::Name(name) => name.0.clone(),
+        Expr::Id(id) => id.0.clone(),
+        Expr::Literal(ast::Literal::Keyword(keyword)) => keyword.clone(),
+        Expr::Literal(ast::Literal::String(string)) => string.trim_matches('\'').to_string(),
+        _ => return None,
+    };
+    let value = normalize_ident(&value);
+    matches!(
+        value.as_str(),
+        "delete" | "truncate" | "persist" | "memory" | "wal" | "off"
+    )
+    .then_some(value)
+}
+
+/// Interprets a pragma value as a boolean the same way SQLite does:
+/// `ON`, `YES`, `TRUE` and non-zero numbers are true, anything else is false.
+fn parse_pragma_bool(value: &ast::Expr) -> bool {
+    let value = match value {
+        Expr::Name(name) => name.0.clone(),
+        Expr::Id(id) => id.0.clone(),
+        Expr::Literal(ast::Literal::Keyword(keyword)) => keyword.clone(),
+        Expr::Literal(ast::Literal::String(string)) => string.trim_matches('\'').to_string(),
+        expr => {
+            return match parse_signed_number(expr) {
+                Ok(Value::Integer(i)) => i != 0,
+                Ok(Value::Float(f)) => f != 0.0,
+                _ => false,
+            }
+        }
+    };
+    let value = normalize_ident(&value);
+    matches!(value.as_str(), "on" | "yes" | "true") || value.parse::<i64>().is_ok_and(|i| i != 0)
+}
+
 fn update_auto_vacuum_mode(
     auto_vacuum_mode: AutoVacuumMode,
     largest_root_page_number: u32,
@@ -381,57 +478,3 @@ fn update_auto_vacuum_mode(
     pager.set_auto_vacuum_mode(auto_vacuum_mode);
     Ok(())
 }
-
-fn update_cache_size(
-    value: i64,
-    pager: Rc<Pager>,
-    connection: Arc<crate::Connection>,
-) -> crate::Result<()> {
-    let mut cache_size_unformatted: i64 = value;
-
-    let mut cache_size = if cache_size_unformatted < 0 {
-        let kb = cache_size_unformatted.abs().saturating_mul(1024);
-        let page_size = header_accessor::get_page_size(&pager)
-            .unwrap_or(storage::sqlite3_ondisk::DEFAULT_PAGE_SIZE) as i64;
-        if page_size == 0 {
-            return Err(LimboError::InternalError(
-                "Page size cannot be zero".to_string(),
-            ));
-        }
-        kb / page_size
-    } else {
-        value
-    };
-
-    // SQLite uses this value as threshold for maximum cache size
-    const MAX_SAFE_CACHE_SIZE: i64 = 2147450880;
-
-    if cache_size > MAX_SAFE_CACHE_SIZE {
-        cache_size = 0;
-        cache_size_unformatted = 0;
-    }
-
-    if cache_size < 0 {
-        cache_size = 0;
-        cache_size_unformatted = 0;
-    }
-
-    let cache_size_usize = cache_size as usize;
-
-    let final_cache_size = if cache_size_usize < MIN_PAGE_CACHE_SIZE {
-        cache_size_unformatted = MIN_PAGE_CACHE_SIZE as i64;
-        MIN_PAGE_CACHE_SIZE
-    } else {
-        cache_size_usize
-    };
-
-    connection.set_cache_size(cache_size_unformatted as i32);
-
-    pager
-        .change_page_cache_size(final_cache_size)
-        .map_err(|e| {
-            LimboError::InternalError(format!("Failed to update page cache size: {}", e))
-        })?;
-
-    Ok(())
-}
@@ -2,8 +2,11 @@ use turso_sqlite3_parser::ast::Name;
 
 use crate::{
     schema::Schema,
-    translate::emitter::TransactionMode,
-    vdbe::{builder::ProgramBuilder, insn::Insn},
+    translate::{emitter::TransactionMode, transaction::translate_savepoint},
+    vdbe::{
+        builder::ProgramBuilder,
+        insn::{Insn, SavepointOp},
+    },
     Result, SymbolTable,
 };
 
@@ -14,10 +17,10 @@ pub fn translate_rollback(
     txn_name: Option<Name>,
     savepoint_name: Option<Name>,
 ) -> Result<ProgramBuilder> {
-    assert!(
-        txn_name.is_none() && savepoint_name.is_none(),
-        "txn_name and savepoint not supported yet"
-    );
+    if let Some(savepoint_name) = savepoint_name {
+        return translate_savepoint(SavepointOp::RollbackTo, savepoint_name, program);
+    }
+    assert!(txn_name.is_none(), "txn_name not supported yet");
     program.emit_insn(Insn::AutoCommit {
         auto_commit: true,
         rollback: true,
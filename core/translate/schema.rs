@@ -4,15 +4,18 @@ use std::rc::Rc;
 
 use crate::ast;
 use crate::ext::VTabImpl;
+use crate::schema::create_table;
 use crate::schema::BTreeTable;
 use crate::schema::Column;
 use crate::schema::Schema;
 use crate::schema::Table;
 use crate::schema::Type;
 use crate::storage::pager::CreateBTreeFlags;
+use crate::temp::{is_temp_table, temp_schema_table};
+use crate::translate::collate::CollationSeq;
 use crate::translate::ProgramBuilder;
 use crate::translate::ProgramBuilderOpts;
-use crate::util::PRIMARY_KEY_AUTOMATIC_INDEX_NAME_PREFIX;
+use crate::util::{normalize_ident, PRIMARY_KEY_AUTOMATIC_INDEX_NAME_PREFIX};
 use crate::vdbe::builder::CursorType;
 use crate::vdbe::insn::Cookie;
 use crate::vdbe::insn::{CmpInsFlags, InsertFlags, Insn};
@@ -31,16 +34,22 @@ pub fn translate_create_table(
     schema: &Schema,
     mut program: ProgramBuilder,
 ) -> Result<ProgramBuilder> {
-    if temporary {
-        bail_parse_error!("TEMPORARY table not supported yet");
-    }
+    // Like in SQLite, `CREATE TABLE temp.t` creates a temp table too
+    let temporary = temporary
+        || tbl_name
+            .db_name
+            .as_ref()
+            .is_some_and(|db_name| db_name.0.eq_ignore_ascii_case("temp"));
+    let db = if temporary { 1 } else { 0 };
     let opts = ProgramBuilderOpts {
         num_cursors: 1,
         approx_num_insns: 30,
         approx_num_labels: 1,
     };
     program.extend(&opts);
-    if schema.get_table(tbl_name.name.0.as_str()).is_some() {
+    let existing = schema.get_table(tbl_name.name.0.as_str());
+    // A temp table may shadow a table of the main database
+    if existing.is_some_and(|table| !temporary || is_temp_table(&table)) {
         if if_not_exists {
             program.epilogue(crate::translate::emitter::TransactionMode::Write);
 
@@ -49,6 +58,54 @@ pub fn translate_create_table(
         bail_parse_error!("Table {} already exists", tbl_name);
     }
 
+    // Generated columns and AUTOINCREMENT are validated here, as the schema of the new table is
+    // only parsed once its row has been written to sqlite_schema
+    let mut has_autoincrement = false;
+    if let ast::CreateTableBody::ColumnsAndConstraints {
+        columns,
+        constraints,
+        ..
+    } = &body
+    {
+        let has_generated_columns = columns.values().any(|column| {
+            column
+                .constraints
+                .iter()
+                .any(|c| matches!(c.constraint, ast::ColumnConstraint::Generated { .. }))
+        });
+        has_autoincrement = columns.values().any(|column| {
+            column.constraints.iter().any(|c| {
+                matches!(
+                    c.constraint,
+                    ast::ColumnConstraint::PrimaryKey {
+                        auto_increment: true,
+                        ..
+                    }
+                )
+            })
+        }) || constraints.iter().flatten().any(|c| {
+            matches!(
+                c.constraint,
+                ast::TableConstraint::PrimaryKey {
+                    auto_increment: true,
+                    ..
+                }
+            )
+        });
+        if has_generated_columns || has_autoincrement {
+            let table = create_table(tbl_name.clone(), body.clone(), 0)?;
+            if has_generated_columns {
+                table.generated_columns_order()?;
+            }
+        }
+    }
+    if has_autoincrement && temporary {
+        bail_parse_error!("AUTOINCREMENT is not supported on temporary tables");
+    }
+    // The first AUTOINCREMENT table of the database creates sqlite_sequence along with it
+    let create_sequence_table =
+        has_autoincrement && schema.get_table(SQLITE_SEQUENCE_TABLEID).is_none();
+
     let sql = create_table_body_to_str(&tbl_name, &body);
 
     let parse_schema_label = program.allocate_label();
@@ -60,7 +117,7 @@ pub fn translate_create_table(
     // Create the table B-tree
     let table_root_reg = program.alloc_register();
     program.emit_insn(Insn::CreateBtree {
-        db: 0,
+        db,
         root: table_root_reg,
         flags: CreateBTreeFlags::new_table(),
     });
@@ -89,6 +146,16 @@ pub fn translate_create_table(
     // https://github.com/sqlite/sqlite/blob/95f6df5b8d55e67d1e34d2bff217305a2f21b1fb/src/build.c#L2856-L2871
     // https://github.com/sqlite/sqlite/blob/95f6df5b8d55e67d1e34d2bff217305a2f21b1fb/src/build.c#L1334C5-L1336C65
 
+    let sequence_root_reg = create_sequence_table.then(|| {
+        let root_reg = program.alloc_register();
+        program.emit_insn(Insn::CreateBtree {
+            db,
+            root: root_reg,
+            flags: CreateBTreeFlags::new_table(),
+        });
+        root_reg
+    });
+
     let index_regs = check_automatic_pk_index_required(&body, &mut program, &tbl_name.name.0)?;
     if let Some(index_regs) = index_regs.as_ref() {
         if !schema.indexes_enabled() {
@@ -96,18 +163,23 @@ pub fn translate_create_table(
         }
         for index_reg in index_regs.clone() {
             program.emit_insn(Insn::CreateBtree {
-                db: 0,
+                db,
                 root: index_reg,
                 flags: CreateBTreeFlags::new_index(),
             });
         }
     }
 
-    let table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    // The entries of temp tables go to the schema table of the temp database
+    let table = if temporary {
+        Rc::new(temp_schema_table())
+    } else {
+        schema.get_btree_table(SQLITE_TABLEID).unwrap()
+    };
     let sqlite_schema_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
     program.emit_insn(Insn::OpenWrite {
         cursor_id: sqlite_schema_cursor_id,
-        root_page: 1usize.into(),
+        root_page: table.root_page.into(),
         name: tbl_name.name.0.clone(),
     });
 
@@ -143,18 +215,43 @@ pub fn translate_create_table(
         }
     }
 
+    if let Some(sequence_root_reg) = sequence_root_reg {
+        emit_schema_entry(
+            &mut program,
+            sqlite_schema_cursor_id,
+            SchemaEntryType::Table,
+            SQLITE_SEQUENCE_TABLEID,
+            SQLITE_SEQUENCE_TABLEID,
+            sequence_root_reg,
+            Some(format!(
+                "CREATE TABLE {}(name,seq)",
+                SQLITE_SEQUENCE_TABLEID
+            )),
+        );
+    }
+
     program.resolve_label(parse_schema_label, program.offset());
     // TODO: SetCookie
-    program.emit_insn(Insn::SetCookie {
-        db: 0,
-        cookie: Cookie::SchemaVersion,
-        value: schema.schema_version as i32 + 1,
-        p5: 0,
-    });
+    // The schema of the main database is left unchanged by temp tables
+    if !temporary {
+        program.emit_insn(Insn::SetCookie {
+            db: 0,
+            cookie: Cookie::SchemaVersion,
+            value: schema.schema_version as i32 + 1,
+            p5: 0,
+        });
+    }
     // TODO: remove format, it sucks for performance but is convenient
-    let parse_schema_where_clause = format!("tbl_name = '{}' AND type != 'trigger'", tbl_name);
+    let parse_schema_where_clause = if create_sequence_table {
+        format!(
+            "(tbl_name = '{}' OR tbl_name = '{}') AND type != 'trigger'",
+            tbl_name.name.0, SQLITE_SEQUENCE_TABLEID
+        )
+    } else {
+        format!("tbl_name = '{}' AND type != 'trigger'", tbl_name.name.0)
+    };
     program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
+        db,
         where_clause: Some(parse_schema_where_clause),
     });
 
@@ -164,10 +261,51 @@ pub fn translate_create_table(
     Ok(program)
 }
 
+/// Deletes the row of `sqlite_sequence` that belongs to the table `table_name`.
+fn emit_delete_sequence_entry(
+    program: &mut ProgramBuilder,
+    sequence_table: &Rc<BTreeTable>,
+    table_name: &str,
+) {
+    let name_reg = program.emit_string8_new_reg(table_name.to_string());
+    let cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(sequence_table.clone()));
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id,
+        root_page: sequence_table.root_page.into(),
+        name: sequence_table.name.clone(),
+    });
+    let loop_start = program.allocate_label();
+    let loop_end = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id,
+        pc_if_empty: loop_end,
+    });
+    program.preassign_label_to_next_insn(loop_start);
+    let entry_name_reg = program.alloc_register();
+    program.emit_column(cursor_id, 0, entry_name_reg);
+    let next_label = program.allocate_label();
+    program.emit_insn(Insn::Ne {
+        lhs: entry_name_reg,
+        rhs: name_reg,
+        target_pc: next_label,
+        flags: CmpInsFlags::default(),
+        collation: Some(CollationSeq::NoCase),
+    });
+    program.emit_insn(Insn::Delete { cursor_id });
+    program.preassign_label_to_next_insn(next_label);
+    program.emit_insn(Insn::Next {
+        cursor_id,
+        pc_if_next: loop_start,
+    });
+    program.preassign_label_to_next_insn(loop_end);
+    program.emit_insn(Insn::Close { cursor_id });
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum SchemaEntryType {
     Table,
     Index,
+    Trigger,
 }
 
 impl SchemaEntryType {
@@ -175,10 +313,13 @@ impl SchemaEntryType {
         match self {
             SchemaEntryType::Table => "table",
             SchemaEntryType::Index => "index",
+            SchemaEntryType::Trigger => "trigger",
         }
     }
 }
 pub const SQLITE_TABLEID: &str = "sqlite_schema";
+/// Table that holds the largest rowid ever used by each AUTOINCREMENT table
+pub const SQLITE_SEQUENCE_TABLEID: &str = "sqlite_sequence";
 
 pub fn emit_schema_entry(
     program: &mut ProgramBuilder,
@@ -204,7 +345,7 @@ pub fn emit_schema_entry(
     if root_page_reg == 0 {
         program.emit_insn(Insn::Integer {
             dest: rootpage_reg,
-            value: 0, // virtual tables in sqlite always have rootpage=0
+            value: 0, // virtual tables and triggers in sqlite always have rootpage=0
         });
     } else {
         program.emit_insn(Insn::Copy {
@@ -604,7 +745,7 @@ pub fn translate_create_virtual_table(
     });
     let parse_schema_where_clause = format!("tbl_name = '{}' AND type != 'trigger'", table_name);
     program.emit_insn(Insn::ParseSchema {
-        db: sqlite_schema_cursor_id,
+        db: 0,
         where_clause: Some(parse_schema_where_clause),
     });
 
@@ -642,27 +783,36 @@ pub fn translate_drop_table(
 
     let table = table.unwrap(); // safe since we just checked for None
 
+    // The entries of temp tables are in the schema table of the temp database
+    let temporary = is_temp_table(&table);
+    let db = if temporary { 1 } else { 0 };
+    if normalize_ident(&tbl_name.name.0) == SQLITE_SEQUENCE_TABLEID {
+        bail_parse_error!("table {} may not be dropped", SQLITE_SEQUENCE_TABLEID);
+    }
+
     let null_reg = program.alloc_register(); //  r1
     program.emit_null(null_reg, None);
     let table_name_and_root_page_register = program.alloc_register(); //  r2, this register is special because it's first used to track table name and then moved root page
     let table_reg = program.emit_string8_new_reg(tbl_name.name.0.clone()); //  r3
     program.mark_last_insn_constant();
-    let table_type = program.emit_string8_new_reg("trigger".to_string()); //  r4
-    program.mark_last_insn_constant();
-    let row_id_reg = program.alloc_register(); //  r5
+    let row_id_reg = program.alloc_register(); //  r4
 
-    let schema_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let schema_table = if temporary {
+        Rc::new(temp_schema_table())
+    } else {
+        schema.get_btree_table(SQLITE_TABLEID).unwrap()
+    };
     let sqlite_schema_cursor_id_0 = program.alloc_cursor_id(
         //  cursor 0
         CursorType::BTreeTable(schema_table.clone()),
     );
     program.emit_insn(Insn::OpenWrite {
         cursor_id: sqlite_schema_cursor_id_0,
-        root_page: 1usize.into(),
-        name: SQLITE_TABLEID.to_string(),
+        root_page: schema_table.root_page.into(),
+        name: schema_table.name.clone(),
     });
 
-    //  1. Remove all entries from the schema table related to the table we are dropping, except for triggers
+    //  1. Remove all entries from the schema table related to the table we are dropping, triggers included
     //  loop to beginning of schema table
     let end_metadata_label = program.allocate_label();
     let metadata_loop = program.allocate_label();
@@ -686,18 +836,6 @@ pub fn translate_drop_table(
         flags: CmpInsFlags::default(),
         collation: program.curr_collation(),
     });
-    program.emit_column(
-        sqlite_schema_cursor_id_0,
-        0,
-        table_name_and_root_page_register,
-    );
-    program.emit_insn(Insn::Eq {
-        lhs: table_name_and_root_page_register,
-        rhs: table_type,
-        target_pc: next_label,
-        flags: CmpInsFlags::default(),
-        collation: program.curr_collation(),
-    });
     program.emit_insn(Insn::RowId {
         cursor_id: sqlite_schema_cursor_id_0,
         dest: row_id_reg,
@@ -714,13 +852,20 @@ pub fn translate_drop_table(
     program.preassign_label_to_next_insn(end_metadata_label);
     //  end of loop on schema table
 
+    //  Forget the largest rowid of an AUTOINCREMENT table, before any root page is moved
+    if let Some(btree) = table.btree().filter(|table| table.has_autoincrement) {
+        if let Some(sequence_table) = schema.get_btree_table(SQLITE_SEQUENCE_TABLEID) {
+            emit_delete_sequence_entry(&mut program, &sequence_table, &btree.name);
+        }
+    }
+
     //  2. Destroy the indices within a loop
     let indices = schema.get_indices(&tbl_name.name.0);
     for index in indices {
         program.emit_insn(Insn::Destroy {
             root: index.root_page,
             former_root_reg: 0, //  no autovacuum (https://www.sqlite.org/opcode.html#Destroy)
-            is_temp: 0,
+            is_temp: db,
         });
 
         //  3. TODO: Open an ephemeral table, and read over triggers from schema table into ephemeral table
@@ -736,7 +881,7 @@ pub fn translate_drop_table(
             program.emit_insn(Insn::Destroy {
                 root: table.root_page,
                 former_root_reg: table_name_and_root_page_register,
-                is_temp: 0,
+                is_temp: db,
             });
         }
         Table::Virtual(vtab) => {
@@ -783,9 +928,13 @@ pub fn translate_drop_table(
                 default: None,
                 unique: false,
                 collation: None,
+                generated: None,
             }],
             is_strict: false,
             unique_sets: None,
+            foreign_keys: vec![],
+            has_autoincrement: false,
+            check_constraints: vec![],
         });
         //  cursor id 2
         let ephemeral_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(simple_table_rc));
@@ -801,7 +950,7 @@ pub fn translate_drop_table(
         });
         program.emit_insn(Insn::OpenRead {
             cursor_id: sqlite_schema_cursor_id_1,
-            root_page: 1usize,
+            root_page: schema_table.root_page,
         });
 
         let schema_column_0_register = program.alloc_register();
@@ -857,8 +1006,8 @@ pub fn translate_drop_table(
         //  5. Open a write cursor to the schema table and re-insert the records placed in the ephemeral table but insert the correct root page now
         program.emit_insn(Insn::OpenWrite {
             cursor_id: sqlite_schema_cursor_id_1,
-            root_page: 1usize.into(),
-            name: SQLITE_TABLEID.to_string(),
+            root_page: schema_table.root_page.into(),
+            name: schema_table.name.clone(),
         });
 
         //  Loop to copy over row id's from the ephemeral table and then re-insert into the schema table with the correct root page
@@ -907,7 +1056,7 @@ pub fn translate_drop_table(
             key_reg: schema_row_id_register,
             record_reg: new_record_register,
             flag: InsertFlags::new(),
-            table_name: SQLITE_TABLEID.to_string(),
+            table_name: schema_table.name.clone(),
         });
 
         program.resolve_label(next_label, program.offset());
@@ -921,7 +1070,7 @@ pub fn translate_drop_table(
 
     //  Drop the in-memory structures for the table
     program.emit_insn(Insn::DropTable {
-        db: 0,
+        db,
         _p2: 0,
         _p3: 0,
         table_name: tbl_name.name.0,
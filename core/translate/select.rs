@@ -160,10 +160,23 @@ pub fn prepare_select_plan(
             if offset.map_or(false, |o| o > 0) {
                 crate::bail_parse_error!("OFFSET is not supported for compound SELECTs yet");
             }
-            // FIXME: handle ORDER BY for compound selects
-            if select.order_by.is_some() {
-                crate::bail_parse_error!("ORDER BY is not supported for compound SELECTs yet");
-            }
+            let order_by = select
+                .order_by
+                .map(|order_by| {
+                    let selects = left
+                        .iter()
+                        .map(|(plan, _)| plan)
+                        .chain(std::iter::once(&last))
+                        .collect::<Vec<_>>();
+                    order_by
+                        .into_iter()
+                        .map(|o| {
+                            let expr = resolve_compound_order_by_term(o.expr, &selects)?;
+                            Ok((expr, o.order.unwrap_or(ast::SortOrder::Asc)))
+                        })
+                        .collect::<Result<Vec<_>>>()
+                })
+                .transpose()?;
             // FIXME: handle WITH for compound selects
             if select.with.is_some() {
                 crate::bail_parse_error!("WITH is not supported for compound SELECTs yet");
@@ -173,12 +186,55 @@ pub fn prepare_select_plan(
                 right_most: last,
                 limit,
                 offset,
-                order_by: None,
+                order_by,
             })
         }
     }
 }
 
+/// Resolves a term of the ORDER BY of a compound SELECT to the number of the result column it
+/// sorts by, keeping its COLLATE clause if any. Like in SQLite, a name is looked up among the
+/// result columns of each SELECT in turn, starting from the leftmost one.
+fn resolve_compound_order_by_term(expr: ast::Expr, selects: &[&SelectPlan]) -> Result<ast::Expr> {
+    let (expr, collation) = match expr {
+        ast::Expr::Collate(expr, collation) => (*expr, Some(collation)),
+        expr => (expr, None),
+    };
+    let num_columns = selects[0].result_columns.len();
+    let column_number = match &expr {
+        ast::Expr::Literal(ast::Literal::Numeric(num)) => {
+            let column_number = num.parse::<usize>()?;
+            if column_number == 0 || column_number > num_columns {
+                crate::bail_parse_error!("invalid column index: {}", column_number);
+            }
+            column_number
+        }
+        ast::Expr::Id(ast::Id(name)) | ast::Expr::Qualified(_, ast::Name(name)) => {
+            let name = normalize_ident(name);
+            let Some(position) = selects.iter().find_map(|select| {
+                select.result_columns.iter().position(|rc| {
+                    rc.name(&select.table_references)
+                        .is_some_and(|n| n.eq_ignore_ascii_case(&name))
+                })
+            }) else {
+                crate::bail_parse_error!(
+                    "ORDER BY term does not match any column in the result set: {}",
+                    name
+                );
+            };
+            position + 1
+        }
+        _ => crate::bail_parse_error!(
+            "ORDER BY term of a compound SELECT must be a column name or number"
+        ),
+    };
+    let expr = ast::Expr::Literal(ast::Literal::Numeric(column_number.to_string()));
+    Ok(match collation {
+        Some(collation) => ast::Expr::Collate(Box::new(expr), collation),
+        None => expr,
+    })
+}
+
 #[allow(clippy::too_many_arguments)]
 fn prepare_one_select_plan(
     schema: &Schema,
@@ -332,7 +388,7 @@ fn prepare_one_select_plan(
                                 distinctness,
                                 args,
                                 filter_over: _,
-                                order_by: _,
+                                order_by,
                             } => {
                                 let args_count = if let Some(args) = &args {
                                     args.len()
@@ -373,6 +429,9 @@ fn prepare_one_select_plan(
                                             args: agg_args.clone(),
                                             original_expr: expr.clone(),
                                             distinctness,
+                                            order_by: Aggregate::order_by_from_ast(
+                                                order_by.as_ref(),
+                                            ),
                                         };
                                         aggregate_expressions.push(agg.clone());
                                         plan.result_columns.push(ResultSetColumn {
@@ -426,6 +485,9 @@ fn prepare_one_select_plan(
                                                     args: args.as_ref().unwrap().clone(),
                                                     original_expr: expr.clone(),
                                                     distinctness,
+                                                    order_by: Aggregate::order_by_from_ast(
+                                                        order_by.as_ref(),
+                                                    ),
                                                 };
                                                 aggregate_expressions.push(agg.clone());
                                                 plan.result_columns.push(ResultSetColumn {
@@ -463,6 +525,7 @@ fn prepare_one_select_plan(
                                         ))],
                                         original_expr: expr.clone(),
                                         distinctness: Distinctness::NonDistinct,
+                                        order_by: vec![],
                                     };
                                     aggregate_expressions.push(agg.clone());
                                     plan.result_columns.push(ResultSetColumn {
@@ -583,7 +646,8 @@ fn prepare_one_select_plan(
                 result_columns.push(ResultSetColumn {
                     // these result_columns work as placeholders for the values, so the expr doesn't matter
                     expr: ast::Expr::Literal(ast::Literal::Numeric(i.to_string())),
-                    alias: None,
+                    // like sqlite, name the columns column1, column2, ...
+                    alias: Some(format!("column{}", i + 1)),
                     contains_aggregates: false,
                 });
             }
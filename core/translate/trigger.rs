@@ -0,0 +1,748 @@
+use crate::schema::{BTreeTable, Schema, Trigger};
+use crate::temp::is_temp_table;
+use crate::translate::delete::translate_delete;
+use crate::translate::emitter::{Resolver, TransactionMode};
+use crate::translate::expr::{translate_expr, walk_expr, walk_expr_mut, WalkControl};
+use crate::translate::insert::translate_insert;
+use crate::translate::optimizer::rewrite_expr;
+use crate::translate::schema::{emit_schema_entry, SchemaEntryType, SQLITE_TABLEID};
+use crate::translate::update::translate_update;
+use crate::util::normalize_ident;
+use crate::vdbe::builder::{CursorType, ProgramBuilder, ProgramBuilderOpts, QueryMode};
+use crate::vdbe::insn::{CmpInsFlags, Cookie, Insn, RegisterOrLiteral};
+use crate::{bail_parse_error, Result, SymbolTable};
+use turso_sqlite3_parser::ast::{self, fmt::ToTokens};
+
+/// Registers holding the `NEW` or `OLD` row of the table a trigger fires on
+#[derive(Debug, Clone, Copy)]
+pub struct TriggerRow {
+    /// First of the registers holding the columns of the row, in the order of the table
+    pub columns_start: usize,
+    pub rowid_reg: usize,
+}
+
+/// The change to a row of a table that fires its triggers
+pub enum TriggerOperation<'a> {
+    Insert,
+    /// An `UPDATE` setting the columns at these positions of the table
+    Update(&'a [usize]),
+    Delete,
+}
+
+pub fn translate_create_trigger(
+    trigger: ast::CreateTrigger,
+    schema: &Schema,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    let opts = ProgramBuilderOpts {
+        num_cursors: 1,
+        approx_num_insns: 20,
+        approx_num_labels: 1,
+    };
+    program.extend(&opts);
+    let temporary = trigger.temporary
+        || trigger
+            .trigger_name
+            .db_name
+            .as_ref()
+            .is_some_and(|db_name| db_name.0.eq_ignore_ascii_case("temp"));
+    if temporary {
+        bail_parse_error!("TEMP triggers are not supported yet");
+    }
+    let trigger_name = normalize_ident(&trigger.trigger_name.name.0);
+    let table_name = normalize_ident(&trigger.tbl_name.name.0);
+    if schema.get_trigger(&trigger_name).is_some() {
+        if trigger.if_not_exists {
+            program.epilogue(TransactionMode::Write);
+            return Ok(program);
+        }
+        bail_parse_error!("trigger {} already exists", trigger_name);
+    }
+    let Some(table) = schema.get_table(&table_name) else {
+        bail_parse_error!("no such table: {}", table_name);
+    };
+    if table_name.starts_with("sqlite_") {
+        bail_parse_error!("cannot create trigger on system table");
+    }
+    if table.virtual_table().is_some() {
+        bail_parse_error!("cannot create triggers on virtual tables");
+    }
+    if is_temp_table(&table) {
+        bail_parse_error!("triggers on temporary tables are not supported yet");
+    }
+    match trigger.time {
+        Some(ast::TriggerTime::After) => {}
+        Some(ast::TriggerTime::InsteadOf) => {
+            bail_parse_error!("cannot create INSTEAD OF trigger on table: {}", table_name)
+        }
+        Some(ast::TriggerTime::Before) | None => {
+            bail_parse_error!("BEFORE triggers are not supported yet")
+        }
+    }
+    if let Some(when_clause) = &trigger.when_clause {
+        check_when_clause(when_clause)?;
+    }
+    for command in &trigger.commands {
+        if let ast::TriggerCmd::Select(_) = command {
+            bail_parse_error!("SELECT in the body of a trigger is not supported yet");
+        }
+    }
+    let sql = ast::Stmt::CreateTrigger(Box::new(trigger))
+        .format()
+        .unwrap();
+
+    let sqlite_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let sqlite_schema_cursor_id =
+        program.alloc_cursor_id(CursorType::BTreeTable(sqlite_table.clone()));
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id: sqlite_schema_cursor_id,
+        root_page: RegisterOrLiteral::Literal(sqlite_table.root_page),
+        name: sqlite_table.name.clone(),
+    });
+    // Triggers have no b-tree, so their root page is 0
+    emit_schema_entry(
+        &mut program,
+        sqlite_schema_cursor_id,
+        SchemaEntryType::Trigger,
+        &trigger_name,
+        &table_name,
+        0,
+        Some(sql),
+    );
+
+    program.emit_insn(Insn::SetCookie {
+        db: 0,
+        cookie: Cookie::SchemaVersion,
+        value: schema.schema_version as i32 + 1,
+        p5: 0,
+    });
+    let parse_schema_where_clause = format!("name = '{}' AND type = 'trigger'", trigger_name);
+    program.emit_insn(Insn::ParseSchema {
+        db: 0,
+        where_clause: Some(parse_schema_where_clause),
+    });
+    program.emit_insn(Insn::Close {
+        cursor_id: sqlite_schema_cursor_id,
+    });
+
+    program.epilogue(TransactionMode::Write);
+    Ok(program)
+}
+
+pub fn translate_drop_trigger(
+    trigger_name: &ast::QualifiedName,
+    if_exists: bool,
+    schema: &Schema,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    let opts = ProgramBuilderOpts {
+        num_cursors: 1,
+        approx_num_insns: 20,
+        approx_num_labels: 3,
+    };
+    program.extend(&opts);
+    let trigger_name = normalize_ident(&trigger_name.name.0);
+    if schema.get_trigger(&trigger_name).is_none() {
+        if if_exists {
+            program.epilogue(TransactionMode::Write);
+            return Ok(program);
+        }
+        bail_parse_error!("no such trigger: {}", trigger_name);
+    }
+
+    let trigger_name_reg = program.emit_string8_new_reg(trigger_name.clone());
+    let trigger_type_reg = program.emit_string8_new_reg("trigger".to_string());
+    let dest_reg = program.alloc_register();
+
+    let sqlite_table = schema.get_btree_table(SQLITE_TABLEID).unwrap();
+    let sqlite_schema_cursor_id =
+        program.alloc_cursor_id(CursorType::BTreeTable(sqlite_table.clone()));
+    program.emit_insn(Insn::OpenWrite {
+        cursor_id: sqlite_schema_cursor_id,
+        root_page: RegisterOrLiteral::Literal(sqlite_table.root_page),
+        name: sqlite_table.name.clone(),
+    });
+
+    // Delete the entry of the trigger from sqlite_schema
+    let loop_start_label = program.allocate_label();
+    let loop_end_label = program.allocate_label();
+    let next_label = program.allocate_label();
+    program.emit_insn(Insn::Rewind {
+        cursor_id: sqlite_schema_cursor_id,
+        pc_if_empty: loop_end_label,
+    });
+    program.preassign_label_to_next_insn(loop_start_label);
+    program.emit_column(sqlite_schema_cursor_id, 1, dest_reg);
+    program.emit_insn(Insn::Ne {
+        lhs: trigger_name_reg,
+        rhs: dest_reg,
+        target_pc: next_label,
+        flags: CmpInsFlags::default(),
+        collation: program.curr_collation(),
+    });
+    program.emit_column(sqlite_schema_cursor_id, 0, dest_reg);
+    program.emit_insn(Insn::Ne {
+        lhs: trigger_type_reg,
+        rhs: dest_reg,
+        target_pc: next_label,
+        flags: CmpInsFlags::default(),
+        collation: program.curr_collation(),
+    });
+    program.emit_insn(Insn::Delete {
+        cursor_id: sqlite_schema_cursor_id,
+    });
+    program.preassign_label_to_next_insn(next_label);
+    program.emit_insn(Insn::Next {
+        cursor_id: sqlite_schema_cursor_id,
+        pc_if_next: loop_start_label,
+    });
+    program.preassign_label_to_next_insn(loop_end_label);
+
+    program.emit_insn(Insn::SetCookie {
+        db: 0,
+        cookie: Cookie::SchemaVersion,
+        value: schema.schema_version as i32 + 1,
+        p5: 0,
+    });
+    program.emit_insn(Insn::DropTrigger {
+        db: 0,
+        trigger_name,
+    });
+
+    program.epilogue(TransactionMode::Write);
+    Ok(program)
+}
+
+/// Emits the commands of the triggers of `table` that fire for `operation`, to be run right after
+/// the row has been written. `new_row` and `old_row` are the rows `NEW` and `OLD` refer to.
+pub fn emit_triggers(
+    program: &mut ProgramBuilder,
+    schema: &Schema,
+    syms: &SymbolTable,
+    table: &BTreeTable,
+    operation: TriggerOperation,
+    new_row: Option<TriggerRow>,
+    old_row: Option<TriggerRow>,
+) -> Result<()> {
+    let rows = TriggerRows {
+        table,
+        new: new_row,
+        old: old_row,
+    };
+    // Like in SQLite, the most recently created trigger fires first
+    for trigger in schema.get_triggers(&table.name).iter().rev() {
+        // As with `PRAGMA recursive_triggers` off, a trigger does not fire from its own commands
+        if !fires_on(trigger, table, &operation) || program.active_triggers.contains(&trigger.name)
+        {
+            continue;
+        }
+        if trigger.time != ast::TriggerTime::After {
+            bail_parse_error!("BEFORE and INSTEAD OF triggers are not supported yet");
+        }
+        let trigger_done_label = program.allocate_label();
+        if let Some(when_clause) = &trigger.when_clause {
+            let mut when_clause = when_clause.clone();
+            rows.rewrite_expr(&mut when_clause)?;
+            check_when_clause(&when_clause)?;
+            let mut param_idx = 1;
+            rewrite_expr(&mut when_clause, &mut param_idx)?;
+            let reg = program.alloc_register();
+            translate_expr(
+                program,
+                None,
+                &when_clause,
+                reg,
+                &Resolver::new(schema, syms),
+            )?;
+            program.emit_insn(Insn::IfNot {
+                reg,
+                target_pc: trigger_done_label,
+                jump_if_null: true,
+            });
+        }
+        program.active_triggers.push(trigger.name.clone());
+        for command in &trigger.commands {
+            let mut command = command.clone();
+            rows.rewrite_command(&mut command)?;
+            translate_command(program, command, schema, syms)?;
+        }
+        program.active_triggers.pop();
+        program.preassign_label_to_next_insn(trigger_done_label);
+    }
+    Ok(())
+}
+
+/// Whether any trigger of `table` fires for `operation`, in which case the `NEW` and `OLD` rows
+/// must be kept in registers
+pub fn has_triggers(schema: &Schema, table: &BTreeTable, operation: &TriggerOperation) -> bool {
+    schema
+        .get_triggers(&table.name)
+        .iter()
+        .any(|trigger| fires_on(trigger, table, operation))
+}
+
+fn fires_on(trigger: &Trigger, table: &BTreeTable, operation: &TriggerOperation) -> bool {
+    match (&trigger.event, operation) {
+        (ast::TriggerEvent::Insert, TriggerOperation::Insert)
+        | (ast::TriggerEvent::Update, TriggerOperation::Update(_))
+        | (ast::TriggerEvent::Delete, TriggerOperation::Delete) => true,
+        (ast::TriggerEvent::UpdateOf(names), TriggerOperation::Update(columns)) => {
+            names.iter().any(|name| {
+                table
+                    .get_column(&name.0)
+                    .is_some_and(|(idx, _)| columns.contains(&idx))
+            })
+        }
+        _ => false,
+    }
+}
+
+/// The WHEN clause is evaluated on its own, outside of any query, so it can only refer to the
+/// `NEW` and `OLD` rows.
+fn check_when_clause(when_clause: &ast::Expr) -> Result<()> {
+    walk_expr(
+        when_clause,
+        &mut |expr: &ast::Expr| -> Result<WalkControl> {
+            match expr {
+                ast::Expr::Id(id) => {
+                    // TRUE and FALSE are parsed as identifiers
+                    if !matches!(normalize_ident(&id.0).as_str(), "true" | "false") {
+                        bail_parse_error!("no such column: {}", id.0);
+                    }
+                    Ok(WalkControl::Continue)
+                }
+                ast::Expr::Exists(_) | ast::Expr::Subquery(_) | ast::Expr::InSelect { .. } => {
+                    bail_parse_error!("subqueries in trigger WHEN clauses are not supported yet")
+                }
+                _ => Ok(WalkControl::Continue),
+            }
+        },
+    )?;
+    Ok(())
+}
+
+/// Translates a command of a trigger, nested in the program of the statement that fires it.
+fn translate_command(
+    program: &mut ProgramBuilder,
+    command: ast::TriggerCmd,
+    schema: &Schema,
+    syms: &SymbolTable,
+) -> Result<()> {
+    let foreign_keys = program.foreign_keys;
+    // The translate functions take the program by value
+    let placeholder = ProgramBuilder::new(
+        QueryMode::Normal,
+        ProgramBuilderOpts {
+            num_cursors: 0,
+            approx_num_insns: 0,
+            approx_num_labels: 0,
+        },
+    );
+    let mut nested = std::mem::replace(program, placeholder);
+    nested.incr_nesting();
+    let mut nested = match command {
+        ast::TriggerCmd::Update(update) => {
+            let ast::TriggerCmdUpdate {
+                or_conflict,
+                tbl_name,
+                sets,
+                from,
+                where_clause,
+            } = *update;
+            let mut update = ast::Update {
+                with: None,
+                or_conflict,
+                tbl_name: ast::QualifiedName::single(tbl_name),
+                indexed: None,
+                sets,
+                from,
+                where_clause: where_clause.map(Box::new),
+                returning: None,
+                order_by: None,
+                limit: None,
+            };
+            translate_update(schema, &mut update, syms, nested)?
+        }
+        ast::TriggerCmd::Insert(insert) => {
+            let ast::TriggerCmdInsert {
+                or_conflict,
+                tbl_name,
+                col_names,
+                select,
+                upsert,
+                returning,
+            } = *insert;
+            translate_insert(
+                schema,
+                None,
+                or_conflict,
+                ast::QualifiedName::single(tbl_name),
+                col_names,
+                ast::InsertBody::Select(select, upsert),
+                returning,
+                syms,
+                foreign_keys,
+                nested,
+            )?
+        }
+        ast::TriggerCmd::Delete(delete) => translate_delete(
+            schema,
+            &ast::QualifiedName::single(delete.tbl_name),
+            delete.where_clause.map(Box::new),
+            None,
+            None,
+            syms,
+            nested,
+        )?,
+        ast::TriggerCmd::Select(_) => {
+            bail_parse_error!("SELECT in the body of a trigger is not supported yet")
+        }
+    };
+    nested.decr_nesting();
+    *program = nested;
+    Ok(())
+}
+
+/// The rows `NEW` and `OLD` refer to, whose references in the body of a trigger are replaced by
+/// the registers holding their values
+struct TriggerRows<'a> {
+    table: &'a BTreeTable,
+    new: Option<TriggerRow>,
+    old: Option<TriggerRow>,
+}
+
+impl TriggerRows<'_> {
+    /// The register of `qualifier.column`, if `qualifier` is `NEW` or `OLD`
+    fn register(&self, qualifier: &ast::Name, column: &ast::Name) -> Result<Option<usize>> {
+        let row = match normalize_ident(&qualifier.0).as_str() {
+            "new" => self.new,
+            "old" => self.old,
+            _ => return Ok(None),
+        };
+        let Some(row) = row else {
+            bail_parse_error!("no such column: {}.{}", qualifier.0, column.0);
+        };
+        match self.table.get_column(&column.0) {
+            Some((_, col)) if col.is_rowid_alias => Ok(Some(row.rowid_reg)),
+            Some((idx, _)) => Ok(Some(row.columns_start + idx)),
+            None if ["rowid", "_rowid_", "oid"].contains(&normalize_ident(&column.0).as_str()) => {
+                Ok(Some(row.rowid_reg))
+            }
+            None => bail_parse_error!("no such column: {}.{}", qualifier.0, column.0),
+        }
+    }
+
+    fn rewrite_expr(&self, expr: &mut ast::Expr) -> Result<()> {
+        walk_exprs(expr, &mut |expr| self.rewrite_row_reference(expr))
+    }
+
+    fn rewrite_command(&self, command: &mut ast::TriggerCmd) -> Result<()> {
+        walk_command_exprs(command, &mut |expr| self.rewrite_row_reference(expr))
+    }
+
+    fn rewrite_row_reference(&self, expr: &mut ast::Expr) -> Result<()> {
+        if let ast::Expr::Qualified(qualifier, column) = expr {
+            if let Some(reg) = self.register(qualifier, column)? {
+                *expr = ast::Expr::Register(reg);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renames the references of a trigger to table `from`, for `ALTER TABLE RENAME TO`. Returns
+/// whether the trigger refers to the table.
+pub fn rename_trigger_table(
+    trigger: &mut ast::CreateTrigger,
+    from: &str,
+    to: &str,
+) -> Result<bool> {
+    let mut renamed = false;
+    if normalize_ident(&trigger.tbl_name.name.0) == from {
+        trigger.tbl_name.name = ast::Name(to.to_string());
+        renamed = true;
+    }
+    for command in &mut trigger.commands {
+        walk_command_exprs(command, &mut |expr| {
+            if let ast::Expr::Qualified(qualifier, _) = expr {
+                if normalize_ident(&qualifier.0) == from {
+                    *qualifier = ast::Name(to.to_string());
+                    renamed = true;
+                }
+            }
+            Ok(())
+        })?;
+        let tbl_name = match command {
+            ast::TriggerCmd::Update(update) => &mut update.tbl_name,
+            ast::TriggerCmd::Insert(insert) => &mut insert.tbl_name,
+            ast::TriggerCmd::Delete(delete) => &mut delete.tbl_name,
+            ast::TriggerCmd::Select(_) => continue,
+        };
+        if normalize_ident(&tbl_name.0) == from {
+            *tbl_name = ast::Name(to.to_string());
+            renamed = true;
+        }
+    }
+    Ok(renamed)
+}
+
+/// Renames the references of a trigger to column `from` of `table`, for
+/// `ALTER TABLE RENAME COLUMN`. Returns whether the trigger refers to the column.
+pub fn rename_trigger_column(
+    trigger: &mut ast::CreateTrigger,
+    table: &str,
+    from: &str,
+    to: &str,
+) -> Result<bool> {
+    let mut renamed = false;
+    // `NEW` and `OLD` are rows of the table the trigger fires on
+    let on_table = normalize_ident(&trigger.tbl_name.name.0) == table;
+    if on_table {
+        if let ast::TriggerEvent::UpdateOf(names) = &mut trigger.event {
+            renamed |= rename_in_names(names, from, to);
+        }
+    }
+    for command in &mut trigger.commands {
+        match command {
+            ast::TriggerCmd::Update(update) if normalize_ident(&update.tbl_name.0) == table => {
+                for set in &mut update.sets {
+                    renamed |= rename_in_names(&mut set.col_names, from, to);
+                }
+            }
+            ast::TriggerCmd::Insert(insert) if normalize_ident(&insert.tbl_name.0) == table => {
+                if let Some(col_names) = &mut insert.col_names {
+                    renamed |= rename_in_names(col_names, from, to);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut rename_qualified = |expr: &mut ast::Expr| -> Result<()> {
+        if let ast::Expr::Qualified(qualifier, column) = expr {
+            let qualifier = normalize_ident(&qualifier.0);
+            let of_table =
+                qualifier == table || (on_table && (qualifier == "new" || qualifier == "old"));
+            if of_table && normalize_ident(&column.0) == from {
+                *column = ast::Name(to.to_string());
+                renamed = true;
+            }
+        }
+        Ok(())
+    };
+    if let Some(when_clause) = &mut trigger.when_clause {
+        walk_exprs(when_clause, &mut rename_qualified)?;
+    }
+    for command in &mut trigger.commands {
+        walk_command_exprs(command, &mut rename_qualified)?;
+    }
+
+    // The unqualified columns of a command, outside of its subqueries, are the ones of the table
+    // it changes
+    let mut rename_unqualified = |expr: &mut ast::Expr| -> Result<()> {
+        walk_expr_mut(expr, &mut |expr: &mut ast::Expr| -> Result<()> {
+            if let ast::Expr::Id(id) = expr {
+                if normalize_ident(&id.0) == from {
+                    *id = ast::Id(to.to_string());
+                    renamed = true;
+                }
+            }
+            Ok(())
+        })
+    };
+    for command in &mut trigger.commands {
+        match command {
+            ast::TriggerCmd::Update(update) if normalize_ident(&update.tbl_name.0) == table => {
+                for set in &mut update.sets {
+                    rename_unqualified(&mut set.expr)?;
+                }
+                if let Some(where_clause) = &mut update.where_clause {
+                    rename_unqualified(where_clause)?;
+                }
+            }
+            ast::TriggerCmd::Delete(delete) if normalize_ident(&delete.tbl_name.0) == table => {
+                if let Some(where_clause) = &mut delete.where_clause {
+                    rename_unqualified(where_clause)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(renamed)
+}
+
+/// Replaces `from` by `to` in a list of column names, returning whether it was there
+fn rename_in_names(names: &mut ast::DistinctNames, from: &str, to: &str) -> bool {
+    if !names.iter().any(|name| normalize_ident(&name.0) == from) {
+        return false;
+    }
+    let mut renamed_names = names.iter().map(|name| {
+        if normalize_ident(&name.0) == from {
+            ast::Name(to.to_string())
+        } else {
+            name.clone()
+        }
+    });
+    let mut new_names = ast::DistinctNames::new(renamed_names.next().unwrap());
+    for name in renamed_names {
+        new_names
+            .insert(name)
+            .expect("renamed column should not already be in the list");
+    }
+    *names = new_names;
+    true
+}
+
+/// Calls `func` on every expression of `command`, including the ones of its subqueries
+fn walk_command_exprs<F>(command: &mut ast::TriggerCmd, func: &mut F) -> Result<()>
+where
+    F: FnMut(&mut ast::Expr) -> Result<()>,
+{
+    match command {
+        ast::TriggerCmd::Update(update) => {
+            for set in &mut update.sets {
+                walk_exprs(&mut set.expr, func)?;
+            }
+            if let Some(from) = &mut update.from {
+                walk_from_exprs(from, func)?;
+            }
+            if let Some(where_clause) = &mut update.where_clause {
+                walk_exprs(where_clause, func)?;
+            }
+        }
+        ast::TriggerCmd::Insert(insert) => {
+            walk_select_exprs(&mut insert.select, func)?;
+            let mut upsert = insert.upsert.as_mut();
+            while let Some(current) = upsert {
+                if let Some(where_clause) = current
+                    .index
+                    .as_mut()
+                    .and_then(|index| index.where_clause.as_mut())
+                {
+                    walk_exprs(where_clause, func)?;
+                }
+                if let ast::UpsertDo::Set { sets, where_clause } = current.do_clause.as_mut() {
+                    for set in sets {
+                        walk_exprs(&mut set.expr, func)?;
+                    }
+                    if let Some(where_clause) = where_clause {
+                        walk_exprs(where_clause, func)?;
+                    }
+                }
+                upsert = current.next.as_deref_mut();
+            }
+        }
+        ast::TriggerCmd::Delete(delete) => {
+            if let Some(where_clause) = &mut delete.where_clause {
+                walk_exprs(where_clause, func)?;
+            }
+        }
+        ast::TriggerCmd::Select(select) => walk_select_exprs(select, func)?,
+    }
+    Ok(())
+}
+
+/// Like [walk_expr_mut], but also walks the expressions of subqueries
+fn walk_exprs<F>(expr: &mut ast::Expr, func: &mut F) -> Result<()>
+where
+    F: FnMut(&mut ast::Expr) -> Result<()>,
+{
+    walk_expr_mut(expr, &mut |expr: &mut ast::Expr| -> Result<()> {
+        func(expr)?;
+        match expr {
+            ast::Expr::Exists(select) | ast::Expr::Subquery(select) => {
+                walk_select_exprs(select, func)
+            }
+            ast::Expr::InSelect { rhs, .. } => walk_select_exprs(rhs, func),
+            _ => Ok(()),
+        }
+    })
+}
+
+fn walk_select_exprs<F>(select: &mut ast::Select, func: &mut F) -> Result<()>
+where
+    F: FnMut(&mut ast::Expr) -> Result<()>,
+{
+    walk_one_select_exprs(&mut select.body.select, func)?;
+    for compound in select.body.compounds.iter_mut().flatten() {
+        walk_one_select_exprs(&mut compound.select, func)?;
+    }
+    for sorted_column in select.order_by.iter_mut().flatten() {
+        walk_exprs(&mut sorted_column.expr, func)?;
+    }
+    if let Some(limit) = &mut select.limit {
+        walk_exprs(&mut limit.expr, func)?;
+        if let Some(offset) = &mut limit.offset {
+            walk_exprs(offset, func)?;
+        }
+    }
+    Ok(())
+}
+
+fn walk_one_select_exprs<F>(one_select: &mut ast::OneSelect, func: &mut F) -> Result<()>
+where
+    F: FnMut(&mut ast::Expr) -> Result<()>,
+{
+    match one_select {
+        ast::OneSelect::Select(inner) => {
+            for column in &mut inner.columns {
+                if let ast::ResultColumn::Expr(expr, _) = column {
+                    walk_exprs(expr, func)?;
+                }
+            }
+            if let Some(from) = &mut inner.from {
+                walk_from_exprs(from, func)?;
+            }
+            if let Some(where_clause) = &mut inner.where_clause {
+                walk_exprs(where_clause, func)?;
+            }
+            if let Some(group_by) = &mut inner.group_by {
+                for expr in &mut group_by.exprs {
+                    walk_exprs(expr, func)?;
+                }
+                if let Some(having) = &mut group_by.having {
+                    walk_exprs(having, func)?;
+                }
+            }
+        }
+        ast::OneSelect::Values(values) => {
+            for expr in values.iter_mut().flatten() {
+                walk_exprs(expr, func)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn walk_from_exprs<F>(from: &mut ast::FromClause, func: &mut F) -> Result<()>
+where
+    F: FnMut(&mut ast::Expr) -> Result<()>,
+{
+    if let Some(select_table) = &mut from.select {
+        walk_select_table_exprs(select_table, func)?;
+    }
+    for join in from.joins.iter_mut().flatten() {
+        walk_select_table_exprs(&mut join.table, func)?;
+        if let Some(ast::JoinConstraint::On(expr)) = &mut join.constraint {
+            walk_exprs(expr, func)?;
+        }
+    }
+    Ok(())
+}
+
+fn walk_select_table_exprs<F>(select_table: &mut ast::SelectTable, func: &mut F) -> Result<()>
+where
+    F: FnMut(&mut ast::Expr) -> Result<()>,
+{
+    match select_table {
+        ast::SelectTable::Table(..) => {}
+        ast::SelectTable::TableCall(_, args, _) => {
+            for expr in args.iter_mut().flatten() {
+                walk_exprs(expr, func)?;
+            }
+        }
+        ast::SelectTable::Select(select, _) => walk_select_exprs(select, func)?,
+        ast::SelectTable::Sub(from, _) => walk_from_exprs(from, func)?,
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use std::iter;
 use std::rc::Rc;
 
 use crate::schema::{BTreeTable, Column, Type};
@@ -11,16 +12,16 @@ use crate::{
     vdbe::builder::{ProgramBuilder, ProgramBuilderOpts},
     SymbolTable,
 };
-use turso_sqlite3_parser::ast::{self, Expr, ResultColumn, SortOrder, Update};
+use turso_sqlite3_parser::ast::{self, Expr, ResolveType, ResultColumn, SortOrder, Update};
 
 use super::emitter::emit_program;
 use super::optimizer::optimize_plan;
 use super::plan::{
-    ColumnUsedMask, IterationDirection, JoinedTable, Plan, ResultSetColumn, TableReferences,
-    UpdatePlan,
+    ColumnUsedMask, IterationDirection, JoinOrderMember, JoinedTable, Plan, ResultSetColumn,
+    TableReferences, UpdatePlan,
 };
 use super::planner::bind_column_references;
-use super::planner::{parse_limit, parse_where};
+use super::planner::{parse_from, parse_limit, parse_where, resolve_index_hint};
 /*
 * Update is simple. By default we scan the table, and for each row, we check the WHERE
 * clause. If it evaluates to true, we build the new record with the updated value and insert.
@@ -56,7 +57,7 @@ pub fn translate_update(
     syms: &SymbolTable,
     mut program: ProgramBuilder,
 ) -> crate::Result<ProgramBuilder> {
-    let mut plan = prepare_update_plan(&mut program, schema, body)?;
+    let mut plan = prepare_update_plan(&mut program, schema, body, syms)?;
     optimize_plan(&mut plan, schema)?;
     // TODO: freestyling these numbers
     let opts = ProgramBuilderOpts {
@@ -76,7 +77,7 @@ pub fn translate_update_with_after(
     mut program: ProgramBuilder,
     after: impl FnOnce(&mut ProgramBuilder),
 ) -> crate::Result<ProgramBuilder> {
-    let mut plan = prepare_update_plan(&mut program, schema, body)?;
+    let mut plan = prepare_update_plan(&mut program, schema, body, syms)?;
     optimize_plan(&mut plan, schema)?;
     // TODO: freestyling these numbers
     let opts = ProgramBuilderOpts {
@@ -93,12 +94,13 @@ pub fn prepare_update_plan(
     program: &mut ProgramBuilder,
     schema: &Schema,
     body: &mut Update,
+    syms: &SymbolTable,
 ) -> crate::Result<Plan> {
     if body.with.is_some() {
         bail_parse_error!("WITH clause is not supported");
     }
-    if body.or_conflict.is_some() {
-        bail_parse_error!("ON CONFLICT clause is not supported");
+    if body.or_conflict == Some(ResolveType::Rollback) {
+        program.rollback_on_constraint = true;
     }
     let table_name = &body.tbl_name.name;
     if schema.table_has_indexes(&table_name.to_string()) && !schema.indexes_enabled() {
@@ -112,6 +114,11 @@ pub fn prepare_update_plan(
         Some(table) => table,
         None => bail_parse_error!("Parse error: no such table: {}", table_name),
     };
+    // Under REPLACE, the rows conflicting with an updated one are deleted
+    let replace_conflicts = body.or_conflict == Some(ResolveType::Replace);
+    if replace_conflicts && table.virtual_table().is_some() {
+        bail_parse_error!("UPDATE OR REPLACE is not supported for virtual tables");
+    }
     let iter_dir = body
         .order_by
         .as_ref()
@@ -125,22 +132,57 @@ pub fn prepare_update_plan(
         })
         .unwrap_or(IterationDirection::Forwards);
 
-    let joined_tables = vec![JoinedTable {
+    let index_hint = resolve_index_hint(
+        schema,
+        &normalize_ident(table_name.0.as_str()),
+        body.indexed.clone(),
+    )?;
+    let target_table = |internal_id| JoinedTable {
         table: match table.as_ref() {
             Table::Virtual(vtab) => Table::Virtual(vtab.clone()),
             Table::BTree(btree_table) => Table::BTree(btree_table.clone()),
             _ => unreachable!(),
         },
         identifier: table_name.0.clone(),
-        internal_id: program.table_reference_counter.next(),
+        internal_id,
         op: Operation::Scan {
             iter_dir,
             index: None,
         },
         join_info: None,
         col_used_mask: ColumnUsedMask::default(),
-    }];
+        index_hint: index_hint.clone(),
+    };
+
+    let joined_tables = vec![target_table(program.table_reference_counter.next())];
     let mut table_references = TableReferences::new(joined_tables, vec![]);
+
+    // With a FROM clause, the SET expressions and the WHERE clause are evaluated over the
+    // join of the target table with the FROM tables, in the ephemeral plan built below
+    let mut from_where_clause = vec![];
+    let mut from_references = match body.from.take() {
+        Some(from) => {
+            if table.virtual_table().is_some() {
+                bail_parse_error!("UPDATE ... FROM is not supported for virtual tables");
+            }
+            if body.order_by.is_some() || body.limit.is_some() {
+                bail_parse_error!("ORDER BY and LIMIT are not supported with UPDATE ... FROM");
+            }
+            let joined_tables = vec![target_table(program.table_reference_counter.next())];
+            let mut table_references = TableReferences::new(joined_tables, vec![]);
+            parse_from(
+                schema,
+                Some(from),
+                syms,
+                None,
+                &mut from_where_clause,
+                &mut table_references,
+                &mut program.table_reference_counter,
+            )?;
+            Some(table_references)
+        }
+        None => None,
+    };
     let set_clauses = body
         .sets
         .iter_mut()
@@ -162,8 +204,15 @@ pub fn prepare_update_plan(
                         ident, table_name.0
                     ))
                 })?;
+            if table.columns()[col_index].generated.is_some() {
+                bail_parse_error!("cannot UPDATE generated column \"{}\"", ident);
+            }
 
-            let _ = bind_column_references(&mut set.expr, &mut table_references, None);
+            let _ = bind_column_references(
+                &mut set.expr,
+                from_references.as_mut().unwrap_or(&mut table_references),
+                None,
+            );
             Ok((col_index, set.expr.clone()))
         })
         .collect::<Result<Vec<(usize, Expr)>, crate::LimboError>>()?;
@@ -206,25 +255,74 @@ pub fn prepare_update_plan(
         accum || columns[*idx].is_rowid_alias
     });
 
-    let (ephemeral_plan, mut where_clause) = if rowid_alias_used {
+    let set_values_in_ephemeral_table = from_references.is_some();
+    let (ephemeral_plan, mut where_clause) = if let Some(mut table_references) = from_references {
+        let mut where_clause = from_where_clause;
+        parse_where(
+            body.where_clause.as_ref().map(|w| *w.clone()),
+            &mut table_references,
+            Some(&result_columns),
+            &mut where_clause,
+        )?;
+
+        let table = ephemeral_set_values_table(set_clauses.len());
+        let temp_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
+        let target_id = table_references.joined_tables()[0].internal_id;
+
+        // One row per target row: the new value of every SET clause, keyed by the target rowid.
+        // A target row matched by several rows of the FROM tables keeps the last match.
+        let result_columns = set_clauses
+            .iter()
+            .map(|(_, expr)| expr.clone())
+            .chain(iter::once(Expr::RowId {
+                database: None,
+                table: target_id,
+            }))
+            .map(|expr| ResultSetColumn {
+                expr,
+                alias: None,
+                contains_aggregates: false,
+            })
+            .collect();
+        let join_order = table_references
+            .joined_tables()
+            .iter()
+            .enumerate()
+            .map(|(i, t)| JoinOrderMember {
+                table_id: t.internal_id,
+                original_idx: i,
+                is_outer: t.join_info.as_ref().map_or(false, |j| j.outer),
+            })
+            .collect();
+
+        let mut ephemeral_plan = SelectPlan {
+            table_references,
+            result_columns,
+            where_clause,
+            group_by: None,
+            order_by: None,
+            aggregates: vec![],
+            limit: None,
+            query_destination: QueryDestination::EphemeralTable {
+                cursor_id: temp_cursor_id,
+                table,
+            },
+            join_order,
+            offset: None,
+            contains_constant_false_condition: false,
+            distinctness: super::plan::Distinctness::NonDistinct,
+            values: vec![],
+        };
+
+        optimize_select_plan(&mut ephemeral_plan, schema)?;
+        (Some(ephemeral_plan), vec![])
+    } else if rowid_alias_used || replace_conflicts {
+        // Deleting the conflicting rows would move the cursors the loop iterates over, so the
+        // rowids of the rows to update are collected first
         let mut where_clause = vec![];
         let internal_id = program.table_reference_counter.next();
 
-        let joined_tables = vec![JoinedTable {
-            table: match table.as_ref() {
-                Table::Virtual(vtab) => Table::Virtual(vtab.clone()),
-                Table::BTree(btree_table) => Table::BTree(btree_table.clone()),
-                _ => unreachable!(),
-            },
-            identifier: table_name.0.clone(),
-            internal_id,
-            op: Operation::Scan {
-                iter_dir,
-                index: None,
-            },
-            join_info: None,
-            col_used_mask: ColumnUsedMask::default(),
-        }];
+        let joined_tables = vec![target_table(internal_id)];
         let mut table_references = TableReferences::new(joined_tables, vec![]);
 
         // Parse the WHERE clause
@@ -235,25 +333,7 @@ pub fn prepare_update_plan(
             &mut where_clause,
         )?;
 
-        let table = Rc::new(BTreeTable {
-            root_page: 0, // Not relevant for ephemeral table definition
-            name: "ephemeral_scratch".to_string(),
-            has_rowid: true,
-            primary_key_columns: vec![],
-            columns: vec![Column {
-                name: Some("rowid".to_string()),
-                ty: Type::Integer,
-                ty_str: "INTEGER".to_string(),
-                primary_key: true,
-                is_rowid_alias: false,
-                notnull: true,
-                default: None,
-                unique: false,
-                collation: None,
-            }],
-            is_strict: false,
-            unique_sets: None,
-        });
+        let table = ephemeral_rowid_table();
 
         let temp_cursor_id = program.alloc_cursor_id(CursorType::BTreeTable(table.clone()));
 
@@ -317,16 +397,19 @@ pub fn prepare_update_plan(
         .unwrap_or(Ok((None, None)))?;
 
     // Check what indexes will need to be updated by checking set_clauses and see
-    // if a column is contained in an index.
+    // if a column is contained in an index. Generated columns may change with any SET clause.
+    // Under REPLACE, every index is opened as the conflicting rows are deleted from all of them.
     let indexes = schema.get_indices(&table_name.0);
     let indexes_to_update = indexes
         .iter()
         .filter(|index| {
-            index.columns.iter().any(|index_column| {
-                set_clauses
-                    .iter()
-                    .any(|(set_index_column, _)| index_column.pos_in_table == *set_index_column)
-            })
+            replace_conflicts
+                || index.columns.iter().any(|index_column| {
+                    columns[index_column.pos_in_table].generated.is_some()
+                        || set_clauses.iter().any(|(set_index_column, _)| {
+                            index_column.pos_in_table == *set_index_column
+                        })
+                })
         })
         .cloned()
         .collect();
@@ -342,5 +425,65 @@ pub fn prepare_update_plan(
         contains_constant_false_condition: false,
         indexes_to_update,
         ephemeral_plan,
+        set_values_in_ephemeral_table,
+        or_conflict: body.or_conflict,
     }))
 }
+
+/// Definition of the scratch table that collects the rowids of the target rows before
+/// they are modified, so the modification does not disturb the scan that finds them.
+pub(super) fn ephemeral_rowid_table() -> Rc<BTreeTable> {
+    Rc::new(BTreeTable {
+        root_page: 0, // Not relevant for ephemeral table definition
+        name: "ephemeral_scratch".to_string(),
+        has_rowid: true,
+        primary_key_columns: vec![],
+        columns: vec![Column {
+            name: Some("rowid".to_string()),
+            ty: Type::Integer,
+            ty_str: "INTEGER".to_string(),
+            primary_key: true,
+            is_rowid_alias: false,
+            notnull: true,
+            default: None,
+            unique: false,
+            collation: None,
+            generated: None,
+        }],
+        is_strict: false,
+        unique_sets: None,
+        foreign_keys: vec![],
+        has_autoincrement: false,
+        check_constraints: vec![],
+    })
+}
+
+/// Definition of the scratch table that holds the new values of the `num_set_clauses`
+/// SET clauses of an `UPDATE ... FROM`, keyed by the rowid of the target row.
+fn ephemeral_set_values_table(num_set_clauses: usize) -> Rc<BTreeTable> {
+    Rc::new(BTreeTable {
+        root_page: 0, // Not relevant for ephemeral table definition
+        name: "ephemeral_scratch".to_string(),
+        has_rowid: true,
+        primary_key_columns: vec![],
+        columns: (0..num_set_clauses)
+            .map(|i| Column {
+                name: Some(format!("value_{i}")),
+                ty: Type::Null,
+                ty_str: String::new(),
+                primary_key: false,
+                is_rowid_alias: false,
+                notnull: false,
+                default: None,
+                unique: false,
+                collation: None,
+                generated: None,
+            })
+            .collect(),
+        is_strict: false,
+        unique_sets: None,
+        foreign_keys: vec![],
+        has_autoincrement: false,
+        check_constraints: vec![],
+    })
+}
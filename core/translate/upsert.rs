@@ -0,0 +1,588 @@
+//! Upsert clauses of an `INSERT`, as in `ON CONFLICT [(target)] DO NOTHING | DO UPDATE SET ...`.
+//!
+//! The clauses are consulted where the insert checks the rowid and the UNIQUE indexes of the
+//! table for conflicts. A conflict one of them covers is resolved by it instead of failing:
+//! `DO NOTHING` skips the new row, and `DO UPDATE` updates the existing row the new one conflicts
+//! with. A clause without a conflict target covers every conflict, which only the last one may
+//! omit.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use turso_sqlite3_parser::ast::{self, Expr, TableInternalId};
+
+use crate::error::{SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY};
+use crate::schema::{BTreeTable, Index, Schema};
+use crate::util::normalize_ident;
+use crate::vdbe::builder::ProgramBuilder;
+use crate::vdbe::insn::{CmpInsFlags, IdxInsertFlags, InsertFlags, Insn};
+use crate::vdbe::BranchOffset;
+use crate::{Result, SymbolTable};
+
+use super::check::emit_check_constraints;
+use super::emitter::Resolver;
+use super::expr::{translate_expr, walk_expr_mut};
+use super::generated::{emit_generated_columns, emit_record_registers};
+use super::insert::emit_foreign_key_check;
+use super::optimizer::rewrite_expr;
+
+/// Table reference the columns of the existing row are bound to in `DO UPDATE`
+fn existing_row() -> TableInternalId {
+    TableInternalId::from(1)
+}
+
+/// Table reference the columns of the row being inserted, `excluded.*`, are bound to
+fn excluded_row() -> TableInternalId {
+    TableInternalId::from(2)
+}
+
+/// Constraint a new row conflicts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConflictTarget {
+    /// The rowid, given through the `INTEGER PRIMARY KEY` column
+    Rowid,
+    /// A UNIQUE index, by name
+    Index(String),
+}
+
+/// Where the rowid of the existing row a new row conflicts with can be found
+pub(crate) enum ConflictRow {
+    /// In a register
+    Register(usize),
+    /// In the entry an index cursor points to
+    IndexCursor(usize),
+}
+
+enum UpsertAction {
+    Nothing,
+    Update {
+        /// Position of the column and new value of each `SET`
+        sets: Vec<(usize, Expr)>,
+        where_clause: Option<Expr>,
+        /// Start of the update of the existing row
+        label: BranchOffset,
+    },
+}
+
+struct UpsertClause {
+    target: Option<ConflictTarget>,
+    action: UpsertAction,
+}
+
+/// The upsert clauses of an insert, in the order they are consulted
+pub(crate) struct Upserts {
+    clauses: Vec<UpsertClause>,
+    /// Rowid of the existing row a `DO UPDATE` applies to
+    rowid_reg: usize,
+}
+
+impl Upserts {
+    /// Resolves the upsert clauses of an insert into `table`, whose indexes are `indexes`. The
+    /// anonymous parameters of their expressions are numbered from `param_idx`.
+    pub(crate) fn new(
+        program: &mut ProgramBuilder,
+        upsert: Option<ast::Upsert>,
+        table: &BTreeTable,
+        indexes: &[Arc<Index>],
+        param_idx: &mut usize,
+    ) -> Result<Self> {
+        let mut clauses = Vec::new();
+        let mut next = upsert.map(Box::new);
+        while let Some(upsert) = next {
+            let ast::Upsert {
+                index,
+                do_clause,
+                next: rest,
+            } = *upsert;
+            let target = match index {
+                Some(index) => Some(resolve_conflict_target(*index, table, indexes)?),
+                None if rest.is_some() => {
+                    crate::bail_parse_error!(
+                        "only the last ON CONFLICT clause may omit the conflict target"
+                    );
+                }
+                None => None,
+            };
+            let action = match *do_clause {
+                ast::UpsertDo::Nothing => UpsertAction::Nothing,
+                ast::UpsertDo::Set {
+                    sets,
+                    mut where_clause,
+                } => {
+                    let sets = sets
+                        .into_iter()
+                        .map(|mut set| {
+                            let name = normalize_ident(set.col_names[0].0.as_str());
+                            let Some((pos, column)) = table.get_column(&name) else {
+                                crate::bail_parse_error!("no such column: {}", name);
+                            };
+                            if column.generated.is_some() {
+                                crate::bail_parse_error!(
+                                    "cannot UPDATE generated column \"{}\"",
+                                    name
+                                );
+                            }
+                            if column.is_rowid_alias {
+                                crate::bail_parse_error!(
+                                    "setting the INTEGER PRIMARY KEY in an upsert is not supported"
+                                );
+                            }
+                            rewrite_expr(&mut set.expr, param_idx)?;
+                            bind_upsert_expr(&mut set.expr, table)?;
+                            Ok((pos, set.expr))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    if let Some(where_clause) = where_clause.as_mut() {
+                        rewrite_expr(where_clause, param_idx)?;
+                        bind_upsert_expr(where_clause, table)?;
+                    }
+                    UpsertAction::Update {
+                        sets,
+                        where_clause,
+                        label: program.allocate_label(),
+                    }
+                }
+            };
+            clauses.push(UpsertClause { target, action });
+            next = rest;
+        }
+        Ok(Self {
+            clauses,
+            rowid_reg: program.alloc_register(),
+        })
+    }
+
+    /// Emits the resolution of a conflict on `target` by the first clause that covers it, if
+    /// any, and returns whether there was one. Skipped rows continue with `row_done_label`.
+    pub(crate) fn emit_conflict(
+        &self,
+        program: &mut ProgramBuilder,
+        target: &ConflictTarget,
+        conflict_row: ConflictRow,
+        row_done_label: BranchOffset,
+    ) -> bool {
+        let Some(clause) = self
+            .clauses
+            .iter()
+            .find(|clause| clause.target.as_ref().is_none_or(|t| t == target))
+        else {
+            return false;
+        };
+        match &clause.action {
+            UpsertAction::Nothing => program.emit_insn(Insn::Goto {
+                target_pc: row_done_label,
+            }),
+            UpsertAction::Update { label, .. } => {
+                match conflict_row {
+                    ConflictRow::Register(reg) => program.emit_insn(Insn::Copy {
+                        src_reg: reg,
+                        dst_reg: self.rowid_reg,
+                        amount: 0,
+                    }),
+                    ConflictRow::IndexCursor(cursor_id) => program.emit_insn(Insn::IdxRowId {
+                        cursor_id,
+                        dest: self.rowid_reg,
+                    }),
+                }
+                program.emit_insn(Insn::Goto { target_pc: *label });
+            }
+        }
+        true
+    }
+
+    /// Whether a clause updates the existing row
+    pub(crate) fn has_updates(&self) -> bool {
+        self.clauses
+            .iter()
+            .any(|clause| matches!(clause.action, UpsertAction::Update { .. }))
+    }
+
+    /// Emits the `DO UPDATE` of the clauses. Each one updates the row of `table`, open with
+    /// `cursor_id`, whose rowid the conflict left in the rowid register, along with its entries
+    /// in the indexes of `index_cursors`. The values of the row being inserted are in the
+    /// registers starting at `excluded_start_reg` and its rowid is in `excluded_rowid_reg`.
+    /// Either way, the insert then continues with `row_done_label`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn emit_updates(
+        &self,
+        program: &mut ProgramBuilder,
+        schema: &Schema,
+        syms: &SymbolTable,
+        table: &Rc<BTreeTable>,
+        cursor_id: usize,
+        index_cursors: &[(Arc<Index>, usize)],
+        excluded_start_reg: usize,
+        excluded_rowid_reg: usize,
+        foreign_keys: bool,
+        row_done_label: BranchOffset,
+    ) -> Result<()> {
+        for clause in &self.clauses {
+            let UpsertAction::Update {
+                sets,
+                where_clause,
+                label,
+            } = &clause.action
+            else {
+                continue;
+            };
+            program.preassign_label_to_next_insn(*label);
+            self.emit_update(
+                program,
+                schema,
+                syms,
+                table,
+                cursor_id,
+                index_cursors,
+                (excluded_start_reg, excluded_rowid_reg),
+                sets,
+                where_clause.as_ref(),
+                foreign_keys,
+                row_done_label,
+            )?;
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_update(
+        &self,
+        program: &mut ProgramBuilder,
+        schema: &Schema,
+        syms: &SymbolTable,
+        table: &Rc<BTreeTable>,
+        cursor_id: usize,
+        index_cursors: &[(Arc<Index>, usize)],
+        (excluded_start_reg, excluded_rowid_reg): (usize, usize),
+        sets: &[(usize, Expr)],
+        where_clause: Option<&Expr>,
+        foreign_keys: bool,
+        row_done_label: BranchOffset,
+    ) -> Result<()> {
+        let rowid_reg = self.rowid_reg;
+        let num_cols = table.columns.len();
+        program.emit_insn(Insn::NotExists {
+            cursor: cursor_id,
+            rowid_reg,
+            target_pc: row_done_label,
+        });
+
+        // The existing row is read into registers, the rowid alias column included
+        let resolver = Resolver::new(schema, syms);
+        let old_start_reg = program.alloc_registers(num_cols);
+        for (i, column) in table.columns.iter().enumerate() {
+            if column.is_rowid_alias {
+                program.emit_insn(Insn::Copy {
+                    src_reg: rowid_reg,
+                    dst_reg: old_start_reg + i,
+                    amount: 0,
+                });
+            } else {
+                program.emit_column(cursor_id, i, old_start_reg + i);
+            }
+        }
+        emit_generated_columns(program, table, old_start_reg, rowid_reg, &resolver)?;
+
+        // Column references are resolved to the registers of the existing and the new row
+        let rows = [
+            (existing_row(), old_start_reg, rowid_reg),
+            (excluded_row(), excluded_start_reg, excluded_rowid_reg),
+        ];
+        let references = rows
+            .iter()
+            .flat_map(|&(table_ref, start_reg, rowid_reg)| {
+                table
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .map(move |(column, col)| {
+                        let reg = if col.is_rowid_alias {
+                            rowid_reg
+                        } else {
+                            start_reg + column
+                        };
+                        let expr = Expr::Column {
+                            database: None,
+                            table: table_ref,
+                            column,
+                            is_rowid_alias: col.is_rowid_alias,
+                        };
+                        (expr, reg)
+                    })
+                    .chain(std::iter::once((
+                        Expr::RowId {
+                            database: None,
+                            table: table_ref,
+                        },
+                        rowid_reg,
+                    )))
+            })
+            .collect::<Vec<_>>();
+        let mut row_resolver = Resolver::new(schema, syms);
+        row_resolver.enable_expr_to_reg_cache();
+        for (expr, reg) in references.iter() {
+            row_resolver.expr_to_reg_cache.push((expr, *reg));
+        }
+
+        if let Some(where_clause) = where_clause {
+            let reg = program.alloc_register();
+            translate_expr(program, None, where_clause, reg, &row_resolver)?;
+            program.emit_insn(Insn::IfNot {
+                reg,
+                target_pc: row_done_label,
+                jump_if_null: true,
+            });
+        }
+
+        // Every SET expression sees the values of the existing row
+        let new_start_reg = program.alloc_registers(num_cols);
+        program.emit_insn(Insn::Copy {
+            src_reg: old_start_reg,
+            dst_reg: new_start_reg,
+            amount: num_cols - 1,
+        });
+        for (column, expr) in sets {
+            translate_expr(program, None, expr, new_start_reg + column, &row_resolver)?;
+        }
+        emit_generated_columns(program, table, new_start_reg, rowid_reg, &resolver)?;
+
+        for (i, column) in table.columns.iter().enumerate() {
+            if column.notnull && !column.is_rowid_alias {
+                program.emit_insn(Insn::HaltIfNull {
+                    target_reg: new_start_reg + i,
+                    err_code: SQLITE_CONSTRAINT_NOTNULL,
+                    description: format!(
+                        "{}.{}",
+                        table.name,
+                        column.name.as_ref().expect("Column name must be present")
+                    ),
+                });
+            }
+        }
+        emit_check_constraints(program, table, new_start_reg, rowid_reg, &resolver, None)?;
+        if table.is_strict {
+            program.emit_insn(Insn::TypeCheck {
+                start_reg: new_start_reg,
+                count: num_cols,
+                check_generated: true,
+                table_reference: Rc::clone(table),
+            });
+        }
+
+        // The new keys are checked against the other rows before any index is written to
+        let mut index_keys = Vec::with_capacity(index_cursors.len());
+        for (index, idx_cursor_id) in index_cursors {
+            let num_key_cols = index.columns.len();
+            let key_start_reg = program.alloc_registers(num_key_cols + 1);
+            for (i, column) in index.columns.iter().enumerate() {
+                program.emit_insn(Insn::Copy {
+                    src_reg: new_start_reg + column.pos_in_table,
+                    dst_reg: key_start_reg + i,
+                    amount: 0,
+                });
+            }
+            program.emit_insn(Insn::Copy {
+                src_reg: rowid_reg,
+                dst_reg: key_start_reg + num_key_cols,
+                amount: 0,
+            });
+            let record_reg = program.alloc_register();
+            program.emit_insn(Insn::MakeRecord {
+                start_reg: key_start_reg,
+                count: num_key_cols + 1,
+                dest_reg: record_reg,
+                index_name: Some(index.name.clone()),
+            });
+            if index.unique {
+                let label_no_conflict = program.allocate_label();
+                program.emit_insn(Insn::NoConflict {
+                    cursor_id: *idx_cursor_id,
+                    target_pc: label_no_conflict,
+                    record_reg: key_start_reg,
+                    num_regs: num_key_cols,
+                });
+                // The entry found may be the one of the row being updated
+                let idx_rowid_reg = program.alloc_register();
+                program.emit_insn(Insn::IdxRowId {
+                    cursor_id: *idx_cursor_id,
+                    dest: idx_rowid_reg,
+                });
+                program.emit_insn(Insn::Eq {
+                    lhs: rowid_reg,
+                    rhs: idx_rowid_reg,
+                    target_pc: label_no_conflict,
+                    flags: CmpInsFlags::default(),
+                    collation: program.curr_collation(),
+                });
+                program.emit_insn(Insn::Halt {
+                    err_code: SQLITE_CONSTRAINT_PRIMARYKEY,
+                    description: index
+                        .columns
+                        .iter()
+                        .map(|column| format!("{}.{}", table.name, column.name))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+                program.preassign_label_to_next_insn(label_no_conflict);
+            }
+            index_keys.push((index, *idx_cursor_id, record_reg, key_start_reg));
+        }
+
+        if foreign_keys {
+            for fk in table.foreign_keys.iter() {
+                emit_foreign_key_check(program, schema, table, fk, new_start_reg, rowid_reg)?;
+            }
+        }
+
+        for (index, idx_cursor_id, record_reg, key_start_reg) in index_keys {
+            let num_regs = index.columns.len() + 1;
+            let old_key_start_reg = program.alloc_registers(num_regs);
+            for (i, column) in index.columns.iter().enumerate() {
+                program.emit_insn(Insn::Copy {
+                    src_reg: old_start_reg + column.pos_in_table,
+                    dst_reg: old_key_start_reg + i,
+                    amount: 0,
+                });
+            }
+            program.emit_insn(Insn::Copy {
+                src_reg: rowid_reg,
+                dst_reg: old_key_start_reg + num_regs - 1,
+                amount: 0,
+            });
+            program.emit_insn(Insn::IdxDelete {
+                start_reg: old_key_start_reg,
+                num_regs,
+                cursor_id: idx_cursor_id,
+            });
+            program.emit_insn(Insn::IdxInsert {
+                cursor_id: idx_cursor_id,
+                record_reg,
+                unpacked_start: Some(key_start_reg),
+                unpacked_count: Some(num_regs as u16),
+                flags: IdxInsertFlags::new(),
+            });
+        }
+
+        // The rowid alias column is NULL in the record, and the checks above may have moved the
+        // table cursor, so it is positioned at the row again
+        if let Some(alias) = table.columns.iter().position(|c| c.is_rowid_alias) {
+            program.emit_insn(Insn::SoftNull {
+                reg: new_start_reg + alias,
+            });
+        }
+        let label_row_found = program.allocate_label();
+        program.emit_insn(Insn::NotExists {
+            cursor: cursor_id,
+            rowid_reg,
+            target_pc: label_row_found,
+        });
+        program.preassign_label_to_next_insn(label_row_found);
+        let (record_start_reg, record_num_cols) =
+            emit_record_registers(program, table, new_start_reg);
+        let record_reg = program.alloc_register();
+        program.emit_insn(Insn::MakeRecord {
+            start_reg: record_start_reg,
+            count: record_num_cols,
+            dest_reg: record_reg,
+            index_name: None,
+        });
+        program.emit_insn(Insn::Insert {
+            cursor: cursor_id,
+            key_reg: rowid_reg,
+            record_reg,
+            flag: InsertFlags::new().update(true),
+            table_name: table.name.clone(),
+        });
+        program.emit_insn(Insn::Goto {
+            target_pc: row_done_label,
+        });
+        Ok(())
+    }
+}
+
+/// Resolves the conflict target of an upsert clause to the `INTEGER PRIMARY KEY` or the UNIQUE
+/// index on exactly its columns.
+fn resolve_conflict_target(
+    target: ast::UpsertIndex,
+    table: &BTreeTable,
+    indexes: &[Arc<Index>],
+) -> Result<ConflictTarget> {
+    if target.where_clause.is_some() {
+        crate::bail_parse_error!("partial index conflict targets are not supported");
+    }
+    let columns = target
+        .targets
+        .iter()
+        .map(|column| match &column.expr {
+            Expr::Id(name) => {
+                let name = normalize_ident(&name.0);
+                if table.get_column(&name).is_none() {
+                    crate::bail_parse_error!("no such column: {}", name);
+                }
+                Ok(name)
+            }
+            expr => crate::bail_parse_error!("unsupported conflict target: {}", expr),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let [column] = columns.as_slice() {
+        if table
+            .get_column(column)
+            .is_some_and(|(_, column)| column.is_rowid_alias)
+        {
+            return Ok(ConflictTarget::Rowid);
+        }
+    }
+    indexes
+        .iter()
+        .find(|index| {
+            index.unique
+                && index.columns.len() == columns.len()
+                && index
+                    .columns
+                    .iter()
+                    .all(|c| columns.iter().any(|name| c.name.eq_ignore_ascii_case(name)))
+        })
+        .map(|index| ConflictTarget::Index(index.name.clone()))
+        .ok_or_else(|| {
+            crate::LimboError::ParseError(
+                "ON CONFLICT clause does not match any PRIMARY KEY or UNIQUE constraint"
+                    .to_string(),
+            )
+        })
+}
+
+/// Binds the column references of a `DO UPDATE` expression: the bare ones and the ones
+/// qualified with the name of the table to the existing row, and the ones qualified with
+/// `excluded` to the row being inserted.
+fn bind_upsert_expr(expr: &mut Expr, table: &BTreeTable) -> Result<()> {
+    walk_expr_mut(expr, &mut |expr: &mut Expr| -> Result<()> {
+        let (table_ref, name) = match expr {
+            Expr::Id(id) => (existing_row(), normalize_ident(&id.0)),
+            Expr::Qualified(qualifier, column) => {
+                let qualifier = normalize_ident(&qualifier.0);
+                let name = normalize_ident(&column.0);
+                if qualifier == "excluded" {
+                    (excluded_row(), name)
+                } else if qualifier == normalize_ident(&table.name) {
+                    (existing_row(), name)
+                } else {
+                    crate::bail_parse_error!("no such column: {}.{}", qualifier, name);
+                }
+            }
+            _ => return Ok(()),
+        };
+        *expr = match table.get_column(&name) {
+            Some((column, col)) => Expr::Column {
+                database: None,
+                table: table_ref,
+                column,
+                is_rowid_alias: col.is_rowid_alias,
+            },
+            None if ["rowid", "_rowid_", "oid"].contains(&name.as_str()) => Expr::RowId {
+                database: None,
+                table: table_ref,
+            },
+            None => crate::bail_parse_error!("no such column: {}", name),
+        };
+        Ok(())
+    })
+}
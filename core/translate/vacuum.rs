@@ -0,0 +1,34 @@
+use turso_sqlite3_parser::ast::{Expr, Name};
+
+use crate::{
+    bail_parse_error,
+    translate::{emitter::TransactionMode, ProgramBuilder, ProgramBuilderOpts},
+    util::normalize_ident,
+    vdbe::insn::Insn,
+    Result,
+};
+
+pub fn translate_vacuum(
+    schema_name: Option<Name>,
+    into: Option<Box<Expr>>,
+    mut program: ProgramBuilder,
+) -> Result<ProgramBuilder> {
+    if into.is_some() {
+        bail_parse_error!("VACUUM INTO not supported yet");
+    }
+    if let Some(name) = schema_name {
+        if normalize_ident(&name.0) != "main" {
+            bail_parse_error!("unknown database {}", name.0);
+        }
+    }
+    program.extend(&ProgramBuilderOpts {
+        num_cursors: 0,
+        approx_num_insns: 0,
+        approx_num_labels: 0,
+    });
+    // VACUUM runs its own write transaction, so that it can checkpoint and shrink the database
+    // file once the rebuilt database is committed
+    program.emit_insn(Insn::Vacuum { db: 0 });
+    program.epilogue(TransactionMode::None);
+    Ok(program)
+}
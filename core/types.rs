@@ -475,6 +475,74 @@ pub enum AggContext {
     Min(Option<Value>),
     GroupConcat(Value),
     External(ExternalAggState),
+    Ordered(Box<OrderedAggState>),
+}
+
+/// The rows given to an aggregate with an `ORDER BY` in its arguments. They are buffered as
+/// they come, and only combined once sorted by their keys when the aggregate is finalized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderedAggState {
+    pub order: Vec<SortOrder>,
+    pub collations: Vec<CollationSeq>,
+    /// The key of the row the next AggStep buffers
+    pub pending_key: Vec<Value>,
+    pub rows: Vec<OrderedAggRow>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderedAggRow {
+    pub key: Vec<Value>,
+    pub args: Vec<Value>,
+    pub delimiter: Option<Value>,
+}
+
+impl OrderedAggState {
+    pub fn new(order: Vec<SortOrder>, collations: Vec<CollationSeq>, key: Vec<Value>) -> Self {
+        Self {
+            order,
+            collations,
+            pending_key: key,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push_row(&mut self, args: Vec<Value>, delimiter: Option<Value>) {
+        self.rows.push(OrderedAggRow {
+            key: self.pending_key.clone(),
+            args,
+            delimiter,
+        });
+    }
+
+    /// Returns the buffered rows sorted by their keys. Rows with equal keys are kept in the order
+    /// they were given in.
+    pub fn into_sorted_rows(self) -> Vec<OrderedAggRow> {
+        let Self {
+            order,
+            collations,
+            mut rows,
+            ..
+        } = self;
+        rows.sort_by(|l, r| {
+            for (i, (l, r)) in l.key.iter().zip(&r.key).enumerate() {
+                let collation = collations.get(i).copied().unwrap_or_default();
+                let cmp = match (l, r) {
+                    (Value::Text(left), Value::Text(right)) => {
+                        collation.compare_strings(left.as_str(), right.as_str())
+                    }
+                    _ => l.partial_cmp(r).unwrap(),
+                };
+                if !cmp.is_eq() {
+                    return match order[i] {
+                        SortOrder::Asc => cmp,
+                        SortOrder::Desc => cmp.reverse(),
+                    };
+                }
+            }
+            std::cmp::Ordering::Equal
+        });
+        rows
+    }
 }
 
 const NULL: Value = Value::Null;
@@ -499,6 +567,7 @@ impl AggContext {
             Self::Min(min) => min.as_ref().unwrap_or(&NULL),
             Self::GroupConcat(s) => s,
             Self::External(ext_state) => ext_state.finalized_value.as_ref().unwrap_or(&NULL),
+            Self::Ordered(_) => &NULL,
         }
     }
 }
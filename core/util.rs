@@ -15,9 +15,32 @@ pub trait RoundToPrecision {
 }
 
 impl RoundToPrecision for f64 {
+    /// Rounds half away from zero like SQLite does, which rounds the value printed with 26
+    /// significant digits. These are enough to tell that a decimal value like `2.675` is stored
+    /// as a double slightly below it, so `2.675` is rounded down to `2.67`.
     fn round_to_precision(self, precision: i32) -> f64 {
-        let factor = 10f64.powi(precision);
-        (self * factor).round() / factor
+        if !self.is_finite() {
+            return self;
+        }
+        let repr = format!("{:.25e}", self.abs());
+        let (mantissa, exponent) = repr.split_once('e').unwrap();
+        let digits = mantissa.replace('.', "");
+        // Number of significant digits kept, which is negative when the value rounds to zero
+        let keep = exponent.parse::<i32>().unwrap() + 1 + precision;
+        if keep >= digits.len() as i32 {
+            return self;
+        }
+        let rounded = if keep < 0 {
+            0
+        } else {
+            let keep = keep as usize;
+            let kept = digits[..keep].parse::<u128>().unwrap_or(0);
+            kept + u128::from(digits.as_bytes()[keep] >= b'5')
+        };
+        let rounded = format!("{}e{}", rounded, -precision)
+            .parse::<f64>()
+            .unwrap();
+        rounded.copysign(self)
     }
 }
 
@@ -67,7 +90,7 @@ pub fn parse_schema_rows(
                 StepResult::Row => {
                     let row = rows.row().unwrap();
                     let ty = row.get::<&str>(0)?;
-                    if !["table", "index"].contains(&ty) {
+                    if !["table", "index", "trigger"].contains(&ty) {
                         continue;
                     }
                     match ty {
@@ -124,6 +147,10 @@ pub fn parse_schema_rows(
                                 }
                             }
                         }
+                        "trigger" => {
+                            let sql = row.get::<&str>(4)?;
+                            schema.add_trigger(Arc::new(schema::Trigger::from_sql(sql)?));
+                        }
                         _ => continue,
                     }
                 }
@@ -433,6 +460,7 @@ pub fn exprs_are_equivalent(expr1: &Expr, expr2: &Expr) -> bool {
         (Expr::Variable(var), Expr::Variable(var2)) if var.is_empty() && var2.is_empty() => false,
         // Named variables can be compared by their name
         (Expr::Variable(val), Expr::Variable(val2)) => val == val2,
+        (Expr::Register(reg1), Expr::Register(reg2)) => reg1 == reg2,
         (Expr::Parenthesized(exprs1), Expr::Parenthesized(exprs2)) => {
             exprs1.len() == exprs2.len()
                 && exprs1
@@ -574,6 +602,7 @@ pub fn columns_from_create_table_body(body: &ast::CreateTableBody) -> crate::Res
                             )),
                             _ => None,
                         }),
+                    generated: None,
                 };
             Some(column)
         })
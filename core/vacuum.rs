@@ -0,0 +1,311 @@
+//! `VACUUM` rebuilds the database into a scratch in-memory database, which leaves the tables and
+//! indexes packed without free pages, and then writes the rebuilt pages over the original ones as
+//! a single write transaction. Once the transaction is checkpointed, the database file is
+//! truncated to the new size.
+//!
+//! Like in SQLite, the rowids of tables without an `INTEGER PRIMARY KEY` may change.
+
+use std::num::NonZero;
+use std::sync::Arc;
+
+use crate::result::LimboResult;
+use crate::schema::{Schema, Table};
+use crate::storage::database::FileMemoryStorage;
+use crate::storage::header_accessor;
+use crate::storage::pager::{Pager, PagerCacheflushStatus};
+use crate::storage::wal::CheckpointMode;
+use crate::translate::schema::SQLITE_SEQUENCE_TABLEID;
+use crate::types::CursorResult;
+use crate::util::{parse_schema_rows, MEMORY_PATH};
+use crate::vdbe::StepResult;
+use crate::{
+    Connection, Database, LimboError, MemoryIO, OpenFlags, Result, Statement, TransactionState,
+    Value, IO,
+};
+
+pub(crate) fn vacuum(conn: &Arc<Connection>) -> Result<()> {
+    if !conn.auto_commit.get() {
+        return Err(LimboError::TxError(
+            "cannot VACUUM from within a transaction".to_string(),
+        ));
+    }
+    if conn.readonly.get() || conn._db.open_flags.contains(OpenFlags::ReadOnly) {
+        return Err(LimboError::ReadOnly);
+    }
+    let pager = conn.pager.clone();
+    begin_write_tx(&pager)?;
+    conn.transaction_state.set(TransactionState::Write {
+        change_schema: true,
+    });
+    let cache_capacity = pager.page_cache_capacity();
+
+    // Statements run on the connection while rebuilding must not commit the transaction
+    conn.auto_commit.set(false);
+    let result = rebuild(conn).and_then(|scratch| swap(conn, &scratch));
+    conn.auto_commit.set(true);
+
+    let wal_checkpoint_disabled = conn.wal_checkpoint_disabled.get();
+    let database_size = match result {
+        Ok(database_size) => database_size,
+        Err(err) => {
+            pager.rollback(true, conn)?;
+            pager.end_tx(true, true, conn, wal_checkpoint_disabled)?;
+            conn.transaction_state.set(TransactionState::None);
+            pager.change_page_cache_size(cache_capacity)?;
+            return Err(err);
+        }
+    };
+    loop {
+        match pager.end_tx(false, true, conn, wal_checkpoint_disabled)? {
+            PagerCacheflushStatus::IO => pager.io.run_once()?,
+            PagerCacheflushStatus::Done(_) => break,
+        }
+    }
+    conn.transaction_state.set(TransactionState::None);
+    pager.change_page_cache_size(cache_capacity)?;
+
+    // The file can only shrink once no page of the database lives in the WAL anymore
+    if !wal_checkpoint_disabled {
+        let checkpoint = pager.wal_checkpoint(false, CheckpointMode::Passive)?;
+        if checkpoint.num_checkpointed_frames == checkpoint.num_wal_frames {
+            let page_size = pager.get_page_size() as u64;
+            pager.db_file.truncate(database_size as u64 * page_size)?;
+        }
+    }
+    Ok(())
+}
+
+fn begin_write_tx(pager: &Pager) -> Result<()> {
+    loop {
+        match pager.begin_read_tx()? {
+            CursorResult::IO => pager.io.run_once()?,
+            CursorResult::Ok(LimboResult::Busy) => return Err(LimboError::Busy),
+            CursorResult::Ok(LimboResult::Ok) => break,
+        }
+    }
+    loop {
+        match pager.begin_write_tx()? {
+            CursorResult::IO => pager.io.run_once()?,
+            CursorResult::Ok(LimboResult::Busy) => {
+                pager.end_read_tx()?;
+                return Err(LimboError::Busy);
+            }
+            CursorResult::Ok(LimboResult::Ok) => return Ok(()),
+        }
+    }
+}
+
+/// Copies the schema and the contents of the database into a new in-memory database.
+fn rebuild(conn: &Arc<Connection>) -> Result<Arc<Connection>> {
+    let schema = conn.schema.borrow().clone();
+    if header_accessor::get_vacuum_mode_largest_root_page(&conn.pager)? != 0 {
+        return Err(LimboError::InternalError(
+            "VACUUM is not supported on auto-vacuum databases".to_string(),
+        ));
+    }
+    if schema
+        .tables
+        .values()
+        .any(|table| matches!(table.as_ref(), Table::Virtual(_)))
+    {
+        return Err(LimboError::InternalError(
+            "VACUUM is not supported on databases with virtual tables".to_string(),
+        ));
+    }
+
+    let io: Arc<dyn IO> = Arc::new(MemoryIO::new());
+    let file = io.open_file(MEMORY_PATH, OpenFlags::Create, false)?;
+    let db_file = Arc::new(FileMemoryStorage::new(file));
+    let scratch_db = Database::open(io, MEMORY_PATH, db_file, false, schema.indexes_enabled)?;
+    let scratch = scratch_db.connect()?;
+    scratch.execute(format!("PRAGMA page_size = {}", conn.pager.get_page_size()))?;
+
+    let entries = query(
+        conn,
+        "SELECT type, name, sql FROM sqlite_schema WHERE sql IS NOT NULL ORDER BY rowid",
+    )?;
+    let entries = entries
+        .into_iter()
+        .map(|row| match row.as_slice() {
+            [Value::Text(ty), Value::Text(name), Value::Text(sql)] => Ok((
+                ty.as_str().to_string(),
+                name.as_str().to_string(),
+                sql.as_str().to_string(),
+            )),
+            _ => Err(LimboError::Corrupt(
+                "malformed row in sqlite_schema".to_string(),
+            )),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if !schema.indexes_enabled && entries.iter().any(|(ty, _, _)| ty == "index") {
+        return Err(LimboError::InternalError(
+            "VACUUM is not supported on databases with indexes while indexes are disabled"
+                .to_string(),
+        ));
+    }
+
+    // Tables are filled before their indexes are created, so that each index is built at once.
+    // sqlite_sequence is created along with the first AUTOINCREMENT table, while internal
+    // tables like sqlite_stat1 are created and copied like any other table.
+    for (_, name, sql) in entries.iter().filter(|(ty, _, _)| ty == "table") {
+        if name == SQLITE_SEQUENCE_TABLEID {
+            continue;
+        }
+        scratch.execute(sql)?;
+        copy_table(conn, &scratch, &schema, name)?;
+    }
+    // Copying the rows of AUTOINCREMENT tables recorded their largest rowids in sqlite_sequence,
+    // which are replaced by the largest rowids they ever used
+    if scratch
+        .schema
+        .borrow()
+        .get_table(SQLITE_SEQUENCE_TABLEID)
+        .is_some()
+    {
+        scratch.execute(format!("DELETE FROM {}", SQLITE_SEQUENCE_TABLEID))?;
+        copy_table(conn, &scratch, &schema, SQLITE_SEQUENCE_TABLEID)?;
+    }
+    for (_, _, sql) in entries.iter().filter(|(ty, _, _)| ty != "table") {
+        scratch.execute(sql)?;
+    }
+    Ok(scratch)
+}
+
+fn copy_table(
+    conn: &Arc<Connection>,
+    scratch: &Arc<Connection>,
+    schema: &Schema,
+    name: &str,
+) -> Result<()> {
+    let Some(table) = schema.get_btree_table(name) else {
+        return Err(LimboError::InternalError(format!(
+            "no such table: {}",
+            name
+        )));
+    };
+    // Generated columns are computed again when the rows are inserted
+    let columns = table
+        .columns
+        .iter()
+        .filter(|column| column.generated.is_none())
+        .filter_map(|column| column.name.as_deref().map(quote_ident))
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let mut select = conn.prepare(format!(
+        "SELECT {} FROM {} ORDER BY rowid",
+        columns.join(", "),
+        quote_ident(name)
+    ))?;
+    let mut insert = scratch.prepare(format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_ident(name),
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    ))?;
+    loop {
+        match select.step()? {
+            StepResult::Row => {
+                let row = select.row().unwrap();
+                for (i, value) in row.get_values().enumerate() {
+                    insert.bind_at(NonZero::new(i + 1).unwrap(), value.clone());
+                }
+                run_to_completion(&mut insert, &scratch.pager)?;
+                insert.reset();
+            }
+            StepResult::IO => conn.pager.io.run_once()?,
+            StepResult::Done => return Ok(()),
+            StepResult::Interrupt => {
+                return Err(LimboError::InternalError("VACUUM interrupted".to_string()))
+            }
+            StepResult::Busy => return Err(LimboError::Busy),
+        }
+    }
+}
+
+/// Writes the pages of the rebuilt database over the ones of the database of `conn`, returning
+/// the new number of pages.
+fn swap(conn: &Arc<Connection>, scratch: &Arc<Connection>) -> Result<u32> {
+    let pager = &conn.pager;
+    let header = header_accessor::get_database_header(pager)?;
+    pager.clear_page_cache();
+
+    loop {
+        match scratch.pager.begin_read_tx()? {
+            CursorResult::IO => scratch.pager.io.run_once()?,
+            CursorResult::Ok(LimboResult::Busy) => return Err(LimboError::Busy),
+            CursorResult::Ok(LimboResult::Ok) => break,
+        }
+    }
+    let database_size = header_accessor::get_database_size(&scratch.pager)?;
+    // Every page of the database is dirty until the transaction commits
+    if database_size as usize > pager.page_cache_capacity() {
+        pager.change_page_cache_size(database_size as usize)?;
+    }
+    for page_id in 1..=database_size as usize {
+        let bytes = scratch.pager.read_page_bytes(page_id)?;
+        pager.overwrite_page(page_id, &bytes)?;
+    }
+    scratch.pager.end_read_tx()?;
+
+    header_accessor::set_user_version(pager, header.user_version)?;
+    header_accessor::set_application_id(pager, header.application_id)?;
+    header_accessor::set_default_page_cache_size(pager, header.default_page_cache_size)?;
+    let schema_version = header.schema_cookie.wrapping_add(1);
+    header_accessor::set_schema_cookie(pager, schema_version)?;
+
+    // The rebuilt tables have new root pages
+    let mut schema = Schema::new(conn.schema.borrow().indexes_enabled);
+    let rows = conn.prepare("SELECT * FROM sqlite_schema")?;
+    parse_schema_rows(
+        Some(rows),
+        &mut schema,
+        pager.io.clone(),
+        &conn.syms.borrow(),
+        None,
+    )?;
+    schema.schema_version = schema_version;
+    // The rebuilt database holds the same rows, so the statistics still apply
+    schema.table_stats = conn.schema.borrow().table_stats.clone();
+    conn.schema.replace(schema);
+    Ok(database_size)
+}
+
+fn query(conn: &Arc<Connection>, sql: &str) -> Result<Vec<Vec<Value>>> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = Vec::new();
+    loop {
+        match stmt.step()? {
+            StepResult::Row => {
+                let row = stmt.row().unwrap();
+                rows.push(row.get_values().cloned().collect());
+            }
+            StepResult::IO => conn.pager.io.run_once()?,
+            StepResult::Done => return Ok(rows),
+            StepResult::Interrupt => {
+                return Err(LimboError::InternalError("VACUUM interrupted".to_string()))
+            }
+            StepResult::Busy => return Err(LimboError::Busy),
+        }
+    }
+}
+
+fn run_to_completion(stmt: &mut Statement, pager: &Pager) -> Result<()> {
+    loop {
+        match stmt.step()? {
+            StepResult::Row => {}
+            StepResult::IO => pager.io.run_once()?,
+            StepResult::Done => return Ok(()),
+            StepResult::Interrupt => {
+                return Err(LimboError::InternalError("VACUUM interrupted".to_string()))
+            }
+            StepResult::Busy => return Err(LimboError::Busy),
+        }
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -104,6 +104,14 @@ pub struct ProgramBuilder {
     pub parameters: Parameters,
     pub result_columns: Vec<ResultSetColumn>,
     pub table_references: TableReferences,
+    /// Whether a constraint violation rolls back the whole transaction, set by `OR ROLLBACK`
+    pub rollback_on_constraint: bool,
+    /// Whether the foreign keys of the connection are enforced, for the statements nested in
+    /// the program by triggers
+    pub foreign_keys: bool,
+    /// Triggers whose commands are being emitted. As with `PRAGMA recursive_triggers` off, a
+    /// trigger does not fire again from within its own commands.
+    pub active_triggers: Vec<String>,
     /// Curr collation sequence. Bool indicates whether it was set by a COLLATE expr
     collation: Option<(CollationSeq, bool)>,
     /// Current parsing nesting level
@@ -167,6 +175,9 @@ impl ProgramBuilder {
             parameters: Parameters::new(),
             result_columns: Vec::new(),
             table_references: TableReferences::new(vec![], vec![]),
+            rollback_on_constraint: false,
+            foreign_keys: false,
+            active_triggers: Vec::new(),
             collation: None,
             nested_level: 0,
             // These labels will be filled when `prologue()` is called
@@ -597,6 +608,9 @@ impl ProgramBuilder {
                 Insn::IfPos { target_pc, .. } => {
                     resolve(target_pc, "IfPos");
                 }
+                Insn::FkIfZero { target_pc } => {
+                    resolve(target_pc, "FkIfZero");
+                }
                 Insn::Next { pc_if_next, .. } => {
                     resolve(pc_if_next, "Next");
                 }
@@ -815,6 +829,21 @@ impl ProgramBuilder {
 
         use crate::translate::expr::sanitize_string;
 
+        // VIRTUAL generated columns are left out of the record, so the stored columns after
+        // them sit at a lower position. Reading a virtual column from the cursor yields NULL,
+        // callers compute its expression instead.
+        let record_column = match cursor_type {
+            CursorType::BTreeTable(btree) => btree.record_position(column),
+            _ => Some(column),
+        };
+        let Some(record_column) = record_column else {
+            self.emit_insn(Insn::Null {
+                dest: out,
+                dest_end: None,
+            });
+            return;
+        };
+
         let default = 'value: {
             let default = match cursor_type {
                 CursorType::BTreeTable(btree) => &btree.columns[column].default,
@@ -852,13 +881,13 @@ impl ProgramBuilder {
 
         self.emit_insn(Insn::Column {
             cursor_id,
-            column,
+            column: record_column,
             dest: out,
             default,
         });
     }
 
-    pub fn build(mut self, connection: Arc<Connection>, change_cnt_on: bool) -> Program {
+    pub fn build(mut self, connection: Arc<Connection>, change_cnt_on: bool, sql: &str) -> Program {
         self.resolve_labels();
 
         self.parameters.list.dedup();
@@ -875,8 +904,10 @@ impl ProgramBuilder {
             parameters: self.parameters,
             n_change: Cell::new(0),
             change_cnt_on,
+            rollback_on_constraint: self.rollback_on_constraint,
             result_columns: self.result_columns,
             table_references: self.table_references,
+            sql: sql.to_string(),
         }
     }
 }
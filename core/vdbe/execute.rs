@@ -8,12 +8,11 @@ use crate::storage::pager::CreateBTreeFlags;
 use crate::storage::wal::DummyWAL;
 use crate::storage::{self, header_accessor};
 use crate::translate::collate::CollationSeq;
+use crate::translate::trigger::{rename_trigger_column, rename_trigger_table};
 use crate::types::{ImmutableRecord, Text};
 use crate::util::normalize_ident;
 use crate::{
-    error::{
-        LimboError, SQLITE_CONSTRAINT, SQLITE_CONSTRAINT_NOTNULL, SQLITE_CONSTRAINT_PRIMARYKEY,
-    },
+    error::{LimboError, SQLITE_CONSTRAINT, SQLITE_CONSTRAINT_FOREIGNKEY},
     ext::ExtValue,
     function::{AggFunc, ExtFunc, MathFunc, MathFuncArity, ScalarFunc, VectorFunc},
     functions::{
@@ -31,14 +30,16 @@ use std::{borrow::BorrowMut, rc::Rc, sync::Arc};
 use crate::{pseudo::PseudoCursor, result::LimboResult};
 
 use crate::{
-    schema::{affinity, Affinity},
+    schema::{affinity, Affinity, BTreeTable, Index},
     storage::btree::{BTreeCursor, BTreeKey},
+    temp::{is_temp_root_page, TEMP_ROOT_PAGE_FLAG, TEMP_SCHEMA_TABLE_NAME},
 };
 
 use crate::{
     storage::wal::CheckpointResult,
     types::{
-        AggContext, Cursor, CursorResult, ExternalAggState, SeekKey, SeekOp, Value, ValueType,
+        AggContext, Cursor, CursorResult, ExternalAggState, OrderedAggState, SeekKey, SeekOp,
+        Value, ValueType,
     },
     util::{
         cast_real_to_integer, cast_text_to_integer, cast_text_to_numeric, cast_text_to_real,
@@ -46,17 +47,18 @@ use crate::{
     },
     vdbe::{
         builder::CursorType,
-        insn::{IdxInsertFlags, Insn},
+        insn::{IdxInsertFlags, InsertFlags, Insn},
     },
     vector::{vector32, vector64, vector_distance_cos, vector_extract},
 };
 
 use crate::{
-    info, BufferPool, MvCursor, OpenFlags, RefValue, Row, StepResult, TransactionState, IO,
+    info, BufferPool, Limit, MvCursor, OpenFlags, RefValue, Row, Savepoint, StepResult,
+    TransactionState, IO,
 };
 
 use super::{
-    insn::{Cookie, RegisterOrLiteral},
+    insn::{Cookie, RegisterOrLiteral, SavepointOp},
     CommitState,
 };
 use fallible_iterator::FallibleIterator;
@@ -66,6 +68,8 @@ use turso_sqlite3_parser::ast;
 use turso_sqlite3_parser::ast::fmt::ToTokens;
 use turso_sqlite3_parser::lexer::sql::Parser;
 
+#[cfg(feature = "regexp")]
+use super::likeop::exec_regexp;
 use super::{
     likeop::{construct_like_escape_arg, exec_glob, exec_like_with_escape},
     sorter::Sorter,
@@ -86,7 +90,8 @@ use crate::{
 
 use super::{get_new_rowid, make_record, Program, ProgramState, Register};
 use crate::{
-    bail_constraint_error, must_be_btree_cursor, resolve_ext_path, MvStore, Pager, Result,
+    bail_constraint_error, must_be_btree_cursor, resolve_ext_path, Connection, MvStore, Pager,
+    Result,
 };
 
 macro_rules! return_if_io {
@@ -221,6 +226,26 @@ pub fn op_drop_index(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_drop_trigger(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::DropTrigger {
+        trigger_name,
+        db: _,
+    } = insn
+    else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    let mut schema = program.connection.schema.borrow_mut();
+    schema.remove_trigger(trigger_name);
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_remainder(
     program: &Program,
     state: &mut ProgramState,
@@ -303,21 +328,22 @@ pub fn op_checkpoint(
 ) -> Result<InsnFunctionStepResult> {
     let Insn::Checkpoint {
         database: _,
-        checkpoint_mode: _,
+        checkpoint_mode,
         dest,
     } = insn
     else {
         unreachable!("unexpected Insn {:?}", insn)
     };
-    let result = program.connection.checkpoint();
+    let result = program.connection.checkpoint(*checkpoint_mode);
     match result {
         Ok(CheckpointResult {
             num_wal_frames: num_wal_pages,
             num_checkpointed_frames: num_checkpointed_pages,
+            busy,
         }) => {
             // https://sqlite.org/pragma.html#pragma_wal_checkpoint
             // 1st col: 1 (checkpoint SQLITE_BUSY) or 0 (not busy).
-            state.registers[*dest] = Register::Value(Value::Integer(0));
+            state.registers[*dest] = Register::Value(Value::Integer(busy as i64));
             // 2nd col: # modified pages written to wal file
             state.registers[*dest + 1] = Register::Value(Value::Integer(num_wal_pages as i64));
             // 3rd col: # pages moved to db after checkpoint
@@ -515,6 +541,40 @@ pub fn op_if_pos(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_fk_counter(
+    _program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    _mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::FkCounter { increment_value } = insn else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    state.fk_constraint_counter += *increment_value;
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
+pub fn op_fk_if_zero(
+    _program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    _mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::FkIfZero { target_pc } = insn else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    assert!(target_pc.is_offset());
+    if state.fk_constraint_counter == 0 {
+        state.pc = target_pc.as_offset_int();
+    } else {
+        state.pc += 1;
+    }
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_not_null(
     program: &Program,
     state: &mut ProgramState,
@@ -878,6 +938,8 @@ pub fn op_open_read(
         unreachable!("unexpected Insn {:?}", insn)
     };
     let (_, cursor_type) = program.cursor_ref.get(*cursor_id).unwrap();
+    let conn = program.connection.clone();
+    let (btree_pager, btree_root_page) = btree_location(&conn, pager, *root_page)?;
     let mv_cursor = match state.mv_tx_id {
         Some(tx_id) => {
             let table_id = *root_page as u64;
@@ -892,36 +954,35 @@ pub fn op_open_read(
     let mut cursors = state.cursors.borrow_mut();
     match cursor_type {
         CursorType::BTreeTable(_) => {
-            let cursor = BTreeCursor::new_table(mv_cursor, pager.clone(), *root_page);
+            let cursor = BTreeCursor::new_table(mv_cursor, btree_pager, btree_root_page);
             cursors
                 .get_mut(*cursor_id)
                 .unwrap()
                 .replace(Cursor::new_btree(cursor));
         }
         CursorType::BTreeIndex(index) => {
-            let conn = program.connection.clone();
-            let schema = conn.schema.borrow();
-            let table = schema
-                .get_table(&index.table_name)
-                .and_then(|table| table.btree());
+            let table = index_table(&conn, index, *root_page);
             let collations = table.map_or(Vec::new(), |table| {
                 index
                     .columns
                     .iter()
                     .map(|c| {
-                        table
-                            .columns
-                            .get(c.pos_in_table)
-                            .unwrap()
-                            .collation
+                        // An explicit COLLATE on the index column overrides the column's declared collation
+                        c.collation
+                            .or_else(|| {
+                                table
+                                    .columns
+                                    .get(c.pos_in_table)
+                                    .and_then(|column| column.collation)
+                            })
                             .unwrap_or_default()
                     })
                     .collect()
             });
             let cursor = BTreeCursor::new_index(
                 mv_cursor,
-                pager.clone(),
-                *root_page,
+                btree_pager,
+                btree_root_page,
                 index.as_ref(),
                 collations,
             );
@@ -1417,12 +1478,11 @@ pub fn op_type_check(
                 && col.primary_key
                 && matches!(reg.get_owned_value(), Value::Null)
             {
-                bail_constraint_error!(
-                    "NOT NULL constraint failed: {}.{} ({})",
+                return Err(LimboError::NotNullConstraint(format!(
+                    "{}.{}",
                     &table_reference.name,
-                    col.name.as_deref().unwrap_or(""),
-                    SQLITE_CONSTRAINT
-                )
+                    col.name.as_deref().unwrap_or("")
+                )));
             } else if col.is_rowid_alias && matches!(reg.get_owned_value(), Value::Null) {
                 // Handle INTEGER PRIMARY KEY for null as usual (Rowid will be auto-assigned)
                 return Ok(());
@@ -1470,6 +1530,9 @@ pub fn op_make_record(
         unreachable!("unexpected Insn {:?}", insn)
     };
     let record = make_record(&state.registers, start_reg, count);
+    if record.get_payload().len() > program.connection.limit(Limit::Length) as usize {
+        return Err(LimboError::TooBig("string or blob too big".to_string()));
+    }
     state.registers[*dest_reg] = Register::Record(record);
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
@@ -1568,26 +1631,8 @@ pub fn halt(
         // invalidate page cache in case of error
         pager.clear_page_cache();
     }
-    match err_code {
-        0 => {}
-        SQLITE_CONSTRAINT_PRIMARYKEY => {
-            return Err(LimboError::Constraint(format!(
-                "UNIQUE constraint failed: {} (19)",
-                description
-            )));
-        }
-        SQLITE_CONSTRAINT_NOTNULL => {
-            return Err(LimboError::Constraint(format!(
-                "NOT NULL constraint failed: {} (19)",
-                description
-            )));
-        }
-        _ => {
-            return Err(LimboError::Constraint(format!(
-                "undocumented halt error code {}",
-                description
-            )));
-        }
+    if err_code > 0 {
+        return Err(LimboError::from_constraint_code(err_code, description));
     }
     match program.commit_txn(pager.clone(), state, mv_store, false)? {
         StepResult::Done => Ok(InsnFunctionStepResult::Done),
@@ -1612,30 +1657,19 @@ pub fn op_halt(
     else {
         unreachable!("unexpected Insn {:?}", insn)
     };
-    if *err_code > 0 {
+    // Like in SQLite, the foreign key constraint violations are counted while the statement
+    // runs, as a later change of the statement can resolve them, and fail it once it ends
+    let err_code = if *err_code == 0 && state.fk_constraint_counter > 0 {
+        SQLITE_CONSTRAINT_FOREIGNKEY
+    } else {
+        *err_code
+    };
+    if err_code > 0 {
         // invalidate page cache in case of error
         pager.clear_page_cache();
     }
-    match *err_code {
-        0 => {}
-        SQLITE_CONSTRAINT_PRIMARYKEY => {
-            return Err(LimboError::Constraint(format!(
-                "UNIQUE constraint failed: {} (19)",
-                description
-            )));
-        }
-        SQLITE_CONSTRAINT_NOTNULL => {
-            return Err(LimboError::Constraint(format!(
-                "NOTNULL constraint failed: {} (19)",
-                description
-            )));
-        }
-        _ => {
-            return Err(LimboError::Constraint(format!(
-                "undocumented halt error code {}",
-                description
-            )));
-        }
+    if err_code > 0 {
+        return Err(LimboError::from_constraint_code(err_code, description));
     }
     let auto_commit = program.connection.auto_commit.get();
     tracing::trace!("op_halt(auto_commit={})", auto_commit);
@@ -1774,6 +1808,8 @@ pub fn op_auto_commit(
         };
 
     if *auto_commit != conn.auto_commit.get() {
+        // Ending the transaction discards all of its savepoints
+        conn.savepoints.borrow_mut().clear();
         if *rollback {
             // TODO(pere): add rollback I/O logic once we implement rollback journal
             pager.rollback(change_schema, &conn)?;
@@ -1803,6 +1839,77 @@ pub fn op_auto_commit(
     }
 }
 
+pub fn op_savepoint(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::Savepoint { op, name } = insn else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    if mv_store.is_some() {
+        return Err(LimboError::TxError(
+            "savepoints are not supported with MVCC".to_string(),
+        ));
+    }
+    let conn = program.connection.clone();
+    match op {
+        SavepointOp::Begin => {
+            // A savepoint outside of a transaction behaves like BEGIN DEFERRED
+            let starts_transaction = conn.auto_commit.get();
+            if starts_transaction {
+                conn.auto_commit.replace(false);
+            }
+            conn.savepoints.borrow_mut().push(Savepoint {
+                name: name.clone(),
+                starts_transaction,
+                pages: pager.snapshot_dirty_pages(),
+                schema: conn.schema.borrow().clone(),
+                temp: conn
+                    .temp
+                    .borrow()
+                    .as_ref()
+                    .filter(|temp| temp.in_tx())
+                    .map(|temp| (temp.pager.snapshot_dirty_pages(), temp.schema.clone())),
+            });
+        }
+        SavepointOp::Release | SavepointOp::RollbackTo => {
+            let mut savepoints = conn.savepoints.borrow_mut();
+            let Some(idx) = savepoints.iter().rposition(|sp| &sp.name == name) else {
+                return Err(LimboError::TxError(format!("no such savepoint: {}", name)));
+            };
+            if *op == SavepointOp::Release {
+                // Releasing a savepoint also releases every savepoint opened after it,
+                // and commits the transaction if the savepoint started it
+                let released = savepoints.split_off(idx);
+                if released[0].starts_transaction {
+                    conn.auto_commit.replace(true);
+                }
+            } else {
+                // The savepoint itself stays open after rolling back to it
+                savepoints.truncate(idx + 1);
+                let savepoint = &savepoints[idx];
+                pager.restore_snapshot(&savepoint.pages)?;
+                conn.schema.replace(savepoint.schema.clone());
+                match &savepoint.temp {
+                    Some((pages, schema)) => {
+                        if let Some(temp) = conn.temp.borrow_mut().as_mut() {
+                            temp.pager.restore_snapshot(pages)?;
+                            temp.schema = schema.clone();
+                        }
+                    }
+                    // The temp database was only changed after the savepoint
+                    None => conn.end_temp_tx(true)?,
+                }
+            }
+        }
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_goto(
     program: &Program,
     state: &mut ProgramState,
@@ -2523,7 +2630,9 @@ pub fn op_decr_jump_zero(
     assert!(target_pc.is_offset());
     match state.registers[*reg].get_owned_value() {
         Value::Integer(n) => {
-            let n = n - 1;
+            // A negative LIMIT, as in `LIMIT -1 OFFSET n`, keeps counting down and never
+            // reaches zero, so it must not overflow either
+            let n = n.saturating_sub(1);
             state.registers[*reg] = Register::Value(Value::Integer(n));
             if n == 0 {
                 state.pc = target_pc.as_offset_int();
@@ -2552,6 +2661,37 @@ pub fn op_agg_step(
     else {
         unreachable!("unexpected Insn {:?}", insn)
     };
+    if let Register::Aggregate(AggContext::Ordered(_)) = state.registers[*acc_reg] {
+        // The row is only buffered, AggFinal steps through the rows once they are sorted
+        let argc = match func {
+            AggFunc::External(func) => match func.as_ref() {
+                ExtFunc::Aggregate { argc, .. } => *argc,
+                _ => unreachable!("scalar function called in aggregate context"),
+            },
+            _ => 1,
+        };
+        let args = state.registers[*col..*col + argc]
+            .iter()
+            .map(|reg| reg.get_owned_value().clone())
+            .collect();
+        let delimiter = match func {
+            AggFunc::GroupConcat | AggFunc::StringAgg => {
+                Some(state.registers[*delimiter].get_owned_value().clone())
+            }
+            #[cfg(feature = "json")]
+            AggFunc::JsonGroupObject | AggFunc::JsonbGroupObject => {
+                Some(state.registers[*delimiter].get_owned_value().clone())
+            }
+            _ => None,
+        };
+        let Register::Aggregate(AggContext::Ordered(ordered)) = &mut state.registers[*acc_reg]
+        else {
+            unreachable!();
+        };
+        ordered.push_row(args, delimiter);
+        state.pc += 1;
+        return Ok(InsnFunctionStepResult::Step);
+    }
     if let Register::Value(Value::Null) = state.registers[*acc_reg] {
         state.registers[*acc_reg] = match func {
             AggFunc::Avg => {
@@ -2845,6 +2985,39 @@ pub fn op_agg_step(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_agg_order_key(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::AggOrderKey {
+        acc_reg,
+        key_start_reg,
+        order,
+        collations,
+    } = insn
+    else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    let key = state.registers[*key_start_reg..*key_start_reg + order.len()]
+        .iter()
+        .map(|reg| reg.get_owned_value().clone())
+        .collect::<Vec<_>>();
+    match &mut state.registers[*acc_reg] {
+        Register::Aggregate(AggContext::Ordered(ordered)) => ordered.pending_key = key,
+        Register::Value(Value::Null) => {
+            state.registers[*acc_reg] = Register::Aggregate(AggContext::Ordered(Box::new(
+                OrderedAggState::new(order.clone(), collations.clone(), key),
+            )));
+        }
+        reg => unreachable!("unexpected value {:?} in AggOrderKey", reg),
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_agg_final(
     program: &Program,
     state: &mut ProgramState,
@@ -2855,6 +3028,41 @@ pub fn op_agg_final(
     let Insn::AggFinal { register, func } = insn else {
         unreachable!("unexpected Insn {:?}", insn)
     };
+    if let Register::Aggregate(AggContext::Ordered(_)) = state.registers[*register] {
+        let Register::Aggregate(AggContext::Ordered(ordered)) = std::mem::replace(
+            &mut state.registers[*register],
+            Register::Value(Value::Null),
+        ) else {
+            unreachable!();
+        };
+        // The rows are stepped through from a scratch range of registers, as the ones they were
+        // read from may have been reused since
+        let rows = ordered.into_sorted_rows();
+        let argc = rows.first().map_or(0, |row| row.args.len());
+        let col = state.registers.len();
+        let delimiter = col + argc;
+        state
+            .registers
+            .resize_with(delimiter + 1, || Register::Value(Value::Null));
+        let step = Insn::AggStep {
+            acc_reg: *register,
+            col,
+            delimiter,
+            func: func.clone(),
+        };
+        let pc = state.pc;
+        for row in rows {
+            for (i, arg) in row.args.into_iter().enumerate() {
+                state.registers[col + i] = Register::Value(arg);
+            }
+            if let Some(value) = row.delimiter {
+                state.registers[delimiter] = Register::Value(value);
+            }
+            op_agg_step(program, state, &step, pager, mv_store)?;
+        }
+        state.pc = pc;
+        state.registers.truncate(col);
+    }
     match state.registers[*register].borrow_mut() {
         Register::Aggregate(agg) => match func {
             AggFunc::Avg => {
@@ -3495,20 +3703,23 @@ pub fn op_function(
                     _ => &match_expression.get_owned_value().exec_cast("TEXT"),
                 };
 
+                let escape = if arg_count == 3 {
+                    construct_like_escape_arg(state.registers[*start_reg + 2].get_owned_value())?
+                } else {
+                    None
+                };
+
                 let result = match (pattern, match_expression) {
                     (Value::Text(pattern), Value::Text(match_expression)) if arg_count == 3 => {
-                        let escape = match construct_like_escape_arg(
-                            state.registers[*start_reg + 2].get_owned_value(),
-                        ) {
-                            Ok(x) => x,
-                            Err(e) => return Err(e),
-                        };
-
-                        Value::Integer(exec_like_with_escape(
-                            pattern.as_str(),
-                            match_expression.as_str(),
-                            escape,
-                        ) as i64)
+                        match escape {
+                            Some(escape) => Value::Integer(exec_like_with_escape(
+                                pattern.as_str(),
+                                match_expression.as_str(),
+                                escape,
+                            ) as i64),
+                            // A NULL escape character makes the whole expression NULL
+                            None => Value::Null,
+                        }
                     }
                     (Value::Text(pattern), Value::Text(match_expression)) => {
                         let cache = if *constant_mask > 0 {
@@ -3752,7 +3963,7 @@ pub fn op_function(
                 let result = exec_strftime(&state.registers[*start_reg..*start_reg + arg_count]);
                 state.registers[*dest] = Register::Value(result);
             }
-            ScalarFunc::Printf => {
+            ScalarFunc::Printf | ScalarFunc::Format => {
                 let result = exec_printf(&state.registers[*start_reg..*start_reg + arg_count])?;
                 state.registers[*dest] = Register::Value(result);
             }
@@ -3770,6 +3981,28 @@ pub fn op_function(
                     .exec_likelihood(probability.get_owned_value());
                 state.registers[*dest] = Register::Value(result);
             }
+            #[cfg(feature = "regexp")]
+            ScalarFunc::Regexp => {
+                assert_eq!(arg_count, 2);
+                let pattern = state.registers[*start_reg].get_owned_value();
+                let text = state.registers[*start_reg + 1].get_owned_value();
+                let result = match (pattern, text) {
+                    (Value::Null, _) | (_, Value::Null) => Value::Null,
+                    (pattern, text) => {
+                        let pattern = pattern.exec_cast("TEXT");
+                        let text = text.exec_cast("TEXT");
+                        let cache = if *constant_mask > 0 {
+                            Some(&mut state.regex_cache.regexp)
+                        } else {
+                            None
+                        };
+                        Value::Integer(
+                            exec_regexp(cache, &pattern.to_string(), &text.to_string())? as i64
+                        )
+                    }
+                };
+                state.registers[*dest] = Register::Value(result);
+            }
         },
         crate::function::Func::Vector(vector_func) => match vector_func {
             VectorFunc::Vector => {
@@ -3937,8 +4170,8 @@ pub fn op_function(
                         };
 
                         let mut parser = Parser::new(sql.as_str().as_bytes());
-                        let ast::Cmd::Stmt(stmt) = parser.next().unwrap().unwrap() else {
-                            todo!()
+                        let Some(ast::Cmd::Stmt(stmt)) = parser.next()? else {
+                            break 'sql None;
                         };
 
                         match stmt {
@@ -3996,7 +4229,14 @@ pub fn op_function(
                                     .unwrap(),
                                 )
                             }
-                            _ => todo!(),
+                            ast::Stmt::CreateTrigger(mut trigger) => {
+                                if !rename_trigger_table(&mut trigger, &rename_from, &rename_to)? {
+                                    break 'sql None;
+                                }
+
+                                Some(ast::Stmt::CreateTrigger(trigger).format().unwrap())
+                            }
+                            _ => None,
                         }
                     };
 
@@ -4025,17 +4265,13 @@ pub fn op_function(
                     };
 
                     let new_sql = 'sql: {
-                        if table != tbl_name {
-                            break 'sql None;
-                        }
-
                         let Value::Text(sql) = sql else {
                             break 'sql None;
                         };
 
                         let mut parser = Parser::new(sql.as_str().as_bytes());
-                        let ast::Cmd::Stmt(stmt) = parser.next().unwrap().unwrap() else {
-                            todo!()
+                        let Some(ast::Cmd::Stmt(stmt)) = parser.next()? else {
+                            break 'sql None;
                         };
 
                         match stmt {
@@ -4091,7 +4327,7 @@ pub fn op_function(
                                     options,
                                 } = *body
                                 else {
-                                    todo!()
+                                    break 'sql None;
                                 };
 
                                 let column_index = columns
@@ -4128,7 +4364,21 @@ pub fn op_function(
                                     .unwrap(),
                                 )
                             }
-                            _ => todo!(),
+                            // A trigger refers to the column if it is on its table, or if one of
+                            // its commands uses the table
+                            ast::Stmt::CreateTrigger(mut trigger) => {
+                                if !rename_trigger_column(
+                                    &mut trigger,
+                                    &table,
+                                    &rename_from,
+                                    &rename_to,
+                                )? {
+                                    break 'sql None;
+                                }
+
+                                Some(ast::Stmt::CreateTrigger(trigger).format().unwrap())
+                            }
+                            _ => None,
                         }
                     };
 
@@ -4280,7 +4530,7 @@ pub fn op_insert(
 
         return_if_io!(cursor.insert(&BTreeKey::new_table_rowid(key, Some(record.as_ref())), true));
         // Only update last_insert_rowid for regular table inserts, not schema modifications
-        if cursor.root_page() != 1 {
+        if cursor.root_page() != 1 && !flag.has(InsertFlags::SKIP_LAST_ROWID) {
             if let Some(rowid) = return_if_io!(cursor.rowid()) {
                 program.connection.update_last_rowid(rowid);
 
@@ -4461,8 +4711,13 @@ pub fn op_idx_insert(
                 // check for uniqueness violation
                 match cursor.key_exists_in_index(record)? {
                     CursorResult::Ok(true) => {
-                        return Err(LimboError::Constraint(
-                            "UNIQUE constraint failed: duplicate key".into(),
+                        return Err(LimboError::UniqueConstraint(
+                            index_meta
+                                .columns
+                                .iter()
+                                .map(|col| format!("{}.{}", index_meta.table_name, col.name))
+                                .collect::<Vec<_>>()
+                                .join(", "),
                         ))
                     }
                     CursorResult::IO => return Ok(InsnFunctionStepResult::IO),
@@ -4503,17 +4758,33 @@ pub fn op_new_rowid(
     mv_store: Option<&Rc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
     let Insn::NewRowid {
-        cursor, rowid_reg, ..
+        cursor,
+        rowid_reg,
+        prev_largest_reg,
     } = insn
     else {
         unreachable!("unexpected Insn {:?}", insn)
     };
-    let rowid = {
+    let mut rowid = {
         let mut cursor = state.get_cursor(*cursor);
         let cursor = cursor.as_btree_mut();
         // TODO: make io handle rng
         return_if_io!(get_new_rowid(cursor, thread_rng()))
     };
+    if *prev_largest_reg > 0 {
+        // An AUTOINCREMENT table never reuses a rowid, even one whose row has been deleted
+        let Value::Integer(prev_largest) = state.registers[*prev_largest_reg].get_owned_value()
+        else {
+            return Err(LimboError::InternalError(
+                "NewRowid: the largest rowid is not an integer".to_string(),
+            ));
+        };
+        if *prev_largest == i64::MAX {
+            return Err(LimboError::Full);
+        }
+        rowid = rowid.max(prev_largest + 1);
+        state.registers[*prev_largest_reg] = Register::Value(Value::Integer(rowid));
+    }
     state.registers[*rowid_reg] = Register::Value(Value::Integer(rowid));
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
@@ -4569,6 +4840,32 @@ pub fn op_soft_null(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_mem_max(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    pager: &Rc<Pager>,
+    mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::MemMax { dest_reg, src_reg } = insn else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    let (Value::Integer(current), Value::Integer(new)) = (
+        state.registers[*dest_reg].get_owned_value(),
+        state.registers[*src_reg].get_owned_value(),
+    ) else {
+        return Err(LimboError::InternalError(
+            "MemMax: registers do not hold integers".to_string(),
+        ));
+    };
+    if new > current {
+        let new = *new;
+        state.registers[*dest_reg] = Register::Value(Value::Integer(new));
+    }
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 pub fn op_no_conflict(
     program: &Program,
     state: &mut ProgramState,
@@ -4742,30 +5039,31 @@ pub fn op_open_write(
         }
         None => None,
     };
+    let conn = program.connection.clone();
+    let (btree_pager, btree_root_page) = btree_location(&conn, pager, root_page as usize)?;
     if let Some(index) = maybe_index {
-        let conn = program.connection.clone();
-        let schema = conn.schema.borrow();
-        let table = schema
-            .get_table(&index.table_name)
-            .and_then(|table| table.btree());
+        let table = index_table(&conn, index, root_page as usize);
         let collations = table.map_or(Vec::new(), |table| {
             index
                 .columns
                 .iter()
                 .map(|c| {
-                    table
-                        .columns
-                        .get(c.pos_in_table)
-                        .unwrap()
-                        .collation
+                    // An explicit COLLATE on the index column overrides the column's declared collation
+                    c.collation
+                        .or_else(|| {
+                            table
+                                .columns
+                                .get(c.pos_in_table)
+                                .and_then(|column| column.collation)
+                        })
                         .unwrap_or_default()
                 })
                 .collect()
         });
         let cursor = BTreeCursor::new_index(
             mv_cursor,
-            pager.clone(),
-            root_page as usize,
+            btree_pager,
+            btree_root_page,
             index.as_ref(),
             collations,
         );
@@ -4774,7 +5072,7 @@ pub fn op_open_write(
             .unwrap()
             .replace(Cursor::new_btree(cursor));
     } else {
-        let cursor = BTreeCursor::new_table(mv_cursor, pager.clone(), root_page as usize);
+        let cursor = BTreeCursor::new_table(mv_cursor, btree_pager, btree_root_page);
         cursors
             .get_mut(*cursor_id)
             .unwrap()
@@ -4819,12 +5117,14 @@ pub fn op_create_btree(
     if program.connection.readonly.get() {
         return Err(LimboError::ReadOnly);
     }
-    if *db > 0 {
-        // TODO: implement temp databases
-        todo!("temp databases not implemented yet");
-    }
-    // FIXME: handle page cache is full
-    let root_page = return_if_io!(pager.btree_create(flags));
+    let root_page = if *db > 0 {
+        let temp_pager = program.connection.temp_pager()?;
+        let root_page = return_if_io!(temp_pager.btree_create(flags));
+        root_page as usize | TEMP_ROOT_PAGE_FLAG
+    } else {
+        // FIXME: handle page cache is full
+        return_if_io!(pager.btree_create(flags)) as usize
+    };
     state.registers[*root] = Register::Value(Value::Integer(root_page as i64));
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
@@ -4845,11 +5145,13 @@ pub fn op_destroy(
     else {
         unreachable!("unexpected Insn {:?}", insn)
     };
-    if *is_temp == 1 {
-        todo!("temp databases not implemented yet.");
-    }
+    let (btree_pager, btree_root_page) = if *is_temp == 1 {
+        btree_location(&program.connection, pager, *root)?
+    } else {
+        (pager.clone(), *root)
+    };
     // TODO not sure if should be BTreeCursor::new_table or BTreeCursor::new_index here or neither and just pass an emtpy vec
-    let mut cursor = BTreeCursor::new(None, pager.clone(), *root, Vec::new());
+    let mut cursor = BTreeCursor::new(None, btree_pager, btree_root_page, Vec::new());
     let former_root_page_result = cursor.btree_destroy()?;
     if let CursorResult::Ok(former_root_page) = former_root_page_result {
         state.registers[*former_root_reg] =
@@ -4869,19 +5171,81 @@ pub fn op_drop_table(
     let Insn::DropTable { db, table_name, .. } = insn else {
         unreachable!("unexpected Insn {:?}", insn)
     };
-    if *db > 0 {
-        todo!("temp databases not implemented yet");
-    }
     let conn = program.connection.clone();
-    {
+    if *db > 0 {
+        if let Some(temp) = conn.temp.borrow_mut().as_mut() {
+            temp.schema.remove_indices_for_table(table_name);
+            temp.schema.remove_table(table_name);
+        }
+    } else {
         let mut schema = conn.schema.borrow_mut();
         schema.remove_indices_for_table(table_name);
+        schema.remove_triggers_for_table(table_name);
         schema.remove_table(table_name);
     }
     state.pc += 1;
     Ok(InsnFunctionStepResult::Step)
 }
 
+/// The pager of the b-tree rooted at `root_page` and the root page of the b-tree in that pager,
+/// which is the pager of the temp database for the b-trees of temp tables
+fn btree_location(
+    conn: &Connection,
+    pager: &Rc<Pager>,
+    root_page: usize,
+) -> Result<(Rc<Pager>, usize)> {
+    if is_temp_root_page(root_page) {
+        Ok((conn.temp_pager()?, root_page & !TEMP_ROOT_PAGE_FLAG))
+    } else {
+        Ok((pager.clone(), root_page))
+    }
+}
+
+/// The table of `index`, looked up in the schema of the database the index rooted at
+/// `root_page` belongs to
+fn index_table(conn: &Connection, index: &Index, root_page: usize) -> Option<Rc<BTreeTable>> {
+    if is_temp_root_page(root_page) {
+        conn.temp
+            .borrow()
+            .as_ref()
+            .and_then(|temp| temp.schema.get_btree_table(&index.table_name))
+    } else {
+        conn.schema.borrow().get_btree_table(&index.table_name)
+    }
+}
+
+/// Parses the rows of `sqlite_temp_schema` that match `where_clause` into the schema of the
+/// temp database
+fn parse_temp_schema(
+    conn: &Arc<Connection>,
+    where_clause: Option<&str>,
+    mv_tx_id: Option<crate::mvcc::database::TxID>,
+) -> Result<()> {
+    let stmt = match where_clause {
+        Some(where_clause) => conn.prepare(format!(
+            "SELECT * FROM {} WHERE {}",
+            TEMP_SCHEMA_TABLE_NAME, where_clause
+        ))?,
+        None => conn.prepare(format!("SELECT * FROM {}", TEMP_SCHEMA_TABLE_NAME))?,
+    };
+    let Some(mut new_schema) = conn.temp.borrow().as_ref().map(|temp| temp.schema.clone()) else {
+        return Err(LimboError::InternalError(
+            "temp database is not open".to_string(),
+        ));
+    };
+    parse_schema_rows(
+        Some(stmt),
+        &mut new_schema,
+        conn.pager.io.clone(),
+        &conn.syms.borrow(),
+        mv_tx_id,
+    )?;
+    if let Some(temp) = conn.temp.borrow_mut().as_mut() {
+        temp.schema = new_schema;
+    }
+    Ok(())
+}
+
 pub fn op_close(
     program: &Program,
     state: &mut ProgramState,
@@ -4943,11 +5307,7 @@ pub fn op_parse_schema(
     pager: &Rc<Pager>,
     mv_store: Option<&Rc<MvStore>>,
 ) -> Result<InsnFunctionStepResult> {
-    let Insn::ParseSchema {
-        db: _,
-        where_clause,
-    } = insn
-    else {
+    let Insn::ParseSchema { db, where_clause } = insn else {
         unreachable!("unexpected Insn {:?}", insn)
     };
     let conn = program.connection.clone();
@@ -4956,7 +5316,9 @@ pub fn op_parse_schema(
     let previous_auto_commit = conn.auto_commit.get();
     conn.auto_commit.set(false);
 
-    if let Some(where_clause) = where_clause {
+    if *db > 0 {
+        parse_temp_schema(&conn, where_clause.as_deref(), state.mv_tx_id)?;
+    } else if let Some(where_clause) = where_clause {
         let stmt = conn.prepare(format!(
             "SELECT * FROM sqlite_schema WHERE {}",
             where_clause
@@ -4978,6 +5340,8 @@ pub fn op_parse_schema(
     } else {
         let stmt = conn.prepare("SELECT * FROM sqlite_schema")?;
         let mut new_schema = conn.schema.borrow().clone();
+        // Every trigger is read again, under the table it now fires on after an ALTER TABLE
+        new_schema.triggers.clear();
 
         // TODO: This function below is synchronous, make it async
         {
@@ -5013,6 +5377,8 @@ pub fn op_read_cookie(
     }
     let cookie_value = match cookie {
         Cookie::UserVersion => header_accessor::get_user_version(pager)?.into(),
+        // The application id is stored unsigned but reported as a signed 32-bit integer.
+        Cookie::ApplicationId => (header_accessor::get_application_id(pager)? as i32).into(),
         Cookie::SchemaVersion => header_accessor::get_schema_cookie(pager)?.into(),
         Cookie::LargestRootPageNumber => {
             header_accessor::get_vacuum_mode_largest_root_page(pager)?.into()
@@ -5047,6 +5413,9 @@ pub fn op_set_cookie(
         Cookie::UserVersion => {
             header_accessor::set_user_version(pager, *value)?;
         }
+        Cookie::ApplicationId => {
+            header_accessor::set_application_id(pager, *value as u32)?;
+        }
         Cookie::LargestRootPageNumber => {
             header_accessor::set_vacuum_mode_largest_root_page(pager, *value as u32)?;
         }
@@ -5571,6 +5940,55 @@ pub fn op_integrity_check(
     Ok(InsnFunctionStepResult::Step)
 }
 
+pub fn op_vacuum(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::Vacuum { .. } = insn else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    if mv_store.is_some() {
+        return Err(LimboError::TxError(
+            "VACUUM is not supported with MVCC".to_string(),
+        ));
+    }
+    crate::vacuum::vacuum(&program.connection)?;
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
+pub fn op_analyze(
+    program: &Program,
+    state: &mut ProgramState,
+    insn: &Insn,
+    _pager: &Rc<Pager>,
+    mv_store: Option<&Rc<MvStore>>,
+) -> Result<InsnFunctionStepResult> {
+    let Insn::Analyze {
+        table_name,
+        index_name,
+        ..
+    } = insn
+    else {
+        unreachable!("unexpected Insn {:?}", insn)
+    };
+    if mv_store.is_some() {
+        return Err(LimboError::TxError(
+            "ANALYZE is not supported with MVCC".to_string(),
+        ));
+    }
+    crate::analyze::analyze(
+        &program.connection,
+        table_name.as_deref(),
+        index_name.as_deref(),
+    )?;
+    state.pc += 1;
+    Ok(InsnFunctionStepResult::Step)
+}
+
 impl Value {
     pub fn exec_lower(&self) -> Option<Self> {
         match self {
@@ -5581,7 +5999,11 @@ impl Value {
 
     pub fn exec_length(&self) -> Self {
         match self {
-            Value::Text(_) | Value::Integer(_) | Value::Float(_) => {
+            // Like in SQLite, text is counted in characters up to the first NUL
+            Value::Text(text) => {
+                Value::Integer(text.as_str().chars().take_while(|&c| c != '\0').count() as i64)
+            }
+            Value::Integer(_) | Value::Float(_) => {
                 Value::Integer(self.to_string().chars().count() as i64)
             }
             Value::Blob(blob) => Value::Integer(blob.len() as i64),
@@ -5754,7 +6176,8 @@ impl Value {
                 }
             }
             Value::Null => Ok(Value::Null),
-            _ => Ok(Value::Float(0.0)),
+            // Text and blobs are converted to a number, which is 0.0 if they do not hold one
+            value => Ok(Value::Float(value._to_float().abs())),
         }
     }
 
@@ -5782,8 +6205,9 @@ impl Value {
     pub fn exec_quote(&self) -> Self {
         match self {
             Value::Null => Value::build_text("NULL"),
-            Value::Integer(_) | Value::Float(_) => self.to_owned(),
-            Value::Blob(_) => todo!(),
+            Value::Integer(i) => Value::build_text(i.to_string()),
+            Value::Float(f) => Value::build_text(quote_float(*f)),
+            Value::Blob(blob) => Value::build_text(format!("X'{}'", hex::encode_upper(blob))),
             Value::Text(s) => {
                 let mut quoted = String::with_capacity(s.as_str().len() + 2);
                 quoted.push('\'');
@@ -5816,31 +6240,29 @@ impl Value {
         start_value: &Value,
         length_value: Option<&Value>,
     ) -> Value {
-        if let (Value::Text(str), Value::Integer(start)) = (str_value, start_value) {
-            let str_len = str.as_str().len() as i64;
-
-            // The left-most character of X is number 1.
-            // If Y is negative then the first character of the substring is found by counting from the right rather than the left.
-            let first_position = if *start < 0 {
-                str_len.saturating_sub((*start).abs())
-            } else {
-                *start - 1
-            };
-            // If Z is negative then the abs(Z) characters preceding the Y-th character are returned.
-            let last_position = match length_value {
-                Some(Value::Integer(length)) => first_position + *length,
-                _ => str_len,
-            };
-            let (start, end) = if first_position <= last_position {
-                (first_position, last_position)
-            } else {
-                (last_position, first_position)
-            };
-            Value::build_text(
-                &str.as_str()[start.clamp(-0, str_len) as usize..end.clamp(0, str_len) as usize],
-            )
-        } else {
-            Value::Null
+        if matches!(str_value, Value::Null) || matches!(start_value, Value::Null) {
+            return Value::Null;
+        }
+        let as_integer = |value: &Value| match value.exec_cast("INTEGER") {
+            Value::Integer(i) => i,
+            _ => 0,
+        };
+        let start = as_integer(start_value);
+        let length = match length_value {
+            Some(Value::Null) | None => None,
+            Some(value) => Some(as_integer(value)),
+        };
+        // Positions count characters, except in a BLOB where they count bytes
+        match str_value {
+            Value::Blob(blob) => {
+                let (start, length) = substring_range(blob.len(), start, length);
+                Value::from_blob(blob.iter().skip(start).take(length).copied().collect())
+            }
+            value => {
+                let text = value.to_string();
+                let (start, length) = substring_range(text.chars().count(), start, length);
+                Value::build_text(text.chars().skip(start).take(length).collect::<String>())
+            }
         }
     }
 
@@ -5898,7 +6320,7 @@ impl Value {
                 Value::build_text(hex::encode_upper(text))
             }
             Value::Blob(blob_bytes) => Value::build_text(hex::encode_upper(blob_bytes)),
-            _ => Value::Null,
+            Value::Null => Value::build_text(""),
         }
     }
 
@@ -5944,12 +6366,14 @@ impl Value {
     }
 
     fn _to_float(&self) -> f64 {
+        let text_to_float = |text: &str| match cast_text_to_numeric(text) {
+            Value::Integer(i) => i as f64,
+            Value::Float(f) => f,
+            _ => unreachable!(),
+        };
         match self {
-            Value::Text(x) => match cast_text_to_numeric(x.as_str()) {
-                Value::Integer(i) => i as f64,
-                Value::Float(f) => f,
-                _ => unreachable!(),
-            },
+            Value::Text(x) => text_to_float(x.as_str()),
+            Value::Blob(b) => text_to_float(&String::from_utf8_lossy(b)),
             Value::Integer(x) => *x as f64,
             Value::Float(x) => *x,
             _ => 0.0,
@@ -5957,21 +6381,15 @@ impl Value {
     }
 
     pub fn exec_round(&self, precision: Option<&Value>) -> Value {
-        let reg = self._to_float();
-        let round = |reg: f64, f: f64| {
-            let precision = if f < 1.0 { 0.0 } else { f };
-            Value::Float(reg.round_to_precision(precision as i32))
+        let precision = match precision {
+            Some(Value::Null) => return Value::Null,
+            // Like in SQLite, the number of digits is truncated and clamped between 0 and 30
+            Some(precision) => precision._to_float().clamp(0.0, 30.0) as i32,
+            None => 0,
         };
-        match precision {
-            Some(Value::Text(x)) => match cast_text_to_numeric(x.as_str()) {
-                Value::Integer(i) => round(reg, i as f64),
-                Value::Float(f) => round(reg, f),
-                _ => unreachable!(),
-            },
-            Some(Value::Integer(i)) => round(reg, *i as f64),
-            Some(Value::Float(f)) => round(reg, *f),
-            None => round(reg, 0.0),
-            _ => Value::Null,
+        match self {
+            Value::Null => Value::Null,
+            reg => Value::Float(reg._to_float().round_to_precision(precision)),
         }
     }
 
@@ -6395,12 +6813,38 @@ impl Value {
         }
     }
 
+    /// `LIKE` with an `ESCAPE` character, which makes the wildcard following it literal
+    pub fn exec_like_with_escape(pattern: &str, text: &str, escape: char) -> bool {
+        exec_like_with_escape(pattern, text, escape)
+    }
+
+    /// Scalar `min(X, Y, ...)`, which is NULL as soon as one of the arguments is NULL.
+    /// Like SQLite, the rightmost of equal arguments wins.
     pub fn exec_min<'a, T: Iterator<Item = &'a Value>>(regs: T) -> Value {
-        regs.min().map(|v| v.to_owned()).unwrap_or(Value::Null)
+        Self::exec_min_max(regs, |ordering| ordering.is_le())
     }
 
+    /// Scalar `max(X, Y, ...)`, which is NULL as soon as one of the arguments is NULL.
+    /// Like SQLite, the leftmost of equal arguments wins.
     pub fn exec_max<'a, T: Iterator<Item = &'a Value>>(regs: T) -> Value {
-        regs.max().map(|v| v.to_owned()).unwrap_or(Value::Null)
+        Self::exec_min_max(regs, |ordering| ordering.is_gt())
+    }
+
+    /// Picks the argument for which `replaces` holds when comparing it to the best one so far
+    fn exec_min_max<'a, T: Iterator<Item = &'a Value>>(
+        regs: T,
+        replaces: fn(std::cmp::Ordering) -> bool,
+    ) -> Value {
+        let mut result: Option<&Value> = None;
+        for value in regs {
+            if matches!(value, Value::Null) {
+                return Value::Null;
+            }
+            if result.is_none_or(|current| replaces(value.cmp(current))) {
+                result = Some(value);
+            }
+        }
+        result.cloned().unwrap_or(Value::Null)
     }
 }
 
@@ -6456,6 +6900,56 @@ fn exec_char(values: &[Register]) -> Value {
     Value::build_text(result)
 }
 
+/// Renders a real like `quote()` does in SQLite, with 15 significant digits if they are enough to
+/// read the value back exactly, and in exponent notation with the shortest exact digits otherwise.
+/// Either way the literal has a decimal point, so that it is read back as a real.
+fn quote_float(f: f64) -> String {
+    if f.is_infinite() {
+        return if f > 0.0 { "9.0e+999" } else { "-9.0e+999" }.to_string();
+    }
+    if f == 0.0 {
+        return "0.0".to_string();
+    }
+    let precise = format!("{f:.14e}");
+    let round_trips = precise.parse::<f64>() == Ok(f);
+    let sci = if round_trips {
+        precise
+    } else {
+        format!("{f:e}")
+    };
+    let (mantissa, exponent) = sci.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    let digits = digits.trim_end_matches('0');
+
+    if !round_trips || !(-4..15).contains(&exponent) {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        return format!(
+            "{sign}{}.{fraction}e{exponent_sign}{:02}",
+            &digits[..1],
+            exponent.abs()
+        );
+    }
+    let (whole, fraction) = if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        ("0".to_string(), format!("{zeros}{digits}"))
+    } else {
+        let point = exponent as usize + 1;
+        if digits.len() > point {
+            (digits[..point].to_string(), digits[point..].to_string())
+        } else {
+            (format!("{digits:0<point$}"), String::new())
+        }
+    };
+    let fraction = if fraction.is_empty() { "0" } else { &fraction };
+    format!("{sign}{whole}.{fraction}")
+}
+
 fn construct_like_regex(pattern: &str) -> Regex {
     let mut regex_pattern = String::with_capacity(pattern.len() * 2);
 
@@ -6640,6 +7134,41 @@ enum NumericParseResult {
     ValidPrefixOnly, // valid prefix but not entire string
 }
 
+/// Resolves the start `Y` and length `Z` of `substr(X, Y, Z)` on a value of `len` characters
+/// into the number of characters to skip and the number to return, as SQLite does.
+fn substring_range(len: usize, start: i64, length: Option<i64>) -> (usize, usize) {
+    let len = len as i64;
+    // The left-most character of X is number 1. If Y is negative then the first character of
+    // the substring is found by counting from the right rather than the left.
+    let mut start = start;
+    // Without Z, the substring runs to the end of X. If Z is negative then the abs(Z)
+    // characters preceding the Y-th character are returned.
+    let (mut length, negative_length) = match length {
+        Some(length) => (length.saturating_abs(), length < 0),
+        None => (i64::MAX, false),
+    };
+    if start < 0 {
+        start = start.saturating_add(len);
+        if start < 0 {
+            length = length.saturating_add(start).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if length > 0 {
+        // Position 0 is the one before the first character, which is never returned
+        length -= 1;
+    }
+    if negative_length {
+        start -= length;
+        if start < 0 {
+            length += start;
+            start = 0;
+        }
+    }
+    (start.min(len) as usize, length.min(len) as usize)
+}
+
 #[derive(Debug)]
 enum ParsedNumber {
     None,
@@ -7406,6 +7935,20 @@ mod tests {
         let expected_blob = Value::Blob("example".as_bytes().to_vec());
         let expected_len = Value::Integer(7);
         assert_eq!(expected_blob.exec_length(), expected_len);
+
+        let input_multibyte = Value::build_text("żółw🐢");
+        let expected_len = Value::Integer(5);
+        assert_eq!(input_multibyte.exec_length(), expected_len);
+
+        let input_with_nul = Value::build_text("ab\0cd");
+        let expected_len = Value::Integer(2);
+        assert_eq!(input_with_nul.exec_length(), expected_len);
+
+        let blob_with_nul = Value::Blob(vec![0x61, 0x00, 0xc5, 0xbc]);
+        let expected_len = Value::Integer(4);
+        assert_eq!(blob_with_nul.exec_length(), expected_len);
+
+        assert_eq!(Value::Null.exec_length(), Value::Null);
     }
 
     #[test]
@@ -7415,7 +7958,27 @@ mod tests {
         assert_eq!(input.exec_quote(), expected);
 
         let input = Value::Integer(123);
-        let expected = Value::Integer(123);
+        let expected = Value::build_text("123");
+        assert_eq!(input.exec_quote(), expected);
+
+        let input = Value::Blob(vec![0x00, 0xff]);
+        let expected = Value::build_text("X'00FF'");
+        assert_eq!(input.exec_quote(), expected);
+
+        let input = Value::Float(1.5);
+        let expected = Value::build_text("1.5");
+        assert_eq!(input.exec_quote(), expected);
+
+        let input = Value::Float(100.0);
+        let expected = Value::build_text("100.0");
+        assert_eq!(input.exec_quote(), expected);
+
+        let input = Value::Float(1e-5);
+        let expected = Value::build_text("1.0e-05");
+        assert_eq!(input.exec_quote(), expected);
+
+        let input = Value::Float(-4503599627370497.0);
+        let expected = Value::build_text("-4.503599627370497e+15");
         assert_eq!(input.exec_quote(), expected);
 
         let input = Value::build_text("hello''world");
@@ -7511,6 +8074,33 @@ mod tests {
             Value::exec_max(input_mixed_vec.iter().map(|v| v.get_owned_value())),
             Value::build_text("A")
         );
+
+        let input_some_null_vec = [
+            Register::Value(Value::Integer(1)),
+            Register::Value(Value::Null),
+            Register::Value(Value::Integer(2)),
+        ];
+        assert_eq!(
+            Value::exec_min(input_some_null_vec.iter().map(|v| v.get_owned_value())),
+            Value::Null
+        );
+        assert_eq!(
+            Value::exec_max(input_some_null_vec.iter().map(|v| v.get_owned_value())),
+            Value::Null
+        );
+
+        let input_tie_vec = [
+            Register::Value(Value::Integer(1)),
+            Register::Value(Value::Float(1.0)),
+        ];
+        assert_eq!(
+            Value::exec_min(input_tie_vec.iter().map(|v| v.get_owned_value())),
+            Value::Float(1.0)
+        );
+        assert_eq!(
+            Value::exec_max(input_tie_vec.iter().map(|v| v.get_owned_value())),
+            Value::Integer(1)
+        );
     }
 
     #[test]
@@ -7640,6 +8230,9 @@ mod tests {
         let input_blob = Value::Blob(vec![0xff]);
         let expected_val = Value::build_text("FF");
         assert_eq!(input_blob.exec_hex(), expected_val);
+
+        let expected_val = Value::build_text("");
+        assert_eq!(Value::Null.exec_hex(), expected_val);
     }
 
     #[test]
@@ -7689,6 +8282,15 @@ mod tests {
 
         // ABS(i64::MIN) should return RuntimeError
         assert!(Value::Integer(i64::MIN).exec_abs().is_err());
+
+        assert_eq!(
+            Value::build_text("-5").exec_abs().unwrap(),
+            Value::Float(5.0)
+        );
+        assert_eq!(
+            Value::Blob(b"-2.5".to_vec()).exec_abs().unwrap(),
+            Value::Float(2.5)
+        );
     }
 
     #[test]
@@ -7851,6 +8453,44 @@ mod tests {
         let input_val = Value::Float(100.123);
         let expected_val = Value::Null;
         assert_eq!(input_val.exec_round(Some(&Value::Null)), expected_val);
+
+        assert_eq!(Value::Null.exec_round(None), Value::Null);
+
+        // Halves are rounded away from zero
+        for (input, expected) in [(0.5, 1.0), (2.5, 3.0), (-2.5, -3.0), (-0.5, -1.0)] {
+            assert_eq!(Value::Float(input).exec_round(None), Value::Float(expected));
+        }
+
+        // Decimal values that are stored slightly below their halves are rounded down
+        for (input, precision, expected) in [
+            (2.675, 2, 2.67),
+            (-2.675, 2, -2.67),
+            (1.005, 2, 1.0),
+            (1.15, 1, 1.1),
+            (2.625, 2, 2.63),
+            (0.05, 1, 0.1),
+            (0.0006, 2, 0.0),
+        ] {
+            let precision = Value::Integer(precision);
+            assert_eq!(
+                Value::Float(input).exec_round(Some(&precision)),
+                Value::Float(expected)
+            );
+        }
+
+        let input_val = Value::Float(1.5);
+        assert_eq!(
+            input_val.exec_round(Some(&Value::Integer(1000))),
+            Value::Float(1.5)
+        );
+        assert_eq!(
+            input_val.exec_round(Some(&Value::Float(0.9))),
+            Value::Float(2.0)
+        );
+        assert_eq!(
+            Value::Float(1e300).exec_round(Some(&Value::Integer(2))),
+            Value::Float(1e300)
+        );
     }
 
     #[test]
@@ -7951,6 +8591,41 @@ mod tests {
             Value::exec_substring(&str_value, &start_value, Some(&length_value)),
             expected_val
         );
+
+        let str_value = Value::build_text("limbo");
+        let start_value = Value::Integer(-3);
+        let expected_val = Value::build_text("mbo");
+        assert_eq!(
+            Value::exec_substring(&str_value, &start_value, None),
+            expected_val
+        );
+
+        let str_value = Value::build_text("limbo");
+        let start_value = Value::Integer(4);
+        let length_value = Value::Integer(-2);
+        let expected_val = Value::build_text("im");
+        assert_eq!(
+            Value::exec_substring(&str_value, &start_value, Some(&length_value)),
+            expected_val
+        );
+
+        let str_value = Value::build_text("héllo wörld");
+        let start_value = Value::Integer(-5);
+        let length_value = Value::Integer(2);
+        let expected_val = Value::build_text("wö");
+        assert_eq!(
+            Value::exec_substring(&str_value, &start_value, Some(&length_value)),
+            expected_val
+        );
+
+        let str_value = Value::Blob(vec![1, 2, 3, 4]);
+        let start_value = Value::Integer(0);
+        let length_value = Value::Integer(3);
+        let expected_val = Value::Blob(vec![1, 2]);
+        assert_eq!(
+            Value::exec_substring(&str_value, &start_value, Some(&length_value)),
+            expected_val
+        );
     }
 
     #[test]
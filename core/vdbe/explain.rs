@@ -1,6 +1,9 @@
 use turso_sqlite3_parser::ast::SortOrder;
 
-use crate::vdbe::{builder::CursorType, insn::RegisterOrLiteral};
+use crate::vdbe::{
+    builder::CursorType,
+    insn::{RegisterOrLiteral, SavepointOp},
+};
 
 use super::{Insn, InsnReference, Program, Value};
 use crate::function::{Func, ScalarFunc};
@@ -218,6 +221,24 @@ pub fn insn_to_str(
                     target_pc.as_debug_int()
                 ),
             ),
+            Insn::FkCounter { increment_value } => (
+                "FkCounter",
+                0,
+                *increment_value as i32,
+                0,
+                Value::build_text(""),
+                0,
+                format!("fk_counter+={}", increment_value),
+            ),
+            Insn::FkIfZero { target_pc } => (
+                "FkIfZero",
+                0,
+                target_pc.as_debug_int(),
+                0,
+                Value::build_text(""),
+                0,
+                format!("if fk_counter==0 goto {}", target_pc.as_debug_int()),
+            ),
             Insn::Eq {
                 lhs,
                 rhs,
@@ -532,7 +553,13 @@ pub fn insn_to_str(
                 let cursor_type = &program.cursor_ref[*cursor_id].1;
                 let column_name: Option<&String> = match cursor_type {
                     CursorType::BTreeTable(table) => {
-                        let name = table.columns.get(*column).and_then(|v| v.name.as_ref());
+                        // `column` is a record position, which skips the VIRTUAL columns
+                        let name = table
+                            .columns
+                            .iter()
+                            .filter(|c| !c.is_virtual())
+                            .nth(*column)
+                            .and_then(|v| v.name.as_ref());
                         name
                     }
                     CursorType::BTreeIndex(index) => {
@@ -936,6 +963,25 @@ pub fn insn_to_str(
                 0,
                 format!("accum=r[{}] step(r[{}])", *acc_reg, *col),
             ),
+            Insn::AggOrderKey {
+                acc_reg,
+                key_start_reg,
+                order,
+                ..
+            } => (
+                "AggOrderKey",
+                *acc_reg as i32,
+                *key_start_reg as i32,
+                order.len() as i32,
+                Value::build_text(""),
+                0,
+                format!(
+                    "accum=r[{}] key=r[{}..{}]",
+                    *acc_reg,
+                    *key_start_reg,
+                    *key_start_reg + order.len() - 1
+                ),
+            ),
             Insn::AggFinal { register, func } => (
                 "AggFinal",
                 0,
@@ -1160,6 +1206,15 @@ pub fn insn_to_str(
                 0,
                 "".to_string(),
             ),
+            Insn::MemMax { dest_reg, src_reg } => (
+                "MemMax",
+                *dest_reg as i32,
+                *src_reg as i32,
+                0,
+                Value::build_text(""),
+                0,
+                format!("r[{}]=max(r[{}],r[{}])", dest_reg, dest_reg, src_reg),
+            ),
             Insn::NoConflict {
                 cursor_id,
                 target_pc,
@@ -1288,6 +1343,15 @@ pub fn insn_to_str(
                 0,
                 format!("DROP INDEX {}", index.name),
             ),
+            Insn::DropTrigger { db, trigger_name } => (
+                "DropTrigger",
+                *db as i32,
+                0,
+                0,
+                Value::build_text(trigger_name),
+                0,
+                format!("DROP TRIGGER {}", trigger_name),
+            ),
             Insn::Close { cursor_id } => (
                 "Close",
                 *cursor_id as i32,
@@ -1459,6 +1523,19 @@ pub fn insn_to_str(
                 0,
                 format!("auto_commit={}, rollback={}", auto_commit, rollback),
             ),
+            Insn::Savepoint { op, name } => (
+                "Savepoint",
+                match op {
+                    SavepointOp::Begin => 0,
+                    SavepointOp::Release => 1,
+                    SavepointOp::RollbackTo => 2,
+                },
+                0,
+                0,
+                Value::build_text(name),
+                0,
+                format!("{:?} {}", op, name),
+            ),
             Insn::OpenEphemeral {
                 cursor_id,
                 is_table,
@@ -1609,6 +1686,33 @@ pub fn insn_to_str(
                 0,
                 format!("r[{}] = data", *dest),
             ),
+            Insn::Vacuum { db } => (
+                "Vacuum",
+                *db as i32,
+                0,
+                0,
+                Value::build_text(""),
+                0,
+                "".to_string(),
+            ),
+            Insn::Analyze {
+                db,
+                table_name,
+                index_name,
+            } => (
+                "Analyze",
+                *db as i32,
+                0,
+                0,
+                Value::build_text(
+                    index_name
+                        .as_deref()
+                        .or(table_name.as_deref())
+                        .unwrap_or(""),
+                ),
+                0,
+                "".to_string(),
+            ),
         };
     format!(
         "{:<4}  {:<17}  {:<4}  {:<4}  {:<4}  {:<13}  {:<2}  {}",
@@ -100,6 +100,7 @@ pub struct InsertFlags(pub u8);
 
 impl InsertFlags {
     pub const UPDATE: u8 = 0x01; // Flag indicating this is part of an UPDATE statement
+    pub const SKIP_LAST_ROWID: u8 = 0x02; // Flag indicating the row counts neither as a change nor as the last inserted rowid
 
     pub fn new() -> Self {
         InsertFlags(0)
@@ -117,6 +118,11 @@ impl InsertFlags {
         }
         self
     }
+
+    pub fn skip_last_rowid(mut self) -> Self {
+        self.0 |= InsertFlags::SKIP_LAST_ROWID;
+        self
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -239,6 +245,15 @@ pub enum Insn {
         target_pc: BranchOffset,
         decrement_by: usize,
     },
+    /// Add `increment_value`, which may be negative, to the statement's count of foreign key
+    /// constraint violations. The statement fails when it ends with a positive count.
+    FkCounter {
+        increment_value: i64,
+    },
+    /// If the statement's count of foreign key constraint violations is zero, jump to the given PC.
+    FkIfZero {
+        target_pc: BranchOffset,
+    },
     /// If the given register is not NULL, jump to the given PC.
     NotNull {
         reg: usize,
@@ -462,6 +477,12 @@ pub enum Insn {
         rollback: bool,
     },
 
+    /// Open, release or roll back to the savepoint with the given name.
+    Savepoint {
+        op: SavepointOp,
+        name: String,
+    },
+
     /// Branch to the given PC.
     Goto {
         target_pc: BranchOffset,
@@ -653,6 +674,15 @@ pub enum Insn {
         func: AggFunc,
     },
 
+    /// Sets the `ORDER BY` keys of the row the next AggStep on `acc_reg` is given. The aggregate
+    /// then buffers its rows instead of combining them, and AggFinal steps through them sorted.
+    AggOrderKey {
+        acc_reg: usize,
+        key_start_reg: usize,
+        order: Vec<SortOrder>,
+        collations: Vec<CollationSeq>,
+    },
+
     AggFinal {
         register: usize,
         func: AggFunc,
@@ -742,7 +772,7 @@ pub enum Insn {
     NewRowid {
         cursor: CursorID,        // P1
         rowid_reg: usize,        // P2  Destination register to store the new rowid
-        prev_largest_reg: usize, // P3 Previous largest rowid in the table (Not used for now)
+        prev_largest_reg: usize, // P3 Largest rowid ever used by an AUTOINCREMENT table, or 0
     },
 
     MustBeInt {
@@ -753,6 +783,14 @@ pub enum Insn {
         reg: usize,
     },
 
+    /// Set the value of register P1 to the maximum of its current value and the value in register P2.
+    ///
+    /// This instruction throws an error if the memory cell is not initially an integer.
+    MemMax {
+        dest_reg: usize, // P1
+        src_reg: usize,  // P2
+    },
+
     /// If P4==0 then register P3 holds a blob constructed by [MakeRecord](https://sqlite.org/opcode.html#MakeRecord). If P4>0 then register P3 is the first of P4 registers that form an unpacked record.\
     ///
     /// Cursor P1 is on an index btree. If the record identified by P3 and P4 contains any NULL value, jump immediately to P2. If all terms of the record are not-NULL then a check is done to determine if any row in the P1 index btree has a matching key prefix. If there are no matches, jump immediately to P2. If there is a match, fall through and leave the P1 cursor pointing to the matching row.\
@@ -826,6 +864,13 @@ pub enum Insn {
         //  The name of the index being dropped
         index: Arc<Index>,
     },
+    /// Remove the trigger from the in-memory schema, once its entry in the schema table is deleted.
+    DropTrigger {
+        ///  The database within which this trigger needs to be dropped (P1).
+        db: usize,
+        //  The name of the trigger being dropped
+        trigger_name: String,
+    },
 
     /// Close a cursor.
     Close {
@@ -978,6 +1023,19 @@ pub enum Insn {
         roots: Vec<usize>,
         message_register: usize,
     },
+
+    /// Rebuild database P1 from scratch, leaving it without free pages or fragmentation.
+    Vacuum {
+        db: usize,
+    },
+
+    /// Gather the statistics of table P4 of database P1, or of all its tables if P4 is empty,
+    /// into sqlite_stat1. When an index is named, only the statistics of that index are gathered.
+    Analyze {
+        db: usize,
+        table_name: Option<String>,
+        index_name: Option<String>,
+    },
 }
 
 impl Insn {
@@ -992,6 +1050,8 @@ impl Insn {
             Insn::Multiply { .. } => execute::op_multiply,
             Insn::Divide { .. } => execute::op_divide,
             Insn::DropIndex { .. } => execute::op_drop_index,
+
+            Insn::DropTrigger { .. } => execute::op_drop_trigger,
             Insn::Compare { .. } => execute::op_compare,
             Insn::BitAnd { .. } => execute::op_bit_and,
             Insn::BitOr { .. } => execute::op_bit_or,
@@ -1001,6 +1061,8 @@ impl Insn {
             Insn::Jump { .. } => execute::op_jump,
             Insn::Move { .. } => execute::op_move,
             Insn::IfPos { .. } => execute::op_if_pos,
+            Insn::FkCounter { .. } => execute::op_fk_counter,
+            Insn::FkIfZero { .. } => execute::op_fk_if_zero,
             Insn::NotNull { .. } => execute::op_not_null,
             Insn::Eq { .. }
             | Insn::Ne { .. }
@@ -1032,6 +1094,7 @@ impl Insn {
             Insn::HaltIfNull { .. } => execute::op_halt_if_null,
             Insn::Transaction { .. } => execute::op_transaction,
             Insn::AutoCommit { .. } => execute::op_auto_commit,
+            Insn::Savepoint { .. } => execute::op_savepoint,
             Insn::Goto { .. } => execute::op_goto,
             Insn::Gosub { .. } => execute::op_gosub,
             Insn::Return { .. } => execute::op_return,
@@ -1056,6 +1119,7 @@ impl Insn {
             Insn::IdxLT { .. } => execute::op_idx_lt,
             Insn::DecrJumpZero { .. } => execute::op_decr_jump_zero,
             Insn::AggStep { .. } => execute::op_agg_step,
+            Insn::AggOrderKey { .. } => execute::op_agg_order_key,
             Insn::AggFinal { .. } => execute::op_agg_final,
             Insn::SorterOpen { .. } => execute::op_sorter_open,
             Insn::SorterInsert { .. } => execute::op_sorter_insert,
@@ -1073,6 +1137,7 @@ impl Insn {
             Insn::NewRowid { .. } => execute::op_new_rowid,
             Insn::MustBeInt { .. } => execute::op_must_be_int,
             Insn::SoftNull { .. } => execute::op_soft_null,
+            Insn::MemMax { .. } => execute::op_mem_max,
             Insn::NoConflict { .. } => execute::op_no_conflict,
             Insn::NotExists { .. } => execute::op_not_exists,
             Insn::OffsetLimit { .. } => execute::op_offset_limit,
@@ -1104,10 +1169,22 @@ impl Insn {
             Insn::IdxDelete { .. } => execute::op_idx_delete,
             Insn::Count { .. } => execute::op_count,
             Insn::IntegrityCk { .. } => execute::op_integrity_check,
+            Insn::Vacuum { .. } => execute::op_vacuum,
+            Insn::Analyze { .. } => execute::op_analyze,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SavepointOp {
+    /// `SAVEPOINT name`
+    Begin,
+    /// `RELEASE name`
+    Release,
+    /// `ROLLBACK TO name`
+    RollbackTo,
+}
+
 // TODO: Add remaining cookies.
 #[derive(Description, Debug, Clone, Copy)]
 pub enum Cookie {
@@ -1125,4 +1202,6 @@ pub enum Cookie {
     UserVersion = 6,
     /// The auto-vacuum mode setting.
     IncrementalVacuum = 7,
+    /// The "Application ID" as read and set by the application_id pragma.
+    ApplicationId = 8,
 }
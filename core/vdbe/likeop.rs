@@ -4,29 +4,30 @@ use regex::{Regex, RegexBuilder};
 
 use crate::{types::Value, LimboError};
 
-pub fn construct_like_escape_arg(escape_value: &Value) -> Result<char, LimboError> {
-    match escape_value {
-        Value::Text(text) => {
-            let mut escape_chars = text.as_str().chars();
-            match (escape_chars.next(), escape_chars.next()) {
-                (Some(escape), None) => Ok(escape),
-                _ => Err(LimboError::Constraint(
-                    "ESCAPE expression must be a single character".to_string(),
-                )),
-            }
-        }
-        _ => {
-            unreachable!("Like on non-text registers");
-        }
+/// Returns the escape character of a `LIKE ... ESCAPE` expression, or `None` if it is NULL, in
+/// which case the whole expression is NULL.
+pub fn construct_like_escape_arg(escape_value: &Value) -> Result<Option<char>, LimboError> {
+    let text = match escape_value {
+        Value::Null => return Ok(None),
+        Value::Text(text) => text.as_str().to_string(),
+        value => value.to_string(),
+    };
+    let mut escape_chars = text.chars();
+    match (escape_chars.next(), escape_chars.next()) {
+        (Some(escape), None) => Ok(Some(escape)),
+        _ => Err(LimboError::Constraint(
+            "ESCAPE expression must be a single character".to_string(),
+        )),
     }
 }
 
 // Implements LIKE pattern matching with escape
 pub fn exec_like_with_escape(pattern: &str, text: &str, escape: char) -> bool {
-    construct_like_regex_with_escape(pattern, escape).is_match(text)
+    construct_like_regex_with_escape(pattern, escape).is_some_and(|re| re.is_match(text))
 }
 
-fn construct_like_regex_with_escape(pattern: &str, escape: char) -> Regex {
+/// Returns `None` for a pattern ending with the escape character, which matches nothing.
+fn construct_like_regex_with_escape(pattern: &str, escape: char) -> Option<Regex> {
     let mut regex_pattern = String::with_capacity(pattern.len() * 2);
 
     regex_pattern.push('^');
@@ -36,12 +37,11 @@ fn construct_like_regex_with_escape(pattern: &str, escape: char) -> Regex {
     while let Some(ch) = chars.next() {
         match ch {
             esc_ch if esc_ch == escape => {
-                if let Some(escaped_char) = chars.next() {
-                    if regex_syntax::is_meta_character(escaped_char) {
-                        regex_pattern.push('\\');
-                    }
-                    regex_pattern.push(escaped_char);
+                let escaped_char = chars.next()?;
+                if regex_syntax::is_meta_character(escaped_char) {
+                    regex_pattern.push('\\');
                 }
+                regex_pattern.push(escaped_char);
             }
             '%' => regex_pattern.push_str(".*"),
             '_' => regex_pattern.push('.'),
@@ -56,11 +56,13 @@ fn construct_like_regex_with_escape(pattern: &str, escape: char) -> Regex {
 
     regex_pattern.push('$');
 
-    RegexBuilder::new(&regex_pattern)
-        .case_insensitive(true)
-        .dot_matches_new_line(true)
-        .build()
-        .unwrap()
+    Some(
+        RegexBuilder::new(&regex_pattern)
+            .case_insensitive(true)
+            .dot_matches_new_line(true)
+            .build()
+            .unwrap(),
+    )
 }
 
 // Implements GLOB pattern matching. Caches the constructed regex if a cache is provided
@@ -88,6 +90,34 @@ pub fn exec_glob(
     }
 }
 
+// Implements the REGEXP operator with the regex crate syntax. Unlike LIKE and GLOB the pattern is
+// not anchored, so `^` and `$` must be used to match the whole text.
+// Caches the compiled regex if a cache is provided
+#[cfg(feature = "regexp")]
+pub fn exec_regexp(
+    regex_cache: Option<&mut HashMap<String, Regex>>,
+    pattern: &str,
+    text: &str,
+) -> Result<bool, LimboError> {
+    let compile = |pattern: &str| {
+        Regex::new(pattern).map_err(|e| {
+            LimboError::InvalidArgument(format!("invalid regular expression: {pattern}: {e}"))
+        })
+    };
+    match regex_cache {
+        Some(cache) => {
+            if let Some(re) = cache.get(pattern) {
+                return Ok(re.is_match(text));
+            }
+            let re = compile(pattern)?;
+            let res = re.is_match(text);
+            cache.insert(pattern.to_string(), re);
+            Ok(res)
+        }
+        None => Ok(compile(pattern)?.is_match(text)),
+    }
+}
+
 fn push_char_to_regex_pattern(c: char, regex_pattern: &mut String) {
     if regex_syntax::is_meta_character(c) {
         regex_pattern.push('\\');
@@ -201,6 +231,28 @@ mod test {
         assert!(!exec_like_with_escape("abcXX", "abc5", 'X'));
         assert!(!exec_like_with_escape("abcXX", "abc", 'X'));
         assert!(!exec_like_with_escape("abcXX", "abcXX", 'X'));
+        assert!(exec_like_with_escape("50\\%%", "50% off", '\\'));
+        assert!(!exec_like_with_escape("50\\%%", "500 off", '\\'));
+        assert!(exec_like_with_escape("%/a\\_b/%", "/srv/a_b/c", '\\'));
+        assert!(!exec_like_with_escape("%/a\\_b/%", "/srv/axb/c", '\\'));
+        // A trailing escape character has nothing to escape and matches nothing
+        assert!(!exec_like_with_escape("abcX", "abc", 'X'));
+        assert!(!exec_like_with_escape("abcX", "abcX", 'X'));
+    }
+
+    #[test]
+    fn test_construct_like_escape_arg() {
+        assert_eq!(
+            construct_like_escape_arg(&Value::build_text("\\")).unwrap(),
+            Some('\\')
+        );
+        assert_eq!(
+            construct_like_escape_arg(&Value::Integer(1)).unwrap(),
+            Some('1')
+        );
+        assert_eq!(construct_like_escape_arg(&Value::Null).unwrap(), None);
+        assert!(construct_like_escape_arg(&Value::build_text("ab")).is_err());
+        assert!(construct_like_escape_arg(&Value::build_text("")).is_err());
     }
 
     #[test]
@@ -214,4 +266,15 @@ mod test {
         assert!(exec_glob(None, r#"abc[^][*?]efg"#, r#"abcdefg"#));
         assert!(!exec_glob(None, r#"abc[^][*?]efg"#, r#"abc]efg"#));
     }
+
+    #[test]
+    #[cfg(feature = "regexp")]
+    fn test_exec_regexp() {
+        assert!(exec_regexp(None, "^a", "alice@example.com").unwrap());
+        assert!(!exec_regexp(None, "^a", "bob@example.com").unwrap());
+        assert!(exec_regexp(None, "b", "abc").unwrap());
+        assert!(exec_regexp(None, "^[0-9]+$", "12345").unwrap());
+        assert!(!exec_regexp(None, "^[0-9]+$", "123a5").unwrap());
+        assert!(exec_regexp(None, "(", "abc").is_err());
+    }
 }
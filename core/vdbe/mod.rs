@@ -168,6 +168,8 @@ macro_rules! return_if_io {
 struct RegexCache {
     like: HashMap<String, Regex>,
     glob: HashMap<String, Regex>,
+    #[cfg(feature = "regexp")]
+    regexp: HashMap<String, Regex>,
 }
 
 impl RegexCache {
@@ -175,6 +177,8 @@ impl RegexCache {
         Self {
             like: HashMap::new(),
             glob: HashMap::new(),
+            #[cfg(feature = "regexp")]
+            regexp: HashMap::new(),
         }
     }
 }
@@ -237,12 +241,16 @@ pub struct ProgramState {
     pub(crate) result_row: Option<Row>,
     last_compare: Option<std::cmp::Ordering>,
     deferred_seeks: Vec<Option<(CursorID, CursorID)>>,
+    /// Foreign key constraint violations of the statement not resolved yet, see [Insn::FkCounter]
+    fk_constraint_counter: i64,
     ended_coroutine: Bitfield<4>, // flag to indicate that a coroutine has ended (key is the yield register. currently we assume that the yield register is always between 0-255, YOLO)
     /// Indicate whether an [Insn::Once] instruction at a given program counter position has already been executed, well, once.
     once: SmallVec<u32, 4>,
     regex_cache: RegexCache,
     pub(crate) mv_tx_id: Option<crate::mvcc::database::TxID>,
     interrupted: bool,
+    /// Instructions executed since the progress handler was last invoked
+    progress_steps: u64,
     parameters: HashMap<NonZero<usize>, Value>,
     commit_state: CommitState,
     #[cfg(feature = "json")]
@@ -264,11 +272,13 @@ impl ProgramState {
             result_row: None,
             last_compare: None,
             deferred_seeks: vec![None; max_cursors],
+            fk_constraint_counter: 0,
             ended_coroutine: Bitfield::new(),
             once: SmallVec::<u32, 4>::new(),
             regex_cache: RegexCache::new(),
             mv_tx_id: None,
             interrupted: false,
+            progress_steps: 0,
             parameters: HashMap::new(),
             commit_state: CommitState::Ready,
             #[cfg(feature = "json")]
@@ -311,9 +321,11 @@ impl ProgramState {
             .for_each(|r| *r = Register::Value(Value::Null));
         self.last_compare = None;
         self.deferred_seeks.iter_mut().for_each(|s| *s = None);
+        self.fk_constraint_counter = 0;
         self.ended_coroutine.0 = [0; 4];
         self.regex_cache.like.clear();
         self.interrupted = false;
+        self.progress_steps = 0;
         self.parameters.clear();
         #[cfg(feature = "json")]
         self.json_cache.clear()
@@ -363,8 +375,12 @@ pub struct Program {
     pub connection: Arc<Connection>,
     pub n_change: Cell<i64>,
     pub change_cnt_on: bool,
+    /// Whether a constraint violation rolls back the whole transaction, as `OR ROLLBACK` asks
+    pub rollback_on_constraint: bool,
     pub result_columns: Vec<ResultSetColumn>,
     pub table_references: TableReferences,
+    /// Text of the statement the program was compiled from
+    pub sql: String,
 }
 
 impl Program {
@@ -374,15 +390,38 @@ impl Program {
         mv_store: Option<Rc<MvStore>>,
         pager: Rc<Pager>,
     ) -> Result<StepResult> {
+        let progress_interval = self.connection.progress_interval();
         loop {
             if state.is_interrupted() {
                 return Ok(StepResult::Interrupt);
             }
+            if progress_interval > 0 {
+                state.progress_steps += 1;
+                if state.progress_steps >= progress_interval {
+                    state.progress_steps = 0;
+                    if self.connection.invoke_progress_handler() {
+                        state.interrupt();
+                        return Ok(StepResult::Interrupt);
+                    }
+                }
+            }
             // invalidate row
             let _ = state.result_row.take();
             let (insn, insn_function) = &self.insns[state.pc as usize];
             trace_insn(self, state.pc as InsnReference, insn);
-            let res = insn_function(self, state, insn, &pager, mv_store.as_ref())?;
+            let res = match insn_function(self, state, insn, &pager, mv_store.as_ref()) {
+                // Foreign keys are not subject to the conflict resolution, so they only abort
+                Err(
+                    err @ (LimboError::Constraint(_)
+                    | LimboError::NotNullConstraint(_)
+                    | LimboError::CheckConstraint(_)
+                    | LimboError::UniqueConstraint(_)),
+                ) if self.rollback_on_constraint => {
+                    self.rollback_txn(&pager)?;
+                    return Err(err);
+                }
+                res => res?,
+            };
             match res {
                 InsnFunctionStepResult::Step => {}
                 InsnFunctionStepResult::Done => return Ok(StepResult::Done),
@@ -394,6 +433,33 @@ impl Program {
         }
     }
 
+    /// Rolls back the explicit transaction open on the connection, if any. Outside of one, the
+    /// failed statement is only aborted.
+    fn rollback_txn(&self, pager: &Pager) -> Result<()> {
+        let conn = &self.connection;
+        if conn.auto_commit.get() {
+            return Ok(());
+        }
+        conn.savepoints.borrow_mut().clear();
+        match conn.transaction_state.get() {
+            TransactionState::Write { change_schema } => {
+                pager.rollback(change_schema, conn)?;
+                pager.end_tx(
+                    true,
+                    change_schema,
+                    conn,
+                    conn.wal_checkpoint_disabled.get(),
+                )?;
+            }
+            TransactionState::Read => pager.end_read_tx()?,
+            TransactionState::None => {}
+        }
+        conn.end_temp_tx(true)?;
+        conn.transaction_state.replace(TransactionState::None);
+        conn.auto_commit.replace(true);
+        Ok(())
+    }
+
     #[instrument(skip_all, level = Level::TRACE)]
     pub fn commit_txn(
         &self,
@@ -447,9 +513,13 @@ impl Program {
                     TransactionState::Read => {
                         connection.transaction_state.replace(TransactionState::None);
                         pager.end_read_tx()?;
+                        connection.end_temp_tx(rollback)?;
+                        Ok(StepResult::Done)
+                    }
+                    TransactionState::None => {
+                        connection.end_temp_tx(rollback)?;
                         Ok(StepResult::Done)
                     }
-                    TransactionState::None => Ok(StepResult::Done),
                 }
             } else {
                 if self.change_cnt_on {
@@ -480,6 +550,7 @@ impl Program {
                 if self.change_cnt_on {
                     self.connection.set_changes(self.n_change.get());
                 }
+                connection.end_temp_tx(rollback)?;
                 connection.transaction_state.replace(TransactionState::None);
                 *commit_state = CommitState::Ready;
             }
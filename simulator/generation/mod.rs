@@ -4,6 +4,7 @@ use anarchist_readable_name_generator_lib::readable_name_custom;
 use rand::{distributions::uniform::SampleUniform, Rng};
 
 mod expr;
+mod mutation;
 pub mod plan;
 mod predicate;
 pub mod property;
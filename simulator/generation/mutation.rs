@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+
+use rand::{seq::SliceRandom, Rng};
+use turso_sqlite3_parser::ast;
+
+use crate::{
+    generation::{pick_index, plan::InteractionPlan, plan::Interactions},
+    model::query::{predicate::Predicate, update::Update, Delete, Query, Select},
+};
+
+type Operator<R> = fn(&mut InteractionPlan, &[HashSet<String>], &mut R) -> bool;
+
+impl InteractionPlan {
+    /// Returns a neighbor of the plan, to explore the plans around it rather than generating new
+    /// ones. One mutation is applied, picked among:
+    /// - moving an interaction past the ones next to it that use none of its tables
+    /// - duplicating a query
+    /// - tweaking a constant in the predicate of a query
+    ///
+    /// Only standalone queries are duplicated or tweaked, as the assertions of a property rely on
+    /// its queries being the ones it was generated with. The same `rng` always gives the same
+    /// neighbor, and a plan no mutation applies to is returned as is.
+    pub(crate) fn mutate<R: Rng>(&self, rng: &mut R) -> InteractionPlan {
+        let mut plan = self.clone();
+        let tables = plan.plan.iter().map(tables).collect::<Vec<_>>();
+        let mut operators: Vec<Operator<R>> =
+            vec![move_interaction, duplicate_query, tweak_predicate];
+        operators.shuffle(rng);
+        for operator in operators {
+            if operator(&mut plan, &tables, rng) {
+                break;
+            }
+        }
+        plan
+    }
+}
+
+/// Tables an interaction depends on or uses. Interactions without any, like faults, pragmas and
+/// savepoints, affect the whole database.
+fn tables(interactions: &Interactions) -> HashSet<String> {
+    let mut tables = interactions.dependencies();
+    tables.extend(interactions.uses());
+    tables
+}
+
+fn independent(a: &HashSet<String>, b: &HashSet<String>) -> bool {
+    !a.is_empty() && !b.is_empty() && a.is_disjoint(b)
+}
+
+/// Moves an interaction before or after the interactions around it it is independent of.
+fn move_interaction<R: Rng>(
+    plan: &mut InteractionPlan,
+    tables: &[HashSet<String>],
+    rng: &mut R,
+) -> bool {
+    let plan = &mut plan.plan;
+    let movable = (0..plan.len())
+        .filter(|&i| {
+            (i > 0 && independent(&tables[i], &tables[i - 1]))
+                || (i + 1 < plan.len() && independent(&tables[i], &tables[i + 1]))
+        })
+        .collect::<Vec<_>>();
+    if movable.is_empty() {
+        return false;
+    }
+    let from = movable[pick_index(movable.len(), rng)];
+    let mut first = from;
+    while first > 0 && independent(&tables[from], &tables[first - 1]) {
+        first -= 1;
+    }
+    let mut last = from;
+    while last + 1 < plan.len() && independent(&tables[from], &tables[last + 1]) {
+        last += 1;
+    }
+    let to = loop {
+        let to = rng.gen_range(first..=last);
+        if to != from {
+            break to;
+        }
+    };
+    let interactions = plan.remove(from);
+    plan.insert(to, interactions);
+    true
+}
+
+/// Runs a standalone query a second time, right after itself.
+fn duplicate_query<R: Rng>(
+    plan: &mut InteractionPlan,
+    _tables: &[HashSet<String>],
+    rng: &mut R,
+) -> bool {
+    let plan = &mut plan.plan;
+    let duplicable = plan
+        .iter()
+        .enumerate()
+        .filter(|(_, interactions)| {
+            matches!(
+                interactions,
+                Interactions::Query(
+                    Query::Select(_) | Query::Insert(_) | Query::Update(_) | Query::Delete(_)
+                )
+            )
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if duplicable.is_empty() {
+        return false;
+    }
+    let i = duplicable[pick_index(duplicable.len(), rng)];
+    plan.insert(i + 1, plan[i].clone());
+    true
+}
+
+/// Changes one of the numeric or text literals in the predicate of a standalone query.
+fn tweak_predicate<R: Rng>(
+    plan: &mut InteractionPlan,
+    _tables: &[HashSet<String>],
+    rng: &mut R,
+) -> bool {
+    let mut literals = plan
+        .plan
+        .iter_mut()
+        .filter_map(|interactions| match interactions {
+            Interactions::Query(
+                Query::Select(Select { predicate, .. })
+                | Query::Delete(Delete { predicate, .. })
+                | Query::Update(Update { predicate, .. }),
+            ) => Some(predicate),
+            _ => None,
+        })
+        .flat_map(|Predicate(expr)| {
+            let mut literals = Vec::new();
+            literals_mut(expr, &mut literals);
+            literals
+        })
+        .collect::<Vec<_>>();
+    if literals.is_empty() {
+        return false;
+    }
+    let i = pick_index(literals.len(), rng);
+    tweak_literal(literals.swap_remove(i), rng);
+    true
+}
+
+fn literals_mut<'a>(expr: &'a mut ast::Expr, literals: &mut Vec<&'a mut ast::Literal>) {
+    match expr {
+        ast::Expr::Literal(literal @ (ast::Literal::Numeric(_) | ast::Literal::String(_))) => {
+            literals.push(literal)
+        }
+        ast::Expr::Binary(lhs, _, rhs) => {
+            literals_mut(lhs, literals);
+            literals_mut(rhs, literals);
+        }
+        ast::Expr::Unary(_, expr) | ast::Expr::IsNull(expr) | ast::Expr::NotNull(expr) => {
+            literals_mut(expr, literals)
+        }
+        ast::Expr::Like { lhs, rhs, .. } => {
+            literals_mut(lhs, literals);
+            literals_mut(rhs, literals);
+        }
+        ast::Expr::Between {
+            lhs, start, end, ..
+        } => {
+            literals_mut(lhs, literals);
+            literals_mut(start, literals);
+            literals_mut(end, literals);
+        }
+        ast::Expr::Parenthesized(exprs) => {
+            for expr in exprs {
+                literals_mut(expr, literals);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Moves a number by one in either direction, and adds a character to a string or removes its
+/// last one.
+fn tweak_literal<R: Rng>(literal: &mut ast::Literal, rng: &mut R) {
+    match literal {
+        ast::Literal::Numeric(n) => {
+            let delta = if rng.gen_bool(0.5) { 1 } else { -1 };
+            if let Ok(value) = n.parse::<i64>() {
+                *n = value
+                    .checked_add(delta)
+                    .unwrap_or(value - delta)
+                    .to_string();
+            } else if let Ok(value) = n.parse::<f64>() {
+                *n = format!("{:?}", value + delta as f64);
+            }
+        }
+        ast::Literal::String(s) => {
+            // The literal is kept quoted, as in `'abc'`
+            let inner = &s[1..s.len() - 1];
+            let inner = if inner.is_empty() || rng.gen_bool(0.5) {
+                format!("{}{}", inner, rng.gen_range('a'..='z'))
+            } else {
+                // A quote inside the literal is escaped by doubling it
+                let mut chars = inner.chars();
+                if chars.next_back() == Some('\'') {
+                    chars.next_back();
+                }
+                chars.as_str().to_string()
+            };
+            *s = format!("'{}'", inner);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng as _;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::{
+        generation::plan::{InteractionPlan, Interactions},
+        model::query::{Drop, Query},
+    };
+
+    fn drop(table: &str) -> Interactions {
+        Interactions::Query(Query::Drop(Drop {
+            table: table.to_string(),
+        }))
+    }
+
+    fn tables(plan: &InteractionPlan) -> Vec<String> {
+        plan.plan
+            .iter()
+            .map(|interactions| interactions.uses().concat())
+            .collect()
+    }
+
+    #[test]
+    fn mutate_reorders_independent_interactions() {
+        let plan = InteractionPlan {
+            plan: vec![drop("a"), drop("b")],
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mutated = plan.mutate(&mut rng);
+        assert_eq!(tables(&mutated), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn mutate_keeps_dependent_interactions() {
+        let plan = InteractionPlan {
+            plan: vec![drop("a"), drop("a")],
+        };
+        for seed in 0..100 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let mutated = plan.mutate(&mut rng);
+            assert_eq!(tables(&mutated), vec!["a", "a"]);
+        }
+    }
+
+    #[test]
+    fn mutate_is_deterministic() {
+        let plan = InteractionPlan {
+            plan: vec![drop("a"), drop("b"), drop("c"), drop("d")],
+        };
+        for seed in 0..100 {
+            let first = plan.mutate(&mut ChaCha8Rng::seed_from_u64(seed));
+            let second = plan.mutate(&mut ChaCha8Rng::seed_from_u64(seed));
+            assert_eq!(tables(&first), tables(&second));
+        }
+    }
+}
@@ -1,29 +1,68 @@
 use std::{
     collections::HashSet,
     fmt::{Debug, Display},
+    num::NonZero,
     path::Path,
     sync::Arc,
+    time::{Duration, Instant},
     vec,
 };
 
 use serde::{Deserialize, Serialize};
-use turso_core::{Connection, Result, StepResult, IO};
+use turso_core::{CheckpointMode, Connection, Result, StepResult, IO};
 
 use crate::{
     model::{
-        query::{update::Update, Create, CreateIndex, Delete, Drop, Insert, Query, Select},
+        query::{
+            update::Update, Alter, Create, CreateIndex, Delete, Drop, Insert, Prepared, Query,
+            Select,
+        },
         table::SimValue,
     },
     runner::{env::SimConnection, io::SimulatorIO},
     SimulatorEnv,
 };
 
-use crate::generation::{frequency, Arbitrary, ArbitraryFrom};
+use crate::generation::{frequency, ArbitraryFrom};
 
 use super::property::{remaining, Property};
+use super::query::droppable_columns;
 
 pub(crate) type ResultSet = Result<Vec<Vec<SimValue>>>;
 
+/// A query that was still running when the interaction timeout expired
+#[derive(Debug)]
+pub(crate) struct Hang {
+    pub(crate) query: String,
+    pub(crate) timeout: Duration,
+}
+
+impl Display for Hang {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "hang: query `{}` did not complete within {:?}",
+            &self.query[0..self.query.len().min(4096)],
+            self.timeout
+        )
+    }
+}
+
+/// Gives up on a query once `timeout`, if any, has passed since `started`
+fn check_timeout(
+    query: &str,
+    started: Instant,
+    timeout: Option<Duration>,
+) -> std::result::Result<(), Hang> {
+    match timeout {
+        Some(timeout) if started.elapsed() >= timeout => Err(Hang {
+            query: query.to_string(),
+            timeout,
+        }),
+        _ => Ok(()),
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct InteractionPlan {
     pub(crate) plan: Vec<Interactions>,
@@ -208,7 +247,7 @@ impl Display for InteractionPlan {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub(crate) struct InteractionStats {
     pub(crate) read_count: usize,
     pub(crate) write_count: usize,
@@ -283,6 +322,13 @@ impl Debug for Assertion {
 pub(crate) enum Fault {
     Disconnect,
     ReopenDatabase,
+    /// Checkpoints the WAL of the connection with `TRUNCATE`, while other connections may be in
+    /// the middle of a transaction
+    Checkpoint,
+    /// Makes every following IO operation take `delay_ms` of simulated time
+    SlowIO {
+        delay_ms: u64,
+    },
 }
 
 impl Display for Fault {
@@ -290,6 +336,8 @@ impl Display for Fault {
         match self {
             Fault::Disconnect => write!(f, "DISCONNECT"),
             Fault::ReopenDatabase => write!(f, "REOPEN_DATABASE"),
+            Fault::Checkpoint => write!(f, "CHECKPOINT"),
+            Fault::SlowIO { delay_ms } => write!(f, "SLOW_IO {}ms", delay_ms),
         }
     }
 }
@@ -299,6 +347,23 @@ impl InteractionPlan {
         Self { plan: Vec::new() }
     }
 
+    /// Appends a fresh batch of interactions generated against the current state of `env`.
+    /// The workload distribution is computed per batch, so a time-boxed run keeps the same mix
+    /// of reads and writes no matter how many batches it goes through.
+    pub(crate) fn extend_from<R: rand::Rng>(&mut self, rng: &mut R, env: &SimulatorEnv) {
+        let mut batch = InteractionPlan::new();
+        while batch.plan.len() < env.opts.max_interactions {
+            let interactions = Interactions::arbitrary_from(rng, (env, batch.stats()));
+            batch.plan.push(interactions);
+        }
+        tracing::info!(
+            "Extended plan with {} interactions, {} in total",
+            batch.plan.len(),
+            self.plan.len() + batch.plan.len()
+        );
+        self.plan.extend(batch.plan);
+    }
+
     pub(crate) fn stats(&self) -> InteractionStats {
         let mut read = 0;
         let mut write = 0;
@@ -314,25 +379,35 @@ impl InteractionPlan {
                     for interaction in &property.interactions() {
                         if let Interaction::Query(query) = interaction {
                             match query {
-                                Query::Select(_) => read += 1,
+                                Query::Select(_) | Query::Prepared(_) => read += 1,
                                 Query::Insert(_) => write += 1,
                                 Query::Delete(_) => delete += 1,
                                 Query::Create(_) => create += 1,
                                 Query::Drop(_) => drop += 1,
                                 Query::Update(_) => update += 1,
                                 Query::CreateIndex(_) => create_index += 1,
+                                Query::Alter(_) => update += 1,
+                                Query::Pragma(_)
+                                | Query::Reindex(_)
+                                | Query::Savepoint(_)
+                                | Query::Vacuum(_) => {}
                             }
                         }
                     }
                 }
                 Interactions::Query(query) => match query {
-                    Query::Select(_) => read += 1,
+                    Query::Select(_) | Query::Prepared(_) => read += 1,
                     Query::Insert(_) => write += 1,
                     Query::Delete(_) => delete += 1,
                     Query::Create(_) => create += 1,
                     Query::Drop(_) => drop += 1,
                     Query::Update(_) => update += 1,
                     Query::CreateIndex(_) => create_index += 1,
+                    Query::Alter(_) => update += 1,
+                    Query::Pragma(_)
+                    | Query::Reindex(_)
+                    | Query::Savepoint(_)
+                    | Query::Vacuum(_) => {}
                 },
                 Interactions::Fault(_) => {}
             }
@@ -356,12 +431,25 @@ impl ArbitraryFrom<&mut SimulatorEnv> for InteractionPlan {
 
         let num_interactions = env.opts.max_interactions;
 
-        // First create at least one table
-        let create_query = Create::arbitrary(rng);
-        env.tables.push(create_query.table.clone());
+        if let Some(schema) = env.opts.schema.clone() {
+            // Start from the fixed schema, which the rest of the plan only reads and writes
+            for create in schema.tables {
+                env.tables.push(create.table.clone());
+                plan.plan.push(Interactions::Query(Query::Create(create)));
+            }
+            for index in schema.indexes {
+                index.shadow(env);
+                plan.plan
+                    .push(Interactions::Query(Query::CreateIndex(index)));
+            }
+        } else {
+            // First create at least one table
+            let create_query = Create::arbitrary_from(rng, &*env);
+            env.tables.push(create_query.table.clone());
 
-        plan.plan
-            .push(Interactions::Query(Query::Create(create_query)));
+            plan.plan
+                .push(Interactions::Query(Query::Create(create_query)));
+        }
 
         while plan.plan.len() < num_interactions {
             tracing::debug!(
@@ -393,24 +481,39 @@ impl Interaction {
             }
         }
     }
-    pub(crate) fn execute_query(&self, conn: &mut Arc<Connection>, io: &SimulatorIO) -> ResultSet {
+    /// Runs the query and collects its rows. Once `timeout` expires the statement is
+    /// interrupted and the query is reported as a [Hang], instead of blocking the whole run.
+    pub(crate) fn execute_query(
+        &self,
+        conn: &mut Arc<Connection>,
+        io: &SimulatorIO,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<ResultSet, Hang> {
+        let started = Instant::now();
+        if let Self::Query(Query::Prepared(prepared)) = self {
+            return Self::execute_prepared(prepared, conn, io, started, timeout);
+        }
         if let Self::Query(query) = self {
             let query_str = query.to_string();
-            let rows = conn.query(&query_str);
-            if rows.is_err() {
-                let err = rows.err();
-                tracing::debug!(
-                    "Error running query '{}': {:?}",
-                    &query_str[0..query_str.len().min(4096)],
-                    err
-                );
-                return Err(err.unwrap());
-            }
-            let rows = rows?;
+            let rows = match conn.query(&query_str) {
+                Ok(rows) => rows,
+                Err(err) => {
+                    tracing::debug!(
+                        "Error running query '{}': {:?}",
+                        &query_str[0..query_str.len().min(4096)],
+                        err
+                    );
+                    return Ok(Err(err));
+                }
+            };
             assert!(rows.is_some());
             let mut rows = rows.unwrap();
             let mut out = Vec::new();
             while let Ok(row) = rows.step() {
+                if let Err(hang) = check_timeout(&query_str, started, timeout) {
+                    rows.interrupt();
+                    return Err(hang);
+                }
                 match row {
                     StepResult::Row => {
                         let row = rows.row().unwrap();
@@ -432,12 +535,62 @@ impl Interaction {
                 }
             }
 
-            Ok(out)
+            Ok(Ok(out))
         } else {
             unreachable!("unexpected: this function should only be called on queries")
         }
     }
 
+    /// Prepares the query once and runs it for each set of bindings, resetting the statement
+    /// before binding the next values. The rows of all the executions are returned in order.
+    fn execute_prepared(
+        prepared: &Prepared,
+        conn: &mut Arc<Connection>,
+        io: &SimulatorIO,
+        started: Instant,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<ResultSet, Hang> {
+        let query_str = prepared.select(None).to_string();
+        let mut stmt = match conn.prepare(&query_str) {
+            Ok(stmt) => stmt,
+            Err(err) => {
+                tracing::debug!("Error preparing query '{}': {:?}", query_str, err);
+                return Ok(Err(err));
+            }
+        };
+        let mut out = Vec::new();
+        for values in &prepared.bindings {
+            stmt.reset();
+            for (i, value) in values.iter().enumerate() {
+                stmt.bind_at(NonZero::new(i + 1).unwrap(), value.0.clone());
+            }
+            loop {
+                if let Err(hang) = check_timeout(&query_str, started, timeout) {
+                    stmt.interrupt();
+                    return Err(hang);
+                }
+                let step = match stmt.step() {
+                    Ok(step) => step,
+                    Err(err) => return Ok(Err(err)),
+                };
+                match step {
+                    StepResult::Row => {
+                        let row = stmt.row().unwrap();
+                        out.push(row.get_values().map(|v| v.into()).collect());
+                    }
+                    StepResult::IO => {
+                        io.run_once().unwrap();
+                    }
+                    StepResult::Interrupt | StepResult::Busy => {}
+                    StepResult::Done => {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(Ok(out))
+    }
+
     pub(crate) fn execute_assertion(
         &self,
         stack: &Vec<ResultSet>,
@@ -501,11 +654,44 @@ impl Interaction {
                             ));
                         }
                         env.connections[conn_index] = SimConnection::Disconnected;
+                        #[cfg(feature = "compare-sqlite")]
+                        if let Some(oracle) = env.oracle.as_mut() {
+                            oracle.disconnect(conn_index);
+                        }
                     }
                     Fault::ReopenDatabase => {
                         reopen_database(env);
                     }
+                    Fault::Checkpoint => {
+                        match &env.connections[conn_index] {
+                            SimConnection::LimboConnection(conn) => {
+                                conn.checkpoint(CheckpointMode::Truncate)?;
+                            }
+                            SimConnection::SQLiteConnection(conn) => {
+                                conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", (), |_| Ok(()))
+                                    .map_err(|err| {
+                                        turso_core::LimboError::InternalError(err.to_string())
+                                    })?;
+                            }
+                            SimConnection::Disconnected => {
+                                return Err(turso_core::LimboError::InternalError(
+                                    "connection disconnected".into(),
+                                ));
+                            }
+                        }
+                        // The connections are left untouched
+                        return Ok(());
+                    }
+                    Fault::SlowIO { delay_ms } => {
+                        // The connections are left untouched
+                        env.io.slow_down(*delay_ms);
+                        return Ok(());
+                    }
                 }
+                // Pragmas and transactions are per connection, so a fresh connection starts
+                // with the defaults and without open savepoints
+                env.foreign_keys = false;
+                env.savepoints.clear();
                 Ok(())
             }
             _ => {
@@ -646,17 +832,26 @@ fn reopen_database(env: &mut SimulatorEnv) {
     env.connections.clear();
 
     // Clear all open files
-    env.io.files.borrow_mut().clear();
+    env.io.close_files();
 
     // 2. Re-open database
     let db_path = env.db_path.clone();
-    let db = match turso_core::Database::open_file(env.io.clone(), &db_path, false, false) {
+    let db = match turso_core::Database::open_file(
+        env.io.clone(),
+        &db_path,
+        false,
+        env.opts.experimental_indexes,
+    ) {
         Ok(db) => db,
         Err(e) => {
             panic!("error opening simulator test file {:?}: {:?}", db_path, e);
         }
     };
     env.db = db;
+    #[cfg(feature = "compare-sqlite")]
+    if let Some(oracle) = env.oracle.as_mut() {
+        oracle.disconnect_all();
+    }
 
     for _ in 0..num_conns {
         env.connections
@@ -664,8 +859,8 @@ fn reopen_database(env: &mut SimulatorEnv) {
     }
 }
 
-fn random_create<R: rand::Rng>(rng: &mut R, _env: &SimulatorEnv) -> Interactions {
-    Interactions::Query(Query::Create(Create::arbitrary(rng)))
+fn random_create<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Interactions {
+    Interactions::Query(Query::Create(Create::arbitrary_from(rng, env)))
 }
 
 fn random_read<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Interactions {
@@ -697,12 +892,32 @@ fn random_create_index<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Option<
     )))
 }
 
+fn random_alter<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Option<Interactions> {
+    if !env
+        .tables
+        .iter()
+        .any(|table| !droppable_columns(env, table).is_empty())
+    {
+        return None;
+    }
+    Some(Interactions::Query(Query::Alter(Alter::arbitrary_from(
+        rng, env,
+    ))))
+}
+
 fn random_fault<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Interactions {
-    let faults = if env.opts.disable_reopen_database {
-        vec![Fault::Disconnect]
-    } else {
-        vec![Fault::Disconnect, Fault::ReopenDatabase]
-    };
+    let mut faults = vec![Fault::Disconnect];
+    if !env.opts.disable_reopen_database {
+        faults.push(Fault::ReopenDatabase);
+    }
+    if !env.opts.disable_checkpoint {
+        faults.push(Fault::Checkpoint);
+    }
+    if !env.opts.disable_slow_io {
+        faults.push(Fault::SlowIO {
+            delay_ms: rng.gen_range(1..=100),
+        });
+    }
     let fault = faults[rng.gen_range(0..faults.len())].clone();
     Interactions::Fault(fault)
 }
@@ -752,6 +967,21 @@ impl ArbitraryFrom<(&SimulatorEnv, InteractionStats)> for Interactions {
                     remaining_.update,
                     Box::new(|rng: &mut R| random_update(rng, env)),
                 ),
+                (
+                    if env.opts.disable_alter_table {
+                        0.0
+                    } else {
+                        remaining_.update / 4.0
+                    },
+                    Box::new(|rng: &mut R| {
+                        if let Some(interaction) = random_alter(rng, env) {
+                            interaction
+                        } else {
+                            // no table has a column that can be dropped, so update a row instead
+                            random_update(rng, env)
+                        }
+                    }),
+                ),
                 (
                     // remaining_.drop,
                     0.0,
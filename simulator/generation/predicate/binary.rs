@@ -1,17 +1,18 @@
 //! Contains code for generation for [ast::Expr::Binary] Predicate
 
+use turso_core::Value;
 use turso_sqlite3_parser::ast::{self, Expr};
 
 use crate::{
     generation::{
         backtrack, one_of, pick,
         predicate::{CompoundPredicate, SimplePredicate},
-        table::{GTValue, LTValue, LikeValue},
+        table::{GTValue, LTValue, LikeValue, RegexpValue},
         ArbitraryFrom, ArbitraryFromMaybe as _,
     },
     model::{
         query::predicate::Predicate,
-        table::{SimValue, Table},
+        table::{Column, SimValue, Table},
     },
 };
 
@@ -39,13 +40,18 @@ impl Predicate {
                         Box::new(Expr::Literal(gt_value.into())),
                     )
                 }),
-                Box::new(|rng| {
-                    let lt_value = LTValue::arbitrary_from(rng, value).0;
-                    Expr::Binary(
+                Box::new(|rng| match LTValue::arbitrary_from_maybe(rng, value) {
+                    Some(lt_value) => Expr::Binary(
                         Box::new(Expr::Id(ast::Id(column_name.to_string()))),
                         ast::Operator::Less,
-                        Box::new(Expr::Literal(lt_value.into())),
-                    )
+                        Box::new(Expr::Literal(lt_value.0.into())),
+                    ),
+                    // Nothing sorts before the value
+                    None => Expr::Binary(
+                        Box::new(Expr::Id(ast::Id(column_name.to_string()))),
+                        ast::Operator::Equals,
+                        Box::new(Expr::Literal(value.into())),
+                    ),
                 }),
             ],
             rng,
@@ -59,8 +65,23 @@ impl Predicate {
         let column_index = rng.gen_range(0..t.columns.len());
         let column = &t.columns[column_index];
         let value = &row[column_index];
+        let column_expr =
+            || ast::Expr::Qualified(ast::Name(t.name.clone()), ast::Name(column.name.clone()));
+        // A comparison with NULL is never true, only testing for NULL or comparing with IS is
+        if matches!(value.0, Value::Null) {
+            return Predicate(true_is_comparison(rng, column_expr(), column, value));
+        }
         let expr = backtrack(
             vec![
+                (1, Box::new(|_| Some(null_test(column_expr(), false)))),
+                (
+                    1,
+                    Box::new(|rng| Some(true_is_comparison(rng, column_expr(), column, value))),
+                ),
+                (
+                    1,
+                    Box::new(|rng| Some(between_comparison(rng, column_expr(), value, true))),
+                ),
                 (
                     1,
                     Box::new(|_| {
@@ -95,15 +116,16 @@ impl Predicate {
                 (
                     1,
                     Box::new(|rng| {
-                        let lt_value = LTValue::arbitrary_from(rng, value).0;
-                        Some(Expr::Binary(
-                            Box::new(ast::Expr::Qualified(
-                                ast::Name(t.name.clone()),
-                                ast::Name(column.name.clone()),
-                            )),
-                            ast::Operator::Greater,
-                            Box::new(Expr::Literal(lt_value.into())),
-                        ))
+                        LTValue::arbitrary_from_maybe(rng, value).map(|lt_value| {
+                            Expr::Binary(
+                                Box::new(ast::Expr::Qualified(
+                                    ast::Name(t.name.clone()),
+                                    ast::Name(column.name.clone()),
+                                )),
+                                ast::Operator::Greater,
+                                Box::new(Expr::Literal(lt_value.0.into())),
+                            )
+                        })
                     }),
                 ),
                 (
@@ -133,11 +155,31 @@ impl Predicate {
                                 not: false, // TODO: also generate this value eventually
                                 op: ast::LikeOperator::Like,
                                 rhs: Box::new(Expr::Literal(like.0.into())),
-                                escape: None, // TODO: implement
+                                escape: like.1.map(|escape| {
+                                    Box::new(Expr::Literal(ast::Literal::String(format!(
+                                        "'{}'",
+                                        escape
+                                    ))))
+                                }),
                             }
                         })
                     }),
                 ),
+                (
+                    1,
+                    Box::new(|rng| {
+                        RegexpValue::arbitrary_from_maybe(rng, value).map(|regexp| Expr::Like {
+                            lhs: Box::new(ast::Expr::Qualified(
+                                ast::Name(t.name.clone()),
+                                ast::Name(column.name.clone()),
+                            )),
+                            not: false,
+                            op: ast::LikeOperator::Regexp,
+                            rhs: Box::new(Expr::Literal(regexp.0.into())),
+                            escape: None,
+                        })
+                    }),
+                ),
             ],
             rng,
         );
@@ -151,8 +193,17 @@ impl Predicate {
         let column_index = rng.gen_range(0..t.columns.len());
         let column = &t.columns[column_index];
         let value = &row[column_index];
+        let column_expr =
+            || ast::Expr::Qualified(ast::Name(t.name.clone()), ast::Name(column.name.clone()));
+        if matches!(value.0, Value::Null) {
+            return Predicate(false_null_predicate(rng, column_expr(), column));
+        }
         let expr = one_of(
             vec![
+                Box::new(|_| null_test(column_expr(), true)),
+                Box::new(|_| null_comparison(column_expr())),
+                Box::new(|rng| false_is_comparison(rng, column_expr(), column, value)),
+                Box::new(|rng| between_comparison(rng, column_expr(), value, false)),
                 Box::new(|_| {
                     Expr::Binary(
                         Box::new(ast::Expr::Qualified(
@@ -190,16 +241,24 @@ impl Predicate {
                         Box::new(Expr::Literal(gt_value.into())),
                     )
                 }),
-                Box::new(|rng| {
-                    let lt_value = LTValue::arbitrary_from(rng, value).0;
-                    Expr::Binary(
+                Box::new(|rng| match LTValue::arbitrary_from_maybe(rng, value) {
+                    Some(lt_value) => Expr::Binary(
                         Box::new(ast::Expr::Qualified(
                             ast::Name(t.name.clone()),
                             ast::Name(column.name.clone()),
                         )),
                         ast::Operator::Less,
-                        Box::new(Expr::Literal(lt_value.into())),
-                    )
+                        Box::new(Expr::Literal(lt_value.0.into())),
+                    ),
+                    // Nothing sorts before the value
+                    None => Expr::Binary(
+                        Box::new(ast::Expr::Qualified(
+                            ast::Name(t.name.clone()),
+                            ast::Name(column.name.clone()),
+                        )),
+                        ast::Operator::NotEquals,
+                        Box::new(Expr::Literal(value.into())),
+                    ),
                 }),
             ],
             rng,
@@ -219,8 +278,25 @@ impl SimplePredicate {
         if row.is_empty() {
             return SimplePredicate(Predicate(Expr::Literal(SimValue::TRUE.into())));
         }
+        let column_expr = || {
+            Expr::Qualified(
+                ast::Name(table.name.clone()),
+                ast::Name(column.name.clone()),
+            )
+        };
+        // A comparison with NULL is never true, only testing for NULL or comparing with IS is
+        if matches!(column_value.0, Value::Null) {
+            return SimplePredicate(Predicate(true_is_comparison(
+                rng,
+                column_expr(),
+                column,
+                column_value,
+            )));
+        }
         let expr = one_of(
             vec![
+                Box::new(|_rng| null_test(column_expr(), false)),
+                Box::new(|rng| true_is_comparison(rng, column_expr(), column, column_value)),
                 Box::new(|_rng| {
                     Expr::Binary(
                         Box::new(ast::Expr::Qualified(
@@ -231,17 +307,27 @@ impl SimplePredicate {
                         Box::new(Expr::Literal(column_value.into())),
                     )
                 }),
-                Box::new(|rng| {
-                    let lt_value = LTValue::arbitrary_from(rng, column_value).0;
-                    Expr::Binary(
-                        Box::new(Expr::Qualified(
-                            ast::Name(table.name.clone()),
-                            ast::Name(column.name.clone()),
-                        )),
-                        ast::Operator::Greater,
-                        Box::new(Expr::Literal(lt_value.into())),
-                    )
-                }),
+                Box::new(
+                    |rng| match LTValue::arbitrary_from_maybe(rng, column_value) {
+                        Some(lt_value) => Expr::Binary(
+                            Box::new(Expr::Qualified(
+                                ast::Name(table.name.clone()),
+                                ast::Name(column.name.clone()),
+                            )),
+                            ast::Operator::Greater,
+                            Box::new(Expr::Literal(lt_value.0.into())),
+                        ),
+                        // Nothing sorts before the value
+                        None => Expr::Binary(
+                            Box::new(Expr::Qualified(
+                                ast::Name(table.name.clone()),
+                                ast::Name(column.name.clone()),
+                            )),
+                            ast::Operator::Equals,
+                            Box::new(Expr::Literal(column_value.into())),
+                        ),
+                    },
+                ),
                 Box::new(|rng| {
                     let gt_value = GTValue::arbitrary_from(rng, column_value).0;
                     Expr::Binary(
@@ -253,6 +339,11 @@ impl SimplePredicate {
                         Box::new(Expr::Literal(gt_value.into())),
                     )
                 }),
+                Box::new(|rng| {
+                    row_value_comparison(rng, table, row, true)
+                        .unwrap_or_else(|| null_test(column_expr(), false))
+                }),
+                Box::new(|rng| between_comparison(rng, column_expr(), column_value, true)),
             ],
             rng,
         );
@@ -269,8 +360,20 @@ impl SimplePredicate {
         if row.is_empty() {
             return SimplePredicate(Predicate(Expr::Literal(SimValue::FALSE.into())));
         }
+        let column_expr = || {
+            Expr::Qualified(
+                ast::Name(table.name.clone()),
+                ast::Name(column.name.clone()),
+            )
+        };
+        if matches!(column_value.0, Value::Null) {
+            return SimplePredicate(Predicate(false_null_predicate(rng, column_expr(), column)));
+        }
         let expr = one_of(
             vec![
+                Box::new(|_rng| null_test(column_expr(), true)),
+                Box::new(|_rng| null_comparison(column_expr())),
+                Box::new(|rng| false_is_comparison(rng, column_expr(), column, column_value)),
                 Box::new(|_rng| {
                     Expr::Binary(
                         Box::new(Expr::Qualified(
@@ -292,17 +395,32 @@ impl SimplePredicate {
                         Box::new(Expr::Literal(gt_value.into())),
                     )
                 }),
+                Box::new(
+                    |rng| match LTValue::arbitrary_from_maybe(rng, column_value) {
+                        Some(lt_value) => Expr::Binary(
+                            Box::new(ast::Expr::Qualified(
+                                ast::Name(table.name.clone()),
+                                ast::Name(column.name.clone()),
+                            )),
+                            ast::Operator::Less,
+                            Box::new(Expr::Literal(lt_value.0.into())),
+                        ),
+                        // Nothing sorts before the value
+                        None => Expr::Binary(
+                            Box::new(ast::Expr::Qualified(
+                                ast::Name(table.name.clone()),
+                                ast::Name(column.name.clone()),
+                            )),
+                            ast::Operator::NotEquals,
+                            Box::new(Expr::Literal(column_value.into())),
+                        ),
+                    },
+                ),
                 Box::new(|rng| {
-                    let lt_value = LTValue::arbitrary_from(rng, column_value).0;
-                    Expr::Binary(
-                        Box::new(ast::Expr::Qualified(
-                            ast::Name(table.name.clone()),
-                            ast::Name(column.name.clone()),
-                        )),
-                        ast::Operator::Less,
-                        Box::new(Expr::Literal(lt_value.into())),
-                    )
+                    row_value_comparison(rng, table, row, false)
+                        .unwrap_or_else(|| null_test(column_expr(), true))
                 }),
+                Box::new(|rng| between_comparison(rng, column_expr(), column_value, false)),
             ],
             rng,
         );
@@ -310,6 +428,228 @@ impl SimplePredicate {
     }
 }
 
+/// `<column> IS NULL`, or `<column> IS NOT NULL` when `is_null` is false
+fn null_test(column: Expr, is_null: bool) -> Expr {
+    let operator = if is_null {
+        ast::Operator::Is
+    } else {
+        ast::Operator::IsNot
+    };
+    Expr::Binary(
+        Box::new(column),
+        operator,
+        Box::new(Expr::Literal(ast::Literal::Null)),
+    )
+}
+
+/// A comparison of the column with NULL, which is NULL and therefore never true whatever the
+/// value of the column is
+fn null_comparison(column: Expr) -> Expr {
+    Expr::Binary(
+        Box::new(column),
+        ast::Operator::Equals,
+        Box::new(Expr::Literal(ast::Literal::Null)),
+    )
+}
+
+/// A predicate that is not true for `column` when it is NULL
+fn false_null_predicate<R: rand::Rng>(rng: &mut R, column_expr: Expr, column: &Column) -> Expr {
+    match rng.gen_range(0..3) {
+        0 => null_test(column_expr, false),
+        1 => null_comparison(column_expr),
+        _ => false_is_comparison(rng, column_expr, column, &SimValue(Value::Null)),
+    }
+}
+
+/// `<column> IS <value>`, or `<column> IS NOT <value>` when `is` is false. Unlike `=` and `<>`,
+/// they compare NULL like any other value, so they are never NULL themselves.
+fn is_comparison(column_expr: Expr, value: &SimValue, is: bool) -> Expr {
+    let operator = if is {
+        ast::Operator::Is
+    } else {
+        ast::Operator::IsNot
+    };
+    Expr::Binary(
+        Box::new(column_expr),
+        operator,
+        Box::new(Expr::Literal(value.into())),
+    )
+}
+
+/// A value of the type of `column` that is distinct from `value` under the collation of the
+/// column, if one is found in a few tries
+fn distinct_value<R: rand::Rng>(
+    rng: &mut R,
+    column: &Column,
+    value: &SimValue,
+) -> Option<SimValue> {
+    let collation = column.collation.unwrap_or_default();
+    (0..10)
+        .map(|_| SimValue::arbitrary_from(rng, &column.column_type))
+        .find(|other| {
+            !value
+                .binary_compare_collated(other, ast::Operator::Is, collation)
+                .as_bool()
+        })
+}
+
+/// A comparison with IS or IS NOT that is true for `column` holding `value`, which may be NULL
+fn true_is_comparison<R: rand::Rng>(
+    rng: &mut R,
+    column_expr: Expr,
+    column: &Column,
+    value: &SimValue,
+) -> Expr {
+    match distinct_value(rng, column, value) {
+        Some(other) if rng.gen_bool(0.5) => is_comparison(column_expr, &other, false),
+        _ => is_comparison(column_expr, value, true),
+    }
+}
+
+/// A comparison with IS or IS NOT that is false for `column` holding `value`, which may be NULL
+fn false_is_comparison<R: rand::Rng>(
+    rng: &mut R,
+    column_expr: Expr,
+    column: &Column,
+    value: &SimValue,
+) -> Expr {
+    match distinct_value(rng, column, value) {
+        Some(other) if rng.gen_bool(0.5) => is_comparison(column_expr, &other, true),
+        _ => is_comparison(column_expr, value, false),
+    }
+}
+
+/// `<column> BETWEEN <low> AND <high>` or `<column> NOT BETWEEN <low> AND <high>`, which is true
+/// for `column` holding `value` when `truth` is set and false otherwise. `value` must not be
+/// NULL. The bounds are inclusive, so `value` itself is often one of them, and a range that is
+/// not meant to hold `value` lies above it, is inverted, or has a NULL bound.
+fn between_comparison<R: rand::Rng>(
+    rng: &mut R,
+    column_expr: Expr,
+    value: &SimValue,
+    truth: bool,
+) -> Expr {
+    let not = rng.gen_bool(0.5);
+    let lt_value = LTValue::arbitrary_from_maybe(rng, value).map(|value| value.0);
+    let gt_value = GTValue::arbitrary_from(rng, value).0;
+    let (start, end) = if truth != not {
+        let start = match lt_value {
+            Some(lt_value) if rng.gen_bool(0.5) => lt_value,
+            _ => value.clone(),
+        };
+        let end = if rng.gen_bool(0.5) {
+            gt_value
+        } else {
+            value.clone()
+        };
+        (start, end)
+    } else {
+        match rng.gen_range(0..3) {
+            0 => {
+                let above = GTValue::arbitrary_from(rng, &gt_value).0;
+                (gt_value, above)
+            }
+            1 => (gt_value, value.clone()),
+            // The comparison with the NULL bound is NULL, so the other bound decides: BETWEEN is
+            // at best NULL, and NOT BETWEEN is true since the value is below the lower bound
+            _ if not => (gt_value, SimValue(Value::Null)),
+            _ => (SimValue(Value::Null), gt_value),
+        }
+    };
+    Expr::Between {
+        lhs: Box::new(column_expr),
+        not,
+        start: Box::new(Expr::Literal(start.into())),
+        end: Box::new(Expr::Literal(end.into())),
+    }
+}
+
+/// A comparison of the row value of two columns with a row value built from their values in
+/// `row`, which is true for `row` when `truth` is set and false otherwise. The columns must
+/// not be NULL in `row`, so there is nothing to compare when fewer than two of them are left.
+fn row_value_comparison<R: rand::Rng>(
+    rng: &mut R,
+    table: &Table,
+    row: &[SimValue],
+    truth: bool,
+) -> Option<Expr> {
+    let columns = (0..table.columns.len())
+        .filter(|&i| !matches!(row[i].0, Value::Null))
+        .collect::<Vec<_>>();
+    if columns.len() < 2 {
+        return None;
+    }
+    let first = rng.gen_range(0..columns.len() - 1);
+    let second = rng.gen_range(first + 1..columns.len());
+    let (first, second) = (columns[first], columns[second]);
+    let (first_value, second_value) = (&row[first], &row[second]);
+    let column_expr = |index: usize| {
+        Expr::Qualified(
+            ast::Name(table.name.clone()),
+            ast::Name(table.columns[index].name.clone()),
+        )
+    };
+    let literal = |value: &SimValue| Expr::Literal(value.into());
+    let lt_value = LTValue::arbitrary_from_maybe(rng, first_value).map(|value| value.0);
+    let gt_value = GTValue::arbitrary_from(rng, second_value).0;
+
+    // Row values compare lexicographically, so only the second values decide ties on the first
+    let (operator, values) = match (truth, rng.gen_range(0..4)) {
+        (true, 0) => (
+            ast::Operator::Equals,
+            (literal(first_value), literal(second_value)),
+        ),
+        (true, 1) => (
+            *pick(
+                &[ast::Operator::LessEquals, ast::Operator::GreaterEquals],
+                rng,
+            ),
+            (literal(first_value), literal(second_value)),
+        ),
+        (true, 2) => (
+            ast::Operator::Less,
+            (literal(first_value), literal(&gt_value)),
+        ),
+        (true, _) => match &lt_value {
+            Some(lt_value) => (
+                ast::Operator::Greater,
+                (literal(lt_value), literal(&gt_value)),
+            ),
+            None => (
+                ast::Operator::GreaterEquals,
+                (literal(first_value), literal(second_value)),
+            ),
+        },
+        (false, 0) => (
+            ast::Operator::NotEquals,
+            (literal(first_value), literal(second_value)),
+        ),
+        (false, 1) => (
+            *pick(&[ast::Operator::Less, ast::Operator::Greater], rng),
+            (literal(first_value), literal(second_value)),
+        ),
+        (false, 2) => (
+            ast::Operator::Greater,
+            (literal(first_value), literal(&gt_value)),
+        ),
+        (false, _) => match &lt_value {
+            Some(lt_value) => (ast::Operator::Less, (literal(lt_value), literal(&gt_value))),
+            None => (
+                ast::Operator::Less,
+                (literal(first_value), literal(second_value)),
+            ),
+        },
+    };
+    Some(Expr::Binary(
+        Box::new(Expr::Parenthesized(vec![
+            column_expr(first),
+            column_expr(second),
+        ])),
+        operator,
+        Box::new(Expr::Parenthesized(vec![values.0, values.1])),
+    ))
+}
+
 impl CompoundPredicate {
     /// Decide if you want to create an AND or an OR
     ///
@@ -441,7 +781,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -470,7 +810,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -499,7 +839,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -527,7 +867,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
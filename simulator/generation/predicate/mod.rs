@@ -260,7 +260,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -289,7 +289,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -318,7 +318,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -347,7 +347,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -374,7 +374,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
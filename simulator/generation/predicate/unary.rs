@@ -45,8 +45,10 @@ impl ArbitraryFromMaybe<&SimValue> for FalseValue {
     where
         Self: Sized,
     {
-        // If the Value is a false value return it else you cannot return a false Value
-        (!value.as_bool()).then_some(Self(value.clone()))
+        // If the Value is a false value return it else you cannot return a false Value. NULL is
+        // neither true nor false, as negating it is still NULL
+        (!value.as_bool() && !matches!(value.0, turso_core::Value::Null))
+            .then_some(Self(value.clone()))
     }
 }
 
@@ -251,7 +253,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -279,7 +281,7 @@ mod tests {
                     table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(&mut rng, &c.column_type))
+                        .map(|c| SimValue::arbitrary_from(&mut rng, c))
                         .collect()
                 })
                 .collect();
@@ -1,22 +1,29 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
-use turso_core::LimboError;
+use turso_core::{Connection, Database, LimboError, MemoryIO, StepResult, Value};
 use turso_sqlite3_parser::ast;
 
 use crate::{
     model::{
         query::{
+            create_index::SortOrder,
+            insert::{ConflictResolution, Upsert},
             predicate::Predicate,
-            select::{Distinctness, ResultColumn},
-            Create, Delete, Drop, Insert, Query, Select,
+            select::{Aggregate, AggregateFunc, Distinctness, Having, OrderingTerm, ResultColumn},
+            Create, CreateIndex, Delete, Drop, Insert, Pragma, Prepared, Query, Reindex, Savepoint,
+            Select, Vacuum,
         },
-        table::SimValue,
+        table::{Column, ColumnDefault, ColumnType, ForeignKey, SimValue, Table},
     },
     runner::env::SimulatorEnv,
 };
 
 use super::{
-    frequency, pick, pick_index,
-    plan::{Assertion, Interaction, InteractionStats, ResultSet},
+    frequency, gen_random_text, pick, pick_index,
+    plan::{Assertion, Fault, Interaction, InteractionStats, ResultSet},
+    query::grouped_select,
     ArbitraryFrom,
 };
 
@@ -143,6 +150,252 @@ pub(crate) enum Property {
         query: Query,
         tables: Vec<String>,
     },
+    /// Foreign-Key-Enforced is a property in which inserting a child row
+    /// whose parent key does not exist must fail while foreign keys are enforced.
+    /// The execution of the property is as follows
+    ///     CREATE TABLE <p> (id INTEGER PRIMARY KEY, ...)
+    ///     CREATE TABLE <c> (..., FOREIGN KEY (<p>_id) REFERENCES <p>(id))
+    ///     INSERT INTO <p> VALUES (...)
+    ///     PRAGMA foreign_keys = ON
+    ///     INSERT INTO <c> VALUES (<missing id>) -> Error
+    ///     INSERT INTO <c> VALUES (<existing id>)
+    ///     SELECT * FROM <c>
+    ///     PRAGMA foreign_keys = OFF
+    /// The interactions have the following constraints;
+    /// - Tables `p` and `c` do not exist before the property runs.
+    /// - The shadow model rejects the orphan row too, so `c` only holds the valid row.
+    ForeignKeyEnforced {
+        parent: Create,
+        child: Create,
+        parent_rows: Insert,
+        orphan: Insert,
+        valid: Insert,
+    },
+    /// Reindex-Preserves-Results is a property in which rebuilding the indexes
+    /// of a table must not change the results of a query on that table.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <predicate>
+    ///     REINDEX <t>
+    ///     SELECT * FROM <t> WHERE <predicate>
+    /// The property is successful if both queries return the same rows.
+    ReindexPreservesResults {
+        select: Select,
+    },
+    /// Unique-Allows-Multiple-Nulls is a property in which a UNIQUE index
+    /// must accept any number of NULL keys, while rejecting a repeated non-NULL key.
+    /// The execution of the property is as follows
+    ///     CREATE TABLE <t> (...)
+    ///     CREATE UNIQUE INDEX <i> ON <t> (<c>)
+    ///     INSERT INTO <t> VALUES (..., NULL, ...)
+    ///     INSERT INTO <t> VALUES (..., NULL, ...)
+    ///     INSERT INTO <t> VALUES (..., <v>, ...)
+    ///     INSERT INTO <t> VALUES (..., <v>, ...) -> Error
+    ///     SELECT * FROM <t>
+    /// The interactions have the following constraints;
+    /// - Table `t` does not exist before the property runs.
+    /// - The shadow model rejects the duplicated key too, so `t` ends up with three rows.
+    UniqueAllowsMultipleNulls {
+        create: Create,
+        index: CreateIndex,
+        nulls: Vec<Insert>,
+        value: Insert,
+        duplicate: Insert,
+    },
+    /// Savepoint-Rollback is a property in which rolling back to an inner savepoint
+    /// must undo only the changes made after it.
+    /// The execution of the property is as follows
+    ///     SAVEPOINT <outer>
+    ///     INSERT INTO <t> VALUES (...)
+    ///     SAVEPOINT <inner>
+    ///     INSERT INTO <t> VALUES (...)
+    ///     ROLLBACK TO <inner>
+    ///     RELEASE <outer>
+    ///     SELECT * FROM <t>
+    /// The property is successful if the table holds the row inserted under the outer
+    /// savepoint but not the one inserted under the inner savepoint.
+    SavepointRollback {
+        outer: Insert,
+        inner: Insert,
+    },
+    /// Select-After-Reopen-Stable is a property in which closing and reopening
+    /// the database must not change the results of a query, which exercises how
+    /// the WAL is recovered and checkpointed when the database is opened again.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <predicate>
+    ///     REOPEN_DATABASE
+    ///     SELECT * FROM <t> WHERE <predicate>
+    /// The property is successful if both queries return the same rows.
+    SelectAfterReopenStable {
+        select: Select,
+    },
+    /// Select-After-Checkpoint-Stable is a property in which checkpointing the
+    /// WAL into the database file must not change the results of a query.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <predicate>
+    ///     CHECKPOINT
+    ///     SELECT * FROM <t> WHERE <predicate>
+    /// The property is successful if both queries return the same rows.
+    SelectAfterCheckpointStable {
+        select: Select,
+    },
+    /// Vacuum-Preserves-Data is a property in which rebuilding the database file
+    /// must not change the results of a query.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <predicate>
+    ///     VACUUM
+    ///     SELECT * FROM <t> WHERE <predicate>
+    /// The property is successful if both queries return the same rows.
+    VacuumPreservesData {
+        select: Select,
+    },
+    /// Default-Applied is a property in which the columns left out of an insert
+    /// must take their default values.
+    /// The execution of the property is as follows
+    ///     CREATE TABLE <t> (..., <c> DEFAULT <literal>, <ts> TEXT DEFAULT CURRENT_TIMESTAMP, ...)
+    ///     INSERT INTO <t> (<columns>) VALUES (...)
+    ///     SELECT * FROM <t>
+    ///     DROP TABLE <t>
+    /// The property is successful if the row holds the inserted values, the literal defaults
+    /// and a timestamp formatted as `YYYY-MM-DD HH:MM:SS`. The table is dropped at the end, as
+    /// the shadow model cannot know the exact timestamp the database stored.
+    DefaultApplied {
+        create: Create,
+        insert: Insert,
+    },
+    /// Limit-Offset-Pagination is a property in which reading an ordered query
+    /// page by page must return the same rows as reading it at once.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <predicate> ORDER BY <c>, rowid
+    ///     SELECT * FROM <t> WHERE <predicate> ORDER BY <c>, rowid LIMIT <k> OFFSET 0
+    ///     SELECT * FROM <t> WHERE <predicate> ORDER BY <c>, rowid LIMIT <k> OFFSET <k>
+    ///     ...
+    ///     SELECT * FROM <t> WHERE <predicate> ORDER BY <c>, rowid LIMIT <k> OFFSET <(n - 1) * k>
+    ///     SELECT * FROM <t> WHERE <predicate> ORDER BY <c>, rowid LIMIT -1 OFFSET <n * k>
+    /// `c` and `rowid` are sorted in the same direction, so that an index on `c` can be
+    /// scanned backwards for a descending order. The property is successful if the pages
+    /// neither overlap nor skip rows, i.e. if their concatenation is a prefix of the rows
+    /// of the unpaged query, only the pages past its end come out short, and the query
+    /// without a limit returns the rest of the rows.
+    LimitOffsetPagination {
+        /// The unpaged select query
+        select: Select,
+        /// Number of rows per page
+        page_size: usize,
+        /// Number of pages read
+        pages: usize,
+    },
+    /// Group-Having-Consistency is a property in which a grouped query must keep
+    /// the same groups as the shadow model after filtering them with `HAVING`.
+    /// The execution of the property is as follows
+    ///     SELECT <c>, <aggregate> FROM <t> WHERE <predicate> GROUP BY <c> HAVING <aggregate> <op> <value>
+    /// The property is successful if the query returns as many rows as the shadow model
+    /// has groups left.
+    GroupHavingConsistency {
+        select: Select,
+    },
+    /// Prepared-Rebind-Consistency is a property in which a prepared statement must
+    /// return the same rows when it is reset and executed again with new bindings as
+    /// the same query with the bound values inlined.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <c> <op> ? AND ... ORDER BY rowid -- BINDINGS (v_0, ...), ..., (v_n, ...)
+    ///     SELECT * FROM <t> WHERE <c> <op> v_0 AND ... ORDER BY rowid
+    ///     ...
+    ///     SELECT * FROM <t> WHERE <c> <op> v_n AND ... ORDER BY rowid
+    /// The property is successful if the rows of the executions of the prepared statement
+    /// are the rows of the inlined queries, in the same order.
+    PreparedRebindConsistency {
+        prepared: Prepared,
+    },
+    /// Is-Null-Partition is a property in which `IS NULL` and `IS NOT NULL` must split
+    /// the rows of a table in two, whatever the three-valued logic of the other operators.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t>
+    ///     SELECT * FROM <t> WHERE <c> IS NULL
+    ///     SELECT * FROM <t> WHERE <c> IS NOT NULL
+    /// The property is successful if the rows of the last two queries add up to the rows
+    /// of the first one.
+    IsNullPartition {
+        table: String,
+        column: String,
+    },
+    /// Distinct-Agg-Consistency is a property in which `count(DISTINCT c)` must count the
+    /// distinct values of `c` the shadow table holds, NULLs aside.
+    /// The execution of the property is as follows
+    ///     SELECT count(DISTINCT <c>) FROM <t>
+    /// The property is successful if the count is the number of values of `c` that are
+    /// distinct under its collation in the shadow table.
+    DistinctAggConsistency {
+        table: String,
+        column: String,
+    },
+    /// Between-Consistency is a property in which `BETWEEN` must select the rows of the
+    /// comparisons it stands for, with NULLs following three-valued logic.
+    /// The execution of the property is as follows
+    ///     SELECT * FROM <t> WHERE <c> BETWEEN <low> AND <high>
+    ///     SELECT * FROM <t> WHERE <c> >= <low> AND <c> <= <high>
+    /// or with `NOT BETWEEN` and `<c> < <low> OR <c> > <high>` when `not` is set.
+    /// The property is successful if both queries return the same rows.
+    BetweenConsistency {
+        table: String,
+        column: String,
+        not: bool,
+        low: SimValue,
+        high: SimValue,
+    },
+    /// Create-Index-Consistency is a property in which a new index must hold exactly one
+    /// entry per row of its table, and find the rows a full scan of the table finds.
+    /// The execution of the property is as follows
+    ///     CREATE INDEX <i> ON <t> (<c>)
+    ///     SELECT * FROM <t>
+    ///     SELECT <c> FROM <t> ORDER BY <c>
+    ///     SELECT * FROM <t> WHERE <c> = <v>
+    /// The interactions have the following constraints;
+    /// - Table `t` has rows, one of which is `row`, whose value of `c` is `v`.
+    /// The property is successful if the ordered scan, which the index covers, returns the
+    /// values of `c` of every row of the full scan, and the lookup returns `row` among rows
+    /// of the full scan.
+    CreateIndexConsistency {
+        index: CreateIndex,
+        lookup: Select,
+        row: Vec<SimValue>,
+    },
+    /// Autoincrement-No-Reuse is a property in which an AUTOINCREMENT table must never
+    /// give the rowid of a deleted row to a new one, even when it was the largest rowid.
+    /// The execution of the property is as follows
+    ///     CREATE TABLE <t> (id INTEGER PRIMARY KEY AUTOINCREMENT, ...)
+    ///     INSERT INTO <t> (<columns but id>) VALUES (...), ...
+    ///     DELETE FROM <t> WHERE id = <n>
+    ///     INSERT INTO <t> (<columns but id>) VALUES (...)
+    ///     SELECT * FROM <t> ORDER BY rowid
+    ///     DROP TABLE <t>
+    /// The interactions have the following constraints;
+    /// - Table `t` does not exist before the property runs, so the `n` rows of the first
+    ///   insert get the ids 1 to `n`.
+    /// The property is successful if the row of the second insert gets the id `n + 1`. The
+    /// table is dropped at the end, as the shadow model does not assign the ids.
+    AutoincrementNoReuse {
+        create: Create,
+        insert: Insert,
+        delete: Delete,
+        reinsert: Insert,
+    },
+    /// Schema-Round-Trip is a property in which the `CREATE` statements kept by the catalog,
+    /// which `.schema` and `.dump` print, must recreate the schema they were captured from.
+    /// The execution of the property is as follows
+    ///     SELECT type, name, tbl_name, sql FROM sqlite_schema WHERE <tables and indexes>
+    ///     PRAGMA table_info(<t_1>)
+    ///     ...
+    ///     PRAGMA table_info(<t_n>)
+    /// The interactions have the following constraints;
+    /// - `t_1` to `t_n` are all the tables of the database.
+    /// The captured statements are then replayed in an empty in-memory database, which
+    /// leaves the shadow model untouched, and the same queries are run on it. The property
+    /// is successful if both databases return the same catalog and the same columns.
+    SchemaRoundTrip {
+        /// The catalog query, with the filter `.schema` uses
+        catalog: Select,
+        tables: Vec<String>,
+    },
 }
 
 impl Property {
@@ -156,6 +409,23 @@ impl Property {
             Property::SelectSelectOptimizer { .. } => "Select-Select-Optimizer",
             Property::FsyncNoWait { .. } => "FsyncNoWait",
             Property::FaultyQuery { .. } => "FaultyQuery",
+            Property::ForeignKeyEnforced { .. } => "Foreign-Key-Enforced",
+            Property::ReindexPreservesResults { .. } => "Reindex-Preserves-Results",
+            Property::UniqueAllowsMultipleNulls { .. } => "Unique-Allows-Multiple-Nulls",
+            Property::SavepointRollback { .. } => "Savepoint-Rollback",
+            Property::SelectAfterReopenStable { .. } => "Select-After-Reopen-Stable",
+            Property::SelectAfterCheckpointStable { .. } => "Select-After-Checkpoint-Stable",
+            Property::VacuumPreservesData { .. } => "Vacuum-Preserves-Data",
+            Property::DefaultApplied { .. } => "Default-Applied",
+            Property::LimitOffsetPagination { .. } => "Limit-Offset-Pagination",
+            Property::GroupHavingConsistency { .. } => "Group-Having-Consistency",
+            Property::PreparedRebindConsistency { .. } => "Prepared-Rebind-Consistency",
+            Property::IsNullPartition { .. } => "Is-Null-Partition",
+            Property::DistinctAggConsistency { .. } => "Distinct-Agg-Consistency",
+            Property::BetweenConsistency { .. } => "Between-Consistency",
+            Property::CreateIndexConsistency { .. } => "Create-Index-Consistency",
+            Property::AutoincrementNoReuse { .. } => "Autoincrement-No-Reuse",
+            Property::SchemaRoundTrip { .. } => "Schema-Round-Trip",
         }
     }
     /// interactions construct a list of interactions, which is an executable representation of the property.
@@ -169,7 +439,7 @@ impl Property {
                 queries,
                 select,
             } => {
-                let (table, values) = if let Insert::Values { table, values } = insert {
+                let (table, values) = if let Insert::Values { table, values, .. } = insert {
                     (table, values)
                 } else {
                     unreachable!(
@@ -310,14 +580,21 @@ impl Property {
                 let delete = Interaction::Query(Query::Delete(Delete {
                     table: table.clone(),
                     predicate: predicate.clone(),
+                    order_by: vec![],
+                    limit: None,
                 }));
 
                 let select = Interaction::Query(Query::Select(Select {
                     table: table.clone(),
+                    table_alias: None,
                     result_columns: vec![ResultColumn::Star],
                     predicate: predicate.clone(),
                     limit: None,
                     distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
                 }));
 
                 let assertion = Interaction::Assertion(Assertion {
@@ -401,18 +678,28 @@ impl Property {
                 });
                 let select1 = Interaction::Query(Query::Select(Select {
                     table: table.clone(),
+                    table_alias: None,
                     result_columns: vec![ResultColumn::Expr(predicate.clone())],
                     predicate: Predicate::true_(),
                     limit: None,
                     distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
                 }));
 
                 let select2_query = Query::Select(Select {
                     table: table.clone(),
+                    table_alias: None,
                     result_columns: vec![ResultColumn::Star],
                     predicate: predicate.clone(),
                     limit: None,
                     distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
                 });
                 let select2 = Interaction::Query(select2_query);
 
@@ -457,131 +744,1257 @@ impl Property {
                 let first = std::iter::once(Interaction::FaultyQuery(query.clone()));
                 Vec::from_iter(first.chain(checks))
             }
-        }
-    }
-}
+            Property::ForeignKeyEnforced {
+                parent,
+                child,
+                parent_rows,
+                orphan,
+                valid,
+            } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!(
+                        "tables {} and {} do not exist",
+                        parent.table.name, child.table.name
+                    ),
+                    func: Box::new({
+                        let names = [parent.table.name.clone(), child.table.name.clone()];
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(!env.tables.iter().any(|t| names.contains(&t.name)))
+                        }
+                    }),
+                });
 
-fn assert_all_table_values(tables: &[String]) -> impl Iterator<Item = Interaction> + use<'_> {
-    let checks = tables.iter().flat_map(|table| {
-        let select = Interaction::Query(Query::Select(Select {
-            table: table.clone(),
-            result_columns: vec![ResultColumn::Star],
-            predicate: Predicate::true_(),
-            limit: None,
-            distinct: Distinctness::All,
-        }));
-        let assertion = Interaction::Assertion(Assertion {
-            message: format!(
-                "table {} should contain all of its values after the wal reopened",
-                table
-            ),
-            func: Box::new({
-                let table = table.clone();
-                move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
-                    let table = env.tables.iter().find(|t| t.name == table).ok_or_else(|| {
-                        LimboError::InternalError(format!("table {} should exist", table))
-                    })?;
-                    let last = stack.last().unwrap();
-                    match last {
-                        Ok(vals) => Ok(*vals == table.rows),
-                        Err(err) => Err(LimboError::InternalError(format!("{}", err))),
-                    }
-                }
-            }),
-        });
-        [select, assertion].into_iter()
-    });
-    checks
-}
+                let orphan_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "inserting a row without a parent into {} should fail",
+                        child.table.name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let last = stack.last().unwrap();
+                        match last {
+                            Ok(_) => Ok(false),
+                            Err(e) => Ok(matches!(e, LimboError::ForeignKeyConstraint)),
+                        }
+                    }),
+                });
 
-#[derive(Debug)]
-pub(crate) struct Remaining {
-    pub(crate) read: f64,
-    pub(crate) write: f64,
-    pub(crate) create: f64,
-    pub(crate) create_index: f64,
-    pub(crate) delete: f64,
-    pub(crate) update: f64,
-    pub(crate) drop: f64,
-}
+                let child_name = child.table.name.clone();
+                let select = Select {
+                    table: child_name.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
 
-pub(crate) fn remaining(env: &SimulatorEnv, stats: &InteractionStats) -> Remaining {
-    let remaining_read = ((env.opts.max_interactions as f64 * env.opts.read_percent / 100.0)
-        - (stats.read_count as f64))
-        .max(0.0);
-    let remaining_write = ((env.opts.max_interactions as f64 * env.opts.write_percent / 100.0)
-        - (stats.write_count as f64))
-        .max(0.0);
-    let remaining_create = ((env.opts.max_interactions as f64 * env.opts.create_percent / 100.0)
-        - (stats.create_count as f64))
-        .max(0.0);
+                let select_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "table {} should only contain the rows with an existing parent",
+                        child_name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                        let table = env
+                            .tables
+                            .iter()
+                            .find(|t| t.name == child_name)
+                            .ok_or_else(|| {
+                                LimboError::InternalError(format!(
+                                    "table {} should exist",
+                                    child_name
+                                ))
+                            })?;
+                        let last = stack.last().unwrap();
+                        match last {
+                            Ok(rows) => Ok(*rows == table.rows),
+                            Err(err) => Err(LimboError::InternalError(err.to_string())),
+                        }
+                    }),
+                });
 
-    let remaining_create_index =
-        ((env.opts.max_interactions as f64 * env.opts.create_index_percent / 100.0)
-            - (stats.create_index_count as f64))
-            .max(0.0);
+                let check_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "foreign_key_check should report no violation in {}",
+                        child.table.name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let last = stack.last().unwrap();
+                        match last {
+                            Ok(rows) => Ok(rows.is_empty()),
+                            Err(err) => Err(LimboError::InternalError(err.to_string())),
+                        }
+                    }),
+                });
 
-    let remaining_delete = ((env.opts.max_interactions as f64 * env.opts.delete_percent / 100.0)
-        - (stats.delete_count as f64))
-        .max(0.0);
-    let remaining_update = ((env.opts.max_interactions as f64 * env.opts.update_percent / 100.0)
-        - (stats.update_count as f64))
-        .max(0.0);
-    let remaining_drop = ((env.opts.max_interactions as f64 * env.opts.drop_percent / 100.0)
-        - (stats.drop_count as f64))
-        .max(0.0);
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Create(parent.clone())),
+                    Interaction::Query(Query::Create(child.clone())),
+                    Interaction::Query(Query::Insert(parent_rows.clone())),
+                    Interaction::Query(Query::Pragma(Pragma::ForeignKeys(true))),
+                    Interaction::Query(Query::Insert(orphan.clone())),
+                    orphan_assertion,
+                    Interaction::Query(Query::Insert(valid.clone())),
+                    Interaction::Query(Query::Select(select)),
+                    select_assertion,
+                    Interaction::Query(Query::Pragma(Pragma::ForeignKeyCheck(
+                        child.table.name.clone(),
+                    ))),
+                    check_assertion,
+                    Interaction::Query(Query::Pragma(Pragma::ForeignKeys(false))),
+                ]
+            }
+            Property::ReindexPreservesResults { select } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", select.table),
+                    func: Box::new({
+                        let table = select.table.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table))
+                        }
+                    }),
+                });
 
-    Remaining {
-        read: remaining_read,
-        write: remaining_write,
-        create: remaining_create,
-        create_index: remaining_create_index,
-        delete: remaining_delete,
-        drop: remaining_drop,
-        update: remaining_update,
-    }
-}
+                let reindex = Interaction::Query(Query::Reindex(Reindex {
+                    table: select.table.clone(),
+                }));
 
-fn property_insert_values_select<R: rand::Rng>(
-    rng: &mut R,
-    env: &SimulatorEnv,
-    remaining: &Remaining,
-) -> Property {
-    // Get a random table
-    let table = pick(&env.tables, rng);
-    // Generate rows to insert
-    let rows = (0..rng.gen_range(1..=5))
-        .map(|_| Vec::<SimValue>::arbitrary_from(rng, table))
-        .collect::<Vec<_>>();
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "results of `{}` should not change after REINDEX {}",
+                        select, select.table
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let after = stack.last().unwrap();
+                        let before = stack.get(stack.len() - 3).unwrap();
+                        match (before, after) {
+                            (Ok(before), Ok(after)) => Ok(before == after),
+                            (Err(err), _) | (_, Err(err)) => {
+                                Err(LimboError::InternalError(err.to_string()))
+                            }
+                        }
+                    }),
+                });
 
-    // Pick a random row to select
-    let row_index = pick_index(rows.len(), rng);
-    let row = rows[row_index].clone();
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select.clone())),
+                    reindex,
+                    Interaction::Query(Query::Select(select.clone())),
+                    assertion,
+                ]
+            }
+            Property::VacuumPreservesData { select } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", select.table),
+                    func: Box::new({
+                        let table = select.table.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table))
+                        }
+                    }),
+                });
 
-    // Insert the rows
-    let insert_query = Insert::Values {
-        table: table.name.clone(),
-        values: rows,
-    };
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!("results of `{}` should not change after VACUUM", select),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let after = stack.last().unwrap();
+                        let before = stack.get(stack.len() - 3).unwrap();
+                        match (before, after) {
+                            (Ok(before), Ok(after)) => Ok(before == after),
+                            (Err(err), _) | (_, Err(err)) => {
+                                Err(LimboError::InternalError(err.to_string()))
+                            }
+                        }
+                    }),
+                });
 
-    // Create random queries respecting the constraints
-    let mut queries = Vec::new();
-    // - [x] There will be no errors in the middle interactions. (this constraint is impossible to check, so this is just best effort)
-    // - [x] The inserted row will not be deleted.
-    // - [ ] The inserted row will not be updated. (todo: add this constraint once UPDATE is implemented)
-    // - [ ] The table `t` will not be renamed, dropped, or altered. (todo: add this constraint once ALTER or DROP is implemented)
-    for _ in 0..rng.gen_range(0..3) {
-        let query = Query::arbitrary_from(rng, (env, remaining));
-        match &query {
-            Query::Delete(Delete {
-                table: t,
-                predicate,
-            }) => {
-                // The inserted row will not be deleted.
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select.clone())),
+                    Interaction::Query(Query::Vacuum(Vacuum)),
+                    Interaction::Query(Query::Select(select.clone())),
+                    assertion,
+                ]
+            }
+            Property::LimitOffsetPagination {
+                select,
+                page_size,
+                pages,
+            } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", select.table),
+                    func: Box::new({
+                        let table = select.table.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table))
+                        }
+                    }),
+                });
+
+                let (page_size, pages) = (*page_size, *pages);
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "pages of {} rows of `{}` should neither overlap nor skip rows",
+                        page_size, select
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let results = &stack[stack.len() - pages - 2..];
+                        let results = results
+                            .iter()
+                            .map(|result| {
+                                result
+                                    .as_ref()
+                                    .map_err(|err| LimboError::InternalError(err.to_string()))
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let (all, results) = results.split_first().unwrap();
+                        let (rest, pages) = results.split_last().unwrap();
+                        let mut expected = all.chunks(page_size);
+                        let paged = pages.len() * page_size;
+                        Ok(pages
+                            .iter()
+                            .all(|page| page.as_slice() == expected.next().unwrap_or_default())
+                            && rest.as_slice() == all.get(paged..).unwrap_or_default())
+                    }),
+                });
+
+                let mut interactions = vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select.clone())),
+                ];
+                interactions.extend((0..pages).map(|i| {
+                    Interaction::Query(Query::Select(Select {
+                        limit: Some(page_size),
+                        offset: Some(i * page_size),
+                        ..select.clone()
+                    }))
+                }));
+                // The rows after the last page, with an offset and no limit
+                interactions.push(Interaction::Query(Query::Select(Select {
+                    limit: None,
+                    offset: Some(pages * page_size),
+                    ..select.clone()
+                })));
+                interactions.push(assertion);
+                interactions
+            }
+            Property::GroupHavingConsistency { select } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", select.table),
+                    func: Box::new({
+                        let table = select.table.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table))
+                        }
+                    }),
+                });
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!("`{}` should return as many groups as the shadow", select),
+                    func: Box::new({
+                        let select = select.clone();
+                        move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            let rows = match stack.last().unwrap() {
+                                Ok(rows) => rows,
+                                Err(err) => return Err(LimboError::InternalError(err.to_string())),
+                            };
+                            Ok(rows.len() == select.shadow(env).len())
+                        }
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select.clone())),
+                    assertion,
+                ]
+            }
+            Property::PreparedRebindConsistency { prepared } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", prepared.table),
+                    func: Box::new({
+                        let table = prepared.table.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table))
+                        }
+                    }),
+                });
+
+                let executions = prepared.bindings.len();
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "`{}` should return the rows of the queries with the values inlined",
+                        prepared
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let results = &stack[stack.len() - executions - 1..];
+                        let results = results
+                            .iter()
+                            .map(|result| {
+                                result
+                                    .as_ref()
+                                    .map_err(|err| LimboError::InternalError(err.to_string()))
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let (rebound, inlined) = results.split_first().unwrap();
+                        Ok(rebound
+                            .iter()
+                            .eq(inlined.iter().flat_map(|rows| rows.iter())))
+                    }),
+                });
+
+                let mut interactions = vec![
+                    assumption,
+                    Interaction::Query(Query::Prepared(prepared.clone())),
+                ];
+                interactions.extend(prepared.bindings.iter().map(|values| {
+                    Interaction::Query(Query::Select(prepared.select(Some(values))))
+                }));
+                interactions.push(assertion);
+                interactions
+            }
+            Property::IsNullPartition { table, column } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} has column {}", table, column),
+                    func: Box::new({
+                        let (table, column) = (table.clone(), column.clone());
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| {
+                                t.name == table && t.columns.iter().any(|c| c.name == column)
+                            }))
+                        }
+                    }),
+                });
+
+                let select = |predicate| Select {
+                    table: table.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate,
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+                let null_test = |operator| {
+                    Predicate(ast::Expr::Binary(
+                        Box::new(ast::Expr::Id(ast::Id(column.clone()))),
+                        operator,
+                        Box::new(ast::Expr::Literal(ast::Literal::Null)),
+                    ))
+                };
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "rows of {} where {} IS NULL and IS NOT NULL should add up to all its rows",
+                        table, column
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let results = &stack[stack.len() - 3..];
+                        let results = results
+                            .iter()
+                            .map(|result| {
+                                result
+                                    .as_ref()
+                                    .map_err(|err| LimboError::InternalError(err.to_string()))
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let [all, null, not_null] = results.as_slice() else {
+                            unreachable!()
+                        };
+                        Ok(null.len() + not_null.len() == all.len())
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select(Predicate::true_()))),
+                    Interaction::Query(Query::Select(select(null_test(ast::Operator::Is)))),
+                    Interaction::Query(Query::Select(select(null_test(ast::Operator::IsNot)))),
+                    assertion,
+                ]
+            }
+            Property::DistinctAggConsistency { table, column } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} has column {}", table, column),
+                    func: Box::new({
+                        let (table, column) = (table.clone(), column.clone());
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| {
+                                t.name == table && t.columns.iter().any(|c| c.name == column)
+                            }))
+                        }
+                    }),
+                });
+
+                let aggregate = Aggregate {
+                    func: AggregateFunc::Count,
+                    column: Some(column.clone()),
+                    distinct: true,
+                };
+                let select = Select {
+                    table: table.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Aggregate(aggregate.clone())],
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "`{}` should count the distinct values of {} in the shadow table",
+                        select, column
+                    ),
+                    func: Box::new({
+                        let table = table.clone();
+                        move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            let rows = stack
+                                .last()
+                                .unwrap()
+                                .as_ref()
+                                .map_err(|err| LimboError::InternalError(err.to_string()))?;
+                            let table =
+                                env.tables.iter().find(|t| t.name == table).ok_or_else(|| {
+                                    LimboError::InternalError(format!(
+                                        "table {} should exist",
+                                        table
+                                    ))
+                                })?;
+                            let shadow_rows =
+                                table.rows.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                            let expected = aggregate.compute(&shadow_rows, table);
+                            match rows.as_slice() {
+                                [row] if *row == [expected.clone()] => Ok(true),
+                                _ => Err(LimboError::InternalError(format!(
+                                    "expected {:?}, got {:?}",
+                                    expected.0, rows
+                                ))),
+                            }
+                        }
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select)),
+                    assertion,
+                ]
+            }
+            Property::BetweenConsistency {
+                table,
+                column,
+                not,
+                low,
+                high,
+            } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} has column {}", table, column),
+                    func: Box::new({
+                        let (table, column) = (table.clone(), column.clone());
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| {
+                                t.name == table && t.columns.iter().any(|c| c.name == column)
+                            }))
+                        }
+                    }),
+                });
+
+                let select = |predicate| Select {
+                    table: table.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate(predicate),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+                let column_expr = || Box::new(ast::Expr::Id(ast::Id(column.clone())));
+                let literal = |value: &SimValue| Box::new(ast::Expr::Literal(value.into()));
+                let between = ast::Expr::Between {
+                    lhs: column_expr(),
+                    not: *not,
+                    start: literal(low),
+                    end: literal(high),
+                };
+                let (lower, upper, connective) = if *not {
+                    (
+                        ast::Operator::Less,
+                        ast::Operator::Greater,
+                        ast::Operator::Or,
+                    )
+                } else {
+                    (
+                        ast::Operator::GreaterEquals,
+                        ast::Operator::LessEquals,
+                        ast::Operator::And,
+                    )
+                };
+                let comparisons = ast::Expr::Binary(
+                    Box::new(ast::Expr::Binary(column_expr(), lower, literal(low))),
+                    connective,
+                    Box::new(ast::Expr::Binary(column_expr(), upper, literal(high))),
+                );
+                let between = select(between);
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "`{}` should return the rows of the comparisons BETWEEN stands for",
+                        between
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let between = stack.get(stack.len() - 2).unwrap();
+                        let comparisons = stack.last().unwrap();
+                        match (between, comparisons) {
+                            (Ok(between), Ok(comparisons)) => {
+                                let sorted = |rows: &Vec<Vec<SimValue>>| {
+                                    let mut rows = rows.clone();
+                                    rows.sort_by(|a, b| {
+                                        a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+                                    });
+                                    rows
+                                };
+                                Ok(sorted(between) == sorted(comparisons))
+                            }
+                            (Err(err), _) | (_, Err(err)) => {
+                                Err(LimboError::InternalError(err.to_string()))
+                            }
+                        }
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(between)),
+                    Interaction::Query(Query::Select(select(comparisons))),
+                    assertion,
+                ]
+            }
+            Property::CreateIndexConsistency { index, lookup, row } => {
+                let table_name = index.table_name.clone();
+                let column = index.columns[0].0.clone();
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} has the looked up row", table_name),
+                    func: Box::new({
+                        let (table_name, row) = (table_name.clone(), row.clone());
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env
+                                .tables
+                                .iter()
+                                .any(|t| t.name == table_name && t.rows.contains(&row)))
+                        }
+                    }),
+                });
+
+                let select = |result_columns, order_by| Select {
+                    table: table_name.clone(),
+                    table_alias: None,
+                    result_columns,
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by,
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+                let full_scan = select(vec![ResultColumn::Star], vec![]);
+                let index_scan = select(
+                    vec![ResultColumn::Column(column.clone())],
+                    vec![(
+                        OrderingTerm::Column(column.clone()),
+                        index.columns[0].1.clone(),
+                    )],
+                );
+
+                let scan_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "index {} should hold one entry per row of {}",
+                        index.index_name, table_name
+                    ),
+                    func: Box::new({
+                        let (table_name, column) = (table_name.clone(), column.clone());
+                        move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            let (full, indexed) = match &stack[stack.len() - 2..] {
+                                [Ok(full), Ok(indexed)] => (full, indexed),
+                                [Err(err), _] | [_, Err(err)] => {
+                                    return Err(LimboError::InternalError(err.to_string()))
+                                }
+                                _ => unreachable!(),
+                            };
+                            let position = env
+                                .tables
+                                .iter()
+                                .find(|t| t.name == table_name)
+                                .and_then(|t| t.columns.iter().position(|c| c.name == column))
+                                .ok_or_else(|| {
+                                    LimboError::InternalError(format!(
+                                        "table {} should have column {}",
+                                        table_name, column
+                                    ))
+                                })?;
+                            // Every value of the ordered scan is matched with a row of the full
+                            // scan, what is left on either side is a discrepancy
+                            let mut unmatched =
+                                full.iter().map(|row| &row[position]).collect::<Vec<_>>();
+                            let mut discrepancies = 0;
+                            for row in indexed {
+                                match unmatched.iter().position(|value| **value == row[0]) {
+                                    Some(i) => {
+                                        unmatched.swap_remove(i);
+                                    }
+                                    None => discrepancies += 1,
+                                }
+                            }
+                            discrepancies += unmatched.len();
+                            if discrepancies > 0 {
+                                return Err(LimboError::InternalError(format!(
+                                    "{} discrepancies between the {} rows of the table and the {} \
+                                     entries of the index",
+                                    discrepancies,
+                                    full.len(),
+                                    indexed.len()
+                                )));
+                            }
+                            Ok(true)
+                        }
+                    }),
+                });
+
+                let lookup_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "`{}` should find the row {:?} of the full scan of {}",
+                        lookup, row, table_name
+                    ),
+                    func: Box::new({
+                        let row = row.clone();
+                        move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                            let (full, found) =
+                                match (&stack[stack.len() - 3], &stack[stack.len() - 1]) {
+                                    (Ok(full), Ok(found)) => (full, found),
+                                    (Err(err), _) | (_, Err(err)) => {
+                                        return Err(LimboError::InternalError(err.to_string()))
+                                    }
+                                };
+                            let discrepancies = found.iter().filter(|r| !full.contains(r)).count()
+                                + usize::from(!found.contains(&row));
+                            if discrepancies > 0 {
+                                return Err(LimboError::InternalError(format!(
+                                    "{} discrepancies between the {} rows found and the full scan",
+                                    discrepancies,
+                                    found.len()
+                                )));
+                            }
+                            Ok(true)
+                        }
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::CreateIndex(index.clone())),
+                    Interaction::Query(Query::Select(full_scan)),
+                    Interaction::Query(Query::Select(index_scan)),
+                    scan_assertion,
+                    Interaction::Query(Query::Select(lookup.clone())),
+                    lookup_assertion,
+                ]
+            }
+            Property::DefaultApplied { create, insert } => {
+                let table = create.table.clone();
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} does not exist", table.name),
+                    func: Box::new({
+                        let table_name = table.name.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(!env.tables.iter().any(|t| t.name == table_name))
+                        }
+                    }),
+                });
+
+                let Insert::Values {
+                    values, columns, ..
+                } = insert
+                else {
+                    unreachable!(
+                        "insert query should be Insert::Values for Default-Applied property"
+                    )
+                };
+                let row = values[0].clone();
+                let provided = columns
+                    .iter()
+                    .flatten()
+                    .map(|(pos, _)| *pos)
+                    .collect::<Vec<_>>();
+                let select = Select {
+                    table: table.name.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+                let drop = Drop {
+                    table: table.name.clone(),
+                };
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "columns left out of `{}` should hold their default values",
+                        insert
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let rows = match stack.last().unwrap() {
+                            Ok(rows) => rows,
+                            Err(err) => return Err(LimboError::InternalError(err.to_string())),
+                        };
+                        let [got] = rows.as_slice() else {
+                            return Ok(false);
+                        };
+                        Ok(table.columns.iter().enumerate().all(|(i, column)| {
+                            if provided.contains(&i) {
+                                return got[i] == row[i];
+                            }
+                            match &column.default {
+                                Some(ColumnDefault::Value(value)) => &got[i] == value,
+                                Some(ColumnDefault::CurrentTimestamp) => match &got[i].0 {
+                                    Value::Text(text) => chrono::NaiveDateTime::parse_from_str(
+                                        text.as_str(),
+                                        "%Y-%m-%d %H:%M:%S",
+                                    )
+                                    .is_ok(),
+                                    _ => false,
+                                },
+                                None => matches!(got[i].0, Value::Null),
+                            }
+                        }))
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Create(create.clone())),
+                    Interaction::Query(Query::Insert(insert.clone())),
+                    Interaction::Query(Query::Select(select)),
+                    assertion,
+                    Interaction::Query(Query::Drop(drop)),
+                ]
+            }
+            Property::AutoincrementNoReuse {
+                create,
+                insert,
+                delete,
+                reinsert,
+            } => {
+                let table_name = create.table.name.clone();
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} does not exist", table_name),
+                    func: Box::new({
+                        let table_name = table_name.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(!env.tables.iter().any(|t| t.name == table_name))
+                        }
+                    }),
+                });
+
+                let Insert::Values { values, .. } = insert else {
+                    unreachable!(
+                        "insert query should be Insert::Values for Autoincrement-No-Reuse property"
+                    )
+                };
+                // The largest id is deleted, and the new row gets the next one
+                let num_rows = values.len() as i64;
+                let expected_ids = (1..num_rows).chain([num_rows + 1]).collect::<Vec<_>>();
+                let select = Select {
+                    table: table_name.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![(OrderingTerm::Column("rowid".to_string()), SortOrder::Asc)],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+                let drop = Drop {
+                    table: table_name.clone(),
+                };
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "`{}` should not reuse the id deleted by `{}`",
+                        reinsert, delete
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let rows = match stack.last().unwrap() {
+                            Ok(rows) => rows,
+                            Err(err) => return Err(LimboError::InternalError(err.to_string())),
+                        };
+                        let ids = rows
+                            .iter()
+                            .map(|row| match row.first().map(|value| &value.0) {
+                                Some(Value::Integer(id)) => Some(*id),
+                                _ => None,
+                            })
+                            .collect::<Option<Vec<_>>>();
+                        Ok(ids.as_ref() == Some(&expected_ids))
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Create(create.clone())),
+                    Interaction::Query(Query::Insert(insert.clone())),
+                    Interaction::Query(Query::Delete(delete.clone())),
+                    Interaction::Query(Query::Insert(reinsert.clone())),
+                    Interaction::Query(Query::Select(select)),
+                    assertion,
+                    Interaction::Query(Query::Drop(drop)),
+                ]
+            }
+            Property::SchemaRoundTrip { catalog, tables } => {
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("the tables of the database are {}", tables.join(", ")),
+                    func: Box::new({
+                        let tables = tables.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.len() == tables.len()
+                                && env.tables.iter().all(|t| tables.contains(&t.name)))
+                        }
+                    }),
+                });
+
+                let queries = tables
+                    .iter()
+                    .map(|table| Query::Pragma(Pragma::TableInfo(table.clone())))
+                    .collect::<Vec<_>>();
+                let replayed_queries = std::iter::once(catalog.to_string())
+                    .chain(queries.iter().map(Query::to_string))
+                    .collect::<Vec<_>>();
+                let assertion = Interaction::Assertion(Assertion {
+                    message: "the CREATE statements of the catalog should recreate the schema"
+                        .to_string(),
+                    func: Box::new(move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                        let captured = stack[stack.len() - replayed_queries.len()..]
+                            .iter()
+                            .map(|result| {
+                                result
+                                    .as_ref()
+                                    .map_err(|err| LimboError::InternalError(err.to_string()))
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let replayed = replay_schema(
+                            captured[0],
+                            &replayed_queries,
+                            env.opts.experimental_indexes,
+                        )?;
+                        Ok(captured
+                            .iter()
+                            .zip(replayed.iter())
+                            .all(|(captured, replayed)| *captured == replayed))
+                    }),
+                });
+
+                let mut interactions = vec![
+                    assumption,
+                    Interaction::Query(Query::Select(catalog.clone())),
+                ];
+                interactions.extend(queries.into_iter().map(Interaction::Query));
+                interactions.push(assertion);
+                interactions
+            }
+            Property::SelectAfterReopenStable { select }
+            | Property::SelectAfterCheckpointStable { select } => {
+                let (fault, action) = match self {
+                    Property::SelectAfterReopenStable { .. } => {
+                        (Fault::ReopenDatabase, "reopening the database")
+                    }
+                    _ => (Fault::Checkpoint, "checkpointing the WAL"),
+                };
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", select.table),
+                    func: Box::new({
+                        let table = select.table.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table))
+                        }
+                    }),
+                });
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!("results of `{}` should not change after {}", select, action),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        // The fault does not push a result, so the two selects
+                        // are the last two entries of the stack
+                        let after = stack.last().unwrap();
+                        let before = stack.get(stack.len() - 2).unwrap();
+                        match (before, after) {
+                            (Ok(before), Ok(after)) => Ok(before == after),
+                            (Err(err), _) | (_, Err(err)) => {
+                                Err(LimboError::InternalError(err.to_string()))
+                            }
+                        }
+                    }),
+                });
+
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Select(select.clone())),
+                    Interaction::Fault(fault),
+                    Interaction::Query(Query::Select(select.clone())),
+                    assertion,
+                ]
+            }
+            Property::UniqueAllowsMultipleNulls {
+                create,
+                index,
+                nulls,
+                value,
+                duplicate,
+            } => {
+                let table_name = create.table.name.clone();
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} does not exist", table_name),
+                    func: Box::new({
+                        let table_name = table_name.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(!env.tables.iter().any(|t| t.name == table_name))
+                        }
+                    }),
+                });
+
+                let nulls_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "inserting several NULL keys into the unique index {} should succeed",
+                        index.index_name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        for result in stack {
+                            if let Err(err) = result {
+                                return Err(LimboError::InternalError(err.to_string()));
+                            }
+                        }
+                        Ok(true)
+                    }),
+                });
+
+                let duplicate_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "inserting a duplicated key into the unique index {} should fail",
+                        index.index_name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, _: &SimulatorEnv| {
+                        let first = stack.first().unwrap();
+                        if let Err(err) = first {
+                            return Err(LimboError::InternalError(err.to_string()));
+                        }
+                        match stack.last().unwrap() {
+                            Ok(_) => Ok(false),
+                            Err(e) => Ok(matches!(e, LimboError::UniqueConstraint(_))),
+                        }
+                    }),
+                });
+
+                let select = Select {
+                    table: table_name.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+
+                let select_assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "table {} should contain the NULL keys and a single copy of the duplicated key",
+                        table_name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                        let table = env
+                            .tables
+                            .iter()
+                            .find(|t| t.name == table_name)
+                            .ok_or_else(|| {
+                                LimboError::InternalError(format!(
+                                    "table {} should exist",
+                                    table_name
+                                ))
+                            })?;
+                        match stack.last().unwrap() {
+                            Ok(rows) => Ok(*rows == table.rows),
+                            Err(err) => Err(LimboError::InternalError(err.to_string())),
+                        }
+                    }),
+                });
+
+                let mut interactions = vec![
+                    assumption,
+                    Interaction::Query(Query::Create(create.clone())),
+                    Interaction::Query(Query::CreateIndex(index.clone())),
+                ];
+                interactions.extend(
+                    nulls
+                        .iter()
+                        .map(|insert| Interaction::Query(Query::Insert(insert.clone()))),
+                );
+                interactions.extend([
+                    nulls_assertion,
+                    Interaction::Query(Query::Insert(value.clone())),
+                    Interaction::Query(Query::Insert(duplicate.clone())),
+                    duplicate_assertion,
+                    Interaction::Query(Query::Select(select)),
+                    select_assertion,
+                ]);
+                interactions
+            }
+            Property::SavepointRollback { outer, inner } => {
+                let table_name = outer.table().to_string();
+                let assumption = Interaction::Assumption(Assertion {
+                    message: format!("table {} exists", table_name),
+                    func: Box::new({
+                        let table_name = table_name.clone();
+                        move |_: &Vec<ResultSet>, env: &SimulatorEnv| {
+                            Ok(env.tables.iter().any(|t| t.name == table_name))
+                        }
+                    }),
+                });
+
+                let select = Select {
+                    table: table_name.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate::true_(),
+                    limit: None,
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: None,
+                };
+
+                let assertion = Interaction::Assertion(Assertion {
+                    message: format!(
+                        "table {} should only keep the changes made before the inner savepoint",
+                        table_name
+                    ),
+                    func: Box::new(move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                        let table = env
+                            .tables
+                            .iter()
+                            .find(|t| t.name == table_name)
+                            .ok_or_else(|| {
+                                LimboError::InternalError(format!(
+                                    "table {} should exist",
+                                    table_name
+                                ))
+                            })?;
+                        for result in stack {
+                            if let Err(err) = result {
+                                return Err(LimboError::InternalError(err.to_string()));
+                            }
+                        }
+                        match stack.last().unwrap() {
+                            Ok(rows) => Ok(*rows == table.rows),
+                            Err(err) => Err(LimboError::InternalError(err.to_string())),
+                        }
+                    }),
+                });
+
+                let outer_name = "sp_outer".to_string();
+                let inner_name = "sp_inner".to_string();
+                vec![
+                    assumption,
+                    Interaction::Query(Query::Savepoint(Savepoint::Begin(outer_name.clone()))),
+                    Interaction::Query(Query::Insert(outer.clone())),
+                    Interaction::Query(Query::Savepoint(Savepoint::Begin(inner_name.clone()))),
+                    Interaction::Query(Query::Insert(inner.clone())),
+                    Interaction::Query(Query::Savepoint(Savepoint::RollbackTo(inner_name))),
+                    Interaction::Query(Query::Savepoint(Savepoint::Release(outer_name))),
+                    Interaction::Query(Query::Select(select)),
+                    assertion,
+                ]
+            }
+        }
+    }
+}
+
+fn assert_all_table_values(tables: &[String]) -> impl Iterator<Item = Interaction> + use<'_> {
+    let checks = tables.iter().flat_map(|table| {
+        let select = Interaction::Query(Query::Select(Select {
+            table: table.clone(),
+            table_alias: None,
+            result_columns: vec![ResultColumn::Star],
+            predicate: Predicate::true_(),
+            limit: None,
+            distinct: Distinctness::All,
+            order_by: vec![],
+            offset: None,
+            group_by: None,
+            join: None,
+        }));
+        let assertion = Interaction::Assertion(Assertion {
+            message: format!(
+                "table {} should contain all of its values after the wal reopened",
+                table
+            ),
+            func: Box::new({
+                let table = table.clone();
+                move |stack: &Vec<ResultSet>, env: &SimulatorEnv| {
+                    let table = env.tables.iter().find(|t| t.name == table).ok_or_else(|| {
+                        LimboError::InternalError(format!("table {} should exist", table))
+                    })?;
+                    let last = stack.last().unwrap();
+                    match last {
+                        Ok(vals) => Ok(*vals == table.rows),
+                        Err(err) => Err(LimboError::InternalError(format!("{}", err))),
+                    }
+                }
+            }),
+        });
+        [select, assertion].into_iter()
+    });
+    checks
+}
+
+#[derive(Debug)]
+pub(crate) struct Remaining {
+    pub(crate) read: f64,
+    pub(crate) write: f64,
+    pub(crate) create: f64,
+    pub(crate) create_index: f64,
+    pub(crate) delete: f64,
+    pub(crate) update: f64,
+    pub(crate) drop: f64,
+}
+
+pub(crate) fn remaining(env: &SimulatorEnv, stats: &InteractionStats) -> Remaining {
+    let remaining_read = ((env.opts.max_interactions as f64 * env.opts.read_percent / 100.0)
+        - (stats.read_count as f64))
+        .max(0.0);
+    let remaining_write = ((env.opts.max_interactions as f64 * env.opts.write_percent / 100.0)
+        - (stats.write_count as f64))
+        .max(0.0);
+    // Once the schema is as large as allowed, the remaining interactions go to the existing tables
+    let remaining_create = if env.tables.len() >= env.opts.max_tables {
+        0.0
+    } else {
+        ((env.opts.max_interactions as f64 * env.opts.create_percent / 100.0)
+            - (stats.create_count as f64))
+            .max(0.0)
+    };
+
+    let remaining_create_index =
+        ((env.opts.max_interactions as f64 * env.opts.create_index_percent / 100.0)
+            - (stats.create_index_count as f64))
+            .max(0.0);
+
+    let remaining_delete = ((env.opts.max_interactions as f64 * env.opts.delete_percent / 100.0)
+        - (stats.delete_count as f64))
+        .max(0.0);
+    let remaining_update = ((env.opts.max_interactions as f64 * env.opts.update_percent / 100.0)
+        - (stats.update_count as f64))
+        .max(0.0);
+    let remaining_drop = ((env.opts.max_interactions as f64 * env.opts.drop_percent / 100.0)
+        - (stats.drop_count as f64))
+        .max(0.0);
+
+    Remaining {
+        read: remaining_read,
+        write: remaining_write,
+        create: remaining_create,
+        create_index: remaining_create_index,
+        delete: remaining_delete,
+        drop: remaining_drop,
+        update: remaining_update,
+    }
+}
+
+fn property_insert_values_select<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    remaining: &Remaining,
+) -> Property {
+    // Get a random table
+    let table = pick(&env.tables, rng);
+    // Generate rows to insert
+    let rows = (0..rng.gen_range(1..=5))
+        .map(|_| Vec::<SimValue>::arbitrary_from(rng, (table, &env.domains)))
+        .collect::<Vec<_>>();
+
+    // Pick a random row to select
+    let row_index = pick_index(rows.len(), rng);
+    let row = rows[row_index].clone();
+
+    // Insert the rows
+    let insert_query = Insert::Values {
+        table: table.name.clone(),
+        values: rows,
+        columns: table.insert_columns(),
+        or_conflict: None,
+        upsert: None,
+    };
+
+    // Create random queries respecting the constraints
+    let mut queries = Vec::new();
+    // - [x] There will be no errors in the middle interactions. (this constraint is impossible to check, so this is just best effort)
+    // - [x] The inserted row will not be deleted.
+    // - [ ] The inserted row will not be updated. (todo: add this constraint once UPDATE is implemented)
+    // - [ ] The table `t` will not be renamed, dropped, or altered. (todo: add this constraint once ALTER or DROP is implemented)
+    for _ in 0..rng.gen_range(0..3) {
+        let query = Query::arbitrary_from(rng, (env, remaining));
+        match &query {
+            Query::Delete(Delete {
+                table: t,
+                predicate,
+                ..
+            }) => {
+                // The inserted row will not be deleted.
                 if t == &table.name && predicate.test(&row, table) {
                     continue;
                 }
             }
+            Query::Insert(Insert::Values {
+                table: t,
+                values,
+                or_conflict,
+                upsert,
+                ..
+            }) if *or_conflict == Some(ConflictResolution::Replace)
+                || matches!(upsert, Some(Upsert::DoUpdate(_))) =>
+            {
+                // The inserted row will not be replaced or updated.
+                let row = [row.clone()];
+                if t == &table.name
+                    && values
+                        .iter()
+                        .any(|v| !table.conflicting_rows(&row, v).is_empty())
+                {
+                    continue;
+                }
+            }
             Query::Create(Create { table: t }) => {
                 // There will be no errors in the middle interactions.
                 // - Creating the same table is an error
@@ -597,10 +2010,15 @@ fn property_insert_values_select<R: rand::Rng>(
     // Select the row
     let select_query = Select {
         table: table.name.clone(),
+        table_alias: None,
         result_columns: vec![ResultColumn::Star],
         predicate: Predicate::arbitrary_from(rng, (table, &row)),
         limit: None,
         distinct: Distinctness::All,
+        order_by: vec![],
+        offset: None,
+        group_by: None,
+        join: None,
     };
 
     Property::InsertValuesSelect {
@@ -617,167 +2035,816 @@ fn property_select_limit<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Prope
     // Select the table
     let select = Select {
         table: table.name.clone(),
+        table_alias: None,
         result_columns: vec![ResultColumn::Star],
         predicate: Predicate::arbitrary_from(rng, table),
         limit: Some(rng.gen_range(1..=5)),
         distinct: Distinctness::All,
+        order_by: vec![],
+        offset: None,
+        group_by: None,
+        join: None,
     };
     Property::SelectLimit { select }
 }
 
-fn property_double_create_failure<R: rand::Rng>(
-    rng: &mut R,
-    env: &SimulatorEnv,
-    remaining: &Remaining,
-) -> Property {
-    // Get a random table
+fn property_double_create_failure<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    remaining: &Remaining,
+) -> Property {
+    // Get a random table
+    let table = pick(&env.tables, rng);
+    // Create the table
+    let create_query = Create {
+        table: table.clone(),
+    };
+
+    // Create random queries respecting the constraints
+    let mut queries = Vec::new();
+    // The interactions in the middle has the following constraints;
+    // - [x] There will be no errors in the middle interactions.(best effort)
+    // - [ ] Table `t` will not be renamed or dropped.(todo: add this constraint once ALTER or DROP is implemented)
+    for _ in 0..rng.gen_range(0..3) {
+        let query = Query::arbitrary_from(rng, (env, remaining));
+        if let Query::Create(Create { table: t }) = &query {
+            // There will be no errors in the middle interactions.
+            // - Creating the same table is an error
+            if t.name == table.name {
+                continue;
+            }
+        }
+        queries.push(query);
+    }
+
+    Property::DoubleCreateFailure {
+        create: create_query,
+        queries,
+    }
+}
+
+fn property_delete_select<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    remaining: &Remaining,
+) -> Property {
+    // Get a random table
+    let table = pick(&env.tables, rng);
+    // Generate a random predicate
+    let predicate = Predicate::arbitrary_from(rng, table);
+
+    // Create random queries respecting the constraints
+    let mut queries = Vec::new();
+    // - [x] There will be no errors in the middle interactions. (this constraint is impossible to check, so this is just best effort)
+    // - [x] A row that holds for the predicate will not be inserted.
+    // - [ ] The table `t` will not be renamed, dropped, or altered. (todo: add this constraint once ALTER or DROP is implemented)
+    for _ in 0..rng.gen_range(0..3) {
+        let query = Query::arbitrary_from(rng, (env, remaining));
+        match &query {
+            Query::Insert(Insert::Values {
+                table: t,
+                values,
+                upsert,
+                ..
+            }) => {
+                // A row that holds for the predicate will not be inserted, nor made by an upsert
+                // out of an existing row and a new one
+                if t == &table.name
+                    && (values.iter().any(|v| predicate.test(v, table))
+                        || matches!(upsert, Some(Upsert::DoUpdate(_))))
+                {
+                    continue;
+                }
+            }
+            Query::Create(Create { table: t }) => {
+                // There will be no errors in the middle interactions.
+                // - Creating the same table is an error
+                if t.name == table.name {
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        queries.push(query);
+    }
+
+    Property::DeleteSelect {
+        table: table.name.clone(),
+        predicate,
+        queries,
+    }
+}
+
+fn property_drop_select<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    remaining: &Remaining,
+) -> Property {
+    // Get a random table
+    let table = pick(&env.tables, rng);
+
+    // Create random queries respecting the constraints
+    let mut queries = Vec::new();
+    // - [x] There will be no errors in the middle interactions. (this constraint is impossible to check, so this is just best effort)
+    // - [-] The table `t` will not be created, no table will be renamed to `t`. (todo: update this constraint once ALTER is implemented)
+    for _ in 0..rng.gen_range(0..3) {
+        let query = Query::arbitrary_from(rng, (env, remaining));
+        if let Query::Create(Create { table: t }) = &query {
+            // - The table `t` will not be created
+            if t.name == table.name {
+                continue;
+            }
+        }
+        queries.push(query);
+    }
+
+    let select = Select {
+        table: table.name.clone(),
+        table_alias: None,
+        result_columns: vec![ResultColumn::Star],
+        predicate: Predicate::arbitrary_from(rng, table),
+        limit: None,
+        distinct: Distinctness::All,
+        order_by: vec![],
+        offset: None,
+        group_by: None,
+        join: None,
+    };
+
+    Property::DropSelect {
+        table: table.name.clone(),
+        queries,
+        select,
+    }
+}
+
+fn property_select_select_optimizer<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    // Get a random table
+    let table = pick(&env.tables, rng);
+    // Generate a random predicate
+    let predicate = Predicate::arbitrary_from(rng, table);
+    // Transform into a Binary predicate to force values to be casted to a bool
+    let expr = ast::Expr::Binary(
+        Box::new(predicate.0),
+        ast::Operator::And,
+        Box::new(Predicate::true_().0),
+    );
+
+    Property::SelectSelectOptimizer {
+        table: table.name.clone(),
+        predicate: Predicate(expr),
+    }
+}
+
+fn property_fsync_no_wait<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    remaining: &Remaining,
+) -> Property {
+    Property::FsyncNoWait {
+        query: Query::arbitrary_from(rng, (env, remaining)),
+        tables: env.tables.iter().map(|t| t.name.clone()).collect(),
+    }
+}
+
+fn property_faulty_query<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    remaining: &Remaining,
+) -> Property {
+    Property::FaultyQuery {
+        query: Query::arbitrary_from(rng, (env, remaining)),
+        tables: env.tables.iter().map(|t| t.name.clone()).collect(),
+    }
+}
+
+fn property_foreign_key_enforced<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    // Both tables get a key column on top of the generated ones
+    let max_columns = env.opts.max_columns.saturating_sub(1).max(1);
+    // The parent key must be a rowid alias so the child can reference it
+    let mut parent = Table::arbitrary_from(rng, max_columns);
+    parent.columns.retain(|c| c.name != "id");
+    parent.columns.insert(
+        0,
+        Column {
+            name: "id".to_string(),
+            column_type: ColumnType::Integer,
+            primary: true,
+            autoincrement: false,
+            unique: false,
+            collation: None,
+            default: None,
+            generated: None,
+        },
+    );
+
+    let mut child = loop {
+        let child = Table::arbitrary_from(rng, max_columns);
+        if child.name != parent.name {
+            break child;
+        }
+    };
+    let fk_column = format!("{}_id", parent.name);
+    child.columns.retain(|c| c.name != fk_column);
+    let fk_index = rng.gen_range(0..=child.columns.len());
+    child.columns.insert(
+        fk_index,
+        Column {
+            name: fk_column.clone(),
+            column_type: ColumnType::Integer,
+            primary: false,
+            autoincrement: false,
+            unique: false,
+            collation: None,
+            default: None,
+            generated: None,
+        },
+    );
+    child.foreign_keys.push(ForeignKey {
+        columns: vec![fk_column],
+        parent_table: parent.name.clone(),
+        parent_columns: vec!["id".to_string()],
+    });
+
+    // Parent ids must be distinct, otherwise the insert would fail
+    let ids = (0..rng.gen_range(1..=5))
+        .map(|_| rng.gen_range(i64::MIN..i64::MAX))
+        .collect::<BTreeSet<_>>();
+    let rows = ids
+        .iter()
+        .map(|id| {
+            let mut row = Vec::<SimValue>::arbitrary_from(rng, &parent);
+            row[0] = SimValue(Value::Integer(*id));
+            row
+        })
+        .collect::<Vec<_>>();
+
+    let missing_id = loop {
+        let id = rng.gen_range(i64::MIN..i64::MAX);
+        if !ids.contains(&id) {
+            break id;
+        }
+    };
+    let existing_id = *ids.iter().nth(pick_index(ids.len(), rng)).unwrap();
+
+    let mut child_row = |id: i64| {
+        let mut row = Vec::<SimValue>::arbitrary_from(rng, &child);
+        row[fk_index] = SimValue(Value::Integer(id));
+        Insert::Values {
+            table: child.name.clone(),
+            values: vec![row],
+            columns: None,
+            or_conflict: None,
+            upsert: None,
+        }
+    };
+    let orphan = child_row(missing_id);
+    let valid = child_row(existing_id);
+
+    Property::ForeignKeyEnforced {
+        parent_rows: Insert::Values {
+            table: parent.name.clone(),
+            values: rows,
+            columns: None,
+            or_conflict: None,
+            upsert: None,
+        },
+        parent: Create { table: parent },
+        child: Create { table: child },
+        orphan,
+        valid,
+    }
+}
+
+/// `SELECT * FROM <t> WHERE <predicate>` on a random table
+fn select_star_where<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Select {
+    let table = pick(&env.tables, rng);
+    Select {
+        table: table.name.clone(),
+        table_alias: None,
+        result_columns: vec![ResultColumn::Star],
+        predicate: Predicate::arbitrary_from(rng, table),
+        limit: None,
+        distinct: Distinctness::All,
+        order_by: vec![],
+        offset: None,
+        group_by: None,
+        join: None,
+    }
+}
+
+fn property_select_after_reopen_stable<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    Property::SelectAfterReopenStable {
+        select: select_star_where(rng, env),
+    }
+}
+
+fn property_select_after_checkpoint_stable<R: rand::Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+) -> Property {
+    Property::SelectAfterCheckpointStable {
+        select: select_star_where(rng, env),
+    }
+}
+
+fn property_default_applied<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let mut table = loop {
+        let table = Table::arbitrary_from(rng, env.opts.max_columns);
+        if table.columns.len() >= 2 {
+            break table;
+        }
+    };
+    // One column is always set by the insert, and another one is stamped with the insert time
+    let provided = pick_index(table.columns.len(), rng);
+    let stamped = loop {
+        let i = pick_index(table.columns.len(), rng);
+        if i != provided {
+            break i;
+        }
+    };
+    for (i, column) in table.columns.iter_mut().enumerate() {
+        if i == stamped {
+            column.column_type = ColumnType::Text;
+            column.default = Some(ColumnDefault::CurrentTimestamp);
+        } else if i != provided && rng.gen_bool(0.5) {
+            column.default = Some(ColumnDefault::arbitrary_from(rng, &column.column_type));
+        }
+    }
+
+    let mut row = Vec::<SimValue>::arbitrary_from(rng, &table);
+    let mut columns = Vec::new();
+    for (i, column) in table.columns.iter().enumerate() {
+        match &column.default {
+            Some(default) if i != provided => row[i] = default.value(),
+            _ => columns.push((i, column.name.clone())),
+        }
+    }
+
+    Property::DefaultApplied {
+        insert: Insert::Values {
+            table: table.name.clone(),
+            values: vec![row],
+            columns: Some(columns),
+            or_conflict: None,
+            upsert: None,
+        },
+        create: Create { table },
+    }
+}
+
+/// Runs the `CREATE` statements in the `sql` column, the last one, of the rows of the catalog
+/// in an empty in-memory database, and returns the rows of `queries` run on it
+fn replay_schema(
+    catalog: &[Vec<SimValue>],
+    queries: &[String],
+    experimental_indexes: bool,
+) -> turso_core::Result<Vec<Vec<Vec<SimValue>>>> {
+    let db = Database::open_file(
+        Arc::new(MemoryIO::new()),
+        ":memory:",
+        false,
+        experimental_indexes,
+    )?;
+    let conn = db.connect()?;
+    for row in catalog {
+        if let Some(SimValue(Value::Text(sql))) = row.last() {
+            conn.execute(sql.as_str())?;
+        }
+    }
+    queries.iter().map(|sql| query_rows(&conn, sql)).collect()
+}
+
+fn query_rows(conn: &Arc<Connection>, sql: &str) -> turso_core::Result<Vec<Vec<SimValue>>> {
+    let mut rows = Vec::new();
+    let Some(mut stmt) = conn.query(sql)? else {
+        return Ok(rows);
+    };
+    loop {
+        match stmt.step()? {
+            StepResult::Row => {
+                let row = stmt.row().unwrap();
+                rows.push(row.get_values().map(SimValue::from).collect());
+            }
+            StepResult::IO => stmt.run_once()?,
+            StepResult::Done | StepResult::Interrupt | StepResult::Busy => break,
+        }
+    }
+    Ok(rows)
+}
+
+fn property_schema_round_trip(env: &SimulatorEnv) -> Property {
+    let column = |name: &str| ast::Expr::Id(ast::Id(name.to_string()));
+    let text = |s: &str| ast::Expr::Literal(ast::Literal::String(format!("'{}'", s)));
+    // type IN ('table', 'index') AND name NOT LIKE 'sqlite_%'
+    let predicate = ast::Expr::Binary(
+        Box::new(ast::Expr::InList {
+            lhs: Box::new(column("type")),
+            not: false,
+            rhs: Some(vec![text("table"), text("index")]),
+        }),
+        ast::Operator::And,
+        Box::new(ast::Expr::Like {
+            lhs: Box::new(column("name")),
+            not: true,
+            op: ast::LikeOperator::Like,
+            rhs: Box::new(text("sqlite_%")),
+            escape: None,
+        }),
+    );
+    Property::SchemaRoundTrip {
+        catalog: Select {
+            table: "sqlite_schema".to_string(),
+            table_alias: None,
+            result_columns: ["type", "name", "tbl_name", "sql"]
+                .into_iter()
+                .map(|name| ResultColumn::Column(name.to_string()))
+                .collect(),
+            predicate: Predicate(predicate),
+            limit: None,
+            distinct: Distinctness::All,
+            order_by: vec![],
+            offset: None,
+            group_by: None,
+            join: None,
+        },
+        tables: env.tables.iter().map(|t| t.name.clone()).collect(),
+    }
+}
+
+fn property_autoincrement_no_reuse<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    // The table gets an id column on top of the generated ones, and keeps another one for the
+    // inserts to set
+    let max_columns = env.opts.max_columns.saturating_sub(1).max(1);
+    let mut table = loop {
+        let table = Table::arbitrary_from(rng, max_columns);
+        if table.columns.iter().any(|c| c.name != "id") {
+            break table;
+        }
+    };
+    table.columns.retain(|c| c.name != "id");
+    table.columns.insert(
+        0,
+        Column {
+            name: "id".to_string(),
+            column_type: ColumnType::Integer,
+            primary: true,
+            autoincrement: true,
+            unique: false,
+            collation: None,
+            default: None,
+            generated: None,
+        },
+    );
+    let columns = table
+        .columns
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| (i, c.name.clone()))
+        .collect::<Vec<_>>();
+    let mut rows = |count: usize| {
+        (0..count)
+            .map(|_| {
+                let mut row = Vec::<SimValue>::arbitrary_from(rng, &table);
+                row[0] = SimValue(Value::Null);
+                row
+            })
+            .collect::<Vec<_>>()
+    };
+    let num_rows = rng.gen_range(1..=5);
+    let (values, new_values) = (rows(num_rows), rows(1));
+
+    Property::AutoincrementNoReuse {
+        insert: Insert::Values {
+            table: table.name.clone(),
+            values,
+            columns: Some(columns.clone()),
+            or_conflict: None,
+            upsert: None,
+        },
+        delete: Delete {
+            table: table.name.clone(),
+            predicate: Predicate(ast::Expr::Binary(
+                Box::new(ast::Expr::Id(ast::Id("id".to_string()))),
+                ast::Operator::Equals,
+                Box::new(ast::Expr::Literal(ast::Literal::Numeric(
+                    num_rows.to_string(),
+                ))),
+            )),
+            order_by: vec![],
+            limit: None,
+        },
+        reinsert: Insert::Values {
+            table: table.name.clone(),
+            values: new_values,
+            columns: Some(columns),
+            or_conflict: None,
+            upsert: None,
+        },
+        create: Create { table },
+    }
+}
+
+fn property_vacuum_preserves_data<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
     let table = pick(&env.tables, rng);
-    // Create the table
-    let create_query = Create {
-        table: table.clone(),
-    };
-
-    // Create random queries respecting the constraints
-    let mut queries = Vec::new();
-    // The interactions in the middle has the following constraints;
-    // - [x] There will be no errors in the middle interactions.(best effort)
-    // - [ ] Table `t` will not be renamed or dropped.(todo: add this constraint once ALTER or DROP is implemented)
-    for _ in 0..rng.gen_range(0..3) {
-        let query = Query::arbitrary_from(rng, (env, remaining));
-        if let Query::Create(Create { table: t }) = &query {
-            // There will be no errors in the middle interactions.
-            // - Creating the same table is an error
-            if t.name == table.name {
-                continue;
-            }
-        }
-        queries.push(query);
+    Property::VacuumPreservesData {
+        select: Select {
+            table: table.name.clone(),
+            table_alias: None,
+            result_columns: vec![ResultColumn::Star],
+            predicate: Predicate::arbitrary_from(rng, table),
+            limit: None,
+            distinct: Distinctness::All,
+            order_by: vec![],
+            offset: None,
+            group_by: None,
+            join: None,
+        },
     }
+}
 
-    Property::DoubleCreateFailure {
-        create: create_query,
-        queries,
+fn property_limit_offset_pagination<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let table = pick(&env.tables, rng);
+    // Prefer indexed columns, as an index can be scanned in the order of the query
+    let column = if !table.indexed_columns.is_empty() && rng.gen_bool(0.5) {
+        pick(&table.indexed_columns, rng).clone()
+    } else {
+        pick(&table.columns, rng).name.clone()
+    };
+    let order = if rng.gen_bool(0.5) {
+        SortOrder::Asc
+    } else {
+        SortOrder::Desc
+    };
+    Property::LimitOffsetPagination {
+        select: Select {
+            table: table.name.clone(),
+            table_alias: None,
+            result_columns: vec![ResultColumn::Star],
+            predicate: Predicate::arbitrary_from(rng, table),
+            limit: None,
+            distinct: Distinctness::All,
+            order_by: vec![
+                (OrderingTerm::Column(column), order.clone()),
+                (OrderingTerm::Column("rowid".to_string()), order),
+            ],
+            offset: None,
+            group_by: None,
+            join: None,
+        },
+        page_size: rng.gen_range(1..=5),
+        pages: rng.gen_range(2..=4),
     }
 }
 
-fn property_delete_select<R: rand::Rng>(
-    rng: &mut R,
-    env: &SimulatorEnv,
-    remaining: &Remaining,
-) -> Property {
-    // Get a random table
+fn property_group_having_consistency<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
     let table = pick(&env.tables, rng);
-    // Generate a random predicate
-    let predicate = Predicate::arbitrary_from(rng, table);
+    let mut select = grouped_select(rng, table);
+    let group_by = select.group_by.as_mut().unwrap();
+    let having = group_by
+        .having
+        .get_or_insert_with(|| Having::arbitrary_from(rng, table));
+    // Return the aggregate the groups are filtered on
+    select.result_columns[1] = ResultColumn::Aggregate(having.aggregate.clone());
+    select.limit = None;
+    Property::GroupHavingConsistency { select }
+}
 
-    // Create random queries respecting the constraints
-    let mut queries = Vec::new();
-    // - [x] There will be no errors in the middle interactions. (this constraint is impossible to check, so this is just best effort)
-    // - [x] A row that holds for the predicate will not be inserted.
-    // - [ ] The table `t` will not be renamed, dropped, or altered. (todo: add this constraint once ALTER or DROP is implemented)
-    for _ in 0..rng.gen_range(0..3) {
-        let query = Query::arbitrary_from(rng, (env, remaining));
-        match &query {
-            Query::Insert(Insert::Values { table: t, values }) => {
-                // A row that holds for the predicate will not be inserted.
-                if t == &table.name && values.iter().any(|v| predicate.test(v, table)) {
-                    continue;
-                }
-            }
-            Query::Create(Create { table: t }) => {
-                // There will be no errors in the middle interactions.
-                // - Creating the same table is an error
-                if t.name == table.name {
-                    continue;
-                }
-            }
-            _ => (),
-        }
-        queries.push(query);
+fn property_prepared_rebind_consistency<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let table = pick(&env.tables, rng);
+    let columns = (0..rng.gen_range(1..=2))
+        .map(|_| pick(&table.columns, rng))
+        .collect::<Vec<_>>();
+    let conditions = columns
+        .iter()
+        .map(|column| {
+            let operator = *pick(
+                &[
+                    ast::Operator::Equals,
+                    ast::Operator::NotEquals,
+                    ast::Operator::Greater,
+                    ast::Operator::GreaterEquals,
+                    ast::Operator::Less,
+                    ast::Operator::LessEquals,
+                ],
+                rng,
+            );
+            (column.name.clone(), operator)
+        })
+        .collect();
+    let bindings = (0..rng.gen_range(2..=4))
+        .map(|_| {
+            columns
+                .iter()
+                .map(|column| {
+                    // Prefer values of existing rows, so that the comparisons are not all one-sided
+                    let position = table.columns.iter().position(|c| c.name == column.name);
+                    match position {
+                        Some(i) if !table.rows.is_empty() && rng.gen_bool(0.5) => {
+                            pick(&table.rows, rng)[i].clone()
+                        }
+                        _ => SimValue::arbitrary_from(rng, &column.column_type),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    Property::PreparedRebindConsistency {
+        prepared: Prepared {
+            table: table.name.clone(),
+            conditions,
+            bindings,
+        },
     }
+}
 
-    Property::DeleteSelect {
+fn property_is_null_partition<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let table = pick(&env.tables, rng);
+    Property::IsNullPartition {
         table: table.name.clone(),
-        predicate,
-        queries,
+        column: pick(&table.columns, rng).name.clone(),
     }
 }
 
-fn property_drop_select<R: rand::Rng>(
-    rng: &mut R,
-    env: &SimulatorEnv,
-    remaining: &Remaining,
-) -> Property {
-    // Get a random table
+fn property_distinct_agg_consistency<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
     let table = pick(&env.tables, rng);
+    Property::DistinctAggConsistency {
+        table: table.name.clone(),
+        column: pick(&table.columns, rng).name.clone(),
+    }
+}
 
-    // Create random queries respecting the constraints
-    let mut queries = Vec::new();
-    // - [x] There will be no errors in the middle interactions. (this constraint is impossible to check, so this is just best effort)
-    // - [-] The table `t` will not be created, no table will be renamed to `t`. (todo: update this constraint once ALTER is implemented)
-    for _ in 0..rng.gen_range(0..3) {
-        let query = Query::arbitrary_from(rng, (env, remaining));
-        if let Query::Create(Create { table: t }) = &query {
-            // - The table `t` will not be created
-            if t.name == table.name {
-                continue;
-            }
-        }
-        queries.push(query);
+fn property_between_consistency<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let table = pick(&env.tables, rng);
+    let column_index = pick_index(table.columns.len(), rng);
+    let column = &table.columns[column_index];
+    // A bound taken from a row puts the row on the inclusive edge of the range
+    let bound = |rng: &mut R| match rng.gen_range(0..10) {
+        0 => SimValue(Value::Null),
+        1..=5 if !table.rows.is_empty() => pick(&table.rows, rng)[column_index].clone(),
+        _ => SimValue::arbitrary_from(rng, &column.column_type),
+    };
+    let (low, high) = (bound(rng), bound(rng));
+    // Ranges are mostly in order, an inverted range selects nothing
+    let (low, high) = if rng.gen_bool(0.8) && high < low {
+        (high, low)
+    } else {
+        (low, high)
+    };
+    Property::BetweenConsistency {
+        table: table.name.clone(),
+        column: column.name.clone(),
+        not: rng.gen_bool(0.5),
+        low,
+        high,
     }
+}
 
-    let select = Select {
+fn property_create_index_consistency<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let tables = env
+        .tables
+        .iter()
+        .filter(|t| !t.rows.is_empty())
+        .collect::<Vec<_>>();
+    let table = *pick(&tables, rng);
+    let row = pick(&table.rows, rng).clone();
+    // VIRTUAL generated columns cannot be indexed
+    let indexable = table
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.generated.as_ref().is_none_or(|g| g.stored))
+        .collect::<Vec<_>>();
+    let (position, column) = *pick(&indexable, rng);
+    let order = if rng.gen_bool(0.5) {
+        SortOrder::Asc
+    } else {
+        SortOrder::Desc
+    };
+    let index = CreateIndex {
+        index_name: format!(
+            "idx_{}_{}",
+            table.name,
+            gen_random_text(rng).chars().take(8).collect::<String>()
+        ),
+        table_name: table.name.clone(),
+        columns: vec![(column.name.clone(), order)],
+        unique: false,
+    };
+
+    // `= NULL` never holds, so a NULL key is looked up with `IS NULL`
+    let key = &row[position];
+    let operator = match key.0 {
+        Value::Null => ast::Operator::Is,
+        _ => ast::Operator::Equals,
+    };
+    let lookup = Select {
         table: table.name.clone(),
+        table_alias: None,
         result_columns: vec![ResultColumn::Star],
-        predicate: Predicate::arbitrary_from(rng, table),
+        predicate: Predicate(ast::Expr::Binary(
+            Box::new(ast::Expr::Id(ast::Id(column.name.clone()))),
+            operator,
+            Box::new(ast::Expr::Literal(key.into())),
+        )),
         limit: None,
         distinct: Distinctness::All,
+        order_by: vec![],
+        offset: None,
+        group_by: None,
+        join: None,
     };
 
-    Property::DropSelect {
-        table: table.name.clone(),
-        queries,
-        select,
-    }
+    Property::CreateIndexConsistency { index, lookup, row }
 }
 
-fn property_select_select_optimizer<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
-    // Get a random table
-    let table = pick(&env.tables, rng);
-    // Generate a random predicate
-    let predicate = Predicate::arbitrary_from(rng, table);
-    // Transform into a Binary predicate to force values to be casted to a bool
-    let expr = ast::Expr::Binary(
-        Box::new(predicate.0),
-        ast::Operator::And,
-        Box::new(Predicate::true_().0),
-    );
+fn property_reindex_preserves_results<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    // Prefer tables that have indexes, as those are the ones REINDEX actually rebuilds
+    let indexed_tables = env
+        .tables
+        .iter()
+        .filter(|t| !t.indexed_columns.is_empty())
+        .collect::<Vec<_>>();
+    let table = if indexed_tables.is_empty() {
+        pick(&env.tables, rng)
+    } else {
+        *pick(&indexed_tables, rng)
+    };
 
-    Property::SelectSelectOptimizer {
-        table: table.name.clone(),
-        predicate: Predicate(expr),
+    Property::ReindexPreservesResults {
+        select: Select {
+            table: table.name.clone(),
+            table_alias: None,
+            result_columns: vec![ResultColumn::Star],
+            predicate: Predicate::arbitrary_from(rng, table),
+            limit: None,
+            distinct: Distinctness::All,
+            order_by: vec![],
+            offset: None,
+            group_by: None,
+            join: None,
+        },
     }
 }
 
-fn property_fsync_no_wait<R: rand::Rng>(
+fn property_unique_allows_multiple_nulls<R: rand::Rng>(
     rng: &mut R,
     env: &SimulatorEnv,
-    remaining: &Remaining,
 ) -> Property {
-    Property::FsyncNoWait {
-        query: Query::arbitrary_from(rng, (env, remaining)),
-        tables: env.tables.iter().map(|t| t.name.clone()).collect(),
+    let table = Table::arbitrary_from(rng, env.opts.max_columns);
+    let key = pick_index(table.columns.len(), rng);
+    let index = CreateIndex {
+        index_name: format!("idx_{}_unique", table.name),
+        table_name: table.name.clone(),
+        columns: vec![(table.columns[key].name.clone(), SortOrder::Asc)],
+        unique: true,
+    };
+
+    let key_value = loop {
+        let value = SimValue::arbitrary_from(rng, &table.columns[key].column_type);
+        if !matches!(value.0, Value::Null) {
+            break value;
+        }
+    };
+    let mut row_with_key = |key_value: SimValue| {
+        let mut row = Vec::<SimValue>::arbitrary_from(rng, &table);
+        row[key] = key_value;
+        Insert::Values {
+            table: table.name.clone(),
+            values: vec![row],
+            columns: None,
+            or_conflict: None,
+            upsert: None,
+        }
+    };
+    let nulls = (0..2)
+        .map(|_| row_with_key(SimValue(Value::Null)))
+        .collect::<Vec<_>>();
+    let value = row_with_key(key_value.clone());
+    let duplicate = row_with_key(key_value);
+
+    Property::UniqueAllowsMultipleNulls {
+        create: Create { table },
+        index,
+        nulls,
+        value,
+        duplicate,
     }
 }
 
-fn property_faulty_query<R: rand::Rng>(
-    rng: &mut R,
-    env: &SimulatorEnv,
-    remaining: &Remaining,
-) -> Property {
-    Property::FaultyQuery {
-        query: Query::arbitrary_from(rng, (env, remaining)),
-        tables: env.tables.iter().map(|t| t.name.clone()).collect(),
+fn property_savepoint_rollback<R: rand::Rng>(rng: &mut R, env: &SimulatorEnv) -> Property {
+    let table = pick(&env.tables, rng);
+    let mut insert = || Insert::Values {
+        table: table.name.clone(),
+        values: vec![Vec::<SimValue>::arbitrary_from(rng, (table, &env.domains))],
+        columns: table.insert_columns(),
+        or_conflict: None,
+        upsert: None,
+    };
+    Property::SavepointRollback {
+        outer: insert(),
+        inner: insert(),
     }
 }
 
@@ -854,6 +2921,154 @@ impl ArbitraryFrom<(&SimulatorEnv, &InteractionStats)> for Property {
                     },
                     Box::new(|rng: &mut R| property_faulty_query(rng, env, &remaining_)),
                 ),
+                (
+                    // Creates both a parent and a child table
+                    if !env.opts.disable_foreign_key_enforced
+                        && env.tables.len() + 2 <= env.opts.max_tables
+                    {
+                        remaining_.create / 2.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_foreign_key_enforced(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_reindex_preserves_results {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_reindex_preserves_results(rng, env)),
+                ),
+                (
+                    if env.opts.experimental_indexes
+                        && !env.opts.disable_unique_allows_multiple_nulls
+                    {
+                        remaining_.create / 2.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_unique_allows_multiple_nulls(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_savepoint_rollback {
+                        remaining_.write / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_savepoint_rollback(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_select_after_reopen_stable
+                        && !env.opts.disable_reopen_database
+                    {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_select_after_reopen_stable(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_select_after_checkpoint_stable
+                        && !env.opts.disable_checkpoint
+                    {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_select_after_checkpoint_stable(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_vacuum_preserves_data {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_vacuum_preserves_data(rng, env)),
+                ),
+                (
+                    // Needs a column set by the insert and another one left to its default
+                    if !env.opts.disable_default_applied && env.opts.max_columns >= 2 {
+                        remaining_.create / 2.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_default_applied(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_limit_offset_pagination {
+                        remaining_.read / 2.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_limit_offset_pagination(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_group_having_consistency {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_group_having_consistency(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_prepared_rebind_consistency {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_prepared_rebind_consistency(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_is_null_partition {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_is_null_partition(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_distinct_agg_consistency {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_distinct_agg_consistency(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_between_consistency {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_between_consistency(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_create_index_consistency
+                        && env.tables.iter().any(|t| !t.rows.is_empty())
+                    {
+                        remaining_.create_index / 2.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_create_index_consistency(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_autoincrement_no_reuse {
+                        remaining_.create / 2.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|rng: &mut R| property_autoincrement_no_reuse(rng, env)),
+                ),
+                (
+                    if !env.opts.disable_schema_round_trip && !env.tables.is_empty() {
+                        remaining_.read / 4.0
+                    } else {
+                        0.0
+                    },
+                    Box::new(|_: &mut R| property_schema_round_trip(env)),
+                ),
             ],
             rng,
         )
@@ -1,34 +1,319 @@
 use std::collections::HashSet;
 
 use crate::generation::{Arbitrary, ArbitraryFrom};
+use crate::model::query::create_index::SortOrder;
+use crate::model::query::insert::{ConflictResolution, Upsert};
 use crate::model::query::predicate::Predicate;
-use crate::model::query::select::{Distinctness, ResultColumn};
-use crate::model::query::update::Update;
-use crate::model::query::{Create, Delete, Drop, Insert, Query, Select};
-use crate::model::table::{SimValue, Table};
+use crate::model::query::select::{
+    Aggregate, AggregateFunc, Distinctness, GroupBy, Having, Join, JoinOperator, OrderingTerm,
+    ResultColumn,
+};
+use crate::model::query::update::{Update, UpdateFrom};
+use crate::model::query::{Alter, Create, Delete, Drop, Insert, Query, Select};
+use crate::model::table::{
+    Collation, Column, ColumnDefault, GeneratedColumn, Name, SimValue, Table,
+};
 use crate::SimulatorEnv;
 use rand::Rng;
+use turso_core::Value;
+use turso_sqlite3_parser::ast;
 
 use super::property::Remaining;
-use super::{backtrack, frequency, pick};
+use super::{backtrack, frequency, pick, pick_n_unique};
 
-impl Arbitrary for Create {
-    fn arbitrary<R: Rng>(rng: &mut R) -> Self {
-        Create {
-            table: Table::arbitrary(rng),
+/// Probability of adding a generated column to a created table
+const GENERATED_COLUMN_PROBABILITY: f64 = 0.2;
+/// Probability of giving an insert or an update an `OR <resolution>` clause
+const CONFLICT_CLAUSE_PROBABILITY: f64 = 0.1;
+
+impl ArbitraryFrom<&SimulatorEnv> for Create {
+    fn arbitrary_from<R: Rng>(rng: &mut R, env: &SimulatorEnv) -> Self {
+        let mut table = Table::arbitrary_from(rng, env.opts.max_columns);
+        if table.columns.len() < env.opts.max_columns && rng.gen_bool(GENERATED_COLUMN_PROBABILITY)
+        {
+            let source = pick(&table.columns, rng).clone();
+            let name = loop {
+                let name = Name::arbitrary(rng).0;
+                if table.columns.iter().all(|c| c.name != name) {
+                    break name;
+                }
+            };
+            table.columns.push(Column {
+                name,
+                column_type: source.column_type,
+                primary: false,
+                autoincrement: false,
+                unique: false,
+                collation: None,
+                default: None,
+                generated: Some(GeneratedColumn {
+                    source: source.name,
+                    stored: rng.gen_bool(0.5),
+                }),
+            });
         }
+        Create { table }
     }
 }
 
 impl ArbitraryFrom<&SimulatorEnv> for Select {
     fn arbitrary_from<R: Rng>(rng: &mut R, env: &SimulatorEnv) -> Self {
         let table = pick(&env.tables, rng);
-        Self {
+        if rng.gen_bool(GROUP_BY_PROBABILITY) {
+            return grouped_select(rng, table);
+        }
+        if rng.gen_bool(JOIN_PROBABILITY) {
+            if let Some(join) = arbitrary_join(rng, env, table) {
+                return Self {
+                    table: table.name.clone(),
+                    table_alias: None,
+                    result_columns: vec![ResultColumn::Star],
+                    predicate: Predicate::arbitrary_from(rng, table),
+                    limit: Some(rng.gen_range(0..=1000)),
+                    distinct: Distinctness::All,
+                    order_by: vec![],
+                    offset: None,
+                    group_by: None,
+                    join: Some(join),
+                };
+            }
+        }
+        let predicate = Predicate::arbitrary_from(rng, table);
+        let max_keys = env.opts.max_order_by_keys.min(table.columns.len());
+        let order_by = if max_keys > 0 && rng.gen_bool(0.5) {
+            let num_keys = rng.gen_range(1..=max_keys);
+            // Every column is a result column of `SELECT *`, so keys may refer to them by position
+            let positional = rng.gen_bool(POSITIONAL_ORDER_BY_PROBABILITY);
+            let mut order_by = pick_n_unique(0..table.columns.len(), num_keys, rng)
+                .into_iter()
+                .map(|i| {
+                    let order = if rng.gen_bool(0.5) {
+                        SortOrder::Asc
+                    } else {
+                        SortOrder::Desc
+                    };
+                    let term = if positional {
+                        OrderingTerm::Position(i + 1)
+                    } else {
+                        OrderingTerm::Column(table.columns[i].name.clone())
+                    };
+                    (term, order)
+                })
+                .collect::<Vec<_>>();
+            // Rows with equal keys may come back in any order, so ties are broken by rowid
+            order_by.push((OrderingTerm::Column("rowid".to_string()), SortOrder::Asc));
+            order_by
+        } else {
+            vec![]
+        };
+        let select = Self {
             table: table.name.clone(),
+            table_alias: None,
             result_columns: vec![ResultColumn::Star],
-            predicate: Predicate::arbitrary_from(rng, table),
+            predicate,
             limit: Some(rng.gen_range(0..=1000)),
             distinct: Distinctness::All,
+            order_by,
+            offset: None,
+            group_by: None,
+            join: None,
+        };
+        if rng.gen_range(0..100) < env.opts.alias_probability {
+            // Short alias built from the initials of the table name, e.g. `user_accounts ua3`.
+            // The trailing digit keeps it from ever being a keyword such as `or` or `in`.
+            let initials = table
+                .name
+                .split('_')
+                .filter_map(|word| word.chars().next())
+                .collect::<String>();
+            let alias = format!("{}{}", initials, rng.gen_range(0..10));
+            select.with_alias(table, alias)
+        } else {
+            select
+        }
+    }
+}
+
+/// Probability of generating a `GROUP BY` query instead of a plain one
+const GROUP_BY_PROBABILITY: f64 = 0.2;
+/// Probability of writing the `ORDER BY` keys of a plain query as result column positions
+const POSITIONAL_ORDER_BY_PROBABILITY: f64 = 0.3;
+/// Probability of joining a second table, as in `FROM t1, t2` or `FROM t1 LEFT JOIN t2`
+const JOIN_PROBABILITY: f64 = 0.1;
+
+/// Picks a table to join with `table`, usually with an equality between two of their columns
+/// that share a type and compare without a collation. Only joins with such an equality are
+/// `LEFT JOIN`s, and the joined table is sometimes filtered as well.
+fn arbitrary_join<R: Rng>(rng: &mut R, env: &SimulatorEnv, table: &Table) -> Option<Join> {
+    // The predicate refers to the columns of `table` unqualified, so they must not be ambiguous
+    let others = env
+        .tables
+        .iter()
+        .filter(|other| {
+            other.name != table.name
+                && !other
+                    .columns
+                    .iter()
+                    .any(|c| table.columns.iter().any(|t| t.name == c.name))
+        })
+        .collect::<Vec<_>>();
+    if others.is_empty() {
+        return None;
+    }
+    let other = pick(&others, rng);
+    let join_columns = table
+        .columns
+        .iter()
+        .flat_map(|left| {
+            other
+                .columns
+                .iter()
+                .filter(move |right| {
+                    left.column_type == right.column_type
+                        && left.collation.is_none()
+                        && right.collation.is_none()
+                })
+                .map(move |right| (left.name.clone(), right.name.clone()))
+        })
+        .collect::<Vec<_>>();
+    let on = if !join_columns.is_empty() && rng.gen_bool(0.8) {
+        Some(pick(&join_columns, rng).clone())
+    } else {
+        None
+    };
+    let operator = if on.is_some() && rng.gen_bool(0.5) {
+        JoinOperator::Left
+    } else {
+        JoinOperator::Comma
+    };
+    let predicate = rng
+        .gen_bool(0.5)
+        .then(|| Predicate::arbitrary_from(rng, *other));
+    Some(Join {
+        table: other.name.clone(),
+        operator,
+        on,
+        predicate,
+    })
+}
+
+/// Generates `SELECT <c>, <aggregate> FROM <t> WHERE <predicate> GROUP BY <c> [HAVING ...]`,
+/// sometimes ordering the groups by `c`
+pub(crate) fn grouped_select<R: Rng>(rng: &mut R, table: &Table) -> Select {
+    let group_by = GroupBy::arbitrary_from(rng, table);
+    let aggregate = match &group_by.having {
+        Some(having) => having.aggregate.clone(),
+        None => Aggregate::arbitrary_from(rng, table),
+    };
+    let order_by = if rng.gen_bool(0.5) {
+        let order = if rng.gen_bool(0.5) {
+            SortOrder::Asc
+        } else {
+            SortOrder::Desc
+        };
+        vec![(OrderingTerm::Column(group_by.column.clone()), order)]
+    } else {
+        vec![]
+    };
+    Select {
+        table: table.name.clone(),
+        table_alias: None,
+        result_columns: vec![
+            ResultColumn::Column(group_by.column.clone()),
+            ResultColumn::Aggregate(aggregate),
+        ],
+        predicate: Predicate::arbitrary_from(rng, table),
+        limit: Some(rng.gen_range(0..=1000)),
+        distinct: Distinctness::All,
+        order_by,
+        offset: None,
+        group_by: Some(group_by),
+        join: None,
+    }
+}
+
+impl ArbitraryFrom<&Table> for GroupBy {
+    fn arbitrary_from<R: Rng>(rng: &mut R, table: &Table) -> Self {
+        GroupBy {
+            column: pick(&table.columns, rng).name.clone(),
+            having: rng
+                .gen_bool(0.75)
+                .then(|| Having::arbitrary_from(rng, table)),
+        }
+    }
+}
+
+impl ArbitraryFrom<&Table> for Having {
+    fn arbitrary_from<R: Rng>(rng: &mut R, table: &Table) -> Self {
+        let aggregate = Aggregate::arbitrary_from(rng, table);
+        let operator = *pick(
+            &[
+                ast::Operator::Equals,
+                ast::Operator::NotEquals,
+                ast::Operator::Greater,
+                ast::Operator::GreaterEquals,
+                ast::Operator::Less,
+                ast::Operator::LessEquals,
+            ],
+            rng,
+        );
+        let column = aggregate
+            .column
+            .as_ref()
+            .and_then(|name| table.columns.iter().position(|c| &c.name == name));
+        let value = match (aggregate.func, column) {
+            (AggregateFunc::Min | AggregateFunc::Max, Some(pos)) => {
+                // Compare to a value of the column so that some groups pass and others don't
+                let values = table
+                    .rows
+                    .iter()
+                    .map(|row| &row[pos])
+                    .filter(|value| !matches!(value.0, Value::Null))
+                    .collect::<Vec<_>>();
+                if values.is_empty() {
+                    SimValue::arbitrary_from(rng, &table.columns[pos].column_type)
+                } else {
+                    SimValue::arbitrary_from(rng, &values)
+                }
+            }
+            _ => SimValue(Value::Integer(rng.gen_range(0..=3))),
+        };
+        Having {
+            aggregate,
+            operator,
+            value,
+        }
+    }
+}
+
+impl ArbitraryFrom<&Table> for Aggregate {
+    fn arbitrary_from<R: Rng>(rng: &mut R, table: &Table) -> Self {
+        // Which of several values that only compare equal under their collation `min` and
+        // `max` return is unspecified, so they only apply to columns compared byte by byte
+        let binary = table
+            .columns
+            .iter()
+            .filter(|c| c.collation.unwrap_or_default() == Collation::Binary)
+            .collect::<Vec<_>>();
+        let func = if binary.is_empty() {
+            AggregateFunc::Count
+        } else {
+            *pick(
+                &[AggregateFunc::Count, AggregateFunc::Min, AggregateFunc::Max],
+                rng,
+            )
+        };
+        let column = match func {
+            AggregateFunc::Count => rng
+                .gen_bool(0.5)
+                .then(|| pick(&table.columns, rng).name.clone()),
+            AggregateFunc::Min | AggregateFunc::Max => Some(pick(&binary, rng).name.clone()),
+        };
+        let distinct = column.is_some() && rng.gen_bool(0.25);
+        Aggregate {
+            func,
+            column,
+            distinct,
         }
     }
 }
@@ -37,19 +322,100 @@ impl ArbitraryFrom<&SimulatorEnv> for Insert {
     fn arbitrary_from<R: Rng>(rng: &mut R, env: &SimulatorEnv) -> Self {
         let gen_values = |rng: &mut R| {
             let table = pick(&env.tables, rng);
+            // Sometimes leave out columns that have a default, so that the default applies
+            let mut omitted = table
+                .columns
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| matches!(c.default, Some(ColumnDefault::Value(_))))
+                .filter(|_| rng.gen_bool(0.5))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            // Generated columns cannot be written, so they are always left out
+            let generated = table
+                .columns
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.generated.is_some())
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            if omitted.len() + generated.len() == table.columns.len() {
+                omitted.pop();
+            }
+            omitted.extend(generated);
             let num_rows = rng.gen_range(1..10);
             let values: Vec<Vec<SimValue>> = (0..num_rows)
                 .map(|_| {
-                    table
+                    let mut row = table
                         .columns
                         .iter()
-                        .map(|c| SimValue::arbitrary_from(rng, &c.column_type))
-                        .collect()
+                        .enumerate()
+                        .map(|(i, c)| match &c.default {
+                            Some(default) if omitted.contains(&i) => default.value(),
+                            _ => SimValue::arbitrary_from(
+                                rng,
+                                (c, env.domains.get(&table.name, &c.name)),
+                            ),
+                        })
+                        .collect::<Vec<_>>();
+                    table.fill_generated(&mut row);
+                    row
                 })
                 .collect();
+            let columns = (!omitted.is_empty()).then(|| {
+                table
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !omitted.contains(i))
+                    .map(|(i, c)| (i, c.name.clone()))
+                    .collect()
+            });
+            // Replacing a row could delete the parent of a child row, which the model does not
+            // check, so that is left to the tables no foreign key refers to
+            let is_parent = env.tables.iter().any(|t| {
+                t.foreign_keys
+                    .iter()
+                    .any(|fk| fk.parent_table == table.name)
+            });
+            let or_conflict =
+                rng.gen_bool(CONFLICT_CLAUSE_PROBABILITY)
+                    .then(|| match rng.gen_range(0..3) {
+                        0 => ConflictResolution::Ignore,
+                        1 if !is_parent => ConflictResolution::Replace,
+                        _ => ConflictResolution::Rollback,
+                    });
+            // Without a conflict target, the upsert applies to whichever UNIQUE index the row
+            // violates, which is only unambiguous with a single one
+            let single_unique_key =
+                table.unique_indexes.len() == 1 && !table.columns.iter().any(|c| c.primary);
+            let upsert = (or_conflict.is_none()
+                && single_unique_key
+                && !is_parent
+                && rng.gen_bool(CONFLICT_CLAUSE_PROBABILITY))
+            .then(|| {
+                let writable = table
+                    .columns
+                    .iter()
+                    .filter(|c| c.generated.is_none())
+                    .collect::<Vec<_>>();
+                if rng.gen_bool(0.5) {
+                    return Upsert::DoNothing;
+                }
+                let num_columns = rng.gen_range(1..=writable.len());
+                Upsert::DoUpdate(
+                    pick_n_unique(0..writable.len(), num_columns, rng)
+                        .into_iter()
+                        .map(|i| writable[i].name.clone())
+                        .collect(),
+                )
+            });
             Some(Insert::Values {
                 table: table.name.clone(),
                 values,
+                columns,
+                or_conflict,
+                upsert,
             })
         };
 
@@ -61,10 +427,15 @@ impl ArbitraryFrom<&SimulatorEnv> for Insert {
             // Pick another table to insert into
             let select = Select {
                 table: select_table.name.clone(),
+                table_alias: None,
                 result_columns: vec![ResultColumn::Star],
                 predicate,
                 limit: None,
                 distinct: Distinctness::All,
+                order_by: vec![],
+                offset: None,
+                group_by: None,
+                join: None,
             };
             let table = pick(&env.tables, rng);
             Some(Insert::Select {
@@ -89,9 +460,30 @@ impl ArbitraryFrom<&SimulatorEnv> for Insert {
 impl ArbitraryFrom<&SimulatorEnv> for Delete {
     fn arbitrary_from<R: Rng>(rng: &mut R, env: &SimulatorEnv) -> Self {
         let table = pick(&env.tables, rng);
+        let predicate = Predicate::arbitrary_from(rng, table);
+        // Sometimes only delete the first few matching rows, like pruning the oldest entries
+        let (order_by, limit) = if !env.opts.disable_delete_limit && rng.gen_bool(0.25) {
+            let num_columns = rng.gen_range(1..=table.columns.len());
+            let order_by = pick_n_unique(0..table.columns.len(), num_columns, rng)
+                .into_iter()
+                .map(|i| {
+                    let order = if rng.gen_bool(0.5) {
+                        SortOrder::Asc
+                    } else {
+                        SortOrder::Desc
+                    };
+                    (table.columns[i].name.clone(), order)
+                })
+                .collect();
+            (order_by, Some(rng.gen_range(0..=5)))
+        } else {
+            (vec![], None)
+        };
         Self {
             table: table.name.clone(),
-            predicate: Predicate::arbitrary_from(rng, table),
+            predicate,
+            order_by,
+            limit,
         }
     }
 }
@@ -105,13 +497,60 @@ impl ArbitraryFrom<&SimulatorEnv> for Drop {
     }
 }
 
+/// Columns of `table` that can be removed with `ALTER TABLE ... DROP COLUMN`. Like SQLite, we
+/// refuse to drop the last column, key or unique columns, indexed columns and columns taking part
+/// in a foreign key. Tables with generated columns cannot be altered at all.
+pub(crate) fn droppable_columns<'a>(env: &SimulatorEnv, table: &'a Table) -> Vec<&'a Column> {
+    if table.columns.len() < 2 || table.columns.iter().any(|c| c.generated.is_some()) {
+        return vec![];
+    }
+    table
+        .columns
+        .iter()
+        .filter(|column| {
+            !column.primary
+                && !column.unique
+                && !table.indexed_columns.contains(&column.name)
+                && !table
+                    .foreign_keys
+                    .iter()
+                    .any(|fk| fk.columns.contains(&column.name))
+                && !env.tables.iter().any(|other| {
+                    other.foreign_keys.iter().any(|fk| {
+                        fk.parent_table == table.name && fk.parent_columns.contains(&column.name)
+                    })
+                })
+        })
+        .collect()
+}
+
+impl ArbitraryFrom<&SimulatorEnv> for Alter {
+    fn arbitrary_from<R: Rng>(rng: &mut R, env: &SimulatorEnv) -> Self {
+        let tables = env
+            .tables
+            .iter()
+            .filter(|table| !droppable_columns(env, table).is_empty())
+            .collect::<Vec<_>>();
+        assert!(
+            !tables.is_empty(),
+            "Cannot drop a column when no table has a droppable column."
+        );
+        let table = pick(&tables, rng);
+        let column = pick(&droppable_columns(env, table), rng);
+        Alter::DropColumn {
+            table: table.name.clone(),
+            column: column.name.clone(),
+        }
+    }
+}
+
 impl ArbitraryFrom<(&SimulatorEnv, &Remaining)> for Query {
     fn arbitrary_from<R: Rng>(rng: &mut R, (env, remaining): (&SimulatorEnv, &Remaining)) -> Self {
         frequency(
             vec![
                 (
                     remaining.create,
-                    Box::new(|rng| Self::Create(Create::arbitrary(rng))),
+                    Box::new(|rng| Self::Create(Create::arbitrary_from(rng, env))),
                 ),
                 (
                     remaining.read,
@@ -131,31 +570,123 @@ impl ArbitraryFrom<(&SimulatorEnv, &Remaining)> for Query {
     }
 }
 
+/// Picks a source table for an `UPDATE ... FROM` on `table`, if there is one that can be
+/// joined with it. Both join columns share a type and compare without a collation, so
+/// source rows matching the same target row hold the very same join value.
+fn arbitrary_update_from<R: Rng>(
+    rng: &mut R,
+    env: &SimulatorEnv,
+    table: &Table,
+) -> Option<UpdateFrom> {
+    // The predicate refers to the target columns unqualified, so they must not be ambiguous
+    let join_columns = env
+        .tables
+        .iter()
+        .filter(|source| {
+            source.name != table.name
+                && !source
+                    .columns
+                    .iter()
+                    .any(|c| table.columns.iter().any(|t| t.name == c.name))
+        })
+        .flat_map(|source| {
+            source.columns.iter().flat_map(move |source_column| {
+                table
+                    .columns
+                    .iter()
+                    .filter(move |target_column| {
+                        target_column.column_type == source_column.column_type
+                            && target_column.collation.is_none()
+                            && source_column.collation.is_none()
+                    })
+                    .map(move |target_column| (source, source_column, target_column))
+            })
+        })
+        .collect::<Vec<_>>();
+    if join_columns.is_empty() {
+        return None;
+    }
+    let (source, source_column, target_column) = pick(&join_columns, rng);
+    let assignable = table
+        .columns
+        .iter()
+        .filter(|c| c.column_type == source_column.column_type && c.generated.is_none())
+        .collect::<Vec<_>>();
+    if assignable.is_empty() {
+        return None;
+    }
+    let num_cols = rng.gen_range(1..=assignable.len());
+    let columns = pick_n_unique(0..assignable.len(), num_cols, rng)
+        .into_iter()
+        .map(|i| assignable[i].name.clone())
+        .collect();
+    Some(UpdateFrom {
+        table: source.name.clone(),
+        alias: format!("src{}", rng.gen_range(0..10)),
+        target_column: target_column.name.clone(),
+        source_column: source_column.name.clone(),
+        columns,
+    })
+}
+
 impl ArbitraryFrom<&SimulatorEnv> for Update {
     fn arbitrary_from<R: Rng>(rng: &mut R, env: &SimulatorEnv) -> Self {
         let table = pick(&env.tables, rng);
-        let mut seen = HashSet::new();
-        let num_cols = rng.gen_range(1..=table.columns.len());
+        let from = if rng.gen_bool(0.3) {
+            arbitrary_update_from(rng, env, table)
+        } else {
+            None
+        };
+        // Columns set from the source table are not set to a literal as well
+        let mut seen = from
+            .as_ref()
+            .map_or_else(HashSet::new, |from| from.columns.iter().cloned().collect());
+        // Generated columns cannot be set
+        let writable = table
+            .columns
+            .iter()
+            .filter(|c| c.generated.is_none())
+            .collect::<Vec<_>>();
+        let min_cols = if from.is_some() { 0 } else { 1 };
+        let num_cols = rng.gen_range(min_cols..=writable.len() - seen.len());
         let set_values: Vec<(String, SimValue)> = (0..num_cols)
             .map(|_| {
                 let column = loop {
-                    let column = pick(&table.columns, rng);
+                    let column = *pick(&writable, rng);
                     if seen.contains(&column.name) {
                         continue;
                     }
                     break column;
                 };
                 seen.insert(column.name.clone());
+                let domain = env.domains.get(&table.name, &column.name);
                 (
                     column.name.clone(),
-                    SimValue::arbitrary_from(rng, &column.column_type),
+                    SimValue::arbitrary_from(rng, (column, domain)),
                 )
             })
             .collect();
-        Update {
+        let or_conflict = rng.gen_bool(CONFLICT_CLAUSE_PROBABILITY).then(|| {
+            if rng.gen_bool(0.5) {
+                ConflictResolution::Ignore
+            } else {
+                ConflictResolution::Rollback
+            }
+        });
+        let mut update = Update {
             table: table.name.clone(),
             set_values,
             predicate: Predicate::arbitrary_from(rng, table),
+            from,
+            or_conflict,
+        };
+        // Which rows `OR IGNORE` skips depends on the order the database updates them in when
+        // two of them would get the same key, so that case is left without the clause
+        if update.or_conflict == Some(ConflictResolution::Ignore)
+            && update.updated_rows(env, false) != update.updated_rows(env, true)
+        {
+            update.or_conflict = None;
         }
+        update
     }
 }
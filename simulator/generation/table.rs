@@ -4,7 +4,8 @@ use rand::Rng;
 use turso_core::Value;
 
 use crate::generation::{gen_random_text, pick, readable_name_custom, Arbitrary, ArbitraryFrom};
-use crate::model::table::{Column, ColumnType, Name, SimValue, Table};
+use crate::model::domain::{Domains, ValueDomain};
+use crate::model::table::{Collation, Column, ColumnDefault, ColumnType, Name, SimValue, Table};
 
 use super::ArbitraryFromMaybe;
 
@@ -15,11 +16,21 @@ impl Arbitrary for Name {
     }
 }
 
+/// Maximum number of columns of a table generated without an explicit bound
+const DEFAULT_MAX_COLUMNS: usize = 9;
+
 impl Arbitrary for Table {
     fn arbitrary<R: Rng>(rng: &mut R) -> Self {
+        Table::arbitrary_from(rng, DEFAULT_MAX_COLUMNS)
+    }
+}
+
+/// Generates a table with at most the given number of columns
+impl ArbitraryFrom<usize> for Table {
+    fn arbitrary_from<R: Rng>(rng: &mut R, max_columns: usize) -> Self {
         let name = Name::arbitrary(rng).0;
         let columns = loop {
-            let columns = (1..=rng.gen_range(1..10))
+            let columns = (1..=rng.gen_range(1..=max_columns))
                 .map(|_| Column::arbitrary(rng))
                 .collect::<Vec<_>>();
             // TODO: see if there is a better way to detect duplicates here
@@ -36,6 +47,9 @@ impl Arbitrary for Table {
             rows: Vec::new(),
             name,
             columns,
+            foreign_keys: Vec::new(),
+            indexed_columns: Vec::new(),
+            unique_indexes: Vec::new(),
         }
     }
 }
@@ -44,15 +58,46 @@ impl Arbitrary for Column {
     fn arbitrary<R: Rng>(rng: &mut R) -> Self {
         let name = Name::arbitrary(rng).0;
         let column_type = ColumnType::arbitrary(rng);
+        // Collations only affect text comparisons
+        let collation = match column_type {
+            ColumnType::Text if rng.gen_bool(0.3) => Some(Collation::arbitrary(rng)),
+            _ => None,
+        };
+        let default = rng
+            .gen_bool(0.2)
+            .then(|| ColumnDefault::arbitrary_from(rng, &column_type));
         Self {
             name,
             column_type,
             primary: false,
+            autoincrement: false,
             unique: false,
+            collation,
+            default,
+            generated: None,
         }
     }
 }
 
+impl ArbitraryFrom<&ColumnType> for ColumnDefault {
+    fn arbitrary_from<R: Rng>(rng: &mut R, column_type: &ColumnType) -> Self {
+        // Numbers are kept small, as a default must be a single literal and the most negative
+        // integer can only be written as an expression
+        let value = match column_type {
+            ColumnType::Integer => SimValue(Value::Integer(rng.gen_range(-1000..1000))),
+            ColumnType::Float => SimValue(Value::Float(rng.gen_range(-1000.0..1000.0))),
+            ColumnType::Text | ColumnType::Blob => SimValue::arbitrary_from(rng, column_type),
+        };
+        Self::Value(value)
+    }
+}
+
+impl Arbitrary for Collation {
+    fn arbitrary<R: Rng>(rng: &mut R) -> Self {
+        pick(&[Self::Binary, Self::NoCase, Self::Rtrim], rng).to_owned()
+    }
+}
+
 impl Arbitrary for ColumnType {
     fn arbitrary<R: Rng>(rng: &mut R) -> Self {
         pick(&[Self::Integer, Self::Float, Self::Text, Self::Blob], rng).to_owned()
@@ -61,15 +106,74 @@ impl Arbitrary for ColumnType {
 
 impl ArbitraryFrom<&Table> for Vec<SimValue> {
     fn arbitrary_from<R: Rng>(rng: &mut R, table: &Table) -> Self {
+        Self::arbitrary_from(rng, (table, &Domains::default()))
+    }
+}
+
+/// A row of the table, with the values of the columns that have a domain drawn from it
+impl ArbitraryFrom<(&Table, &Domains)> for Vec<SimValue> {
+    fn arbitrary_from<R: Rng>(rng: &mut R, (table, domains): (&Table, &Domains)) -> Self {
         let mut row = Vec::new();
         for column in table.columns.iter() {
-            let value = SimValue::arbitrary_from(rng, &column.column_type);
+            let value =
+                SimValue::arbitrary_from(rng, (column, domains.get(&table.name, &column.name)));
             row.push(value);
         }
+        table.fill_generated(&mut row);
         row
     }
 }
 
+/// Probability of storing NULL in a column instead of a value of its type
+const NULL_VALUE_PROBABILITY: f64 = 0.05;
+
+/// A value to store in the column, which is sometimes NULL
+impl ArbitraryFrom<&Column> for SimValue {
+    fn arbitrary_from<R: Rng>(rng: &mut R, column: &Column) -> Self {
+        Self::arbitrary_from(rng, (column, None))
+    }
+}
+
+/// A value to store in the column, drawn from its domain if it has one
+impl ArbitraryFrom<(&Column, Option<ValueDomain>)> for SimValue {
+    fn arbitrary_from<R: Rng>(
+        rng: &mut R,
+        (column, domain): (&Column, Option<ValueDomain>),
+    ) -> Self {
+        // NULL stored in an INTEGER PRIMARY KEY is replaced with a new rowid
+        if !column.primary && rng.gen_bool(NULL_VALUE_PROBABILITY) {
+            return SimValue(Value::Null);
+        }
+        match domain {
+            Some(domain) => SimValue::arbitrary_from(rng, domain),
+            None => SimValue::arbitrary_from(rng, &column.column_type),
+        }
+    }
+}
+
+/// First names of the `name` domain
+const NAMES: &[&str] = &[
+    "alice", "bob", "carol", "dave", "erin", "frank", "grace", "heidi", "ivan", "judy", "mallory",
+    "niaj", "olivia", "peggy", "rupert", "sybil", "trent", "victor", "walter", "zoe",
+];
+
+impl ArbitraryFrom<ValueDomain> for SimValue {
+    fn arbitrary_from<R: Rng>(rng: &mut R, domain: ValueDomain) -> Self {
+        let value = match domain {
+            ValueDomain::SmallInt => Value::Integer(rng.gen_range(1..=100)),
+            ValueDomain::Name => Value::build_text(*pick(NAMES, rng)),
+            // Days past the 28th are left out so every date is valid
+            ValueDomain::Date => Value::build_text(format!(
+                "{:04}-{:02}-{:02}",
+                rng.gen_range(2000..=2030),
+                rng.gen_range(1..=12),
+                rng.gen_range(1..=28)
+            )),
+        };
+        SimValue(value)
+    }
+}
+
 impl ArbitraryFrom<&Vec<&SimValue>> for SimValue {
     fn arbitrary_from<R: Rng>(rng: &mut R, values: &Vec<&Self>) -> Self {
         if values.is_empty() {
@@ -80,8 +184,14 @@ impl ArbitraryFrom<&Vec<&SimValue>> for SimValue {
     }
 }
 
+/// Probability of generating a boundary value instead of a typical one
+const BOUNDARY_VALUE_PROBABILITY: f64 = 0.1;
+
 impl ArbitraryFrom<&ColumnType> for SimValue {
     fn arbitrary_from<R: Rng>(rng: &mut R, column_type: &ColumnType) -> Self {
+        if rng.gen_bool(BOUNDARY_VALUE_PROBABILITY) {
+            return SimValue(boundary_value(rng, column_type));
+        }
         let value = match column_type {
             ColumnType::Integer => Value::Integer(rng.gen_range(i64::MIN..i64::MAX)),
             ColumnType::Float => Value::Float(rng.gen_range(-1e10..1e10)),
@@ -92,6 +202,44 @@ impl ArbitraryFrom<&ColumnType> for SimValue {
     }
 }
 
+/// Values at the edges of each type's domain, they are the ones that tend to expose overflow,
+/// serialization and comparison bugs that typical values miss. NaN is left out since SQLite
+/// has no literal for it and stores it as NULL.
+fn boundary_value<R: Rng>(rng: &mut R, column_type: &ColumnType) -> Value {
+    match column_type {
+        ColumnType::Integer => Value::Integer(*pick(
+            &[
+                i64::MIN,
+                i64::MIN + 1,
+                -1,
+                0,
+                1,
+                1 << 53,
+                i64::MAX - 1,
+                i64::MAX,
+            ],
+            rng,
+        )),
+        ColumnType::Float => Value::Float(*pick(
+            &[
+                0.0,
+                -0.0,
+                f64::EPSILON,
+                f64::MIN_POSITIVE,
+                -f64::MIN_POSITIVE,
+                9007199254740992.0,
+                1e300,
+                f64::MAX,
+                f64::MIN,
+                f64::INFINITY,
+            ],
+            rng,
+        )),
+        ColumnType::Text => Value::build_text(""),
+        ColumnType::Blob => Value::Blob(vec![]),
+    }
+}
+
 pub(crate) struct LTValue(pub(crate) SimValue);
 
 impl ArbitraryFrom<&Vec<&SimValue>> for LTValue {
@@ -102,15 +250,23 @@ impl ArbitraryFrom<&Vec<&SimValue>> for LTValue {
 
         // Get value less than all values
         let value = Value::exec_min(values.iter().map(|value| &value.0));
-        Self::arbitrary_from(rng, &SimValue(value))
+        Self::arbitrary_from_maybe(rng, &SimValue(value)).unwrap_or(Self(SimValue(Value::Null)))
     }
 }
 
-impl ArbitraryFrom<&SimValue> for LTValue {
-    fn arbitrary_from<R: Rng>(rng: &mut R, value: &SimValue) -> Self {
+/// A value strictly less than the given one. There is none for the smallest value of a type
+/// that cannot be undercut through another type, like the empty string or the empty blob.
+impl ArbitraryFromMaybe<&SimValue> for LTValue {
+    fn arbitrary_from_maybe<R: Rng>(rng: &mut R, value: &SimValue) -> Option<Self> {
         let new_value = match &value.0 {
-            Value::Integer(i) => Value::Integer(rng.gen_range(i64::MIN..*i - 1)),
-            Value::Float(f) => Value::Float(f - rng.gen_range(0.0..1e10)),
+            Value::Integer(i64::MIN) => Value::Float(f64::MIN),
+            Value::Integer(i) => Value::Integer(rng.gen_range(i64::MIN..*i)),
+            Value::Float(f) if *f == f64::NEG_INFINITY => return None,
+            Value::Float(f) if *f == f64::INFINITY => Value::Float(f64::MAX),
+            // Scale the distance with the value so it is not absorbed by large magnitudes
+            Value::Float(f) => Value::Float(f - rng.gen_range(1.0..1e10).max(f.abs() / 2.0)),
+            Value::Text(t) if t.as_str().is_empty() => return None,
+            Value::Blob(b) if b.is_empty() => return None,
             value @ Value::Text(..) => {
                 // Either shorten the string, or make at least one character smaller and mutate the rest
                 let mut t = value.to_string();
@@ -150,7 +306,7 @@ impl ArbitraryFrom<&SimValue> for LTValue {
             }
             _ => unreachable!(),
         };
-        Self(SimValue(new_value))
+        Some(Self(SimValue(new_value)))
     }
 }
 
@@ -171,12 +327,17 @@ impl ArbitraryFrom<&Vec<&SimValue>> for GTValue {
 impl ArbitraryFrom<&SimValue> for GTValue {
     fn arbitrary_from<R: Rng>(rng: &mut R, value: &SimValue) -> Self {
         let new_value = match &value.0 {
-            Value::Integer(i) => Value::Integer(rng.gen_range(*i..i64::MAX)),
-            Value::Float(f) => Value::Float(rng.gen_range(*f..1e10)),
+            Value::Integer(i64::MAX) => Value::Float(f64::MAX),
+            Value::Integer(i) => Value::Integer(rng.gen_range(*i + 1..=i64::MAX)),
+            Value::Float(f) if *f == f64::NEG_INFINITY => Value::Float(f64::MIN),
+            // Every text sorts after every number
+            Value::Float(f) if *f == f64::INFINITY => Value::build_text(gen_random_text(rng)),
+            // Scale the distance with the value so it is not absorbed by large magnitudes
+            Value::Float(f) => Value::Float(f + rng.gen_range(1.0..1e10).max(f.abs() / 2.0)),
             value @ Value::Text(..) => {
                 // Either lengthen the string, or make at least one character smaller and mutate the rest
                 let mut t = value.to_string();
-                if rng.gen_bool(0.01) {
+                if t.is_empty() || rng.gen_bool(0.01) {
                     t.push(rng.gen_range(0..=255) as u8 as char);
                     Value::build_text(t)
                 } else {
@@ -197,7 +358,7 @@ impl ArbitraryFrom<&SimValue> for GTValue {
             Value::Blob(b) => {
                 // Either lengthen the blob, or make at least one byte smaller and mutate the rest
                 let mut b = b.clone();
-                if rng.gen_bool(0.01) {
+                if b.is_empty() || rng.gen_bool(0.01) {
                     b.push(rng.gen_range(0..=255));
                     Value::Blob(b)
                 } else {
@@ -216,22 +377,25 @@ impl ArbitraryFrom<&SimValue> for GTValue {
     }
 }
 
-pub(crate) struct LikeValue(pub(crate) SimValue);
+/// A `LIKE` pattern matching the text value it was generated from, and the `ESCAPE` character
+/// the pattern uses, if any
+pub(crate) struct LikeValue(pub(crate) SimValue, pub(crate) Option<char>);
 
 impl ArbitraryFromMaybe<&SimValue> for LikeValue {
     fn arbitrary_from_maybe<R: Rng>(rng: &mut R, value: &SimValue) -> Option<Self> {
         match &value.0 {
             value @ Value::Text(..) => {
                 let t = value.to_string();
-                let mut t = t.chars().collect::<Vec<_>>();
+                // Each character is paired with whether it is a wildcard of the pattern
+                let mut t = t.chars().map(|c| (c, false)).collect::<Vec<_>>();
                 // Remove a number of characters, either insert `_` for each character removed, or
                 // insert one `%` for the whole substring
                 let mut i = 0;
                 while i < t.len() {
                     if rng.gen_bool(0.1) {
-                        t[i] = '_';
+                        t[i] = ('_', true);
                     } else if rng.gen_bool(0.05) {
-                        t[i] = '%';
+                        t[i] = ('%', true);
                         // skip a list of characters
                         for _ in 0..rng.gen_range(0..=3.min(t.len() - i - 1)) {
                             t.remove(i + 1);
@@ -239,13 +403,60 @@ impl ArbitraryFromMaybe<&SimValue> for LikeValue {
                     }
                     i += 1;
                 }
-                let index = rng.gen_range(0..t.len());
-                t.insert(index, '%');
-                Some(Self(SimValue(Value::build_text(
-                    t.into_iter().collect::<String>(),
-                ))))
+                let index = rng.gen_range(0..=t.len());
+                t.insert(index, ('%', true));
+                // With an escape character, the `%` and `_` of the text only match themselves
+                let escape = rng.gen_bool(0.5).then(|| *pick(&['\\', '!', '#'], rng));
+                let mut pattern = String::with_capacity(t.len());
+                for (c, wildcard) in t {
+                    match escape {
+                        Some(escape) if !wildcard && (c == '%' || c == '_' || c == escape) => {
+                            pattern.push(escape);
+                        }
+                        _ => {}
+                    }
+                    pattern.push(c);
+                }
+                Some(Self(SimValue(Value::build_text(pattern)), escape))
             }
             _ => None,
         }
     }
 }
+
+/// An anchored regular expression that matches the text value it was generated from
+pub(crate) struct RegexpValue(pub(crate) SimValue);
+
+impl ArbitraryFromMaybe<&SimValue> for RegexpValue {
+    fn arbitrary_from_maybe<R: Rng>(rng: &mut R, value: &SimValue) -> Option<Self> {
+        let Value::Text(text) = &value.0 else {
+            return None;
+        };
+        let chars = text.as_str().chars().collect::<Vec<_>>();
+        let start = rng.gen_range(0..=chars.len());
+        let end = rng.gen_range(start..=chars.len());
+        let prefix = regexp_fragment(rng, &chars[..start]);
+        let suffix = regexp_fragment(rng, &chars[end..]);
+        // Anchor the prefix, the suffix, or both with anything in between
+        let pattern = match rng.gen_range(0..3) {
+            0 => format!("^{prefix}"),
+            1 => format!("{suffix}$"),
+            _ => format!("(?s)^{prefix}.*{suffix}$"),
+        };
+        Some(Self(SimValue(Value::build_text(pattern))))
+    }
+}
+
+/// Escapes `chars` into a regular expression that matches them, sometimes using `.` or a
+/// character class in place of a character
+fn regexp_fragment<R: Rng>(rng: &mut R, chars: &[char]) -> String {
+    chars
+        .iter()
+        .map(|c| match rng.gen_range(0..10) {
+            0 if *c != '\n' => ".".to_string(),
+            1 if c.is_ascii_lowercase() => "[a-z]".to_string(),
+            1 if c.is_ascii_digit() => "[0-9]".to_string(),
+            _ => regex::escape(&c.to_string()),
+        })
+        .collect()
+}
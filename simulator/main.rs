@@ -9,12 +9,16 @@ use rand::prelude::*;
 use runner::bugbase::{Bug, BugBase, LoadedBug};
 use runner::cli::{SimulatorCLI, SimulatorCommand};
 use runner::env::SimulatorEnv;
-use runner::execution::{execute_plans, Execution, ExecutionHistory, ExecutionResult};
-use runner::{differential, watch};
+use runner::execution::{
+    execute_plans, failure_report, Execution, ExecutionHistory, ExecutionResult,
+};
+use runner::report::{Artifacts, Report};
+use runner::{determinism, differential, watch};
 use std::any::Any;
 use std::backtrace::Backtrace;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
 use std::sync::{mpsc, Arc, Mutex};
 use tracing_subscriber::field::MakeExt;
@@ -35,6 +39,7 @@ struct Paths {
     doublecheck_db: PathBuf,
     shrunk_db: PathBuf,
     diff_db: PathBuf,
+    determinism_db: PathBuf,
 }
 
 impl Paths {
@@ -48,6 +53,7 @@ impl Paths {
             doublecheck_db: PathBuf::from(output_dir).join("double.db"),
             shrunk_db: PathBuf::from(output_dir).join("shrunk.db"),
             diff_db: PathBuf::from(output_dir).join("diff.db"),
+            determinism_db: PathBuf::from(output_dir).join("determinism.db"),
         }
     }
 }
@@ -57,6 +63,12 @@ fn main() -> anyhow::Result<()> {
     let mut cli_opts = SimulatorCLI::parse();
     cli_opts.validate()?;
 
+    // Ctrl-C stops the simulation gracefully, so the plan executed so far is kept for replay
+    ctrlc::set_handler(|| {
+        println!("received Ctrl-C, shutting down the simulation");
+        runner::request_shutdown();
+    })?;
+
     match cli_opts.subcommand {
         Some(SimulatorCommand::List) => {
             let mut bugbase = BugBase::load()?;
@@ -67,6 +79,10 @@ fn main() -> anyhow::Result<()> {
             for i in 0..n {
                 println!("iteration {}", i);
                 let result = testing_main(&cli_opts);
+                if runner::shutdown_requested() {
+                    println!("stopping the loop after {} iterations", i + 1);
+                    return result;
+                }
                 if result.is_err() && short_circuit {
                     println!("short circuiting after {} iterations", i);
                     return result;
@@ -123,6 +139,10 @@ fn main() -> anyhow::Result<()> {
             println!("\t{} failed runs", failures.len());
             Ok(())
         }
+        Some(SimulatorCommand::Shrink {
+            ref plan,
+            ref output,
+        }) => shrink_offline(&cli_opts, plan, output.as_deref()),
         None => {
             banner();
             testing_main(&cli_opts)
@@ -140,6 +160,9 @@ fn testing_main(cli_opts: &SimulatorCLI) -> anyhow::Result<()> {
     let last_execution = Arc::new(Mutex::new(Execution::new(0, 0, 0)));
     let (seed, env, plans, paths) =
         setup_simulation(bugbase.as_mut(), cli_opts, |p| &p.plan, |p| &p.db);
+    let stats = plans[0].stats();
+    // Shrinking and the other modes run on environments of their own, so these count the main run
+    let io = env.io.clone();
 
     if cli_opts.watch {
         watch_mode(seed, cli_opts, &paths, last_execution.clone()).unwrap();
@@ -155,6 +178,15 @@ fn testing_main(cli_opts: &SimulatorCLI) -> anyhow::Result<()> {
             plans,
             last_execution.clone(),
         )
+    } else if cli_opts.assert_determinism {
+        determinism_testing(
+            seed,
+            bugbase.as_mut(),
+            cli_opts,
+            &paths,
+            plans,
+            last_execution.clone(),
+        )
     } else {
         run_simulator(
             seed,
@@ -175,6 +207,8 @@ fn testing_main(cli_opts: &SimulatorCLI) -> anyhow::Result<()> {
         } else {
             result = Err(err);
         }
+    } else if runner::shutdown_requested() {
+        tracing::info!("simulation was interrupted, not recording it as a successful run");
     } else if let Some(bugbase) = bugbase.as_mut() {
         bugbase.mark_successful_run(seed, cli_opts).unwrap();
     };
@@ -183,6 +217,29 @@ fn testing_main(cli_opts: &SimulatorCLI) -> anyhow::Result<()> {
     println!("seed: {}", seed);
     println!("path: {}", paths.base.display());
 
+    if cli_opts.report.is_some() {
+        let artifacts = Artifacts::new(
+            &paths.base,
+            &paths.db,
+            &paths.plan,
+            &paths.shrunk_plan,
+            &paths.history,
+        );
+        let last_execution = *last_execution.lock().unwrap();
+        let io_counters =
+            (!cli_opts.differential && !cli_opts.assert_determinism).then(|| io.counters());
+        Report::new(
+            seed,
+            cli_opts,
+            stats,
+            io_counters,
+            &result,
+            last_execution,
+            artifacts,
+        )
+        .write(cli_opts.report_file.as_deref())?;
+    }
+
     result
 }
 
@@ -270,13 +327,20 @@ fn run_simulator(
     }));
 
     let env = Arc::new(Mutex::new(env));
+    let mut plans = plans;
     let result = SandboxedResult::from(
-        std::panic::catch_unwind(|| {
-            run_simulation(env.clone(), &mut plans.clone(), last_execution.clone())
-        }),
+        std::panic::catch_unwind(AssertUnwindSafe(|| {
+            run_simulation(env.clone(), &mut plans, last_execution.clone())
+        })),
         last_execution.clone(),
     );
 
+    // A time-boxed or interrupted run changes the plan while executing, so write out what ran
+    if cli_opts.duration.is_some() || runner::shutdown_requested() {
+        tracing::info!("{}", plans[0].stats());
+        write_plan(&paths.plan, &plans[0]);
+    }
+
     if cli_opts.doublecheck {
         doublecheck(
             seed,
@@ -509,7 +573,7 @@ fn differential_testing(
             differential::run_simulation(
                 env,
                 rusqlite_env,
-                &|| rusqlite::Connection::open(paths.diff_db.clone()).unwrap(),
+                &|| differential::open_rusqlite_connection(&paths.diff_db),
                 &mut [plan],
                 last_execution.clone(),
             )
@@ -535,6 +599,130 @@ fn differential_testing(
     }
 }
 
+fn determinism_testing(
+    seed: u64,
+    bugbase: Option<&mut BugBase>,
+    cli_opts: &SimulatorCLI,
+    paths: &Paths,
+    plans: Vec<InteractionPlan>,
+    last_execution: Arc<Mutex<Execution>>,
+) -> anyhow::Result<()> {
+    let env = Arc::new(Mutex::new(SimulatorEnv::new(seed, cli_opts, &paths.db)));
+    let second_env = Arc::new(Mutex::new(SimulatorEnv::new(
+        seed,
+        cli_opts,
+        &paths.determinism_db,
+    )));
+
+    let result = SandboxedResult::from(
+        std::panic::catch_unwind(|| {
+            let plan = plans[0].clone();
+            determinism::run_simulation(env, second_env, &mut [plan], last_execution.clone())
+        }),
+        last_execution.clone(),
+    );
+
+    match result {
+        SandboxedResult::Correct => {
+            tracing::info!("simulation succeeded, both runs produced the same results");
+            println!("simulation succeeded, both runs produced the same results");
+            Ok(())
+        }
+        SandboxedResult::Panicked { error, .. } | SandboxedResult::FoundBug { error, .. } => {
+            tracing::error!("simulation failed: '{}'", error);
+            if let Some(bugbase) = bugbase {
+                bugbase
+                    .add_bug(seed, plans[0].clone(), Some(error.clone()), cli_opts)
+                    .unwrap();
+            }
+            Err(anyhow!("simulation failed: '{}'", error))
+        }
+    }
+}
+
+/// Minimizes the plan of a failing run without generating a new one, replaying each candidate
+/// plan against a fresh database to check that it still fails with the same error.
+fn shrink_offline(
+    cli_opts: &SimulatorCLI,
+    plan_path: &Path,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let json_path = plan_path.with_extension("json");
+    let plan = std::fs::read_to_string(&json_path).with_context(|| {
+        format!(
+            "should be able to read plan file at {}",
+            json_path.display()
+        )
+    })?;
+    let plan: InteractionPlan =
+        serde_json::from_str(&plan).with_context(|| "should be able to deserialize plan")?;
+    // The bug base keeps the plan of each bug in a directory named after its seed
+    let seed = cli_opts
+        .seed
+        .or_else(|| plan_path.parent()?.file_name()?.to_str()?.parse().ok())
+        .ok_or_else(|| anyhow!("the seed of the failing run is needed to replay its plan"))?;
+    tracing::info!("seed={}", seed);
+
+    let db_path = plan_path.with_file_name("shrunk.db");
+    let replay = |plan: &InteractionPlan| {
+        let last_execution = Arc::new(Mutex::new(Execution::new(0, 0, 0)));
+        let env = Arc::new(Mutex::new(SimulatorEnv::new(seed, cli_opts, &db_path)));
+        SandboxedResult::from(
+            std::panic::catch_unwind(AssertUnwindSafe(|| {
+                run_simulation(env.clone(), &mut [plan.clone()], last_execution.clone())
+            })),
+            last_execution.clone(),
+        )
+    };
+
+    let result = replay(&plan);
+    let (error, last_execution) = match &result {
+        SandboxedResult::Correct => {
+            anyhow::bail!(
+                "the plan at {} does not fail, there is nothing to shrink",
+                plan_path.display()
+            )
+        }
+        SandboxedResult::Panicked {
+            error,
+            last_execution,
+        }
+        | SandboxedResult::FoundBug {
+            error,
+            last_execution,
+            ..
+        } => (error.clone(), *last_execution),
+    };
+    tracing::info!("the plan failed with '{}', starting to shrink", error);
+    let reproduces = |plan: &InteractionPlan| {
+        let shrunk = replay(plan);
+        std::mem::discriminant(&shrunk) == std::mem::discriminant(&result)
+            && shrunk.error() == Some(error.as_str())
+    };
+
+    // Nothing runs after the failing property
+    let mut truncated = plan.clone();
+    truncated
+        .plan
+        .truncate(last_execution.interaction_index + 1);
+    let shrunk = if reproduces(&truncated) {
+        truncated.delta_debug(reproduces)
+    } else {
+        plan.delta_debug(reproduces)
+    };
+
+    let output = output.map_or_else(|| plan_path.with_file_name("shrunk.sql"), Path::to_path_buf);
+    write_plan(&output, &shrunk);
+    tracing::info!("{}", shrunk.stats());
+    println!(
+        "shrunk the plan from {} to {} interactions",
+        plan.interaction_count(),
+        shrunk.interaction_count()
+    );
+    println!("shrunk plan: {}", output.display());
+    Ok(())
+}
+
 #[derive(Debug)]
 enum SandboxedResult {
     Panicked {
@@ -550,6 +738,15 @@ enum SandboxedResult {
 }
 
 impl SandboxedResult {
+    fn error(&self) -> Option<&str> {
+        match self {
+            SandboxedResult::Panicked { error, .. } | SandboxedResult::FoundBug { error, .. } => {
+                Some(error)
+            }
+            SandboxedResult::Correct => None,
+        }
+    }
+
     fn from(
         result: Result<ExecutionResult, Box<dyn Any + Send>>,
         last_execution: Arc<Mutex<Execution>>,
@@ -620,12 +817,7 @@ fn setup_simulation(
             }
         };
 
-        std::fs::write(plan_path(&paths), plan.to_string()).unwrap();
-        std::fs::write(
-            plan_path(&paths).with_extension("json"),
-            serde_json::to_string_pretty(&plan).unwrap(),
-        )
-        .unwrap();
+        write_plan(plan_path(&paths), &plan);
         let plans = vec![plan];
         (seed, env, plans, paths)
     } else {
@@ -652,26 +844,40 @@ fn setup_simulation(
 
         let mut env = SimulatorEnv::new(seed, cli_opts, &paths.db);
 
-        tracing::info!("Generating database interaction plan...");
-
-        let plans = (1..=env.opts.max_connections)
-            .map(|_| InteractionPlan::arbitrary_from(&mut env.rng.clone(), &mut env))
-            .collect::<Vec<_>>();
+        let plans = if let Some(mutate_from) = &cli_opts.mutate_from {
+            tracing::info!("Mutating database interaction plan...");
+            let plan = std::fs::read_to_string(mutate_from).unwrap_or_else(|e| {
+                panic!("could not read plan file {}: {}", mutate_from.display(), e)
+            });
+            let plan: InteractionPlan =
+                serde_json::from_str(&plan).expect("plan file should hold a serialized plan");
+            vec![plan.mutate(&mut env.rng.clone())]
+        } else {
+            tracing::info!("Generating database interaction plan...");
+            (1..=env.opts.max_connections)
+                .map(|_| InteractionPlan::arbitrary_from(&mut env.rng.clone(), &mut env))
+                .collect::<Vec<_>>()
+        };
 
         // todo: for now, we only use 1 connection, so it's safe to use the first plan.
         let plan = &plans[0];
         tracing::info!("{}", plan.stats());
-        std::fs::write(plan_path(&paths), plan.to_string()).unwrap();
-        std::fs::write(
-            plan_path(&paths).with_extension("json"),
-            serde_json::to_string_pretty(&plan).unwrap(),
-        )
-        .unwrap();
+        write_plan(plan_path(&paths), plan);
 
         (seed, env, plans, paths)
     }
 }
 
+/// Writes the human readable plan and its JSON counterpart used by watch mode and the bug base.
+fn write_plan(path: &Path, plan: &InteractionPlan) {
+    std::fs::write(path, plan.to_string()).unwrap();
+    std::fs::write(
+        path.with_extension("json"),
+        serde_json::to_string_pretty(plan).unwrap(),
+    )
+    .unwrap();
+}
+
 fn run_simulation(
     env: Arc<Mutex<SimulatorEnv>>,
     plans: &mut [InteractionPlan],
@@ -689,6 +895,10 @@ fn run_simulation(
         .collect::<Vec<_>>();
     let result = execute_plans(env.clone(), plans, &mut states, last_execution);
 
+    if !result.failures.is_empty() {
+        println!("{}", failure_report(&result.failures));
+    }
+
     let env = env.lock().unwrap();
     env.io.print_stats();
 
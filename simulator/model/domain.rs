@@ -0,0 +1,117 @@
+//! Value domains of columns, hints that make the generated values resemble the data of an
+//! application instead of arbitrary values of the column type. Values drawn from a small
+//! domain repeat, so rows share values, predicates on existing values select more rows, and
+//! properties check non-empty results more often.
+//!
+//! Domains are declared in the file of `--tables-from-file` with annotations in comments:
+//!
+//! ```sql
+//! -- @domain orders.user_id small_int
+//! -- @domain users.name name
+//! ```
+
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context};
+
+use crate::model::table::ColumnType;
+
+/// Prefix of the comment lines that declare the domain of a column
+const DOMAIN_ANNOTATION: &str = "-- @domain";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueDomain {
+    /// Integers from 1 to 100, such as the ids of a handful of rows
+    SmallInt,
+    /// First names from a short word list
+    Name,
+    /// `YYYY-MM-DD` dates from 2000 to 2030
+    Date,
+}
+
+impl ValueDomain {
+    /// Whether the column stores the values of the domain as they are generated
+    pub(crate) fn accepts(&self, column_type: &ColumnType) -> bool {
+        match self {
+            Self::SmallInt => *column_type == ColumnType::Integer,
+            Self::Name | Self::Date => *column_type == ColumnType::Text,
+        }
+    }
+}
+
+impl FromStr for ValueDomain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "small_int" => Ok(Self::SmallInt),
+            "name" => Ok(Self::Name),
+            "date" => Ok(Self::Date),
+            _ => bail!(
+                "unknown value domain {}, expected one of small_int, name or date",
+                s
+            ),
+        }
+    }
+}
+
+impl Display for ValueDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SmallInt => write!(f, "small_int"),
+            Self::Name => write!(f, "name"),
+            Self::Date => write!(f, "date"),
+        }
+    }
+}
+
+/// Domains of the columns that declare one, keyed by table and column name
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Domains(HashMap<(String, String), ValueDomain>);
+
+impl Domains {
+    /// Collects the `-- @domain <table>.<column> <domain>` annotations of a SQL file, with the
+    /// names lowercased like the identifiers of the schema
+    pub(crate) fn from_annotations(sql: &str) -> anyhow::Result<Self> {
+        let mut domains = Domains::default();
+        for (i, line) in sql.lines().enumerate() {
+            let Some(annotation) = line.trim().strip_prefix(DOMAIN_ANNOTATION) else {
+                continue;
+            };
+            let (key, domain) = parse_annotation(annotation)
+                .with_context(|| format!("invalid domain annotation on line {}", i + 1))?;
+            if domains.0.contains_key(&key) {
+                bail!("the domain of {}.{} is declared twice", key.0, key.1);
+            }
+            domains.0.insert(key, domain);
+        }
+        Ok(domains)
+    }
+
+    pub(crate) fn get(&self, table: &str, column: &str) -> Option<ValueDomain> {
+        self.0
+            .get(&(table.to_string(), column.to_string()))
+            .copied()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &str, ValueDomain)> {
+        self.0
+            .iter()
+            .map(|((table, column), domain)| (table.as_str(), column.as_str(), *domain))
+    }
+}
+
+/// Parses the `<table>.<column> <domain>` part of an annotation
+fn parse_annotation(annotation: &str) -> anyhow::Result<((String, String), ValueDomain)> {
+    let mut parts = annotation.split_whitespace();
+    let (Some(column), Some(domain), None) = (parts.next(), parts.next(), parts.next()) else {
+        bail!("expected `{} <table>.<column> <domain>`", DOMAIN_ANNOTATION);
+    };
+    let Some((table, column)) = column.split_once('.') else {
+        bail!("expected a column as <table>.<column>, got {}", column);
+    };
+    Ok((
+        (table.to_lowercase(), column.to_lowercase()),
+        domain.parse()?,
+    ))
+}
@@ -1,2 +1,4 @@
+pub mod domain;
 pub mod query;
+pub mod schema;
 pub mod table;
@@ -0,0 +1,53 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::table::SimValue, SimulatorEnv};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Alter {
+    /// Removes a column and its values from every row of the table
+    DropColumn { table: String, column: String },
+}
+
+impl Alter {
+    pub(crate) fn table(&self) -> &str {
+        match self {
+            Alter::DropColumn { table, .. } => table,
+        }
+    }
+
+    pub(crate) fn shadow(&self, env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
+        match self {
+            Alter::DropColumn { table, column } => {
+                let table = env
+                    .tables
+                    .iter_mut()
+                    .find(|t| &t.name == table)
+                    .expect("table must exist");
+                let index = table
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == column)
+                    .expect("column must exist");
+
+                table.columns.remove(index);
+                for row in table.rows.iter_mut() {
+                    row.remove(index);
+                }
+            }
+        }
+
+        vec![]
+    }
+}
+
+impl Display for Alter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Alter::DropColumn { table, column } => {
+                write!(f, "ALTER TABLE {} DROP COLUMN {}", table, column)
+            }
+        }
+    }
+}
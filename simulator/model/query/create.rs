@@ -31,6 +31,31 @@ impl Display for Create {
                 write!(f, ",")?;
             }
             write!(f, "{} {}", column.name, column.column_type)?;
+            if column.primary {
+                write!(f, " PRIMARY KEY")?;
+            }
+            if column.autoincrement {
+                write!(f, " AUTOINCREMENT")?;
+            }
+            if let Some(collation) = &column.collation {
+                write!(f, " COLLATE {}", collation)?;
+            }
+            if let Some(default) = &column.default {
+                write!(f, " DEFAULT {}", default)?;
+            }
+            if let Some(generated) = &column.generated {
+                write!(f, " {}", generated)?;
+            }
+        }
+
+        for fk in &self.table.foreign_keys {
+            write!(
+                f,
+                ", FOREIGN KEY ({}) REFERENCES {}({})",
+                fk.columns.join(", "),
+                fk.parent_table,
+                fk.parent_columns.join(", ")
+            )?;
         }
 
         write!(f, ")")
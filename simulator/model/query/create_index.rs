@@ -25,15 +25,39 @@ pub(crate) struct CreateIndex {
     pub(crate) index_name: String,
     pub(crate) table_name: String,
     pub(crate) columns: Vec<(String, SortOrder)>,
+    #[serde(default)]
+    pub(crate) unique: bool,
 }
 
 impl CreateIndex {
     pub(crate) fn shadow(
         &self,
-        _env: &mut crate::runner::env::SimulatorEnv,
+        env: &mut crate::runner::env::SimulatorEnv,
     ) -> Vec<Vec<crate::model::table::SimValue>> {
-        // CREATE INDEX doesn't require any shadowing; we don't need to keep track
-        // in the simulator what indexes exist.
+        // The index contents are not modeled; we only remember which columns are
+        // indexed so that they are never picked by `ALTER TABLE ... DROP COLUMN`,
+        // and the keys of UNIQUE indexes, which restrict later writes.
+        if let Some(table) = env.tables.iter_mut().find(|t| t.name == self.table_name) {
+            let columns = self
+                .columns
+                .iter()
+                .map(|(column, _)| column.clone())
+                .collect::<Vec<_>>();
+            if self.unique {
+                // Building a UNIQUE index over duplicated keys fails
+                if table.has_duplicate_key(&columns, &table.rows) {
+                    return vec![];
+                }
+                if !table.unique_indexes.contains(&columns) {
+                    table.unique_indexes.push(columns.clone());
+                }
+            }
+            for column in columns {
+                if !table.indexed_columns.contains(&column) {
+                    table.indexed_columns.push(column);
+                }
+            }
+        }
         vec![]
     }
 }
@@ -42,7 +66,8 @@ impl std::fmt::Display for CreateIndex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "CREATE INDEX {} ON {} ({})",
+            "CREATE {}INDEX {} ON {} ({})",
+            if self.unique { "UNIQUE " } else { "" },
             self.index_name,
             self.table_name,
             self.columns
@@ -70,13 +95,19 @@ impl ArbitraryFrom<&SimulatorEnv> for CreateIndex {
             );
         }
 
-        let num_columns_to_pick = rng.gen_range(1..=table.columns.len());
-        let picked_column_indices = pick_n_unique(0..table.columns.len(), num_columns_to_pick, rng);
+        // VIRTUAL generated columns cannot be indexed
+        let indexable = table
+            .columns
+            .iter()
+            .filter(|c| c.generated.as_ref().is_none_or(|g| g.stored))
+            .collect::<Vec<_>>();
+        let num_columns_to_pick = rng.gen_range(1..=indexable.len());
+        let picked_column_indices = pick_n_unique(0..indexable.len(), num_columns_to_pick, rng);
 
         let columns = picked_column_indices
             .into_iter()
             .map(|i| {
-                let column = &table.columns[i];
+                let column = indexable[i];
                 (
                     column.name.clone(),
                     if rng.gen_bool(0.5) {
@@ -98,6 +129,9 @@ impl ArbitraryFrom<&SimulatorEnv> for CreateIndex {
             index_name,
             table_name: table.name.clone(),
             columns,
+            // UNIQUE indexes change which writes succeed, so they are only generated
+            // when the database actually builds indexes
+            unique: env.opts.experimental_indexes && rng.gen_bool(0.3),
         }
     }
 }
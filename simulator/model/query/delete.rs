@@ -4,12 +4,18 @@ use serde::{Deserialize, Serialize};
 
 use crate::{model::table::SimValue, SimulatorEnv};
 
-use super::predicate::Predicate;
+use super::{create_index::SortOrder, predicate::Predicate};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Delete {
     pub(crate) table: String,
     pub(crate) predicate: Predicate,
+    /// `ORDER BY` of a limited delete. The rows are ordered by `rowid` last, so exactly
+    /// which rows are deleted never depends on how the engine breaks ties.
+    #[serde(default)]
+    pub(crate) order_by: Vec<(String, SortOrder)>,
+    #[serde(default)]
+    pub(crate) limit: Option<usize>,
 }
 
 impl Delete {
@@ -22,7 +28,22 @@ impl Delete {
 
         let t2 = table.clone();
 
-        table.rows.retain_mut(|r| !self.predicate.test(r, &t2));
+        match self.limit {
+            None => table.rows.retain_mut(|r| !self.predicate.test(r, &t2)),
+            Some(limit) => {
+                let mut candidates = (0..t2.rows.len())
+                    .filter(|&i| self.predicate.test(&t2.rows[i], &t2))
+                    .collect::<Vec<_>>();
+                candidates.sort_by(|&a, &b| t2.compare_rows(&self.order_by, a, b));
+                candidates.truncate(limit);
+                let mut idx = 0;
+                table.rows.retain(|_| {
+                    let keep = !candidates.contains(&idx);
+                    idx += 1;
+                    keep
+                });
+            }
+        }
 
         vec![]
     }
@@ -30,6 +51,14 @@ impl Delete {
 
 impl Display for Delete {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "DELETE FROM {} WHERE {}", self.table, self.predicate)
+        write!(f, "DELETE FROM {} WHERE {}", self.table, self.predicate)?;
+        if let Some(limit) = self.limit {
+            write!(f, " ORDER BY ")?;
+            for (name, order) in &self.order_by {
+                write!(f, "{} {}, ", name, order)?;
+            }
+            write!(f, "rowid LIMIT {}", limit)?;
+        }
+        Ok(())
     }
 }
@@ -2,7 +2,10 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{model::table::SimValue, SimulatorEnv};
+use crate::{
+    model::table::{SimValue, Table},
+    SimulatorEnv,
+};
 
 use super::select::Select;
 
@@ -11,6 +14,18 @@ pub(crate) enum Insert {
     Values {
         table: String,
         values: Vec<Vec<SimValue>>,
+        /// Explicit column list of the statement, as the position and name of each column.
+        /// The columns left out take their default value, the rows still hold a value for
+        /// every column of the table.
+        #[serde(default)]
+        columns: Option<Vec<(usize, String)>>,
+        /// Resolution of the UNIQUE constraint violations, given by `INSERT OR <resolution>`
+        #[serde(default)]
+        or_conflict: Option<ConflictResolution>,
+        /// `ON CONFLICT` clause without a conflict target, resolving the violation of any
+        /// UNIQUE index
+        #[serde(default)]
+        upsert: Option<Upsert>,
     },
     Select {
         table: String,
@@ -18,17 +33,137 @@ pub(crate) enum Insert {
     },
 }
 
+/// The `OR <resolution>` of an `INSERT` or `UPDATE`. Without one, a violation aborts the
+/// statement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ConflictResolution {
+    /// Skips the rows that violate a constraint
+    Ignore,
+    /// Deletes the existing rows the new row conflicts with
+    Replace,
+    /// Aborts the statement and rolls back the transaction it runs in
+    Rollback,
+}
+
+impl ConflictResolution {
+    /// Applies the failure of a statement run `OR ROLLBACK`: the open savepoints are a
+    /// transaction, which is rolled back as a whole.
+    pub(crate) fn rollback(env: &mut SimulatorEnv) {
+        if let Some((_, tables)) = env.savepoints.first() {
+            env.tables = tables.clone();
+            env.savepoints.clear();
+        }
+    }
+}
+
+/// The action of an `ON CONFLICT` clause
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Upsert {
+    /// Skips the rows that conflict with an existing row
+    DoNothing,
+    /// Sets the given columns of the existing row to their value in the new row, as in
+    /// `SET c = excluded.c`
+    DoUpdate(Vec<String>),
+}
+
+impl Display for ConflictResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictResolution::Ignore => write!(f, "IGNORE"),
+            ConflictResolution::Replace => write!(f, "REPLACE"),
+            ConflictResolution::Rollback => write!(f, "ROLLBACK"),
+        }
+    }
+}
+
 impl Insert {
     pub(crate) fn shadow(&self, env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
         match self {
-            Insert::Values { table, values } => {
+            Insert::Values {
+                table,
+                values,
+                columns,
+                or_conflict,
+                upsert,
+            } => {
+                let values = match (columns, env.tables.iter().find(|t| &t.name == table)) {
+                    (Some(columns), Some(t)) => {
+                        let mut values = with_defaults(t, columns, values);
+                        values.iter_mut().for_each(|row| t.fill_generated(row));
+                        values
+                    }
+                    _ => values.clone(),
+                };
+                let Some(t) = env.tables.iter().find(|t| &t.name == table) else {
+                    return vec![];
+                };
+                let mut rows = t.rows.clone();
+                for row in &values {
+                    let conflicting = t.conflicting_rows(&rows, row);
+                    match (or_conflict, upsert) {
+                        _ if conflicting.is_empty() => {}
+                        (_, Some(Upsert::DoNothing)) => continue,
+                        (_, Some(Upsert::DoUpdate(columns))) => {
+                            // Existing rows never conflict with each other, so a single UNIQUE
+                            // index leaves a single row to update
+                            let i = conflicting[0];
+                            let mut updated = rows[i].clone();
+                            for (pos, column) in t.columns.iter().enumerate() {
+                                if columns.contains(&column.name) {
+                                    updated[pos] = row[pos].clone();
+                                }
+                            }
+                            t.fill_generated(&mut updated);
+                            let others = rows
+                                .iter()
+                                .enumerate()
+                                .filter(|(j, _)| *j != i)
+                                .map(|(_, row)| row.clone())
+                                .collect::<Vec<_>>();
+                            if !t.conflicting_rows(&others, &updated).is_empty()
+                                || (env.foreign_keys
+                                    && !satisfies_foreign_keys(env, table, &[updated.clone()]))
+                            {
+                                return vec![];
+                            }
+                            rows[i] = updated;
+                            continue;
+                        }
+                        (None, None) => return vec![],
+                        (Some(ConflictResolution::Rollback), None) => {
+                            ConflictResolution::rollback(env);
+                            return vec![];
+                        }
+                        (Some(ConflictResolution::Ignore), None) => continue,
+                        (Some(ConflictResolution::Replace), None) => {
+                            rows = rows
+                                .into_iter()
+                                .enumerate()
+                                .filter(|(i, _)| !conflicting.contains(i))
+                                .map(|(_, row)| row)
+                                .collect();
+                        }
+                    }
+                    // Foreign keys are not subject to the conflict resolution, so a violation
+                    // always aborts the statement
+                    if env.foreign_keys && !satisfies_foreign_keys(env, table, &[row.clone()]) {
+                        return vec![];
+                    }
+                    rows.push(row.clone());
+                }
                 if let Some(t) = env.tables.iter_mut().find(|t| &t.name == table) {
-                    t.rows.extend(values.clone());
+                    t.rows = rows;
                 }
             }
             Insert::Select { table, select } => {
                 let rows = select.shadow(env);
+                if env.foreign_keys && !satisfies_foreign_keys(env, table, &rows) {
+                    return vec![];
+                }
                 if let Some(t) = env.tables.iter_mut().find(|t| &t.name == table) {
+                    if !satisfies_unique_indexes(t, &rows) {
+                        return vec![];
+                    }
                     t.rows.extend(rows);
                 }
             }
@@ -44,24 +179,136 @@ impl Insert {
     }
 }
 
+/// Replaces the values of the columns left out of `columns` with the defaults the database
+/// fills them with, which is NULL for the columns without a default.
+fn with_defaults(
+    table: &Table,
+    columns: &[(usize, String)],
+    rows: &[Vec<SimValue>],
+) -> Vec<Vec<SimValue>> {
+    let defaults = table
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !columns.iter().any(|(pos, _)| pos == i))
+        .map(|(i, column)| {
+            let value = column
+                .default
+                .as_ref()
+                .map_or(SimValue(turso_core::Value::Null), |d| d.value());
+            (i, value)
+        })
+        .collect::<Vec<_>>();
+    rows.iter()
+        .map(|row| {
+            let mut row = row.clone();
+            for (i, value) in &defaults {
+                row[*i] = value.clone();
+            }
+            row
+        })
+        .collect()
+}
+
+/// Checks that `rows` do not duplicate a UNIQUE index key, neither among themselves
+/// nor with the existing rows. A single violation aborts the whole statement.
+fn satisfies_unique_indexes(table: &Table, rows: &[Vec<SimValue>]) -> bool {
+    if table.unique_indexes.is_empty() {
+        return true;
+    }
+    let all_rows = table.rows.iter().chain(rows).cloned().collect::<Vec<_>>();
+    !table.has_unique_conflict(&all_rows)
+}
+
+/// Checks that every non-NULL child key in `rows` has a matching parent row.
+/// A single violation aborts the whole statement, so no row is inserted.
+fn satisfies_foreign_keys(env: &SimulatorEnv, table: &str, rows: &[Vec<SimValue>]) -> bool {
+    let Some(child) = env.tables.iter().find(|t| t.name == table) else {
+        return true;
+    };
+    let column_index =
+        |t: &Table, name: &str| t.columns.iter().position(|c| c.name == name).unwrap();
+
+    child.foreign_keys.iter().all(|fk| {
+        let Some(parent) = env.tables.iter().find(|t| t.name == fk.parent_table) else {
+            return false;
+        };
+        let child_indexes = fk
+            .columns
+            .iter()
+            .map(|c| column_index(child, c))
+            .collect::<Vec<_>>();
+        let parent_indexes = fk
+            .parent_columns
+            .iter()
+            .map(|c| column_index(parent, c))
+            .collect::<Vec<_>>();
+
+        rows.iter().all(|row| {
+            let key = child_indexes.iter().map(|&i| &row[i]).collect::<Vec<_>>();
+            if key.iter().any(|v| matches!(v.0, turso_core::Value::Null)) {
+                return true;
+            }
+            parent.rows.iter().any(|parent_row| {
+                parent_indexes
+                    .iter()
+                    .zip(&key)
+                    .all(|(&i, v)| &parent_row[i] == *v)
+            })
+        })
+    })
+}
+
 impl Display for Insert {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Insert::Values { table, values } => {
-                write!(f, "INSERT INTO {} VALUES ", table)?;
+            Insert::Values {
+                table,
+                values,
+                columns,
+                or_conflict,
+                upsert,
+            } => {
+                match or_conflict {
+                    Some(or_conflict) => write!(f, "INSERT OR {} INTO {} ", or_conflict, table)?,
+                    None => write!(f, "INSERT INTO {} ", table)?,
+                }
+                if let Some(columns) = columns {
+                    let names = columns
+                        .iter()
+                        .map(|(_, name)| name.as_str())
+                        .collect::<Vec<_>>();
+                    write!(f, "({}) ", names.join(", "))?;
+                }
+                write!(f, "VALUES ")?;
                 for (i, row) in values.iter().enumerate() {
                     if i != 0 {
                         write!(f, ", ")?;
                     }
                     write!(f, "(")?;
-                    for (j, value) in row.iter().enumerate() {
+                    let positions = match columns {
+                        Some(columns) => columns.iter().map(|(pos, _)| *pos).collect(),
+                        None => (0..row.len()).collect::<Vec<_>>(),
+                    };
+                    for (j, pos) in positions.into_iter().enumerate() {
                         if j != 0 {
                             write!(f, ", ")?;
                         }
-                        write!(f, "{}", value)?;
+                        write!(f, "{}", row[pos])?;
                     }
                     write!(f, ")")?;
                 }
+                match upsert {
+                    Some(Upsert::DoNothing) => write!(f, " ON CONFLICT DO NOTHING")?,
+                    Some(Upsert::DoUpdate(columns)) => {
+                        let sets = columns
+                            .iter()
+                            .map(|c| format!("{} = excluded.{}", c, c))
+                            .collect::<Vec<_>>();
+                        write!(f, " ON CONFLICT DO UPDATE SET {}", sets.join(", "))?;
+                    }
+                    None => {}
+                }
                 Ok(())
             }
             Insert::Select { table, select } => {
@@ -1,25 +1,37 @@
-use std::{collections::HashSet, fmt::Display};
+use std::{collections::HashSet, fmt::Display, iter};
 
+pub(crate) use alter::Alter;
 pub(crate) use create::Create;
 pub(crate) use create_index::CreateIndex;
 pub(crate) use delete::Delete;
 pub(crate) use drop::Drop;
 pub(crate) use insert::Insert;
+pub(crate) use pragma::Pragma;
+pub(crate) use prepared::Prepared;
+pub(crate) use reindex::Reindex;
+pub(crate) use savepoint::Savepoint;
 pub(crate) use select::Select;
 use serde::{Deserialize, Serialize};
 use turso_sqlite3_parser::to_sql_string::ToSqlContext;
 use update::Update;
+pub(crate) use vacuum::Vacuum;
 
 use crate::{model::table::SimValue, runner::env::SimulatorEnv};
 
+pub mod alter;
 pub mod create;
 pub mod create_index;
 pub mod delete;
 pub mod drop;
 pub mod insert;
+pub mod pragma;
 pub mod predicate;
+pub mod prepared;
+pub mod reindex;
+pub mod savepoint;
 pub mod select;
 pub mod update;
+pub mod vacuum;
 
 // This type represents the potential queries on the database.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,33 +43,56 @@ pub(crate) enum Query {
     Update(Update),
     Drop(Drop),
     CreateIndex(CreateIndex),
+    Pragma(Pragma),
+    Alter(Alter),
+    Reindex(Reindex),
+    Savepoint(Savepoint),
+    Vacuum(Vacuum),
+    Prepared(Prepared),
 }
 
 impl Query {
     pub(crate) fn dependencies(&self) -> HashSet<String> {
         match self {
-            Query::Create(_) => HashSet::new(),
-            Query::Select(Select { table, .. })
-            | Query::Insert(Insert::Select { table, .. })
+            Query::Create(_) | Query::Pragma(_) | Query::Savepoint(_) | Query::Vacuum(_) => {
+                HashSet::new()
+            }
+            Query::Select(Select { table, join, .. }) => HashSet::from_iter(
+                iter::once(table.clone()).chain(join.iter().map(|j| j.table.clone())),
+            ),
+            Query::Insert(Insert::Select { table, .. })
             | Query::Insert(Insert::Values { table, .. })
             | Query::Delete(Delete { table, .. })
-            | Query::Update(Update { table, .. })
-            | Query::Drop(Drop { table, .. }) => HashSet::from_iter([table.clone()]),
+            | Query::Drop(Drop { table, .. })
+            | Query::Reindex(Reindex { table })
+            | Query::Prepared(Prepared { table, .. }) => HashSet::from_iter([table.clone()]),
+            Query::Update(Update { table, from, .. }) => HashSet::from_iter(
+                iter::once(table.clone()).chain(from.iter().map(|f| f.table.clone())),
+            ),
             Query::CreateIndex(CreateIndex { table_name, .. }) => {
                 HashSet::from_iter([table_name.clone()])
             }
+            Query::Alter(alter) => HashSet::from_iter([alter.table().to_string()]),
         }
     }
     pub(crate) fn uses(&self) -> Vec<String> {
         match self {
             Query::Create(Create { table }) => vec![table.name.clone()],
-            Query::Select(Select { table, .. })
-            | Query::Insert(Insert::Select { table, .. })
+            Query::Select(Select { table, join, .. }) => iter::once(table.clone())
+                .chain(join.iter().map(|j| j.table.clone()))
+                .collect(),
+            Query::Insert(Insert::Select { table, .. })
             | Query::Insert(Insert::Values { table, .. })
             | Query::Delete(Delete { table, .. })
-            | Query::Update(Update { table, .. })
-            | Query::Drop(Drop { table, .. }) => vec![table.clone()],
+            | Query::Drop(Drop { table, .. })
+            | Query::Reindex(Reindex { table })
+            | Query::Prepared(Prepared { table, .. }) => vec![table.clone()],
+            Query::Update(Update { table, from, .. }) => iter::once(table.clone())
+                .chain(from.iter().map(|f| f.table.clone()))
+                .collect(),
             Query::CreateIndex(CreateIndex { table_name, .. }) => vec![table_name.clone()],
+            Query::Alter(alter) => vec![alter.table().to_string()],
+            Query::Pragma(_) | Query::Savepoint(_) | Query::Vacuum(_) => vec![],
         }
     }
 
@@ -70,6 +105,12 @@ impl Query {
             Query::Update(update) => update.shadow(env),
             Query::Drop(drop) => drop.shadow(env),
             Query::CreateIndex(create_index) => create_index.shadow(env),
+            Query::Pragma(pragma) => pragma.shadow(env),
+            Query::Alter(alter) => alter.shadow(env),
+            Query::Reindex(reindex) => reindex.shadow(env),
+            Query::Savepoint(savepoint) => savepoint.shadow(env),
+            Query::Vacuum(vacuum) => vacuum.shadow(env),
+            Query::Prepared(prepared) => prepared.shadow(env),
         }
     }
 }
@@ -84,6 +125,12 @@ impl Display for Query {
             Self::Update(update) => write!(f, "{}", update),
             Self::Drop(drop) => write!(f, "{}", drop),
             Self::CreateIndex(create_index) => write!(f, "{}", create_index),
+            Self::Pragma(pragma) => write!(f, "{}", pragma),
+            Self::Alter(alter) => write!(f, "{}", alter),
+            Self::Reindex(reindex) => write!(f, "{}", reindex),
+            Self::Savepoint(savepoint) => write!(f, "{}", savepoint),
+            Self::Vacuum(vacuum) => write!(f, "{}", vacuum),
+            Self::Prepared(prepared) => write!(f, "{}", prepared),
         }
     }
 }
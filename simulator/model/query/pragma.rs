@@ -0,0 +1,42 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::table::SimValue, SimulatorEnv};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Pragma {
+    /// Toggles enforcement of foreign key constraints
+    ForeignKeys(bool),
+    /// Lists the rows of a table violating its foreign keys. The rowids of the violating rows
+    /// are not modeled, so the shadow has no rows: it is only run where none are expected.
+    ForeignKeyCheck(String),
+    /// Lists the columns of a table as the database parsed them. The shadow has no rows, the
+    /// result is only compared with the one of another database.
+    TableInfo(String),
+}
+
+impl Pragma {
+    pub(crate) fn shadow(&self, env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
+        match self {
+            Pragma::ForeignKeys(enabled) => env.foreign_keys = *enabled,
+            Pragma::ForeignKeyCheck(_) | Pragma::TableInfo(_) => {}
+        }
+
+        vec![]
+    }
+}
+
+impl Display for Pragma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pragma::ForeignKeys(enabled) => write!(
+                f,
+                "PRAGMA foreign_keys = {}",
+                if *enabled { "ON" } else { "OFF" }
+            ),
+            Pragma::ForeignKeyCheck(table) => write!(f, "PRAGMA foreign_key_check({})", table),
+            Pragma::TableInfo(table) => write!(f, "PRAGMA table_info({})", table),
+        }
+    }
+}
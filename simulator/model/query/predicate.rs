@@ -1,11 +1,12 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use turso_core::Value;
 use turso_sqlite3_parser::{ast, to_sql_string::ToSqlString};
 
 use crate::model::{
     query::EmptyContext,
-    table::{SimValue, Table},
+    table::{Collation, SimValue, Table},
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -24,6 +25,53 @@ impl Predicate {
         let value = expr_to_value(&self.0, row, table);
         value.map_or(false, |value| value.as_bool())
     }
+
+    /// Rewrites column references qualified with `table` to be qualified with `alias` instead
+    pub(crate) fn requalify(&self, table: &str, alias: &str) -> Self {
+        Self(requalify_expr(&self.0, table, alias))
+    }
+}
+
+fn requalify_expr(expr: &ast::Expr, table: &str, alias: &str) -> ast::Expr {
+    let requalify = |expr: &ast::Expr| Box::new(requalify_expr(expr, table, alias));
+    match expr {
+        ast::Expr::Qualified(ast::Name(name), column) if name == table => {
+            ast::Expr::Qualified(ast::Name(alias.to_string()), column.clone())
+        }
+        ast::Expr::Binary(lhs, op, rhs) => ast::Expr::Binary(requalify(lhs), *op, requalify(rhs)),
+        ast::Expr::Unary(op, expr) => ast::Expr::Unary(*op, requalify(expr)),
+        ast::Expr::Between {
+            lhs,
+            not,
+            start,
+            end,
+        } => ast::Expr::Between {
+            lhs: requalify(lhs),
+            not: *not,
+            start: requalify(start),
+            end: requalify(end),
+        },
+        ast::Expr::Like {
+            lhs,
+            not,
+            op,
+            rhs,
+            escape,
+        } => ast::Expr::Like {
+            lhs: requalify(lhs),
+            not: *not,
+            op: *op,
+            rhs: requalify(rhs),
+            escape: escape.as_deref().map(requalify),
+        },
+        ast::Expr::Parenthesized(exprs) => ast::Expr::Parenthesized(
+            exprs
+                .iter()
+                .map(|expr| requalify_expr(expr, table, alias))
+                .collect(),
+        ),
+        expr => expr.clone(),
+    }
 }
 
 // TODO: In the future pass a Vec<Table> to support resolving a value from another table
@@ -45,32 +93,159 @@ pub fn expr_to_value(expr: &ast::Expr, row: &[SimValue], table: &Table) -> Optio
                 .cloned()
         }
         ast::Expr::Literal(literal) => Some(literal.into()),
-        ast::Expr::Binary(lhs, op, rhs) => {
-            let lhs = expr_to_value(lhs, row, table)?;
-            let rhs = expr_to_value(rhs, row, table)?;
-            Some(lhs.binary_compare(&rhs, *op))
+        ast::Expr::Binary(lhs_expr, op, rhs_expr) => {
+            if let (ast::Expr::Parenthesized(lhs), ast::Expr::Parenthesized(rhs)) =
+                (lhs_expr.as_ref(), rhs_expr.as_ref())
+            {
+                if lhs.len() > 1 && lhs.len() == rhs.len() {
+                    return row_value_compare(lhs, *op, rhs, row, table);
+                }
+            }
+            let lhs = expr_to_value(lhs_expr, row, table)?;
+            let rhs = expr_to_value(rhs_expr, row, table)?;
+            // The collation of the left operand takes precedence over the right one
+            let collation = expr_collation(lhs_expr, table)
+                .or_else(|| expr_collation(rhs_expr, table))
+                .unwrap_or_default();
+            Some(lhs.binary_compare_collated(&rhs, *op, collation))
         }
         ast::Expr::Like {
             lhs,
             not,
             op,
             rhs,
-            escape: _, // TODO: support escape
+            escape,
         } => {
             let lhs = expr_to_value(lhs, row, table)?;
             let rhs = expr_to_value(rhs, row, table)?;
-            let res = lhs.like_compare(&rhs, *op);
+            let escape = match escape {
+                Some(escape) => Some(expr_to_value(escape, row, table)?),
+                None => None,
+            };
+            if matches!(lhs.0, Value::Null) || matches!(rhs.0, Value::Null) {
+                return Some(SimValue(Value::Null));
+            }
+            let res = lhs.like_compare(&rhs, *op, escape.as_ref());
             let value: SimValue = if *not { !res } else { res }.into();
             Some(value)
         }
+        ast::Expr::Between {
+            lhs,
+            not,
+            start,
+            end,
+        } => {
+            // `x BETWEEN a AND b` is `x >= a AND x <= b`, and `x NOT BETWEEN a AND b` is
+            // `x < a OR x > b`, with the NULLs of the comparisons following three-valued logic
+            let (lower, upper, connective) = if *not {
+                (
+                    ast::Operator::Less,
+                    ast::Operator::Greater,
+                    ast::Operator::Or,
+                )
+            } else {
+                (
+                    ast::Operator::GreaterEquals,
+                    ast::Operator::LessEquals,
+                    ast::Operator::And,
+                )
+            };
+            expr_to_value(
+                &ast::Expr::Binary(
+                    Box::new(ast::Expr::Binary(lhs.clone(), lower, start.clone())),
+                    connective,
+                    Box::new(ast::Expr::Binary(lhs.clone(), upper, end.clone())),
+                ),
+                row,
+                table,
+            )
+        }
         ast::Expr::Unary(op, expr) => {
             let value = expr_to_value(expr, row, table)?;
             Some(value.unary_exec(*op))
         }
+        ast::Expr::IsNull(expr) => {
+            let value = expr_to_value(expr, row, table)?;
+            Some(matches!(value.0, Value::Null).into())
+        }
+        ast::Expr::NotNull(expr) => {
+            let value = expr_to_value(expr, row, table)?;
+            Some((!matches!(value.0, Value::Null)).into())
+        }
         _ => unreachable!("{:?}", expr),
     }
 }
 
+/// Compares two row values of the same size. Equality holds when every pair of elements is
+/// equal, and ordering is lexicographic: the first pair that is not equal decides, and the
+/// result is NULL if a NULL is met before the order is decided.
+fn row_value_compare(
+    lhs: &[ast::Expr],
+    op: ast::Operator,
+    rhs: &[ast::Expr],
+    row: &[SimValue],
+    table: &Table,
+) -> Option<SimValue> {
+    let compare = |i: usize, op| {
+        expr_to_value(
+            &ast::Expr::Binary(Box::new(lhs[i].clone()), op, Box::new(rhs[i].clone())),
+            row,
+            table,
+        )
+    };
+    let is_null = |value: &SimValue| matches!(value.0, Value::Null);
+    let strict_op = match op {
+        ast::Operator::Equals | ast::Operator::NotEquals => {
+            let pairs = (0..lhs.len())
+                .map(|i| compare(i, ast::Operator::Equals))
+                .collect::<Option<Vec<_>>>()?;
+            let equal = if pairs.iter().any(|eq| !is_null(eq) && !eq.as_bool()) {
+                false
+            } else if pairs.iter().any(is_null) {
+                return Some(SimValue(Value::Null));
+            } else {
+                true
+            };
+            return Some((equal == (op == ast::Operator::Equals)).into());
+        }
+        ast::Operator::Is | ast::Operator::IsNot => {
+            let mut same = true;
+            for i in 0..lhs.len() {
+                same &= compare(i, ast::Operator::Is)?.as_bool();
+            }
+            return Some((same == (op == ast::Operator::Is)).into());
+        }
+        ast::Operator::Less | ast::Operator::LessEquals => ast::Operator::Less,
+        ast::Operator::Greater | ast::Operator::GreaterEquals => ast::Operator::Greater,
+        _ => unreachable!("row values cannot be compared with {:?}", op),
+    };
+    let last = lhs.len() - 1;
+    for i in 0..last {
+        let equal = compare(i, ast::Operator::Equals)?;
+        if is_null(&equal) {
+            return Some(equal);
+        }
+        if !equal.as_bool() {
+            return compare(i, strict_op);
+        }
+    }
+    compare(last, op)
+}
+
+/// Returns the collating sequence of a column reference, if the column declares one
+fn expr_collation(expr: &ast::Expr, table: &Table) -> Option<Collation> {
+    match expr {
+        ast::Expr::DoublyQualified(_, _, ast::Name(col_name))
+        | ast::Expr::Qualified(_, ast::Name(col_name))
+        | ast::Expr::Id(ast::Id(col_name)) => table
+            .columns
+            .iter()
+            .find(|column| column.name == *col_name)
+            .and_then(|column| column.collation),
+        _ => None,
+    }
+}
+
 impl Display for Predicate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0.to_sql_string(&EmptyContext))
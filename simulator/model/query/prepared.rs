@@ -0,0 +1,89 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use turso_sqlite3_parser::ast;
+
+use crate::{model::table::SimValue, SimulatorEnv};
+
+use super::{
+    create_index::SortOrder,
+    predicate::Predicate,
+    select::{Distinctness, OrderingTerm, ResultColumn},
+    Select,
+};
+
+/// `SELECT * FROM <table> WHERE <column> <op> ? AND ... ORDER BY rowid`, prepared once and
+/// then executed once for each set of bindings, resetting the statement in between
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Prepared {
+    pub(crate) table: String,
+    /// Comparisons of a column to a placeholder that every returned row satisfies
+    pub(crate) conditions: Vec<(String, ast::Operator)>,
+    /// Values bound to the placeholders of each execution, in order
+    pub(crate) bindings: Vec<Vec<SimValue>>,
+}
+
+impl Prepared {
+    /// The query with `?` placeholders, or with the literal values bound to them when `values`
+    /// is provided
+    pub(crate) fn select(&self, values: Option<&[SimValue]>) -> Select {
+        let expr = self
+            .conditions
+            .iter()
+            .enumerate()
+            .map(|(i, (column, operator))| {
+                let rhs = match values {
+                    Some(values) => ast::Expr::Literal(values[i].clone().into()),
+                    None => ast::Expr::Variable(String::new()),
+                };
+                ast::Expr::Binary(
+                    Box::new(ast::Expr::Id(ast::Id(column.clone()))),
+                    *operator,
+                    Box::new(rhs),
+                )
+            })
+            .reduce(|lhs, rhs| ast::Expr::Binary(Box::new(lhs), ast::Operator::And, Box::new(rhs)));
+        Select {
+            table: self.table.clone(),
+            table_alias: None,
+            result_columns: vec![ResultColumn::Star],
+            predicate: expr.map_or_else(Predicate::true_, Predicate),
+            limit: None,
+            distinct: Distinctness::All,
+            order_by: vec![(OrderingTerm::Column("rowid".to_string()), SortOrder::Asc)],
+            offset: None,
+            group_by: None,
+            join: None,
+        }
+    }
+
+    /// The rows of every execution, one after the other
+    pub(crate) fn shadow(&self, env: &SimulatorEnv) -> Vec<Vec<SimValue>> {
+        self.bindings
+            .iter()
+            .flat_map(|values| self.select(Some(values)).shadow(env))
+            .collect()
+    }
+}
+
+impl Display for Prepared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -- BINDINGS {}",
+            self.select(None),
+            self.bindings
+                .iter()
+                .map(|values| format!(
+                    "({})",
+                    values
+                        .iter()
+                        .map(SimValue::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
@@ -0,0 +1,24 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::table::SimValue, SimulatorEnv};
+
+/// `REINDEX <table>`, which rebuilds every index of the table from its rows
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Reindex {
+    pub(crate) table: String,
+}
+
+impl Reindex {
+    pub(crate) fn shadow(&self, _env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
+        // Rebuilding indexes never changes the table contents
+        vec![]
+    }
+}
+
+impl Display for Reindex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "REINDEX {}", self.table)
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::table::SimValue, SimulatorEnv};
+
+/// Savepoint statements, which open nested transactions that can be partially rolled back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Savepoint {
+    /// `SAVEPOINT <name>`
+    Begin(String),
+    /// `RELEASE <name>`, which also releases the savepoints opened after it
+    Release(String),
+    /// `ROLLBACK TO <name>`, which keeps the savepoint open
+    RollbackTo(String),
+}
+
+impl Savepoint {
+    pub(crate) fn shadow(&self, env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
+        match self {
+            Savepoint::Begin(name) => env.savepoints.push((name.clone(), env.tables.clone())),
+            Savepoint::Release(name) => {
+                if let Some(idx) = env.savepoints.iter().rposition(|(n, _)| n == name) {
+                    env.savepoints.truncate(idx);
+                }
+            }
+            Savepoint::RollbackTo(name) => {
+                if let Some(idx) = env.savepoints.iter().rposition(|(n, _)| n == name) {
+                    env.savepoints.truncate(idx + 1);
+                    env.tables = env.savepoints[idx].1.clone();
+                }
+            }
+        }
+
+        vec![]
+    }
+}
+
+impl Display for Savepoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Savepoint::Begin(name) => write!(f, "SAVEPOINT {}", name),
+            Savepoint::Release(name) => write!(f, "RELEASE {}", name),
+            Savepoint::RollbackTo(name) => write!(f, "ROLLBACK TO {}", name),
+        }
+    }
+}
@@ -1,10 +1,17 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use turso_sqlite3_parser::ast;
 
-use crate::{model::table::SimValue, SimulatorEnv};
+use crate::{
+    model::table::{Collation, SimValue, Table},
+    SimulatorEnv,
+};
 
-use super::predicate::Predicate;
+use super::{
+    create_index::SortOrder,
+    predicate::{expr_to_value, Predicate},
+};
 
 /// `SELECT` distinctness
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -15,6 +22,25 @@ pub(crate) enum Distinctness {
     All,
 }
 
+/// Key of an `ORDER BY` term
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum OrderingTerm {
+    /// column of the table, or `rowid`
+    Column(String),
+    /// 1-based position of a result column, as in `ORDER BY 3, 2`
+    Position(usize),
+}
+
+impl Display for OrderingTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderingTerm::Column(name) => write!(f, "{}", name),
+            OrderingTerm::Position(position) => write!(f, "{}", position),
+        }
+    }
+}
+
 /// `SELECT` or `RETURNING` result column
 // https://sqlite.org/syntax/result-column.html
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -25,6 +51,10 @@ pub enum ResultColumn {
     Star,
     /// column name
     Column(String),
+    /// expression renamed with `AS`
+    Aliased(Predicate, String),
+    /// aggregate over the rows of a group
+    Aggregate(Aggregate),
 }
 
 impl Display for ResultColumn {
@@ -33,48 +63,481 @@ impl Display for ResultColumn {
             ResultColumn::Expr(expr) => write!(f, "({})", expr),
             ResultColumn::Star => write!(f, "*"),
             ResultColumn::Column(name) => write!(f, "{}", name),
+            ResultColumn::Aliased(expr, alias) => write!(f, "({}) AS {}", expr, alias),
+            ResultColumn::Aggregate(aggregate) => write!(f, "{}", aggregate),
         }
     }
 }
+
+/// Aggregate functions the shadow model knows how to compute
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum AggregateFunc {
+    Count,
+    Min,
+    Max,
+}
+
+/// Aggregate function applied to a column, or `count(*)` when there is no column
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Aggregate {
+    pub(crate) func: AggregateFunc,
+    pub(crate) column: Option<String>,
+    /// Whether the function only sees each distinct value of the column once
+    #[serde(default)]
+    pub(crate) distinct: bool,
+}
+
+impl Aggregate {
+    fn to_expr(&self) -> ast::Expr {
+        let name = ast::Id(
+            match self.func {
+                AggregateFunc::Count => "count",
+                AggregateFunc::Min => "min",
+                AggregateFunc::Max => "max",
+            }
+            .to_string(),
+        );
+        match &self.column {
+            Some(column) => ast::Expr::FunctionCall {
+                name,
+                distinctness: self.distinct.then_some(ast::Distinctness::Distinct),
+                args: Some(vec![ast::Expr::Id(ast::Id(column.clone()))]),
+                order_by: None,
+                filter_over: None,
+            },
+            None => ast::Expr::FunctionCallStar {
+                name,
+                filter_over: None,
+            },
+        }
+    }
+
+    /// Computes the aggregate over the rows of a group. NULLs are skipped, values that compare
+    /// equal under the collation of the column count once when the aggregate is distinct, and
+    /// `min` and `max` are NULL when the group holds no other value.
+    pub(crate) fn compute(&self, rows: &[&[SimValue]], table: &Table) -> SimValue {
+        let Some(column) = &self.column else {
+            return SimValue(turso_core::Value::Integer(rows.len() as i64));
+        };
+        let Some(pos) = table.columns.iter().position(|c| &c.name == column) else {
+            return SimValue(turso_core::Value::Null);
+        };
+        let collation = table.columns[pos].collation.unwrap_or_default();
+        let compare = |a: &&SimValue, b: &&SimValue| compare_collated(a, b, collation);
+        let mut values = rows
+            .iter()
+            .map(|row| &row[pos])
+            .filter(|value| !matches!(value.0, turso_core::Value::Null))
+            .collect::<Vec<_>>();
+        if self.distinct {
+            values.sort_by(compare);
+            values.dedup_by(|a, b| compare(a, b) == std::cmp::Ordering::Equal);
+        }
+        let values = values.into_iter();
+        match self.func {
+            AggregateFunc::Count => SimValue(turso_core::Value::Integer(values.count() as i64)),
+            AggregateFunc::Min => values
+                .min_by(compare)
+                .cloned()
+                .unwrap_or(SimValue(turso_core::Value::Null)),
+            AggregateFunc::Max => values
+                .max_by(compare)
+                .cloned()
+                .unwrap_or(SimValue(turso_core::Value::Null)),
+        }
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Predicate(self.to_expr()))
+    }
+}
+
+fn compare_collated(a: &SimValue, b: &SimValue, collation: Collation) -> std::cmp::Ordering {
+    match (&a.0, &b.0) {
+        (turso_core::Value::Text(l), turso_core::Value::Text(r)) => {
+            collation.compare_strings(l.as_str(), r.as_str())
+        }
+        (l, r) => l.partial_cmp(r).unwrap_or(std::cmp::Ordering::Equal),
+    }
+}
+
+/// `HAVING <aggregate> <operator> <value>`, comparing the aggregate of each group to a literal
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Having {
+    pub(crate) aggregate: Aggregate,
+    pub(crate) operator: ast::Operator,
+    pub(crate) value: SimValue,
+}
+
+impl Having {
+    /// Checks whether a group is kept. Comparing a NULL aggregate is NULL, which drops the group.
+    pub(crate) fn test(&self, rows: &[&[SimValue]], table: &Table) -> bool {
+        let aggregate = self.aggregate.compute(rows, table);
+        if matches!(aggregate.0, turso_core::Value::Null)
+            || matches!(self.value.0, turso_core::Value::Null)
+        {
+            return false;
+        }
+        aggregate
+            .binary_compare(&self.value, self.operator)
+            .as_bool()
+    }
+}
+
+impl Display for Having {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expr = ast::Expr::Binary(
+            Box::new(self.aggregate.to_expr()),
+            self.operator,
+            Box::new(ast::Expr::Literal(self.value.clone().into())),
+        );
+        write!(f, "{}", Predicate(expr))
+    }
+}
+
+/// `GROUP BY` a single column, optionally filtering the groups with `HAVING`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GroupBy {
+    pub(crate) column: String,
+    pub(crate) having: Option<Having>,
+}
+
+/// How the second table of a `SELECT` is joined
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum JoinOperator {
+    /// `FROM <table>, <join.table>`, with `on` in the `WHERE` clause
+    Comma,
+    /// `FROM <table> LEFT JOIN <join.table> ON ...`, padding the rows of `table` without any
+    /// match with NULLs
+    Left,
+}
+
+/// Second table of a `SELECT`. With `on`, only the pairs of rows where
+/// `<table>.left_column = <join.table>.right_column` are kept.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Join {
+    pub(crate) table: String,
+    #[serde(default = "default_join_operator")]
+    pub(crate) operator: JoinOperator,
+    pub(crate) on: Option<(String, String)>,
+    /// `WHERE` filter on the columns of the joined table. It sees the NULLs of padded rows, so
+    /// it can turn a `LEFT JOIN` into an inner one.
+    #[serde(default)]
+    pub(crate) predicate: Option<Predicate>,
+}
+
+fn default_join_operator() -> JoinOperator {
+    JoinOperator::Comma
+}
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Select {
     pub(crate) table: String,
+    /// Name the table is referred to by in the rest of the query, e.g. `users u`
+    #[serde(default)]
+    pub(crate) table_alias: Option<String>,
     pub(crate) result_columns: Vec<ResultColumn>,
     pub(crate) predicate: Predicate,
     pub(crate) distinct: Distinctness,
+    /// `ORDER BY` keys, which may include `rowid`. Rows the keys don't tell apart are
+    /// returned in `rowid` order by the shadow model.
+    #[serde(default)]
+    pub(crate) order_by: Vec<(OrderingTerm, SortOrder)>,
     pub(crate) limit: Option<usize>,
+    /// `OFFSET` of the `LIMIT` clause. Without a limit, the query is written with
+    /// `LIMIT -1` and returns every row after the offset.
+    #[serde(default)]
+    pub(crate) offset: Option<usize>,
+    /// `GROUP BY` clause. The result columns of a grouped query are evaluated on the first row
+    /// of each group, except for aggregates, and its `ORDER BY` keys only use the group column.
+    #[serde(default)]
+    pub(crate) group_by: Option<GroupBy>,
+    /// Table joined with `table`, whose columns are named differently. The predicate only
+    /// refers to the columns of `table`, and the result columns are those of both tables.
+    #[serde(default)]
+    pub(crate) join: Option<Join>,
 }
 
 impl Select {
-    pub(crate) fn shadow(&self, env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
+    /// Refers to the table through `alias`, qualifying the predicate and every result column
+    /// with it and renaming the result columns with `AS`. The rows returned are unchanged.
+    pub(crate) fn with_alias(self, table: &Table, alias: String) -> Self {
+        let result_columns = table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                ResultColumn::Aliased(
+                    Predicate(ast::Expr::Qualified(
+                        ast::Name(alias.clone()),
+                        ast::Name(column.name.clone()),
+                    )),
+                    format!("{}_{}", alias, i),
+                )
+            })
+            .collect();
+        Self {
+            predicate: self.predicate.requalify(&self.table, &alias),
+            table_alias: Some(alias),
+            result_columns,
+            ..self
+        }
+    }
+
+    pub(crate) fn shadow(&self, env: &SimulatorEnv) -> Vec<Vec<SimValue>> {
         let table = env.tables.iter().find(|t| t.name == self.table.as_str());
         if let Some(table) = table {
-            table
-                .rows
-                .iter()
-                .filter(|row| self.predicate.test(row, table))
-                .cloned()
+            let mut positions = (0..table.rows.len())
+                .filter(|&i| self.predicate.test(&table.rows[i], table))
+                .collect::<Vec<_>>();
+            if let Some(join) = &self.join {
+                return self.shadow_join(join, positions, table, env);
+            }
+            if let Some(group_by) = &self.group_by {
+                return self.shadow_groups(group_by, positions, table);
+            }
+            if !self.order_by.is_empty() {
+                let order_by = self.resolve_order_by(table);
+                positions.sort_by(|&a, &b| table.compare_rows(&order_by, a, b));
+            }
+            positions = positions
+                .into_iter()
+                .skip(self.offset.unwrap_or(0))
+                .take(self.limit.unwrap_or(usize::MAX))
+                .collect();
+            positions
+                .into_iter()
+                .map(|i| self.project(&[&table.rows[i]], table))
                 .collect()
         } else {
             vec![]
         }
     }
+
+    /// Pairs the rows at `positions` with every row of the joined table, keeping the pairs whose
+    /// join columns are equal. NULLs never match anything. With a `LEFT JOIN`, a row without any
+    /// match is paired with NULLs, before the predicate of the joined table is tested.
+    fn shadow_join(
+        &self,
+        join: &Join,
+        positions: Vec<usize>,
+        table: &Table,
+        env: &SimulatorEnv,
+    ) -> Vec<Vec<SimValue>> {
+        let Some(joined) = env.tables.iter().find(|t| t.name == join.table) else {
+            return vec![];
+        };
+        let on = join.on.as_ref().map(|(left, right)| {
+            (
+                table.columns.iter().position(|c| &c.name == left).unwrap(),
+                joined
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == right)
+                    .unwrap(),
+            )
+        });
+        let padding = vec![SimValue(turso_core::Value::Null); joined.columns.len()];
+        let rows = positions.into_iter().flat_map(|i| {
+            let left = &table.rows[i];
+            let mut matches = joined
+                .rows
+                .iter()
+                .filter(|right| {
+                    on.map_or(true, |(l, r)| {
+                        !matches!(left[l].0, turso_core::Value::Null)
+                            && left[l]
+                                .binary_compare(&right[r], ast::Operator::Equals)
+                                .as_bool()
+                    })
+                })
+                .collect::<Vec<_>>();
+            if matches.is_empty() && join.operator == JoinOperator::Left {
+                matches.push(&padding);
+            }
+            matches
+                .into_iter()
+                .filter(|right| {
+                    join.predicate
+                        .as_ref()
+                        .map_or(true, |predicate| predicate.test(right, joined))
+                })
+                .map(|right| left.iter().chain(right.iter()).cloned().collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        });
+        rows.skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Groups the rows at `positions` by the group column, comparing text with its collation,
+    /// and computes the result columns of the groups kept by `HAVING`
+    fn shadow_groups(
+        &self,
+        group_by: &GroupBy,
+        positions: Vec<usize>,
+        table: &Table,
+    ) -> Vec<Vec<SimValue>> {
+        let Some(pos) = table.columns.iter().position(|c| c.name == group_by.column) else {
+            return vec![];
+        };
+        let collation = table.columns[pos].collation.unwrap_or_default();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for i in positions {
+            let key = &table.rows[i][pos];
+            match groups
+                .iter_mut()
+                .find(|group| compare_collated(&table.rows[group[0]][pos], key, collation).is_eq())
+            {
+                Some(group) => group.push(i),
+                None => groups.push(vec![i]),
+            }
+        }
+        // Groups come out in the order of their key unless the query orders them otherwise
+        let order_by = if self.order_by.is_empty() {
+            vec![(group_by.column.clone(), SortOrder::Asc)]
+        } else {
+            self.resolve_order_by(table)
+        };
+        groups.sort_by(|a, b| table.compare_rows(&order_by, a[0], b[0]));
+
+        let groups = groups.into_iter().filter_map(|group| {
+            let rows = group
+                .iter()
+                .map(|&i| table.rows[i].as_slice())
+                .collect::<Vec<_>>();
+            group_by
+                .having
+                .as_ref()
+                .map_or(true, |having| having.test(&rows, table))
+                .then(|| self.project(&rows, table))
+        });
+        groups
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Names the column of the table each `ORDER BY` key sorts by, resolving positions to the
+    /// result columns they refer to. Positions of result columns that aren't a plain column of
+    /// the table are dropped, like positions out of range.
+    fn resolve_order_by(&self, table: &Table) -> Vec<(String, SortOrder)> {
+        let projected = self
+            .result_columns
+            .iter()
+            .flat_map(|column| match column {
+                ResultColumn::Star => table.columns.iter().map(|c| Some(c.name.clone())).collect(),
+                ResultColumn::Column(name) => vec![Some(name.clone())],
+                ResultColumn::Expr(expr) | ResultColumn::Aliased(expr, _) => match &expr.0 {
+                    ast::Expr::Id(ast::Id(name)) | ast::Expr::Qualified(_, ast::Name(name)) => {
+                        vec![Some(name.clone())]
+                    }
+                    _ => vec![None],
+                },
+                ResultColumn::Aggregate(_) => vec![None],
+            })
+            .collect::<Vec<_>>();
+        self.order_by
+            .iter()
+            .filter_map(|(term, order)| {
+                let name = match term {
+                    OrderingTerm::Column(name) => name.clone(),
+                    OrderingTerm::Position(position) => {
+                        projected.get(position.checked_sub(1)?)?.clone()?
+                    }
+                };
+                Some((name, order.clone()))
+            })
+            .collect()
+    }
+
+    /// Computes the result columns for a group of rows of the table, which is a single row
+    /// unless the query is grouped
+    fn project(&self, rows: &[&[SimValue]], table: &Table) -> Vec<SimValue> {
+        let row = rows[0];
+        let mut values = Vec::with_capacity(row.len());
+        for column in &self.result_columns {
+            let expr = match column {
+                ResultColumn::Star => {
+                    values.extend(row.iter().cloned());
+                    continue;
+                }
+                ResultColumn::Aggregate(aggregate) => {
+                    values.push(aggregate.compute(rows, table));
+                    continue;
+                }
+                ResultColumn::Column(name) => &ast::Expr::Id(ast::Id(name.clone())),
+                ResultColumn::Expr(expr) | ResultColumn::Aliased(expr, _) => &expr.0,
+            };
+            values
+                .push(expr_to_value(expr, row, table).unwrap_or(SimValue(turso_core::Value::Null)));
+        }
+        values
+    }
 }
 
 impl Display for Select {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "SELECT {} FROM {} WHERE {}{}",
+            "SELECT {} FROM {}{}",
             self.result_columns
                 .iter()
                 .map(ResultColumn::to_string)
                 .collect::<Vec<_>>()
                 .join(", "),
             self.table,
-            self.predicate,
-            self.limit
-                .map_or("".to_string(), |l| format!(" LIMIT {}", l))
-        )
+            self.table_alias
+                .as_ref()
+                .map_or("".to_string(), |alias| format!(" {}", alias)),
+        )?;
+        match &self.join {
+            None => write!(f, " WHERE {}", self.predicate)?,
+            Some(join) => {
+                let on = join.on.as_ref().map(|(left, right)| {
+                    format!("{}.{} = {}.{}", self.table, left, join.table, right)
+                });
+                let mut filters = vec![format!("({})", self.predicate)];
+                match join.operator {
+                    JoinOperator::Comma => {
+                        write!(f, ", {}", join.table)?;
+                        filters.extend(on);
+                    }
+                    JoinOperator::Left => {
+                        write!(f, " LEFT JOIN {}", join.table)?;
+                        if let Some(on) = on {
+                            write!(f, " ON {}", on)?;
+                        }
+                    }
+                }
+                filters.extend(join.predicate.iter().map(|p| format!("({})", p)));
+                write!(f, " WHERE {}", filters.join(" AND "))?;
+            }
+        }
+        if let Some(group_by) = &self.group_by {
+            write!(f, " GROUP BY {}", group_by.column)?;
+            if let Some(having) = &group_by.having {
+                write!(f, " HAVING {}", having)?;
+            }
+        }
+        if !self.order_by.is_empty() {
+            write!(
+                f,
+                " ORDER BY {}",
+                self.order_by
+                    .iter()
+                    .map(|(term, order)| format!("{} {}", term, order))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        match (self.limit, self.offset) {
+            (Some(limit), Some(offset)) => write!(f, " LIMIT {} OFFSET {}", limit, offset)?,
+            (Some(limit), None) => write!(f, " LIMIT {}", limit)?,
+            (None, Some(offset)) => write!(f, " LIMIT -1 OFFSET {}", offset)?,
+            (None, None) => {}
+        }
+        Ok(())
     }
 }
@@ -1,56 +1,163 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use turso_core::types;
 
 use crate::{model::table::SimValue, SimulatorEnv};
 
-use super::predicate::Predicate;
+use super::{insert::ConflictResolution, predicate::Predicate};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Update {
     pub(crate) table: String,
     pub(crate) set_values: Vec<(String, SimValue)>, // Pair of value for set expressions => SET name=value
     pub(crate) predicate: Predicate,
+    #[serde(default)]
+    pub(crate) from: Option<UpdateFrom>,
+    /// Resolution of the UNIQUE constraint violations, given by `UPDATE OR <resolution>`.
+    /// The database does not support `OR REPLACE` for updates.
+    #[serde(default)]
+    pub(crate) or_conflict: Option<ConflictResolution>,
+}
+
+/// Source table of an `UPDATE ... FROM`. Target rows are matched with the source rows where
+/// `target_column = alias.source_column`, and only matched rows are updated.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct UpdateFrom {
+    pub(crate) table: String,
+    pub(crate) alias: String,
+    pub(crate) target_column: String,
+    pub(crate) source_column: String,
+    /// Target columns set to `alias.source_column`. Every source row matching a target row
+    /// holds the same value there, so the result never depends on which match is used.
+    pub(crate) columns: Vec<String>,
 }
 
 impl Update {
     pub(crate) fn shadow(&self, env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
-        let table = env
-            .tables
-            .iter_mut()
-            .find(|t| t.name == self.table)
-            .unwrap();
-        let t2 = table.clone();
-        for row in table
-            .rows
-            .iter_mut()
-            .filter(|r| self.predicate.test(r, &t2))
-        {
-            for (column, set_value) in &self.set_values {
-                let (idx, _) = table
-                    .columns
-                    .iter()
-                    .enumerate()
-                    .find(|(_, c)| &c.name == column)
+        match self.updated_rows(env, false) {
+            Some(rows) => {
+                let table = env
+                    .tables
+                    .iter_mut()
+                    .find(|t| t.name == self.table)
                     .unwrap();
-                row[idx] = set_value.clone();
+                table.rows = rows;
+            }
+            None if self.or_conflict == Some(ConflictResolution::Rollback) => {
+                ConflictResolution::rollback(env);
             }
+            None => {}
         }
 
         vec![]
     }
+
+    /// Returns the rows of the table once updated, or `None` when a UNIQUE index key is
+    /// duplicated and the update fails as a whole. With `OR IGNORE`, the rows are updated one
+    /// at a time in rowid order, or in reverse if `reverse` is set, skipping the ones that would
+    /// duplicate a key.
+    pub(crate) fn updated_rows(
+        &self,
+        env: &SimulatorEnv,
+        reverse: bool,
+    ) -> Option<Vec<Vec<SimValue>>> {
+        // NULLs never match anything, so they are left out of the source values
+        let source_values = self.from.as_ref().map(|from| {
+            let source = env.tables.iter().find(|t| t.name == from.table).unwrap();
+            let idx = source
+                .columns
+                .iter()
+                .position(|c| c.name == from.source_column)
+                .unwrap();
+            source
+                .rows
+                .iter()
+                .map(|row| row[idx].clone())
+                .filter(|value| !matches!(value.0, types::Value::Null))
+                .collect::<Vec<_>>()
+        });
+        let table = env.tables.iter().find(|t| t.name == self.table).unwrap();
+        let position = |column: &String| {
+            table
+                .columns
+                .iter()
+                .position(|c| &c.name == column)
+                .unwrap()
+        };
+        let mut rows = table.rows.clone();
+        let mut order = (0..rows.len()).collect::<Vec<_>>();
+        if reverse {
+            order.reverse();
+        }
+        for i in order {
+            if !self.predicate.test(&table.rows[i], table) {
+                continue;
+            }
+            let mut row = rows[i].clone();
+            if let (Some(from), Some(source_values)) = (&self.from, &source_values) {
+                let target_value = &row[position(&from.target_column)];
+                let Some(value) = source_values.iter().find(|v| *v == target_value).cloned() else {
+                    continue;
+                };
+                for column in &from.columns {
+                    row[position(column)] = value.clone();
+                }
+            }
+            for (column, set_value) in &self.set_values {
+                row[position(column)] = set_value.clone();
+            }
+            table.fill_generated(&mut row);
+            if self.or_conflict == Some(ConflictResolution::Ignore)
+                && table.conflicting_rows(&rows, &row).iter().any(|&j| j != i)
+            {
+                continue;
+            }
+            rows[i] = row;
+        }
+        // An update that duplicates a UNIQUE index key fails as a whole
+        if table.has_unique_conflict(&rows) {
+            return None;
+        }
+        Some(rows)
+    }
 }
 
 impl Display for Update {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "UPDATE {} SET ", self.table)?;
-        for (i, (name, value)) in self.set_values.iter().enumerate() {
+        match self.or_conflict {
+            Some(or_conflict) => write!(f, "UPDATE OR {} {} SET ", or_conflict, self.table)?,
+            None => write!(f, "UPDATE {} SET ", self.table)?,
+        }
+        let assignments = self
+            .set_values
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .chain(self.from.iter().flat_map(|from| {
+                from.columns
+                    .iter()
+                    .map(move |name| format!("{} = {}.{}", name, from.alias, from.source_column))
+            }));
+        for (i, assignment) in assignments.enumerate() {
             if i != 0 {
                 write!(f, ", ")?;
             }
-            write!(f, "{} = {}", name, value)?;
+            write!(f, "{}", assignment)?;
+        }
+        match &self.from {
+            None => write!(f, " WHERE {}", self.predicate)?,
+            Some(from) => write!(
+                f,
+                " FROM {} AS {} WHERE ({}) AND {}.{} = {}.{}",
+                from.table,
+                from.alias,
+                self.predicate,
+                self.table,
+                from.target_column,
+                from.alias,
+                from.source_column
+            )?,
         }
-        write!(f, " WHERE {}", self.predicate)?;
         Ok(())
     }
 }
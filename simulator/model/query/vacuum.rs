@@ -0,0 +1,22 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::table::SimValue, SimulatorEnv};
+
+/// `VACUUM`, which rebuilds the whole database file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Vacuum;
+
+impl Vacuum {
+    pub(crate) fn shadow(&self, _env: &mut SimulatorEnv) -> Vec<Vec<SimValue>> {
+        // Rebuilding the database never changes its contents
+        vec![]
+    }
+}
+
+impl Display for Vacuum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "VACUUM")
+    }
+}
@@ -0,0 +1,595 @@
+//! Fixed schema loaded with `--tables-from-file`. The plan starts by creating its tables and
+//! indexes, and then only runs DML and queries against them.
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use fallible_iterator::FallibleIterator;
+use turso_sqlite3_parser::{ast, lexer::sql::Parser};
+
+use crate::model::{
+    domain::Domains,
+    query::{create_index::SortOrder, Create, CreateIndex},
+    table::{
+        Collation, Column, ColumnDefault, ColumnType, ForeignKey, GeneratedColumn, SimValue, Table,
+    },
+};
+
+/// Tables and indexes of a SQL file, in the order they are declared
+#[derive(Debug, Clone)]
+pub(crate) struct Schema {
+    pub(crate) tables: Vec<Create>,
+    /// Indexes of the file, along with the ones standing for `UNIQUE` and composite
+    /// `PRIMARY KEY` constraints of the tables
+    pub(crate) indexes: Vec<CreateIndex>,
+    /// Value domains declared with `-- @domain` annotations
+    pub(crate) domains: Domains,
+}
+
+impl Schema {
+    pub(crate) fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let sql = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read schema file {}", path.display()))?;
+        Self::from_sql(&sql).with_context(|| format!("invalid schema file {}", path.display()))
+    }
+
+    /// Parses `CREATE TABLE` and `CREATE INDEX` statements into the tables of the model.
+    /// Anything the model cannot follow, such as `NOT NULL` or `CHECK` constraints, is refused
+    /// rather than silently dropped, as it would make the shadow state diverge from the database.
+    pub(crate) fn from_sql(sql: &str) -> anyhow::Result<Self> {
+        let mut schema = Schema {
+            tables: Vec::new(),
+            indexes: Vec::new(),
+            domains: Domains::from_annotations(sql)?,
+        };
+        let mut parser = Parser::new(sql.as_bytes());
+        while let Some(cmd) = parser.next()? {
+            match cmd {
+                ast::Cmd::Stmt(ast::Stmt::CreateTable {
+                    temporary,
+                    tbl_name,
+                    body,
+                    ..
+                }) => {
+                    if temporary {
+                        bail!("temporary tables are not supported");
+                    }
+                    schema.add_table(unquote(&tbl_name.name.0), *body)?;
+                }
+                ast::Cmd::Stmt(ast::Stmt::CreateIndex {
+                    unique,
+                    idx_name,
+                    tbl_name,
+                    columns,
+                    where_clause,
+                    ..
+                }) => {
+                    if where_clause.is_some() {
+                        bail!("partial indexes are not supported");
+                    }
+                    let table_name = unquote(&tbl_name.0);
+                    let columns = columns
+                        .iter()
+                        .map(|column| {
+                            let name = indexed_column_name(&column.expr)?;
+                            let order = match column.order {
+                                Some(ast::SortOrder::Desc) => SortOrder::Desc,
+                                _ => SortOrder::Asc,
+                            };
+                            Ok((name, order))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    schema.add_index(CreateIndex {
+                        index_name: unquote(&idx_name.name.0),
+                        table_name,
+                        columns,
+                        unique,
+                    })?;
+                }
+                cmd => bail!(
+                    "only CREATE TABLE and CREATE INDEX statements are supported, got `{}`",
+                    cmd
+                ),
+            }
+        }
+        if schema.tables.is_empty() {
+            bail!("the schema has no tables");
+        }
+        schema.validate_domains()?;
+        Ok(schema)
+    }
+
+    /// Checks that every domain is declared on a column that stores its values unchanged.
+    /// Key columns are refused, as values repeated on purpose would keep violating the key.
+    fn validate_domains(&self) -> anyhow::Result<()> {
+        for (table_name, column_name, domain) in self.domains.iter() {
+            let Some(table) = self.table(table_name) else {
+                bail!(
+                    "domain {} is declared on unknown table {}",
+                    domain,
+                    table_name
+                );
+            };
+            let Some(column) = table.columns.iter().find(|c| c.name == column_name) else {
+                bail!("no such column {}.{}", table_name, column_name);
+            };
+            if !domain.accepts(&column.column_type) {
+                bail!(
+                    "domain {} does not match the type {} of {}.{}",
+                    domain,
+                    column.column_type,
+                    table_name,
+                    column_name
+                );
+            }
+            let is_key = column.primary
+                || self.indexes.iter().any(|index| {
+                    index.unique
+                        && index.table_name == table_name
+                        && index.columns.iter().any(|(name, _)| name == column_name)
+                });
+            if is_key || column.generated.is_some() {
+                bail!(
+                    "domain {} cannot be declared on {}.{}, which is a key or a generated column",
+                    domain,
+                    table_name,
+                    column_name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the schema declares keys that can only be enforced with an index
+    pub(crate) fn has_unique_keys(&self) -> bool {
+        self.indexes.iter().any(|index| index.unique)
+            || self
+                .tables
+                .iter()
+                .any(|create| !create.table.unique_indexes.is_empty())
+    }
+
+    fn table(&self, name: &str) -> Option<&Table> {
+        self.tables
+            .iter()
+            .map(|create| &create.table)
+            .find(|table| table.name == name)
+    }
+
+    fn add_table(&mut self, name: String, body: ast::CreateTableBody) -> anyhow::Result<()> {
+        let ast::CreateTableBody::ColumnsAndConstraints {
+            columns: definitions,
+            constraints,
+            options,
+        } = body
+        else {
+            bail!("CREATE TABLE ... AS SELECT is not supported");
+        };
+        if self.table(&name).is_some() {
+            bail!("table {} is declared twice", name);
+        }
+        if !options.is_empty() {
+            bail!("WITHOUT ROWID and STRICT tables are not supported");
+        }
+
+        let mut table = Table {
+            rows: Vec::new(),
+            name,
+            columns: Vec::new(),
+            foreign_keys: Vec::new(),
+            indexed_columns: Vec::new(),
+            unique_indexes: Vec::new(),
+        };
+        // Keys that need an index of their own, created right after the table
+        let mut unique_keys = Vec::new();
+        for definition in definitions.values() {
+            let column_name = unquote(&definition.col_name.0);
+            let column_type = column_type(definition.col_type.as_ref())?;
+            let mut column = Column {
+                name: column_name.clone(),
+                column_type,
+                primary: false,
+                autoincrement: false,
+                unique: false,
+                collation: None,
+                default: None,
+                generated: None,
+            };
+            for constraint in &definition.constraints {
+                match &constraint.constraint {
+                    ast::ColumnConstraint::PrimaryKey { auto_increment, .. } => {
+                        column.primary = true;
+                        column.autoincrement = *auto_increment;
+                    }
+                    ast::ColumnConstraint::Unique(_) => {
+                        unique_keys.push(vec![column_name.clone()]);
+                    }
+                    ast::ColumnConstraint::Collate { collation_name } => {
+                        column.collation = Some(collation(&collation_name.0)?);
+                    }
+                    ast::ColumnConstraint::Default(expr) => {
+                        column.default = Some(column_default(expr, &column.column_type)?);
+                    }
+                    ast::ColumnConstraint::Generated { expr, typ } => {
+                        let stored = match typ {
+                            Some(typ) if typ.0.eq_ignore_ascii_case("stored") => true,
+                            Some(typ) if typ.0.eq_ignore_ascii_case("virtual") => false,
+                            None => false,
+                            Some(typ) => bail!("unknown generated column kind {}", typ.0),
+                        };
+                        column.generated = Some(GeneratedColumn {
+                            source: indexed_column_name(expr).with_context(|| {
+                                format!("generated column {} must copy a column", column_name)
+                            })?,
+                            stored,
+                        });
+                    }
+                    ast::ColumnConstraint::ForeignKey {
+                        clause,
+                        deref_clause: None,
+                    } => {
+                        table
+                            .foreign_keys
+                            .push(foreign_key(vec![column_name.clone()], clause)?);
+                    }
+                    _ => bail!(
+                        "unsupported constraint on column {}.{}",
+                        table.name,
+                        column_name
+                    ),
+                }
+            }
+            table.columns.push(column);
+        }
+
+        for constraint in constraints.iter().flatten() {
+            match &constraint.constraint {
+                ast::TableConstraint::PrimaryKey {
+                    columns,
+                    auto_increment,
+                    ..
+                } => {
+                    let columns = columns
+                        .iter()
+                        .map(|column| indexed_column_name(&column.expr))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    match columns.as_slice() {
+                        [name] => {
+                            let Some(column) = table.columns.iter_mut().find(|c| &c.name == name)
+                            else {
+                                bail!("no such column {}.{}", table.name, name);
+                            };
+                            column.primary = true;
+                            column.autoincrement = *auto_increment;
+                        }
+                        // A composite key behaves as a UNIQUE one in a rowid table
+                        _ => unique_keys.push(columns),
+                    }
+                }
+                ast::TableConstraint::Unique { columns, .. } => {
+                    unique_keys.push(
+                        columns
+                            .iter()
+                            .map(|column| indexed_column_name(&column.expr))
+                            .collect::<anyhow::Result<Vec<_>>>()?,
+                    );
+                }
+                ast::TableConstraint::ForeignKey {
+                    columns,
+                    clause,
+                    deref_clause: None,
+                } => {
+                    let columns = columns
+                        .iter()
+                        .map(|column| unquote(&column.col_name.0))
+                        .collect();
+                    table.foreign_keys.push(foreign_key(columns, clause)?);
+                }
+                _ => bail!("unsupported table constraint on {}", table.name),
+            }
+        }
+
+        validate_table(&table)?;
+        // Only an INTEGER PRIMARY KEY aliases the rowid, any other key is backed by an index
+        if let Some(column) = table
+            .columns
+            .iter()
+            .find(|c| c.primary && c.column_type != ColumnType::Integer)
+        {
+            table.unique_indexes.push(vec![column.name.clone()]);
+        }
+        let table_name = table.name.clone();
+        self.tables.push(Create { table });
+        for (i, columns) in unique_keys.into_iter().enumerate() {
+            self.add_index(CreateIndex {
+                index_name: format!("{}_unique_{}", table_name, i + 1),
+                table_name: table_name.clone(),
+                columns: columns
+                    .into_iter()
+                    .map(|name| (name, SortOrder::Asc))
+                    .collect(),
+                unique: true,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn add_index(&mut self, index: CreateIndex) -> anyhow::Result<()> {
+        let Some(table) = self.table(&index.table_name) else {
+            bail!(
+                "index {} is on table {}, which is not declared before it",
+                index.index_name,
+                index.table_name
+            );
+        };
+        for (name, _) in &index.columns {
+            let Some(column) = table.columns.iter().find(|c| &c.name == name) else {
+                bail!("no such column {}.{}", table.name, name);
+            };
+            if column.generated.as_ref().is_some_and(|g| !g.stored) {
+                bail!(
+                    "VIRTUAL generated column {}.{} cannot be indexed",
+                    table.name,
+                    name
+                );
+            }
+        }
+        if self
+            .indexes
+            .iter()
+            .any(|other| other.index_name == index.index_name)
+        {
+            bail!("index {} is declared twice", index.index_name);
+        }
+        self.indexes.push(index);
+        Ok(())
+    }
+}
+
+/// Checks the parts of a table that refer to its own columns, or to other tables
+fn validate_table(table: &Table) -> anyhow::Result<()> {
+    if table.columns.iter().filter(|c| c.primary).count() > 1 {
+        bail!("table {} has more than one primary key", table.name);
+    }
+    for column in &table.columns {
+        if column.autoincrement && column.column_type != ColumnType::Integer {
+            bail!("AUTOINCREMENT is only allowed on an INTEGER PRIMARY KEY");
+        }
+        let Some(generated) = &column.generated else {
+            continue;
+        };
+        match table.columns.iter().find(|c| c.name == generated.source) {
+            Some(source)
+                if source.generated.is_none() && source.column_type == column.column_type => {}
+            _ => bail!(
+                "generated column {}.{} must copy a regular column of the same type",
+                table.name,
+                column.name
+            ),
+        }
+    }
+    for fk in &table.foreign_keys {
+        if fk.columns.len() != fk.parent_columns.len() {
+            bail!(
+                "foreign key of {} has {} columns but references {}",
+                table.name,
+                fk.columns.len(),
+                fk.parent_columns.len()
+            );
+        }
+        if let Some(name) = fk
+            .columns
+            .iter()
+            .find(|name| !table.columns.iter().any(|c| &c.name == *name))
+        {
+            bail!("no such column {}.{}", table.name, name);
+        }
+    }
+    Ok(())
+}
+
+/// Maps a declared type to the column type of the same affinity, following the rules of
+/// <https://www.sqlite.org/datatype3.html#determination_of_column_affinity>
+fn column_type(col_type: Option<&ast::Type>) -> anyhow::Result<ColumnType> {
+    let name = col_type.map(|t| t.name.to_uppercase()).unwrap_or_default();
+    if name.contains("INT") {
+        Ok(ColumnType::Integer)
+    } else if ["CHAR", "CLOB", "TEXT"].iter().any(|s| name.contains(s)) {
+        Ok(ColumnType::Text)
+    } else if name.is_empty() || name.contains("BLOB") {
+        Ok(ColumnType::Blob)
+    } else if ["REAL", "FLOA", "DOUB"].iter().any(|s| name.contains(s)) {
+        Ok(ColumnType::Float)
+    } else {
+        bail!("type {} has NUMERIC affinity, which is not supported", name)
+    }
+}
+
+fn collation(name: &str) -> anyhow::Result<Collation> {
+    match unquote(name).as_str() {
+        "binary" => Ok(Collation::Binary),
+        "nocase" => Ok(Collation::NoCase),
+        "rtrim" => Ok(Collation::Rtrim),
+        name => bail!("unknown collation {}", name),
+    }
+}
+
+/// Converts a `DEFAULT` clause, which must be a literal that the column stores as it is
+fn column_default(expr: &ast::Expr, column_type: &ColumnType) -> anyhow::Result<ColumnDefault> {
+    let value = match expr {
+        ast::Expr::Literal(ast::Literal::CurrentTimestamp) if *column_type == ColumnType::Text => {
+            return Ok(ColumnDefault::CurrentTimestamp)
+        }
+        ast::Expr::Literal(
+            literal @ (ast::Literal::Null
+            | ast::Literal::Numeric(_)
+            | ast::Literal::String(_)
+            | ast::Literal::Blob(_)),
+        ) => SimValue::from(literal),
+        ast::Expr::Unary(ast::UnaryOperator::Negative, operand) => match operand.as_ref() {
+            ast::Expr::Literal(literal @ ast::Literal::Numeric(_)) => {
+                SimValue::from(literal).unary_exec(ast::UnaryOperator::Negative)
+            }
+            _ => bail!("DEFAULT must be a literal, got `{}`", expr),
+        },
+        _ => bail!("DEFAULT must be a literal, got `{}`", expr),
+    };
+    // The value must come back unchanged by the affinity of the column
+    let value = match (column_type, value.0) {
+        (ColumnType::Float, turso_core::Value::Integer(i)) => {
+            SimValue(turso_core::Value::Float(i as f64))
+        }
+        (_, value @ turso_core::Value::Null)
+        | (ColumnType::Blob, value)
+        | (ColumnType::Integer, value @ turso_core::Value::Integer(_))
+        | (ColumnType::Float, value @ turso_core::Value::Float(_))
+        | (ColumnType::Text, value @ turso_core::Value::Text(_)) => SimValue(value),
+        _ => bail!(
+            "DEFAULT `{}` does not match the column type {}",
+            expr,
+            column_type
+        ),
+    };
+    Ok(ColumnDefault::Value(value))
+}
+
+fn foreign_key(columns: Vec<String>, clause: &ast::ForeignKeyClause) -> anyhow::Result<ForeignKey> {
+    if !clause.args.is_empty() {
+        bail!("foreign key actions are not supported");
+    }
+    let Some(parent_columns) = &clause.columns else {
+        bail!(
+            "the foreign key referencing {} must name the parent columns",
+            clause.tbl_name.0
+        );
+    };
+    Ok(ForeignKey {
+        columns,
+        parent_table: unquote(&clause.tbl_name.0),
+        parent_columns: parent_columns
+            .iter()
+            .map(|column| unquote(&column.col_name.0))
+            .collect(),
+    })
+}
+
+/// Name of an indexed or copied column, which must be a plain column reference
+fn indexed_column_name(expr: &ast::Expr) -> anyhow::Result<String> {
+    match expr {
+        ast::Expr::Id(ast::Id(name)) | ast::Expr::Name(ast::Name(name)) => Ok(unquote(name)),
+        _ => bail!("expected a column name, got `{}`", expr),
+    }
+}
+
+/// Strips the quotes of an identifier and lowercases it, the way the database compares them
+fn unquote(name: &str) -> String {
+    let unquoted = match name.as_bytes() {
+        [b'"', .., b'"'] | [b'`', .., b'`'] | [b'[', .., b']'] | [b'\'', .., b'\''] => {
+            &name[1..name.len() - 1]
+        }
+        _ => name,
+    };
+    unquoted.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Schema;
+    use crate::model::{
+        domain::ValueDomain,
+        table::{Collation, ColumnDefault, ColumnType},
+    };
+
+    #[test]
+    fn test_schema_from_sql() {
+        let schema = Schema::from_sql(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, name VARCHAR(20) COLLATE NOCASE, score REAL DEFAULT -1);
+             CREATE TABLE \"Orders\" (id INTEGER PRIMARY KEY, user_id INT, code TEXT UNIQUE, data,
+                 FOREIGN KEY (user_id) REFERENCES users(id));
+             CREATE INDEX idx_orders_user ON orders (user_id DESC);",
+        )
+        .unwrap();
+
+        assert_eq!(schema.tables.len(), 2);
+        let users = &schema.tables[0].table;
+        assert_eq!(users.name, "users");
+        assert!(users.columns[0].primary && users.columns[0].autoincrement);
+        assert_eq!(users.columns[1].column_type, ColumnType::Text);
+        assert_eq!(users.columns[1].collation, Some(Collation::NoCase));
+        assert_eq!(users.columns[2].column_type, ColumnType::Float);
+        assert!(matches!(
+            &users.columns[2].default,
+            Some(ColumnDefault::Value(v)) if v.0 == turso_core::Value::Float(-1.0)
+        ));
+
+        let orders = &schema.tables[1].table;
+        assert_eq!(orders.name, "orders");
+        assert_eq!(orders.columns[3].column_type, ColumnType::Blob);
+        assert_eq!(orders.foreign_keys[0].parent_table, "users");
+
+        let names = schema
+            .indexes
+            .iter()
+            .map(|index| (index.index_name.as_str(), index.unique))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![("orders_unique_1", true), ("idx_orders_user", false)]
+        );
+        assert!(schema.has_unique_keys());
+    }
+
+    #[test]
+    fn test_schema_from_sql_refuses_unsupported() {
+        for sql in [
+            "",
+            "INSERT INTO t VALUES (1)",
+            "CREATE TABLE t (a INTEGER NOT NULL)",
+            "CREATE TABLE t (a INTEGER CHECK (a > 0))",
+            "CREATE TABLE t (a NUMERIC)",
+            "CREATE TABLE t (a TEXT DEFAULT 1)",
+            "CREATE TABLE t (a INTEGER) WITHOUT ROWID",
+            "CREATE INDEX i ON t (a)",
+            "CREATE TABLE t (a INTEGER); CREATE INDEX i ON t (a) WHERE a > 0",
+            "CREATE TABLE t (a INTEGER); CREATE INDEX i ON t (a + 1)",
+        ] {
+            assert!(Schema::from_sql(sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_schema_domains() {
+        let schema = Schema::from_sql(
+            "-- @domain orders.user_id small_int
+             -- @domain Orders.Placed date
+             CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INT, placed TEXT, note TEXT);",
+        )
+        .unwrap();
+        assert_eq!(
+            schema.domains.get("orders", "user_id"),
+            Some(ValueDomain::SmallInt)
+        );
+        assert_eq!(
+            schema.domains.get("orders", "placed"),
+            Some(ValueDomain::Date)
+        );
+        assert_eq!(schema.domains.get("orders", "note"), None);
+
+        for annotation in [
+            "-- @domain t.a",
+            "-- @domain a small_int",
+            "-- @domain t.a zip_code",
+            "-- @domain t.b small_int",
+            "-- @domain u.a small_int",
+            "-- @domain t.a name",
+            "-- @domain t.id small_int",
+            "-- @domain t.a small_int\n-- @domain t.a small_int",
+        ] {
+            let sql = format!(
+                "{}\nCREATE TABLE t (id INTEGER PRIMARY KEY, a INTEGER);",
+                annotation
+            );
+            assert!(Schema::from_sql(&sql).is_err(), "{}", sql);
+        }
+    }
+}
@@ -1,9 +1,11 @@
-use std::{fmt::Display, hash::Hash, ops::Deref};
+use std::{cmp::Ordering, fmt::Display, hash::Hash, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 use turso_core::{numeric::Numeric, types};
 use turso_sqlite3_parser::ast;
 
+use super::query::create_index::SortOrder;
+
 pub(crate) struct Name(pub(crate) String);
 
 impl Deref for Name {
@@ -19,6 +21,147 @@ pub(crate) struct Table {
     pub(crate) rows: Vec<Vec<SimValue>>,
     pub(crate) name: String,
     pub(crate) columns: Vec<Column>,
+    #[serde(default)]
+    pub(crate) foreign_keys: Vec<ForeignKey>,
+    /// Columns covered by at least one index, which cannot be dropped
+    #[serde(default)]
+    pub(crate) indexed_columns: Vec<String>,
+    /// Column sets of the UNIQUE indexes on the table
+    #[serde(default)]
+    pub(crate) unique_indexes: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Checks whether two of `rows` share a key in one of the UNIQUE indexes of the table.
+    /// Keys containing a NULL never conflict, as NULLs are distinct from each other.
+    pub(crate) fn has_unique_conflict(&self, rows: &[Vec<SimValue>]) -> bool {
+        self.unique_indexes
+            .iter()
+            .any(|columns| self.has_duplicate_key(columns, rows))
+    }
+
+    /// Checks whether two of `rows` have the same non-NULL key over `columns`,
+    /// comparing text with the collation of each column.
+    pub(crate) fn has_duplicate_key(&self, columns: &[String], rows: &[Vec<SimValue>]) -> bool {
+        let columns = columns
+            .iter()
+            .filter_map(|name| self.columns.iter().position(|c| &c.name == name))
+            .collect::<Vec<_>>();
+        let keys = rows
+            .iter()
+            .filter(|row| {
+                !columns
+                    .iter()
+                    .any(|&i| matches!(row[i].0, types::Value::Null))
+            })
+            .collect::<Vec<_>>();
+        keys.iter().enumerate().any(|(i, a)| {
+            keys[i + 1..].iter().any(|b| {
+                columns
+                    .iter()
+                    .all(|&c| match (&a[c].0, &b[c].0, self.columns[c].collation) {
+                        (types::Value::Text(l), types::Value::Text(r), Some(collation)) => {
+                            collation.compare_strings(l.as_str(), r.as_str()) == Ordering::Equal
+                        }
+                        _ => a[c] == b[c],
+                    })
+            })
+        })
+    }
+
+    /// Returns the positions in `rows` of the rows sharing a key with `row` in one of the
+    /// UNIQUE indexes of the table.
+    pub(crate) fn conflicting_rows(&self, rows: &[Vec<SimValue>], row: &[SimValue]) -> Vec<usize> {
+        rows.iter()
+            .enumerate()
+            .filter(|(_, existing)| {
+                let pair = [existing.to_vec(), row.to_vec()];
+                self.unique_indexes
+                    .iter()
+                    .any(|columns| self.has_duplicate_key(columns, &pair))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Orders the rows at positions `a` and `b` the way `ORDER BY <order_by>` does, breaking
+    /// ties by `rowid`. The key `rowid` itself may be part of `order_by`, too.
+    pub(crate) fn compare_rows(
+        &self,
+        order_by: &[(String, SortOrder)],
+        a: usize,
+        b: usize,
+    ) -> Ordering {
+        let (row_a, row_b) = (&self.rows[a], &self.rows[b]);
+        for (name, order) in order_by {
+            let ordering = if name.eq_ignore_ascii_case("rowid") {
+                self.compare_rowids(a, b)
+            } else {
+                let Some(pos) = self.columns.iter().position(|c| &c.name == name) else {
+                    continue;
+                };
+                match (&row_a[pos].0, &row_b[pos].0, self.columns[pos].collation) {
+                    (types::Value::Text(l), types::Value::Text(r), Some(collation)) => {
+                        collation.compare_strings(l.as_str(), r.as_str())
+                    }
+                    (l, r, _) => l.partial_cmp(r).unwrap_or(Ordering::Equal),
+                }
+            };
+            let ordering = match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        self.compare_rowids(a, b)
+    }
+
+    /// Computes the generated columns of `row`, whose other columns are already set
+    pub(crate) fn fill_generated(&self, row: &mut [SimValue]) {
+        for (i, column) in self.columns.iter().enumerate() {
+            let Some(generated) = &column.generated else {
+                continue;
+            };
+            if let Some(source) = self.columns.iter().position(|c| c.name == generated.source) {
+                row[i] = row[source].clone();
+            }
+        }
+    }
+
+    /// Columns an insert can write, with their positions, or `None` when that is all of them.
+    /// Generated columns cannot be written, so inserts into tables having any must list the
+    /// other columns explicitly.
+    pub(crate) fn insert_columns(&self) -> Option<Vec<(usize, String)>> {
+        if self.columns.iter().all(|c| c.generated.is_none()) {
+            return None;
+        }
+        Some(
+            self.columns
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.generated.is_none())
+                .map(|(i, c)| (i, c.name.clone()))
+                .collect(),
+        )
+    }
+
+    fn compare_rowids(&self, a: usize, b: usize) -> Ordering {
+        // Without an INTEGER PRIMARY KEY, rowids grow with every insert, so they follow
+        // the order in which the rows are kept
+        match self
+            .columns
+            .iter()
+            .position(|c| c.primary && matches!(c.column_type, ColumnType::Integer))
+        {
+            Some(pos) => self.rows[a][pos]
+                .0
+                .partial_cmp(&self.rows[b][pos].0)
+                .unwrap_or(Ordering::Equal),
+            None => a.cmp(&b),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,7 +169,15 @@ pub(crate) struct Column {
     pub(crate) name: String,
     pub(crate) column_type: ColumnType,
     pub(crate) primary: bool,
+    /// Whether the `INTEGER PRIMARY KEY` is declared `AUTOINCREMENT`
+    #[serde(default)]
+    pub(crate) autoincrement: bool,
     pub(crate) unique: bool,
+    pub(crate) collation: Option<Collation>,
+    #[serde(default)]
+    pub(crate) default: Option<ColumnDefault>,
+    #[serde(default)]
+    pub(crate) generated: Option<GeneratedColumn>,
 }
 
 // Uniquely defined by name in this case
@@ -44,7 +195,61 @@ impl PartialEq for Column {
 
 impl Eq for Column {}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Value a column takes when an insert leaves it out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ColumnDefault {
+    Value(SimValue),
+    /// `CURRENT_TIMESTAMP`, the UTC time of the insert as `YYYY-MM-DD HH:MM:SS`
+    CurrentTimestamp,
+}
+
+impl ColumnDefault {
+    /// Evaluates the default the way the database does when a row is inserted
+    pub(crate) fn value(&self) -> SimValue {
+        match self {
+            Self::Value(value) => value.clone(),
+            Self::CurrentTimestamp => SimValue(types::Value::build_text(
+                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            )),
+        }
+    }
+}
+
+impl Display for ColumnDefault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Value(value) => write!(f, "{}", value),
+            Self::CurrentTimestamp => write!(f, "CURRENT_TIMESTAMP"),
+        }
+    }
+}
+
+/// A `GENERATED ALWAYS AS (source)` clause, computing the column as a copy of another column of
+/// the same type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct GeneratedColumn {
+    /// Name of the non-generated column the value is copied from
+    pub(crate) source: String,
+    /// Whether the value is kept in the record (`STORED`) or computed on read (`VIRTUAL`)
+    pub(crate) stored: bool,
+}
+
+impl Display for GeneratedColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.stored { "STORED" } else { "VIRTUAL" };
+        write!(f, "AS ({}) {}", self.source, kind)
+    }
+}
+
+/// A `FOREIGN KEY (columns) REFERENCES parent_table(parent_columns)` table constraint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ForeignKey {
+    pub(crate) columns: Vec<String>,
+    pub(crate) parent_table: String,
+    pub(crate) parent_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) enum ColumnType {
     Integer,
     Float,
@@ -63,6 +268,39 @@ impl Display for ColumnType {
     }
 }
 
+/// Built-in collating sequences supported by the database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Collation {
+    #[default]
+    Binary,
+    NoCase,
+    Rtrim,
+}
+
+impl Collation {
+    /// Compares two strings following the rules of the collating sequence
+    pub(crate) fn compare_strings(&self, lhs: &str, rhs: &str) -> Ordering {
+        match self {
+            Self::Binary => lhs.cmp(rhs),
+            Self::NoCase => lhs
+                .bytes()
+                .map(|b| b.to_ascii_lowercase())
+                .cmp(rhs.bytes().map(|b| b.to_ascii_lowercase())),
+            Self::Rtrim => lhs.trim_end().cmp(rhs.trim_end()),
+        }
+    }
+}
+
+impl Display for Collation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Binary => write!(f, "BINARY"),
+            Self::NoCase => write!(f, "NOCASE"),
+            Self::Rtrim => write!(f, "RTRIM"),
+        }
+    }
+}
+
 fn float_to_string<S>(float: &f64, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -95,7 +333,7 @@ impl Display for SimValue {
         match &self.0 {
             types::Value::Null => write!(f, "NULL"),
             types::Value::Integer(i) => write!(f, "{}", i),
-            types::Value::Float(fl) => write!(f, "{}", fl),
+            types::Value::Float(fl) => write!(f, "{}", float_literal(*fl)),
             value @ types::Value::Text(..) => write!(f, "'{}'", value),
             types::Value::Blob(b) => write!(f, "{}", to_sqlite_blob(b)),
         }
@@ -113,11 +351,51 @@ impl SimValue {
     // TODO: support more predicates
     /// Returns a Result of a Binary Operation
     ///
-    /// TODO: forget collations for now
     /// TODO: have the [ast::Operator::Equals], [ast::Operator::NotEquals], [ast::Operator::Greater],
     /// [ast::Operator::GreaterEquals], [ast::Operator::Less], [ast::Operator::LessEquals] function to be extracted
     /// into its functions in turso_core so that it can be used here
     pub fn binary_compare(&self, other: &Self, operator: ast::Operator) -> SimValue {
+        self.binary_compare_collated(other, operator, Collation::default())
+    }
+
+    /// Same as [SimValue::binary_compare], but comparisons between two text values
+    /// follow the provided collating sequence
+    pub fn binary_compare_collated(
+        &self,
+        other: &Self,
+        operator: ast::Operator,
+        collation: Collation,
+    ) -> SimValue {
+        if let (types::Value::Text(lhs), types::Value::Text(rhs)) = (&self.0, &other.0) {
+            let ordering = collation.compare_strings(lhs.as_str(), rhs.as_str());
+            let res = match operator {
+                ast::Operator::Equals => Some(ordering.is_eq()),
+                ast::Operator::NotEquals => Some(ordering.is_ne()),
+                ast::Operator::Greater => Some(ordering.is_gt()),
+                ast::Operator::GreaterEquals => Some(ordering.is_ge()),
+                ast::Operator::Less => Some(ordering.is_lt()),
+                ast::Operator::LessEquals => Some(ordering.is_le()),
+                _ => None,
+            };
+            if let Some(res) = res {
+                return res.into();
+            }
+        }
+        let is_null = matches!(self.0, types::Value::Null) || matches!(other.0, types::Value::Null);
+        match operator {
+            // A comparison with NULL is NULL, only `IS` and `IS NOT` can test for it
+            ast::Operator::Equals
+            | ast::Operator::NotEquals
+            | ast::Operator::Greater
+            | ast::Operator::GreaterEquals
+            | ast::Operator::Less
+            | ast::Operator::LessEquals
+                if is_null =>
+            {
+                return SimValue(types::Value::Null);
+            }
+            _ => {}
+        }
         match operator {
             ast::Operator::Add => self.0.exec_add(&other.0).into(),
             ast::Operator::And => self.0.exec_and(&other.0).into(),
@@ -131,9 +409,8 @@ impl SimValue {
             ast::Operator::Divide => self.0.exec_divide(&other.0).into(),
             ast::Operator::Greater => (self > other).into(),
             ast::Operator::GreaterEquals => (self >= other).into(),
-            // TODO: Should attempt to extract `Is` and `IsNot` handling in a function in Core
-            ast::Operator::Is => todo!(),
-            ast::Operator::IsNot => todo!(),
+            ast::Operator::Is => self.is(other, collation).into(),
+            ast::Operator::IsNot => (!self.is(other, collation)).into(),
             ast::Operator::LeftShift => self.0.exec_shift_left(&other.0).into(),
             ast::Operator::Less => (self < other).into(),
             ast::Operator::LessEquals => (self <= other).into(),
@@ -146,21 +423,48 @@ impl SimValue {
         }
     }
 
+    /// `IS`, which is `=` except that NULL is the same as NULL and different from anything else
+    fn is(&self, other: &Self, collation: Collation) -> bool {
+        match (&self.0, &other.0) {
+            (types::Value::Null, types::Value::Null) => true,
+            (types::Value::Null, _) | (_, types::Value::Null) => false,
+            _ => self
+                .binary_compare_collated(other, ast::Operator::Equals, collation)
+                .as_bool(),
+        }
+    }
+
     // TODO: support more operators. Copy the implementation for exec_glob
-    pub fn like_compare(&self, other: &Self, operator: ast::LikeOperator) -> bool {
+    pub fn like_compare(
+        &self,
+        other: &Self,
+        operator: ast::LikeOperator,
+        escape: Option<&Self>,
+    ) -> bool {
         match operator {
             ast::LikeOperator::Glob => todo!(),
             ast::LikeOperator::Like => {
-                // TODO: support ESCAPE `expr` option in AST
                 // TODO: regex cache
-                types::Value::exec_like(
-                    None,
-                    other.0.to_string().as_str(),
-                    self.0.to_string().as_str(),
-                )
+                let escape = escape.map(|escape| escape.0.to_string());
+                let mut escape_chars = escape.as_deref().unwrap_or_default().chars();
+                match (escape_chars.next(), escape_chars.next()) {
+                    (Some(escape), None) => types::Value::exec_like_with_escape(
+                        other.0.to_string().as_str(),
+                        self.0.to_string().as_str(),
+                        escape,
+                    ),
+                    _ => types::Value::exec_like(
+                        None,
+                        other.0.to_string().as_str(),
+                        self.0.to_string().as_str(),
+                    ),
+                }
             }
             ast::LikeOperator::Match => todo!(),
-            ast::LikeOperator::Regexp => todo!(),
+            // `X REGEXP Y` matches X against the regular expression Y, an invalid pattern is an
+            // error in the database so it is never generated
+            ast::LikeOperator::Regexp => regex::Regex::new(other.0.to_string().as_str())
+                .is_ok_and(|re| re.is_match(self.0.to_string().as_str())),
         }
     }
 
@@ -273,13 +577,23 @@ impl From<&SimValue> for ast::Literal {
         match &value.0 {
             types::Value::Null => Self::Null,
             types::Value::Integer(i) => Self::Numeric(i.to_string()),
-            types::Value::Float(f) => Self::Numeric(f.to_string()),
+            types::Value::Float(f) => Self::Numeric(float_literal(*f)),
             text @ types::Value::Text(..) => Self::String(escape_singlequotes(&text.to_string())),
             types::Value::Blob(blob) => Self::Blob(hex::encode(blob)),
         }
     }
 }
 
+/// Formats a real so that it is read back as the same real: always with a decimal point or an
+/// exponent so it is not taken for an integer, and with an overflowing exponent for the infinities
+fn float_literal(f: f64) -> String {
+    if f.is_infinite() {
+        if f > 0.0 { "1e999" } else { "-1e999" }.to_string()
+    } else {
+        format!("{f:?}")
+    }
+}
+
 impl From<bool> for SimValue {
     fn from(value: bool) -> Self {
         if value {
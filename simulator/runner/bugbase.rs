@@ -361,6 +361,8 @@ impl BugBase {
                         "differential"
                     } else if run.cli_options.doublecheck {
                         "doublecheck"
+                    } else if run.cli_options.assert_determinism {
+                        "determinism"
                     } else {
                         "default"
                     }
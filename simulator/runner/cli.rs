@@ -1,5 +1,9 @@
 use clap::{command, Parser};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::model::schema::Schema;
 
 #[derive(Parser, Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord)]
 #[command(name = "limbo-simulator")]
@@ -34,8 +38,30 @@ pub struct SimulatorCLI {
         default_value_t = 60 * 60 // default to 1 hour
     )]
     pub maximum_time: usize,
+    #[clap(
+        long,
+        help = "keep generating and executing interactions until the time budget expires (e.g. 60s, 5m, 1h)",
+        value_parser = parse_duration
+    )]
+    pub duration: Option<Duration>,
+    #[clap(
+        long,
+        help = "fail the run as a hang when a single query takes longer than this (e.g. 30s, 5m)",
+        value_parser = parse_duration
+    )]
+    pub interaction_timeout: Option<Duration>,
     #[clap(short = 'l', long, help = "load plan from the bug base")]
     pub load: Option<String>,
+    #[clap(
+        long,
+        help = "run a mutation of the plan in the given JSON file instead of generating a new one"
+    )]
+    pub mutate_from: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "create the tables and indexes of the given SQL file, and only generate DML and queries against them"
+    )]
+    pub tables_from_file: Option<PathBuf>,
     #[clap(
         short = 'w',
         long,
@@ -44,6 +70,44 @@ pub struct SimulatorCLI {
     pub watch: bool,
     #[clap(long, help = "run differential testing between sqlite and Limbo")]
     pub differential: bool,
+    #[clap(
+        long,
+        help = "run every query of the plan on SQLite too and fail when the results differ, requires the compare-sqlite feature"
+    )]
+    pub compare_with_sqlite: bool,
+    #[clap(
+        long,
+        help = "run the plan against two fresh databases and assert every query returns the same results"
+    )]
+    pub assert_determinism: bool,
+    #[clap(
+        long,
+        help = "execute the plan without checking assertions against the shadow model, only failing on crashes, hangs and corruption"
+    )]
+    pub no_shadow_check: bool,
+    #[clap(
+        long,
+        help = "stop the simulation at the first failing assertion (default)",
+        conflicts_with = "collect_all"
+    )]
+    pub fail_fast: bool,
+    #[clap(
+        long,
+        help = "keep executing the plan after a failing assertion, and report every failure grouped by assertion message"
+    )]
+    pub collect_all: bool,
+    #[clap(
+        long,
+        value_enum,
+        help = "print a machine-readable summary of the run in the given format at the end"
+    )]
+    pub report: Option<ReportFormat>,
+    #[clap(
+        long,
+        requires = "report",
+        help = "write the summary requested with --report to this file instead of stdout"
+    )]
+    pub report_file: Option<PathBuf>,
     #[clap(subcommand)]
     pub subcommand: Option<SimulatorCommand>,
     #[clap(long, help = "disable BugBase", default_value_t = false)]
@@ -86,14 +150,170 @@ pub struct SimulatorCLI {
     pub disable_fsync_no_wait: bool,
     #[clap(long, help = "disable FaultyQuery Property", default_value_t = true)]
     pub disable_faulty_query: bool,
+    #[clap(
+        long,
+        help = "disable Foreign-Key-Enforced Property",
+        default_value_t = false
+    )]
+    pub disable_foreign_key_enforced: bool,
+    #[clap(
+        long,
+        help = "disable ALTER TABLE ... DROP COLUMN queries",
+        default_value_t = false
+    )]
+    pub disable_alter_table: bool,
+    #[clap(
+        long,
+        help = "disable DELETE ... ORDER BY ... LIMIT queries",
+        default_value_t = false
+    )]
+    pub disable_delete_limit: bool,
+    #[clap(
+        long,
+        help = "disable Reindex-Preserves-Results Property",
+        default_value_t = false
+    )]
+    pub disable_reindex_preserves_results: bool,
+    #[clap(
+        long,
+        help = "disable Unique-Allows-Multiple-Nulls Property",
+        default_value_t = false
+    )]
+    pub disable_unique_allows_multiple_nulls: bool,
+    #[clap(
+        long,
+        help = "disable Savepoint-Rollback Property",
+        default_value_t = false
+    )]
+    pub disable_savepoint_rollback: bool,
+    #[clap(
+        long,
+        help = "disable Select-After-Reopen-Stable Property",
+        default_value_t = false
+    )]
+    pub disable_select_after_reopen_stable: bool,
+    #[clap(
+        long,
+        help = "disable Select-After-Checkpoint-Stable Property",
+        default_value_t = false
+    )]
+    pub disable_select_after_checkpoint_stable: bool,
+    #[clap(
+        long,
+        help = "disable Vacuum-Preserves-Data Property",
+        default_value_t = false
+    )]
+    pub disable_vacuum_preserves_data: bool,
+    #[clap(
+        long,
+        help = "disable Default-Applied Property",
+        default_value_t = false
+    )]
+    pub disable_default_applied: bool,
+    #[clap(
+        long,
+        help = "disable Limit-Offset-Pagination Property",
+        default_value_t = false
+    )]
+    pub disable_limit_offset_pagination: bool,
+    #[clap(
+        long,
+        help = "disable Group-Having-Consistency Property",
+        default_value_t = false
+    )]
+    pub disable_group_having_consistency: bool,
+    #[clap(
+        long,
+        help = "disable Prepared-Rebind-Consistency Property",
+        default_value_t = false
+    )]
+    pub disable_prepared_rebind_consistency: bool,
+    #[clap(
+        long,
+        help = "disable Is-Null-Partition Property",
+        default_value_t = false
+    )]
+    pub disable_is_null_partition: bool,
+    #[clap(
+        long,
+        help = "disable Distinct-Agg-Consistency Property",
+        default_value_t = false
+    )]
+    pub disable_distinct_agg_consistency: bool,
+    #[clap(
+        long,
+        help = "disable Between-Consistency Property",
+        default_value_t = false
+    )]
+    pub disable_between_consistency: bool,
+    #[clap(
+        long,
+        help = "disable Create-Index-Consistency Property",
+        default_value_t = false
+    )]
+    pub disable_create_index_consistency: bool,
+    #[clap(
+        long,
+        help = "disable Autoincrement-No-Reuse Property",
+        default_value_t = false
+    )]
+    pub disable_autoincrement_no_reuse: bool,
+    #[clap(
+        long,
+        help = "disable Schema-Round-Trip Property",
+        default_value_t = false
+    )]
+    pub disable_schema_round_trip: bool,
+    #[clap(
+        long,
+        help = "open the database with experimental index support",
+        default_value_t = false
+    )]
+    pub experimental_indexes: bool,
     #[clap(long, help = "disable Reopen-Database fault", default_value_t = false)]
     pub disable_reopen_database: bool,
+    #[clap(long, help = "disable Checkpoint fault", default_value_t = false)]
+    pub disable_checkpoint: bool,
+    #[clap(long, help = "disable Slow-IO fault", default_value_t = false)]
+    pub disable_slow_io: bool,
     #[clap(
         long = "latency_prob",
         help = "added IO latency probability",
         default_value_t = 0
     )]
     pub latency_probability: usize,
+    #[clap(
+        long = "alias_prob",
+        help = "probability of generated SELECTs referring to tables and result columns through aliases",
+        default_value_t = 25
+    )]
+    pub alias_probability: usize,
+    #[clap(
+        long,
+        help = "maximum number of ORDER BY keys in generated SELECTs, 0 to never order them",
+        default_value_t = 3
+    )]
+    pub max_order_by_keys: usize,
+    #[clap(
+        long,
+        help = "maximum number of tables in the database, once reached the plan only runs queries on the existing ones",
+        default_value_t = 128
+    )]
+    pub max_tables: usize,
+    #[clap(
+        long,
+        help = "maximum number of columns of a created table",
+        default_value_t = 10
+    )]
+    pub max_columns: usize,
+}
+
+#[derive(
+    clap::ValueEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord,
+)]
+pub enum ReportFormat {
+    /// A single JSON object, see `runner::report` for the schema
+    Json,
 }
 
 #[derive(Parser, Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord)]
@@ -126,6 +346,19 @@ pub enum SimulatorCommand {
         )]
         filter: String,
     },
+    #[clap(about = "minimize a failing plan offline, replaying it from its plan file")]
+    Shrink {
+        #[clap(
+            help = "plan file written by the failing run, next to which its JSON counterpart must be"
+        )]
+        plan: PathBuf,
+        #[clap(
+            short = 'o',
+            long,
+            help = "where to write the shrunk plan, defaults to shrunk.sql next to the plan file"
+        )]
+        output: Option<PathBuf>,
+    },
 }
 
 impl SimulatorCLI {
@@ -151,6 +384,57 @@ impl SimulatorCLI {
             anyhow::bail!("Cannot set seed and load plan at the same time");
         }
 
+        if self.mutate_from.is_some() && self.load.is_some() {
+            anyhow::bail!("Cannot mutate a plan and load plan at the same time");
+        }
+
+        if self.assert_determinism && (self.differential || self.doublecheck) {
+            anyhow::bail!(
+                "--assert-determinism cannot be combined with --differential or --doublecheck"
+            );
+        }
+
+        if self.compare_with_sqlite && !cfg!(feature = "compare-sqlite") {
+            anyhow::bail!(
+                "--compare-with-sqlite requires building the simulator with the compare-sqlite feature"
+            );
+        }
+
+        if self.compare_with_sqlite && (self.differential || self.assert_determinism) {
+            anyhow::bail!(
+                "--compare-with-sqlite cannot be combined with --differential or --assert-determinism"
+            );
+        }
+
+        if self.no_shadow_check && (self.differential || self.assert_determinism) {
+            anyhow::bail!(
+                "--no-shadow-check cannot be combined with --differential or --assert-determinism"
+            );
+        }
+
+        if self.collect_all && (self.differential || self.assert_determinism || self.watch) {
+            anyhow::bail!(
+                "--collect-all cannot be combined with --differential, --assert-determinism or --watch"
+            );
+        }
+
+        if self.duration.is_some() && (self.differential || self.assert_determinism) {
+            anyhow::bail!(
+                "--duration cannot be combined with --differential or --assert-determinism"
+            );
+        }
+
+        if self.duration.is_some() && (self.doublecheck || self.watch) {
+            anyhow::bail!("--duration cannot be combined with --doublecheck or --watch");
+        }
+
+        if self
+            .interaction_timeout
+            .is_some_and(|timeout| timeout.is_zero())
+        {
+            anyhow::bail!("interaction timeout must be greater than zero");
+        }
+
         if self.latency_probability > 100 {
             anyhow::bail!(
                 "latency probability must be a number between 0 and 100. Got `{}`",
@@ -158,6 +442,53 @@ impl SimulatorCLI {
             );
         }
 
+        if self.max_tables < 1 {
+            anyhow::bail!("maximum number of tables must be at least 1");
+        }
+
+        if self.max_columns < 1 {
+            anyhow::bail!("maximum number of columns must be at least 1");
+        }
+
+        if let Some(path) = &self.tables_from_file {
+            let schema = Schema::from_file(path)?;
+            if schema.has_unique_keys() && !self.experimental_indexes {
+                anyhow::bail!(
+                    "the UNIQUE and PRIMARY KEY constraints of --tables-from-file require --experimental-indexes"
+                );
+            }
+        }
+
+        if self.alias_probability > 100 {
+            anyhow::bail!(
+                "alias probability must be a number between 0 and 100. Got `{}`",
+                self.alias_probability
+            );
+        }
+
         Ok(())
     }
 }
+
+/// Parses a duration such as `90`, `60s`, `5m` or `1h`. A bare number is read as seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (value, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => s.split_at(idx),
+        None => (s, "s"),
+    };
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration `{s}`"))?;
+    let seconds = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid duration unit `{unit}`, expected one of s, m, h"
+            ))
+        }
+    };
+    Ok(Duration::from_secs(seconds))
+}
@@ -0,0 +1,133 @@
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+
+use turso_core::{LimboError, Result};
+
+use crate::{generation::plan::ResultSet, model::query::Query};
+
+use super::differential::{
+    execute_query_rusqlite, limbo_error_from_rusqlite, open_rusqlite_connection,
+};
+
+/// Real SQLite used as an oracle by `--compare-with-sqlite`. Every query the plan runs on Limbo
+/// also runs on a SQLite database created alongside it, through the SQLite connection with the
+/// same index, and the two result sets must match.
+pub(crate) struct SqliteOracle {
+    path: PathBuf,
+    page_size: usize,
+    /// Opened on the first query of each connection, and closed when Limbo's is
+    connections: Vec<Option<rusqlite::Connection>>,
+}
+
+impl SqliteOracle {
+    /// Creates the SQLite database next to the Limbo one at `db_path`, replacing any previous one
+    pub(crate) fn new(db_path: &Path, page_size: usize, connections: usize) -> Self {
+        let path = db_path.with_extension("sqlite.db");
+        for path in [path.clone(), path.with_extension("db-wal")] {
+            if path.exists() {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+        Self {
+            path,
+            page_size,
+            connections: (0..connections).map(|_| None).collect(),
+        }
+    }
+
+    /// The oracle of the same database, without open connections
+    pub(crate) fn clone_without_connections(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            page_size: self.page_size,
+            connections: self.connections.iter().map(|_| None).collect(),
+        }
+    }
+
+    /// Closes the SQLite connection, rolling back its open transaction like Limbo does
+    pub(crate) fn disconnect(&mut self, connection_index: usize) {
+        self.connections[connection_index] = None;
+    }
+
+    pub(crate) fn disconnect_all(&mut self) {
+        self.connections.iter_mut().for_each(|conn| *conn = None);
+    }
+
+    /// Runs `query` on SQLite and fails if its results differ from the ones Limbo returned
+    pub(crate) fn compare(
+        &mut self,
+        connection_index: usize,
+        query: &Query,
+        limbo_results: &ResultSet,
+    ) -> Result<()> {
+        let (path, page_size) = (&self.path, self.page_size);
+        let conn = self.connections[connection_index].get_or_insert_with(|| {
+            let conn = open_rusqlite_connection(path);
+            // Limbo runs in WAL mode, where readers do not block writers
+            conn.execute_batch(&format!(
+                "PRAGMA page_size = {page_size}; PRAGMA journal_mode = WAL;"
+            ))
+            .unwrap();
+            conn
+        });
+        let sqlite_results = execute_query_rusqlite(conn, query).map_err(limbo_error_from_rusqlite);
+
+        match (limbo_results, &sqlite_results) {
+            (Ok(limbo_rows), Ok(sqlite_rows)) => {
+                if !is_comparable(query) {
+                    return Ok(());
+                }
+                let (mut limbo_rows, mut sqlite_rows) = (limbo_rows.clone(), sqlite_rows.clone());
+                if !is_ordered(query) {
+                    for rows in [&mut limbo_rows, &mut sqlite_rows] {
+                        rows.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                    }
+                }
+                if limbo_rows == sqlite_rows {
+                    return Ok(());
+                }
+            }
+            (Err(limbo_err), Err(sqlite_err)) => {
+                tracing::warn!(
+                    "limbo and sqlite both fail on `{}`, requires manual check",
+                    query
+                );
+                tracing::warn!("limbo error {}", limbo_err);
+                tracing::warn!("sqlite error {}", sqlite_err);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        tracing::error!("limbo and sqlite results differ for `{}`", query);
+        tracing::error!("limbo {:?}", limbo_results);
+        tracing::error!("sqlite {:?}", sqlite_results);
+        Err(LimboError::InternalError(format!(
+            "limbo and sqlite results differ for `{}`\nlimbo: {:?}\nsqlite: {:?}",
+            query, limbo_results, sqlite_results
+        )))
+    }
+}
+
+/// Whether the query returns the rows in an order both databases must agree on
+fn is_ordered(query: &Query) -> bool {
+    match query {
+        Query::Select(select) => !select.order_by.is_empty(),
+        // Always ordered by rowid
+        Query::Prepared(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether the query returns the same set of rows on both databases. A LIMIT or OFFSET without
+/// ORDER BY picks rows that depend on the plan of the query, so only the statement runs.
+fn is_comparable(query: &Query) -> bool {
+    match query {
+        Query::Select(select) => {
+            !select.order_by.is_empty() || (select.limit.is_none() && select.offset.is_none())
+        }
+        _ => true,
+    }
+}
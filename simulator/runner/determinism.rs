@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+
+use turso_core::LimboError;
+
+use crate::{
+    generation::plan::{Interaction, InteractionPlanState, ResultSet},
+    runner::execution::ExecutionContinuation,
+    InteractionPlan,
+};
+
+use super::{
+    env::{SimConnection, SimulatorEnv},
+    execution::{execute_interaction, execute_plans_in_lockstep, Execution, ExecutionResult},
+};
+
+/// Runs the same plan against two fresh databases created from the same seed,
+/// and asserts that every query produces the same result set on both of them.
+pub(crate) fn run_simulation(
+    env: Arc<Mutex<SimulatorEnv>>,
+    second_env: Arc<Mutex<SimulatorEnv>>,
+    plans: &mut [InteractionPlan],
+    last_execution: Arc<Mutex<Execution>>,
+) -> ExecutionResult {
+    tracing::info!("Executing database interaction plan twice...");
+
+    let new_states = || {
+        plans
+            .iter()
+            .map(|_| InteractionPlanState {
+                stack: vec![],
+                interaction_pointer: 0,
+                secondary_pointer: 0,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut states = new_states();
+    let mut second_states = new_states();
+
+    let result = execute_plans(
+        env,
+        second_env,
+        plans,
+        &mut states,
+        &mut second_states,
+        last_execution,
+    );
+
+    tracing::info!("Simulation completed");
+
+    result
+}
+
+pub(crate) fn execute_plans(
+    env: Arc<Mutex<SimulatorEnv>>,
+    second_env: Arc<Mutex<SimulatorEnv>>,
+    plans: &mut [InteractionPlan],
+    states: &mut [InteractionPlanState],
+    second_states: &mut [InteractionPlanState],
+    last_execution: Arc<Mutex<Execution>>,
+) -> ExecutionResult {
+    execute_plans_in_lockstep(
+        env,
+        second_env,
+        plans,
+        states,
+        second_states,
+        last_execution,
+        |env, second_env, connection_index| {
+            env.connections[connection_index] =
+                SimConnection::LimboConnection(env.db.connect().unwrap());
+            second_env.connections[connection_index] =
+                SimConnection::LimboConnection(second_env.db.connect().unwrap());
+        },
+        execute_interactions,
+    )
+}
+
+/// Executes the interaction against both databases and checks that they agree.
+fn execute_interactions(
+    env: &mut SimulatorEnv,
+    second_env: &mut SimulatorEnv,
+    connection_index: usize,
+    interaction: &Interaction,
+    state: &mut InteractionPlanState,
+    second_state: &mut InteractionPlanState,
+) -> turso_core::Result<ExecutionContinuation> {
+    let next_execution = execute_interaction(env, connection_index, interaction, &mut state.stack)?;
+    let second_next_execution = execute_interaction(
+        second_env,
+        connection_index,
+        interaction,
+        &mut second_state.stack,
+    )?;
+    interaction.shadow(env);
+    interaction.shadow(second_env);
+
+    if next_execution != second_next_execution {
+        return Err(LimboError::InternalError(format!(
+            "interaction {}.{} diverged: {:?} vs {:?}",
+            state.interaction_pointer,
+            state.secondary_pointer,
+            next_execution,
+            second_next_execution
+        )));
+    }
+
+    if produces_result_set(interaction) {
+        compare_result_sets(
+            state.interaction_pointer,
+            state.secondary_pointer,
+            state.stack.last(),
+            second_state.stack.last(),
+        )?;
+    }
+
+    Ok(next_execution)
+}
+
+fn produces_result_set(interaction: &Interaction) -> bool {
+    matches!(
+        interaction,
+        Interaction::Query(_) | Interaction::FsyncQuery(_) | Interaction::FaultyQuery(_)
+    )
+}
+
+fn compare_result_sets(
+    interaction_index: usize,
+    secondary_index: usize,
+    first: Option<&ResultSet>,
+    second: Option<&ResultSet>,
+) -> turso_core::Result<()> {
+    let equal = match (first, second) {
+        (Some(Ok(first)), Some(Ok(second))) => first == second,
+        (Some(Err(first)), Some(Err(second))) => first.to_string() == second.to_string(),
+        (None, None) => true,
+        _ => false,
+    };
+    if equal {
+        return Ok(());
+    }
+
+    tracing::error!(
+        "nondeterministic result at interaction {}.{}",
+        interaction_index,
+        secondary_index
+    );
+    tracing::error!("first run: {:?}", first);
+    tracing::error!("second run: {:?}", second);
+    Err(LimboError::InternalError(format!(
+        "nondeterministic result at interaction {}.{}: {:?} vs {:?}",
+        interaction_index, secondary_index, first, second
+    )))
+}
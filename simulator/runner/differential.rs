@@ -1,12 +1,14 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use rusqlite::functions::FunctionFlags;
 
 use turso_core::Value;
 
 use crate::{
-    generation::{
-        pick_index,
-        plan::{Interaction, InteractionPlanState, ResultSet},
-    },
+    generation::plan::{Interaction, InteractionPlanState, ResultSet},
     model::{query::Query, table::SimValue},
     runner::execution::ExecutionContinuation,
     InteractionPlan,
@@ -14,9 +16,32 @@ use crate::{
 
 use super::{
     env::{SimConnection, SimulatorEnv},
-    execution::{execute_interaction, Execution, ExecutionHistory, ExecutionResult},
+    execution::{execute_interaction, execute_plans_in_lockstep, Execution, ExecutionResult},
 };
 
+/// Opens the SQLite connection used as the reference. SQLite leaves `regexp` to the application,
+/// so it is registered here with the same regex syntax Limbo uses for the `REGEXP` operator.
+pub(crate) fn open_rusqlite_connection(path: &Path) -> rusqlite::Connection {
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let (Some(pattern), Some(text)) =
+                (ctx.get::<Option<String>>(0)?, ctx.get::<Option<String>>(1)?)
+            else {
+                return Ok(None);
+            };
+            let re = regex::Regex::new(&pattern)
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+            Ok(Some(re.is_match(&text)))
+        },
+    )
+    .unwrap();
+    conn
+}
+
 pub(crate) fn run_simulation(
     env: Arc<Mutex<SimulatorEnv>>,
     rusqlite_env: Arc<Mutex<SimulatorEnv>>,
@@ -59,7 +84,34 @@ pub(crate) fn run_simulation(
     result
 }
 
-fn execute_query_rusqlite(
+/// Converts an error returned by SQLite into the one Limbo returns in the same case, so that the
+/// properties asserting on the kind of a constraint violation hold against both.
+pub(crate) fn limbo_error_from_rusqlite(err: rusqlite::Error) -> turso_core::LimboError {
+    use rusqlite::ffi;
+    use turso_core::LimboError;
+
+    if let rusqlite::Error::SqliteFailure(failure, message) = &err {
+        // SQLite reports the constraint as e.g. "NOT NULL constraint failed: t.a"
+        let description = || {
+            message
+                .as_deref()
+                .and_then(|message| message.split_once(": "))
+                .map_or_else(String::new, |(_, description)| description.to_string())
+        };
+        match failure.extended_code {
+            ffi::SQLITE_CONSTRAINT_NOTNULL => return LimboError::NotNullConstraint(description()),
+            ffi::SQLITE_CONSTRAINT_CHECK => return LimboError::CheckConstraint(description()),
+            ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+                return LimboError::UniqueConstraint(description())
+            }
+            ffi::SQLITE_CONSTRAINT_FOREIGNKEY => return LimboError::ForeignKeyConstraint,
+            _ => {}
+        }
+    }
+    LimboError::InternalError(format!("error executing query: {}", err))
+}
+
+pub(crate) fn execute_query_rusqlite(
     connection: &rusqlite::Connection,
     query: &Query,
 ) -> rusqlite::Result<Vec<Vec<SimValue>>> {
@@ -68,27 +120,25 @@ fn execute_query_rusqlite(
             connection.execute(create.to_string().as_str(), ())?;
             Ok(vec![])
         }
-        Query::Select(select) => {
-            let mut stmt = connection.prepare(select.to_string().as_str())?;
+        Query::Select(select) => query_rows_rusqlite(connection, &select.to_string()),
+        Query::Prepared(prepared) => {
+            let mut stmt = connection.prepare(prepared.select(None).to_string().as_str())?;
             let columns = stmt.column_count();
-            let rows = stmt.query_map([], |row| {
-                let mut values = vec![];
-                for i in 0..columns {
-                    let value = row.get_unwrap(i);
-                    let value = match value {
-                        rusqlite::types::Value::Null => Value::Null,
-                        rusqlite::types::Value::Integer(i) => Value::Integer(i),
-                        rusqlite::types::Value::Real(f) => Value::Float(f),
-                        rusqlite::types::Value::Text(s) => Value::build_text(s),
-                        rusqlite::types::Value::Blob(b) => Value::Blob(b),
-                    };
-                    values.push(SimValue(value));
-                }
-                Ok(values)
-            })?;
             let mut result = vec![];
-            for row in rows {
-                result.push(row?);
+            for values in &prepared.bindings {
+                let params = values.iter().map(|value| match &value.0 {
+                    Value::Null => rusqlite::types::Value::Null,
+                    Value::Integer(i) => rusqlite::types::Value::Integer(*i),
+                    Value::Float(f) => rusqlite::types::Value::Real(*f),
+                    Value::Text(s) => rusqlite::types::Value::Text(s.as_str().to_string()),
+                    Value::Blob(b) => rusqlite::types::Value::Blob(b.clone()),
+                });
+                let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+                    rusqlite_row_values(row, columns)
+                })?;
+                for row in rows {
+                    result.push(row?);
+                }
             }
             Ok(result)
         }
@@ -112,9 +162,57 @@ fn execute_query_rusqlite(
             connection.execute(create_index.to_string().as_str(), ())?;
             Ok(vec![])
         }
+        // Some pragmas return rows, e.g. `PRAGMA table_info`
+        Query::Pragma(pragma) => query_rows_rusqlite(connection, &pragma.to_string()),
+        Query::Alter(alter) => {
+            connection.execute(alter.to_string().as_str(), ())?;
+            Ok(vec![])
+        }
+        Query::Reindex(reindex) => {
+            connection.execute(reindex.to_string().as_str(), ())?;
+            Ok(vec![])
+        }
+        Query::Savepoint(savepoint) => {
+            connection.execute(savepoint.to_string().as_str(), ())?;
+            Ok(vec![])
+        }
+        Query::Vacuum(vacuum) => {
+            connection.execute(vacuum.to_string().as_str(), ())?;
+            Ok(vec![])
+        }
     }
 }
 
+fn query_rows_rusqlite(
+    connection: &rusqlite::Connection,
+    sql: &str,
+) -> rusqlite::Result<Vec<Vec<SimValue>>> {
+    let mut stmt = connection.prepare(sql)?;
+    let columns = stmt.column_count();
+    let rows = stmt.query_map([], |row| rusqlite_row_values(row, columns))?;
+    let mut result = vec![];
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+fn rusqlite_row_values(row: &rusqlite::Row, columns: usize) -> rusqlite::Result<Vec<SimValue>> {
+    let mut values = vec![];
+    for i in 0..columns {
+        let value = row.get_unwrap(i);
+        let value = match value {
+            rusqlite::types::Value::Null => Value::Null,
+            rusqlite::types::Value::Integer(i) => Value::Integer(i),
+            rusqlite::types::Value::Real(f) => Value::Float(f),
+            rusqlite::types::Value::Text(s) => Value::build_text(s),
+            rusqlite::types::Value::Blob(b) => Value::Blob(b),
+        };
+        values.push(SimValue(value));
+    }
+    Ok(values)
+}
+
 pub(crate) fn execute_plans(
     env: Arc<Mutex<SimulatorEnv>>,
     rusqlite_env: Arc<Mutex<SimulatorEnv>>,
@@ -124,191 +222,112 @@ pub(crate) fn execute_plans(
     rusqlite_states: &mut [InteractionPlanState],
     last_execution: Arc<Mutex<Execution>>,
 ) -> ExecutionResult {
-    let mut history = ExecutionHistory::new();
-    let now = std::time::Instant::now();
-
-    let mut env = env.lock().unwrap();
-    let mut rusqlite_env = rusqlite_env.lock().unwrap();
-
-    for _tick in 0..env.opts.ticks {
-        // Pick the connection to interact with
-        let connection_index = pick_index(env.connections.len(), &mut env.rng);
-        let state = &mut states[connection_index];
-
-        history.history.push(Execution::new(
-            connection_index,
-            state.interaction_pointer,
-            state.secondary_pointer,
-        ));
-        let mut last_execution = last_execution.lock().unwrap();
-        last_execution.connection_index = connection_index;
-        last_execution.interaction_index = state.interaction_pointer;
-        last_execution.secondary_index = state.secondary_pointer;
-        // Execute the interaction for the selected connection
-        match execute_plan(
-            &mut env,
-            &mut rusqlite_env,
-            rusqlite_conn,
-            connection_index,
-            plans,
-            states,
-            rusqlite_states,
-        ) {
-            Ok(_) => {}
-            Err(err) => {
-                return ExecutionResult::new(history, Some(err));
-            }
-        }
-        // Check if the maximum time for the simulation has been reached
-        if now.elapsed().as_secs() >= env.opts.max_time_simulation as u64 {
-            return ExecutionResult::new(
-                history,
-                Some(turso_core::LimboError::InternalError(
-                    "maximum time for simulation reached".into(),
-                )),
-            );
-        }
-    }
-
-    ExecutionResult::new(history, None)
+    execute_plans_in_lockstep(
+        env,
+        rusqlite_env,
+        plans,
+        states,
+        rusqlite_states,
+        last_execution,
+        |env, rusqlite_env, connection_index| {
+            env.connections[connection_index] =
+                SimConnection::LimboConnection(env.db.connect().unwrap());
+            rusqlite_env.connections[connection_index] =
+                SimConnection::SQLiteConnection(rusqlite_conn());
+        },
+        execute_interactions,
+    )
 }
 
-fn execute_plan(
+/// Executes the interaction against Limbo and SQLite and checks that they agree.
+fn execute_interactions(
     env: &mut SimulatorEnv,
     rusqlite_env: &mut SimulatorEnv,
-    rusqlite_conn: &dyn Fn() -> rusqlite::Connection,
     connection_index: usize,
-    plans: &mut [InteractionPlan],
-    states: &mut [InteractionPlanState],
-    rusqlite_states: &mut [InteractionPlanState],
-) -> turso_core::Result<()> {
-    let connection = &env.connections[connection_index];
-    let rusqlite_connection = &rusqlite_env.connections[connection_index];
-    let plan = &mut plans[connection_index];
-    let state = &mut states[connection_index];
-    let rusqlite_state = &mut rusqlite_states[connection_index];
-    if state.interaction_pointer >= plan.plan.len() {
-        return Ok(());
-    }
-
-    let interaction = &plan.plan[state.interaction_pointer].interactions()[state.secondary_pointer];
-
-    match (connection, rusqlite_connection) {
-        (SimConnection::Disconnected, SimConnection::Disconnected) => {
-            tracing::debug!("connecting {}", connection_index);
-            env.connections[connection_index] =
-                SimConnection::LimboConnection(env.db.connect().unwrap());
-            rusqlite_env.connections[connection_index] =
-                SimConnection::SQLiteConnection(rusqlite_conn());
-        }
-        (SimConnection::LimboConnection(_), SimConnection::SQLiteConnection(_)) => {
-            let limbo_result =
-                execute_interaction(env, connection_index, interaction, &mut state.stack);
-            let ruqlite_result = execute_interaction_rusqlite(
-                rusqlite_env,
-                connection_index,
-                interaction,
-                &mut rusqlite_state.stack,
-            );
-            match (limbo_result, ruqlite_result) {
-                (Ok(next_execution), Ok(next_execution_rusqlite)) => {
-                    if next_execution != next_execution_rusqlite {
-                        tracing::error!("limbo and rusqlite results do not match");
-                        return Err(turso_core::LimboError::InternalError(
-                            "limbo and rusqlite results do not match".into(),
-                        ));
-                    }
+    interaction: &Interaction,
+    state: &mut InteractionPlanState,
+    rusqlite_state: &mut InteractionPlanState,
+) -> turso_core::Result<ExecutionContinuation> {
+    let limbo_result = execute_interaction(env, connection_index, interaction, &mut state.stack);
+    let ruqlite_result = execute_interaction_rusqlite(
+        rusqlite_env,
+        connection_index,
+        interaction,
+        &mut rusqlite_state.stack,
+    );
+    match (limbo_result, ruqlite_result) {
+        (Ok(next_execution), Ok(next_execution_rusqlite)) => {
+            if next_execution != next_execution_rusqlite {
+                tracing::error!("limbo and rusqlite results do not match");
+                return Err(turso_core::LimboError::InternalError(
+                    "limbo and rusqlite results do not match".into(),
+                ));
+            }
 
-                    let limbo_values = state.stack.last();
-                    let rusqlite_values = rusqlite_state.stack.last();
+            let limbo_values = state.stack.last();
+            let rusqlite_values = rusqlite_state.stack.last();
+            match (limbo_values, rusqlite_values) {
+                (Some(limbo_values), Some(rusqlite_values)) => {
                     match (limbo_values, rusqlite_values) {
-                        (Some(limbo_values), Some(rusqlite_values)) => {
-                            match (limbo_values, rusqlite_values) {
-                                (Ok(limbo_values), Ok(rusqlite_values)) => {
-                                    if limbo_values != rusqlite_values {
-                                        tracing::error!("limbo and rusqlite results do not match");
-                                        return Err(turso_core::LimboError::InternalError(
-                                            "limbo and rusqlite results do not match".into(),
-                                        ));
-                                    }
-                                }
-                                (Err(limbo_err), Err(rusqlite_err)) => {
-                                    tracing::warn!(
-                                        "limbo and rusqlite both fail, requires manual check"
-                                    );
-                                    tracing::warn!("limbo error {}", limbo_err);
-                                    tracing::warn!("rusqlite error {}", rusqlite_err);
-                                }
-                                (Ok(limbo_result), Err(rusqlite_err)) => {
-                                    tracing::error!("limbo and rusqlite results do not match");
-                                    tracing::error!("limbo values {:?}", limbo_result);
-                                    tracing::error!("rusqlite error {}", rusqlite_err);
-                                    return Err(turso_core::LimboError::InternalError(
-                                        "limbo and rusqlite results do not match".into(),
-                                    ));
-                                }
-                                (Err(limbo_err), Ok(_)) => {
-                                    tracing::error!("limbo and rusqlite results do not match");
-                                    tracing::error!("limbo error {}", limbo_err);
-                                    return Err(turso_core::LimboError::InternalError(
-                                        "limbo and rusqlite results do not match".into(),
-                                    ));
-                                }
+                        (Ok(limbo_values), Ok(rusqlite_values)) => {
+                            if limbo_values != rusqlite_values {
+                                tracing::error!("limbo and rusqlite results do not match");
+                                return Err(turso_core::LimboError::InternalError(
+                                    "limbo and rusqlite results do not match".into(),
+                                ));
                             }
                         }
-                        (None, None) => {}
-                        _ => {
+                        (Err(limbo_err), Err(rusqlite_err)) => {
+                            tracing::warn!("limbo and rusqlite both fail, requires manual check");
+                            tracing::warn!("limbo error {}", limbo_err);
+                            tracing::warn!("rusqlite error {}", rusqlite_err);
+                        }
+                        (Ok(limbo_result), Err(rusqlite_err)) => {
                             tracing::error!("limbo and rusqlite results do not match");
+                            tracing::error!("limbo values {:?}", limbo_result);
+                            tracing::error!("rusqlite error {}", rusqlite_err);
                             return Err(turso_core::LimboError::InternalError(
                                 "limbo and rusqlite results do not match".into(),
                             ));
                         }
-                    }
-
-                    // Move to the next interaction or property
-                    match next_execution {
-                        ExecutionContinuation::NextInteraction => {
-                            if state.secondary_pointer + 1
-                                >= plan.plan[state.interaction_pointer].interactions().len()
-                            {
-                                // If we have reached the end of the interactions for this property, move to the next property
-                                state.interaction_pointer += 1;
-                                state.secondary_pointer = 0;
-                            } else {
-                                // Otherwise, move to the next interaction
-                                state.secondary_pointer += 1;
-                            }
-                        }
-                        ExecutionContinuation::NextProperty => {
-                            // Skip to the next property
-                            state.interaction_pointer += 1;
-                            state.secondary_pointer = 0;
+                        (Err(limbo_err), Ok(_)) => {
+                            tracing::error!("limbo and rusqlite results do not match");
+                            tracing::error!("limbo error {}", limbo_err);
+                            return Err(turso_core::LimboError::InternalError(
+                                "limbo and rusqlite results do not match".into(),
+                            ));
                         }
                     }
                 }
-                (Err(err), Ok(_)) => {
+                (None, None) => {}
+                _ => {
                     tracing::error!("limbo and rusqlite results do not match");
-                    tracing::error!("limbo error {}", err);
-                    return Err(err);
-                }
-                (Ok(val), Err(err)) => {
-                    tracing::error!("limbo and rusqlite results do not match");
-                    tracing::error!("limbo {:?}", val);
-                    tracing::error!("rusqlite error {}", err);
-                    return Err(err);
-                }
-                (Err(err), Err(err_rusqlite)) => {
-                    tracing::error!("limbo and rusqlite both fail, requires manual check");
-                    tracing::error!("limbo error {}", err);
-                    tracing::error!("rusqlite error {}", err_rusqlite);
-                    return Err(err);
+                    return Err(turso_core::LimboError::InternalError(
+                        "limbo and rusqlite results do not match".into(),
+                    ));
                 }
             }
+
+            Ok(next_execution)
+        }
+        (Err(err), Ok(_)) => {
+            tracing::error!("limbo and rusqlite results do not match");
+            tracing::error!("limbo error {}", err);
+            Err(err)
+        }
+        (Ok(val), Err(err)) => {
+            tracing::error!("limbo and rusqlite results do not match");
+            tracing::error!("limbo {:?}", val);
+            tracing::error!("rusqlite error {}", err);
+            Err(err)
+        }
+        (Err(err), Err(err_rusqlite)) => {
+            tracing::error!("limbo and rusqlite both fail, requires manual check");
+            tracing::error!("limbo error {}", err);
+            tracing::error!("rusqlite error {}", err_rusqlite);
+            Err(err)
         }
-        _ => unreachable!("{} vs {}", connection, rusqlite_connection),
     }
-
-    Ok(())
 }
 
 fn execute_interaction_rusqlite(
@@ -331,9 +350,7 @@ fn execute_interaction_rusqlite(
             };
 
             tracing::debug!("{}", interaction);
-            let results = execute_query_rusqlite(conn, query).map_err(|e| {
-                turso_core::LimboError::InternalError(format!("error executing query: {}", e))
-            });
+            let results = execute_query_rusqlite(conn, query).map_err(limbo_error_from_rusqlite);
             tracing::debug!("{:?}", results);
             stack.push(results);
         }
@@ -7,8 +7,12 @@ use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use turso_core::Database;
 
+use crate::model::domain::Domains;
+use crate::model::schema::Schema;
 use crate::model::table::Table;
 
+#[cfg(feature = "compare-sqlite")]
+use crate::runner::compare::SqliteOracle;
 use crate::runner::io::SimulatorIO;
 
 use super::cli::SimulatorCLI;
@@ -21,6 +25,15 @@ pub(crate) struct SimulatorEnv {
     pub(crate) db: Arc<Database>,
     pub(crate) rng: ChaCha8Rng,
     pub(crate) db_path: String,
+    /// Whether `PRAGMA foreign_keys` is currently enabled on the connection
+    pub(crate) foreign_keys: bool,
+    /// Open savepoints with the tables as they were when each one was opened
+    pub(crate) savepoints: Vec<(String, Vec<Table>)>,
+    /// Value domains of the columns that declare one, used when generating their values
+    pub(crate) domains: Domains,
+    /// SQLite checking the results of every query, with `--compare-with-sqlite`
+    #[cfg(feature = "compare-sqlite")]
+    pub(crate) oracle: Option<SqliteOracle>,
 }
 
 impl SimulatorEnv {
@@ -35,6 +48,14 @@ impl SimulatorEnv {
             db: self.db.clone(),
             rng: self.rng.clone(),
             db_path: self.db_path.clone(),
+            foreign_keys: false,
+            savepoints: Vec::new(),
+            domains: self.domains.clone(),
+            #[cfg(feature = "compare-sqlite")]
+            oracle: self
+                .oracle
+                .as_ref()
+                .map(SqliteOracle::clone_without_connections),
         }
     }
 }
@@ -43,6 +64,16 @@ impl SimulatorEnv {
     pub(crate) fn new(seed: u64, cli_opts: &SimulatorCLI, db_path: &Path) -> Self {
         let mut rng = ChaCha8Rng::seed_from_u64(seed);
 
+        let schema = cli_opts.tables_from_file.as_ref().map(|path| {
+            Schema::from_file(path).unwrap_or_else(|e| panic!("error loading schema: {:?}", e))
+        });
+        // A fixed schema is never changed by the plan
+        let fixed_schema = schema.is_some();
+        let domains = schema
+            .as_ref()
+            .map(|schema| schema.domains.clone())
+            .unwrap_or_default();
+
         let total = 100.0;
 
         let mut create_percent = 0.0;
@@ -54,15 +85,15 @@ impl SimulatorEnv {
         let read_percent = rng.gen_range(0.0..=total);
         let write_percent = total - read_percent;
 
-        if !cli_opts.disable_create {
+        if !cli_opts.disable_create && !fixed_schema {
             // Create percent should be 5-15% of the write percent
             create_percent = rng.gen_range(0.05..=0.15) * write_percent;
         }
-        if !cli_opts.disable_create_index {
+        if !cli_opts.disable_create_index && !fixed_schema {
             // Create indexpercent should be 2-5% of the write percent
             create_index_percent = rng.gen_range(0.02..=0.05) * write_percent;
         }
-        if !cli_opts.disable_drop {
+        if !cli_opts.disable_drop && !fixed_schema {
             // Drop percent should be 2-5% of the write percent
             drop_percent = rng.gen_range(0.02..=0.05) * write_percent;
         }
@@ -103,7 +134,7 @@ impl SimulatorEnv {
             ticks: rng.gen_range(cli_opts.minimum_tests..=cli_opts.maximum_tests),
             max_connections: 1, // TODO: for now let's use one connection as we didn't implement
             // correct transactions processing
-            max_tables: rng.gen_range(0..128),
+            max_tables: cli_opts.max_tables,
             create_percent,
             create_index_percent,
             read_percent,
@@ -117,12 +148,47 @@ impl SimulatorEnv {
             disable_select_limit: cli_opts.disable_select_limit,
             disable_delete_select: cli_opts.disable_delete_select,
             disable_drop_select: cli_opts.disable_drop_select,
-            disable_fsync_no_wait: cli_opts.disable_fsync_no_wait,
-            disable_faulty_query: cli_opts.disable_faulty_query,
-            page_size: 4096, // TODO: randomize this too
+            // Faults leave the outcome of the query unknown, so SQLite could not follow it
+            disable_fsync_no_wait: cli_opts.disable_fsync_no_wait || cli_opts.compare_with_sqlite,
+            disable_faulty_query: cli_opts.disable_faulty_query || cli_opts.compare_with_sqlite,
+            disable_foreign_key_enforced: cli_opts.disable_foreign_key_enforced,
+            disable_alter_table: cli_opts.disable_alter_table || fixed_schema,
+            // The SQLite bundled with rusqlite is built without SQLITE_ENABLE_UPDATE_DELETE_LIMIT
+            disable_delete_limit: cli_opts.disable_delete_limit
+                || cli_opts.differential
+                || cli_opts.compare_with_sqlite,
+            disable_reindex_preserves_results: cli_opts.disable_reindex_preserves_results,
+            disable_unique_allows_multiple_nulls: cli_opts.disable_unique_allows_multiple_nulls,
+            disable_savepoint_rollback: cli_opts.disable_savepoint_rollback,
+            disable_select_after_reopen_stable: cli_opts.disable_select_after_reopen_stable,
+            disable_select_after_checkpoint_stable: cli_opts.disable_select_after_checkpoint_stable,
+            disable_vacuum_preserves_data: cli_opts.disable_vacuum_preserves_data,
+            disable_default_applied: cli_opts.disable_default_applied,
+            disable_limit_offset_pagination: cli_opts.disable_limit_offset_pagination,
+            disable_group_having_consistency: cli_opts.disable_group_having_consistency,
+            disable_prepared_rebind_consistency: cli_opts.disable_prepared_rebind_consistency,
+            disable_is_null_partition: cli_opts.disable_is_null_partition,
+            disable_distinct_agg_consistency: cli_opts.disable_distinct_agg_consistency,
+            disable_between_consistency: cli_opts.disable_between_consistency,
+            disable_create_index_consistency: cli_opts.disable_create_index_consistency,
+            disable_autoincrement_no_reuse: cli_opts.disable_autoincrement_no_reuse,
+            disable_schema_round_trip: cli_opts.disable_schema_round_trip,
+            experimental_indexes: cli_opts.experimental_indexes,
+            alias_probability: cli_opts.alias_probability,
+            max_order_by_keys: cli_opts.max_order_by_keys,
+            max_columns: cli_opts.max_columns,
+            // TODO: allow 65536 once the btree stops storing the usable space in a u16
+            page_size: 1 << rng.gen_range(9..=15),
             max_interactions: rng.gen_range(cli_opts.minimum_tests..=cli_opts.maximum_tests),
             max_time_simulation: cli_opts.maximum_time,
+            duration: cli_opts.duration,
+            interaction_timeout: cli_opts.interaction_timeout,
             disable_reopen_database: cli_opts.disable_reopen_database,
+            disable_checkpoint: cli_opts.disable_checkpoint,
+            disable_slow_io: cli_opts.disable_slow_io,
+            no_shadow_check: cli_opts.no_shadow_check,
+            collect_all: cli_opts.collect_all,
+            schema,
         };
 
         let io =
@@ -138,17 +204,33 @@ impl SimulatorEnv {
             std::fs::remove_file(wal_path).unwrap();
         }
 
-        let db = match Database::open_file(io.clone(), db_path.to_str().unwrap(), false, false) {
+        let db = match Database::open_file(
+            io.clone(),
+            db_path.to_str().unwrap(),
+            false,
+            opts.experimental_indexes,
+        ) {
             Ok(db) => db,
             Err(e) => {
                 panic!("error opening simulator test file {:?}: {:?}", db_path, e);
             }
         };
+        // The page size can only change before the first write, so it is set on the new database
+        // before any interaction runs
+        let conn = db.connect().unwrap();
+        conn.execute(format!("PRAGMA page_size = {}", opts.page_size))
+            .unwrap();
+        conn.close().unwrap();
 
         let connections = (0..opts.max_connections)
             .map(|_| SimConnection::Disconnected)
             .collect::<Vec<_>>();
 
+        #[cfg(feature = "compare-sqlite")]
+        let oracle = cli_opts
+            .compare_with_sqlite
+            .then(|| SqliteOracle::new(db_path, opts.page_size, opts.max_connections));
+
         SimulatorEnv {
             opts,
             tables: Vec::new(),
@@ -157,6 +239,11 @@ impl SimulatorEnv {
             io,
             db,
             db_path: db_path.to_str().unwrap().to_string(),
+            foreign_keys: false,
+            savepoints: Vec::new(),
+            domains,
+            #[cfg(feature = "compare-sqlite")]
+            oracle,
         }
     }
 }
@@ -217,6 +304,7 @@ impl Display for SimConnection {
 pub(crate) struct SimulatorOpts {
     pub(crate) ticks: usize,
     pub(crate) max_connections: usize,
+    /// Number of tables past which the plan stops creating new ones
     pub(crate) max_tables: usize,
     // this next options are the distribution of workload where read_percent + write_percent +
     // delete_percent == 100%
@@ -236,9 +324,48 @@ pub(crate) struct SimulatorOpts {
     pub(crate) disable_drop_select: bool,
     pub(crate) disable_fsync_no_wait: bool,
     pub(crate) disable_faulty_query: bool,
+    pub(crate) disable_foreign_key_enforced: bool,
+    pub(crate) disable_alter_table: bool,
+    pub(crate) disable_delete_limit: bool,
+    pub(crate) disable_reindex_preserves_results: bool,
+    pub(crate) disable_unique_allows_multiple_nulls: bool,
+    pub(crate) disable_savepoint_rollback: bool,
+    pub(crate) disable_select_after_reopen_stable: bool,
+    pub(crate) disable_select_after_checkpoint_stable: bool,
+    pub(crate) disable_vacuum_preserves_data: bool,
+    pub(crate) disable_default_applied: bool,
+    pub(crate) disable_limit_offset_pagination: bool,
+    pub(crate) disable_group_having_consistency: bool,
+    pub(crate) disable_prepared_rebind_consistency: bool,
+    pub(crate) disable_is_null_partition: bool,
+    pub(crate) disable_distinct_agg_consistency: bool,
+    pub(crate) disable_between_consistency: bool,
+    pub(crate) disable_create_index_consistency: bool,
+    pub(crate) disable_autoincrement_no_reuse: bool,
+    pub(crate) disable_schema_round_trip: bool,
+    /// Whether the database is opened with index support
+    pub(crate) experimental_indexes: bool,
+    /// Percentage of generated SELECTs that use table and result column aliases
+    pub(crate) alias_probability: usize,
+    /// Maximum number of `ORDER BY` keys of generated SELECTs, not counting the `rowid` tie-breaker
+    pub(crate) max_order_by_keys: usize,
+    /// Maximum number of columns of a created table, generated columns included
+    pub(crate) max_columns: usize,
     pub(crate) disable_reopen_database: bool,
+    pub(crate) disable_checkpoint: bool,
+    pub(crate) disable_slow_io: bool,
+    /// Skip assertions and assumptions, only running the queries of the plan
+    pub(crate) no_shadow_check: bool,
+    /// Keep executing the plan after a failing assertion instead of stopping at the first one
+    pub(crate) collect_all: bool,
 
     pub(crate) max_interactions: usize,
     pub(crate) page_size: usize,
     pub(crate) max_time_simulation: usize,
+    /// When set, the plan is extended with new interactions until this budget expires
+    pub(crate) duration: Option<std::time::Duration>,
+    /// Time after which a query still running is interrupted and reported as a hang
+    pub(crate) interaction_timeout: Option<std::time::Duration>,
+    /// Schema of `--tables-from-file`, which the plan creates instead of a random first table
+    pub(crate) schema: Option<Schema>,
 }
@@ -44,14 +44,61 @@ impl ExecutionHistory {
     }
 }
 
+/// An assertion that failed while running with `--collect-all`.
+#[derive(Debug, Clone)]
+pub(crate) struct AssertionFailure {
+    pub(crate) execution: Execution,
+    pub(crate) message: String,
+    pub(crate) error: String,
+}
+
+/// Formats the failures of a run, grouped by assertion message in the order they first failed,
+/// along with the interactions they failed at.
+pub(crate) fn failure_report(failures: &[AssertionFailure]) -> String {
+    let mut groups: Vec<(&str, Vec<&AssertionFailure>)> = Vec::new();
+    for failure in failures {
+        match groups
+            .iter_mut()
+            .find(|(message, _)| *message == failure.message)
+        {
+            Some((_, group)) => group.push(failure),
+            None => groups.push((&failure.message, vec![failure])),
+        }
+    }
+    let mut report = format!(
+        "{} assertion failure(s) in {} group(s):",
+        failures.len(),
+        groups.len()
+    );
+    for (message, group) in groups {
+        report.push_str(&format!("\n- {} ({} time(s))", message, group.len()));
+        for failure in group {
+            report.push_str(&format!(
+                "\n    connection {}, interaction {}.{}: {}",
+                failure.execution.connection_index,
+                failure.execution.interaction_index,
+                failure.execution.secondary_index,
+                failure.error
+            ));
+        }
+    }
+    report
+}
+
 pub(crate) struct ExecutionResult {
     pub(crate) history: ExecutionHistory,
     pub(crate) error: Option<LimboError>,
+    /// Assertion failures the run went past, only collected with `--collect-all`
+    pub(crate) failures: Vec<AssertionFailure>,
 }
 
 impl ExecutionResult {
     pub(crate) fn new(history: ExecutionHistory, error: Option<LimboError>) -> Self {
-        Self { history, error }
+        Self {
+            history,
+            error,
+            failures: Vec::new(),
+        }
     }
 }
 
@@ -65,9 +112,38 @@ pub(crate) fn execute_plans(
     let now = std::time::Instant::now();
     env.clear_poison();
     let mut env = env.lock().unwrap();
-    for _tick in 0..env.opts.ticks {
+    let mut failures = Vec::new();
+    let mut tick = 0;
+    let error = loop {
+        if super::shutdown_requested() {
+            tracing::info!(
+                "shutdown requested, stopping the simulation after {} ticks",
+                tick
+            );
+            truncate_unexecuted(plans, states);
+            break None;
+        }
+        match env.opts.duration {
+            Some(duration) if now.elapsed() >= duration => {
+                tracing::info!("time budget of {:?} expired after {} ticks", duration, tick);
+                truncate_unexecuted(plans, states);
+                break None;
+            }
+            Some(_) => {}
+            None if tick >= env.opts.ticks => break None,
+            None => {}
+        }
+        tick += 1;
         // Pick the connection to interact with
         let connection_index = pick_index(env.connections.len(), &mut env.rng);
+        // Under a time budget, keep the plan going with interactions generated from the current state
+        if env.opts.duration.is_some()
+            && states[connection_index].interaction_pointer >= plans[connection_index].plan.len()
+        {
+            let mut rng = env.rng.clone();
+            plans[connection_index].extend_from(&mut rng, &env);
+            env.rng = rng;
+        }
         let state = &mut states[connection_index];
         std::thread::sleep(std::time::Duration::from_millis(
             std::env::var("TICK_SLEEP")
@@ -80,29 +156,46 @@ pub(crate) fn execute_plans(
             state.interaction_pointer,
             state.secondary_pointer,
         ));
-        let mut last_execution = last_execution.lock().unwrap();
-        last_execution.connection_index = connection_index;
-        last_execution.interaction_index = state.interaction_pointer;
-        last_execution.secondary_index = state.secondary_pointer;
+        {
+            let mut last_execution = last_execution.lock().unwrap();
+            last_execution.connection_index = connection_index;
+            last_execution.interaction_index = state.interaction_pointer;
+            last_execution.secondary_index = state.secondary_pointer;
+        }
         // Execute the interaction for the selected connection
-        match execute_plan(&mut env, connection_index, plans, states) {
-            Ok(_) => {}
-            Err(err) => {
-                return ExecutionResult::new(history, Some(err));
-            }
+        if let Err(err) = execute_plan(&mut env, connection_index, plans, states, &mut failures) {
+            break Some(err);
         }
         // Check if the maximum time for the simulation has been reached
-        if now.elapsed().as_secs() >= env.opts.max_time_simulation as u64 {
-            return ExecutionResult::new(
-                history,
-                Some(LimboError::InternalError(
-                    "maximum time for simulation reached".into(),
-                )),
-            );
+        if env.opts.duration.is_none()
+            && now.elapsed().as_secs() >= env.opts.max_time_simulation as u64
+        {
+            break Some(LimboError::InternalError(
+                "maximum time for simulation reached".into(),
+            ));
         }
-    }
+    };
 
-    ExecutionResult::new(history, None)
+    // A run that went past failed assertions still fails, on the first of them so that it is the
+    // one shrunk and saved to the bug base
+    let error = match (error, failures.first()) {
+        (None, Some(first)) => {
+            *last_execution.lock().unwrap() = first.execution;
+            Some(LimboError::InternalError(first.error.clone()))
+        }
+        (error, _) => error,
+    };
+    let mut result = ExecutionResult::new(history, error);
+    result.failures = failures;
+    result
+}
+
+/// Drops the interactions that were never reached, so the written plan replays exactly what ran.
+fn truncate_unexecuted(plans: &mut [InteractionPlan], states: &[InteractionPlanState]) {
+    for (plan, state) in plans.iter_mut().zip(states) {
+        let executed = state.interaction_pointer + usize::from(state.secondary_pointer > 0);
+        plan.plan.truncate(executed);
+    }
 }
 
 fn execute_plan(
@@ -110,6 +203,7 @@ fn execute_plan(
     connection_index: usize,
     plans: &mut [InteractionPlan],
     states: &mut [InteractionPlanState],
+    failures: &mut Vec<AssertionFailure>,
 ) -> Result<()> {
     let connection = &env.connections[connection_index];
     let plan = &mut plans[connection_index];
@@ -127,41 +221,173 @@ fn execute_plan(
             SimConnection::LimboConnection(env.db.connect().unwrap());
     } else {
         tracing::debug!("connection {} already connected", connection_index);
-        match execute_interaction(env, connection_index, interaction, &mut state.stack) {
+        env.io.reset_interaction_counters();
+        let result = execute_interaction(env, connection_index, interaction, &mut state.stack);
+        tracing::trace!("interaction io: {}", env.io.interaction_counters());
+        let next_execution = match result {
             Ok(next_execution) => {
-                interaction.shadow(env);
-                tracing::debug!("connection {} processed", connection_index);
-                // Move to the next interaction or property
-                match next_execution {
-                    ExecutionContinuation::NextInteraction => {
-                        if state.secondary_pointer + 1
-                            >= plan.plan[state.interaction_pointer].interactions().len()
-                        {
-                            // If we have reached the end of the interactions for this property, move to the next property
-                            state.interaction_pointer += 1;
-                            state.secondary_pointer = 0;
-                        } else {
-                            // Otherwise, move to the next interaction
-                            state.secondary_pointer += 1;
-                        }
-                    }
-                    ExecutionContinuation::NextProperty => {
-                        // Skip to the next property
-                        state.interaction_pointer += 1;
-                        state.secondary_pointer = 0;
-                    }
+                // Without assertions, the shadow model is only needed to extend the plan
+                if !env.opts.no_shadow_check || env.opts.duration.is_some() {
+                    interaction.shadow(env);
                 }
+                next_execution
             }
+            // With `--collect-all`, a failed assertion is recorded and the plan goes on
+            Err(err) if env.opts.collect_all => match interaction {
+                Interaction::Assertion(assertion) => {
+                    tracing::error!("assertion failed, continuing: {}", err);
+                    failures.push(AssertionFailure {
+                        execution: Execution::new(
+                            connection_index,
+                            state.interaction_pointer,
+                            state.secondary_pointer,
+                        ),
+                        message: assertion.message.clone(),
+                        error: err.to_string(),
+                    });
+                    state.stack.clear();
+                    ExecutionContinuation::NextInteraction
+                }
+                _ => {
+                    tracing::error!("error {}", err);
+                    return Err(err);
+                }
+            },
             Err(err) => {
                 tracing::error!("error {}", err);
                 return Err(err);
             }
-        }
+        };
+        tracing::debug!("connection {} processed", connection_index);
+        advance_state(plan, state, next_execution);
     }
 
     Ok(())
 }
 
+/// Moves the state of a plan to the next interaction or property.
+fn advance_state(
+    plan: &InteractionPlan,
+    state: &mut InteractionPlanState,
+    next_execution: ExecutionContinuation,
+) {
+    match next_execution {
+        ExecutionContinuation::NextInteraction => {
+            if state.secondary_pointer + 1
+                >= plan.plan[state.interaction_pointer].interactions().len()
+            {
+                // If we have reached the end of the interactions for this property, move to the next property
+                state.interaction_pointer += 1;
+                state.secondary_pointer = 0;
+            } else {
+                // Otherwise, move to the next interaction
+                state.secondary_pointer += 1;
+            }
+        }
+        ExecutionContinuation::NextProperty => {
+            // Skip to the next property
+            state.interaction_pointer += 1;
+            state.secondary_pointer = 0;
+        }
+    }
+}
+
+/// Runs the plans against two environments in lockstep, for the modes comparing them. At every
+/// tick, `connect` opens the picked connection on both environments, or once it is open,
+/// `execute` runs the next interaction against both and compares the outcomes.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn execute_plans_in_lockstep(
+    env: Arc<Mutex<SimulatorEnv>>,
+    second_env: Arc<Mutex<SimulatorEnv>>,
+    plans: &mut [InteractionPlan],
+    states: &mut [InteractionPlanState],
+    second_states: &mut [InteractionPlanState],
+    last_execution: Arc<Mutex<Execution>>,
+    connect: impl Fn(&mut SimulatorEnv, &mut SimulatorEnv, usize),
+    mut execute: impl FnMut(
+        &mut SimulatorEnv,
+        &mut SimulatorEnv,
+        usize,
+        &Interaction,
+        &mut InteractionPlanState,
+        &mut InteractionPlanState,
+    ) -> Result<ExecutionContinuation>,
+) -> ExecutionResult {
+    let mut history = ExecutionHistory::new();
+    let now = std::time::Instant::now();
+
+    let mut env = env.lock().unwrap();
+    let mut second_env = second_env.lock().unwrap();
+
+    for _tick in 0..env.opts.ticks {
+        // Pick the connection to interact with, both environments share the same seed
+        // so the second one has to consume its rng in the same way
+        let connection_index = pick_index(env.connections.len(), &mut env.rng);
+        let _ = pick_index(second_env.connections.len(), &mut second_env.rng);
+        let plan = &plans[connection_index];
+        let state = &mut states[connection_index];
+        let second_state = &mut second_states[connection_index];
+
+        history.history.push(Execution::new(
+            connection_index,
+            state.interaction_pointer,
+            state.secondary_pointer,
+        ));
+        {
+            let mut last_execution = last_execution.lock().unwrap();
+            last_execution.connection_index = connection_index;
+            last_execution.interaction_index = state.interaction_pointer;
+            last_execution.secondary_index = state.secondary_pointer;
+        }
+        if state.interaction_pointer >= plan.plan.len() {
+            continue;
+        }
+        let interaction =
+            &plan.plan[state.interaction_pointer].interactions()[state.secondary_pointer];
+
+        match (
+            env.connections[connection_index].is_connected(),
+            second_env.connections[connection_index].is_connected(),
+        ) {
+            (false, false) => {
+                tracing::debug!("connecting {}", connection_index);
+                connect(&mut env, &mut second_env, connection_index);
+            }
+            (true, true) => {
+                match execute(
+                    &mut env,
+                    &mut second_env,
+                    connection_index,
+                    interaction,
+                    state,
+                    second_state,
+                ) {
+                    Ok(next_execution) => advance_state(plan, state, next_execution),
+                    Err(err) => return ExecutionResult::new(history, Some(err)),
+                }
+                // Both states advance in lockstep
+                second_state.interaction_pointer = state.interaction_pointer;
+                second_state.secondary_pointer = state.secondary_pointer;
+            }
+            _ => unreachable!(
+                "{} vs {}",
+                env.connections[connection_index], second_env.connections[connection_index]
+            ),
+        }
+        // Check if the maximum time for the simulation has been reached
+        if now.elapsed().as_secs() >= env.opts.max_time_simulation as u64 {
+            return ExecutionResult::new(
+                history,
+                Some(LimboError::InternalError(
+                    "maximum time for simulation reached".into(),
+                )),
+            );
+        }
+    }
+
+    ExecutionResult::new(history, None)
+}
+
 /// The next point of control flow after executing an interaction.
 /// `execute_interaction` uses this type in conjunction with a result, where
 /// the `Err` case indicates a full-stop due to a bug, and the `Ok` case
@@ -191,9 +417,22 @@ pub(crate) fn execute_interaction(
                 SimConnection::Disconnected => unreachable!(),
             };
 
-            let results = interaction.execute_query(conn, &env.io);
+            let results = interaction
+                .execute_query(conn, &env.io, env.opts.interaction_timeout)
+                .map_err(|hang| {
+                    tracing::error!("{}", hang);
+                    LimboError::InternalError(hang.to_string())
+                })?;
             tracing::debug!(?results);
-            stack.push(results);
+            #[cfg(feature = "compare-sqlite")]
+            if let (Some(oracle), Interaction::Query(query)) = (env.oracle.as_mut(), interaction) {
+                oracle.compare(connection_index, query, &results)?;
+            }
+            if env.opts.no_shadow_check {
+                report_query_error(interaction, &results);
+            } else {
+                stack.push(results);
+            }
             limbo_integrity_check(conn)?;
         }
         Interaction::FsyncQuery(query) => {
@@ -205,12 +444,17 @@ pub(crate) fn execute_interaction(
 
             let results = interaction.execute_fsync_query(conn.clone(), env);
             tracing::debug!(?results);
-            stack.push(results);
+            if env.opts.no_shadow_check {
+                report_query_error(interaction, &results);
+            } else {
+                stack.push(results);
+            }
 
             let query_interaction = Interaction::Query(query.clone());
 
             execute_interaction(env, connection_index, &query_interaction, stack)?;
         }
+        Interaction::Assertion(_) | Interaction::Assumption(_) if env.opts.no_shadow_check => {}
         Interaction::Assertion(_) => {
             interaction.execute_assertion(stack, env)?;
             stack.clear();
@@ -236,7 +480,11 @@ pub(crate) fn execute_interaction(
 
             let results = interaction.execute_faulty_query(&conn, env);
             tracing::debug!(?results);
-            stack.push(results);
+            if env.opts.no_shadow_check {
+                report_query_error(interaction, &results);
+            } else {
+                stack.push(results);
+            }
             // Reset fault injection
             env.io.inject_fault(false);
             limbo_integrity_check(&conn)?;
@@ -246,6 +494,13 @@ pub(crate) fn execute_interaction(
     Ok(ExecutionContinuation::NextInteraction)
 }
 
+/// Logs the error a query returned, as nothing checks the results with `--no-shadow-check`
+fn report_query_error(interaction: &Interaction, results: &ResultSet) {
+    if let Err(err) = results {
+        tracing::warn!("query `{}` returned an error: {}", interaction, err);
+    }
+}
+
 fn limbo_integrity_check(conn: &Arc<Connection>) -> Result<()> {
     let mut rows = conn.query("PRAGMA integrity_check;")?.unwrap();
     let mut result = Vec::new();
@@ -7,6 +7,9 @@ use rand::Rng as _;
 use rand_chacha::ChaCha8Rng;
 use tracing::{instrument, Level};
 use turso_core::{CompletionType, File, Result};
+
+use crate::runner::io::IoCounters;
+
 pub(crate) struct SimulatorFile {
     pub(crate) inner: Arc<dyn File>,
     pub(crate) fault: Cell<bool>,
@@ -29,6 +32,12 @@ pub(crate) struct SimulatorFile {
     /// Number of `sync` function calls with injected fault.
     pub(crate) nr_sync_faults: Cell<usize>,
 
+    /// Bytes requested by `pread` calls that were not faulted.
+    pub(crate) nr_pread_bytes: Cell<usize>,
+
+    /// Bytes submitted by `pwrite` calls that were not faulted.
+    pub(crate) nr_pwrite_bytes: Cell<usize>,
+
     pub(crate) page_size: usize,
 
     pub(crate) rng: RefCell<ChaCha8Rng>,
@@ -46,6 +55,19 @@ impl SimulatorFile {
         self.fault.replace(fault);
     }
 
+    pub(crate) fn counters(&self) -> IoCounters {
+        IoCounters {
+            reads: self.nr_pread_calls.get(),
+            writes: self.nr_pwrite_calls.get(),
+            syncs: self.nr_sync_calls.get(),
+            bytes_read: self.nr_pread_bytes.get(),
+            bytes_written: self.nr_pwrite_bytes.get(),
+            faults: self.nr_pread_faults.get()
+                + self.nr_pwrite_faults.get()
+                + self.nr_sync_faults.get(),
+        }
+    }
+
     pub(crate) fn stats_table(&self) -> String {
         let sum_calls =
             self.nr_pread_calls.get() + self.nr_pwrite_calls.get() + self.nr_sync_calls.get();
@@ -116,6 +138,10 @@ impl File for SimulatorFile {
                 "Injected fault".into(),
             ));
         }
+        if let CompletionType::Read(read_completion) = &c.completion_type {
+            self.nr_pread_bytes
+                .set(self.nr_pread_bytes.get() + read_completion.buf().len());
+        }
         if let Some(latency) = self.generate_latency_duration() {
             let CompletionType::Read(read_completion) = &mut c.completion_type else {
                 unreachable!();
@@ -151,6 +177,8 @@ impl File for SimulatorFile {
                 "Injected fault".into(),
             ));
         }
+        self.nr_pwrite_bytes
+            .set(self.nr_pwrite_bytes.get() + buffer.borrow().len());
         if let Some(latency) = self.generate_latency_duration() {
             let CompletionType::Write(write_completion) = &mut c.completion_type else {
                 unreachable!();
@@ -207,6 +235,10 @@ impl File for SimulatorFile {
     fn size(&self) -> Result<u64> {
         self.inner.size()
     }
+
+    fn truncate(&self, len: u64) -> Result<()> {
+        self.inner.truncate(len)
+    }
 }
 
 impl Drop for SimulatorFile {
@@ -1,14 +1,70 @@
 use std::{
     cell::{Cell, RefCell},
+    fmt::Display,
+    ops::{Add, Sub},
     sync::Arc,
 };
 
 use rand::{RngCore, SeedableRng};
 use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
 use turso_core::{Clock, Instant, OpenFlags, PlatformIO, Result, IO};
 
 use crate::runner::file::SimulatorFile;
 
+/// IO issued through [SimulatorIO], counted per call. Faulted calls count as calls and faults,
+/// but not towards the bytes transferred.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct IoCounters {
+    pub(crate) reads: usize,
+    pub(crate) writes: usize,
+    pub(crate) syncs: usize,
+    pub(crate) bytes_read: usize,
+    pub(crate) bytes_written: usize,
+    /// Injected faults that were triggered, either by a file operation or by `run_once`
+    pub(crate) faults: usize,
+}
+
+impl Add for IoCounters {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        IoCounters {
+            reads: self.reads + other.reads,
+            writes: self.writes + other.writes,
+            syncs: self.syncs + other.syncs,
+            bytes_read: self.bytes_read + other.bytes_read,
+            bytes_written: self.bytes_written + other.bytes_written,
+            faults: self.faults + other.faults,
+        }
+    }
+}
+
+impl Sub for IoCounters {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        IoCounters {
+            reads: self.reads - other.reads,
+            writes: self.writes - other.writes,
+            syncs: self.syncs - other.syncs,
+            bytes_read: self.bytes_read - other.bytes_read,
+            bytes_written: self.bytes_written - other.bytes_written,
+            faults: self.faults - other.faults,
+        }
+    }
+}
+
+impl Display for IoCounters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "reads: {}, writes: {}, syncs: {}, bytes read: {}, bytes written: {}, faults: {}",
+            self.reads, self.writes, self.syncs, self.bytes_read, self.bytes_written, self.faults
+        )
+    }
+}
+
 pub(crate) struct SimulatorIO {
     pub(crate) inner: Box<dyn IO>,
     pub(crate) fault: Cell<bool>,
@@ -18,6 +74,15 @@ pub(crate) struct SimulatorIO {
     pub(crate) page_size: usize,
     seed: u64,
     latency_probability: usize,
+    /// Simulated time each IO operation takes, set by [crate::generation::plan::Fault::SlowIO]
+    io_delay_ms: Cell<u64>,
+    /// Simulated time elapsed since the start of the simulation, which the clock reports
+    elapsed_micros: Cell<u64>,
+    /// Counters of the files dropped by [Self::close_files], so that reopening the database
+    /// does not lose them
+    closed_files_counters: Cell<IoCounters>,
+    /// Counters at the start of the current interaction
+    interaction_start: Cell<IoCounters>,
 }
 
 unsafe impl Send for SimulatorIO {}
@@ -39,9 +104,19 @@ impl SimulatorIO {
             page_size,
             seed,
             latency_probability,
+            io_delay_ms: Cell::new(0),
+            elapsed_micros: Cell::new(0),
+            closed_files_counters: Cell::new(IoCounters::default()),
+            interaction_start: Cell::new(IoCounters::default()),
         })
     }
 
+    /// Makes every following IO operation take `delay_ms` of simulated time. The delay advances
+    /// the clock instead of sleeping, so that runs stay deterministic.
+    pub(crate) fn slow_down(&self, delay_ms: u64) {
+        self.io_delay_ms.set(delay_ms);
+    }
+
     pub(crate) fn inject_fault(&self, fault: bool) {
         self.fault.replace(fault);
         for file in self.files.borrow().iter() {
@@ -49,8 +124,44 @@ impl SimulatorIO {
         }
     }
 
+    /// Forgets all open files, keeping their counters
+    pub(crate) fn close_files(&self) {
+        let mut files = self.files.borrow_mut();
+        let closed = files
+            .drain(..)
+            .fold(IoCounters::default(), |acc, file| acc + file.counters());
+        self.closed_files_counters
+            .set(self.closed_files_counters.get() + closed);
+    }
+
+    /// IO issued since the start of the run
+    pub(crate) fn counters(&self) -> IoCounters {
+        let run_once = IoCounters {
+            faults: self.nr_run_once_faults.get(),
+            ..Default::default()
+        };
+        self.files
+            .borrow()
+            .iter()
+            .fold(self.closed_files_counters.get() + run_once, |acc, file| {
+                acc + file.counters()
+            })
+    }
+
+    /// Starts counting the IO of a new interaction from zero
+    pub(crate) fn reset_interaction_counters(&self) {
+        self.interaction_start.set(self.counters());
+    }
+
+    /// IO issued since the last [Self::reset_interaction_counters]
+    pub(crate) fn interaction_counters(&self) -> IoCounters {
+        self.counters() - self.interaction_start.get()
+    }
+
     pub(crate) fn print_stats(&self) {
+        tracing::info!("io: {}", self.counters());
         tracing::info!("run_once faults: {}", self.nr_run_once_faults.get());
+        tracing::info!("simulated time: {}ms", self.elapsed_micros.get() / 1000);
         for file in self.files.borrow().iter() {
             tracing::info!("\n===========================\n{}", file.stats_table());
         }
@@ -59,9 +170,10 @@ impl SimulatorIO {
 
 impl Clock for SimulatorIO {
     fn now(&self) -> Instant {
+        let elapsed_micros = self.elapsed_micros.get();
         Instant {
-            secs: 1704067200, // 2024-01-01 00:00:00 UTC
-            micros: 0,
+            secs: 1704067200 + (elapsed_micros / 1_000_000) as i64, // 2024-01-01 00:00:00 UTC
+            micros: (elapsed_micros % 1_000_000) as u32,
         }
     }
 }
@@ -83,6 +195,8 @@ impl IO for SimulatorIO {
             nr_pread_calls: Cell::new(0),
             nr_pwrite_calls: Cell::new(0),
             nr_sync_calls: Cell::new(0),
+            nr_pread_bytes: Cell::new(0),
+            nr_pwrite_bytes: Cell::new(0),
             page_size: self.page_size,
             rng: RefCell::new(ChaCha8Rng::seed_from_u64(self.seed)),
             latency_probability: self.latency_probability,
@@ -108,6 +222,8 @@ impl IO for SimulatorIO {
             ));
         }
         self.inner.run_once()?;
+        self.elapsed_micros
+            .set(self.elapsed_micros.get() + self.io_delay_ms.get() * 1000);
         Ok(())
     }
 
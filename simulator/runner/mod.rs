@@ -1,9 +1,26 @@
 pub mod bugbase;
 pub mod cli;
+#[cfg(feature = "compare-sqlite")]
+pub mod compare;
+pub mod determinism;
 pub mod differential;
 pub mod env;
 pub mod execution;
 #[allow(dead_code)]
 pub mod file;
 pub mod io;
+pub mod report;
 pub mod watch;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the Ctrl-C handler, the runner stops after the current tick once this is set.
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn request_shutdown() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+pub(crate) fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
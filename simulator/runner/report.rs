@@ -0,0 +1,122 @@
+//! Machine-readable summary of a simulator run, for CI scripts that would otherwise scrape
+//! the logs. The schema is versioned: fields are only added within a version, anything that
+//! renames, removes or changes the meaning of a field bumps [REPORT_VERSION].
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::generation::plan::InteractionStats;
+use crate::runner::cli::SimulatorCLI;
+use crate::runner::execution::Execution;
+use crate::runner::io::IoCounters;
+
+pub(crate) const REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Status {
+    Passed,
+    Failed,
+    /// Stopped by Ctrl-C before the plan ran to completion
+    Interrupted,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Failure {
+    /// Connection and position in the plan of the interaction that failed
+    pub(crate) connection_index: usize,
+    pub(crate) interaction_index: usize,
+    pub(crate) secondary_index: usize,
+    /// The failing assertion message, or the error or panic that stopped the run
+    pub(crate) message: String,
+}
+
+/// Files left behind by the run, only those that exist are reported
+#[derive(Debug, Serialize)]
+pub(crate) struct Artifacts {
+    pub(crate) base: PathBuf,
+    pub(crate) db: Option<PathBuf>,
+    pub(crate) plan: Option<PathBuf>,
+    pub(crate) shrunk_plan: Option<PathBuf>,
+    pub(crate) history: Option<PathBuf>,
+}
+
+impl Artifacts {
+    pub(crate) fn new(
+        base: &Path,
+        db: &Path,
+        plan: &Path,
+        shrunk_plan: &Path,
+        history: &Path,
+    ) -> Self {
+        let existing = |path: &Path| path.exists().then(|| path.to_path_buf());
+        Artifacts {
+            base: base.to_path_buf(),
+            db: existing(db),
+            plan: existing(plan),
+            shrunk_plan: existing(shrunk_plan),
+            history: existing(history),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Report<'a> {
+    pub(crate) version: u32,
+    pub(crate) seed: u64,
+    pub(crate) options: &'a SimulatorCLI,
+    /// Interactions of the generated plan, by kind
+    pub(crate) stats: InteractionStats,
+    /// IO issued by the run, absent in differential and determinism modes
+    pub(crate) io: Option<IoCounters>,
+    pub(crate) status: Status,
+    pub(crate) failure: Option<Failure>,
+    pub(crate) artifacts: Artifacts,
+}
+
+impl<'a> Report<'a> {
+    pub(crate) fn new(
+        seed: u64,
+        options: &'a SimulatorCLI,
+        stats: InteractionStats,
+        io: Option<IoCounters>,
+        result: &anyhow::Result<()>,
+        last_execution: Execution,
+        artifacts: Artifacts,
+    ) -> Self {
+        let (status, failure) = match result {
+            Ok(()) if super::shutdown_requested() => (Status::Interrupted, None),
+            Ok(()) => (Status::Passed, None),
+            Err(err) => (
+                Status::Failed,
+                Some(Failure {
+                    connection_index: last_execution.connection_index,
+                    interaction_index: last_execution.interaction_index,
+                    secondary_index: last_execution.secondary_index,
+                    message: format!("{:#}", err),
+                }),
+            ),
+        };
+        Report {
+            version: REPORT_VERSION,
+            seed,
+            options,
+            stats,
+            io,
+            status,
+            failure,
+            artifacts,
+        }
+    }
+
+    /// Writes the report as a single line of JSON to `path`, or to stdout without one
+    pub(crate) fn write(&self, path: Option<&Path>) -> anyhow::Result<()> {
+        let json = serde_json::to_string(self)?;
+        match path {
+            Some(path) => std::fs::write(path, json + "\n")?,
+            None => println!("{}", json),
+        }
+        Ok(())
+    }
+}
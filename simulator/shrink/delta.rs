@@ -0,0 +1,54 @@
+use crate::generation::plan::InteractionPlan;
+
+impl InteractionPlan {
+    /// Number of interactions of the plan, counting each interaction of a property
+    pub(crate) fn interaction_count(&self) -> usize {
+        self.plan
+            .iter()
+            .map(|interactions| interactions.interactions().len())
+            .sum()
+    }
+
+    /// Minimizes the plan with delta debugging, keeping it failing according to `reproduces`.
+    ///
+    /// The properties are split in `n` chunks and the plan is replayed without each chunk in
+    /// turn, dropping the chunks whose removal still reproduces the failure. When no chunk can
+    /// be removed, the chunks are halved, until single properties are tried one by one.
+    pub(crate) fn delta_debug(
+        &self,
+        mut reproduces: impl FnMut(&InteractionPlan) -> bool,
+    ) -> InteractionPlan {
+        let mut plan = self.plan.clone();
+        let mut n = 2;
+        while plan.len() >= 2 {
+            let chunk_size = plan.len().div_ceil(n);
+            let mut reduced = false;
+            let mut start = 0;
+            while start < plan.len() && plan.len() > 1 {
+                let end = (start + chunk_size).min(plan.len());
+                let candidate = InteractionPlan {
+                    plan: [&plan[..start], &plan[end..]].concat(),
+                };
+                if reproduces(&candidate) {
+                    tracing::info!(
+                        "removed {} properties, {} left",
+                        end - start,
+                        candidate.plan.len()
+                    );
+                    plan = candidate.plan;
+                    reduced = true;
+                } else {
+                    start = end;
+                }
+            }
+            if reduced {
+                n = (n - 1).max(2);
+            } else if chunk_size == 1 {
+                break;
+            } else {
+                n = (n * 2).min(plan.len());
+            }
+        }
+        InteractionPlan { plan }
+    }
+}
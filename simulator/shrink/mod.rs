@@ -1 +1,2 @@
+mod delta;
 pub mod plan;
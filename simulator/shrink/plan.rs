@@ -73,7 +73,24 @@ impl InteractionPlan {
                             Property::SelectLimit { .. }
                             | Property::SelectSelectOptimizer { .. }
                             | Property::FsyncNoWait { .. }
-                            | Property::FaultyQuery { .. } => {}
+                            | Property::FaultyQuery { .. }
+                            | Property::ForeignKeyEnforced { .. }
+                            | Property::ReindexPreservesResults { .. }
+                            | Property::UniqueAllowsMultipleNulls { .. }
+                            | Property::SavepointRollback { .. }
+                            | Property::SelectAfterReopenStable { .. }
+                            | Property::SelectAfterCheckpointStable { .. }
+                            | Property::VacuumPreservesData { .. }
+                            | Property::DefaultApplied { .. }
+                            | Property::LimitOffsetPagination { .. }
+                            | Property::GroupHavingConsistency { .. }
+                            | Property::PreparedRebindConsistency { .. }
+                            | Property::IsNullPartition { .. }
+                            | Property::DistinctAggConsistency { .. }
+                            | Property::BetweenConsistency { .. }
+                            | Property::CreateIndexConsistency { .. }
+                            | Property::AutoincrementNoReuse { .. }
+                            | Property::SchemaRoundTrip { .. } => {}
                         }
                     }
                     // Check again after query clear if the interactions still uses the failing table
@@ -86,8 +103,20 @@ impl InteractionPlan {
                     && !matches!(
                         interactions,
                         Interactions::Query(Query::Select(_))
+                            | Interactions::Query(Query::Prepared(_))
                             | Interactions::Property(Property::SelectLimit { .. })
                             | Interactions::Property(Property::SelectSelectOptimizer { .. })
+                            | Interactions::Property(Property::ReindexPreservesResults { .. })
+                            | Interactions::Property(Property::SelectAfterReopenStable { .. })
+                            | Interactions::Property(Property::SelectAfterCheckpointStable { .. })
+                            | Interactions::Property(Property::VacuumPreservesData { .. })
+                            | Interactions::Property(Property::LimitOffsetPagination { .. })
+                            | Interactions::Property(Property::GroupHavingConsistency { .. })
+                            | Interactions::Property(Property::PreparedRebindConsistency { .. })
+                            | Interactions::Property(Property::IsNullPartition { .. })
+                            | Interactions::Property(Property::DistinctAggConsistency { .. })
+                            | Interactions::Property(Property::BetweenConsistency { .. })
+                            | Interactions::Property(Property::SchemaRoundTrip { .. })
                     )
             };
             idx += 1;
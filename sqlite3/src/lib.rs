@@ -1103,18 +1103,33 @@ pub unsafe extern "C" fn sqlite3_wal_checkpoint(
 pub unsafe extern "C" fn sqlite3_wal_checkpoint_v2(
     db: *mut sqlite3,
     _db_name: *const ffi::c_char,
-    _mode: ffi::c_int,
-    _log_size: *mut ffi::c_int,
-    _checkpoint_count: *mut ffi::c_int,
+    mode: ffi::c_int,
+    log_size: *mut ffi::c_int,
+    checkpoint_count: *mut ffi::c_int,
 ) -> ffi::c_int {
     if db.is_null() {
         return SQLITE_MISUSE;
     }
+    let mode = match mode {
+        SQLITE_CHECKPOINT_PASSIVE => turso_core::CheckpointMode::Passive,
+        SQLITE_CHECKPOINT_FULL => turso_core::CheckpointMode::Full,
+        SQLITE_CHECKPOINT_RESTART => turso_core::CheckpointMode::Restart,
+        SQLITE_CHECKPOINT_TRUNCATE => turso_core::CheckpointMode::Truncate,
+        _ => return SQLITE_MISUSE,
+    };
     let db: &mut sqlite3 = &mut *db;
     let db = db.inner.lock().unwrap();
-    // TODO: Checkpointing modes and reporting back log size and checkpoint count to caller.
-    if db.conn.checkpoint().is_err() {
+    let Ok(result) = db.conn.checkpoint(mode) else {
         return SQLITE_ERROR;
+    };
+    if !log_size.is_null() {
+        *log_size = result.num_wal_frames as ffi::c_int;
+    }
+    if !checkpoint_count.is_null() {
+        *checkpoint_count = result.num_checkpointed_frames as ffi::c_int;
+    }
+    if result.busy {
+        return SQLITE_BUSY;
     }
     SQLITE_OK
 }
@@ -1,4 +1,5 @@
 #!/usr/bin/env python3
+import json
 import os
 import time
 from pathlib import Path
@@ -59,7 +60,7 @@ def test_file_operations():
 def test_joins():
     shell = TestTursoShell()
     shell.run_test("open-file", ".open testing/testing.db", "")
-    shell.run_test("verify-tables", ".tables", "products users")
+    shell.run_test("verify-tables", ".tables", "products  users")
     shell.run_test(
         "file-cross-join",
         "select * from users, products limit 1;",
@@ -159,6 +160,20 @@ def test_output_file():
     os.remove(output_file)
 
 
+def test_echo_read_script():
+    shell = TestTursoShell()
+    script = shell.config.test_dir / shell.config.py_folder / "echo_script.sql"
+    with open(script, "w") as f:
+        f.write(".echo on\nSELECT 'from script';\n.nullvalue NIL\nSELECT NULL;\n.echo off\nSELECT 'quiet';\n")
+    shell.run_test(
+        "echo-read-script",
+        f".read {script}",
+        "SELECT 'from script';\nfrom script\n.nullvalue NIL\nSELECT NULL;\nNIL\n.echo off\nquiet",
+    )
+    os.remove(script)
+    shell.quit()
+
+
 def test_multi_line_single_line_comments_succession():
     shell = TestTursoShell()
     comments = """-- First of the comments
@@ -228,9 +243,569 @@ def test_import_csv_skip():
     shell.quit()
 
 
+def test_import_ndjson():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    shell.run_test("create-ndjson-table", "CREATE TABLE t (c1 INTEGER, c2 REAL, c3 TEXT);", "")
+    shell.run_test(
+        "import-ndjson",
+        ".import --ndjson -v ./testing/test_files/test.ndjson t",
+        "Added 2 rows",
+    )
+    shell.run_test(
+        "verify-ndjson",
+        "SELECT c1, typeof(c1), c2, c3 FROM t;",
+        "1|integer|2.5|a\n7|integer|TURSO|b",
+    )
+
+    bad_path = "testing/turso_bad.ndjson"
+    with open(bad_path, "w") as f:
+        f.write('{"c1": 10}\n{"c1": 11,}\n')
+    shell.run_test_fn(
+        f".import --ndjson {bad_path} t",
+        lambda res: "line 2: malformed JSON" in res,
+        "import-ndjson-malformed",
+    )
+    with open(bad_path, "w") as f:
+        f.write('{"c1": 10, "extra": 1}\n')
+    shell.run_test_fn(
+        f".import --ndjson {bad_path} t",
+        lambda res: "line 1: table t has no column named extra" in res,
+        "import-ndjson-extra-key",
+    )
+    shell.run_test("ndjson-failed-imports-rolled-back", "SELECT count(*) FROM t;", "2")
+    shell.run_test("import-ndjson-ignore-extra", f".import --ndjson --ignore-extra {bad_path} t", "")
+    shell.run_test("verify-ndjson-ignore-extra", "SELECT count(*) FROM t WHERE c1 = 10;", "1")
+    shell.quit()
+    os.remove(bad_path)
+
+
+def test_import_csv_schema_infer():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    csv_path = "testing/turso_schema_infer.csv"
+    with open(csv_path, "w") as f:
+        f.write("id,price,name,note\n1,2.5,a,\n2,3,b,\n")
+    shell.run_test(
+        "import-csv-schema-infer",
+        f".import --csv --schema-infer {csv_path} t",
+        'CREATE TABLE t ("id" INTEGER, "price" REAL, "name" TEXT, "note" TEXT);',
+    )
+    shell.run_test(
+        "verify-csv-schema-infer",
+        "SELECT id, typeof(id), price, typeof(price), name FROM t;",
+        "1|integer|2.5|real|a\n2|integer|3.0|real|b",
+    )
+    # Into an existing table, the header row is skipped and nothing is created
+    shell.run_test("import-csv-schema-infer-existing", f".import --csv --schema-infer {csv_path} t", "")
+    shell.run_test("verify-csv-schema-infer-existing", "SELECT count(*) FROM t;", "4")
+    with open(csv_path, "w") as f:
+        f.write("id,name\n1,a\n2\n")
+    shell.run_test_fn(
+        f".import --csv --schema-infer {csv_path} u",
+        lambda res: "line 3: expected 2 values, got 1" in res,
+        "import-csv-schema-infer-bad-row",
+    )
+    shell.run_test_fn(
+        "SELECT * FROM u;",
+        lambda res: "no such table" in res,
+        "csv-schema-infer-rolled-back",
+    )
+    shell.quit()
+    os.remove(csv_path)
+
+
+def test_table_mode_width():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    shell.execute_dot(".mode table")
+    shell.execute_dot(".width 5 0")
+    # Wide characters take two columns, so only two of them fit next to the ellipsis
+    shell.run_test_fn(
+        "SELECT '日本語テキスト' AS name, 'unlimited text' AS note;",
+        lambda res: "日本…" in res and "日本語" not in res and "unlimited text" in res,
+        "width truncates wide characters on grapheme boundaries",
+    )
+    # The accent is a combining character, which stays with its letter
+    shell.run_test_fn(
+        "SELECT 'cafe\u0301 au lait' AS name;",
+        lambda res: "┌" in res and "cafe\u0301…" in res,
+        "table mode is an alias of the box-drawing pretty mode",
+    )
+    shell.execute_dot(".width")
+    shell.run_test_fn(
+        "SELECT '日本語テキスト' AS name;",
+        lambda res: "日本語テキスト" in res,
+        "width without arguments removes the limits",
+    )
+    shell.quit()
+
+
+def test_csv_mode():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    shell.execute_dot("CREATE TABLE t (a, b, c);")
+    shell.execute_dot("INSERT INTO t VALUES (1, 'a,b', 'say \"hi\"');")
+    shell.execute_dot("INSERT INTO t VALUES (2.5, 'plain', NULL);")
+    shell.execute_dot(".mode csv")
+    shell.run_test(
+        "csv-mode-quotes-when-needed",
+        "SELECT * FROM t;",
+        '1,"a,b","say ""hi"""\n2.5,plain,TURSO',
+    )
+    shell.execute_dot(".mode csv --quote-all")
+    shell.run_test(
+        "csv-mode-quote-all",
+        "SELECT * FROM t;",
+        '"1","a,b","say ""hi"""\n"2.5","plain",TURSO',
+    )
+
+    # What csv mode writes is read back as is by .import
+    export_path = "testing/turso_csv_export.csv"
+    shell.execute_dot(".mode csv")
+    shell.execute_dot(f".output {export_path}")
+    shell.execute_dot("SELECT a, b, c FROM t WHERE c IS NOT NULL;")
+    shell.execute_dot(".output stdout")
+    shell.execute_dot(".mode list")
+    shell.execute_dot("CREATE TABLE u (a, b, c);")
+    shell.execute_dot(f".import --csv {export_path} u")
+    shell.run_test("csv-mode-round-trip", "SELECT * FROM u;", '1|a,b|say "hi"')
+    shell.quit()
+    os.remove(export_path)
+
+
+def test_ascii_mode():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    shell.execute_dot("CREATE TABLE t (a INTEGER, b TEXT);")
+    shell.execute_dot("INSERT INTO t VALUES (1, 'a,b|c');")
+    shell.execute_dot("INSERT INTO t VALUES (2, 'two' || char(10) || 'lines \"quoted\"');")
+
+    # Fields and rows are written as they are, between unit and record separators
+    export_path = "testing/turso_ascii_export.txt"
+    shell.execute_dot(".mode ascii")
+    shell.execute_dot(f".output {export_path}")
+    shell.execute_dot("SELECT a, b FROM t;")
+    shell.execute_dot(".output stdout")
+    shell.execute_dot(".mode list")
+    shell.run_test("ascii-mode-export-done", "SELECT count(*) FROM t;", "2")
+    with open(export_path) as f:
+        exported = f.read()
+    assert exported == '1\x1fa,b|c\x1e2\x1ftwo\nlines "quoted"\x1e', repr(exported)
+
+    # What ascii mode writes is read back as is by .import
+    shell.execute_dot("CREATE TABLE u (a INTEGER, b TEXT);")
+    shell.execute_dot(".mode ascii")
+    shell.execute_dot(f".import {export_path} u")
+    shell.execute_dot(".mode list")
+    shell.run_test(
+        "ascii-mode-round-trip",
+        "SELECT count(*) FROM t JOIN u ON t.a = u.a AND t.b = u.b;",
+        "2",
+    )
+    shell.execute_dot("DELETE FROM u;")
+    shell.execute_dot(f".import --ascii {export_path} u")
+    shell.run_test("ascii-import-option", "SELECT count(*) FROM u;", "2")
+
+    # The separators can be changed, for list mode too
+    shell.execute_dot(".separator ; \\n")
+    shell.run_test("separator-list-mode", "SELECT 1, 'x';", "1;x")
+    shell.execute_dot(".mode list")
+    shell.run_test("separator-reset-by-mode", "SELECT 1, 'x';", "1|x")
+    shell.quit()
+    os.remove(export_path)
+
+
+def test_html_mode():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    shell.execute_dot("CREATE TABLE t (a, b);")
+    shell.execute_dot("INSERT INTO t VALUES (1, '<b>Tom & \"Jerry\"</b>');")
+    shell.execute_dot("INSERT INTO t VALUES (2, NULL);")
+    shell.execute_dot(".mode html")
+    shell.run_test(
+        "html-mode-escapes-values",
+        "SELECT * FROM t;",
+        "<table>\n"
+        "<tr><td>1</td><td>&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;</td></tr>\n"
+        "<tr><td>2</td><td>TURSO</td></tr>\n"
+        "</table>",
+    )
+    shell.execute_dot(".headers on")
+    shell.run_test(
+        "html-mode-headers",
+        "SELECT a AS 'x<y' FROM t WHERE a = 2;",
+        "<table>\n<tr><th>x&lt;y</th></tr>\n<tr><td>2</td></tr>\n</table>",
+    )
+
+    # The table goes to the file set by .output
+    export_path = "testing/turso_html_export.html"
+    shell.execute_dot(f".output {export_path}")
+    shell.execute_dot("SELECT a FROM t;")
+    shell.execute_dot(".output stdout")
+    shell.quit()
+    with open(export_path) as f:
+        assert f.read() == (
+            "<table>\n<tr><th>a</th></tr>\n<tr><td>1</td></tr>\n<tr><td>2</td></tr>\n</table>\n"
+        )
+    os.remove(export_path)
+
+
+def test_quote_mode():
+    shell = TestTursoShell()
+    shell.run_test("open-memory", ".open :memory:", "")
+    shell.execute_dot("CREATE TABLE t (a, b, c);")
+    shell.execute_dot("INSERT INTO t VALUES (1, 'it''s', x'0aff');")
+    shell.execute_dot("INSERT INTO t VALUES (2.5, 'plain', NULL);")
+    shell.execute_dot(".mode quote")
+    shell.run_test(
+        "quote-mode-literals",
+        "SELECT * FROM t;",
+        "1,'it''s',X'0AFF'\n2.5,'plain',NULL",
+    )
+    shell.execute_dot(".headers on")
+    shell.run_test(
+        "quote-mode-headers",
+        "SELECT a AS \"a'b\" FROM t WHERE a = 1;",
+        "'a''b'\n1",
+    )
+    shell.quit()
+
+
 def test_table_patterns():
     shell = TestTursoShell()
     shell.run_test("tables-pattern", ".tables us%", "users")
+    shell.run_test(
+        "tables-pattern-with-quote",
+        ".tables o'brien%",
+        "Error: Tables with pattern 'o'brien%' not found.",
+    )
+    shell.quit()
+
+
+def test_tables_in_columns():
+    shell = TestTursoShell(init_commands="")
+    for i in range(1, 7):
+        shell.run_test(f"create-table-{i}", f"CREATE TABLE table_number_0{i} (x);", "")
+    shell.run_test(
+        "tables-columns",
+        ".tables",
+        "table_number_01  table_number_03  table_number_05\ntable_number_02  table_number_04  table_number_06",
+    )
+    shell.run_test("tables-columns-pattern", ".tables %6", "table_number_06")
+    shell.quit()
+
+
+def test_trace_file():
+    shell = TestTursoShell()
+    trace_file = shell.config.test_dir / shell.config.py_folder / "turso_trace.sql"
+
+    shell.run_test("trace-on", f".trace {trace_file}", "")
+    shell.run_test("trace-select", "SELECT 'TRACED';", "TRACED")
+    shell.run_test("trace-off", ".trace off", "")
+    shell.run_test("trace-untraced-select", "SELECT 'UNTRACED';", "UNTRACED")
+    shell.quit()
+
+    with open(trace_file, "r") as f:
+        contents = f.read()
+
+    assert "SELECT 'TRACED';" in contents, "Executed statement should be traced"
+    assert "SELECT 'UNTRACED';" not in contents, "Statements after .trace off should not be traced"
+
+    os.remove(trace_file)
+
+
+def test_trace_file_expanded():
+    shell = TestTursoShell()
+    trace_file = shell.config.test_dir / shell.config.py_folder / "turso_trace_expanded.sql"
+
+    shell.run_test("trace-expanded-on", f".trace --expanded {trace_file}", "")
+    shell.run_test("trace-expanded-set-anonymous", ".parameter set ?1 NULL", "")
+    shell.run_test("trace-expanded-set-named", ".parameter set :name 'a''b'", "")
+    shell.run_test("trace-expanded-select", "SELECT 'it''s', ?, :name;", "it's||a'b")
+    shell.run_test("trace-expanded-off", ".trace off", "")
+    shell.quit()
+
+    with open(trace_file, "r") as f:
+        contents = f.read()
+
+    assert "SELECT 'it''s', NULL, 'a''b'" in contents, "Parameters should be traced as their values"
+
+    os.remove(trace_file)
+
+
+def test_parameter_read_script():
+    shell = TestTursoShell()
+    script = shell.config.test_dir / shell.config.py_folder / "parameter_script.sql"
+    with open(script, "w") as f:
+        f.write(".parameter set :step 10\nSELECT :start + :step, ?3;\n")
+
+    shell.run_test("parameter-set", ".parameter set :start 32", "")
+    shell.run_test("parameter-set-text", ".parameter set ?3 hello", "")
+    shell.run_test("parameter-read-script", f".read {script}", "42|hello")
+    shell.run_test("parameter-list", ".parameter list", ":start 32\n:step  10\n?3     'hello'")
+    shell.run_test("parameter-rerun-script", ".parameter set :start -1", "")
+    shell.run_test("parameter-read-script-again", f".read {script}", "9|hello")
+    shell.run_test("parameter-unset", ".parameter unset :start", "")
+    shell.run_test_fn(
+        "SELECT :start;",
+        lambda res: "no value for parameter :start" in res,
+        "parameter-missing",
+    )
+    shell.run_test("parameter-clear", ".parameter clear", "")
+    shell.run_test("parameter-list-empty", ".parameter list", "")
+    os.remove(script)
+    shell.quit()
+
+
+def test_limit():
+    shell = TestTursoShell()
+    shell.run_test("limit-show", ".limit column", "              column 2000")
+    shell.run_test("limit-set", ".limit sql_length 20", "          sql_length 20")
+    shell.run_test_fn(
+        "SELECT 'this statement is too long';",
+        lambda res: "statement too long" in res,
+        "limit-sql-length-enforced",
+    )
+    shell.run_test("limit-reset", ".limit sql_length 1000000", "          sql_length 1000000")
+    shell.run_test("limit-compound", ".limit compound_select 2", "     compound_select 2")
+    shell.run_test_fn(
+        "SELECT 1 UNION SELECT 2 UNION SELECT 3;",
+        lambda res: "too many terms in compound SELECT" in res,
+        "limit-compound-select-enforced",
+    )
+    shell.run_test_fn(".limit foo", lambda res: 'unknown limit: "foo"' in res, "limit-unknown")
+    shell.quit()
+
+
+def test_stats():
+    shell = TestTursoShell("CREATE TABLE t (a); INSERT INTO t VALUES (1), (2);")
+    shell.run_test_fn(".stats", lambda res: "Page cache hits:" in res, "stats-show")
+    shell.run_test("stats-off", ".stats off", "")
+    shell.run_test("stats-off-query", "SELECT count(*) FROM t;", "2")
+    shell.run_test("stats-on", ".stats on", "")
+    shell.run_test_fn(
+        "SELECT count(*) FROM t;",
+        lambda res: res.startswith("2") and "Page cache misses:" in res and "Pages written:" in res,
+        "stats-after-statement",
+    )
+    shell.quit()
+
+
+def test_dbinfo():
+    shell = TestTursoShell("CREATE TABLE t (a); PRAGMA user_version = 7;")
+    shell.run_test_fn(".dbinfo", lambda res: "database page size:  4096" in res, "dbinfo-page-size")
+    shell.run_test_fn(".dbinfo", lambda res: "text encoding:       1 (utf8)" in res, "dbinfo-encoding")
+    shell.run_test_fn(".dbinfo", lambda res: "user version:        7" in res, "dbinfo-user-version")
+    shell.run_test_fn(".dbinfo", lambda res: "number of tables:    1" in res, "dbinfo-tables")
+    shell.quit()
+
+
+def test_selftest():
+    shell = TestTursoShell("CREATE TABLE t (a); INSERT INTO t VALUES (1), (2);")
+    shell.run_test("selftest-integrity-only", ".selftest", "0 errors out of 1 tests")
+    shell.run_test("selftest-init", ".selftest --init", "0 errors out of 2 tests")
+    shell.run_test_fn(
+        "SELECT cmd, ans FROM selftest WHERE tno = 2;",
+        lambda res: 'SELECT count(*) FROM "t"|2' in res,
+        "selftest-init-stores-row-counts",
+    )
+    shell.execute_dot("INSERT INTO t VALUES (3);")
+    shell.execute_dot("INSERT INTO selftest VALUES (3, 'memo', 'checking t', NULL);")
+    shell.run_test_fn(
+        ".selftest",
+        lambda res: "checking t" in res
+        and "2: Expected: [2]" in res
+        and "2:      Got: [3]" in res
+        and "1 errors out of 2 tests" in res,
+        "selftest-reports-mismatches",
+    )
+    shell.quit()
+
+
+def test_hexdump():
+    shell = TestTursoShell("CREATE TABLE t (a);")
+    shell.run_test_fn(
+        ".hexdump 1",
+        lambda res: res.startswith("00000000  53 51 4c 69 74 65 20 66  6f 72 6d 61 74 20 33 00  |SQLite format 3.|"),
+        "hexdump-header",
+    )
+    shell.run_test_fn(".hexdump 1", lambda res: res.strip().endswith("00001000"), "hexdump-page-size")
+    shell.run_test_fn(".hexdump 0", lambda res: "out of range" in res, "hexdump-page-zero")
+    shell.run_test_fn(".hexdump 100", lambda res: "out of range" in res, "hexdump-out-of-range")
+    shell.quit()
+
+
+def test_recover():
+    shell = TestTursoShell(
+        "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT);"
+        " INSERT INTO t VALUES (1, 'it''s'), (2, NULL);"
+        " CREATE TABLE u (x); INSERT INTO u VALUES (X'01ff'), (1.5);"
+    )
+    shell.run_test_fn(
+        ".recover",
+        lambda res: res.startswith("BEGIN;")
+        and """INSERT INTO "t"("id","name") VALUES(1,'it''s');""" in res
+        and """INSERT INTO "t"("id","name") VALUES(2,NULL);""" in res
+        and """INSERT INTO "u"(rowid,"x") VALUES(1,X'01ff');""" in res
+        and """INSERT INTO "u"(rowid,"x") VALUES(2,1.5);""" in res
+        and "lost_and_found" not in res
+        and """/* 2 rows recovered from "t" */""" in res
+        and """/* 2 rows recovered from "u" */""" in res,
+        "recover-rows",
+    )
+    shell.quit()
+
+
+def test_connection():
+    shell = TestTursoShell()
+    shell.execute_dot(".connection new")
+    shell.run_test("connection-new-sees-data", "SELECT count(*) FROM users;", "4")
+    shell.execute_dot("BEGIN;")
+    shell.run_test("connection-write", "INSERT INTO users VALUES (5, 'Eve', 'Adams', 40);", "")
+
+    # The other connection does not see the uncommitted row
+    shell.execute_dot(".connection 0")
+    shell.run_test("connection-isolated", "SELECT count(*) FROM users;", "4")
+    shell.run_test_fn(
+        ".connection",
+        lambda res: "ACTIVE 0:" in res and "1:" in res and "(in transaction)" in res,
+        "connection-list",
+    )
+    shell.execute_dot(".connection 1")
+    shell.execute_dot("COMMIT;")
+    shell.execute_dot(".connection 0")
+    shell.run_test("connection-committed", "SELECT count(*) FROM users;", "5")
+    shell.run_test_fn(
+        ".connection 2",
+        lambda res: "Error: no connection 2" in res,
+        "connection-missing",
+    )
+    shell.quit()
+
+
+def test_json_errors():
+    def is_json_error(res, code, sql):
+        error = json.loads(res)
+        return error["code"] == code and error["sql"] == sql and error["message"]
+
+    def is_constraint_error(res, constraint, sql):
+        error = json.loads(res)
+        return is_json_error(res, "constraint", sql) and error["constraint"] == constraint
+
+    shell = TestTursoShell(
+        "CREATE TABLE t (a INTEGER PRIMARY KEY); CREATE TABLE c (x NOT NULL, y CONSTRAINT y_positive CHECK (y > 0));",
+        flags="-q --json-errors",
+    )
+    shell.run_test_fn(
+        "SELEC 1;",
+        lambda res: is_json_error(res, "parse", "SELEC 1;"),
+        "json-errors-parse",
+    )
+    shell.run_test_fn(
+        "SELECT * FROM missing;",
+        lambda res: is_json_error(res, "parse", "SELECT * FROM missing;"),
+        "json-errors-no-such-table",
+    )
+    shell.run_test("json-errors-insert", "INSERT INTO t VALUES (1);", "")
+    shell.run_test_fn(
+        "INSERT INTO t VALUES (1);",
+        lambda res: is_constraint_error(res, "unique", "INSERT INTO t VALUES (1);"),
+        "json-errors-constraint",
+    )
+    shell.run_test_fn(
+        "INSERT INTO c VALUES (NULL, 1);",
+        lambda res: is_constraint_error(res, "notnull", "INSERT INTO c VALUES (NULL, 1);"),
+        "json-errors-not-null-constraint",
+    )
+    shell.run_test_fn(
+        "INSERT INTO c VALUES (1, -1);",
+        lambda res: is_constraint_error(res, "check", "INSERT INTO c VALUES (1, -1);"),
+        "json-errors-check-constraint",
+    )
+    shell.quit()
+
+
+def test_types():
+    shell = TestTursoShell("")
+    shell.run_test("types-on", ".types on", "")
+    shell.run_test("types-values", "SELECT 1, 2.5, 'a', NULL;", "1 (integer)|2.5 (real)|a (text)| (null)")
+    shell.run_test("types-off", ".types off", "")
+    shell.run_test("types-values-off", "SELECT 1, 'a';", "1|a")
+    shell.quit()
+
+
+def test_binary():
+    shell = TestTursoShell("")
+    shell.run_test("binary-on", ".binary on", "")
+    shell.run_test(
+        "binary-values",
+        "SELECT x'0aff', 'a' || char(10) || 'b\\' || char(27);",
+        "X'0AFF'|a\\nb\\\\\\x1b",
+    )
+    shell.execute_dot(".mode csv")
+    shell.run_test("binary-csv", "SELECT 'a,' || char(9);", '"a,\\t"')
+    shell.execute_dot(".mode list")
+    shell.run_test("binary-off", ".binary off", "")
+    shell.run_test("binary-values-off", "SELECT 'a' || char(9) || 'b';", "a\tb")
+    shell.quit()
+
+
+def test_progress():
+    shell = TestTursoShell("CREATE TABLE t (a); INSERT INTO t VALUES (1), (2), (3);")
+    # The dots go to stderr, so the results are unchanged
+    shell.run_test("progress-on", ".progress 1", "")
+    shell.run_test("progress-query", "SELECT sum(a) FROM t;", "6")
+    shell.run_test("progress-off", ".progress 0", "")
+    shell.run_test("progress-off-query", "SELECT count(*) FROM t;", "3")
+    shell.quit()
+
+
+def test_sha3sum():
+    shell = TestTursoShell("CREATE TABLE t (a, b); INSERT INTO t VALUES (1, 'x'), (2, NULL);")
+    digest = shell.shell.execute(".sha3sum")
+    shell.run_test_fn(
+        ".sha3sum --sha3-512",
+        lambda res: len(res) == 128 and res != digest,
+        "sha3sum-512",
+    )
+    # Reinserting the same rows in another order changes the rowids, not the digest
+    shell.execute_dot("DELETE FROM t;")
+    shell.execute_dot("INSERT INTO t VALUES (2, NULL), (1, 'x');")
+    shell.run_test("sha3sum-reinserted", ".sha3sum", digest)
+    shell.run_test("sha3sum-cksum-alias", ".cksum", digest)
+    shell.execute_dot("UPDATE t SET b = 'y' WHERE a = 1;")
+    shell.run_test_fn(
+        ".sha3sum",
+        lambda res: len(res) == 64 and res != digest,
+        "sha3sum-changed-row",
+    )
+    shell.quit()
+
+
+def test_cd():
+    shell = TestTursoShell("")
+    shell.run_test("cd", f".cd {shell.config.test_dir}", "")
+    shell.run_test("cd-print", ".cd", f"{shell.config.cwd}/{shell.config.test_dir}")
+    shell.run_test("cd-missing", ".cd does-not-exist", 'Cannot change to directory "does-not-exist"')
+    shell.run_test("create-csv-table", "CREATE TABLE csv_table (c1 INT, c2 REAL, c3 String);", "")
+    shell.run_test("import-relative", f".import --csv {shell.config.test_files}/test.csv csv_table", "")
+    shell.run_test("verify-import-relative", "select count(*) from csv_table;", "2")
+    shell.quit()
+
+
+def test_system():
+    shell = TestTursoShell("")
+    shell.run_test("system", ".system echo hello", "hello")
+    shell.run_test("shell", ".shell echo hello", "hello")
+    shell.run_test("system-exit-status", ".system exit 3", "System command returns 3")
+    shell.run_test("system-quoted-argument", '.system echo "a   b" c', "a   b c")
+    shell.run_test("cd-for-system", f".cd {shell.config.test_dir}", "")
+    shell.run_test("system-in-cwd", ".system pwd", f"{shell.config.cwd}/{shell.config.test_dir}")
+    shell.quit()
+
+    shell = TestTursoShell("", flags="-q --no-shell")
+    shell.run_test("system-disabled", ".system echo hello", "Error: the .system and .shell commands are disabled")
     shell.quit()
 
 
@@ -299,12 +874,38 @@ def main():
     test_switch_back_to_in_memory()
     test_verify_null_value()
     test_output_file()
+    test_echo_read_script()
     test_multi_line_single_line_comments_succession()
     test_comments()
     test_import_csv()
     test_import_csv_verbose()
     test_import_csv_skip()
+    test_import_ndjson()
+    test_import_csv_schema_infer()
+    test_csv_mode()
+    test_ascii_mode()
+    test_table_mode_width()
+    test_html_mode()
+    test_quote_mode()
     test_table_patterns()
+    test_tables_in_columns()
+    test_trace_file()
+    test_trace_file_expanded()
+    test_parameter_read_script()
+    test_limit()
+    test_stats()
+    test_dbinfo()
+    test_selftest()
+    test_hexdump()
+    test_recover()
+    test_connection()
+    test_types()
+    test_binary()
+    test_progress()
+    test_sha3sum()
+    test_cd()
+    test_system()
+    test_json_errors()
     test_update_with_limit()
     test_update_with_limit_and_offset()
     test_uri_readonly()
@@ -75,7 +75,7 @@ def test_regexp():
     extension_path = "./target/debug/liblimbo_regexp"
     # before extension loads, assert no function
     limbo.run_test_fn(
-        "SELECT regexp('a.c', 'abc');",
+        "SELECT regexp_like('abc', 'a.c');",
         lambda res: "Parse error: no such function" in res,
     )
     limbo.run_test_fn(f".load {extension_path}", null)
@@ -61,6 +61,19 @@ proc do_execsql_test_regex {test_name sql_statements expected_regex} {
     }
 }
 
+proc do_execsql_test_regex_on_specific_db {db_name test_name sql_statements expected_regex} {
+    test_put "Running test" $db_name $test_name
+    set combined_sql [string trim $sql_statements]
+    set actual_output [evaluate_sql $::sqlite_exec $db_name $combined_sql]
+
+    # Validate the actual output against the regular expression
+    if {![regexp $expected_regex $actual_output]} {
+        error_put $sql_statements
+        puts "returned '$actual_output'"
+        puts "expected to match regex '$expected_regex'"
+        exit 1
+    }
+}
 
 proc do_execsql_test_on_specific_db {db_name test_name sql_statements expected_outputs} {
     test_put "Running test" $db_name $test_name
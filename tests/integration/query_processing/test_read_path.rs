@@ -1,4 +1,5 @@
 use crate::common::TempDatabase;
+use std::{cell::Cell, rc::Rc};
 use turso_core::{StepResult, Value};
 
 #[test]
@@ -98,6 +99,75 @@ fn test_statement_bind() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_statement_bind_named() -> anyhow::Result<()> {
+    let tmp_db = TempDatabase::new_with_rusqlite(
+        "create table t (id integer primary key, name text);",
+        false,
+    );
+    let conn = tmp_db.connect_limbo();
+    conn.execute("insert into t values (1, 'a'), (2, 'b'), (3, 'c')")?;
+
+    let mut stmt = conn.prepare("select name from t where id >= :low and id <= ?2")?;
+    assert_eq!(stmt.parameter_index(":low"), Some(1.try_into()?));
+    assert_eq!(stmt.parameter_index("?2"), Some(2.try_into()?));
+    assert_eq!(stmt.parameter_index(":missing"), None);
+    assert_eq!(stmt.parameter_index("?3"), None);
+    assert!(stmt.bind_named(":missing", Value::Integer(1)).is_err());
+
+    let run = |stmt: &mut turso_core::Statement| -> anyhow::Result<Vec<String>> {
+        let mut names = Vec::new();
+        loop {
+            match stmt.step()? {
+                StepResult::Row => {
+                    let row = stmt.row().unwrap();
+                    names.push(row.get::<&Value>(0).unwrap().to_string());
+                }
+                StepResult::IO => tmp_db.io.run_once()?,
+                _ => break,
+            }
+        }
+        Ok(names)
+    };
+
+    stmt.bind_named(":low", Value::Integer(1))?;
+    stmt.bind_named("?2", Value::Integer(2))?;
+    assert_eq!(run(&mut stmt)?, vec!["a", "b"]);
+
+    stmt.reset();
+    stmt.bind_named(":low", Value::Integer(2))?;
+    stmt.bind_at(2.try_into()?, Value::Integer(3));
+    assert_eq!(run(&mut stmt)?, vec!["b", "c"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_statement_bind_between() -> anyhow::Result<()> {
+    let tmp_db = TempDatabase::new_with_rusqlite("create table test (i integer);", false);
+    let conn = tmp_db.connect_limbo();
+
+    // The parameters are numbered in order even though BETWEEN compares the first one twice
+    let mut stmt = conn.prepare("select ? between ? and ?, ? not between ? and ?")?;
+    for (i, value) in [3, 1, 4, 5, 5, 9].into_iter().enumerate() {
+        stmt.bind_at((i + 1).try_into()?, Value::Integer(value));
+    }
+
+    loop {
+        match stmt.step()? {
+            StepResult::Row => {
+                let row = stmt.row().unwrap();
+                assert_eq!(*row.get::<&Value>(0).unwrap(), Value::Integer(1));
+                assert_eq!(*row.get::<&Value>(1).unwrap(), Value::Integer(0));
+            }
+            StepResult::IO => tmp_db.io.run_once()?,
+            _ => break,
+        }
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_insert_parameter_remap() -> anyhow::Result<()> {
     // ───────────────────────  schema  ──────────────────────────────
@@ -703,3 +773,46 @@ fn test_bind_parameters_delete_rowid_alias_seek_out_of_order() -> anyhow::Result
     assert_eq!(ins.parameters().count(), 4);
     Ok(())
 }
+
+#[test]
+fn test_progress_handler_interrupts_statement() -> anyhow::Result<()> {
+    let tmp_db = TempDatabase::new_with_rusqlite("create table test (i integer);", false);
+    let conn = tmp_db.connect_limbo();
+
+    let calls = Rc::new(Cell::new(0));
+    {
+        let calls = calls.clone();
+        conn.set_progress_handler(
+            1,
+            Some(Box::new(move || {
+                calls.set(calls.get() + 1);
+                true
+            })),
+        );
+    }
+
+    let mut stmt = conn.prepare("select 1")?;
+    loop {
+        match stmt.step()? {
+            StepResult::IO => tmp_db.io.run_once()?,
+            StepResult::Interrupt => break,
+            step => panic!("expected the statement to be interrupted, got {:?}", step),
+        }
+    }
+    assert_eq!(calls.get(), 1);
+
+    // Without a handler the same statement runs to completion
+    conn.set_progress_handler(1, None);
+    let mut stmt = conn.prepare("select 1")?;
+    let mut rows = 0;
+    loop {
+        match stmt.step()? {
+            StepResult::Row => rows += 1,
+            StepResult::IO => tmp_db.io.run_once()?,
+            StepResult::Done => break,
+            step => panic!("unexpected step result {:?}", step),
+        }
+    }
+    assert_eq!(rows, 1);
+    Ok(())
+}
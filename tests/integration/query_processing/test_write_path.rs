@@ -3,7 +3,7 @@ use crate::common::{compare_string, do_flush, TempDatabase};
 use log::debug;
 use std::io::{Read, Seek, Write};
 use std::sync::Arc;
-use turso_core::{Connection, Database, Row, Statement, StepResult, Value};
+use turso_core::{CheckpointMode, Connection, Database, Row, Statement, StepResult, Value};
 
 const WAL_HEADER_SIZE: usize = 32;
 const WAL_FRAME_HEADER_SIZE: usize = 24;
@@ -285,7 +285,7 @@ fn test_wal_checkpoint() -> anyhow::Result<()> {
     for i in 0..iterations {
         let insert_query = format!("INSERT INTO test VALUES ({})", i);
         do_flush(&conn, &tmp_db)?;
-        conn.checkpoint()?;
+        conn.checkpoint(CheckpointMode::Passive)?;
         run_query(&tmp_db, &conn, &insert_query)?;
     }
 
@@ -347,6 +347,92 @@ fn test_wal_restart() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn wal_checkpoint(
+    tmp_db: &TempDatabase,
+    conn: &Arc<Connection>,
+    mode: &str,
+) -> anyhow::Result<(i64, i64, i64)> {
+    let mut result = None;
+    run_query_on_row(
+        tmp_db,
+        conn,
+        &format!("PRAGMA wal_checkpoint({})", mode),
+        |row: &Row| {
+            result = Some((
+                row.get::<i64>(0).unwrap(),
+                row.get::<i64>(1).unwrap(),
+                row.get::<i64>(2).unwrap(),
+            ));
+        },
+    )?;
+    Ok(result.unwrap())
+}
+
+#[test]
+fn test_wal_checkpoint_truncate() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER);", false);
+    let wal_path = tmp_db.path.with_extension("db-wal");
+    {
+        let conn = tmp_db.connect_limbo();
+        for i in 0..10 {
+            run_query(&tmp_db, &conn, &format!("INSERT INTO t VALUES ({})", i))?;
+        }
+        assert!(std::fs::metadata(&wal_path)?.len() > 0);
+        assert_eq!(wal_checkpoint(&tmp_db, &conn, "TRUNCATE")?, (0, 0, 0));
+        assert_eq!(std::fs::metadata(&wal_path)?.len(), 0);
+
+        // The next writer starts a new log with a header of its own
+        run_query(&tmp_db, &conn, "INSERT INTO t VALUES (10)")?;
+        assert!(std::fs::metadata(&wal_path)?.len() > 0);
+        conn.close()?;
+    }
+
+    let sqlite_conn = rusqlite::Connection::open(&tmp_db.path)?;
+    let (count, sum): (i64, i64) =
+        sqlite_conn.query_row("SELECT count(*), sum(x) FROM t", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    assert_eq!((count, sum), (11, 55));
+    Ok(())
+}
+
+#[test]
+fn test_wal_checkpoint_restart() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER);", false);
+    let wal_path = tmp_db.path.with_extension("db-wal");
+    {
+        let conn = tmp_db.connect_limbo();
+        for i in 0..10 {
+            run_query(&tmp_db, &conn, &format!("INSERT INTO t VALUES ({})", i))?;
+        }
+        let (busy, log, checkpointed) = wal_checkpoint(&tmp_db, &conn, "RESTART")?;
+        assert_eq!(busy, 0);
+        assert!(log > 0);
+        assert_eq!(log, checkpointed);
+        let wal_frames = log;
+        let wal_size = std::fs::metadata(&wal_path)?.len();
+
+        // The log was restarted, so the next writer overwrites it from the first frame
+        run_query(&tmp_db, &conn, "INSERT INTO t VALUES (10)")?;
+        assert_eq!(std::fs::metadata(&wal_path)?.len(), wal_size);
+        let (busy, log, checkpointed) = wal_checkpoint(&tmp_db, &conn, "FULL")?;
+        assert_eq!(busy, 0);
+        assert!(log > 0 && log < wal_frames);
+        assert_eq!(log, checkpointed);
+        conn.close()?;
+    }
+
+    let sqlite_conn = rusqlite::Connection::open(&tmp_db.path)?;
+    let (count, sum): (i64, i64) =
+        sqlite_conn.query_row("SELECT count(*), sum(x) FROM t", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    assert_eq!((count, sum), (11, 55));
+    Ok(())
+}
+
 #[test]
 fn test_insert_after_big_blob() -> anyhow::Result<()> {
     let _ = env_logger::try_init();
@@ -734,6 +820,646 @@ fn test_wal_bad_frame() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_header_pragmas_persist_after_reopen() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let db_path = {
+        let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t(x)", false);
+        let conn = tmp_db.connect_limbo();
+        run_query(&tmp_db, &conn, "PRAGMA user_version = 42")?;
+        run_query(&tmp_db, &conn, "PRAGMA application_id = -123")?;
+        run_query_on_row(&tmp_db, &conn, "PRAGMA application_id", |row| {
+            assert_eq!(row.get::<i64>(0).unwrap(), -123);
+        })?;
+        conn.close()?;
+        tmp_db.path.clone()
+    };
+
+    let tmp_db = TempDatabase::new_with_existent(&db_path, false);
+    let conn = tmp_db.connect_limbo();
+    run_query_on_row(&tmp_db, &conn, "PRAGMA user_version", |row| {
+        assert_eq!(row.get::<i64>(0).unwrap(), 42);
+    })?;
+    run_query_on_row(&tmp_db, &conn, "PRAGMA application_id", |row| {
+        assert_eq!(row.get::<i64>(0).unwrap(), -123);
+    })?;
+
+    let sqlite_conn = rusqlite::Connection::open(&db_path)?;
+    let application_id: i64 =
+        sqlite_conn.query_row("PRAGMA application_id", [], |row| row.get(0))?;
+    assert_eq!(application_id, -123);
+
+    Ok(())
+}
+
+#[test]
+fn test_page_size_persists_after_reopen() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let db_path = {
+        let tmp_db = TempDatabase::new_empty(false);
+        let conn = tmp_db.connect_limbo();
+        run_query(&tmp_db, &conn, "PRAGMA page_size = 8192")?;
+        run_query(&tmp_db, &conn, "CREATE TABLE t (x INTEGER, y TEXT)")?;
+        for i in 0..1000 {
+            run_query(
+                &tmp_db,
+                &conn,
+                &format!("INSERT INTO t VALUES ({}, '{}')", i, "y".repeat(100)),
+            )?;
+        }
+        // Once the database has pages its page size can no longer change
+        run_query(&tmp_db, &conn, "PRAGMA page_size = 1024")?;
+        run_query_on_row(&tmp_db, &conn, "PRAGMA page_size", |row| {
+            assert_eq!(row.get::<i64>(0).unwrap(), 8192);
+        })?;
+        conn.close()?;
+        tmp_db.path.clone()
+    };
+
+    let tmp_db = TempDatabase::new_with_existent(&db_path, false);
+    let conn = tmp_db.connect_limbo();
+    run_query_on_row(&tmp_db, &conn, "PRAGMA page_size", |row| {
+        assert_eq!(row.get::<i64>(0).unwrap(), 8192);
+    })?;
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT count(*), sum(x) FROM t"),
+        vec![vec![
+            rusqlite::types::Value::Integer(1000),
+            rusqlite::types::Value::Integer(499500)
+        ]]
+    );
+    conn.close()?;
+
+    let sqlite_conn = rusqlite::Connection::open(&db_path)?;
+    let page_size: i64 = sqlite_conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    assert_eq!(page_size, 8192);
+    let integrity: String =
+        sqlite_conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    assert_eq!(integrity, "ok");
+
+    Ok(())
+}
+
+#[test]
+fn test_cache_size_small_cache_scan() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", false);
+    {
+        let sqlite_conn = rusqlite::Connection::open(&tmp_db.path)?;
+        sqlite_conn.execute(
+            "WITH RECURSIVE s(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM s WHERE i < 5000)
+             INSERT INTO t SELECT i, hex(zeroblob(100)) FROM s",
+            [],
+        )?;
+    }
+    let conn = tmp_db.connect_limbo();
+    run_query(&tmp_db, &conn, "PRAGMA cache_size = 10")?;
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "PRAGMA cache_size"),
+        vec![vec![rusqlite::types::Value::Integer(10)]]
+    );
+
+    // The table spans far more pages than the cache can hold, so they get evicted while scanning
+    for _ in 0..2 {
+        assert_eq!(
+            common::limbo_exec_rows(
+                &tmp_db,
+                &conn,
+                "SELECT count(*), sum(x), sum(length(y)) FROM t"
+            ),
+            vec![vec![
+                rusqlite::types::Value::Integer(5000),
+                rusqlite::types::Value::Integer(12502500),
+                rusqlite::types::Value::Integer(1000000)
+            ]]
+        );
+    }
+
+    run_query(&tmp_db, &conn, "PRAGMA cache_size = -64")?;
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "PRAGMA cache_size"),
+        vec![vec![rusqlite::types::Value::Integer(-64)]]
+    );
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT max(x) FROM t"),
+        vec![vec![rusqlite::types::Value::Integer(5000)]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_vacuum_shrinks_file() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", true);
+    let conn = tmp_db.connect_limbo();
+    run_query(&tmp_db, &conn, "CREATE INDEX tx ON t (x)")?;
+    for i in 0..2000 {
+        run_query(
+            &tmp_db,
+            &conn,
+            &format!("INSERT INTO t VALUES ({}, '{}')", i, "y".repeat(200)),
+        )?;
+    }
+    run_query(&tmp_db, &conn, "DELETE FROM t WHERE x % 10 != 0")?;
+    conn.checkpoint(CheckpointMode::Passive)?;
+    let size_before = std::fs::metadata(&tmp_db.path)?.len();
+
+    run_query(&tmp_db, &conn, "VACUUM")?;
+    let size_after = std::fs::metadata(&tmp_db.path)?.len();
+    assert!(
+        size_after < size_before / 2,
+        "file did not shrink: {} -> {}",
+        size_before,
+        size_after
+    );
+    assert_eq!(
+        common::limbo_exec_rows(
+            &tmp_db,
+            &conn,
+            "SELECT count(*), sum(x) FROM t WHERE x >= 0"
+        ),
+        vec![vec![
+            rusqlite::types::Value::Integer(200),
+            rusqlite::types::Value::Integer(199000)
+        ]]
+    );
+    conn.close()?;
+
+    let sqlite_conn = rusqlite::Connection::open(&tmp_db.path)?;
+    let integrity: String =
+        sqlite_conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    assert_eq!(integrity, "ok");
+    let count: i64 =
+        sqlite_conn.query_row("SELECT count(*) FROM t WHERE x >= 0", [], |row| row.get(0))?;
+    assert_eq!(count, 200);
+
+    Ok(())
+}
+
+#[test]
+fn test_vacuum_keeps_statistics() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let db_path = {
+        let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", true);
+        let conn = tmp_db.connect_limbo();
+        run_query(&tmp_db, &conn, "CREATE INDEX tx ON t (x)")?;
+        for i in 0..100 {
+            run_query(
+                &tmp_db,
+                &conn,
+                &format!("INSERT INTO t VALUES ({}, 'y')", i % 10),
+            )?;
+        }
+        run_query(&tmp_db, &conn, "ANALYZE")?;
+        run_query(&tmp_db, &conn, "VACUUM")?;
+        conn.close()?;
+        tmp_db.path.clone()
+    };
+
+    let tmp_db = TempDatabase::new_with_existent(&db_path, true);
+    let conn = tmp_db.connect_limbo();
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT * FROM sqlite_stat1"),
+        vec![vec![
+            rusqlite::types::Value::Text("t".to_string()),
+            rusqlite::types::Value::Text("tx".to_string()),
+            rusqlite::types::Value::Text("100 10".to_string())
+        ]]
+    );
+    conn.close()?;
+
+    let sqlite_conn = rusqlite::Connection::open(&db_path)?;
+    let integrity: String =
+        sqlite_conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    assert_eq!(integrity, "ok");
+
+    Ok(())
+}
+
+#[test]
+fn test_recover_orphaned_leaf_pages() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let (db_path, root_page, page_size) = {
+        let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", false);
+        let conn = tmp_db.connect_limbo();
+        // The first row spills into overflow pages
+        run_query(
+            &tmp_db,
+            &conn,
+            &format!("INSERT INTO t VALUES (0, '{}')", "o".repeat(10000)),
+        )?;
+        for i in 1..200 {
+            run_query(
+                &tmp_db,
+                &conn,
+                &format!("INSERT INTO t VALUES ({}, '{}')", i, "y".repeat(200)),
+            )?;
+        }
+        conn.checkpoint(CheckpointMode::Passive)?;
+
+        let tables = conn.recover()?;
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].name.as_deref(), Some("t"));
+        assert_eq!(tables[0].rows.len(), 200);
+        assert_eq!(
+            tables[0].rows[0].values,
+            vec![Value::Integer(0), Value::build_text("o".repeat(10000))]
+        );
+
+        let mut root_page = 0;
+        run_query_on_row(
+            &tmp_db,
+            &conn,
+            "SELECT rootpage FROM sqlite_schema WHERE name = 't'",
+            |row: &Row| root_page = row.get::<i64>(0).unwrap() as u64,
+        )?;
+        let page_size = conn.database_header()?.get_page_size() as u64;
+        conn.close()?;
+        (tmp_db.path.clone(), root_page, page_size)
+    };
+
+    // Every page was checkpointed, so the database file alone holds the database. Clearing the
+    // cell count of the root page leaves only its right-most child reachable.
+    let _ = std::fs::remove_file(format!("{}-wal", db_path.display()));
+    let mut file = std::fs::OpenOptions::new().write(true).open(&db_path)?;
+    file.seek(std::io::SeekFrom::Start((root_page - 1) * page_size + 3))?;
+    file.write_all(&[0, 0])?;
+    drop(file);
+
+    let tmp_db = TempDatabase::new_with_existent(&db_path, false);
+    let conn = tmp_db.connect_limbo();
+    let tables = conn.recover()?;
+    assert_eq!(tables.len(), 2);
+    let (table, lost) = (&tables[0], &tables[1]);
+    assert_eq!(table.name.as_deref(), Some("t"));
+    assert_eq!(lost.name, None);
+    assert!(!table.rows.is_empty() && !lost.rows.is_empty());
+    let mut xs = table
+        .rows
+        .iter()
+        .chain(lost.rows.iter())
+        .map(|row| match row.values[0] {
+            Value::Integer(x) => x,
+            _ => panic!("unexpected value {:?}", row.values[0]),
+        })
+        .collect::<Vec<_>>();
+    xs.sort();
+    assert_eq!(xs, (0..200).collect::<Vec<_>>());
+    let overflowing = table
+        .rows
+        .iter()
+        .chain(lost.rows.iter())
+        .find(|row| row.values[0] == Value::Integer(0))
+        .unwrap();
+    assert_eq!(overflowing.values[1], Value::build_text("o".repeat(10000)));
+
+    Ok(())
+}
+
+#[test]
+fn test_reindex_preserves_results() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", true);
+    let conn = tmp_db.connect_limbo();
+    run_query(&tmp_db, &conn, "CREATE INDEX tx ON t (x)")?;
+    run_query(&tmp_db, &conn, "CREATE INDEX ty ON t (y DESC)")?;
+    for i in 0..200 {
+        run_query(
+            &tmp_db,
+            &conn,
+            &format!("INSERT INTO t VALUES ({}, 'value-{}')", (i * 37) % 200, i),
+        )?;
+    }
+    run_query(&tmp_db, &conn, "DELETE FROM t WHERE x % 3 = 0")?;
+
+    let queries = [
+        "SELECT x, y FROM t WHERE x > 50",
+        "SELECT y FROM t WHERE y >= 'value-150'",
+    ];
+    let before = queries
+        .iter()
+        .map(|query| common::limbo_exec_rows(&tmp_db, &conn, query))
+        .collect::<Vec<_>>();
+
+    for reindex in ["REINDEX", "REINDEX t", "REINDEX ty"] {
+        run_query(&tmp_db, &conn, reindex)?;
+        let after = queries
+            .iter()
+            .map(|query| common::limbo_exec_rows(&tmp_db, &conn, query))
+            .collect::<Vec<_>>();
+        assert_eq!(before, after, "results changed after {reindex}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_savepoint_rollback_to_spanning_pages() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", false);
+    let conn = tmp_db.connect_limbo();
+    let insert = |from: i64, to: i64| -> anyhow::Result<()> {
+        for i in from..to {
+            run_query(
+                &tmp_db,
+                &conn,
+                &format!("INSERT INTO t VALUES ({}, '{}')", i, "x".repeat(100)),
+            )?;
+        }
+        Ok(())
+    };
+
+    run_query(&tmp_db, &conn, "BEGIN")?;
+    insert(0, 100)?;
+    run_query(&tmp_db, &conn, "SAVEPOINT outer_sp")?;
+    insert(100, 200)?;
+    run_query(&tmp_db, &conn, "SAVEPOINT inner_sp")?;
+    // Enough rows to split pages and allocate new ones after the inner savepoint
+    insert(200, 1000)?;
+    run_query(&tmp_db, &conn, "ROLLBACK TO inner_sp")?;
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT count(*), max(x) FROM t"),
+        vec![vec![
+            rusqlite::types::Value::Integer(200),
+            rusqlite::types::Value::Integer(199)
+        ]]
+    );
+    run_query(&tmp_db, &conn, "RELEASE outer_sp")?;
+    run_query(&tmp_db, &conn, "COMMIT")?;
+    conn.close()?;
+
+    let sqlite_conn = rusqlite::Connection::open(&tmp_db.path)?;
+    let count: i64 = sqlite_conn.query_row("SELECT count(*) FROM t", [], |row| row.get(0))?;
+    assert_eq!(count, 200);
+    let integrity: String =
+        sqlite_conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    assert_eq!(integrity, "ok");
+
+    Ok(())
+}
+
+#[test]
+fn test_count_star_matches_manual_count() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", true);
+    let conn = tmp_db.connect_limbo();
+    run_query(&tmp_db, &conn, "CREATE INDEX ty ON t (y)")?;
+    run_query(&tmp_db, &conn, "CREATE INDEX tx ON t (x)")?;
+    for batch in 0..20 {
+        let values = (0..500)
+            .map(|i| {
+                let x = batch * 500 + i;
+                format!("({x}, '{}')", "y".repeat((x % 50) as usize + 1))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        run_query(&tmp_db, &conn, &format!("INSERT INTO t VALUES {values}"))?;
+    }
+
+    let count_star =
+        |tmp_db: &TempDatabase| common::limbo_exec_rows(tmp_db, &conn, "SELECT count(*) FROM t");
+    // `sum(1)` cannot take the count(*) fast path and scans every row of the table
+    let manual_count =
+        |tmp_db: &TempDatabase| common::limbo_exec_rows(tmp_db, &conn, "SELECT sum(1) FROM t");
+
+    assert_eq!(
+        count_star(&tmp_db),
+        vec![vec![rusqlite::types::Value::Integer(10000)]]
+    );
+    assert_eq!(count_star(&tmp_db), manual_count(&tmp_db));
+
+    run_query(&tmp_db, &conn, "DELETE FROM t WHERE x % 7 = 0")?;
+    assert_eq!(count_star(&tmp_db), manual_count(&tmp_db));
+
+    run_query(&tmp_db, &conn, "DROP INDEX tx")?;
+    run_query(&tmp_db, &conn, "DROP INDEX ty")?;
+    assert_eq!(count_star(&tmp_db), manual_count(&tmp_db));
+
+    run_query(&tmp_db, &conn, "DELETE FROM t")?;
+    assert_eq!(
+        count_star(&tmp_db),
+        vec![vec![rusqlite::types::Value::Integer(0)]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_analyze_writes_sqlite_stat1() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER, y TEXT);", true);
+    let conn = tmp_db.connect_limbo();
+    run_query(&tmp_db, &conn, "CREATE INDEX txy ON t (x, y)")?;
+    run_query(&tmp_db, &conn, "CREATE TABLE u (z)")?;
+    let values = (0..100)
+        .map(|i| format!("({}, '{}')", i % 10, i % 20))
+        .collect::<Vec<_>>()
+        .join(", ");
+    run_query(&tmp_db, &conn, &format!("INSERT INTO t VALUES {values}"))?;
+    run_query(&tmp_db, &conn, "INSERT INTO u VALUES (1), (2)")?;
+    run_query(&tmp_db, &conn, "ANALYZE")?;
+
+    let expected = vec![
+        vec![
+            rusqlite::types::Value::Text("t".to_string()),
+            rusqlite::types::Value::Text("txy".to_string()),
+            rusqlite::types::Value::Text("100 10 5".to_string()),
+        ],
+        vec![
+            rusqlite::types::Value::Text("u".to_string()),
+            rusqlite::types::Value::Null,
+            rusqlite::types::Value::Text("2".to_string()),
+        ],
+    ];
+    let stat1_query = "SELECT tbl, idx, stat FROM sqlite_stat1 ORDER BY tbl";
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, stat1_query),
+        expected
+    );
+    conn.close()?;
+
+    // The statistics are loaded when the database is opened again
+    let reopened = TempDatabase::new_with_existent(&tmp_db.path, true);
+    let conn = reopened.connect_limbo();
+    assert_eq!(
+        common::limbo_exec_rows(&reopened, &conn, "SELECT count(*) FROM t WHERE x = 3"),
+        vec![vec![rusqlite::types::Value::Integer(10)]]
+    );
+    conn.close()?;
+
+    let sqlite_conn = rusqlite::Connection::open(&tmp_db.path)?;
+    let rows = sqlite_conn
+        .prepare(stat1_query)?
+        .query_map([], |row| {
+            Ok(vec![
+                row.get::<_, rusqlite::types::Value>(0)?,
+                row.get::<_, rusqlite::types::Value>(1)?,
+                row.get::<_, rusqlite::types::Value>(2)?,
+            ])
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(rows, expected);
+    let integrity: String =
+        sqlite_conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    assert_eq!(integrity, "ok");
+
+    Ok(())
+}
+
+#[test]
+fn test_temp_table_shadows_main_table() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER);", false);
+    let conn = tmp_db.connect_limbo();
+    common::limbo_exec_rows_error(&tmp_db, &conn, "INSERT INTO t VALUES (1), (2)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "CREATE TEMP TABLE t (x INTEGER)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "INSERT INTO t VALUES (10)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "CREATE TEMPORARY TABLE u (y TEXT)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "INSERT INTO u VALUES ('temp')")?;
+
+    let select_t =
+        |conn: &Arc<Connection>| common::limbo_exec_rows(&tmp_db, conn, "SELECT x FROM t");
+    assert_eq!(
+        select_t(&conn),
+        vec![vec![rusqlite::types::Value::Integer(10)]]
+    );
+    // Temp tables are private to the connection that created them
+    let other = tmp_db.connect_limbo();
+    assert_eq!(
+        select_t(&other),
+        vec![
+            vec![rusqlite::types::Value::Integer(1)],
+            vec![rusqlite::types::Value::Integer(2)]
+        ]
+    );
+    assert!(other.prepare("SELECT y FROM u").is_err());
+
+    // Dropping the temp table uncovers the table of the main database
+    common::limbo_exec_rows_error(&tmp_db, &conn, "DROP TABLE t")?;
+    assert_eq!(select_t(&conn), select_t(&other));
+
+    conn.close()?;
+    let conn = tmp_db.connect_limbo();
+    assert!(conn.prepare("SELECT y FROM u").is_err());
+    assert_eq!(select_t(&conn), select_t(&other));
+
+    // Nothing of the temp tables is written to the database file
+    let sqlite_conn = rusqlite::Connection::open(tmp_db.path.clone())?;
+    let names = sqlite_conn
+        .prepare("SELECT name FROM sqlite_schema ORDER BY name")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(names, vec!["t".to_string()]);
+
+    Ok(())
+}
+
+#[test]
+fn test_temp_table_larger_than_page_cache() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER);", false);
+    let conn = tmp_db.connect_limbo();
+    common::limbo_exec_rows_error(&tmp_db, &conn, "CREATE TEMP TABLE big (x BLOB)")?;
+    // A row per page, for more pages than the 2000 of the default page cache
+    let values = vec!["(randomblob(3000))"; 100].join(", ");
+    for _ in 0..30 {
+        common::limbo_exec_rows_error(&tmp_db, &conn, &format!("INSERT INTO big VALUES {values}"))?;
+    }
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT count(*), sum(length(x)) FROM big"),
+        vec![vec![
+            rusqlite::types::Value::Integer(3000),
+            rusqlite::types::Value::Integer(9_000_000)
+        ]]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_temp_table_rollback() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (x INTEGER);", false);
+    let conn = tmp_db.connect_limbo();
+    common::limbo_exec_rows_error(&tmp_db, &conn, "CREATE TEMP TABLE t (x INTEGER)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "INSERT INTO t VALUES (1)")?;
+
+    common::limbo_exec_rows_error(&tmp_db, &conn, "BEGIN")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "INSERT INTO t VALUES (2)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "CREATE TEMP TABLE u (y TEXT)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "ROLLBACK")?;
+    assert!(conn.prepare("SELECT y FROM u").is_err());
+
+    common::limbo_exec_rows_error(&tmp_db, &conn, "BEGIN")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "INSERT INTO t VALUES (3)")?;
+    common::limbo_exec_rows_error(&tmp_db, &conn, "COMMIT")?;
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT x FROM t"),
+        vec![
+            vec![rusqlite::types::Value::Integer(1)],
+            vec![rusqlite::types::Value::Integer(3)]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_update_or_rollback_ends_transaction() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_with_rusqlite("CREATE TABLE t (a NOT NULL);", false);
+    let conn = tmp_db.connect_limbo();
+
+    run_query(&tmp_db, &conn, "INSERT INTO t VALUES (1)")?;
+    run_query(&tmp_db, &conn, "BEGIN")?;
+    run_query(&tmp_db, &conn, "INSERT INTO t VALUES (2)")?;
+    let err = run_query(&tmp_db, &conn, "UPDATE OR ROLLBACK t SET a = NULL").unwrap_err();
+    assert!(err.to_string().contains("constraint failed"), "{}", err);
+    // The whole transaction is rolled back, not only the failed statement
+    assert!(conn.get_auto_commit());
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT a FROM t"),
+        vec![vec![rusqlite::types::Value::Integer(1)]]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_reindex_collation_of_table_column() -> anyhow::Result<()> {
+    maybe_setup_tracing();
+    let db_path = TempDatabase::new_with_rusqlite("CREATE TABLE t (a TEXT);", true)
+        .path
+        .clone();
+    {
+        // Build the index with the BINARY collation, then give the column the NOCASE
+        // collation so that the index is out of order until it is rebuilt
+        let sqlite_conn = rusqlite::Connection::open(&db_path)?;
+        sqlite_conn.execute("CREATE INDEX ta ON t (a)", [])?;
+        sqlite_conn.execute("INSERT INTO t VALUES ('a'), ('B')", [])?;
+        sqlite_conn.execute("PRAGMA writable_schema = ON", [])?;
+        sqlite_conn.execute(
+            "UPDATE sqlite_schema SET sql = 'CREATE TABLE t (a TEXT COLLATE NOCASE)' WHERE name = 't'",
+            [],
+        )?;
+    }
+
+    let tmp_db = TempDatabase::new_with_existent(&db_path, true);
+    let conn = tmp_db.connect_limbo();
+    // The index has no COLLATE clause of its own, it uses the collation of the column
+    run_query(&tmp_db, &conn, "REINDEX nocase")?;
+    assert_eq!(
+        common::limbo_exec_rows(&tmp_db, &conn, "SELECT a FROM t WHERE a = 'A'"),
+        vec![vec![rusqlite::types::Value::Text("a".to_string())]]
+    );
+    conn.close()?;
+
+    let sqlite_conn = rusqlite::Connection::open(&db_path)?;
+    let integrity: String =
+        sqlite_conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    assert_eq!(integrity, "ok");
+
+    Ok(())
+}
+
 fn run_query(tmp_db: &TempDatabase, conn: &Arc<Connection>, query: &str) -> anyhow::Result<()> {
     run_query_core(tmp_db, conn, query, None::<fn(&Row)>)
 }
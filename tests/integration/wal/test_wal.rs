@@ -109,6 +109,36 @@ fn test_wal_1_writer_1_reader() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_journal_mode_only_wal() -> Result<()> {
+    maybe_setup_tracing();
+    let tmp_db = TempDatabase::new_empty(false);
+    let conn = tmp_db.connect_limbo();
+    conn.execute("CREATE TABLE t (x)")?;
+
+    for mode in ["delete", "truncate", "persist", "memory", "off"] {
+        let err = conn
+            .execute(format!("PRAGMA journal_mode = {mode}"))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("journal mode {mode} is not supported")),
+            "{}",
+            err
+        );
+    }
+    assert_eq!(
+        execute_and_get_strings(&tmp_db, &conn, "PRAGMA journal_mode = WAL")?,
+        vec!["wal"]
+    );
+    // Like in SQLite, an unknown mode leaves the journal mode unchanged
+    assert_eq!(
+        execute_and_get_strings(&tmp_db, &conn, "PRAGMA journal_mode = bogus")?,
+        vec!["wal"]
+    );
+    Ok(())
+}
+
 /// Execute a statement and get strings result
 pub(crate) fn execute_and_get_strings(
     tmp_db: &TempDatabase,
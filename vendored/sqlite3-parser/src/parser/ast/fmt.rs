@@ -775,6 +775,7 @@ impl ToTokens for Expr {
                 s.append(TK_RP, None)
             }
             Self::RowId { .. } => Ok(()),
+            Self::Register(_) => Ok(()),
             Self::Subquery(query) => {
                 s.append(TK_LP, None)?;
                 query.to_tokens(s)?;
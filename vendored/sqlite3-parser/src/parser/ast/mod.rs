@@ -416,6 +416,9 @@ pub enum Expr {
         /// the y in `x.y.z`. index of the table in catalog.
         table: TableInternalId,
     },
+    /// Value already computed into a register of the program, e.g. a `NEW` or `OLD` column
+    /// referenced by the body of a trigger
+    Register(usize),
     /// `IN`
     InList {
         /// expression
@@ -1726,10 +1729,16 @@ pub type PragmaValue = Expr; // TODO
 #[strum(serialize_all = "snake_case")]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PragmaName {
+    /// Returns the application id of the database file.
+    ApplicationId,
     /// set the autovacuum mode
     AutoVacuum,
     /// `cache_size` pragma
     CacheSize,
+    /// Check the database for rows violating foreign key constraints
+    ForeignKeyCheck,
+    /// Query or toggle enforcement of foreign key constraints
+    ForeignKeys,
     /// Run integrity check on the database file
     IntegrityCheck,
     /// `journal_mode` pragma
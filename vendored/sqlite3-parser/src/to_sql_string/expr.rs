@@ -149,6 +149,7 @@ impl ToSqlString for Expr {
             Expr::RowId { database: _, table } => {
                 ret.push_str(&format!("{}.rowid", context.get_table_name(*table)))
             }
+            Expr::Register(reg) => ret.push_str(&format!("r[{}]", reg)),
             Expr::InList { lhs, not, rhs } => {
                 ret.push_str(&format!(
                     "{} {}IN ({})",